@@ -0,0 +1,219 @@
+using System;
+using System.Collections.Concurrent;
+using System.Collections.Generic;
+using System.Text.Json;
+using System.Text.RegularExpressions;
+using System.Threading;
+using LibreHardwareMonitor.Hardware;
+
+namespace SensorBridge
+{
+    /// <summary>
+    /// 命令通道 - 后台线程从 stdin 读取请求，主循环线程执行（Computer 非线程安全）并经 stdout 输出响应
+    /// </summary>
+    public static class CommandChannel
+    {
+        private static readonly ConcurrentQueue<CommandRequest> s_queue = new ConcurrentQueue<CommandRequest>();
+        private static readonly AutoResetEvent s_signal = new AutoResetEvent(false);
+        private static readonly JsonSerializerOptions s_readOptions = new JsonSerializerOptions { PropertyNameCaseInsensitive = true };
+        private static readonly Regex s_idPattern = new Regex("\"id\"\\s*:\\s*(\\d+)", RegexOptions.IgnoreCase);
+
+        /// <summary>
+        /// 采样间隔（毫秒），setInterval 修改后下一轮生效
+        /// </summary>
+        public static int IntervalMs { get; private set; } = ConfigurationManager.ReadEnvInt("BRIDGE_INTERVAL_MS", 1000, 100, 60000);
+
+        /// <summary>
+        /// 启动 stdin 读取线程（stdin 关闭时线程自然结束）
+        /// </summary>
+        public static void Start()
+        {
+            var t = new Thread(ReadLoop) { IsBackground = true, Name = "bridge-stdin" };
+            t.Start();
+        }
+
+        private static void ReadLoop()
+        {
+            try
+            {
+                string? line;
+                while ((line = Console.In.ReadLine()) != null)
+                {
+                    if (string.IsNullOrWhiteSpace(line)) continue;
+                    CommandRequest? req;
+                    try
+                    {
+                        req = JsonSerializer.Deserialize<CommandRequest>(line, s_readOptions);
+                    }
+                    catch (Exception ex)
+                    {
+                        ConfigurationManager.Log($"[cmd] invalid request: {ex.Message}");
+                        // 尽量取回 id 作答，否则 Rust 侧要等满超时
+                        req = new CommandRequest { Id = RecoverId(line), Method = null };
+                    }
+                    if (req == null) continue;
+                    s_queue.Enqueue(req);
+                    s_signal.Set();
+                }
+            }
+            catch (Exception ex)
+            {
+                ConfigurationManager.Log($"[cmd] stdin closed: {ex.Message}");
+            }
+        }
+
+        /// <summary>
+        /// 从无法解析的请求行中取回 id（字段类型不符时读 JSON，JSON 残缺时按正则匹配）；取不到返回 0
+        /// </summary>
+        private static long RecoverId(string line)
+        {
+            try
+            {
+                using var doc = JsonDocument.Parse(line);
+                if (doc.RootElement.ValueKind == JsonValueKind.Object)
+                {
+                    foreach (var prop in doc.RootElement.EnumerateObject())
+                    {
+                        if (string.Equals(prop.Name, "id", StringComparison.OrdinalIgnoreCase) && prop.Value.ValueKind == JsonValueKind.Number && prop.Value.TryGetInt64(out var id))
+                            return id;
+                    }
+                }
+                return 0;
+            }
+            catch (JsonException) { }
+            var m = s_idPattern.Match(line);
+            return m.Success && long.TryParse(m.Groups[1].Value, out var v) ? v : 0;
+        }
+
+        /// <summary>
+        /// 等待到下一轮采样，期间到达的命令立即执行；返回是否重建了 Computer
+        /// </summary>
+        public static bool WaitNextTick(ref Computer computer, JsonSerializerOptions jsonOptions)
+        {
+            bool reopened = false;
+            var deadline = DateTime.UtcNow.AddMilliseconds(IntervalMs);
+            while (true)
+            {
+                while (s_queue.TryDequeue(out var req))
+                {
+                    var resp = Execute(req, ref computer, ref reopened);
+                    Console.WriteLine(JsonSerializer.Serialize(resp, jsonOptions));
+                    Console.Out.Flush();
+                }
+                var remaining = (int)(deadline - DateTime.UtcNow).TotalMilliseconds;
+                if (remaining <= 0) break;
+                s_signal.WaitOne(remaining);
+            }
+            return reopened;
+        }
+
+        private static CommandResponse Execute(CommandRequest req, ref Computer computer, ref bool reopened)
+        {
+            var resp = new CommandResponse { Id = req.Id };
+            try
+            {
+                switch (req.Method)
+                {
+                    case null:
+                        resp.Error = new CommandError { Code = -32700, Message = "invalid request" };
+                        break;
+                    case "ping":
+                        resp.Result = new { pong = true, intervalMs = IntervalMs };
+                        break;
+                    case "setInterval":
+                        {
+                            int ms = 0;
+                            if (req.Params is JsonElement p && p.ValueKind == JsonValueKind.Object &&
+                                p.TryGetProperty("ms", out var v) && v.TryGetInt32(out ms) && ms >= 100 && ms <= 60000)
+                            {
+                                IntervalMs = ms;
+                                ConfigurationManager.Log($"[cmd] interval -> {ms}ms");
+                                resp.Result = new { intervalMs = IntervalMs };
+                            }
+                            else
+                            {
+                                resp.Error = new CommandError { Code = -32602, Message = "params.ms must be 100..60000" };
+                            }
+                            break;
+                        }
+                    case "setHardware":
+                        {
+                            HardwareGroups? g = null;
+                            if (req.Params is JsonElement p && p.ValueKind == JsonValueKind.Object)
+                            {
+                                g = p.Deserialize<HardwareGroups>(s_readOptions);
+                            }
+                            if (g == null)
+                            {
+                                resp.Error = new CommandError { Code = -32602, Message = "params must be an object of hardware groups" };
+                                break;
+                            }
+                            if (g.Cpu.HasValue) HardwareOptions.Cpu = g.Cpu.Value;
+                            if (g.Gpu.HasValue) HardwareOptions.Gpu = g.Gpu.Value;
+                            if (g.Storage.HasValue) HardwareOptions.Storage = g.Storage.Value;
+                            if (g.Motherboard.HasValue) HardwareOptions.Motherboard = g.Motherboard.Value;
+                            if (g.Controller.HasValue) HardwareOptions.Controller = g.Controller.Value;
+                            if (g.Memory.HasValue) HardwareOptions.Memory = g.Memory.Value;
+                            if (g.Network.HasValue) HardwareOptions.Network = g.Network.Value;
+                            HardwareOptions.ApplyTo(computer);
+                            ConfigurationManager.Log($"[cmd] hardware -> {JsonSerializer.Serialize(HardwareOptions.Snapshot())}");
+                            resp.Result = HardwareOptions.Snapshot();
+                            break;
+                        }
+                    case "reenumerate":
+                        {
+                            ConfigurationManager.Log("[cmd] reenumerate: reopening Computer...");
+                            try { computer.Close(); } catch { }
+                            computer = HardwareManager.MakeComputer();
+                            reopened = true;
+                            resp.Result = new { hardwareCount = computer.Hardware.Count };
+                            break;
+                        }
+                    case "dumpSensors":
+                        computer.Accept(new UpdateVisitor());
+                        resp.Result = new { hardware = BuildTree(computer.Hardware) };
+                        break;
+                    default:
+                        resp.Error = new CommandError { Code = -32601, Message = $"unknown method: {req.Method}" };
+                        break;
+                }
+            }
+            catch (Exception ex)
+            {
+                ConfigurationManager.Log($"[cmd] {req.Method} failed: {ex}");
+                resp.Result = null;
+                resp.Error = new CommandError { Code = -32000, Message = ex.Message };
+            }
+            return resp;
+        }
+
+        private static List<SensorTreeNode> BuildTree(IEnumerable<IHardware> hardware)
+        {
+            var list = new List<SensorTreeNode>();
+            foreach (var hw in hardware)
+            {
+                var node = new SensorTreeNode
+                {
+                    HardwareType = hw.HardwareType.ToString(),
+                    Name = hw.Name,
+                    Identifier = hw.Identifier.ToString(),
+                    SubHardware = BuildTree(hw.SubHardware),
+                };
+                foreach (var s in hw.Sensors)
+                {
+                    node.Sensors.Add(new SensorTreeSensor
+                    {
+                        SensorType = s.SensorType.ToString(),
+                        Name = s.Name,
+                        Identifier = s.Identifier.ToString(),
+                        Value = s.Value,
+                        Min = s.Min,
+                        Max = s.Max,
+                    });
+                }
+                list.Add(node);
+            }
+            return list;
+        }
+    }
+}
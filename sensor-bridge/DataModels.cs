@@ -75,4 +75,98 @@ namespace SensorBridge
         public bool? ThrottleActive { get; set; }
         public List<string>? ThrottleReasons { get; set; }
     }
+
+    /// <summary>
+    /// 握手消息：进程启动后首行输出，声明协议版本与能力
+    /// </summary>
+    public class BridgeHello
+    {
+        public string Type { get; set; } = "hello";
+        public int ProtocolVersion { get; set; } = BridgeProtocol.Version;
+        public int MinHostVersion { get; set; } = BridgeProtocol.MinHostVersion;
+        public string? BridgeVersion { get; set; }
+        public List<string> Capabilities { get; set; } = new List<string>(BridgeProtocol.Capabilities);
+    }
+
+    /// <summary>
+    /// 桥接输出协议常量（变更数据帧字段时同步提升版本并更新宿主端 bridge_protocol.rs）
+    /// </summary>
+    public static class BridgeProtocol
+    {
+        public const int Version = 2;
+        public const int MinHostVersion = 1;
+        public static readonly string[] Capabilities =
+        {
+            "temps", "fans", "voltages", "storageTemps", "gpus", "cpuExtra", "perCore", "health", "commands"
+        };
+    }
+
+    /// <summary>
+    /// 命令请求：宿主经 stdin 每行写入一个 {"id":N,"method":"...","params":{...}}
+    /// </summary>
+    public class CommandRequest
+    {
+        public long Id { get; set; }
+        public string? Method { get; set; }
+        public System.Text.Json.JsonElement? Params { get; set; }
+    }
+
+    /// <summary>
+    /// 命令响应：经 stdout 输出，与数据帧以 type 区分
+    /// </summary>
+    public class CommandResponse
+    {
+        public string Type { get; set; } = "response";
+        public long Id { get; set; }
+        public object? Result { get; set; }
+        public CommandError? Error { get; set; }
+    }
+
+    /// <summary>
+    /// 命令错误（code 沿用 JSON-RPC 约定：-32700 解析失败，-32601 未知方法，-32602 参数无效，-32000 执行失败）
+    /// </summary>
+    public class CommandError
+    {
+        public int Code { get; set; }
+        public string Message { get; set; } = "";
+    }
+
+    /// <summary>
+    /// 硬件分组开关（setHardware 参数与响应）
+    /// </summary>
+    public class HardwareGroups
+    {
+        public bool? Cpu { get; set; }
+        public bool? Gpu { get; set; }
+        public bool? Storage { get; set; }
+        public bool? Motherboard { get; set; }
+        public bool? Controller { get; set; }
+        public bool? Memory { get; set; }
+        public bool? Network { get; set; }
+    }
+
+    /// <summary>
+    /// 传感器树节点（dumpSensors 响应）
+    /// </summary>
+    public class SensorTreeNode
+    {
+        public string? HardwareType { get; set; }
+        public string? Name { get; set; }
+        public string? Identifier { get; set; }
+        public List<SensorTreeSensor> Sensors { get; set; } = new List<SensorTreeSensor>();
+        public List<SensorTreeNode> SubHardware { get; set; } = new List<SensorTreeNode>();
+    }
+
+    /// <summary>
+    /// 传感器树叶子
+    /// </summary>
+    public class SensorTreeSensor
+    {
+        public string? SensorType { get; set; }
+        public string? Name { get; set; }
+        public string? Identifier { get; set; }
+        public float? Value { get; set; }
+        public float? Min { get; set; }
+        public float? Max { get; set; }
+    }
 }
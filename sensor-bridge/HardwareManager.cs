@@ -15,16 +15,8 @@ namespace SensorBridge
         /// <returns>已初始化的 Computer 实例</returns>
         public static Computer MakeComputer()
         {
-            var c = new Computer
-            {
-                IsCpuEnabled = true,
-                IsMotherboardEnabled = true,
-                IsControllerEnabled = true,
-                IsMemoryEnabled = true,
-                IsStorageEnabled = true,
-                IsNetworkEnabled = true,
-                IsGpuEnabled = true,
-            };
+            var c = new Computer();
+            HardwareOptions.ApplyTo(c);
             c.Open();
             return c;
         }
@@ -62,6 +54,48 @@ namespace SensorBridge
         }
     }
 
+    /// <summary>
+    /// 硬件分组开关 - 可经命令通道 setHardware 修改，重建 Computer 时沿用
+    /// </summary>
+    public static class HardwareOptions
+    {
+        public static bool Cpu { get; set; } = true;
+        public static bool Gpu { get; set; } = true;
+        public static bool Storage { get; set; } = true;
+        public static bool Motherboard { get; set; } = true;
+        public static bool Controller { get; set; } = true;
+        public static bool Memory { get; set; } = true;
+        public static bool Network { get; set; } = true;
+
+        /// <summary>
+        /// 将当前开关应用到 Computer（已打开的实例会即时增删对应硬件）
+        /// </summary>
+        public static void ApplyTo(Computer c)
+        {
+            c.IsCpuEnabled = Cpu;
+            c.IsGpuEnabled = Gpu;
+            c.IsStorageEnabled = Storage;
+            c.IsMotherboardEnabled = Motherboard;
+            c.IsControllerEnabled = Controller;
+            c.IsMemoryEnabled = Memory;
+            c.IsNetworkEnabled = Network;
+        }
+
+        /// <summary>
+        /// 当前开关快照（命令响应用）
+        /// </summary>
+        public static HardwareGroups Snapshot() => new HardwareGroups
+        {
+            Cpu = Cpu,
+            Gpu = Gpu,
+            Storage = Storage,
+            Motherboard = Motherboard,
+            Controller = Controller,
+            Memory = Memory,
+            Network = Network,
+        };
+    }
+
     /// <summary>
     /// 硬件更新访问者 - 递归刷新所有硬件与子硬件
     /// </summary>
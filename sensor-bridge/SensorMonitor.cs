@@ -37,6 +37,17 @@ namespace SensorBridge
             
             ConfigurationManager.Log($"[start] idleSec={idleSecThreshold} excMax={excThreshold} periodicReopenSec={periodicReopenSec} summaryEvery={summaryEvery} dumpEvery={dumpEvery} isAdmin={IsAdmin()}");
             
+            // 握手：首行声明协议版本与能力，宿主据此判定兼容性
+            var hello = new BridgeHello
+            {
+                BridgeVersion = typeof(SensorMonitor).Assembly.GetName().Version?.ToString()
+            };
+            Console.WriteLine(JsonSerializer.Serialize(hello, jsonOptions));
+            Console.Out.Flush();
+            
+            // 命令通道：stdin 每行一个请求，响应与数据帧一同经 stdout 输出
+            CommandChannel.Start();
+            
             int tick = 0;
             int? maxTicks = GetMaxTicks();
             bool? lastHasTempValue = null;
@@ -105,7 +116,12 @@ namespace SensorBridge
                                                        idleSecThreshold, periodicReopenSec, 
                                                        ref lastReopen, ref lastGood);
                 
-                Thread.Sleep(1000);
+                // 等待下一轮采样，期间执行宿主命令（reenumerate 会重建 Computer）
+                if (CommandChannel.WaitNextTick(ref computer, jsonOptions))
+                {
+                    lastReopen = DateTime.UtcNow;
+                    lastGood = DateTime.UtcNow;
+                }
             }
         }
         
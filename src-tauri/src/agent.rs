@@ -0,0 +1,182 @@
+// 无界面采集服务（sys-sensor-agent）
+// 说明：
+// - 不创建窗口与托盘：与 GUI 共用 sensor_core（采样循环、调度、Runner、SMART Worker、状态仓库）
+// - 读取与 GUI 相同的 config.json，数据经 InfluxDB / MQTT / 本地 HTTP API 输出
+// - 配置目录默认与 Tauri 的 app_config_dir 一致，可用 --config-dir 或环境变量 SYS_SENSOR_CONFIG_DIR 覆盖
+// - --bridge-replay 以桥接录制文件代替 sensor-bridge，便于在 Linux 上用真机采集的数据跑完整流程
+// - 收到 Ctrl+C（Unix 下另含 SIGTERM）后优雅退出，便于作为 systemd / Windows 服务运行
+// - 不依赖 tauri：cargo build --no-default-features --bin sys-sensor-agent
+
+use std::path::PathBuf;
+
+use crate::collector::CollectorHooks;
+use crate::sensor_core::{start_sensor_core, CoreOptions, SensorCore};
+
+/// 与 tauri.conf.json 的 identifier 保持一致（决定默认配置目录）
+pub const APP_IDENTIFIER: &str = "com.terry.sys-sensor";
+/// 配置目录覆盖用环境变量
+pub const CONFIG_DIR_ENV: &str = "SYS_SENSOR_CONFIG_DIR";
+
+pub const USAGE: &str = "用法: sys-sensor-agent [选项]
+
+选项:
+  --config-dir <DIR>   配置目录（默认与桌面版相同，含 config.json / api_tokens.json；Token 用 sys-sensor-cli token 管理）
+  --no-bridge          不启动 sensor-bridge（非 Windows 平台默认不启动）
+  --bridge-replay <FILE>  以桥接录制文件代替 sensor-bridge（覆盖配置 bridge_replay，任意平台可用）
+  --replay-speed <X>   回放倍速（默认 1）
+  --replay-loop        回放完毕后从头循环
+  --print-config-dir   打印解析后的配置目录并退出
+  -h, --help           显示本帮助
+
+环境变量:
+  SYS_SENSOR_CONFIG_DIR  等同于 --config-dir（命令行优先）";
+
+/// 命令行选项
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct AgentOptions {
+    pub config_dir: Option<PathBuf>,
+    pub no_bridge: bool,
+    pub bridge_replay: Option<PathBuf>,
+    pub replay_speed: Option<f64>,
+    pub replay_loop: bool,
+    pub print_config_dir: bool,
+    pub show_help: bool,
+}
+
+/// 解析命令行参数（不含程序名）
+pub fn parse_args<I: IntoIterator<Item = String>>(args: I) -> Result<AgentOptions, String> {
+    let mut opts = AgentOptions::default();
+    let mut it = args.into_iter();
+    while let Some(arg) = it.next() {
+        match arg.as_str() {
+            "--config-dir" => {
+                let v = it.next().ok_or_else(|| "--config-dir 需要一个目录参数".to_string())?;
+                opts.config_dir = Some(PathBuf::from(v));
+            }
+            s if s.starts_with("--config-dir=") => {
+                opts.config_dir = Some(PathBuf::from(&s["--config-dir=".len()..]));
+            }
+            "--no-bridge" => opts.no_bridge = true,
+            "--bridge-replay" => {
+                let v = it.next().ok_or_else(|| "--bridge-replay 需要一个文件参数".to_string())?;
+                opts.bridge_replay = Some(PathBuf::from(v));
+            }
+            "--replay-speed" => {
+                let v = it.next().ok_or_else(|| "--replay-speed 需要一个倍速参数".to_string())?;
+                let speed = v.parse::<f64>().ok().filter(|x| x.is_finite() && *x > 0.0).ok_or_else(|| format!("无效的回放倍速: {}", v))?;
+                opts.replay_speed = Some(speed);
+            }
+            "--replay-loop" => opts.replay_loop = true,
+            "--print-config-dir" => opts.print_config_dir = true,
+            "-h" | "--help" => opts.show_help = true,
+            other => return Err(format!("未知参数: {}", other)),
+        }
+    }
+    Ok(opts)
+}
+
+/// Tauri app_config_dir 的等价实现：<系统配置目录>/<identifier>
+pub fn default_config_dir() -> PathBuf {
+    let env_dir = |k: &str| std::env::var_os(k).filter(|v| !v.is_empty()).map(PathBuf::from);
+    let base = if cfg!(windows) {
+        env_dir("APPDATA")
+    } else if cfg!(target_os = "macos") {
+        env_dir("HOME").map(|h| h.join("Library").join("Application Support"))
+    } else {
+        env_dir("XDG_CONFIG_HOME").or_else(|| env_dir("HOME").map(|h| h.join(".config")))
+    };
+    base.unwrap_or_else(|| std::env::current_dir().unwrap_or_else(|_| PathBuf::from(".")))
+        .join(APP_IDENTIFIER)
+}
+
+/// 解析配置目录：命令行 > 环境变量 > 默认目录
+pub fn resolve_config_dir(opts: &AgentOptions) -> PathBuf {
+    opts.config_dir
+        .clone()
+        .or_else(|| std::env::var_os(CONFIG_DIR_ENV).filter(|v| !v.is_empty()).map(PathBuf::from))
+        .unwrap_or_else(default_config_dir)
+}
+
+/// 启动无界面采集核心（不阻塞）
+pub(crate) fn start_agent(opts: &AgentOptions) -> (SensorCore, PathBuf) {
+    let config_dir = resolve_config_dir(opts);
+    let config_path = config_dir.join("config.json");
+    if !config_path.exists() {
+        eprintln!("[agent] 未找到 {}，使用默认配置", config_path.display());
+    }
+    let mut config = crate::config_utils::load_config_from(&config_path);
+    if let Some(path) = opts.bridge_replay.as_ref() {
+        config.bridge_replay = Some(crate::bridge_replay::BridgeReplayConfig {
+            path: Some(path.display().to_string()),
+            speed: opts.replay_speed,
+            looped: Some(opts.replay_loop),
+            ..config.bridge_replay.clone().unwrap_or_default()
+        });
+    }
+
+    let influx_on = config.influx.as_ref().map(|c| c.enabled).unwrap_or(false);
+    let mqtt_on = config.mqtt.as_ref().map(|c| c.enabled).unwrap_or(false);
+    let api_on = config.api.as_ref().map(|c| c.enabled).unwrap_or(false);
+    eprintln!(
+        "[agent] 配置目录 {}，输出: influx={} mqtt={} api={}",
+        config_dir.display(), influx_on, mqtt_on, api_on
+    );
+    if !influx_on && !mqtt_on && !api_on {
+        eprintln!("[agent] 未启用任何输出（influx / mqtt / api），采集结果仅保留在内存中");
+    }
+
+    let sensor = start_sensor_core(CoreOptions {
+        config,
+        config_dir: config_dir.clone(),
+        // sensor-bridge 依赖 .NET + LibreHardwareMonitor，仅在 Windows 上可用
+        bridge: cfg!(windows) && !opts.no_bridge,
+        packaged_bridge_exe: None,
+        on_smart: None,
+        hooks: CollectorHooks::default(),
+        on_config_changed: None,
+    });
+    (sensor, config_dir)
+}
+
+/// 运行 agent 直至收到退出信号
+pub fn run(opts: AgentOptions) -> Result<(), String> {
+    if opts.show_help {
+        println!("{}", USAGE);
+        return Ok(());
+    }
+    if opts.print_config_dir {
+        println!("{}", resolve_config_dir(&opts).display());
+        return Ok(());
+    }
+
+    let rt = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .map_err(|e| format!("创建信号监听运行时失败: {}", e))?;
+
+    let (sensor, _) = start_agent(&opts);
+    eprintln!("[agent] 已启动，按 Ctrl+C 退出");
+
+    rt.block_on(wait_for_shutdown_signal());
+
+    eprintln!("[agent] 收到退出信号，正在停止...");
+    sensor.shutdown();
+    sensor.join();
+    eprintln!("[agent] 已退出");
+    Ok(())
+}
+
+async fn wait_for_shutdown_signal() {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+        if let Ok(mut term) = signal(SignalKind::terminate()) {
+            tokio::select! {
+                _ = tokio::signal::ctrl_c() => {}
+                _ = term.recv() => {}
+            }
+            return;
+        }
+    }
+    let _ = tokio::signal::ctrl_c().await;
+}
@@ -0,0 +1,471 @@
+// 阈值告警规则引擎
+// 说明：
+// - 每 tick 由采样线程调用，输入为 SensorSnapshot 标量指标 + Aggregated 聚合字段（同名时以快照为准）
+// - 条件：数值比较（> >= < <= == !=）或变化率（每秒，按 rate_window_sec 窗口首尾计算）
+// - 持续时间：条件需连续满足 for_sec 秒才触发（期间为 pending）；恢复同理使用 clear_for_sec
+// - 滞回：触发后需越过 clear_threshold（缺省同 threshold）才恢复，避免在阈值附近反复抖动
+// - 规则存于 AppConfig.alerts，热更新：规则内容变化时仅重置该规则的状态；被修改/删除/停用时仍在 firing 的规则补发 resolved
+// - alerts.anomaly 中的每个检测项派生一条规则，评估 anomaly_detector 输出的 anomaly_z.<metric> 分数
+// - 状态转换（firing / resolved）经 sensor://alert 事件与事件总线 "alert" 流广播，并写入告警日志（alert_journal）
+
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::{Mutex, OnceLock};
+
+use serde::{Deserialize, Serialize};
+
+use crate::state_store::Aggregated;
+use crate::types::SensorSnapshot;
+
+/// 比较运算符
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq, Default)]
+pub enum AlertOp {
+    #[default]
+    #[serde(rename = ">")]
+    Gt,
+    #[serde(rename = ">=")]
+    Ge,
+    #[serde(rename = "<")]
+    Lt,
+    #[serde(rename = "<=")]
+    Le,
+    #[serde(rename = "==")]
+    Eq,
+    #[serde(rename = "!=")]
+    Ne,
+}
+
+impl AlertOp {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AlertOp::Gt => ">",
+            AlertOp::Ge => ">=",
+            AlertOp::Lt => "<",
+            AlertOp::Le => "<=",
+            AlertOp::Eq => "==",
+            AlertOp::Ne => "!=",
+        }
+    }
+
+    pub fn test(&self, v: f64, threshold: f64) -> bool {
+        match self {
+            AlertOp::Gt => v > threshold,
+            AlertOp::Ge => v >= threshold,
+            AlertOp::Lt => v < threshold,
+            AlertOp::Le => v <= threshold,
+            AlertOp::Eq => (v - threshold).abs() < f64::EPSILON,
+            AlertOp::Ne => (v - threshold).abs() >= f64::EPSILON,
+        }
+    }
+}
+
+/// 条件类型：value=当前值，rate=每秒变化率
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum AlertCondition {
+    #[default]
+    Value,
+    Rate,
+}
+
+/// 严重级别
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum AlertSeverity {
+    Info,
+    #[default]
+    Warning,
+    Critical,
+}
+
+/// 单条告警规则
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Default)]
+#[serde(default)]
+pub struct AlertRule {
+    // 唯一标识（用于状态跟踪与热更新比对）
+    pub id: String,
+    // 显示名（默认使用 id）
+    pub name: Option<String>,
+    // 指标键（与 SensorSnapshot / Aggregated 字段同名，如 "cpu_temp_c"、"rtt_avg_ms"）
+    pub metric: String,
+    pub op: AlertOp,
+    pub threshold: f64,
+    // 恢复阈值（滞回）；缺省同 threshold
+    pub clear_threshold: Option<f64>,
+    // 条件需持续满足的秒数（默认 0，即立即触发）
+    pub for_sec: Option<u64>,
+    // 恢复条件需持续满足的秒数（默认 0）
+    pub clear_for_sec: Option<u64>,
+    // 条件类型（默认 value）
+    pub condition: Option<AlertCondition>,
+    // 变化率窗口（秒，默认 60；仅 rate 条件使用）
+    pub rate_window_sec: Option<u64>,
+    pub severity: Option<AlertSeverity>,
+    // 是否启用（默认启用）
+    pub enabled: Option<bool>,
+    // 自定义消息（缺省自动生成）
+    pub message: Option<String>,
+}
+
+/// 告警配置（AppConfig.alerts）
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Default)]
+#[serde(default)]
+pub struct AlertConfig {
+    // 总开关（默认启用；无规则时不产生任何告警）
+    pub enabled: Option<bool>,
+    pub rules: Option<Vec<AlertRule>>,
+    // 通知 Sink（桌面/Webhook/邮件/脚本）
+    pub sinks: Option<Vec<crate::alert_notify::NotifySinkConfig>>,
+    // 告警日志保留条数（默认 5000）
+    pub journal_max_entries: Option<usize>,
+    // 统计异常检测（每个检测项派生一条 anomaly.<metric> 规则）
+    pub anomaly: Option<crate::anomaly_detector::AnomalyConfig>,
+}
+
+/// 规则状态
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum AlertState {
+    #[default]
+    Ok,
+    Pending,
+    Firing,
+}
+
+/// 状态转换类型
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum AlertTransition {
+    Firing,
+    Resolved,
+    // 以下两类由用户操作产生，仅写入告警日志
+    Acknowledged,
+    Silenced,
+}
+
+/// 状态转换事件（sensor://alert 负载）
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct AlertEvent {
+    pub rule_id: String,
+    pub rule_name: String,
+    pub metric: String,
+    pub severity: AlertSeverity,
+    pub state: AlertTransition,
+    // 触发/恢复时的观测值（rate 条件下为每秒变化率）
+    pub value: f64,
+    pub threshold: f64,
+    pub timestamp_ms: i64,
+    // 本次告警的触发时间（resolved 事件中用于计算持续时长）
+    pub fired_at_ms: i64,
+    pub message: String,
+}
+
+/// 单条规则的运行状态（供前端/调试读取）
+#[derive(Clone, Debug, Serialize, Deserialize, Default)]
+pub struct AlertRuleStatus {
+    pub id: String,
+    pub name: String,
+    pub metric: String,
+    pub severity: AlertSeverity,
+    pub state: AlertState,
+    pub value: Option<f64>,
+    // 进入当前状态的时间
+    pub since_ms: Option<i64>,
+    pub fired_at_ms: Option<i64>,
+    // 当前这次触发是否已确认
+    pub acknowledged: bool,
+    // 静默截止时间（静默期间不发送通知）
+    pub silenced_until_ms: Option<i64>,
+}
+
+/// 引擎状态
+#[derive(Clone, Debug, Serialize, Deserialize, Default)]
+pub struct AlertStatus {
+    pub enabled: bool,
+    pub rules: Vec<AlertRuleStatus>,
+    // 当前 firing 的规则数
+    pub active: usize,
+    // 其中未确认的规则数
+    pub unacknowledged: usize,
+    pub last_eval_ms: Option<i64>,
+    // 无效规则（被跳过）的原因
+    pub errors: Vec<String>,
+}
+
+static STATUS: OnceLock<Mutex<AlertStatus>> = OnceLock::new();
+
+/// Tauri命令：获取告警状态
+#[cfg_attr(feature = "desktop", tauri::command)]
+pub fn alert_get_status() -> AlertStatus {
+    STATUS
+        .get_or_init(|| Mutex::new(AlertStatus::default()))
+        .lock()
+        .map(|g| g.clone())
+        .unwrap_or_default()
+}
+
+/// 汇总可供规则引用的指标：快照标量字段 + 聚合结构中的数值字段
+pub fn metric_values(snapshot: &SensorSnapshot, agg: Option<&Aggregated>) -> HashMap<String, f64> {
+    let mut out: HashMap<String, f64> = crate::metrics_utils::snapshot_scalar_fields(snapshot)
+        .into_iter()
+        .map(|(k, v)| (k.to_string(), v))
+        .collect();
+    if let Some(serde_json::Value::Object(obj)) = agg.and_then(|a| serde_json::to_value(a).ok()) {
+        for (k, v) in obj {
+            if k == "timestamp_ms" { continue; }
+            if let Some(f) = v.as_f64() { out.entry(k).or_insert(f); }
+        }
+    }
+    out
+}
+
+struct RuleRuntime {
+    rule: AlertRule,
+    state: AlertState,
+    since_ms: Option<i64>,
+    // 恢复条件开始满足的时间（firing 状态下使用）
+    clear_since_ms: Option<i64>,
+    fired_at_ms: Option<i64>,
+    value: Option<f64>,
+    // rate 条件的采样窗口
+    samples: VecDeque<(i64, f64)>,
+}
+
+impl RuleRuntime {
+    fn new(rule: AlertRule) -> Self {
+        Self { rule, state: AlertState::Ok, since_ms: None, clear_since_ms: None, fired_at_ms: None, value: None, samples: VecDeque::new() }
+    }
+
+    fn name(&self) -> String {
+        self.rule.name.clone().filter(|n| !n.trim().is_empty()).unwrap_or_else(|| self.rule.id.clone())
+    }
+
+    fn severity(&self) -> AlertSeverity {
+        self.rule.severity.unwrap_or_default()
+    }
+
+    // 观测值：value 条件直接取值；rate 条件取窗口首尾的每秒变化率（样本不足时返回 None）
+    fn observe(&mut self, now_ms: i64, v: f64) -> Option<f64> {
+        if self.rule.condition.unwrap_or_default() == AlertCondition::Value {
+            return Some(v);
+        }
+        let window_ms = (self.rule.rate_window_sec.unwrap_or(60).max(1) * 1000) as i64;
+        self.samples.push_back((now_ms, v));
+        while let Some(&(ts, _)) = self.samples.front() {
+            if now_ms - ts > window_ms { self.samples.pop_front(); } else { break; }
+        }
+        let &(t0, v0) = self.samples.front()?;
+        let dt = (now_ms - t0) as f64 / 1000.0;
+        if dt <= 0.0 { return None; }
+        Some((v - v0) / dt)
+    }
+
+    fn event(&self, state: AlertTransition, value: f64, now_ms: i64) -> AlertEvent {
+        let name = self.name();
+        let message = self.rule.message.clone().filter(|m| !m.trim().is_empty()).unwrap_or_else(|| {
+            let what = if self.rule.condition.unwrap_or_default() == AlertCondition::Rate {
+                format!("{} 变化率", self.rule.metric)
+            } else {
+                self.rule.metric.clone()
+            };
+            match state {
+                AlertTransition::Firing => format!("{}: {} = {:.2}（{} {}）", name, what, value, self.rule.op.as_str(), self.rule.threshold),
+                _ => format!("{} 已恢复: {} = {:.2}", name, what, value),
+            }
+        });
+        AlertEvent {
+            rule_id: self.rule.id.clone(),
+            rule_name: name,
+            metric: self.rule.metric.clone(),
+            severity: self.severity(),
+            state,
+            value,
+            threshold: self.rule.threshold,
+            timestamp_ms: now_ms,
+            fired_at_ms: self.fired_at_ms.unwrap_or(now_ms),
+            message,
+        }
+    }
+
+    // 推进状态机；发生 firing / resolved 转换时返回事件
+    fn step(&mut self, now_ms: i64, observed: f64) -> Option<AlertEvent> {
+        self.value = Some(observed);
+        let rule = &self.rule;
+        let breach = rule.op.test(observed, rule.threshold);
+        let for_ms = (rule.for_sec.unwrap_or(0) * 1000) as i64;
+        let clear_for_ms = (rule.clear_for_sec.unwrap_or(0) * 1000) as i64;
+        match self.state {
+            AlertState::Ok | AlertState::Pending => {
+                if !breach {
+                    if self.state == AlertState::Pending {
+                        self.state = AlertState::Ok;
+                        self.since_ms = Some(now_ms);
+                    }
+                    return None;
+                }
+                if self.state == AlertState::Ok {
+                    self.state = AlertState::Pending;
+                    self.since_ms = Some(now_ms);
+                }
+                if now_ms - self.since_ms.unwrap_or(now_ms) >= for_ms {
+                    self.state = AlertState::Firing;
+                    self.since_ms = Some(now_ms);
+                    self.fired_at_ms = Some(now_ms);
+                    self.clear_since_ms = None;
+                    return Some(self.event(AlertTransition::Firing, observed, now_ms));
+                }
+                None
+            }
+            AlertState::Firing => {
+                // 滞回：仍越过恢复阈值则保持 firing
+                let still_active = rule.op.test(observed, rule.clear_threshold.unwrap_or(rule.threshold));
+                if still_active {
+                    self.clear_since_ms = None;
+                    return None;
+                }
+                let clear_since = *self.clear_since_ms.get_or_insert(now_ms);
+                if now_ms - clear_since >= clear_for_ms {
+                    let ev = self.event(AlertTransition::Resolved, observed, now_ms);
+                    self.state = AlertState::Ok;
+                    self.since_ms = Some(now_ms);
+                    self.fired_at_ms = None;
+                    self.clear_since_ms = None;
+                    return Some(ev);
+                }
+                None
+            }
+        }
+    }
+}
+
+/// 校验规则，返回错误描述
+pub fn validate_rule(rule: &AlertRule) -> Result<(), String> {
+    if rule.id.trim().is_empty() {
+        return Err("规则 id 不能为空".to_string());
+    }
+    if rule.metric.trim().is_empty() {
+        return Err(format!("规则 {}: metric 不能为空", rule.id));
+    }
+    if !rule.threshold.is_finite() || rule.clear_threshold.map(|c| !c.is_finite()).unwrap_or(false) {
+        return Err(format!("规则 {}: 阈值无效", rule.id));
+    }
+    // 恢复阈值须位于触发阈值的恢复一侧，否则触发后立即满足恢复条件或永远无法恢复
+    if let Some(clear) = rule.clear_threshold {
+        let ok = match rule.op {
+            AlertOp::Gt | AlertOp::Ge => clear <= rule.threshold,
+            AlertOp::Lt | AlertOp::Le => clear >= rule.threshold,
+            AlertOp::Eq | AlertOp::Ne => clear == rule.threshold,
+        };
+        if !ok {
+            return Err(format!("规则 {}: clear_threshold {} 与 {} {} 方向不符", rule.id, clear, rule.op.as_str(), rule.threshold));
+        }
+    }
+    Ok(())
+}
+
+/// 规则引擎（由采样线程持有）
+#[derive(Default)]
+pub struct AlertEngine {
+    rules: Vec<RuleRuntime>,
+    errors: Vec<String>,
+}
+
+impl AlertEngine {
+    pub fn new() -> Self { Self::default() }
+
+    /// 同步规则：新增/删除规则，内容变化的规则重置状态，未变化的保留状态；
+    /// 被替换或移除时仍在 firing 的规则返回 resolved 事件，避免日志与通知中留下永不恢复的告警
+    pub fn sync_rules(&mut self, rules: &[AlertRule], now_ms: i64) -> Vec<AlertEvent> {
+        let mut old: HashMap<String, RuleRuntime> = self.rules.drain(..).map(|r| (r.rule.id.clone(), r)).collect();
+        let mut seen: HashSet<String> = HashSet::new();
+        self.errors.clear();
+        for rule in rules.iter().filter(|r| r.enabled.unwrap_or(true)) {
+            if let Err(e) = validate_rule(rule) {
+                self.errors.push(e);
+                continue;
+            }
+            if !seen.insert(rule.id.clone()) {
+                self.errors.push(format!("规则 id 重复: {}", rule.id));
+                continue;
+            }
+            let rt = match old.remove(&rule.id) {
+                Some(rt) if rt.rule == *rule => rt,
+                Some(rt) => {
+                    old.insert(rule.id.clone(), rt);
+                    RuleRuntime::new(rule.clone())
+                }
+                None => RuleRuntime::new(rule.clone()),
+            };
+            self.rules.push(rt);
+        }
+        let mut dropped: Vec<RuleRuntime> = old.into_values().filter(|rt| rt.state == AlertState::Firing).collect();
+        dropped.sort_by(|a, b| a.rule.id.cmp(&b.rule.id));
+        dropped
+            .iter()
+            .map(|rt| {
+                let mut ev = rt.event(AlertTransition::Resolved, rt.value.unwrap_or(rt.rule.threshold), now_ms);
+                ev.message = format!("{} 已结束：规则已修改或停用", ev.rule_name);
+                ev
+            })
+            .collect()
+    }
+
+    /// 评估一帧指标，返回本次发生的状态转换
+    pub fn evaluate(&mut self, now_ms: i64, values: &HashMap<String, f64>) -> Vec<AlertEvent> {
+        let mut events = Vec::new();
+        for rt in self.rules.iter_mut() {
+            // 指标缺失（传感器不可用/任务未执行）时保持原状态
+            let Some(v) = values.get(&rt.rule.metric).copied().filter(|v| v.is_finite()) else { continue };
+            let Some(observed) = rt.observe(now_ms, v) else { continue };
+            if let Some(ev) = rt.step(now_ms, observed) { events.push(ev); }
+        }
+        events
+    }
+
+    pub fn status(&self, enabled: bool, now_ms: i64) -> AlertStatus {
+        let rules: Vec<AlertRuleStatus> = self
+            .rules
+            .iter()
+            .map(|rt| AlertRuleStatus {
+                id: rt.rule.id.clone(),
+                name: rt.name(),
+                metric: rt.rule.metric.clone(),
+                severity: rt.severity(),
+                state: rt.state,
+                value: rt.value,
+                since_ms: rt.since_ms,
+                fired_at_ms: rt.fired_at_ms,
+                acknowledged: rt.fired_at_ms.map(|t| crate::alert_journal::is_acknowledged(&rt.rule.id, t)).unwrap_or(false),
+                silenced_until_ms: crate::alert_journal::silenced_until(&rt.rule.id, now_ms),
+            })
+            .collect();
+        AlertStatus {
+            enabled,
+            active: rules.iter().filter(|r| r.state == AlertState::Firing).count(),
+            unacknowledged: rules.iter().filter(|r| r.state == AlertState::Firing && !r.acknowledged).count(),
+            rules,
+            last_eval_ms: Some(now_ms),
+            errors: self.errors.clone(),
+        }
+    }
+
+    /// 每 tick 入口：按配置同步规则、评估并刷新全局状态
+    pub fn tick(&mut self, cfg: Option<&AlertConfig>, now_ms: i64, values: &HashMap<String, f64>) -> Vec<AlertEvent> {
+        let enabled = cfg.map(|c| c.enabled.unwrap_or(true)).unwrap_or(false);
+        let mut rules: Vec<AlertRule> = Vec::new();
+        if enabled {
+            if let Some(c) = cfg {
+                rules.extend(c.rules.iter().flatten().cloned());
+                if let Some(a) = c.anomaly.as_ref() { rules.extend(crate::anomaly_detector::derived_rules(a)); }
+            }
+        }
+        let mut events = self.sync_rules(&rules, now_ms);
+        events.extend(self.evaluate(now_ms, values));
+        for ev in events.iter() {
+            eprintln!("[alert] {} {:?} {}", ev.rule_id, ev.state, ev.message);
+        }
+        if let Ok(mut g) = STATUS.get_or_init(|| Mutex::new(AlertStatus::default())).lock() {
+            *g = self.status(enabled, now_ms);
+        }
+        events
+    }
+}
@@ -0,0 +1,221 @@
+// 告警日志与确认/静默
+// 说明：
+// - 每次状态转换（firing / resolved / acknowledged / silenced）追加到配置目录下的 alerts.jsonl（每行一条 JSON）
+// - 条目数超过 alerts.journal_max_entries（默认 5000）时丢弃最旧的记录（文件按 jsonl_journal 规则延迟压缩）
+// - 确认（acknowledge）针对当前这次触发，规则恢复后自动失效；静默（silence）按时长屏蔽通知，规则仍照常评估与记录
+// - 启动时从日志恢复尚未到期的静默，避免重启后静默失效
+
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::{Mutex, OnceLock};
+
+use serde::{Deserialize, Serialize};
+
+use crate::alert_engine::{AlertEvent, AlertSeverity, AlertState, AlertTransition};
+use crate::jsonl_journal::JsonlJournal;
+
+const JOURNAL_FILE: &str = "alerts.jsonl";
+const DEFAULT_MAX_ENTRIES: usize = 5000;
+
+/// 日志条目
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct AlertJournalEntry {
+    pub timestamp_ms: i64,
+    pub rule_id: String,
+    pub transition: AlertTransition,
+    pub rule_name: Option<String>,
+    pub metric: Option<String>,
+    pub severity: Option<AlertSeverity>,
+    // 触发/恢复时的观测值与阈值
+    pub value: Option<f64>,
+    pub threshold: Option<f64>,
+    // 所属这次触发的时间（用于关联 firing / acknowledged / resolved）
+    pub fired_at_ms: Option<i64>,
+    // 静默截止时间（等于 timestamp_ms 表示解除静默）
+    pub silenced_until_ms: Option<i64>,
+    pub message: Option<String>,
+    // 确认/静默备注
+    pub note: Option<String>,
+}
+
+#[derive(Default)]
+struct Journal {
+    file: Option<JsonlJournal>,
+    // rule_id -> 静默截止时间
+    silences: HashMap<String, i64>,
+    // rule_id -> 已确认的那次触发时间
+    acks: HashMap<String, i64>,
+}
+
+static JOURNAL: OnceLock<Mutex<Journal>> = OnceLock::new();
+
+fn with_journal<R, F: FnOnce(&mut Journal) -> R>(f: F) -> Option<R> {
+    let cell = JOURNAL.get_or_init(|| Mutex::new(Journal::default()));
+    cell.lock().ok().map(|mut g| f(&mut g))
+}
+
+/// 打开日志目录并恢复未到期的静默（未调用时只在内存中维护确认/静默状态）
+pub fn open(dir: &Path, max_entries: Option<usize>) {
+    let file = JsonlJournal::open(dir.join(JOURNAL_FILE), max_entries.unwrap_or(DEFAULT_MAX_ENTRIES));
+    let entries: Vec<AlertJournalEntry> = crate::jsonl_journal::read_entries(file.path());
+    let now = chrono::Local::now().timestamp_millis();
+    let mut silences: HashMap<String, i64> = HashMap::new();
+    for e in entries.iter().filter(|e| e.transition == AlertTransition::Silenced) {
+        silences.insert(e.rule_id.clone(), e.silenced_until_ms.unwrap_or(e.timestamp_ms));
+    }
+    silences.retain(|_, until| *until > now);
+    with_journal(|j| {
+        j.file = Some(file);
+        j.silences = silences;
+        j.acks.clear();
+    });
+}
+
+/// 追加一条记录；超出上限时保留最新的 max_entries 条
+pub fn record(entry: &AlertJournalEntry, max_entries: Option<usize>) {
+    let max = max_entries.unwrap_or(DEFAULT_MAX_ENTRIES).max(1);
+    with_journal(|j| {
+        let Some(file) = j.file.as_mut() else { return };
+        if let Err(e) = file.append(entry, max) {
+            eprintln!("[alert] 写入告警日志失败: {}", e);
+        }
+    });
+}
+
+/// 记录引擎产生的状态转换；恢复时清除对应确认
+pub fn record_event(ev: &AlertEvent, max_entries: Option<usize>) {
+    if ev.state == AlertTransition::Resolved {
+        with_journal(|j| j.acks.remove(&ev.rule_id));
+    }
+    record(
+        &AlertJournalEntry {
+            timestamp_ms: ev.timestamp_ms,
+            rule_id: ev.rule_id.clone(),
+            transition: ev.state,
+            rule_name: Some(ev.rule_name.clone()),
+            metric: Some(ev.metric.clone()),
+            severity: Some(ev.severity),
+            value: Some(ev.value),
+            threshold: Some(ev.threshold),
+            fired_at_ms: Some(ev.fired_at_ms),
+            silenced_until_ms: None,
+            message: Some(ev.message.clone()),
+            note: None,
+        },
+        max_entries,
+    );
+}
+
+/// 查询日志（新记录在前）
+pub fn query(since_ms: Option<i64>, until_ms: Option<i64>, rule_id: Option<&str>, limit: usize) -> Vec<AlertJournalEntry> {
+    let Some(file) = with_journal(|j| j.file.clone()).flatten() else { return Vec::new() };
+    let mut out: Vec<AlertJournalEntry> = file
+        .read::<AlertJournalEntry>()
+        .into_iter()
+        .filter(|e| since_ms.map(|s| e.timestamp_ms >= s).unwrap_or(true) && until_ms.map(|u| e.timestamp_ms <= u).unwrap_or(true))
+        .filter(|e| rule_id.map(|r| e.rule_id == r).unwrap_or(true))
+        .collect();
+    out.reverse();
+    out.truncate(limit);
+    out
+}
+
+/// 规则当前静默截止时间（未静默或已到期返回 None）
+pub fn silenced_until(rule_id: &str, now_ms: i64) -> Option<i64> {
+    with_journal(|j| j.silences.get(rule_id).copied().filter(|until| *until > now_ms)).flatten()
+}
+
+/// 当前这次触发是否已确认
+pub fn is_acknowledged(rule_id: &str, fired_at_ms: i64) -> bool {
+    with_journal(|j| j.acks.get(rule_id) == Some(&fired_at_ms)).unwrap_or(false)
+}
+
+/// 确认当前处于 firing 的规则
+pub fn acknowledge(rule_id: &str, note: Option<String>, max_entries: Option<usize>) -> Result<AlertJournalEntry, String> {
+    let status = crate::alert_engine::alert_get_status();
+    let rule = status
+        .rules
+        .iter()
+        .find(|r| r.id == rule_id)
+        .ok_or_else(|| format!("未知告警规则: {}", rule_id))?;
+    let fired_at = match (rule.state, rule.fired_at_ms) {
+        (AlertState::Firing, Some(t)) => t,
+        _ => return Err(format!("规则 {} 当前未触发", rule_id)),
+    };
+    if is_acknowledged(rule_id, fired_at) {
+        return Err(format!("规则 {} 已确认", rule_id));
+    }
+    with_journal(|j| j.acks.insert(rule_id.to_string(), fired_at));
+    let entry = AlertJournalEntry {
+        timestamp_ms: chrono::Local::now().timestamp_millis(),
+        rule_id: rule_id.to_string(),
+        transition: AlertTransition::Acknowledged,
+        rule_name: Some(rule.name.clone()),
+        metric: Some(rule.metric.clone()),
+        severity: Some(rule.severity),
+        value: rule.value,
+        threshold: None,
+        fired_at_ms: Some(fired_at),
+        silenced_until_ms: None,
+        message: None,
+        note,
+    };
+    record(&entry, max_entries);
+    Ok(entry)
+}
+
+/// 静默规则 duration_sec 秒（0 表示解除静默）
+pub fn silence(rule_id: &str, duration_sec: u64, note: Option<String>, max_entries: Option<usize>) -> Result<AlertJournalEntry, String> {
+    let status = crate::alert_engine::alert_get_status();
+    let rule = status
+        .rules
+        .iter()
+        .find(|r| r.id == rule_id)
+        .ok_or_else(|| format!("未知告警规则: {}", rule_id))?;
+    let now = chrono::Local::now().timestamp_millis();
+    let until = now + (duration_sec as i64) * 1000;
+    with_journal(|j| {
+        if duration_sec == 0 { j.silences.remove(rule_id); } else { j.silences.insert(rule_id.to_string(), until); }
+    });
+    let entry = AlertJournalEntry {
+        timestamp_ms: now,
+        rule_id: rule_id.to_string(),
+        transition: AlertTransition::Silenced,
+        rule_name: Some(rule.name.clone()),
+        metric: Some(rule.metric.clone()),
+        severity: Some(rule.severity),
+        value: rule.value,
+        threshold: None,
+        fired_at_ms: rule.fired_at_ms,
+        silenced_until_ms: Some(until),
+        message: None,
+        note,
+    };
+    record(&entry, max_entries);
+    Ok(entry)
+}
+
+#[cfg(feature = "desktop")]
+fn journal_max(state: &tauri::State<crate::config_utils::AppState>) -> Option<usize> {
+    state.config.lock().ok().and_then(|c| c.alerts.as_ref().and_then(|a| a.journal_max_entries))
+}
+
+/// Tauri命令：查询告警日志（新记录在前，默认 200 条）
+#[cfg_attr(feature = "desktop", tauri::command)]
+pub fn alert_history(since_ms: Option<i64>, until_ms: Option<i64>, rule_id: Option<String>, limit: Option<usize>) -> Vec<AlertJournalEntry> {
+    query(since_ms, until_ms, rule_id.as_deref(), limit.unwrap_or(200))
+}
+
+/// Tauri命令：确认告警
+#[cfg(feature = "desktop")]
+#[tauri::command]
+pub fn alert_acknowledge(rule_id: String, note: Option<String>, state: tauri::State<crate::config_utils::AppState>) -> Result<AlertJournalEntry, String> {
+    acknowledge(&rule_id, note, journal_max(&state))
+}
+
+/// Tauri命令：静默告警规则（duration_sec=0 解除静默）
+#[cfg(feature = "desktop")]
+#[tauri::command]
+pub fn alert_silence(rule_id: String, duration_sec: u64, note: Option<String>, state: tauri::State<crate::config_utils::AppState>) -> Result<AlertJournalEntry, String> {
+    silence(&rule_id, duration_sec, note, journal_max(&state))
+}
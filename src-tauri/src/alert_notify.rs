@@ -0,0 +1,717 @@
+// 告警通知 Sink
+// 说明：
+// - 类型：desktop（系统桌面通知）、webhook（模板化 JSON，内置 generic/slack/teams 格式）、email（SMTP）、script（执行用户命令）
+// - 采样线程只做 push（非阻塞），发送在后台线程 "alert-notify" 中串行完成
+// - 每个 Sink 独立过滤：最低严重级别、是否发送恢复通知、去重窗口（同一规则同一转换）与每小时发送上限
+// - notify_test 命令绕过过滤直接发送测试通知，便于验证配置
+
+use std::collections::{HashMap, VecDeque};
+use std::io::{Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::process::{Command, Stdio};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+
+use crate::alert_engine::{AlertEvent, AlertSeverity, AlertTransition};
+use crate::config_utils::AppConfig;
+
+/// Sink 类型
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum NotifyKind {
+    #[default]
+    Desktop,
+    Webhook,
+    Email,
+    Script,
+}
+
+/// Webhook 内置消息格式
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum WebhookFormat {
+    #[default]
+    Generic,
+    Slack,
+    Teams,
+}
+
+/// SMTP 传输安全
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum SmtpSecurity {
+    // 明文（仅建议用于本机中继）
+    None,
+    // 先明文连接再 STARTTLS 升级（默认，端口 587）
+    #[default]
+    Starttls,
+    // 隐式 TLS（端口 465）
+    Tls,
+}
+
+/// 单个通知 Sink 配置（AppConfig.alerts.sinks）
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Default)]
+#[serde(default)]
+pub struct NotifySinkConfig {
+    // 唯一标识（用于状态与测试通知）
+    pub id: String,
+    pub kind: NotifyKind,
+    // 是否启用（默认启用）
+    pub enabled: Option<bool>,
+    // 最低严重级别（默认 info，即全部）
+    pub min_severity: Option<AlertSeverity>,
+    // 是否发送恢复通知（默认 true）
+    pub send_resolved: Option<bool>,
+    // 去重窗口（秒，默认 300）：同一规则的同一转换在窗口内只发送一次
+    pub dedup_sec: Option<u64>,
+    // 每小时发送上限（默认 30，0 表示不限）
+    pub max_per_hour: Option<u32>,
+    // --- webhook ---
+    pub url: Option<String>,
+    pub format: Option<WebhookFormat>,
+    // 自定义 JSON 模板（占位符 {{rule_id}} {{rule_name}} {{metric}} {{severity}} {{state}} {{value}} {{threshold}} {{message}} {{host}} {{time}}），优先于 format
+    pub template: Option<String>,
+    pub headers: Option<HashMap<String, String>>,
+    // 请求超时（默认 5000ms）
+    pub timeout_ms: Option<u64>,
+    // --- email ---
+    pub smtp_host: Option<String>,
+    pub smtp_port: Option<u16>,
+    pub smtp_security: Option<SmtpSecurity>,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    pub from: Option<String>,
+    pub to: Option<Vec<String>>,
+    // --- script ---
+    pub command: Option<String>,
+    pub args: Option<Vec<String>>,
+}
+
+/// 单个 Sink 的发送统计
+#[derive(Clone, Debug, Serialize, Deserialize, Default)]
+pub struct NotifySinkStatus {
+    pub id: String,
+    pub kind: NotifyKind,
+    pub sent: u64,
+    pub failed: u64,
+    // 因去重/限流被跳过的次数
+    pub suppressed: u64,
+    pub last_sent_ms: Option<i64>,
+    pub last_error: Option<String>,
+}
+
+/// 通知状态（供前端/调试读取）
+#[derive(Clone, Debug, Serialize, Deserialize, Default)]
+pub struct NotifyStatus {
+    pub sinks: Vec<NotifySinkStatus>,
+    pub queued: u64,
+}
+
+static STATUS: OnceLock<Mutex<NotifyStatus>> = OnceLock::new();
+
+fn with_status<F: FnOnce(&mut NotifyStatus)>(f: F) {
+    let cell = STATUS.get_or_init(|| Mutex::new(NotifyStatus::default()));
+    if let Ok(mut g) = cell.lock() { f(&mut g); }
+}
+
+fn with_sink_status<F: FnOnce(&mut NotifySinkStatus)>(sink: &NotifySinkConfig, f: F) {
+    with_status(|s| {
+        let idx = match s.sinks.iter().position(|x| x.id == sink.id) {
+            Some(i) => i,
+            None => {
+                s.sinks.push(NotifySinkStatus { id: sink.id.clone(), kind: sink.kind, ..Default::default() });
+                s.sinks.len() - 1
+            }
+        };
+        s.sinks[idx].kind = sink.kind;
+        f(&mut s.sinks[idx]);
+    });
+}
+
+/// Tauri命令：获取告警通知状态
+#[cfg_attr(feature = "desktop", tauri::command)]
+pub fn notify_get_status() -> NotifyStatus {
+    STATUS
+        .get_or_init(|| Mutex::new(NotifyStatus::default()))
+        .lock()
+        .map(|g| g.clone())
+        .unwrap_or_default()
+}
+
+/// Tauri命令：发送测试通知（sink_id 为空时发送到全部已启用的 Sink），返回各 Sink 的结果
+#[cfg(feature = "desktop")]
+#[tauri::command]
+pub fn notify_test(sink_id: Option<String>, state: tauri::State<crate::config_utils::AppState>) -> Result<Vec<String>, String> {
+    let sinks = state
+        .config
+        .lock()
+        .map_err(|_| "配置锁定失败".to_string())?
+        .alerts
+        .as_ref()
+        .and_then(|a| a.sinks.clone())
+        .unwrap_or_default();
+    send_test(&sinks, sink_id.as_deref())
+}
+
+/// 发送测试通知（不经过去重/限流）
+pub fn send_test(sinks: &[NotifySinkConfig], sink_id: Option<&str>) -> Result<Vec<String>, String> {
+    let targets: Vec<&NotifySinkConfig> = sinks
+        .iter()
+        .filter(|s| match sink_id {
+            Some(id) => s.id == id,
+            None => s.enabled.unwrap_or(true),
+        })
+        .collect();
+    if targets.is_empty() {
+        return Err(match sink_id {
+            Some(id) => format!("未找到通知 Sink: {}", id),
+            None => "没有已启用的通知 Sink".to_string(),
+        });
+    }
+    let now = chrono::Local::now().timestamp_millis();
+    let ev = AlertEvent {
+        rule_id: "test".to_string(),
+        rule_name: "测试通知".to_string(),
+        metric: "cpu_usage".to_string(),
+        severity: AlertSeverity::Info,
+        state: AlertTransition::Firing,
+        value: 0.0,
+        threshold: 0.0,
+        timestamp_ms: now,
+        fired_at_ms: now,
+        message: "这是一条来自 sys-sensor 的测试通知".to_string(),
+    };
+    let host = host_name();
+    Ok(targets
+        .into_iter()
+        .map(|s| match send_to_sink(s, &ev, &host) {
+            Ok(()) => format!("{}: 已发送", s.id),
+            Err(e) => format!("{}: 失败（{}）", s.id, e),
+        })
+        .collect())
+}
+
+fn host_name() -> String {
+    sysinfo::System::host_name().unwrap_or_else(|| "sys-sensor".to_string())
+}
+
+// ---- 过滤：严重级别 / 恢复 / 去重 / 限流 ----
+
+/// 各 Sink 的去重与限流状态
+#[derive(Default)]
+pub struct NotifyGate {
+    // (sink_id, rule_id, transition) -> 上次发送时间
+    last_sent: HashMap<(String, String, AlertTransition), Instant>,
+    // sink_id -> 最近一小时的发送时间
+    window: HashMap<String, VecDeque<Instant>>,
+}
+
+impl NotifyGate {
+    pub fn new() -> Self { Self::default() }
+
+    /// 判断是否发送；Err 为跳过原因。允许发送时同时记账
+    pub fn admit(&mut self, sink: &NotifySinkConfig, ev: &AlertEvent, now: Instant) -> Result<(), &'static str> {
+        if ev.severity < sink.min_severity.unwrap_or(AlertSeverity::Info) {
+            return Err("低于最低严重级别");
+        }
+        if ev.state == AlertTransition::Resolved && !sink.send_resolved.unwrap_or(true) {
+            return Err("未启用恢复通知");
+        }
+        let key = (sink.id.clone(), ev.rule_id.clone(), ev.state);
+        let dedup = Duration::from_secs(sink.dedup_sec.unwrap_or(300));
+        if let Some(t) = self.last_sent.get(&key) {
+            if now.saturating_duration_since(*t) < dedup {
+                return Err("去重窗口内");
+            }
+        }
+        let max = sink.max_per_hour.unwrap_or(30) as usize;
+        let win = self.window.entry(sink.id.clone()).or_default();
+        while let Some(t) = win.front() {
+            if now.saturating_duration_since(*t) >= Duration::from_secs(3600) { win.pop_front(); } else { break; }
+        }
+        if max > 0 && win.len() >= max {
+            return Err("超过每小时发送上限");
+        }
+        win.push_back(now);
+        self.last_sent.insert(key, now);
+        Ok(())
+    }
+}
+
+// ---- 模板 ----
+
+fn state_str(t: AlertTransition) -> &'static str {
+    match t {
+        AlertTransition::Firing => "firing",
+        AlertTransition::Resolved => "resolved",
+        AlertTransition::Acknowledged => "acknowledged",
+        AlertTransition::Silenced => "silenced",
+    }
+}
+
+fn severity_str(s: AlertSeverity) -> &'static str {
+    match s {
+        AlertSeverity::Info => "info",
+        AlertSeverity::Warning => "warning",
+        AlertSeverity::Critical => "critical",
+    }
+}
+
+fn format_time(ts_ms: i64) -> String {
+    use chrono::TimeZone;
+    chrono::Local
+        .timestamp_millis_opt(ts_ms)
+        .single()
+        .map(|t| t.format("%Y-%m-%d %H:%M:%S").to_string())
+        .unwrap_or_else(|| ts_ms.to_string())
+}
+
+/// 一行摘要（桌面通知标题/邮件主题/Slack 文本）
+pub fn summary_line(ev: &AlertEvent, host: &str) -> String {
+    let tag = match ev.state {
+        AlertTransition::Firing => severity_str(ev.severity).to_uppercase(),
+        other => state_str(other).to_uppercase(),
+    };
+    format!("[{}] {} @ {}", tag, ev.rule_name, host)
+}
+
+/// 渲染模板；json_escape 为 true 时对替换值做 JSON 字符串转义（模板中占位符应位于引号内）
+pub fn render_template(tpl: &str, ev: &AlertEvent, host: &str, json_escape: bool) -> String {
+    let vars: [(&str, String); 10] = [
+        ("rule_id", ev.rule_id.clone()),
+        ("rule_name", ev.rule_name.clone()),
+        ("metric", ev.metric.clone()),
+        ("severity", severity_str(ev.severity).to_string()),
+        ("state", state_str(ev.state).to_string()),
+        ("value", format!("{:.2}", ev.value)),
+        ("threshold", ev.threshold.to_string()),
+        ("message", ev.message.clone()),
+        ("host", host.to_string()),
+        ("time", format_time(ev.timestamp_ms)),
+    ];
+    let mut out = tpl.to_string();
+    for (k, v) in vars.iter() {
+        let v = if json_escape {
+            let quoted = serde_json::to_string(v).unwrap_or_default();
+            quoted[1..quoted.len() - 1].to_string()
+        } else {
+            v.clone()
+        };
+        out = out.replace(&format!("{{{{{}}}}}", k), &v);
+    }
+    out
+}
+
+/// 构造 webhook 请求体
+pub fn webhook_body(sink: &NotifySinkConfig, ev: &AlertEvent, host: &str) -> Result<String, String> {
+    if let Some(tpl) = sink.template.as_ref().filter(|t| !t.trim().is_empty()) {
+        let body = render_template(tpl, ev, host, true);
+        // 校验渲染结果为合法 JSON，避免把半截模板发给服务端
+        serde_json::from_str::<serde_json::Value>(&body).map_err(|e| format!("模板渲染结果不是合法 JSON: {}", e))?;
+        return Ok(body);
+    }
+    let v = match sink.format.unwrap_or_default() {
+        WebhookFormat::Generic => serde_json::json!({
+            "host": host,
+            "rule_id": ev.rule_id,
+            "rule_name": ev.rule_name,
+            "metric": ev.metric,
+            "severity": ev.severity,
+            "state": ev.state,
+            "value": ev.value,
+            "threshold": ev.threshold,
+            "timestamp_ms": ev.timestamp_ms,
+            "fired_at_ms": ev.fired_at_ms,
+            "message": ev.message,
+        }),
+        WebhookFormat::Slack => serde_json::json!({
+            "text": format!("*{}*\n{}", summary_line(ev, host), ev.message),
+        }),
+        WebhookFormat::Teams => {
+            let color = match (ev.state, ev.severity) {
+                (AlertTransition::Resolved, _) => "2EB886",
+                (_, AlertSeverity::Critical) => "D93F0B",
+                (_, AlertSeverity::Warning) => "FBCA04",
+                (_, AlertSeverity::Info) => "0366D6",
+            };
+            serde_json::json!({
+                "@type": "MessageCard",
+                "@context": "https://schema.org/extensions",
+                "themeColor": color,
+                "summary": summary_line(ev, host),
+                "title": summary_line(ev, host),
+                "text": ev.message,
+            })
+        }
+    };
+    Ok(v.to_string())
+}
+
+// ---- 发送 ----
+
+/// 发送到单个 Sink（不做过滤）
+pub fn send_to_sink(sink: &NotifySinkConfig, ev: &AlertEvent, host: &str) -> Result<(), String> {
+    match sink.kind {
+        NotifyKind::Desktop => send_desktop(ev, host),
+        NotifyKind::Webhook => send_webhook(sink, ev, host),
+        NotifyKind::Email => send_email(sink, ev, host),
+        NotifyKind::Script => run_script(sink, ev, host),
+    }
+}
+
+fn send_webhook(sink: &NotifySinkConfig, ev: &AlertEvent, host: &str) -> Result<(), String> {
+    let url = sink.url.as_deref().filter(|u| !u.trim().is_empty()).ok_or("未配置 url")?;
+    let body = webhook_body(sink, ev, host)?;
+    let mut req = ureq::post(url)
+        .timeout(Duration::from_millis(sink.timeout_ms.unwrap_or(5000)))
+        .set("Content-Type", "application/json");
+    if let Some(h) = sink.headers.as_ref() {
+        for (k, v) in h.iter() { req = req.set(k, v); }
+    }
+    match req.send_string(&body) {
+        Ok(_) => Ok(()),
+        Err(ureq::Error::Status(code, resp)) => {
+            Err(format!("HTTP {}: {}", code, resp.into_string().unwrap_or_default().trim()))
+        }
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+/// 脚本环境变量（SYS_SENSOR_ALERT_*）
+pub fn script_env(ev: &AlertEvent, host: &str) -> Vec<(String, String)> {
+    vec![
+        ("SYS_SENSOR_ALERT_RULE_ID".into(), ev.rule_id.clone()),
+        ("SYS_SENSOR_ALERT_RULE_NAME".into(), ev.rule_name.clone()),
+        ("SYS_SENSOR_ALERT_METRIC".into(), ev.metric.clone()),
+        ("SYS_SENSOR_ALERT_SEVERITY".into(), severity_str(ev.severity).to_string()),
+        ("SYS_SENSOR_ALERT_STATE".into(), state_str(ev.state).to_string()),
+        ("SYS_SENSOR_ALERT_VALUE".into(), ev.value.to_string()),
+        ("SYS_SENSOR_ALERT_THRESHOLD".into(), ev.threshold.to_string()),
+        ("SYS_SENSOR_ALERT_TIMESTAMP_MS".into(), ev.timestamp_ms.to_string()),
+        ("SYS_SENSOR_ALERT_MESSAGE".into(), ev.message.clone()),
+        ("SYS_SENSOR_ALERT_HOST".into(), host.to_string()),
+    ]
+}
+
+/// 脚本失败时错误信息中保留的 stderr 上限（字节）
+const SCRIPT_STDERR_MAX: usize = 4096;
+
+fn run_script(sink: &NotifySinkConfig, ev: &AlertEvent, host: &str) -> Result<(), String> {
+    let program = sink.command.as_deref().filter(|c| !c.trim().is_empty()).ok_or("未配置 command")?;
+    let mut cmd = Command::new(program);
+    cmd.args(sink.args.clone().unwrap_or_default())
+        .envs(script_env(ev, host))
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped());
+    #[cfg(windows)]
+    {
+        use std::os::windows::process::CommandExt;
+        cmd.creation_flags(0x08000000); // CREATE_NO_WINDOW
+    }
+    let mut child = cmd.spawn().map_err(|e| format!("启动命令失败: {}", e))?;
+    // stderr 在独立线程中持续读取（只保留开头一段），避免输出填满管道后脚本阻塞直到超时
+    let (err_tx, err_rx) = mpsc::channel::<String>();
+    if let Some(mut pipe) = child.stderr.take() {
+        std::thread::spawn(move || {
+            let mut kept = Vec::new();
+            let mut buf = [0u8; 4096];
+            while let Ok(n) = pipe.read(&mut buf) {
+                if n == 0 { break; }
+                let room = SCRIPT_STDERR_MAX.saturating_sub(kept.len());
+                kept.extend_from_slice(&buf[..n.min(room)]);
+            }
+            let _ = err_tx.send(String::from_utf8_lossy(&kept).into_owned());
+        });
+    }
+    // 超时（默认 30s）后强制结束，避免卡住通知线程
+    let deadline = Instant::now() + Duration::from_millis(sink.timeout_ms.unwrap_or(30_000));
+    loop {
+        match child.try_wait() {
+            Ok(Some(status)) if status.success() => return Ok(()),
+            Ok(Some(status)) => {
+                // 脚本派生的后台进程可能继续持有 stderr，最多再等 1s
+                let err = err_rx.recv_timeout(Duration::from_secs(1)).unwrap_or_default();
+                return Err(format!("命令退出码 {:?}: {}", status.code(), err.trim()));
+            }
+            Ok(None) if Instant::now() >= deadline => {
+                let _ = child.kill();
+                let _ = child.wait();
+                return Err("命令执行超时".to_string());
+            }
+            Ok(None) => std::thread::sleep(Duration::from_millis(50)),
+            Err(e) => return Err(e.to_string()),
+        }
+    }
+}
+
+fn send_desktop(ev: &AlertEvent, host: &str) -> Result<(), String> {
+    let title = summary_line(ev, host);
+    let body = ev.message.clone();
+    #[cfg(windows)]
+    let output = {
+        use std::os::windows::process::CommandExt;
+        // Windows 10+ Toast（借用 PowerShell 的 AppUserModelID，无需注册快捷方式）
+        let esc = |s: &str| s.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('\'', "''");
+        let script = format!(
+            "[Windows.UI.Notifications.ToastNotificationManager, Windows.UI.Notifications, ContentType = WindowsRuntime] > $null; \
+             [Windows.Data.Xml.Dom.XmlDocument, Windows.Data.Xml.Dom.XmlDocument, ContentType = WindowsRuntime] > $null; \
+             $xml = New-Object Windows.Data.Xml.Dom.XmlDocument; \
+             $xml.LoadXml('<toast><visual><binding template=\"ToastGeneric\"><text>{}</text><text>{}</text></binding></visual></toast>'); \
+             $toast = [Windows.UI.Notifications.ToastNotification]::new($xml); \
+             [Windows.UI.Notifications.ToastNotificationManager]::CreateToastNotifier('{{1AC14E77-02E7-4E5D-B744-2EB1AE5198B7}}\\WindowsPowerShell\\v1.0\\powershell.exe').Show($toast)",
+            esc(&title), esc(&body)
+        );
+        Command::new("powershell")
+            .args(["-NoProfile", "-NonInteractive", "-Command", &script])
+            .creation_flags(0x08000000) // CREATE_NO_WINDOW
+            .output()
+    };
+    #[cfg(target_os = "macos")]
+    let output = {
+        let esc = |s: &str| s.replace('\\', "\\\\").replace('"', "\\\"");
+        Command::new("osascript")
+            .args(["-e", &format!("display notification \"{}\" with title \"{}\"", esc(&body), esc(&title))])
+            .output()
+    };
+    #[cfg(all(unix, not(target_os = "macos")))]
+    let output = {
+        let urgency = match (ev.state, ev.severity) {
+            (AlertTransition::Firing, AlertSeverity::Critical) => "critical",
+            _ => "normal",
+        };
+        Command::new("notify-send").args(["-u", urgency, "-a", "sys-sensor", &title, &body]).output()
+    };
+    match output {
+        Ok(o) if o.status.success() => Ok(()),
+        Ok(o) => Err(format!("桌面通知失败: {}", String::from_utf8_lossy(&o.stderr).trim())),
+        Err(e) => Err(format!("桌面通知不可用: {}", e)),
+    }
+}
+
+// ---- SMTP ----
+
+enum SmtpStream {
+    Plain(TcpStream),
+    Tls(Box<rustls::StreamOwned<rustls::ClientConnection, TcpStream>>),
+}
+
+impl Read for SmtpStream {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        match self {
+            SmtpStream::Plain(s) => s.read(buf),
+            SmtpStream::Tls(s) => s.read(buf),
+        }
+    }
+}
+
+impl Write for SmtpStream {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        match self {
+            SmtpStream::Plain(s) => s.write(buf),
+            SmtpStream::Tls(s) => s.write(buf),
+        }
+    }
+    fn flush(&mut self) -> std::io::Result<()> {
+        match self {
+            SmtpStream::Plain(s) => s.flush(),
+            SmtpStream::Tls(s) => s.flush(),
+        }
+    }
+}
+
+struct SmtpConn {
+    stream: SmtpStream,
+    buf: Vec<u8>,
+}
+
+impl SmtpConn {
+    // 读取一条（可能多行的）应答，返回 (状态码, 全文)
+    fn read_reply(&mut self) -> Result<(u16, String), String> {
+        let mut lines: Vec<String> = Vec::new();
+        let mut tmp = [0u8; 1024];
+        loop {
+            while let Some(pos) = self.buf.windows(2).position(|w| w == b"\r\n") {
+                let line = String::from_utf8_lossy(&self.buf[..pos]).to_string();
+                self.buf.drain(..pos + 2);
+                let done = line.len() < 4 || line.as_bytes()[3] != b'-';
+                lines.push(line);
+                if done {
+                    let last = lines.last().cloned().unwrap_or_default();
+                    let code = last.get(..3).and_then(|c| c.parse::<u16>().ok()).ok_or_else(|| format!("无效的 SMTP 应答: {}", last))?;
+                    return Ok((code, lines.join("\n")));
+                }
+            }
+            let n = self.stream.read(&mut tmp).map_err(|e| format!("读取 SMTP 应答失败: {}", e))?;
+            if n == 0 { return Err("SMTP 连接被关闭".to_string()); }
+            self.buf.extend_from_slice(&tmp[..n]);
+        }
+    }
+
+    // 发送命令并校验应答码
+    fn cmd(&mut self, line: &str, expect: u16) -> Result<String, String> {
+        self.stream.write_all(format!("{}\r\n", line).as_bytes()).map_err(|e| format!("发送 SMTP 命令失败: {}", e))?;
+        self.stream.flush().map_err(|e| e.to_string())?;
+        let (code, text) = self.read_reply()?;
+        if code != expect {
+            // 不回显 AUTH 凭据
+            let shown = if line.starts_with("AUTH") { "AUTH" } else { line };
+            return Err(format!("{} -> {}", shown, text));
+        }
+        Ok(text)
+    }
+}
+
+fn encode_header(s: &str) -> String {
+    if s.is_ascii() { s.to_string() } else { format!("=?UTF-8?B?{}?=", base64::engine::general_purpose::STANDARD.encode(s)) }
+}
+
+/// 构造邮件正文（RFC 5322，正文 base64 编码）
+pub fn build_email(from: &str, to: &[String], ev: &AlertEvent, host: &str) -> String {
+    let subject = summary_line(ev, host);
+    let text = format!(
+        "{}\r\n\r\n主机: {}\r\n规则: {} ({})\r\n指标: {}\r\n级别: {}\r\n状态: {}\r\n数值: {:.2}\r\n阈值: {}\r\n时间: {}\r\n",
+        ev.message, host, ev.rule_name, ev.rule_id, ev.metric, severity_str(ev.severity), state_str(ev.state),
+        ev.value, ev.threshold, format_time(ev.timestamp_ms)
+    );
+    let encoded = base64::engine::general_purpose::STANDARD.encode(text.as_bytes());
+    let body: Vec<&str> = encoded.as_bytes().chunks(76).map(|c| std::str::from_utf8(c).unwrap_or("")).collect();
+    format!(
+        "From: {}\r\nTo: {}\r\nSubject: {}\r\nDate: {}\r\nMIME-Version: 1.0\r\nContent-Type: text/plain; charset=utf-8\r\nContent-Transfer-Encoding: base64\r\n\r\n{}\r\n",
+        from,
+        to.join(", "),
+        encode_header(&subject),
+        chrono::Local::now().to_rfc2822(),
+        body.join("\r\n")
+    )
+}
+
+fn send_email(sink: &NotifySinkConfig, ev: &AlertEvent, host: &str) -> Result<(), String> {
+    let smtp_host = sink.smtp_host.as_deref().filter(|h| !h.trim().is_empty()).ok_or("未配置 smtp_host")?;
+    let to: Vec<String> = sink.to.clone().unwrap_or_default().into_iter().filter(|t| !t.trim().is_empty()).collect();
+    if to.is_empty() { return Err("未配置收件人 to".to_string()); }
+    let from = sink.from.clone().or_else(|| sink.username.clone()).ok_or("未配置发件人 from")?;
+    let security = sink.smtp_security.unwrap_or_default();
+    let port = sink.smtp_port.unwrap_or(match security {
+        SmtpSecurity::None => 25,
+        SmtpSecurity::Starttls => 587,
+        SmtpSecurity::Tls => 465,
+    });
+    let timeout = Duration::from_millis(sink.timeout_ms.unwrap_or(10_000));
+
+    let addr = (smtp_host, port)
+        .to_socket_addrs()
+        .map_err(|e| format!("解析 SMTP 地址失败: {}", e))?
+        .next()
+        .ok_or_else(|| "SMTP 地址无结果".to_string())?;
+    let tcp = TcpStream::connect_timeout(&addr, timeout).map_err(|e| format!("连接 SMTP 服务器失败: {}", e))?;
+    tcp.set_read_timeout(Some(timeout)).map_err(|e| e.to_string())?;
+    let wrap_tls = |tcp: TcpStream| -> Result<SmtpStream, String> {
+        let tls = crate::mqtt_sink::tls_config(None)?;
+        let name = rustls::pki_types::ServerName::try_from(smtp_host.to_string()).map_err(|e| format!("无效的 TLS 主机名: {}", e))?;
+        let conn = rustls::ClientConnection::new(tls, name).map_err(|e| e.to_string())?;
+        Ok(SmtpStream::Tls(Box::new(rustls::StreamOwned::new(conn, tcp))))
+    };
+    let stream = if security == SmtpSecurity::Tls { wrap_tls(tcp)? } else { SmtpStream::Plain(tcp) };
+    let mut conn = SmtpConn { stream, buf: Vec::new() };
+
+    let (code, greet) = conn.read_reply()?;
+    if code != 220 { return Err(format!("SMTP 服务器拒绝连接: {}", greet)); }
+    let helo_name = host.chars().filter(|c| c.is_ascii_alphanumeric() || *c == '-' || *c == '.').collect::<String>();
+    let helo_name = if helo_name.is_empty() { "localhost".to_string() } else { helo_name };
+    let mut caps = conn.cmd(&format!("EHLO {}", helo_name), 250)?;
+    if security == SmtpSecurity::Starttls {
+        conn.cmd("STARTTLS", 220)?;
+        let SmtpStream::Plain(tcp) = conn.stream else { return Err("STARTTLS 状态错误".to_string()) };
+        conn = SmtpConn { stream: wrap_tls(tcp)?, buf: Vec::new() };
+        caps = conn.cmd(&format!("EHLO {}", helo_name), 250)?;
+    }
+    if let Some(user) = sink.username.as_deref().filter(|u| !u.is_empty()) {
+        let pass = sink.password.clone().unwrap_or_default();
+        if caps.to_ascii_uppercase().contains("PLAIN") || !caps.to_ascii_uppercase().contains("LOGIN") {
+            let token = base64::engine::general_purpose::STANDARD.encode(format!("\0{}\0{}", user, pass));
+            conn.cmd(&format!("AUTH PLAIN {}", token), 235)?;
+        } else {
+            conn.cmd("AUTH LOGIN", 334)?;
+            conn.cmd(&base64::engine::general_purpose::STANDARD.encode(user), 334)?;
+            conn.cmd(&base64::engine::general_purpose::STANDARD.encode(pass), 235)?;
+        }
+    }
+    let addr_only = |s: &str| -> String {
+        match (s.find('<'), s.rfind('>')) {
+            (Some(a), Some(b)) if a < b => s[a + 1..b].to_string(),
+            _ => s.trim().to_string(),
+        }
+    };
+    conn.cmd(&format!("MAIL FROM:<{}>", addr_only(&from)), 250)?;
+    for rcpt in to.iter() {
+        conn.cmd(&format!("RCPT TO:<{}>", addr_only(rcpt)), 250)?;
+    }
+    conn.cmd("DATA", 354)?;
+    let msg = build_email(&from, &to, ev, host);
+    // 点填充：以 "." 开头的行前补一个 "."
+    let stuffed = msg.split("\r\n").map(|l| if l.starts_with('.') { format!(".{}", l) } else { l.to_string() }).collect::<Vec<_>>().join("\r\n");
+    conn.cmd(&format!("{}.", stuffed), 250)?;
+    let _ = conn.cmd("QUIT", 221);
+    Ok(())
+}
+
+// ---- 后台分发 ----
+
+/// 通知句柄：采样线程持有，push 只做一次 channel 发送
+#[derive(Clone)]
+pub struct Notifier {
+    tx: Sender<AlertEvent>,
+}
+
+impl Notifier {
+    pub fn push(&self, ev: &AlertEvent) {
+        if self.tx.send(ev.clone()).is_ok() {
+            with_status(|s| s.queued += 1);
+        }
+    }
+}
+
+pub fn start_notifier(cfg_state: Arc<Mutex<AppConfig>>) -> Notifier {
+    let (tx, rx): (Sender<AlertEvent>, Receiver<AlertEvent>) = mpsc::channel();
+    std::thread::Builder::new()
+        .name("alert-notify".into())
+        .spawn(move || {
+            let host = host_name();
+            let mut gate = NotifyGate::new();
+            for ev in rx.iter() {
+                with_status(|s| s.queued = s.queued.saturating_sub(1));
+                let sinks = cfg_state
+                    .lock()
+                    .ok()
+                    .and_then(|c| c.alerts.as_ref().and_then(|a| a.sinks.clone()))
+                    .unwrap_or_default();
+                for sink in sinks.iter().filter(|s| s.enabled.unwrap_or(true)) {
+                    if gate.admit(sink, &ev, Instant::now()).is_err() {
+                        with_sink_status(sink, |st| st.suppressed += 1);
+                        continue;
+                    }
+                    match send_to_sink(sink, &ev, &host) {
+                        Ok(()) => with_sink_status(sink, |st| {
+                            st.sent += 1;
+                            st.last_sent_ms = Some(chrono::Local::now().timestamp_millis());
+                            st.last_error = None;
+                        }),
+                        Err(e) => {
+                            eprintln!("[notify] {} 发送失败: {}", sink.id, e);
+                            with_sink_status(sink, |st| {
+                                st.failed += 1;
+                                st.last_error = Some(e);
+                            });
+                        }
+                    }
+                }
+            }
+        })
+        .expect("spawn alert-notify");
+    Notifier { tx }
+}
@@ -0,0 +1,472 @@
+// 传感器流统计异常检测
+// 说明：
+// - 对配置的指标维护滚动基线，按偏离程度（z 分数）输出派生指标 "anomaly_z.<metric>"，与快照指标一起送入告警引擎
+// - 基线模型：
+//   - ewma：指数加权均值/方差
+//   - seasonal：按本地小时划分 24 桶的日周期画像（桶按样本时长加权，记忆约 SEASONAL_MEMORY_DAYS 天；桶内样本不足时回退到全局 EWMA）
+//   - regression：以另一指标为自变量的指数加权线性回归（如 CPU 温度 ~ CPU 占用、风扇转速 ~ CPU 温度），对残差计算 z 分数
+// - 每个检测项自动生成一条告警规则（id "anomaly.<metric>"），持续时间/滞回/确认/静默/通知全部复用告警引擎
+// - 样本数未达 min_samples 前不输出分数（对应规则保持原状态）；新建检测项时先用历史环形缓冲预热
+// - 处于偏离状态的样本以 1/10 权重学习，避免基线被异常值迅速拖走，同时仍能逐步适应长期的水平变化
+
+use std::collections::{HashMap, HashSet};
+use std::sync::{Mutex, OnceLock};
+
+use serde::{Deserialize, Serialize};
+
+use crate::alert_engine::{AlertOp, AlertRule, AlertSeverity};
+use crate::state_store::HistoryPoint;
+
+const DEFAULT_SENSITIVITY: f64 = 3.0;
+const DEFAULT_ALPHA: f64 = 0.01;
+const DEFAULT_MIN_SAMPLES: u64 = 120;
+const DEFAULT_FOR_SEC: u64 = 60;
+// 恢复阈值 = sensitivity × CLEAR_RATIO（滞回）
+const CLEAR_RATIO: f64 = 0.75;
+// 偏离期间的学习权重
+const ANOMALY_LEARN_RATIO: f64 = 0.1;
+// seasonal 小时桶的记忆跨度（天）：每个样本的权重 = 样本时长 / 跨度内该小时的总时长，与采样节拍无关
+const SEASONAL_MEMORY_DAYS: f64 = 7.0;
+const HOUR_MS: i64 = 3_600_000;
+
+/// 基线模型
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum AnomalyModel {
+    #[default]
+    Ewma,
+    Seasonal,
+    Regression,
+}
+
+/// 偏离方向：above=仅偏高，below=仅偏低，both=双向
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum AnomalyDirection {
+    Above,
+    Below,
+    #[default]
+    Both,
+}
+
+/// 单个指标的检测配置
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Default)]
+#[serde(default)]
+pub struct AnomalyMetricConfig {
+    // 被检测指标（与告警规则的 metric 同名，如 "fan_rpm"）
+    pub metric: String,
+    pub model: Option<AnomalyModel>,
+    // regression 模型的自变量指标（如 "cpu_usage"、"cpu_temp_c"）
+    pub regressor: Option<String>,
+    // 灵敏度：偏离超过多少个标准差视为异常（默认 3.0，越小越敏感）
+    pub sensitivity: Option<f64>,
+    pub direction: Option<AnomalyDirection>,
+    // 平滑系数（默认 0.01，约相当于最近 100 个样本；seasonal 模型仅用于全局回退基线）
+    pub alpha: Option<f64>,
+    // 开始输出分数前需要的样本数（默认 120；seasonal 模型按每个小时桶计）
+    pub min_samples: Option<u64>,
+    // 标准差下限，避免恒定信号上的微小波动被放大（默认取期望值的 1%）
+    pub min_std: Option<f64>,
+    // 异常需持续的秒数（默认 60）
+    pub for_sec: Option<u64>,
+    // 恢复需持续的秒数（默认 60）
+    pub clear_for_sec: Option<u64>,
+    pub severity: Option<AlertSeverity>,
+    // 是否启用（默认启用）
+    pub enabled: Option<bool>,
+}
+
+/// 异常检测配置（AppConfig.alerts.anomaly）
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Default)]
+#[serde(default)]
+pub struct AnomalyConfig {
+    // 总开关（默认启用；受 alerts.enabled 约束）
+    pub enabled: Option<bool>,
+    pub metrics: Option<Vec<AnomalyMetricConfig>>,
+}
+
+/// 单个检测项状态
+#[derive(Clone, Debug, Serialize, Deserialize, Default)]
+pub struct AnomalyMetricStatus {
+    pub metric: String,
+    pub model: AnomalyModel,
+    pub regressor: Option<String>,
+    pub samples: u64,
+    // 已达到 min_samples，开始输出分数
+    pub ready: bool,
+    pub value: Option<f64>,
+    // 基线给出的期望值与标准差
+    pub expected: Option<f64>,
+    pub std_dev: Option<f64>,
+    // 带符号的 z 分数与按方向折算后的分数（即 anomaly_z.<metric> 的值）
+    pub z: Option<f64>,
+    pub score: Option<f64>,
+}
+
+/// 检测器状态
+#[derive(Clone, Debug, Serialize, Deserialize, Default)]
+pub struct AnomalyStatus {
+    pub enabled: bool,
+    pub metrics: Vec<AnomalyMetricStatus>,
+    pub last_eval_ms: Option<i64>,
+    // 无效配置（被跳过）的原因
+    pub errors: Vec<String>,
+}
+
+static STATUS: OnceLock<Mutex<AnomalyStatus>> = OnceLock::new();
+
+/// Tauri命令：获取异常检测状态
+#[cfg_attr(feature = "desktop", tauri::command)]
+pub fn anomaly_get_status() -> AnomalyStatus {
+    STATUS
+        .get_or_init(|| Mutex::new(AnomalyStatus::default()))
+        .lock()
+        .map(|g| g.clone())
+        .unwrap_or_default()
+}
+
+/// 派生指标键
+pub fn score_key(metric: &str) -> String {
+    format!("anomaly_z.{}", metric)
+}
+
+/// 校验检测配置，返回错误描述
+pub fn validate_metric(m: &AnomalyMetricConfig) -> Result<(), String> {
+    if m.metric.trim().is_empty() {
+        return Err("异常检测: metric 不能为空".to_string());
+    }
+    if m.model.unwrap_or_default() == AnomalyModel::Regression
+        && m.regressor.as_deref().map(|r| r.trim().is_empty()).unwrap_or(true)
+    {
+        return Err(format!("异常检测 {}: regression 模型需要 regressor", m.metric));
+    }
+    if let Some(s) = m.sensitivity {
+        if !s.is_finite() || s <= 0.0 {
+            return Err(format!("异常检测 {}: sensitivity 必须为正数", m.metric));
+        }
+    }
+    if let Some(a) = m.alpha {
+        if !(a > 0.0 && a <= 1.0) {
+            return Err(format!("异常检测 {}: alpha 须在 (0, 1] 范围内", m.metric));
+        }
+    }
+    Ok(())
+}
+
+fn enabled_metrics(cfg: &AnomalyConfig) -> impl Iterator<Item = &AnomalyMetricConfig> {
+    let on = cfg.enabled.unwrap_or(true);
+    cfg.metrics
+        .as_deref()
+        .unwrap_or(&[])
+        .iter()
+        .filter(move |m| on && m.enabled.unwrap_or(true))
+}
+
+/// 由检测配置生成告警规则（score > sensitivity 持续 for_sec 触发，低于 sensitivity×0.75 持续 clear_for_sec 恢复）
+pub fn derived_rules(cfg: &AnomalyConfig) -> Vec<AlertRule> {
+    let mut seen: HashSet<&str> = HashSet::new();
+    enabled_metrics(cfg)
+        .filter(|m| validate_metric(m).is_ok() && seen.insert(m.metric.as_str()))
+        .map(|m| {
+            let sens = m.sensitivity.unwrap_or(DEFAULT_SENSITIVITY);
+            AlertRule {
+                id: format!("anomaly.{}", m.metric),
+                name: Some(format!("{} 异常", m.metric)),
+                metric: score_key(&m.metric),
+                op: AlertOp::Gt,
+                threshold: sens,
+                clear_threshold: Some(sens * CLEAR_RATIO),
+                for_sec: Some(m.for_sec.unwrap_or(DEFAULT_FOR_SEC)),
+                clear_for_sec: Some(m.clear_for_sec.unwrap_or(DEFAULT_FOR_SEC)),
+                severity: Some(m.severity.unwrap_or_default()),
+                ..Default::default()
+            }
+        })
+        .collect()
+}
+
+/// 指数加权均值/方差；前 1/alpha 个样本内退化为累计均值，避免初期方差被低估
+#[derive(Clone, Debug, Default)]
+struct Ewma {
+    n: u64,
+    mean: f64,
+    var: f64,
+}
+
+impl Ewma {
+    fn update(&mut self, x: f64, alpha: f64, damped: bool) {
+        if self.n == 0 {
+            self.mean = x;
+            self.var = 0.0;
+        } else {
+            let mut a = alpha.max(1.0 / (self.n as f64 + 1.0));
+            if damped { a *= ANOMALY_LEARN_RATIO; }
+            let d = x - self.mean;
+            self.mean += a * d;
+            self.var = (1.0 - a) * (self.var + a * d * d);
+        }
+        self.n += 1;
+    }
+
+    fn std_dev(&self) -> f64 { self.var.max(0.0).sqrt() }
+}
+
+/// 指数加权最小二乘 y = a + b·x
+#[derive(Clone, Debug, Default)]
+struct Regression {
+    n: u64,
+    sw: f64,
+    sx: f64,
+    sy: f64,
+    sxx: f64,
+    sxy: f64,
+    // 残差分布
+    resid: Ewma,
+}
+
+impl Regression {
+    fn predict(&self, x: f64) -> Option<f64> {
+        if self.sw <= 0.0 { return None; }
+        let den = self.sw * self.sxx - self.sx * self.sx;
+        // 自变量几乎不变时退化为均值
+        let b = if den.abs() > 1e-9 * self.sw * self.sw { (self.sw * self.sxy - self.sx * self.sy) / den } else { 0.0 };
+        Some((self.sy - b * self.sx) / self.sw + b * x)
+    }
+
+    fn update(&mut self, x: f64, y: f64, alpha: f64, damped: bool) {
+        if let Some(p) = self.predict(x) { self.resid.update(y - p, alpha, damped); }
+        let mut a = alpha.max(1.0 / (self.n as f64 + 1.0));
+        if damped && self.n > 0 { a *= ANOMALY_LEARN_RATIO; }
+        let keep = 1.0 - a;
+        self.sw = self.sw * keep + a;
+        self.sx = self.sx * keep + a * x;
+        self.sy = self.sy * keep + a * y;
+        self.sxx = self.sxx * keep + a * x * x;
+        self.sxy = self.sxy * keep + a * x * y;
+        self.n += 1;
+    }
+}
+
+#[derive(Clone, Debug)]
+enum Model {
+    Ewma(Ewma),
+    Seasonal { hours: Box<[Ewma; 24]>, global: Ewma },
+    Regression(Regression),
+}
+
+/// 单帧评估结果
+#[derive(Clone, Copy, Debug)]
+struct Estimate {
+    expected: f64,
+    std_dev: f64,
+    ready: bool,
+}
+
+struct Detector {
+    cfg: AnomalyMetricConfig,
+    model: Model,
+    // 新建后尚未预热
+    fresh: bool,
+    // 上一个学习样本的时间（seasonal 桶按样本间隔折算权重）
+    last_learn_ms: Option<i64>,
+    last_value: Option<f64>,
+    last_estimate: Option<Estimate>,
+    last_z: Option<f64>,
+    last_score: Option<f64>,
+}
+
+fn local_hour(ts_ms: i64) -> usize {
+    use chrono::{TimeZone, Timelike};
+    chrono::Local
+        .timestamp_millis_opt(ts_ms)
+        .single()
+        .map(|d| d.hour() as usize)
+        .unwrap_or(0)
+        % 24
+}
+
+impl Detector {
+    fn new(cfg: AnomalyMetricConfig) -> Self {
+        let model = match cfg.model.unwrap_or_default() {
+            AnomalyModel::Ewma => Model::Ewma(Ewma::default()),
+            AnomalyModel::Seasonal => Model::Seasonal { hours: Box::default(), global: Ewma::default() },
+            AnomalyModel::Regression => Model::Regression(Regression::default()),
+        };
+        Self { cfg, model, fresh: true, last_learn_ms: None, last_value: None, last_estimate: None, last_z: None, last_score: None }
+    }
+
+    fn min_samples(&self) -> u64 { self.cfg.min_samples.unwrap_or(DEFAULT_MIN_SAMPLES).max(2) }
+
+    fn samples(&self) -> u64 {
+        match &self.model {
+            Model::Ewma(e) => e.n,
+            Model::Seasonal { global, .. } => global.n,
+            Model::Regression(r) => r.n,
+        }
+    }
+
+    /// 当前输入下的期望值与标准差（不更新模型）
+    fn estimate(&self, ts_ms: i64, regressor: Option<f64>) -> Option<Estimate> {
+        let min = self.min_samples();
+        match &self.model {
+            Model::Ewma(e) => (e.n > 0).then(|| Estimate { expected: e.mean, std_dev: e.std_dev(), ready: e.n >= min }),
+            Model::Seasonal { hours, global } => {
+                let h = &hours[local_hour(ts_ms)];
+                if h.n >= min {
+                    Some(Estimate { expected: h.mean, std_dev: h.std_dev(), ready: true })
+                } else {
+                    (global.n > 0).then(|| Estimate { expected: global.mean, std_dev: global.std_dev(), ready: global.n >= min })
+                }
+            }
+            Model::Regression(r) => {
+                let p = r.predict(regressor?)?;
+                Some(Estimate { expected: p + r.resid.mean, std_dev: r.resid.std_dev(), ready: r.resid.n >= min })
+            }
+        }
+    }
+
+    fn learn(&mut self, ts_ms: i64, value: f64, regressor: Option<f64>, damped: bool) {
+        let alpha = self.cfg.alpha.unwrap_or(DEFAULT_ALPHA);
+        // 样本间隔（首个样本按 1 秒计；间隔上限 1 小时，避免停机后单个样本占据整个桶）
+        let dt_ms = self.last_learn_ms.map(|t| (ts_ms - t).clamp(1, HOUR_MS)).unwrap_or(1000);
+        self.last_learn_ms = Some(ts_ms);
+        match &mut self.model {
+            Model::Ewma(e) => e.update(value, alpha, damped),
+            Model::Seasonal { hours, global } => {
+                let hour_alpha = dt_ms as f64 / (SEASONAL_MEMORY_DAYS * HOUR_MS as f64);
+                hours[local_hour(ts_ms)].update(value, hour_alpha, damped);
+                global.update(value, alpha, damped);
+            }
+            Model::Regression(r) => {
+                if let Some(x) = regressor { r.update(x, value, alpha, damped); }
+            }
+        }
+    }
+
+    /// 评估并学习一帧；返回按方向折算后的分数（未就绪时为 None）
+    fn observe(&mut self, ts_ms: i64, get: &dyn Fn(&str) -> Option<f64>) -> Option<f64> {
+        let value = get(&self.cfg.metric).filter(|v| v.is_finite())?;
+        let regressor = self.cfg.regressor.as_deref().and_then(get).filter(|v| v.is_finite());
+        let est = self.estimate(ts_ms, regressor);
+        let sens = self.cfg.sensitivity.unwrap_or(DEFAULT_SENSITIVITY);
+        let z = est.map(|e| {
+            let floor = self.cfg.min_std.unwrap_or(e.expected.abs() * 0.01).max(1e-6);
+            (value - e.expected) / e.std_dev.max(floor)
+        });
+        let score = match (est, z) {
+            (Some(e), Some(z)) if e.ready => Some(match self.cfg.direction.unwrap_or_default() {
+                AnomalyDirection::Above => z,
+                AnomalyDirection::Below => -z,
+                AnomalyDirection::Both => z.abs(),
+            }),
+            _ => None,
+        };
+        self.learn(ts_ms, value, regressor, score.map(|s| s > sens).unwrap_or(false));
+        self.last_value = Some(value);
+        self.last_estimate = est;
+        self.last_z = z;
+        self.last_score = score;
+        score
+    }
+
+    fn status(&self) -> AnomalyMetricStatus {
+        AnomalyMetricStatus {
+            metric: self.cfg.metric.clone(),
+            model: self.cfg.model.unwrap_or_default(),
+            regressor: self.cfg.regressor.clone(),
+            samples: self.samples(),
+            ready: self.last_estimate.map(|e| e.ready).unwrap_or(false),
+            value: self.last_value,
+            expected: self.last_estimate.map(|e| e.expected),
+            std_dev: self.last_estimate.map(|e| e.std_dev),
+            z: self.last_z,
+            score: self.last_score,
+        }
+    }
+}
+
+/// 异常检测器（由采样线程持有）
+#[derive(Default)]
+pub struct AnomalyDetector {
+    detectors: Vec<Detector>,
+    errors: Vec<String>,
+}
+
+impl AnomalyDetector {
+    pub fn new() -> Self { Self::default() }
+
+    /// 同步检测项：配置变化的指标重建基线，未变化的保留；返回是否有待预热的新检测项
+    pub fn sync(&mut self, cfg: Option<&AnomalyConfig>) -> bool {
+        let mut old: HashMap<String, Detector> = self.detectors.drain(..).map(|d| (d.cfg.metric.clone(), d)).collect();
+        let mut seen: HashSet<String> = HashSet::new();
+        self.errors.clear();
+        let Some(cfg) = cfg else { return false };
+        for m in enabled_metrics(cfg) {
+            if let Err(e) = validate_metric(m) {
+                self.errors.push(e);
+                continue;
+            }
+            if !seen.insert(m.metric.clone()) {
+                self.errors.push(format!("异常检测指标重复: {}", m.metric));
+                continue;
+            }
+            let d = match old.remove(&m.metric) {
+                Some(d) if d.cfg == *m => d,
+                _ => Detector::new(m.clone()),
+            };
+            self.detectors.push(d);
+        }
+        self.detectors.iter().any(|d| d.fresh)
+    }
+
+    /// 用历史数据（按时间升序）预热新建的检测项
+    pub fn warm_up(&mut self, history: &[HistoryPoint]) {
+        for d in self.detectors.iter_mut().filter(|d| d.fresh) {
+            d.fresh = false;
+            for p in history {
+                d.observe(p.timestamp_ms, &|k: &str| p.values.get(k).copied());
+            }
+        }
+    }
+
+    /// 评估一帧：把就绪检测项的分数以 anomaly_z.<metric> 写入 values
+    pub fn observe(&mut self, now_ms: i64, values: &mut HashMap<String, f64>) {
+        let mut scores: Vec<(String, f64)> = Vec::new();
+        for d in self.detectors.iter_mut() {
+            d.fresh = false;
+            if let Some(s) = d.observe(now_ms, &|k: &str| values.get(k).copied()) {
+                scores.push((score_key(&d.cfg.metric), s));
+            }
+        }
+        values.extend(scores);
+    }
+
+    pub fn status(&self, enabled: bool, now_ms: i64) -> AnomalyStatus {
+        AnomalyStatus {
+            enabled,
+            metrics: self.detectors.iter().map(|d| d.status()).collect(),
+            last_eval_ms: Some(now_ms),
+            errors: self.errors.clone(),
+        }
+    }
+
+    /// 每 tick 入口：同步配置、必要时预热（history 仅在有新检测项时调用）、评估并刷新全局状态
+    pub fn tick<F: FnOnce() -> Vec<HistoryPoint>>(
+        &mut self,
+        cfg: Option<&crate::alert_engine::AlertConfig>,
+        now_ms: i64,
+        values: &mut HashMap<String, f64>,
+        history: F,
+    ) {
+        let enabled = cfg.map(|c| c.enabled.unwrap_or(true)).unwrap_or(false);
+        let anomaly = if enabled { cfg.and_then(|c| c.anomaly.as_ref()) } else { None };
+        if self.sync(anomaly) {
+            let hist: Vec<HistoryPoint> = history().into_iter().filter(|p| p.timestamp_ms < now_ms).collect();
+            self.warm_up(&hist);
+        }
+        self.observe(now_ms, values);
+        if let Ok(mut g) = STATUS.get_or_init(|| Mutex::new(AnomalyStatus::default())).lock() {
+            *g = self.status(enabled && anomaly.map(|a| a.enabled.unwrap_or(true)).unwrap_or(false), now_ms);
+        }
+    }
+}
@@ -0,0 +1,258 @@
+// 本地 API 鉴权与访问控制
+// 说明：
+// - Bearer Token：明文仅在创建时返回一次，配置目录 api_tokens.json 中只保存 SHA-256 摘要
+// - 作用域：metrics:read（读取指标/历史/订阅）、config:read、config:write、tasks:trigger、speedtest（测速端点）
+// - mTLS：客户端证书经 CA 校验通过后按配置授予作用域（见 api_server 的 ApiTlsConfig）
+// - 限流：按身份（token id / 证书 / 来源 IP）的令牌桶
+// - 管理：桌面版经 Tauri 命令，无界面部署（sys-sensor-agent）经 sys-sensor-cli token create/list/revoke --config-dir
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+use std::time::Instant;
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+const TOKENS_FILE: &str = "api_tokens.json";
+const TOKEN_PREFIX: &str = "sst_";
+
+/// 访问作用域
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Scope {
+    #[serde(rename = "metrics:read")]
+    ReadMetrics,
+    #[serde(rename = "config:read")]
+    ReadConfig,
+    #[serde(rename = "config:write")]
+    WriteConfig,
+    #[serde(rename = "tasks:trigger")]
+    TriggerTasks,
+    #[serde(rename = "speedtest")]
+    Speedtest,
+}
+
+impl Scope {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Scope::ReadMetrics => "metrics:read",
+            Scope::ReadConfig => "config:read",
+            Scope::WriteConfig => "config:write",
+            Scope::TriggerTasks => "tasks:trigger",
+            Scope::Speedtest => "speedtest",
+        }
+    }
+}
+
+/// 持久化的 Token 记录（仅保存摘要）
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ApiToken {
+    pub id: String,
+    pub name: String,
+    pub sha256: String,
+    pub scopes: Vec<Scope>,
+    pub created_ms: i64,
+    // 过期时间（可空表示永不过期）
+    pub expires_ms: Option<i64>,
+}
+
+/// 对外展示的 Token 信息（不含摘要）
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ApiTokenInfo {
+    pub id: String,
+    pub name: String,
+    pub scopes: Vec<Scope>,
+    pub created_ms: i64,
+    pub expires_ms: Option<i64>,
+}
+
+/// 创建结果：token 明文只在此返回一次
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CreatedToken {
+    pub id: String,
+    pub token: String,
+    pub scopes: Vec<Scope>,
+}
+
+/// 鉴权通过的调用方
+#[derive(Clone, Debug)]
+pub struct Principal {
+    // 限流键："token:<id>" | "cert:<摘要前缀>" | "ip:<addr>"
+    pub key: String,
+    pub scopes: Vec<Scope>,
+}
+
+impl Principal {
+    pub fn has(&self, scope: Scope) -> bool {
+        self.scopes.contains(&scope)
+    }
+}
+
+// Token 缓存：按配置目录缓存文件内容，变更时同步落盘；
+// 文件被其他进程改写（如 sys-sensor-cli token create/revoke）后按修改时间与大小重新加载
+type FileStamp = Option<(std::time::SystemTime, u64)>;
+type TokenCache = HashMap<PathBuf, (FileStamp, Vec<ApiToken>)>;
+static TOKENS: OnceLock<Mutex<TokenCache>> = OnceLock::new();
+
+fn tokens_path(dir: &Path) -> PathBuf {
+    dir.join(TOKENS_FILE)
+}
+
+fn load_tokens(dir: &Path) -> Vec<ApiToken> {
+    std::fs::read_to_string(tokens_path(dir))
+        .ok()
+        .and_then(|s| serde_json::from_str::<Vec<ApiToken>>(&s).ok())
+        .unwrap_or_default()
+}
+
+fn save_tokens(dir: &Path, tokens: &[ApiToken]) -> Result<(), String> {
+    std::fs::create_dir_all(dir).map_err(|e| format!("创建配置目录失败: {}", e))?;
+    let content = serde_json::to_string_pretty(tokens).map_err(|e| format!("序列化 Token 失败: {}", e))?;
+    std::fs::write(tokens_path(dir), content).map_err(|e| format!("写入 Token 文件失败: {}", e))
+}
+
+fn file_stamp(dir: &Path) -> FileStamp {
+    let meta = std::fs::metadata(tokens_path(dir)).ok()?;
+    Some((meta.modified().ok()?, meta.len()))
+}
+
+fn with_tokens<R>(dir: &Path, f: impl FnOnce(&mut Vec<ApiToken>) -> R) -> Result<R, String> {
+    let cell = TOKENS.get_or_init(|| Mutex::new(HashMap::new()));
+    let mut map = cell.lock().map_err(|_| "获取 Token 缓存锁失败".to_string())?;
+    let stamp = file_stamp(dir);
+    let entry = map.entry(dir.to_path_buf()).or_insert_with(|| (stamp, load_tokens(dir)));
+    if entry.0 != stamp {
+        *entry = (stamp, load_tokens(dir));
+    }
+    Ok(f(&mut entry.1))
+}
+
+pub fn sha256_hex(data: &[u8]) -> String {
+    Sha256::digest(data).iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// 定长比较，避免按字节短路泄露时序
+fn ct_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+fn random_hex(n: usize) -> Result<String, String> {
+    let mut buf = vec![0u8; n];
+    getrandom::getrandom(&mut buf).map_err(|e| format!("生成随机数失败: {}", e))?;
+    Ok(buf.iter().map(|b| format!("{:02x}", b)).collect())
+}
+
+/// 创建 Token（scopes 为空时默认仅 metrics:read）
+pub fn create_token(dir: &Path, name: &str, scopes: Vec<Scope>, ttl_days: Option<u64>) -> Result<CreatedToken, String> {
+    let scopes = if scopes.is_empty() { vec![Scope::ReadMetrics] } else { scopes };
+    let id = random_hex(6)?;
+    let token = format!("{}{}", TOKEN_PREFIX, random_hex(32)?);
+    let now = chrono::Local::now().timestamp_millis();
+    let rec = ApiToken {
+        id: id.clone(),
+        name: name.trim().to_string(),
+        sha256: sha256_hex(token.as_bytes()),
+        scopes: scopes.clone(),
+        created_ms: now,
+        expires_ms: ttl_days.map(|d| now + (d as i64) * 86_400_000),
+    };
+    with_tokens(dir, |list| {
+        let mut next = list.clone();
+        next.push(rec);
+        save_tokens(dir, &next)?;
+        *list = next;
+        Ok::<(), String>(())
+    })??;
+    Ok(CreatedToken { id, token, scopes })
+}
+
+pub fn list_tokens(dir: &Path) -> Result<Vec<ApiTokenInfo>, String> {
+    with_tokens(dir, |list| {
+        list.iter()
+            .map(|t| ApiTokenInfo {
+                id: t.id.clone(),
+                name: t.name.clone(),
+                scopes: t.scopes.clone(),
+                created_ms: t.created_ms,
+                expires_ms: t.expires_ms,
+            })
+            .collect()
+    })
+}
+
+/// 吊销 Token：返回是否存在该 id
+pub fn revoke_token(dir: &Path, id: &str) -> Result<bool, String> {
+    with_tokens(dir, |list| {
+        let next: Vec<ApiToken> = list.iter().filter(|t| t.id != id).cloned().collect();
+        if next.len() == list.len() { return Ok(false); }
+        save_tokens(dir, &next)?;
+        *list = next;
+        Ok(true)
+    })?
+}
+
+/// 校验 Bearer Token
+pub fn authenticate_token(dir: &Path, token: &str) -> Option<Principal> {
+    let digest = sha256_hex(token.trim().as_bytes());
+    let now = chrono::Local::now().timestamp_millis();
+    with_tokens(dir, |list| {
+        list.iter()
+            .find(|t| ct_eq(t.sha256.as_bytes(), digest.as_bytes()) && t.expires_ms.map(|e| e > now).unwrap_or(true))
+            .map(|t| Principal { key: format!("token:{}", t.id), scopes: t.scopes.clone() })
+    })
+    .ok()
+    .flatten()
+}
+
+// ---- 限流：令牌桶 ----
+
+#[derive(Default)]
+pub struct RateLimiter {
+    buckets: HashMap<String, (f64, Instant)>,
+}
+
+impl RateLimiter {
+    /// 消耗一个令牌；被限流时返回建议的重试秒数。per_min 为 0 表示不限流
+    pub fn check(&mut self, key: &str, per_min: u32, burst: u32) -> Result<(), u64> {
+        if per_min == 0 { return Ok(()); }
+        let rate = per_min as f64 / 60.0;
+        let cap = burst.max(1) as f64;
+        let now = Instant::now();
+        // 顺带清理长时间空闲的桶，避免来源 IP 过多时无限增长
+        if self.buckets.len() > 1024 {
+            self.buckets.retain(|_, (_, t)| now.duration_since(*t).as_secs() < 600);
+        }
+        let (tokens, last) = self.buckets.entry(key.to_string()).or_insert((cap, now));
+        *tokens = (*tokens + now.duration_since(*last).as_secs_f64() * rate).min(cap);
+        *last = now;
+        if *tokens >= 1.0 {
+            *tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(((1.0 - *tokens) / rate).ceil().max(1.0) as u64)
+        }
+    }
+}
+
+// ---- Tauri 命令 ----
+
+/// Tauri命令：创建 API Token（明文只返回一次）
+#[cfg(feature = "desktop")]
+#[tauri::command]
+pub fn api_token_create(name: String, scopes: Vec<Scope>, ttl_days: Option<u64>, app_handle: tauri::AppHandle) -> Result<CreatedToken, String> {
+    create_token(&crate::config_utils::get_config_dir(&app_handle), &name, scopes, ttl_days)
+}
+
+/// Tauri命令：列出 API Token（不含摘要）
+#[cfg(feature = "desktop")]
+#[tauri::command]
+pub fn api_token_list(app_handle: tauri::AppHandle) -> Result<Vec<ApiTokenInfo>, String> {
+    list_tokens(&crate::config_utils::get_config_dir(&app_handle))
+}
+
+/// Tauri命令：吊销 API Token
+#[cfg(feature = "desktop")]
+#[tauri::command]
+pub fn api_token_revoke(id: String, app_handle: tauri::AppHandle) -> Result<bool, String> {
+    revoke_token(&crate::config_utils::get_config_dir(&app_handle), &id)
+}
@@ -0,0 +1,1113 @@
+// 本地 HTTP + WebSocket API（供 Stream Deck 组件、OBS 叠加层等第三方面板读取）
+// 说明：
+// - 默认关闭，仅监听 127.0.0.1；配置 api.enabled/bind/port 热更新（约 1s 内生效）
+// - 鉴权（见 api_auth）：Authorization: Bearer <token>（WebSocket 亦可用 ?access_token=），或 mTLS 客户端证书
+//   监听非回环地址时强制鉴权；回环地址默认允许匿名读取指标（仅 metrics:read，读取配置需 Token）
+// - CORS 默认不返回 Access-Control-Allow-Origin，仅对 api.cors_origin 中列出的来源放行
+// - 按身份令牌桶限流，超限返回 429 + Retry-After
+// - HTTP（JSON）：
+//   GET /api                 路由与事件流列表
+//   GET /api/snapshot        最近一次 sensor://snapshot
+//   GET /api/agg             StateStore 聚合（sensor://agg）
+//   GET /api/tick            调度节拍遥测
+//   GET /api/smart           最近一次 SMART 快照
+//   GET /api/history         历史查询：metrics=a,b&since=ms&until=ms&limit=N
+//   GET /api/outages         网络中断时间线：since=ms&until=ms&limit=N
+//   GET /api/public_ip/history  公网 IP 变化历史：limit=N
+//   GET /api/speedtest/history  测速历史：limit=N
+//   GET /api/speedtest/download 测速端点：返回 bytes=N 字节（默认 1GiB）的填充数据
+//   POST /api/speedtest/upload  测速端点：读取并丢弃请求体（不受请求体大小限制）
+//     两个测速端点默认关闭（api.speedtest_endpoint），需 speedtest 作用域，并发连接数受 api.speedtest_max_connections 限制
+//   GET /api/metrics         指标目录（键/名称/单位）
+//   GET /api/scheduler       调度器状态
+//   GET /api/config          当前配置（凭据、HTTP 头、URL userinfo/查询串、脚本命令已脱敏）
+//   PATCH /api/config        增量更新配置（config:write，语义同 cmd_cfg_update；值为脱敏占位时保留原值）
+//   POST /api/tasks/<kind>/trigger  立即触发任务（tasks:trigger，kind 同 trigger_task）
+// - WebSocket：GET /ws?streams=snapshot,agg
+//   客户端文本消息：{"op":"subscribe"|"unsubscribe","streams":["snapshot","agg","smart","config","alert","public_ip","speedtest"]}
+//   服务端推送：{"type":"event","stream":"snapshot","ts_ms":..,"data":{..}}
+// - 每个连接一个线程；WebSocket 订阅使用事件总线有界队列，客户端过慢时丢帧
+
+use std::collections::{HashMap, HashSet};
+use std::io::{Read, Write};
+use std::net::{IpAddr, SocketAddr, TcpListener, TcpStream};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+use crate::api_auth::{Principal, RateLimiter, Scope};
+use crate::config_utils::AppConfig;
+use crate::scheduler::SchedulerState;
+use crate::state_store::StateStore;
+
+const DEFAULT_PORT: u16 = 18730;
+const MAX_HEAD_BYTES: usize = 16 * 1024;
+const MAX_BODY_BYTES: usize = 64 * 1024;
+const MAX_WS_FRAME_BYTES: usize = 64 * 1024;
+const SPEEDTEST_DOWNLOAD_PATH: &str = "/api/speedtest/download";
+const SPEEDTEST_UPLOAD_PATH: &str = "/api/speedtest/upload";
+const DEFAULT_SPEEDTEST_CONNECTIONS: usize = 8;
+const WS_GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+
+/// 本地 API 配置（AppConfig.api）
+#[derive(Clone, Debug, Serialize, Deserialize, Default, PartialEq)]
+#[serde(default)]
+pub struct ApiConfig {
+    // 总开关（默认关闭）
+    pub enabled: bool,
+    // 监听地址（默认 127.0.0.1；如需局域网访问可设为 0.0.0.0）
+    pub bind: Option<String>,
+    // 监听端口（默认 18730）
+    pub port: Option<u16>,
+    // CORS 允许的来源，逗号分隔（如 "http://localhost:5173,https://obs.local"；"*" 表示任意来源）；默认不返回 CORS 头
+    pub cors_origin: Option<String>,
+    // 最大并发连接数（默认 16）
+    pub max_clients: Option<usize>,
+    // 是否强制鉴权（默认：回环地址 false，其余 true；非回环地址不允许关闭）
+    pub require_auth: Option<bool>,
+    // 限流：每分钟请求数（默认 120，0 表示不限）与突发上限（默认 20）
+    pub rate_limit_per_min: Option<u32>,
+    pub rate_limit_burst: Option<u32>,
+    // HTTPS / mTLS（缺省为明文 HTTP）
+    pub tls: Option<ApiTlsConfig>,
+    // 是否开放测速端点 /api/speedtest/download|upload（默认关闭）
+    pub speedtest_endpoint: Option<bool>,
+    // 测速端点同时服务的连接数上限（默认 8，超出返回 503）
+    pub speedtest_max_connections: Option<usize>,
+}
+
+/// HTTPS / mTLS 配置（证书与私钥均为 PEM 文件）
+#[derive(Clone, Debug, Serialize, Deserialize, Default, PartialEq)]
+#[serde(default)]
+pub struct ApiTlsConfig {
+    pub cert_file: String,
+    pub key_file: String,
+    // 客户端证书 CA：设置后启用 mTLS
+    pub client_ca_file: Option<String>,
+    // 是否必须出示客户端证书（默认 true；false 时证书与 Token 二选一）
+    pub require_client_cert: Option<bool>,
+    // 证书通过校验后授予的作用域（默认 ["metrics:read"]）
+    pub client_cert_scopes: Option<Vec<Scope>>,
+}
+
+/// 任务触发回调（kind 同 trigger_task 命令）
+pub type TaskTrigger = Arc<dyn Fn(&str) -> Result<(), String> + Send + Sync>;
+/// 配置变更回调（用于向 webview 广播 config://changed）
+pub type ConfigListener = Arc<dyn Fn(&AppConfig) + Send + Sync>;
+
+/// API 访问的共享状态（与 AppState 中的 Arc 指向同一份数据）
+#[derive(Clone)]
+pub struct ApiContext {
+    pub config: Arc<Mutex<AppConfig>>,
+    pub scheduler: Arc<Mutex<SchedulerState>>,
+    pub state_store: Arc<Mutex<StateStore>>,
+    // 配置目录：config.json 与 api_tokens.json 所在位置
+    pub config_dir: PathBuf,
+    pub trigger_task: Option<TaskTrigger>,
+    pub on_config_changed: Option<ConfigListener>,
+}
+
+/// 运行状态（供前端/调试读取）
+#[derive(Clone, Debug, Serialize, Deserialize, Default)]
+pub struct ApiStatus {
+    pub enabled: bool,
+    pub listening: Option<String>,
+    pub clients: usize,
+    pub ws_clients: usize,
+    pub requests: u64,
+    pub rejected: u64,
+    pub last_error: Option<String>,
+}
+
+static STATUS: OnceLock<Mutex<ApiStatus>> = OnceLock::new();
+
+fn with_status<F: FnOnce(&mut ApiStatus)>(f: F) {
+    let cell = STATUS.get_or_init(|| Mutex::new(ApiStatus::default()));
+    if let Ok(mut g) = cell.lock() { f(&mut g); }
+}
+
+/// Tauri命令：获取本地 API 运行状态
+#[cfg_attr(feature = "desktop", tauri::command)]
+pub fn api_get_status() -> ApiStatus {
+    STATUS
+        .get_or_init(|| Mutex::new(ApiStatus::default()))
+        .lock()
+        .map(|g| g.clone())
+        .unwrap_or_default()
+}
+
+/// 运行中的监听器句柄：置位 stop 后接收线程与所有连接线程退出
+pub struct ServerHandle {
+    pub addr: SocketAddr,
+    stop: Arc<AtomicBool>,
+}
+
+impl ServerHandle {
+    pub fn stop(&self) {
+        self.stop.store(true, Ordering::SeqCst);
+    }
+}
+
+/// 启动 API 监督线程：按配置绑定/重绑/关闭监听
+pub fn start_api_server(ctx: ApiContext) {
+    std::thread::Builder::new()
+        .name("api-server".into())
+        .spawn(move || {
+            let mut current: Option<(ApiConfig, ServerHandle)> = None;
+            let mut last_failed: Option<ApiConfig> = None;
+            loop {
+                let cfg: ApiConfig = ctx.config.lock().ok().and_then(|c| c.api.clone()).unwrap_or_default();
+                with_status(|s| s.enabled = cfg.enabled);
+
+                let changed = current.as_ref().map(|(c, _)| *c != cfg).unwrap_or(true);
+                if changed {
+                    if let Some((_, h)) = current.take() {
+                        eprintln!("[api] 停止监听 {}", h.addr);
+                        h.stop();
+                        with_status(|s| s.listening = None);
+                    }
+                    // 同一份失败配置不反复重试，等待配置变更
+                    if cfg.enabled && last_failed.as_ref() != Some(&cfg) {
+                        let bind = cfg.bind.as_deref().unwrap_or("127.0.0.1");
+                        let addr = format!("{}:{}", bind, cfg.port.unwrap_or(DEFAULT_PORT));
+                        let prepared = if !is_loopback_bind(bind) && cfg.require_auth == Some(false) {
+                            Err(format!("拒绝在非回环地址 {} 上关闭鉴权", bind))
+                        } else {
+                            cfg.tls.as_ref().map(build_tls_config).transpose()
+                        };
+                        let scheme = if cfg.tls.is_some() { "https" } else { "http" };
+                        match prepared.and_then(|tls| TcpListener::bind(&addr).map(|l| (l, tls)).map_err(|e| format!("绑定 {} 失败: {}", addr, e))) {
+                            Ok((listener, tls)) => match serve_listener(listener, ctx.clone(), tls) {
+                                Ok(h) => {
+                                    eprintln!("[api] 监听 {}://{}", scheme, h.addr);
+                                    with_status(|s| {
+                                        s.listening = Some(h.addr.to_string());
+                                        s.last_error = None;
+                                    });
+                                    last_failed = None;
+                                    current = Some((cfg.clone(), h));
+                                }
+                                Err(e) => {
+                                    with_status(|s| s.last_error = Some(e));
+                                    last_failed = Some(cfg.clone());
+                                }
+                            },
+                            Err(e) => {
+                                eprintln!("[api] 启动失败: {}", e);
+                                with_status(|s| s.last_error = Some(e));
+                                last_failed = Some(cfg.clone());
+                            }
+                        }
+                    }
+                    if !cfg.enabled { last_failed = None; }
+                }
+                std::thread::sleep(Duration::from_millis(1000));
+            }
+        })
+        .expect("spawn api-server");
+}
+
+fn is_loopback_bind(bind: &str) -> bool {
+    bind.eq_ignore_ascii_case("localhost") || bind.parse::<IpAddr>().map(|ip| ip.is_loopback()).unwrap_or(false)
+}
+
+/// 由 PEM 文件构建 rustls 服务端配置（设置 client_ca_file 时启用客户端证书校验）
+pub fn build_tls_config(cfg: &ApiTlsConfig) -> Result<Arc<rustls::ServerConfig>, String> {
+    use rustls::pki_types::pem::PemObject;
+    use rustls::pki_types::{CertificateDer, PrivateKeyDer};
+    let certs = CertificateDer::pem_file_iter(&cfg.cert_file)
+        .map_err(|e| format!("读取证书失败: {}", e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("解析证书失败: {}", e))?;
+    let key = PrivateKeyDer::from_pem_file(&cfg.key_file).map_err(|e| format!("读取私钥失败: {}", e))?;
+    let provider = Arc::new(rustls::crypto::ring::default_provider());
+    let builder = rustls::ServerConfig::builder_with_provider(provider.clone())
+        .with_safe_default_protocol_versions()
+        .map_err(|e| e.to_string())?;
+    let builder = match cfg.client_ca_file.as_deref() {
+        Some(ca) => {
+            let mut roots = rustls::RootCertStore::empty();
+            for c in CertificateDer::pem_file_iter(ca).map_err(|e| format!("读取客户端 CA 失败: {}", e))? {
+                roots.add(c.map_err(|e| format!("解析客户端 CA 失败: {}", e))?).map_err(|e| format!("添加客户端 CA 失败: {}", e))?;
+            }
+            let verifier = rustls::server::WebPkiClientVerifier::builder_with_provider(Arc::new(roots), provider);
+            let verifier = if cfg.require_client_cert.unwrap_or(true) { verifier } else { verifier.allow_unauthenticated() };
+            builder.with_client_cert_verifier(verifier.build().map_err(|e| format!("构建客户端证书校验器失败: {}", e))?)
+        }
+        None => builder.with_no_client_auth(),
+    };
+    builder
+        .with_single_cert(certs, key)
+        .map(Arc::new)
+        .map_err(|e| format!("加载证书失败: {}", e))
+}
+
+/// 在已绑定的监听器上提供服务（测试亦直接使用）；tls 为 None 时为明文 HTTP
+pub fn serve_listener(listener: TcpListener, ctx: ApiContext, tls: Option<Arc<rustls::ServerConfig>>) -> Result<ServerHandle, String> {
+    let addr = listener.local_addr().map_err(|e| e.to_string())?;
+    listener.set_nonblocking(true).map_err(|e| e.to_string())?;
+    let stop = Arc::new(AtomicBool::new(false));
+    let stop_c = stop.clone();
+    std::thread::Builder::new()
+        .name("api-accept".into())
+        .spawn(move || {
+            let clients = Arc::new(AtomicUsize::new(0));
+            let limiter = Arc::new(Mutex::new(RateLimiter::default()));
+            while !stop_c.load(Ordering::SeqCst) {
+                match listener.accept() {
+                    Ok((mut stream, peer)) => {
+                        let max = ctx.config.lock().ok().and_then(|c| c.api.as_ref().and_then(|a| a.max_clients)).unwrap_or(16).max(1);
+                        if clients.load(Ordering::SeqCst) >= max {
+                            // TLS 连接无法直接回写明文，直接断开
+                            if tls.is_none() {
+                                let _ = stream.set_nonblocking(false);
+                                let _ = write_json(&mut stream, 503, &serde_json::json!({ "error": "too many clients" }), "", &[]);
+                            }
+                            continue;
+                        }
+                        clients.fetch_add(1, Ordering::SeqCst);
+                        with_status(|s| s.clients = clients.load(Ordering::SeqCst));
+                        let ctx = ctx.clone();
+                        let stop = stop_c.clone();
+                        let clients = clients.clone();
+                        let limiter = limiter.clone();
+                        let tls = tls.clone();
+                        let spawned = std::thread::Builder::new()
+                            .name("api-conn".into())
+                            .spawn(move || {
+                                let _ = stream.set_nonblocking(false);
+                                let conn = match tls {
+                                    Some(cfg) => match rustls::ServerConnection::new(cfg) {
+                                        Ok(sc) => Some(Conn::Tls(Box::new(rustls::StreamOwned::new(sc, stream)))),
+                                        Err(e) => { eprintln!("[api] 创建 TLS 会话失败: {}", e); None }
+                                    },
+                                    None => Some(Conn::Plain(stream)),
+                                };
+                                if let Some(conn) = conn { handle_connection(conn, peer, &ctx, &stop, &limiter); }
+                                clients.fetch_sub(1, Ordering::SeqCst);
+                                with_status(|s| s.clients = clients.load(Ordering::SeqCst));
+                            });
+                        if spawned.is_err() { eprintln!("[api] 创建连接线程失败"); }
+                    }
+                    Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => std::thread::sleep(Duration::from_millis(50)),
+                    Err(e) => {
+                        eprintln!("[api] accept 失败: {}", e);
+                        std::thread::sleep(Duration::from_millis(200));
+                    }
+                }
+            }
+        })
+        .map_err(|e| format!("创建 api-accept 线程失败: {}", e))?;
+    Ok(ServerHandle { addr, stop })
+}
+
+// ---- 连接 ----
+
+enum Conn {
+    Plain(TcpStream),
+    Tls(Box<rustls::StreamOwned<rustls::ServerConnection, TcpStream>>),
+}
+
+impl Conn {
+    fn tcp(&self) -> &TcpStream {
+        match self {
+            Conn::Plain(s) => s,
+            Conn::Tls(s) => &s.sock,
+        }
+    }
+
+    /// mTLS：握手完成后返回客户端证书摘要（已由 rustls 按 CA 校验）
+    fn client_cert_digest(&self) -> Option<String> {
+        match self {
+            Conn::Plain(_) => None,
+            Conn::Tls(s) => s.conn.peer_certificates()
+                .and_then(|certs| certs.first())
+                .map(|c| crate::api_auth::sha256_hex(c.as_ref())),
+        }
+    }
+
+    /// 结束连接：TLS 发送 close_notify
+    fn close(&mut self) {
+        if let Conn::Tls(s) = self {
+            s.conn.send_close_notify();
+            let _ = s.flush();
+        }
+    }
+
+    /// 写入并立即刷新（TLS 需 flush 才会真正发出记录）
+    fn send(&mut self, data: &[u8]) -> std::io::Result<()> {
+        self.write_all(data)?;
+        self.flush()
+    }
+}
+
+impl Read for Conn {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        match self {
+            Conn::Plain(s) => s.read(buf),
+            Conn::Tls(s) => s.read(buf),
+        }
+    }
+}
+
+impl Write for Conn {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        match self {
+            Conn::Plain(s) => s.write(buf),
+            Conn::Tls(s) => s.write(buf),
+        }
+    }
+    fn flush(&mut self) -> std::io::Result<()> {
+        match self {
+            Conn::Plain(s) => s.flush(),
+            Conn::Tls(s) => s.flush(),
+        }
+    }
+}
+
+// ---- HTTP ----
+
+struct Request {
+    method: String,
+    path: String,
+    query: HashMap<String, String>,
+    headers: HashMap<String, String>,
+    body: Vec<u8>,
+}
+
+fn percent_decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut out: Vec<u8> = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'+' => out.push(b' '),
+            b'%' if i + 2 < bytes.len() => {
+                match std::str::from_utf8(&bytes[i + 1..i + 3]).ok().and_then(|h| u8::from_str_radix(h, 16).ok()) {
+                    Some(b) => { out.push(b); i += 2; }
+                    None => out.push(b'%'),
+                }
+            }
+            b => out.push(b),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&out).to_string()
+}
+
+fn read_request(stream: &mut Conn) -> Result<Request, String> {
+    let mut buf: Vec<u8> = Vec::new();
+    let mut tmp = [0u8; 2048];
+    let head_end = loop {
+        if let Some(pos) = buf.windows(4).position(|w| w == b"\r\n\r\n") { break pos; }
+        if buf.len() > MAX_HEAD_BYTES { return Err("请求头过大".to_string()); }
+        match stream.read(&mut tmp) {
+            Ok(0) => return Err("连接已关闭".to_string()),
+            Ok(n) => buf.extend_from_slice(&tmp[..n]),
+            Err(e) => return Err(format!("读取请求失败: {}", e)),
+        }
+    };
+    let head = String::from_utf8_lossy(&buf[..head_end]).to_string();
+    let mut lines = head.split("\r\n");
+    let request_line = lines.next().unwrap_or("");
+    let mut parts = request_line.split_whitespace();
+    let method = parts.next().unwrap_or("").to_ascii_uppercase();
+    let target = parts.next().ok_or_else(|| "请求行无效".to_string())?;
+    let (path, qs) = target.split_once('?').unwrap_or((target, ""));
+    let query = qs
+        .split('&')
+        .filter(|p| !p.is_empty())
+        .map(|p| {
+            let (k, v) = p.split_once('=').unwrap_or((p, ""));
+            (percent_decode(k), percent_decode(v))
+        })
+        .collect();
+    let headers: HashMap<String, String> = lines
+        .filter_map(|l| l.split_once(':'))
+        .map(|(k, v)| (k.trim().to_ascii_lowercase(), v.trim().to_string()))
+        .collect();
+    let content_len = headers.get("content-length").and_then(|v| v.parse::<usize>().ok()).unwrap_or(0);
+    let mut body = buf[head_end + 4..].to_vec();
+    // 测速上传：请求体留在连接中由处理函数边读边丢弃
+    if method == "POST" && path.trim_end_matches('/') == SPEEDTEST_UPLOAD_PATH {
+        return Ok(Request { method, path: SPEEDTEST_UPLOAD_PATH.to_string(), query, headers, body });
+    }
+    if content_len > MAX_BODY_BYTES { return Err("请求体过大".to_string()); }
+    while body.len() < content_len {
+        match stream.read(&mut tmp) {
+            Ok(0) => break,
+            Ok(n) => body.extend_from_slice(&tmp[..n]),
+            Err(e) => return Err(format!("读取请求体失败: {}", e)),
+        }
+    }
+    body.truncate(content_len);
+    Ok(Request { method, path: path.trim_end_matches('/').to_string(), query, headers, body })
+}
+
+fn status_text(code: u16) -> &'static str {
+    match code {
+        200 => "OK",
+        204 => "No Content",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        403 => "Forbidden",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        429 => "Too Many Requests",
+        500 => "Internal Server Error",
+        503 => "Service Unavailable",
+        _ => "Unknown",
+    }
+}
+
+/// CORS 响应头：请求 Origin 在 api.cors_origin 列表中时回显该来源，否则为空（浏览器将拒绝跨域读取）
+fn cors_headers(api: &ApiConfig, req: &Request) -> String {
+    let Some(origin) = req.headers.get("origin") else { return String::new() };
+    let allowed = api.cors_origin.as_deref().unwrap_or("")
+        .split(',')
+        .map(|o| o.trim().trim_end_matches('/'))
+        .any(|o| o == "*" || (!o.is_empty() && o.eq_ignore_ascii_case(origin)));
+    if allowed { format!("Access-Control-Allow-Origin: {}\r\nVary: Origin\r\n", origin) } else { String::new() }
+}
+
+fn write_json<W: Write>(stream: &mut W, code: u16, body: &serde_json::Value, cors: &str, extra: &[(&str, String)]) -> std::io::Result<()> {
+    let body = body.to_string();
+    let extra: String = extra.iter().map(|(k, v)| format!("{}: {}\r\n", k, v)).collect();
+    let head = format!(
+        "HTTP/1.1 {} {}\r\nContent-Type: application/json; charset=utf-8\r\nContent-Length: {}\r\n{}Cache-Control: no-store\r\n{}Connection: close\r\n\r\n",
+        code, status_text(code), body.len(), cors, extra
+    );
+    stream.write_all(head.as_bytes())?;
+    stream.write_all(body.as_bytes())?;
+    stream.flush()
+}
+
+/// 路由所需作用域
+fn required_scope(method: &str, path: &str) -> Scope {
+    match (method, path) {
+        ("GET", "/api/config") => Scope::ReadConfig,
+        ("PATCH", "/api/config") => Scope::WriteConfig,
+        ("POST", p) if p.starts_with("/api/tasks/") => Scope::TriggerTasks,
+        (_, SPEEDTEST_DOWNLOAD_PATH) | (_, SPEEDTEST_UPLOAD_PATH) => Scope::Speedtest,
+        _ => Scope::ReadMetrics,
+    }
+}
+
+/// 鉴权：Bearer Token 优先，其次 mTLS 客户端证书，最后（允许时）匿名读取指标
+/// 返回 Err(原因) 表示携带了无效凭据
+fn authenticate(req: &Request, conn: &Conn, peer: &SocketAddr, ctx: &ApiContext, api: &ApiConfig) -> Result<Option<Principal>, &'static str> {
+    let bearer = req.headers
+        .get("authorization")
+        .and_then(|v| v.split_once(' ').filter(|(k, _)| k.eq_ignore_ascii_case("bearer")).map(|(_, t)| t.trim().to_string()))
+        .or_else(|| req.query.get("access_token").cloned());
+    if let Some(token) = bearer {
+        return crate::api_auth::authenticate_token(&ctx.config_dir, &token).map(Some).ok_or("invalid token");
+    }
+    if let Some(digest) = conn.client_cert_digest() {
+        let scopes = api.tls.as_ref().and_then(|t| t.client_cert_scopes.clone()).unwrap_or_else(|| vec![Scope::ReadMetrics]);
+        return Ok(Some(Principal { key: format!("cert:{}", &digest[..16]), scopes }));
+    }
+    let bind = api.bind.as_deref().unwrap_or("127.0.0.1");
+    let require = !is_loopback_bind(bind) || api.require_auth.unwrap_or(false);
+    if require { return Ok(None); }
+    // 匿名调用方可能是浏览器中的任意网页，不授予 config:read
+    Ok(Some(Principal { key: format!("ip:{}", peer.ip()), scopes: vec![Scope::ReadMetrics] }))
+}
+
+fn handle_connection(mut stream: Conn, peer: SocketAddr, ctx: &ApiContext, stop: &AtomicBool, limiter: &Mutex<RateLimiter>) {
+    serve_request(&mut stream, peer, ctx, stop, limiter);
+    stream.close();
+}
+
+fn serve_request(stream: &mut Conn, peer: SocketAddr, ctx: &ApiContext, stop: &AtomicBool, limiter: &Mutex<RateLimiter>) {
+    let _ = stream.tcp().set_read_timeout(Some(Duration::from_secs(5)));
+    let _ = stream.tcp().set_write_timeout(Some(Duration::from_secs(5)));
+    let req = match read_request(stream) {
+        Ok(r) => r,
+        Err(_) => return,
+    };
+    with_status(|s| s.requests = s.requests.saturating_add(1));
+    let api: ApiConfig = ctx.config.lock().ok().and_then(|c| c.api.clone()).unwrap_or_default();
+    let cors = cors_headers(&api, &req);
+
+    if req.method == "OPTIONS" {
+        let head = format!(
+            "HTTP/1.1 204 No Content\r\n{}Access-Control-Allow-Methods: GET, PATCH, POST, OPTIONS\r\nAccess-Control-Allow-Headers: Authorization, Content-Type\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+            cors
+        );
+        let _ = stream.send(head.as_bytes());
+        return;
+    }
+
+    // 鉴权 + 限流（未通过鉴权的请求按来源 IP 计入限流，减缓暴力尝试）
+    let auth = authenticate(&req, stream, &peer, ctx, &api);
+    let limit_key = match &auth {
+        Ok(Some(p)) => p.key.clone(),
+        _ => format!("ip:{}", peer.ip()),
+    };
+    let limited = limiter.lock().ok().and_then(|mut l| {
+        l.check(&limit_key, api.rate_limit_per_min.unwrap_or(120), api.rate_limit_burst.unwrap_or(20)).err()
+    });
+    let reject = |stream: &mut Conn, code: u16, msg: &str, extra: &[(&str, String)]| {
+        with_status(|s| s.rejected = s.rejected.saturating_add(1));
+        let _ = write_json(stream, code, &serde_json::json!({ "error": msg }), &cors, extra);
+    };
+    if let Some(retry) = limited {
+        reject(stream, 429, "rate limited", &[("Retry-After", retry.to_string())]);
+        return;
+    }
+    let principal = match auth {
+        Ok(Some(p)) => p,
+        Ok(None) => return reject(stream, 401, "authentication required", &[("WWW-Authenticate", "Bearer".to_string())]),
+        Err(e) => return reject(stream, 401, e, &[("WWW-Authenticate", "Bearer error=\"invalid_token\"".to_string())]),
+    };
+    let speedtest_path = req.path == SPEEDTEST_DOWNLOAD_PATH || req.path == SPEEDTEST_UPLOAD_PATH;
+    if speedtest_path && !api.speedtest_endpoint.unwrap_or(false) {
+        return reject(stream, 404, "speedtest endpoint disabled", &[]);
+    }
+    let scope = required_scope(&req.method, &req.path);
+    if !principal.has(scope) {
+        return reject(stream, 403, &format!("missing scope {}", scope.as_str()), &[]);
+    }
+
+    if req.path == "/ws" {
+        let is_upgrade = req.headers.get("upgrade").map(|v| v.eq_ignore_ascii_case("websocket")).unwrap_or(false);
+        if req.method == "GET" && is_upgrade {
+            handle_websocket(stream, &req, &cors, stop);
+        } else {
+            let _ = write_json(stream, 400, &serde_json::json!({ "error": "expected websocket upgrade" }), &cors, &[]);
+        }
+        return;
+    }
+
+    if speedtest_path {
+        let Some(_slot) = SpeedtestSlot::acquire(api.speedtest_max_connections.unwrap_or(DEFAULT_SPEEDTEST_CONNECTIONS)) else {
+            return reject(stream, 503, "too many speedtest connections", &[]);
+        };
+        match req.method.as_str() {
+            "GET" if req.path == SPEEDTEST_DOWNLOAD_PATH => speedtest_download(stream, &req, &cors),
+            "POST" if req.path == SPEEDTEST_UPLOAD_PATH => speedtest_upload(stream, &req, &cors),
+            _ => reject(stream, 405, "method not allowed", &[]),
+        }
+        return;
+    }
+
+    let (code, body) = match req.method.as_str() {
+        "GET" => route(&req, ctx),
+        "PATCH" if req.path == "/api/config" => patch_config(&req, ctx),
+        "POST" if req.path.starts_with("/api/tasks/") => trigger(&req, ctx),
+        _ => (405, serde_json::json!({ "error": "method not allowed" })),
+    };
+    let _ = write_json(stream, code, &body, &cors, &[]);
+}
+
+static SPEEDTEST_ACTIVE: AtomicUsize = AtomicUsize::new(0);
+
+/// 测速端点连接占位：超过上限时拒绝，连接结束时释放
+struct SpeedtestSlot;
+
+impl SpeedtestSlot {
+    fn acquire(max: usize) -> Option<Self> {
+        SPEEDTEST_ACTIVE
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| (n < max.max(1)).then_some(n + 1))
+            .ok()
+            .map(|_| SpeedtestSlot)
+    }
+}
+
+impl Drop for SpeedtestSlot {
+    fn drop(&mut self) {
+        SPEEDTEST_ACTIVE.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// 测速下载：按 bytes 参数返回填充数据（客户端提前断开即结束）
+fn speedtest_download(stream: &mut Conn, req: &Request, cors: &str) {
+    let total = req.query
+        .get("bytes")
+        .and_then(|v| v.parse::<u64>().ok())
+        .unwrap_or(crate::speedtest::ENDPOINT_DEFAULT_BYTES)
+        .min(crate::speedtest::ENDPOINT_MAX_BYTES);
+    let head = format!(
+        "HTTP/1.1 200 OK\r\nContent-Type: application/octet-stream\r\nContent-Length: {}\r\n{}Cache-Control: no-store\r\nConnection: close\r\n\r\n",
+        total, cors
+    );
+    if stream.send(head.as_bytes()).is_err() { return; }
+    let block = vec![0u8; crate::speedtest::BLOCK_BYTES];
+    let mut sent = 0u64;
+    while sent < total {
+        let n = block.len().min((total - sent) as usize);
+        if stream.send(&block[..n]).is_err() { return; }
+        sent += n as u64;
+    }
+}
+
+/// 测速上传：读取并丢弃请求体，返回实际收到的字节数与耗时（客户端到时断开时不再应答）
+fn speedtest_upload(stream: &mut Conn, req: &Request, cors: &str) {
+    let total = req.headers.get("content-length").and_then(|v| v.parse::<u64>().ok()).unwrap_or(0);
+    let start = Instant::now();
+    let mut received = req.body.len() as u64;
+    let mut buf = vec![0u8; crate::speedtest::BLOCK_BYTES];
+    while received < total {
+        match stream.read(&mut buf) {
+            Ok(0) | Err(_) => return,
+            Ok(n) => received += n as u64,
+        }
+    }
+    let body = serde_json::json!({ "bytes": received, "elapsed_ms": start.elapsed().as_millis() as u64 });
+    let _ = write_json(stream, 200, &body, cors, &[]);
+}
+
+fn patch_config(req: &Request, ctx: &ApiContext) -> (u16, serde_json::Value) {
+    let mut patch: serde_json::Value = match serde_json::from_slice(&req.body) {
+        Ok(v @ serde_json::Value::Object(_)) => v,
+        _ => return (400, serde_json::json!({ "error": "body must be a JSON object" })),
+    };
+    let cfg = match ctx.config.lock() {
+        Ok(mut guard) => {
+            let mut cfg = guard.clone();
+            // GET /api/config 返回的是脱敏配置，读改写时回填原值
+            restore_redacted(&mut patch, &serde_json::to_value(&cfg).unwrap_or_default());
+            crate::config_utils::apply_patch(&mut cfg, &patch);
+            if let Err(e) = crate::config_utils::save_config_to(&ctx.config_dir.join("config.json"), &cfg) {
+                return (500, serde_json::json!({ "error": e }));
+            }
+            *guard = cfg.clone();
+            cfg
+        }
+        Err(_) => return lock_err(),
+    };
+    crate::event_hub::publish("config", &cfg);
+    if let Some(cb) = ctx.on_config_changed.as_ref() { cb(&cfg); }
+    let mut v = serde_json::to_value(cfg).unwrap_or_default();
+    redact_secrets(&mut v);
+    (200, v)
+}
+
+fn trigger(req: &Request, ctx: &ApiContext) -> (u16, serde_json::Value) {
+    // 路径形如 /api/tasks/<kind>/trigger
+    let kind = match req.path.trim_start_matches("/api/tasks/").split_once('/') {
+        Some((k, "trigger")) if !k.is_empty() => k,
+        _ => return (404, serde_json::json!({ "error": "not found" })),
+    };
+    match ctx.trigger_task.as_ref() {
+        Some(f) => match f(kind) {
+            Ok(()) => (200, serde_json::json!({ "ok": true, "task": kind })),
+            Err(e) => (400, serde_json::json!({ "error": e })),
+        },
+        None => (503, serde_json::json!({ "error": "task control unavailable" })),
+    }
+}
+
+fn lock_err() -> (u16, serde_json::Value) {
+    (500, serde_json::json!({ "error": "state lock poisoned" }))
+}
+
+fn route(req: &Request, ctx: &ApiContext) -> (u16, serde_json::Value) {
+    match req.path.as_str() {
+        "" | "/api" => (200, serde_json::json!({
+            "routes": ["/api/snapshot", "/api/agg", "/api/tick", "/api/smart", "/api/history", "/api/outages", "/api/public_ip/history", "/api/speedtest/history", "/api/speedtest/download", "/api/speedtest/upload", "/api/metrics", "/api/scheduler", "/api/config", "/api/tasks/<kind>/trigger", "/ws"],
+            "streams": crate::event_hub::STREAMS,
+        })),
+        "/api/snapshot" => match crate::event_hub::last("snapshot") {
+            Some(ev) => (200, (*ev.payload).clone()),
+            None => (503, serde_json::json!({ "error": "no snapshot yet" })),
+        },
+        "/api/agg" => match ctx.state_store.lock() {
+            Ok(ss) => (200, serde_json::to_value(ss.get_agg()).unwrap_or_default()),
+            Err(_) => lock_err(),
+        },
+        "/api/tick" => match ctx.state_store.lock() {
+            Ok(ss) => (200, serde_json::to_value(ss.get_tick()).unwrap_or_default()),
+            Err(_) => lock_err(),
+        },
+        "/api/smart" => (200, crate::smart_worker::get_last_snapshot()),
+        "/api/history" => {
+            let metrics: Option<Vec<String>> = req.query.get("metrics").map(|m| {
+                m.split(',').map(|s| s.trim().to_string()).filter(|s| !s.is_empty()).collect()
+            });
+            let parse_i64 = |k: &str| req.query.get(k).and_then(|v| v.parse::<i64>().ok());
+            let limit = req.query.get("limit").and_then(|v| v.parse::<usize>().ok()).unwrap_or(600);
+            match ctx.state_store.lock() {
+                Ok(ss) => {
+                    let points = ss.query_history(metrics.as_deref(), parse_i64("since"), parse_i64("until"), limit);
+                    (200, serde_json::json!({ "count": points.len(), "points": points }))
+                }
+                Err(_) => lock_err(),
+            }
+        }
+        "/api/outages" => {
+            let parse_i64 = |k: &str| req.query.get(k).and_then(|v| v.parse::<i64>().ok());
+            let limit = req.query.get("limit").and_then(|v| v.parse::<usize>().ok()).unwrap_or(200);
+            let outages = crate::connectivity::query(parse_i64("since"), parse_i64("until"), limit);
+            (200, serde_json::json!({ "status": crate::connectivity::status(), "count": outages.len(), "outages": outages }))
+        }
+        "/api/public_ip/history" => {
+            let limit = req.query.get("limit").and_then(|v| v.parse::<usize>().ok()).unwrap_or(100);
+            let changes = crate::public_net_utils::history(limit);
+            (200, serde_json::json!({ "count": changes.len(), "changes": changes }))
+        }
+        "/api/speedtest/history" => {
+            let limit = req.query.get("limit").and_then(|v| v.parse::<usize>().ok()).unwrap_or(50);
+            let results = crate::speedtest::history(limit);
+            (200, serde_json::json!({ "count": results.len(), "results": results }))
+        }
+        "/api/metrics" => {
+            let snap = crate::event_hub::last("snapshot")
+                .and_then(|ev| serde_json::from_value::<crate::types::SensorSnapshot>((*ev.payload).clone()).ok())
+                .unwrap_or_default();
+            let list: Vec<serde_json::Value> = crate::metrics_utils::snapshot_scalar_fields(&snap)
+                .into_iter()
+                .map(|(k, _)| {
+                    let meta = crate::metrics_utils::metric_meta(k);
+                    serde_json::json!({
+                        "key": k,
+                        "name": meta.map(|m| m.name),
+                        "unit": meta.and_then(|m| m.unit),
+                    })
+                })
+                .collect();
+            (200, serde_json::json!({ "metrics": list }))
+        }
+        "/api/scheduler" => match ctx.scheduler.lock() {
+            Ok(st) => (200, serde_json::to_value(st.clone()).unwrap_or_default()),
+            Err(_) => lock_err(),
+        },
+        "/api/config" => match ctx.config.lock() {
+            Ok(cfg) => {
+                let mut v = serde_json::to_value(cfg.clone()).unwrap_or_default();
+                redact_secrets(&mut v);
+                (200, v)
+            }
+            Err(_) => lock_err(),
+        },
+        _ => (404, serde_json::json!({ "error": "not found" })),
+    }
+}
+
+/// 脱敏占位符
+pub const REDACTED: &str = "***";
+
+/// 脱敏方式
+#[derive(Clone, Copy)]
+enum Redact {
+    // 整个值替换为 "***"
+    Full,
+    // URL：仅隐藏 userinfo 与查询串
+    Url,
+    // URL：仅保留 scheme://host（Webhook 地址的路径本身即凭据）
+    UrlHost,
+    // 对象/数组：逐项替换为 "***"（如 HTTP 头的值）
+    Entries,
+}
+
+/// 需要脱敏的配置字段（路径段 "*" 匹配数组任意元素）
+const SECRET_FIELDS: [(&str, Redact); 17] = [
+    ("influx.token", Redact::Full),
+    ("influx.org", Redact::Full),
+    ("influx.url", Redact::Url),
+    ("mqtt.username", Redact::Full),
+    ("mqtt.password", Redact::Full),
+    ("alerts.sinks.*.url", Redact::UrlHost),
+    ("alerts.sinks.*.headers", Redact::Entries),
+    ("alerts.sinks.*.username", Redact::Full),
+    ("alerts.sinks.*.password", Redact::Full),
+    ("alerts.sinks.*.command", Redact::Full),
+    ("alerts.sinks.*.args", Redact::Entries),
+    ("speedtest.token", Redact::Full),
+    ("speedtest.download_url", Redact::Url),
+    ("speedtest.upload_url", Redact::Url),
+    ("public_net_api", Redact::Url),
+    ("public_net_providers.*.url", Redact::Url),
+    ("connectivity_check_url", Redact::Url),
+];
+
+fn redact_url(url: &str, host_only: bool) -> String {
+    // 无 "scheme://" 的值（如 "stun:host:port"）不含凭据
+    let Some((scheme, rest)) = url.split_once("://") else { return url.to_string() };
+    let (authority, tail) = rest.split_at(rest.find(['/', '?', '#']).unwrap_or(rest.len()));
+    let authority = match authority.rsplit_once('@') {
+        Some((_, host)) => format!("{}@{}", REDACTED, host),
+        None => authority.to_string(),
+    };
+    let tail = if host_only {
+        if tail.is_empty() { String::new() } else { format!("/{}", REDACTED) }
+    } else {
+        match tail.split_once('?') {
+            Some((path, _)) => format!("{}?{}", path, REDACTED),
+            None => tail.to_string(),
+        }
+    };
+    format!("{}://{}{}", scheme, authority, tail)
+}
+
+fn redact_value(v: &serde_json::Value, how: Redact) -> serde_json::Value {
+    match (how, v) {
+        (_, serde_json::Value::Null) => serde_json::Value::Null,
+        (Redact::Url, serde_json::Value::String(s)) => serde_json::json!(redact_url(s, false)),
+        (Redact::UrlHost, serde_json::Value::String(s)) => serde_json::json!(redact_url(s, true)),
+        (Redact::Entries, serde_json::Value::Object(m)) => {
+            serde_json::Value::Object(m.keys().map(|k| (k.clone(), serde_json::json!(REDACTED))).collect())
+        }
+        (Redact::Entries, serde_json::Value::Array(a)) => serde_json::json!(vec![REDACTED; a.len()]),
+        _ => serde_json::json!(REDACTED),
+    }
+}
+
+/// 字段所在的数组位置：每层 "*" 对应（下标, id）
+type ArrayPos = Vec<(usize, Option<String>)>;
+
+/// 依路径访问匹配的字段；数组元素同时给出下标与 id（用于与旧配置对齐）
+fn visit_path(v: &mut serde_json::Value, segs: &[&str], f: &mut dyn FnMut(&mut serde_json::Value, &ArrayPos), at: &mut ArrayPos) {
+    let Some((seg, rest)) = segs.split_first() else { return f(v, at) };
+    if *seg == "*" {
+        if let serde_json::Value::Array(arr) = v {
+            for (i, item) in arr.iter_mut().enumerate() {
+                at.push((i, item.get("id").and_then(|x| x.as_str()).map(|s| s.to_string())));
+                visit_path(item, rest, f, at);
+                at.pop();
+            }
+        }
+    } else if let Some(child) = v.get_mut(*seg) {
+        visit_path(child, rest, f, at);
+    }
+}
+
+/// 在旧配置中定位与补丁同一位置的字段（数组元素优先按 id 对齐，否则按下标）
+fn lookup_path<'a>(v: &'a serde_json::Value, segs: &[&str], at: &[(usize, Option<String>)]) -> Option<&'a serde_json::Value> {
+    let Some((seg, rest)) = segs.split_first() else { return Some(v) };
+    if *seg == "*" {
+        let ((idx, id), at_rest) = at.split_first()?;
+        let arr = v.as_array()?;
+        let item = match id {
+            Some(id) => arr.iter().find(|x| x.get("id").and_then(|y| y.as_str()) == Some(id.as_str()))?,
+            None => arr.get(*idx)?,
+        };
+        lookup_path(item, rest, at_rest)
+    } else {
+        lookup_path(v.get(*seg)?, rest, at)
+    }
+}
+
+/// 配置脱敏：按 SECRET_FIELDS 替换凭据（token/password/用户名/HTTP 头/URL userinfo 与查询串/脚本命令）
+pub fn redact_secrets(v: &mut serde_json::Value) {
+    for (path, how) in SECRET_FIELDS {
+        let segs: Vec<&str> = path.split('.').collect();
+        visit_path(v, &segs, &mut |val, _| *val = redact_value(val, how), &mut Vec::new());
+    }
+}
+
+/// 还原补丁中的脱敏占位：与旧值脱敏结果相同的字段（如读取配置后原样回写）保留旧值，避免 "***" 覆盖已保存的凭据
+pub fn restore_redacted(patch: &mut serde_json::Value, current: &serde_json::Value) {
+    for (path, how) in SECRET_FIELDS {
+        let segs: Vec<&str> = path.split('.').collect();
+        visit_path(patch, &segs, &mut |val, at| {
+            let Some(old) = lookup_path(current, &segs, at) else { return };
+            match (how, &mut *val, old) {
+                (Redact::Entries, serde_json::Value::Object(m), serde_json::Value::Object(old_m)) => {
+                    for (k, x) in m.iter_mut() {
+                        if *x == REDACTED { if let Some(o) = old_m.get(k) { *x = o.clone(); } }
+                    }
+                }
+                (Redact::Entries, serde_json::Value::Array(a), serde_json::Value::Array(old_a)) => {
+                    for (i, x) in a.iter_mut().enumerate() {
+                        if *x == REDACTED { if let Some(o) = old_a.get(i) { *x = o.clone(); } }
+                    }
+                }
+                _ => {
+                    if !old.is_null() && *val == redact_value(old, how) { *val = old.clone(); }
+                }
+            }
+        }, &mut Vec::new());
+    }
+}
+
+// ---- WebSocket ----
+
+/// 计算 Sec-WebSocket-Accept
+pub fn websocket_accept_key(key: &str) -> String {
+    use base64::Engine;
+    use sha1::{Digest, Sha1};
+    let mut h = Sha1::new();
+    h.update(key.trim().as_bytes());
+    h.update(WS_GUID.as_bytes());
+    base64::engine::general_purpose::STANDARD.encode(h.finalize())
+}
+
+/// 编码服务端帧（不掩码）
+pub fn encode_ws_frame(opcode: u8, payload: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(payload.len() + 10);
+    out.push(0x80 | (opcode & 0x0F));
+    let len = payload.len();
+    if len < 126 {
+        out.push(len as u8);
+    } else if len <= u16::MAX as usize {
+        out.push(126);
+        out.extend_from_slice(&(len as u16).to_be_bytes());
+    } else {
+        out.push(127);
+        out.extend_from_slice(&(len as u64).to_be_bytes());
+    }
+    out.extend_from_slice(payload);
+    out
+}
+
+/// 解码后的帧：(opcode, 去掩码后的负载, 是否带掩码, 消耗字节数)
+pub type WsFrame = (u8, Vec<u8>, bool, usize);
+
+/// 解析一帧；数据不足返回 Ok(None)
+pub fn decode_ws_frame(buf: &[u8]) -> Result<Option<WsFrame>, String> {
+    if buf.len() < 2 { return Ok(None); }
+    let opcode = buf[0] & 0x0F;
+    let masked = buf[1] & 0x80 != 0;
+    let mut len = (buf[1] & 0x7F) as usize;
+    let mut idx = 2;
+    if len == 126 {
+        if buf.len() < 4 { return Ok(None); }
+        len = u16::from_be_bytes([buf[2], buf[3]]) as usize;
+        idx = 4;
+    } else if len == 127 {
+        if buf.len() < 10 { return Ok(None); }
+        let mut b = [0u8; 8];
+        b.copy_from_slice(&buf[2..10]);
+        len = u64::from_be_bytes(b) as usize;
+        idx = 10;
+    }
+    if len > MAX_WS_FRAME_BYTES { return Err("帧过大".to_string()); }
+    let mask_len = if masked { 4 } else { 0 };
+    if buf.len() < idx + mask_len + len { return Ok(None); }
+    let mut payload = buf[idx + mask_len..idx + mask_len + len].to_vec();
+    if masked {
+        let key = [buf[idx], buf[idx + 1], buf[idx + 2], buf[idx + 3]];
+        for (i, b) in payload.iter_mut().enumerate() { *b ^= key[i % 4]; }
+    }
+    Ok(Some((opcode, payload, masked, idx + mask_len + len)))
+}
+
+fn parse_streams<'a, I: Iterator<Item = &'a str>>(it: I) -> Vec<&'static str> {
+    it.filter_map(crate::event_hub::stream_name).collect()
+}
+
+fn handle_websocket(stream: &mut Conn, req: &Request, cors: &str, stop: &AtomicBool) {
+    let Some(key) = req.headers.get("sec-websocket-key") else {
+        let _ = write_json(stream, 400, &serde_json::json!({ "error": "missing Sec-WebSocket-Key" }), cors, &[]);
+        return;
+    };
+    let head = format!(
+        "HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Accept: {}\r\n\r\n",
+        websocket_accept_key(key)
+    );
+    if stream.send(head.as_bytes()).is_err() { return; }
+    with_status(|s| s.ws_clients += 1);
+    ws_loop(stream, req, stop);
+    with_status(|s| s.ws_clients = s.ws_clients.saturating_sub(1));
+}
+
+fn ws_send_json(stream: &mut Conn, v: &serde_json::Value) -> std::io::Result<()> {
+    stream.send(&encode_ws_frame(0x1, v.to_string().as_bytes()))
+}
+
+fn ws_loop(stream: &mut Conn, req: &Request, stop: &AtomicBool) {
+    let _ = stream.tcp().set_read_timeout(Some(Duration::from_millis(50)));
+    let rx = crate::event_hub::subscribe(64);
+    let mut subs: HashSet<&'static str> = HashSet::new();
+    let mut buf: Vec<u8> = Vec::new();
+    let mut tmp = [0u8; 4096];
+    let mut last_ping = Instant::now();
+
+    // 订阅新流后立即推送其最近值，便于叠加层首帧渲染
+    let send_subscribed = |stream: &mut Conn, subs: &HashSet<&'static str>, added: &[&'static str]| -> std::io::Result<()> {
+        let mut list: Vec<&str> = subs.iter().copied().collect();
+        list.sort();
+        ws_send_json(stream, &serde_json::json!({ "type": "subscribed", "streams": list }))?;
+        for s in added {
+            if let Some(ev) = crate::event_hub::last(s) {
+                ws_send_json(stream, &serde_json::json!({ "type": "event", "stream": ev.stream, "ts_ms": ev.ts_ms, "data": &*ev.payload }))?;
+            }
+        }
+        Ok(())
+    };
+
+    if let Some(q) = req.query.get("streams") {
+        let added = parse_streams(q.split(','));
+        subs.extend(added.iter().copied());
+        if send_subscribed(stream, &subs, &added).is_err() { return; }
+    }
+
+    loop {
+        if stop.load(Ordering::SeqCst) {
+            let _ = stream.send(&encode_ws_frame(0x8, &1001u16.to_be_bytes()));
+            return;
+        }
+
+        // 1) 入站帧
+        match stream.read(&mut tmp) {
+            Ok(0) => return,
+            Ok(n) => buf.extend_from_slice(&tmp[..n]),
+            Err(e) if matches!(e.kind(), std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut) => {}
+            Err(_) => return,
+        }
+        loop {
+            let (opcode, payload, masked, used) = match decode_ws_frame(&buf) {
+                Ok(Some(f)) => f,
+                Ok(None) => break,
+                Err(_) => {
+                    let _ = stream.send(&encode_ws_frame(0x8, &1009u16.to_be_bytes()));
+                    return;
+                }
+            };
+            buf.drain(..used);
+            // 客户端帧必须带掩码（RFC 6455 5.1）
+            if !masked {
+                let _ = stream.send(&encode_ws_frame(0x8, &1002u16.to_be_bytes()));
+                return;
+            }
+            match opcode {
+                0x1 => {
+                    let msg: serde_json::Value = serde_json::from_slice(&payload).unwrap_or_default();
+                    let op = msg.get("op").and_then(|v| v.as_str()).unwrap_or("");
+                    let streams = parse_streams(
+                        msg.get("streams").and_then(|v| v.as_array()).into_iter().flatten().filter_map(|v| v.as_str()),
+                    );
+                    let res = match op {
+                        "subscribe" => {
+                            let added: Vec<&'static str> = streams.into_iter().filter(|s| subs.insert(s)).collect();
+                            send_subscribed(stream, &subs, &added)
+                        }
+                        "unsubscribe" => {
+                            for s in streams { subs.remove(s); }
+                            send_subscribed(stream, &subs, &[])
+                        }
+                        _ => ws_send_json(stream, &serde_json::json!({ "type": "error", "error": "unknown op" })),
+                    };
+                    if res.is_err() { return; }
+                }
+                0x8 => {
+                    let _ = stream.send(&encode_ws_frame(0x8, &payload));
+                    return;
+                }
+                0x9 => {
+                    let pong = encode_ws_frame(0xA, &payload);
+                    if stream.send(&pong).is_err() { return; }
+                }
+                _ => {}
+            }
+        }
+
+        // 2) 出站事件
+        while let Ok(ev) = rx.try_recv() {
+            if !subs.contains(ev.stream) { continue; }
+            let msg = serde_json::json!({ "type": "event", "stream": ev.stream, "ts_ms": ev.ts_ms, "data": &*ev.payload });
+            if ws_send_json(stream, &msg).is_err() { return; }
+        }
+
+        // 3) 保活
+        if last_ping.elapsed() >= Duration::from_secs(30) {
+            if stream.send(&encode_ws_frame(0x9, b"")).is_err() { return; }
+            last_ping = Instant::now();
+        }
+    }
+}
@@ -0,0 +1,16 @@
+// 无界面采集服务：不启动窗口与托盘，读取桌面版同一份 config.json 并通过各输出端提供数据
+use sys_sensor_lib::agent::{parse_args, run, USAGE};
+
+fn main() {
+    let opts = match parse_args(std::env::args().skip(1)) {
+        Ok(o) => o,
+        Err(e) => {
+            eprintln!("{}\n\n{}", e, USAGE);
+            std::process::exit(2);
+        }
+    };
+    if let Err(e) = run(opts) {
+        eprintln!("[agent] 运行失败: {}", e);
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,16 @@
+// 命令行工具：一次性快照 / 刷新视图 / SMART 报告 / RTT 测量 / 历史查询，便于通过 SSH 诊断
+use sys_sensor_lib::cli::{parse_args, run, USAGE};
+
+fn main() {
+    let opts = match parse_args(std::env::args().skip(1)) {
+        Ok(o) => o,
+        Err(e) => {
+            eprintln!("{}\n\n{}", e, USAGE);
+            std::process::exit(2);
+        }
+    };
+    if let Err(e) = run(opts) {
+        eprintln!("错误: {}", e);
+        std::process::exit(1);
+    }
+}
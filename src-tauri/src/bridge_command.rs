@@ -0,0 +1,217 @@
+// 桥接命令通道（stdin JSON-RPC）
+// 说明：
+// - 请求：向桥接 stdin 写入一行 {"id":N,"method":"...","params":{...}}
+// - 响应：桥接在 stdout 输出一行 {"type":"response","id":N,"result":{...}} 或 {"type":"response","id":N,"error":{"code":..,"message":".."}}，与数据帧交错，由 bridge_protocol 分流
+// - 每个请求独立超时；桥接进程退出时挂起的请求立即失败
+// - 需要桥接在握手中声明 "commands" 能力，旧版桥接直接返回错误
+// - 方法：setInterval（采样间隔）/ setHardware（硬件分组开关）/ reenumerate（重建硬件枚举）/ dumpSensors（完整传感器树）
+// - 运行时命令不写回配置；需要在桥接重启后保持的设置放在 AppConfig.bridge_control，握手后自动下发
+
+use std::collections::HashMap;
+use std::io::Write;
+use std::sync::mpsc::{channel, Sender};
+use std::sync::{Mutex, OnceLock};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+const DEFAULT_TIMEOUT_MS: u64 = 5000;
+// dumpSensors 需要完整刷新一次硬件树，给更长的超时
+const DUMP_TIMEOUT_MS: u64 = 15000;
+
+/// 错误响应
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct BridgeRpcError {
+    pub code: i64,
+    pub message: String,
+}
+
+/// 响应行
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct BridgeResponse {
+    pub id: u64,
+    pub result: Option<serde_json::Value>,
+    pub error: Option<BridgeRpcError>,
+}
+
+#[derive(Serialize)]
+struct BridgeRequest<'a> {
+    id: u64,
+    method: &'a str,
+    params: &'a serde_json::Value,
+}
+
+/// 硬件分组开关（缺省字段保持桥接当前值）
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct BridgeHardwareGroups {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cpu: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub gpu: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub storage: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub motherboard: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub controller: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub memory: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub network: Option<bool>,
+}
+
+/// 桥接启动后自动下发的设置（AppConfig.bridge_control）
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Default)]
+pub struct BridgeControlConfig {
+    // 桥接采样间隔（毫秒，100..60000；缺省使用桥接默认 1000）
+    pub interval_ms: Option<u64>,
+    pub hardware: Option<BridgeHardwareGroups>,
+}
+
+type Pending = Sender<Result<serde_json::Value, String>>;
+
+#[derive(Default)]
+struct Channel {
+    writer: Option<Box<dyn Write + Send>>,
+    next_id: u64,
+    pending: HashMap<u64, Pending>,
+    // 本会话经 setInterval 生效的采样间隔（看门狗据此放宽阈值）
+    interval_ms: Option<u64>,
+}
+
+static CHANNEL: OnceLock<Mutex<Channel>> = OnceLock::new();
+
+fn with_channel<R, F: FnOnce(&mut Channel) -> R>(f: F) -> Option<R> {
+    let cell = CHANNEL.get_or_init(|| Mutex::new(Channel::default()));
+    cell.lock().ok().map(|mut g| f(&mut g))
+}
+
+/// 桥接进程启动：接管其 stdin
+pub fn attach(writer: Box<dyn Write + Send>) {
+    with_channel(|c| {
+        c.writer = Some(writer);
+        c.interval_ms = None;
+    });
+}
+
+/// 桥接进程退出：释放 stdin，挂起的请求全部失败
+pub fn detach(reason: &str) {
+    let pending: Vec<Pending> = with_channel(|c| {
+        c.writer = None;
+        c.interval_ms = None;
+        c.pending.drain().map(|(_, tx)| tx).collect()
+    })
+    .unwrap_or_default();
+    for tx in pending {
+        let _ = tx.send(Err(reason.to_string()));
+    }
+}
+
+/// 分发响应（由 stdout 读取线程调用）；未知/已超时的 id 直接丢弃
+pub fn handle_response(resp: BridgeResponse) {
+    let Some(tx) = with_channel(|c| c.pending.remove(&resp.id)).flatten() else {
+        eprintln!("[bridge] response for unknown request id {}", resp.id);
+        return;
+    };
+    let out = match (resp.result, resp.error) {
+        (_, Some(e)) => Err(format!("桥接返回错误 {}: {}", e.code, e.message)),
+        (Some(v), None) => Ok(v),
+        (None, None) => Ok(serde_json::Value::Null),
+    };
+    let _ = tx.send(out);
+}
+
+/// 发送请求并等待响应
+pub fn call(method: &str, params: serde_json::Value, timeout: Duration) -> Result<serde_json::Value, String> {
+    if !crate::bridge_protocol::has_capability("commands") {
+        return Err("桥接未声明 commands 能力（旧版桥接或尚未握手）".to_string());
+    }
+    let (tx, rx) = channel();
+    let id = with_channel(|c| -> Result<u64, String> {
+        let writer = c.writer.as_mut().ok_or_else(|| "桥接进程未运行".to_string())?;
+        c.next_id += 1;
+        let id = c.next_id;
+        let line = serde_json::to_string(&BridgeRequest { id, method, params: &params }).map_err(|e| e.to_string())?;
+        writer
+            .write_all(format!("{}\n", line).as_bytes())
+            .and_then(|_| writer.flush())
+            .map_err(|e| format!("写入桥接 stdin 失败: {}", e))?;
+        c.pending.insert(id, tx);
+        Ok(id)
+    })
+    .ok_or_else(|| "命令通道锁定失败".to_string())??;
+    match rx.recv_timeout(timeout) {
+        Ok(out) => out,
+        Err(_) => {
+            with_channel(|c| c.pending.remove(&id));
+            Err(format!("桥接命令 {} 超时（{}ms）", method, timeout.as_millis()))
+        }
+    }
+}
+
+fn timeout_or(ms: Option<u64>, default_ms: u64) -> Duration {
+    Duration::from_millis(ms.unwrap_or(default_ms).max(1))
+}
+
+/// 修改桥接采样间隔
+pub fn set_interval(ms: u64, timeout_ms: Option<u64>) -> Result<serde_json::Value, String> {
+    if !(100..=60_000).contains(&ms) {
+        return Err("采样间隔须在 100..60000 毫秒之间".to_string());
+    }
+    let v = call("setInterval", serde_json::json!({ "ms": ms }), timeout_or(timeout_ms, DEFAULT_TIMEOUT_MS))?;
+    with_channel(|c| c.interval_ms = Some(ms));
+    Ok(v)
+}
+
+/// 当前会话生效的采样间隔（未下发过 setInterval 时为 None，即桥接默认 1000ms）
+pub fn active_interval_ms() -> Option<u64> {
+    with_channel(|c| c.interval_ms).flatten()
+}
+
+/// 启用/禁用硬件分组，返回桥接当前的全部开关
+pub fn set_hardware(groups: &BridgeHardwareGroups, timeout_ms: Option<u64>) -> Result<BridgeHardwareGroups, String> {
+    let params = serde_json::to_value(groups).map_err(|e| e.to_string())?;
+    let v = call("setHardware", params, timeout_or(timeout_ms, DEFAULT_TIMEOUT_MS))?;
+    serde_json::from_value(v).map_err(|e| format!("响应格式错误: {}", e))
+}
+
+/// 下发 AppConfig.bridge_control（桥接握手后由管理线程调用）
+pub fn apply_control(cfg: &BridgeControlConfig) -> Result<(), String> {
+    if let Some(ms) = cfg.interval_ms { set_interval(ms, None)?; }
+    if let Some(g) = cfg.hardware.as_ref() { set_hardware(g, None)?; }
+    Ok(())
+}
+
+#[cfg(feature = "desktop")]
+async fn run_blocking<T: Send + 'static, F: FnOnce() -> Result<T, String> + Send + 'static>(f: F) -> Result<T, String> {
+    tauri::async_runtime::spawn_blocking(f).await.map_err(|e| e.to_string())?
+}
+
+/// Tauri命令：修改桥接采样间隔（毫秒）
+#[cfg(feature = "desktop")]
+#[tauri::command]
+pub async fn bridge_set_interval(ms: u64, timeout_ms: Option<u64>) -> Result<serde_json::Value, String> {
+    run_blocking(move || set_interval(ms, timeout_ms)).await
+}
+
+/// Tauri命令：启用/禁用硬件分组（GPU/存储/主板等）
+#[cfg(feature = "desktop")]
+#[tauri::command]
+pub async fn bridge_set_hardware(groups: BridgeHardwareGroups, timeout_ms: Option<u64>) -> Result<BridgeHardwareGroups, String> {
+    run_blocking(move || set_hardware(&groups, timeout_ms)).await
+}
+
+/// Tauri命令：强制桥接重新枚举硬件
+#[cfg(feature = "desktop")]
+#[tauri::command]
+pub async fn bridge_reenumerate(timeout_ms: Option<u64>) -> Result<serde_json::Value, String> {
+    run_blocking(move || call("reenumerate", serde_json::Value::Null, timeout_or(timeout_ms, DUMP_TIMEOUT_MS))).await
+}
+
+/// Tauri命令：获取完整传感器树
+#[cfg(feature = "desktop")]
+#[tauri::command]
+pub async fn bridge_dump_sensors(timeout_ms: Option<u64>) -> Result<serde_json::Value, String> {
+    run_blocking(move || call("dumpSensors", serde_json::Value::Null, timeout_or(timeout_ms, DUMP_TIMEOUT_MS))).await
+}
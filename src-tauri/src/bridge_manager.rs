@@ -1,12 +1,198 @@
 // 桥接进程管理模块
 // 包含sensor-bridge进程的启动、监控和数据接收功能
+// 看门狗：
+// - 启动后 startup_grace_sec 内无任何输出、或此后 stale_sec 内无新行、或 hb_tick 在 hb_stall_sec 内不前进时，结束子进程并重启
+// - 经 setInterval 调大采样间隔后，stale_sec / hb_stall_sec 至少放宽到 3 个采样周期，避免正常的慢速桥接被反复重启
+// - 重启使用指数退避（backoff_initial_ms 起每次翻倍，上限 backoff_max_ms）；子进程稳定运行 backoff_reset_sec 后退避复位
+// - 配置位于 AppConfig.bridge_watchdog，热更新；重启次数与最近原因经 bridge_get_watchdog_status 读取
+// 录制/回放：见 bridge_replay；配置了 bridge_replay 时以录制文件代替桥接进程
+// 模拟器：见 sensor_sim；配置了 bridge_simulator 时以合成数据代替桥接进程（优先于回放）
+// 命令通道：子进程 stdin 交由 bridge_command 持有；握手声明 commands 后下发 AppConfig.bridge_control（配置变化时重新下发）
 
+use crate::bridge_command::BridgeControlConfig;
+use crate::bridge_protocol::BridgeLine;
+use crate::bridge_replay::{BridgeRecorder, BridgeReplayConfig};
+use crate::config_utils::AppConfig;
 use crate::types::BridgeOut;
+use serde::{Deserialize, Serialize};
 use std::io::{BufRead, BufReader};
-use std::process::{Command, Stdio};
-use std::sync::{Arc, Mutex, atomic::AtomicBool};
-use std::time::Instant as StdInstant;
-use std::path::PathBuf;
+use std::process::{Child, ChildStdout, Command, Stdio};
+use std::sync::{Arc, Mutex, OnceLock, atomic::AtomicBool};
+use std::time::{Duration, Instant as StdInstant};
+use std::path::{Path, PathBuf};
+
+/// 看门狗配置（AppConfig.bridge_watchdog）
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Default)]
+pub struct BridgeWatchdogConfig {
+    // 总开关（默认启用）；关闭后仅在子进程退出时重启
+    pub enabled: Option<bool>,
+    // 多少秒没有新输出行即重启（默认 15）
+    pub stale_sec: Option<u64>,
+    // hb_tick 多少秒不前进即重启（默认 30）
+    pub hb_stall_sec: Option<u64>,
+    // 启动后等待首行输出的宽限期（默认 90；dotnet run 需要现场编译时可调大）
+    pub startup_grace_sec: Option<u64>,
+    // 重启退避初始值/上限（毫秒，默认 1000 / 60000）
+    pub backoff_initial_ms: Option<u64>,
+    pub backoff_max_ms: Option<u64>,
+    // 子进程稳定运行超过该秒数后退避复位（默认 60）
+    pub backoff_reset_sec: Option<u64>,
+}
+
+/// 看门狗状态（供前端/调试读取）
+#[derive(Clone, Debug, Serialize, Deserialize, Default)]
+pub struct BridgeWatchdogStatus {
+    pub running: bool,
+    pub pid: Option<u32>,
+    pub started_ms: Option<i64>,
+    pub last_line_ms: Option<i64>,
+    pub hb_tick: Option<i64>,
+    // 子进程重启次数（含进程自行退出与看门狗结束）
+    pub restarts: u64,
+    // 其中由看门狗主动结束的次数
+    pub watchdog_kills: u64,
+    pub spawn_failures: u64,
+    pub last_restart_reason: Option<String>,
+    pub last_restart_ms: Option<i64>,
+    // 下一次重启前的等待（毫秒）
+    pub next_backoff_ms: u64,
+}
+
+static STATUS: OnceLock<Mutex<BridgeWatchdogStatus>> = OnceLock::new();
+
+fn with_status<R, F: FnOnce(&mut BridgeWatchdogStatus) -> R>(f: F) -> Option<R> {
+    let cell = STATUS.get_or_init(|| Mutex::new(BridgeWatchdogStatus::default()));
+    cell.lock().ok().map(|mut g| f(&mut g))
+}
+
+/// Tauri命令：获取桥接看门狗状态
+#[cfg_attr(feature = "desktop", tauri::command)]
+pub fn bridge_get_watchdog_status() -> BridgeWatchdogStatus {
+    with_status(|s| s.clone()).unwrap_or_default()
+}
+
+/// 子进程输出新鲜度（由 stdout 读取线程更新）
+#[derive(Clone, Debug)]
+pub struct WatchdogProbe {
+    pub started: StdInstant,
+    pub last_line: Option<StdInstant>,
+    pub hb_tick: Option<i64>,
+    // hb_tick 最近一次变化的时间
+    pub hb_changed: Option<StdInstant>,
+    // 桥接当前采样间隔（毫秒，None 表示桥接默认）
+    pub interval_ms: Option<u64>,
+}
+
+impl WatchdogProbe {
+    pub fn new(started: StdInstant) -> Self {
+        Self { started, last_line: None, hb_tick: None, hb_changed: None, interval_ms: None }
+    }
+
+    /// 记录一行输出（数据帧附带 hb_tick）
+    pub fn on_line(&mut self, now: StdInstant, hb_tick: Option<i64>) {
+        self.last_line = Some(now);
+        if let Some(hb) = hb_tick {
+            if self.hb_tick != Some(hb) {
+                self.hb_tick = Some(hb);
+                self.hb_changed = Some(now);
+            }
+        }
+    }
+}
+
+/// 无输出/心跳停滞阈值至少覆盖的采样周期数
+const WATCHDOG_MIN_INTERVALS: u64 = 3;
+
+/// 判定是否需要重启，返回原因
+pub fn check_watchdog(cfg: &BridgeWatchdogConfig, probe: &WatchdogProbe, now: StdInstant) -> Option<String> {
+    if !cfg.enabled.unwrap_or(true) { return None; }
+    let min_window = Duration::from_millis(probe.interval_ms.unwrap_or(0).saturating_mul(WATCHDOG_MIN_INTERVALS));
+    let stale = Duration::from_secs(cfg.stale_sec.unwrap_or(15).max(1)).max(min_window);
+    let hb_stall = Duration::from_secs(cfg.hb_stall_sec.unwrap_or(30).max(1)).max(min_window);
+    let grace = Duration::from_secs(cfg.startup_grace_sec.unwrap_or(90).max(1));
+    match probe.last_line {
+        None if now.saturating_duration_since(probe.started) > grace => {
+            return Some(format!("启动后 {}s 无输出", grace.as_secs()));
+        }
+        Some(t) if now.saturating_duration_since(t) > stale => {
+            return Some(format!("{}s 无新输出", now.saturating_duration_since(t).as_secs()));
+        }
+        _ => {}
+    }
+    match (probe.hb_tick, probe.hb_changed) {
+        (Some(hb), Some(t)) if now.saturating_duration_since(t) > hb_stall => {
+            Some(format!("心跳停滞 {}s（hb_tick={}）", now.saturating_duration_since(t).as_secs(), hb))
+        }
+        _ => None,
+    }
+}
+
+/// 指数退避
+#[derive(Clone, Debug, Default)]
+pub struct Backoff {
+    next_ms: Option<u64>,
+}
+
+impl Backoff {
+    /// 本次重启前的等待；ran_for 为上一个子进程的运行时长（启动失败为 None）
+    pub fn delay_ms(&mut self, cfg: &BridgeWatchdogConfig, ran_for: Option<Duration>) -> u64 {
+        let initial = cfg.backoff_initial_ms.unwrap_or(1000).max(1);
+        let max = cfg.backoff_max_ms.unwrap_or(60_000).max(initial);
+        let reset = Duration::from_secs(cfg.backoff_reset_sec.unwrap_or(60));
+        let delay = match (self.next_ms, ran_for) {
+            (Some(n), Some(ran)) if ran < reset => n,
+            (Some(n), None) => n,
+            _ => initial,
+        }
+        .min(max);
+        self.next_ms = Some(delay.saturating_mul(2).min(max));
+        delay
+    }
+}
+
+/// bridge_control 下发失败后的重试间隔
+const CONTROL_RETRY: Duration = Duration::from_secs(5);
+
+/// 本会话 bridge_control 的下发进度：调用成功后才记为已下发，失败后按 CONTROL_RETRY 重试
+#[derive(Clone, Debug, Default)]
+pub struct ControlSync {
+    applied: Option<BridgeControlConfig>,
+    in_flight: bool,
+    retry_at: Option<StdInstant>,
+}
+
+impl ControlSync {
+    /// 是否需要下发 control；返回 true 时调用方必须在下发结束后调用 finish
+    pub fn begin(&mut self, control: &BridgeControlConfig, now: StdInstant) -> bool {
+        if self.in_flight || self.applied.as_ref() == Some(control) { return false; }
+        if self.retry_at.is_some_and(|t| now < t) { return false; }
+        self.in_flight = true;
+        true
+    }
+
+    pub fn finish(&mut self, control: BridgeControlConfig, ok: bool, now: StdInstant) {
+        self.in_flight = false;
+        if ok {
+            self.applied = Some(control);
+            self.retry_at = None;
+        } else {
+            self.retry_at = Some(now + CONTROL_RETRY);
+        }
+    }
+}
+
+fn watchdog_config(config: &Arc<Mutex<AppConfig>>) -> BridgeWatchdogConfig {
+    config.lock().ok().and_then(|c| c.bridge_watchdog.clone()).unwrap_or_default()
+}
+
+/// 分段休眠，期间响应退出请求
+fn sleep_unless_shutdown(ms: u64, shutdown_flag: &AtomicBool) {
+    let deadline = StdInstant::now() + Duration::from_millis(ms);
+    while StdInstant::now() < deadline {
+        if shutdown_flag.load(std::sync::atomic::Ordering::SeqCst) { return; }
+        std::thread::sleep(Duration::from_millis(200).min(deadline.saturating_duration_since(StdInstant::now())));
+    }
+}
 
 /// 启动桥接进程管理线程
 pub fn start_bridge_manager(
@@ -14,8 +200,28 @@ pub fn start_bridge_manager(
     packaged_bridge_exe: Option<PathBuf>,
     shutdown_flag: Arc<AtomicBool>,
     bridge_pid: Arc<Mutex<Option<u32>>>,
+    config: Arc<Mutex<AppConfig>>,
 ) {
     std::thread::spawn(move || {
+        // 模拟器模式：不启动真实桥接
+        let simulator = config.lock().ok().and_then(|c| c.bridge_simulator.clone()).filter(|s| s.is_enabled());
+        if let Some(sim) = simulator {
+            if let Err(e) = simulate_bridge(&sim, &bridge_data, &shutdown_flag) {
+                eprintln!("[bridge] simulator failed: {}", e);
+            }
+            crate::sensor_sim::publish_overlay(None);
+            return;
+        }
+
+        // 回放模式：不启动真实桥接
+        let replay = config.lock().ok().and_then(|c| c.bridge_replay.clone()).filter(|r| r.source().is_some());
+        if let Some(replay) = replay {
+            let result = replay_bridge(&replay, &bridge_data, &shutdown_flag);
+            if let Err(e) = result.as_ref() { eprintln!("[bridge] replay failed: {}", e); }
+            crate::bridge_replay::end_replay(result.err());
+            return;
+        }
+
         // Resolve project root by walking up until we find `sensor-bridge/sensor-bridge.csproj`
         let exe_dir = std::env::current_exe().ok().and_then(|p| p.parent().map(|p| p.to_path_buf()));
         let mut cursor = exe_dir.clone();
@@ -42,99 +248,283 @@ pub fn start_bridge_manager(
             .ok()
             .and_then(|p| p.parent().map(|d| d.join("resources").join("sensor-bridge").join("sensor-bridge.exe")));
 
+        let mut backoff = Backoff::default();
         loop {
             if shutdown_flag.load(std::sync::atomic::Ordering::SeqCst) { break; }
-            
+
             // 0) 若存在打包资源中的自包含 EXE，优先直接启动
-            if let Some(ref p) = packaged_bridge_exe {
-                if p.exists() {
-                    if try_spawn_bridge_exe(p, &project_root, &bridge_data, &bridge_pid) {
-                        continue;
-                    }
-                }
-            }
-            
             // 0b) 便携版兜底：尝试 exe 同目录下的 resources 路径
-            if let Some(ref p) = portable_bridge_exe {
-                if p.exists() {
-                    if try_spawn_bridge_exe(p, &project_root, &bridge_data, &bridge_pid) {
-                        continue;
-                    }
+            // 1) 最后尝试开发模式路径
+            let child = [packaged_bridge_exe.as_ref(), portable_bridge_exe.as_ref()]
+                .into_iter()
+                .flatten()
+                .filter(|p| p.exists())
+                .find_map(|p| try_spawn_bridge_exe(p, &project_root))
+                .or_else(|| try_spawn_dev_bridge(&project_root));
+
+            let (reason, ran_for) = match child {
+                Some(child_proc) => {
+                    let started = StdInstant::now();
+                    let reason = supervise(child_proc, &bridge_data, &bridge_pid, &config, &shutdown_flag);
+                    (reason, Some(started.elapsed()))
                 }
-            }
-            
-            // 尝试开发模式路径
-            if try_spawn_dev_bridge(&project_root, &bridge_data, &bridge_pid) {
-                continue;
-            }
-            
-            eprintln!("[bridge] Failed to spawn sensor-bridge process, retry in 3s.");
-            std::thread::sleep(std::time::Duration::from_secs(3));
+                None => {
+                    with_status(|s| s.spawn_failures += 1);
+                    ("启动失败".to_string(), None)
+                }
+            };
+            if shutdown_flag.load(std::sync::atomic::Ordering::SeqCst) { break; }
+
+            let delay = backoff.delay_ms(&watchdog_config(&config), ran_for);
+            with_status(|s| {
+                s.restarts += 1;
+                s.last_restart_reason = Some(reason.clone());
+                s.last_restart_ms = Some(chrono::Local::now().timestamp_millis());
+                s.next_backoff_ms = delay;
+            });
+            eprintln!("[bridge] {}, respawn in {}ms", reason, delay);
+            sleep_unless_shutdown(delay, &shutdown_flag);
         }
     });
 }
 
-/// 尝试启动打包的桥接可执行文件
-fn try_spawn_bridge_exe(
-    exe_path: &PathBuf,
-    project_root: &PathBuf,
+/// 以模拟器代替桥接进程，直至退出
+fn simulate_bridge(
+    cfg: &crate::sensor_sim::SimulatorConfig,
     bridge_data: &Arc<Mutex<(Option<BridgeOut>, StdInstant)>>,
-    bridge_pid: &Arc<Mutex<Option<u32>>>,
-) -> bool {
-    eprintln!("[bridge] spawning packaged exe: {}", exe_path.display());
-    let mut cmd = Command::new(exe_path);
-    cmd.current_dir(exe_path.parent().unwrap_or(project_root));
+    shutdown_flag: &AtomicBool,
+) -> Result<(), String> {
+    let scenario = cfg.load_scenario()?;
+    let interval_ms = cfg.interval_ms.unwrap_or(1000).max(50);
+    let dt = interval_ms as f64 / 1000.0 * cfg.speed();
+    eprintln!("[bridge] simulating scenario {} at {}x", scenario.name.as_deref().unwrap_or("default"), cfg.speed());
+    let mut sim = crate::sensor_sim::Simulator::new(scenario);
+
+    crate::bridge_protocol::begin_session();
+    let hello = serde_json::json!({
+        "type": "hello",
+        "protocolVersion": crate::bridge_protocol::HOST_PROTOCOL_VERSION,
+        "bridgeVersion": "simulator",
+        "capabilities": crate::bridge_protocol::HOST_CAPABILITIES.iter().filter(|c| **c != "commands").collect::<Vec<_>>(),
+    });
+    let mut last_reject: Option<String> = None;
+    dispatch_line(&hello.to_string(), bridge_data, &mut last_reject);
+    while !shutdown_flag.load(std::sync::atomic::Ordering::SeqCst) {
+        let frame = sim.step(dt);
+        crate::sensor_sim::publish_overlay(Some(frame.overlay));
+        dispatch_line(&frame.bridge.to_string(), bridge_data, &mut last_reject);
+        sleep_unless_shutdown(interval_ms, shutdown_flag);
+    }
+    Ok(())
+}
+
+/// 以录制文件代替桥接进程，直至播放完毕（非循环）或退出
+fn replay_bridge(
+    cfg: &BridgeReplayConfig,
+    bridge_data: &Arc<Mutex<(Option<BridgeOut>, StdInstant)>>,
+    shutdown_flag: &AtomicBool,
+) -> Result<(), String> {
+    let path = cfg.source().unwrap_or_default();
+    let lines = crate::bridge_replay::load_recording(Path::new(path))?;
+    let speed = cfg.speed();
+    let delays = crate::bridge_replay::replay_delays(&lines, speed, cfg.max_gap_ms.unwrap_or(10_000));
+    // 从末尾回到开头时的间隔：按 1s 采样周期计
+    let loop_gap_ms = (1000.0 / speed).round() as u64;
+    eprintln!("[bridge] replaying {} lines from {} at {}x (loop={})", lines.len(), path, speed, cfg.looped.unwrap_or(false));
+    crate::bridge_replay::begin_replay(path, lines.len(), speed);
+
+    let mut last_reject: Option<String> = None;
+    let mut loops = 0u64;
+    loop {
+        crate::bridge_protocol::begin_session();
+        for (i, (rec, delay)) in lines.iter().zip(delays.iter()).enumerate() {
+            sleep_unless_shutdown(*delay, shutdown_flag);
+            if shutdown_flag.load(std::sync::atomic::Ordering::SeqCst) { return Ok(()); }
+            dispatch_line(&rec.line, bridge_data, &mut last_reject);
+            crate::bridge_replay::replay_progress(i + 1, loops);
+        }
+        if !cfg.looped.unwrap_or(false) { return Ok(()); }
+        loops += 1;
+        sleep_unless_shutdown(loop_gap_ms, shutdown_flag);
+    }
+}
+
+/// 结束子进程（Windows 下连同子进程树）
+fn kill_child(child: &mut Child) {
     #[cfg(windows)]
     {
         use std::os::windows::process::CommandExt;
-        cmd.creation_flags(0x08000000); // CREATE_NO_WINDOW
+        let _ = Command::new("taskkill")
+            .args(["/PID", &child.id().to_string(), "/T", "/F"])
+            .creation_flags(0x08000000) // CREATE_NO_WINDOW
+            .output();
     }
-    
-    if let Ok(mut child_proc) = cmd.stdout(Stdio::piped()).stderr(Stdio::piped()).spawn() {
-        if let Ok(mut g) = bridge_pid.lock() { *g = Some(child_proc.id()); }
-        
-        if let Some(stdout) = child_proc.stdout.take() {
-            let reader = BufReader::new(stdout);
-            for line in reader.lines().flatten() {
+    let _ = child.kill();
+}
+
+/// 托管子进程直至退出：stdout/stderr 由独立线程读取，本线程按配置执行看门狗检查；返回结束原因
+fn supervise(
+    mut child_proc: Child,
+    bridge_data: &Arc<Mutex<(Option<BridgeOut>, StdInstant)>>,
+    bridge_pid: &Arc<Mutex<Option<u32>>>,
+    config: &Arc<Mutex<AppConfig>>,
+    shutdown_flag: &AtomicBool,
+) -> String {
+    let pid = child_proc.id();
+    if let Ok(mut g) = bridge_pid.lock() { *g = Some(pid); }
+    let probe = Arc::new(Mutex::new(WatchdogProbe::new(StdInstant::now())));
+    with_status(|s| {
+        s.running = true;
+        s.pid = Some(pid);
+        s.started_ms = Some(chrono::Local::now().timestamp_millis());
+        s.last_line_ms = None;
+        s.hb_tick = None;
+    });
+
+    // Drain and print stderr if available for diagnostics
+    if let Some(stderr) = child_proc.stderr.take() {
+        std::thread::spawn(move || {
+            let rdr = BufReader::new(stderr);
+            for line in rdr.lines().map_while(Result::ok) {
                 if line.trim().is_empty() { continue; }
-                if let Ok(parsed) = serde_json::from_str::<BridgeOut>(&line) {
-                    if let Ok(mut guard) = bridge_data.lock() {
-                        *guard = (Some(parsed), StdInstant::now());
-                    }
-                } else {
-                    eprintln!("[bridge] Non-JSON line: {}", line);
-                }
+                eprintln!("[bridge][stderr] {}", line);
             }
+        });
+    }
+    if let Some(stdout) = child_proc.stdout.take() {
+        let data = bridge_data.clone();
+        let probe_c = probe.clone();
+        let record_cfg = config.lock().ok().and_then(|c| c.bridge_record.clone()).unwrap_or_default();
+        let recorder = BridgeRecorder::open(&record_cfg).unwrap_or_else(|e| {
+            eprintln!("[bridge] recorder disabled: {}", e);
+            crate::bridge_replay::record_failed(e);
+            None
+        });
+        std::thread::spawn(move || pump_stdout(stdout, &data, &probe_c, recorder));
+    }
+    if let Some(stdin) = child_proc.stdin.take() {
+        crate::bridge_command::attach(Box::new(stdin));
+    }
+    // 本会话 bridge_control 的下发进度（下发线程回报结果）
+    let control_sync: Arc<Mutex<ControlSync>> = Arc::default();
+
+    let reason = loop {
+        std::thread::sleep(Duration::from_millis(500));
+        if shutdown_flag.load(std::sync::atomic::Ordering::SeqCst) {
+            kill_child(&mut child_proc);
+            break "退出".to_string();
+        }
+        match child_proc.try_wait() {
+            Ok(Some(status)) => break format!("进程退出（{}）", status),
+            Ok(None) => {}
+            Err(e) => break format!("等待进程失败: {}", e),
         }
-        
-        if let Some(stderr) = child_proc.stderr.take() {
+        let snapshot = probe.lock().ok().map(|p| p.clone());
+        let Some(mut p) = snapshot else { continue };
+        p.interval_ms = crate::bridge_command::active_interval_ms();
+        with_status(|s| {
+            s.last_line_ms = p.last_line.map(|t| chrono::Local::now().timestamp_millis() - t.elapsed().as_millis() as i64);
+            s.hb_tick = p.hb_tick;
+        });
+        if let Some(why) = check_watchdog(&watchdog_config(config), &p, StdInstant::now()) {
+            eprintln!("[bridge] watchdog: {}, killing pid {}", why, pid);
+            kill_child(&mut child_proc);
+            with_status(|s| s.watchdog_kills += 1);
+            break format!("看门狗: {}", why);
+        }
+        // 下发在独立线程执行，避免等待响应时阻塞看门狗检查
+        let control = config.lock().ok().and_then(|c| c.bridge_control.clone()).unwrap_or_default();
+        let due = crate::bridge_protocol::has_capability("commands")
+            && control_sync.lock().map(|mut g| g.begin(&control, StdInstant::now())).unwrap_or(false);
+        if due {
+            let sync = control_sync.clone();
             std::thread::spawn(move || {
-                let rdr = BufReader::new(stderr);
-                for line in rdr.lines().flatten() {
-                    if line.trim().is_empty() { continue; }
-                    eprintln!("[bridge][stderr] {}", line);
+                let result = crate::bridge_command::apply_control(&control);
+                if let Err(e) = &result {
+                    eprintln!("[bridge] apply bridge_control failed, will retry: {}", e);
                 }
+                if let Ok(mut g) = sync.lock() { g.finish(control, result.is_ok(), StdInstant::now()); }
             });
         }
-        
-        let _ = child_proc.wait();
-        if let Ok(mut g) = bridge_pid.lock() { *g = None; }
-        eprintln!("[bridge] packaged bridge exited, respawn in 3s...");
-        std::thread::sleep(std::time::Duration::from_secs(3));
-        true
-    } else {
-        eprintln!("[bridge] Failed to spawn packaged sensor-bridge.exe, fallback to dev paths in 3s...");
-        std::thread::sleep(std::time::Duration::from_secs(3));
-        false
+    };
+
+    crate::bridge_command::detach("桥接进程已退出");
+    let _ = child_proc.wait();
+    if let Ok(mut g) = bridge_pid.lock() { *g = None; }
+    with_status(|s| { s.running = false; s.pid = None; });
+    reason
+}
+
+/// 读取桥接 stdout：逐行录制（如已启用）并交由 dispatch_line 处理；每行刷新看门狗探针
+fn pump_stdout(
+    stdout: ChildStdout,
+    bridge_data: &Arc<Mutex<(Option<BridgeOut>, StdInstant)>>,
+    probe: &Mutex<WatchdogProbe>,
+    mut recorder: Option<BridgeRecorder>,
+) {
+    crate::bridge_protocol::begin_session();
+    let reader = BufReader::new(stdout);
+    // 拒收原因变化时才打印，避免每帧刷屏
+    let mut last_reject: Option<String> = None;
+    for line in reader.lines().map_while(Result::ok) {
+        if line.trim().is_empty() { continue; }
+        if let Some(rec) = recorder.as_mut() {
+            if let Err(e) = rec.record(chrono::Local::now().timestamp_millis(), &line) {
+                eprintln!("[bridge] recording stopped: {}", e);
+                crate::bridge_replay::record_failed(e);
+                recorder = None;
+            }
+        }
+        let hb_tick = dispatch_line(&line, bridge_data, &mut last_reject);
+        if let Ok(mut p) = probe.lock() { p.on_line(StdInstant::now(), hb_tick); }
+    }
+}
+
+/// 处理桥接输出的一行（真实进程与回放共用）：握手消息更新协议状态，数据帧写入共享槽位，命令响应转交命令通道；返回数据帧的 hb_tick
+fn dispatch_line(line: &str, bridge_data: &Arc<Mutex<(Option<BridgeOut>, StdInstant)>>, last_reject: &mut Option<String>) -> Option<i64> {
+    match crate::bridge_protocol::handle_line(line) {
+        BridgeLine::Frame(parsed) => {
+            let hb_tick = parsed.hb_tick;
+            if let Ok(mut guard) = bridge_data.lock() {
+                *guard = (Some(*parsed), StdInstant::now());
+            }
+            return hb_tick;
+        }
+        BridgeLine::Hello(_) => {}
+        BridgeLine::Response(resp) => crate::bridge_command::handle_response(resp),
+        BridgeLine::Rejected(reason) => {
+            if last_reject.as_deref() != Some(reason.as_str()) {
+                eprintln!("[bridge] Rejected frame: {}", reason);
+                *last_reject = Some(reason);
+            }
+        }
+        BridgeLine::NonJson => eprintln!("[bridge] Non-JSON line: {}", line),
+    }
+    None
+}
+
+/// 尝试启动打包的桥接可执行文件
+fn try_spawn_bridge_exe(exe_path: &Path, project_root: &Path) -> Option<Child> {
+    eprintln!("[bridge] spawning packaged exe: {}", exe_path.display());
+    let mut cmd = Command::new(exe_path);
+    cmd.current_dir(exe_path.parent().unwrap_or(project_root));
+    #[cfg(windows)]
+    {
+        use std::os::windows::process::CommandExt;
+        cmd.creation_flags(0x08000000); // CREATE_NO_WINDOW
+    }
+
+    match cmd.stdin(Stdio::piped()).stdout(Stdio::piped()).stderr(Stdio::piped()).spawn() {
+        Ok(child_proc) => Some(child_proc),
+        Err(e) => {
+            eprintln!("[bridge] Failed to spawn packaged sensor-bridge.exe ({}), fallback to next candidate", e);
+            None
+        }
     }
 }
 
 /// 尝试启动开发模式的桥接进程
-fn try_spawn_dev_bridge(
-    project_root: &PathBuf,
-    bridge_data: &Arc<Mutex<(Option<BridgeOut>, StdInstant)>>,
-    bridge_pid: &Arc<Mutex<Option<u32>>>,
-) -> bool {
+fn try_spawn_dev_bridge(project_root: &Path) -> Option<Child> {
     let dll_candidates = [
         project_root.join("sensor-bridge/bin/Release/net8.0/sensor-bridge.dll"),
         project_root.join("sensor-bridge/bin/Debug/net8.0/sensor-bridge.dll"),
@@ -145,75 +535,37 @@ fn try_spawn_dev_bridge(
     ];
 
     // 1) 优先使用 dll: dotnet <dll>
-    let mut child = if let Some(dll) = dll_candidates.iter().find(|p| p.exists()) {
+    if let Some(dll) = dll_candidates.iter().find(|p| p.exists()) {
         eprintln!("[bridge] spawning via dotnet: {}", dll.display());
         let mut cmd = Command::new("dotnet");
-        cmd.arg(dll).current_dir(project_root.clone());
+        cmd.arg(dll).current_dir(project_root);
         #[cfg(windows)]
         {
             use std::os::windows::process::CommandExt;
             cmd.creation_flags(0x08000000); // CREATE_NO_WINDOW
         }
-        cmd.stdout(Stdio::piped()).stderr(Stdio::piped()).spawn().ok()
+        cmd.stdin(Stdio::piped()).stdout(Stdio::piped()).stderr(Stdio::piped()).spawn().ok()
     // 2) 其次尝试 exe 直接启动
     } else if let Some(exe) = exe_candidates.iter().find(|p| p.exists()) {
         eprintln!("[bridge] spawning exe: {}", exe.display());
         let mut cmd = Command::new(exe);
-        cmd.current_dir(project_root.clone());
+        cmd.current_dir(project_root);
         #[cfg(windows)]
         {
             use std::os::windows::process::CommandExt;
             cmd.creation_flags(0x08000000); // CREATE_NO_WINDOW
         }
-        cmd.stdout(Stdio::piped()).stderr(Stdio::piped()).spawn().ok()
+        cmd.stdin(Stdio::piped()).stdout(Stdio::piped()).stderr(Stdio::piped()).spawn().ok()
     } else {
         // 3) 最后 fallback 到 dotnet run
         eprintln!("[bridge] fallback to 'dotnet run --project sensor-bridge'");
         let mut cmd = Command::new("dotnet");
-        cmd.args(["run", "--project", "sensor-bridge"]).current_dir(project_root.clone());
+        cmd.args(["run", "--project", "sensor-bridge"]).current_dir(project_root);
         #[cfg(windows)]
         {
             use std::os::windows::process::CommandExt;
             cmd.creation_flags(0x08000000); // CREATE_NO_WINDOW
         }
-        cmd.stdout(Stdio::piped()).stderr(Stdio::piped()).spawn().ok()
-    };
-
-    if let Some(ref mut child_proc) = child {
-        if let Ok(mut g) = bridge_pid.lock() { *g = Some(child_proc.id()); }
-        
-        if let Some(stdout) = child_proc.stdout.take() {
-            let reader = BufReader::new(stdout);
-            for line in reader.lines().flatten() {
-                if line.trim().is_empty() { continue; }
-                if let Ok(parsed) = serde_json::from_str::<BridgeOut>(&line) {
-                    if let Ok(mut guard) = bridge_data.lock() {
-                        *guard = (Some(parsed), StdInstant::now());
-                    }
-                } else {
-                    eprintln!("[bridge] Non-JSON line: {}", line);
-                }
-            }
-        }
-        
-        // Drain and print stderr if available for diagnostics
-        if let Some(stderr) = child_proc.stderr.take() {
-            std::thread::spawn(move || {
-                let rdr = BufReader::new(stderr);
-                for line in rdr.lines().flatten() {
-                    if line.trim().is_empty() { continue; }
-                    eprintln!("[bridge][stderr] {}", line);
-                }
-            });
-        }
-        
-        // Wait child and then respawn
-        let _ = child_proc.wait();
-        if let Ok(mut g) = bridge_pid.lock() { *g = None; }
-        eprintln!("[bridge] bridge process exited, will respawn in 3s...");
-        std::thread::sleep(std::time::Duration::from_secs(3));
-        true
-    } else {
-        false
+        cmd.stdin(Stdio::piped()).stdout(Stdio::piped()).stderr(Stdio::piped()).spawn().ok()
     }
 }
@@ -0,0 +1,323 @@
+// 桥接协议版本与握手
+// 说明：
+// - 桥接进程启动后先输出一行握手消息：
+//   {"type":"hello","protocolVersion":2,"minHostVersion":1,"bridgeVersion":"x.y.z","capabilities":["temps",...]}
+// - 未发送握手就开始输出数据帧的旧版桥接视为协议版本 0
+// - 兼容矩阵见 evaluate_compat()：版本一致 / 桥接较旧（缺少的能力字段保持为空）/ 桥接较新（多出的字段作为未知字段上报）/ 不兼容
+// - 解析模式：lenient（默认）照常接收并统计未知字段；strict 拒收含未知字段的数据帧、旧版桥接与不兼容版本的全部数据帧
+// - v2 起桥接支持 stdin 命令通道（能力 "commands"），命令响应行 {"type":"response",...} 转交 bridge_command，不计入数据帧
+// - 未知字段按顶层键统计，已知字段清单由 BridgeOut 的 Deserialize 实现反射得到，新增字段无需手工维护
+
+use std::collections::{BTreeMap, HashSet};
+use std::sync::{Mutex, OnceLock};
+
+use serde::{Deserialize, Serialize};
+
+use crate::bridge_command::BridgeResponse;
+use crate::types::BridgeOut;
+
+/// 本端实现的协议版本
+pub const HOST_PROTOCOL_VERSION: u32 = 2;
+/// 本端可接受的最低桥接协议版本（0 = 无握手的旧版桥接，仅 lenient 模式接受）
+pub const MIN_BRIDGE_PROTOCOL_VERSION: u32 = 0;
+/// 本端期望桥接提供的能力（与桥接输出的字段分组对应）
+pub const HOST_CAPABILITIES: [&str; 9] = ["temps", "fans", "voltages", "storageTemps", "gpus", "cpuExtra", "perCore", "health", "commands"];
+
+/// 握手消息
+#[derive(Clone, Debug, Serialize, Deserialize, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct BridgeHello {
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub protocol_version: u32,
+    // 桥接要求的最低宿主协议版本（缺省等于 protocol_version）
+    pub min_host_version: Option<u32>,
+    pub bridge_version: Option<String>,
+    #[serde(default)]
+    pub capabilities: Vec<String>,
+}
+
+/// 解析模式
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum BridgeParseMode {
+    #[default]
+    Lenient,
+    Strict,
+}
+
+impl BridgeParseMode {
+    /// 从配置字符串解析（未知值按 lenient 处理）
+    pub fn from_config(s: Option<&str>) -> Self {
+        match s.map(|v| v.trim().to_ascii_lowercase()) {
+            Some(v) if v == "strict" => BridgeParseMode::Strict,
+            _ => BridgeParseMode::Lenient,
+        }
+    }
+}
+
+/// 兼容性判定
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum BridgeCompat {
+    // 尚未收到任何输出
+    #[default]
+    Unknown,
+    // 无握手的旧版桥接
+    Legacy,
+    Match,
+    BridgeOlder,
+    BridgeNewer,
+    Incompatible,
+}
+
+/// 单行解析结果
+#[derive(Clone, Debug)]
+pub enum BridgeLine {
+    Hello(BridgeHello),
+    Frame(Box<BridgeOut>),
+    // 命令通道响应
+    Response(BridgeResponse),
+    // 被 strict 模式拒收的数据帧（附原因）
+    Rejected(String),
+    NonJson,
+}
+
+/// 协议状态（供前端/调试读取）
+#[derive(Clone, Debug, Serialize, Deserialize, Default)]
+pub struct BridgeProtocolStatus {
+    pub mode: BridgeParseMode,
+    pub host_protocol_version: u32,
+    pub handshake: Option<BridgeHello>,
+    pub compat: BridgeCompat,
+    // 本端期望但桥接未声明的能力
+    pub missing_capabilities: Vec<String>,
+    // 桥接声明但本端不认识的能力
+    pub extra_capabilities: Vec<String>,
+    // 顶层未知字段 -> 出现次数（本次会话）
+    pub unknown_fields: BTreeMap<String, u64>,
+    pub frames_ok: u64,
+    pub frames_rejected: u64,
+    pub non_json_lines: u64,
+    pub last_error: Option<String>,
+    // 面向 UI 的版本不一致提示（兼容时为空）
+    pub warning: Option<String>,
+    // 本次桥接进程会话开始时间
+    pub session_started_ms: Option<i64>,
+}
+
+static STATUS: OnceLock<Mutex<BridgeProtocolStatus>> = OnceLock::new();
+
+fn with_status<R, F: FnOnce(&mut BridgeProtocolStatus) -> R>(f: F) -> Option<R> {
+    let cell = STATUS.get_or_init(|| Mutex::new(BridgeProtocolStatus { host_protocol_version: HOST_PROTOCOL_VERSION, ..Default::default() }));
+    cell.lock().ok().map(|mut g| f(&mut g))
+}
+
+/// Tauri命令：获取桥接协议状态
+#[cfg_attr(feature = "desktop", tauri::command)]
+pub fn bridge_get_protocol_status() -> BridgeProtocolStatus {
+    with_status(|s| s.clone()).unwrap_or_default()
+}
+
+/// 当前版本不一致提示（供托盘/快照使用）
+pub fn current_warning() -> Option<String> {
+    with_status(|s| s.warning.clone()).flatten()
+}
+
+/// 桥接是否在握手中声明了指定能力（无握手视为未声明）
+pub fn has_capability(cap: &str) -> bool {
+    with_status(|s| s.handshake.as_ref().is_some_and(|h| h.capabilities.iter().any(|c| c == cap))).unwrap_or(false)
+}
+
+/// 设置解析模式（由采样线程按配置同步）
+pub fn set_mode(mode: BridgeParseMode) {
+    with_status(|s| {
+        if s.mode != mode {
+            s.mode = mode;
+            s.warning = warning_for(s.compat, s.handshake.as_ref(), mode);
+        }
+    });
+}
+
+/// 桥接进程（重新）启动：清空上一会话的握手与统计
+pub fn begin_session() {
+    with_status(|s| {
+        *s = BridgeProtocolStatus {
+            mode: s.mode,
+            host_protocol_version: HOST_PROTOCOL_VERSION,
+            session_started_ms: Some(chrono::Local::now().timestamp_millis()),
+            ..Default::default()
+        };
+    });
+}
+
+/// 兼容矩阵
+// MIN_BRIDGE_PROTOCOL_VERSION 目前为 0，比较恒为假；保留该比较以便将来提高最低版本时只改常量
+#[allow(clippy::absurd_extreme_comparisons)]
+pub fn evaluate_compat(hello: Option<&BridgeHello>) -> BridgeCompat {
+    let Some(h) = hello else { return BridgeCompat::Legacy };
+    let min_host = h.min_host_version.unwrap_or(h.protocol_version);
+    if h.protocol_version < MIN_BRIDGE_PROTOCOL_VERSION || min_host > HOST_PROTOCOL_VERSION {
+        BridgeCompat::Incompatible
+    } else if h.protocol_version < HOST_PROTOCOL_VERSION {
+        BridgeCompat::BridgeOlder
+    } else if h.protocol_version > HOST_PROTOCOL_VERSION {
+        BridgeCompat::BridgeNewer
+    } else {
+        BridgeCompat::Match
+    }
+}
+
+/// 当前模式下是否接收数据帧
+fn accepts(compat: BridgeCompat, mode: BridgeParseMode) -> bool {
+    match compat {
+        BridgeCompat::Incompatible => false,
+        BridgeCompat::Legacy => mode == BridgeParseMode::Lenient,
+        _ => true,
+    }
+}
+
+fn warning_for(compat: BridgeCompat, hello: Option<&BridgeHello>, mode: BridgeParseMode) -> Option<String> {
+    let ver = hello.map(|h| h.protocol_version).unwrap_or(0);
+    let tag = hello.and_then(|h| h.bridge_version.clone()).map(|v| format!(" {}", v)).unwrap_or_default();
+    match compat {
+        BridgeCompat::Incompatible => Some(format!("桥接协议不兼容（桥接 v{}{}，本端 v{}）", ver, tag, HOST_PROTOCOL_VERSION)),
+        BridgeCompat::Legacy if mode == BridgeParseMode::Strict => Some("旧版桥接未握手，strict 模式已拒收".to_string()),
+        BridgeCompat::BridgeOlder => Some(format!("桥接较旧（v{}{}，本端 v{}），部分指标不可用", ver, tag, HOST_PROTOCOL_VERSION)),
+        BridgeCompat::BridgeNewer => Some(format!("桥接较新（v{}{}，本端 v{}），新字段将被忽略", ver, tag, HOST_PROTOCOL_VERSION)),
+        _ => None,
+    }
+}
+
+/// 通过 Deserialize 实现反射结构体的字段清单（serde 在 deserialize_struct 中传入全部字段名）
+fn struct_fields<'de, T: Deserialize<'de>>() -> &'static [&'static str] {
+    use serde::de::{self, Visitor};
+    struct Probe<'a>(&'a mut Option<&'static [&'static str]>);
+    impl<'de> de::Deserializer<'de> for Probe<'_> {
+        type Error = de::value::Error;
+        fn deserialize_any<V: Visitor<'de>>(self, _v: V) -> Result<V::Value, Self::Error> {
+            Err(de::Error::custom("probe"))
+        }
+        fn deserialize_struct<V: Visitor<'de>>(self, _name: &'static str, fields: &'static [&'static str], _v: V) -> Result<V::Value, Self::Error> {
+            *self.0 = Some(fields);
+            Err(de::Error::custom("probe"))
+        }
+        serde::forward_to_deserialize_any! {
+            bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+            bytes byte_buf option unit unit_struct newtype_struct seq tuple
+            tuple_struct map enum identifier ignored_any
+        }
+    }
+    let mut fields = None;
+    let _ = T::deserialize(Probe(&mut fields));
+    fields.unwrap_or(&[])
+}
+
+fn known_fields() -> &'static HashSet<&'static str> {
+    static FIELDS: OnceLock<HashSet<&'static str>> = OnceLock::new();
+    FIELDS.get_or_init(|| struct_fields::<BridgeOut>().iter().copied().collect())
+}
+
+/// 数据帧中 BridgeOut 不认识的顶层字段
+pub fn unknown_fields(obj: &serde_json::Map<String, serde_json::Value>) -> Vec<String> {
+    let known = known_fields();
+    obj.keys().filter(|k| !known.contains(k.as_str())).cloned().collect()
+}
+
+fn apply_hello(s: &mut BridgeProtocolStatus, hello: BridgeHello) {
+    let compat = evaluate_compat(Some(&hello));
+    s.missing_capabilities = HOST_CAPABILITIES
+        .iter()
+        .filter(|c| !hello.capabilities.iter().any(|h| h == *c))
+        .map(|c| c.to_string())
+        .collect();
+    s.extra_capabilities = hello
+        .capabilities
+        .iter()
+        .filter(|c| !HOST_CAPABILITIES.contains(&c.as_str()))
+        .cloned()
+        .collect();
+    s.warning = warning_for(compat, Some(&hello), s.mode);
+    if let Some(w) = s.warning.as_ref() { eprintln!("[bridge] {}", w); }
+    eprintln!(
+        "[bridge] handshake: protocol v{} bridge {} caps={:?} compat={:?}",
+        hello.protocol_version,
+        hello.bridge_version.as_deref().unwrap_or("?"),
+        hello.capabilities,
+        compat
+    );
+    s.compat = compat;
+    s.handshake = Some(hello);
+}
+
+/// 解析桥接输出的一行并更新协议状态
+pub fn handle_line(line: &str) -> BridgeLine {
+    let value: serde_json::Value = match serde_json::from_str(line) {
+        Ok(v) => v,
+        Err(_) => {
+            with_status(|s| s.non_json_lines += 1);
+            return BridgeLine::NonJson;
+        }
+    };
+    let Some(obj) = value.as_object() else {
+        with_status(|s| s.non_json_lines += 1);
+        return BridgeLine::NonJson;
+    };
+    if obj.get("type").and_then(|t| t.as_str()) == Some("response") {
+        return match serde_json::from_value::<BridgeResponse>(value.clone()) {
+            Ok(resp) => BridgeLine::Response(resp),
+            Err(e) => {
+                let reason = format!("命令响应无效: {}", e);
+                with_status(|s| s.last_error = Some(reason.clone()));
+                BridgeLine::Rejected(reason)
+            }
+        };
+    }
+    if obj.get("type").and_then(|t| t.as_str()) == Some("hello") {
+        return match serde_json::from_value::<BridgeHello>(value.clone()) {
+            Ok(hello) => {
+                with_status(|s| apply_hello(s, hello.clone()));
+                BridgeLine::Hello(hello)
+            }
+            Err(e) => {
+                let reason = format!("握手消息无效: {}", e);
+                with_status(|s| { s.frames_rejected += 1; s.last_error = Some(reason.clone()); });
+                BridgeLine::Rejected(reason)
+            }
+        };
+    }
+
+    let unknown = unknown_fields(obj);
+    let parsed = serde_json::from_value::<BridgeOut>(value);
+    let reject = with_status(|s| {
+        // 首个数据帧前未收到握手：按旧版桥接处理
+        if s.compat == BridgeCompat::Unknown {
+            s.compat = BridgeCompat::Legacy;
+            s.warning = warning_for(s.compat, None, s.mode);
+        }
+        for k in unknown.iter() {
+            let n = s.unknown_fields.entry(k.clone()).or_insert(0);
+            if *n == 0 { eprintln!("[bridge] unknown field: {}", k); }
+            *n += 1;
+        }
+        let strict = s.mode == BridgeParseMode::Strict;
+        let reject = if !accepts(s.compat, s.mode) {
+            Some(format!("协议不兼容（{:?}）", s.compat))
+        } else if strict && !unknown.is_empty() {
+            Some(format!("未知字段: {}", unknown.join(",")))
+        } else {
+            parsed.as_ref().err().map(|e| format!("数据帧解析失败: {}", e))
+        };
+        match reject.as_ref() {
+            Some(reason) => { s.frames_rejected += 1; s.last_error = Some(reason.clone()); }
+            None => s.frames_ok += 1,
+        }
+        reject
+    })
+    .flatten();
+    match (reject, parsed) {
+        (None, Ok(out)) => BridgeLine::Frame(Box::new(out)),
+        (Some(reason), _) => BridgeLine::Rejected(reason),
+        (None, Err(e)) => BridgeLine::Rejected(e.to_string()),
+    }
+}
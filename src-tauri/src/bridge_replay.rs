@@ -0,0 +1,208 @@
+// 桥接输出录制与回放
+// 说明：
+// - 录制：桥接 stdout 的每一行（握手、数据帧、命令响应）连同接收时间追加到 JSON Lines 文件：{"ts":<毫秒时间戳>,"line":"<原始行>"}
+// - 录制文件超过 max_mb 后轮转为 <path>.1（仅保留一份）；录制配置在桥接进程（重新）启动时生效
+// - 回放：以录制文件代替桥接进程，按原始间隔（speed 倍速）把各行送回与真实桥接相同的处理流程，托盘/快照/告警均可在非 Windows 平台上运行
+// - 相邻两行的间隔超过 max_gap_ms 时按 max_gap_ms 计（录制中途暂停/休眠不会让回放卡住）
+// - loop=true 时播放完毕从头开始（重新握手）；否则停在最后一帧，数据随后按正常逻辑变为过期
+// - 回放配置在启动时读取，设置后不再启动真实桥接
+
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+
+use serde::{Deserialize, Serialize};
+
+/// 录制配置（AppConfig.bridge_record）
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Default)]
+pub struct BridgeRecordConfig {
+    // 总开关（设置 path 后默认启用）
+    pub enabled: Option<bool>,
+    // 录制文件路径（JSON Lines，追加写入）
+    pub path: Option<String>,
+    // 单个文件上限（MB，默认 100）
+    pub max_mb: Option<u64>,
+}
+
+/// 回放配置（AppConfig.bridge_replay）
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Default)]
+pub struct BridgeReplayConfig {
+    // 录制文件路径；为空时不回放
+    pub path: Option<String>,
+    // 倍速（默认 1.0 = 实时，范围 0.01..1000）
+    pub speed: Option<f64>,
+    // 播放完毕后是否从头循环（默认 false）
+    #[serde(rename = "loop")]
+    pub looped: Option<bool>,
+    // 相邻两行的最大间隔（毫秒，按录制时间计，默认 10000）
+    pub max_gap_ms: Option<u64>,
+}
+
+impl BridgeReplayConfig {
+    /// 已配置的回放文件
+    pub fn source(&self) -> Option<&str> {
+        self.path.as_deref().map(|p| p.trim()).filter(|p| !p.is_empty())
+    }
+
+    pub fn speed(&self) -> f64 {
+        self.speed.filter(|s| s.is_finite() && *s > 0.0).unwrap_or(1.0).clamp(0.01, 1000.0)
+    }
+}
+
+/// 录制文件中的一行
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct RecordedLine {
+    pub ts: i64,
+    pub line: String,
+}
+
+/// 录制/回放状态（供前端/调试读取）
+#[derive(Clone, Debug, Serialize, Deserialize, Default)]
+pub struct BridgeReplayStatus {
+    pub recording_path: Option<String>,
+    pub recorded_lines: u64,
+    pub record_error: Option<String>,
+    pub replay_path: Option<String>,
+    pub replay_speed: Option<f64>,
+    pub replay_total: u64,
+    // 当前轮已播放行数
+    pub replay_position: u64,
+    pub replay_loops: u64,
+    pub replay_finished: bool,
+    pub replay_error: Option<String>,
+}
+
+static STATUS: OnceLock<Mutex<BridgeReplayStatus>> = OnceLock::new();
+
+fn with_status<R, F: FnOnce(&mut BridgeReplayStatus) -> R>(f: F) -> Option<R> {
+    let cell = STATUS.get_or_init(|| Mutex::new(BridgeReplayStatus::default()));
+    cell.lock().ok().map(|mut g| f(&mut g))
+}
+
+/// Tauri命令：获取桥接录制/回放状态
+#[cfg_attr(feature = "desktop", tauri::command)]
+pub fn bridge_get_replay_status() -> BridgeReplayStatus {
+    with_status(|s| s.clone()).unwrap_or_default()
+}
+
+/// 回放开始
+pub fn begin_replay(path: &str, total: usize, speed: f64) {
+    with_status(|s| {
+        s.replay_path = Some(path.to_string());
+        s.replay_speed = Some(speed);
+        s.replay_total = total as u64;
+        s.replay_position = 0;
+        s.replay_loops = 0;
+        s.replay_finished = false;
+        s.replay_error = None;
+    });
+}
+
+/// 回放进度（loops 为已完成的轮数）
+pub fn replay_progress(position: usize, loops: u64) {
+    with_status(|s| {
+        s.replay_position = position as u64;
+        s.replay_loops = loops;
+    });
+}
+
+/// 回放结束（error 为空表示正常播放完毕或退出）
+pub fn end_replay(error: Option<String>) {
+    with_status(|s| {
+        s.replay_finished = true;
+        s.replay_error = error;
+    });
+}
+
+/// 录制写入失败（录制随即停止，不影响数据处理）
+pub fn record_failed(error: String) {
+    with_status(|s| s.record_error = Some(error));
+}
+
+/// 桥接输出录制器
+pub struct BridgeRecorder {
+    path: PathBuf,
+    file: File,
+    written: u64,
+    max_bytes: u64,
+}
+
+impl BridgeRecorder {
+    /// 按配置打开录制文件（未启用时返回 None）
+    pub fn open(cfg: &BridgeRecordConfig) -> Result<Option<Self>, String> {
+        let Some(path) = cfg.path.as_deref().map(|p| p.trim()).filter(|p| !p.is_empty()) else { return Ok(None) };
+        if !cfg.enabled.unwrap_or(true) { return Ok(None); }
+        let max_bytes = cfg.max_mb.unwrap_or(100).max(1).saturating_mul(1024 * 1024);
+        Self::create(Path::new(path), max_bytes).map(Some)
+    }
+
+    pub fn create(path: &Path, max_bytes: u64) -> Result<Self, String> {
+        if let Some(dir) = path.parent().filter(|d| !d.as_os_str().is_empty()) {
+            std::fs::create_dir_all(dir).map_err(|e| format!("创建录制目录失败: {}", e))?;
+        }
+        let file = OpenOptions::new().create(true).append(true).open(path).map_err(|e| format!("打开录制文件失败: {}", e))?;
+        let written = file.metadata().map(|m| m.len()).unwrap_or(0);
+        with_status(|s| {
+            s.recording_path = Some(path.display().to_string());
+            s.record_error = None;
+        });
+        Ok(Self { path: path.to_path_buf(), file, written, max_bytes })
+    }
+
+    /// 追加一行（超过上限先轮转）
+    pub fn record(&mut self, ts_ms: i64, line: &str) -> Result<(), String> {
+        let mut buf = serde_json::to_string(&RecordedLine { ts: ts_ms, line: line.to_string() }).map_err(|e| e.to_string())?;
+        buf.push('\n');
+        if self.written > 0 && self.written + buf.len() as u64 > self.max_bytes {
+            self.rotate()?;
+        }
+        self.file.write_all(buf.as_bytes()).map_err(|e| format!("写入录制文件失败: {}", e))?;
+        self.written += buf.len() as u64;
+        with_status(|s| s.recorded_lines += 1);
+        Ok(())
+    }
+
+    fn rotate(&mut self) -> Result<(), String> {
+        let mut rotated = self.path.clone().into_os_string();
+        rotated.push(".1");
+        let _ = std::fs::remove_file(&rotated);
+        std::fs::rename(&self.path, &rotated).map_err(|e| format!("轮转录制文件失败: {}", e))?;
+        self.file = OpenOptions::new().create(true).append(true).open(&self.path).map_err(|e| format!("打开录制文件失败: {}", e))?;
+        self.written = 0;
+        Ok(())
+    }
+}
+
+/// 读取录制文件（跳过无法解析的行）
+pub fn load_recording(path: &Path) -> Result<Vec<RecordedLine>, String> {
+    let file = File::open(path).map_err(|e| format!("打开回放文件失败 {}: {}", path.display(), e))?;
+    let mut out = Vec::new();
+    let mut skipped = 0u64;
+    for line in BufReader::new(file).lines().map_while(Result::ok) {
+        if line.trim().is_empty() { continue; }
+        match serde_json::from_str::<RecordedLine>(&line) {
+            Ok(rec) => out.push(rec),
+            Err(_) => skipped += 1,
+        }
+    }
+    if skipped > 0 {
+        eprintln!("[bridge] replay: skipped {} malformed lines in {}", skipped, path.display());
+    }
+    if out.is_empty() {
+        return Err(format!("回放文件为空: {}", path.display()));
+    }
+    Ok(out)
+}
+
+/// 每行播放前的等待（毫秒）：首行为 0，其余为录制间隔（限幅到 max_gap_ms）除以倍速
+pub fn replay_delays(lines: &[RecordedLine], speed: f64, max_gap_ms: u64) -> Vec<u64> {
+    let mut out = Vec::with_capacity(lines.len());
+    let mut prev: Option<i64> = None;
+    for rec in lines {
+        let gap = prev.map(|p| (rec.ts - p).clamp(0, max_gap_ms as i64)).unwrap_or(0);
+        out.push((gap as f64 / speed).round() as u64);
+        prev = Some(rec.ts);
+    }
+    out
+}
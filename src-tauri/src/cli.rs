@@ -0,0 +1,751 @@
+// 命令行工具（sys-sensor-cli），便于通过 SSH 诊断机器
+// 说明：
+// - snapshot：一次性输出 SensorSnapshot（表格或 --json）
+// - watch：刷新终端视图，持续显示选定指标
+// - smart：磁盘 SMART 健康报告
+// - rtt：对临时目标执行多目标 RTT 测量（measure_multi_rtt）
+// - history：查询已存储的历史序列（来自运行中的 agent / 桌面版本地 API）
+// - outages：网络中断时间线（读取配置目录下的 outages.jsonl；指定 --api 时含进行中的中断），--csv 导出用于向运营商报障
+// - token：管理本地 API Token（直接读写配置目录下的 api_tokens.json，无需运行中的实例，供 sys-sensor-agent 部署使用）
+// - snapshot / watch / smart 默认在本进程内采集（不启动 sensor-bridge）；指定 --api 时改为读取运行中的实例
+// - 不依赖 tauri：cargo build --no-default-features --bin sys-sensor-cli
+
+use std::collections::BTreeSet;
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::agent::{resolve_config_dir, AgentOptions};
+use crate::api_auth::{ApiTokenInfo, Scope};
+use crate::collector::{start_collector, CollectorContext, CollectorHooks};
+use crate::config_utils::{AppConfig, PublicNetInfo};
+use crate::connectivity::Outage;
+use crate::metrics_utils::{metric_meta, snapshot_scalar_fields};
+use crate::ping_utils::{FamilyPref, ProbeDefaults, RttTarget};
+use crate::process_utils::RttResultPayload;
+use crate::state_store::{HistoryPoint, StateStore};
+use crate::types::{SensorSnapshot, SmartHealthPayload};
+
+/// API Token 环境变量（避免在命令行历史中留下明文）
+pub const TOKEN_ENV: &str = "SYS_SENSOR_TOKEN";
+
+// 本地一次性采集：取第 N 个 tick 的快照（首个 tick 仅建立速率基线）
+const LOCAL_SAMPLE_TICKS: usize = 3;
+const LOCAL_SAMPLE_INTERVAL_MS: u64 = 500;
+// watch 默认指标
+const WATCH_DEFAULT_METRICS: [&str; 8] = [
+    "cpu_usage", "mem_pct", "net_rx_bps", "net_tx_bps", "disk_r_bps", "disk_w_bps", "cpu_temp_c", "ping_rtt_ms",
+];
+
+pub const USAGE: &str = "用法: sys-sensor-cli <子命令> [选项]
+
+子命令:
+  snapshot             输出一次完整快照
+  watch                刷新终端视图（Ctrl+C 退出）
+  smart                磁盘 SMART 健康报告
+  rtt [目标...]        多目标 RTT 测量（目标形如 host:port；缺省使用配置中的 rtt_targets）
+  history              查询历史序列（需运行中的 agent / 桌面版开启本地 API）
+  outages              网络中断时间线（开始/结束/时长/状态/疑似原因）
+  token create <名称>  创建本地 API Token（明文只显示一次）
+  token list           列出 API Token
+  token revoke <id>    吊销 API Token
+
+通用选项:
+  --json               以 JSON 输出
+  --csv                以 CSV 输出（outages）
+  --api <URL>          从运行中的实例读取（如 http://127.0.0.1:18730）；history 缺省取配置中的 api
+  --token <TOKEN>      API Token（亦可用环境变量 SYS_SENSOR_TOKEN）
+  --config-dir <DIR>   配置目录（默认与桌面版相同）
+  -m, --metrics <a,b>  指标子集（snapshot / watch / history）
+  -i, --interval <MS>  watch 刷新间隔（默认 1000）
+  --since <T>          history / outages 起始：毫秒时间戳或相对时间（如 -10m、-2h、-30s、-1d）
+  --until <T>          history / outages 结束（格式同 --since）
+  -n, --limit <N>      history 最多返回点数（默认 60）；outages 最多返回条数（默认 200）
+  --timeout <MS>       rtt 单目标超时（缺省取配置 rtt_timeout_ms，默认 300）
+  -c, --count <N>      rtt 每目标探测包数（缺省取配置 rtt_probe_count，默认 5）
+  --probe-interval <MS> rtt 相邻探测包间隔（缺省取配置 rtt_probe_interval_ms，默认 200）
+  --family <F>         rtt 地址族：auto / v4 / v6 / both / happy_eyeballs（缺省取配置 rtt_family）
+  --scopes <a,b>       token create 作用域：metrics:read / config:read / config:write / tasks:trigger / speedtest（默认 metrics:read）
+  --ttl-days <N>       token create 有效天数（默认永不过期）
+  -h, --help           显示本帮助";
+
+/// 子命令
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum CliCommand {
+    #[default]
+    Help,
+    Snapshot,
+    Watch,
+    Smart,
+    Rtt,
+    History,
+    Outages,
+    Token,
+}
+
+/// token 子命令动作
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TokenAction {
+    Create(String),
+    List,
+    Revoke(String),
+}
+
+/// 命令行选项
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct CliOptions {
+    pub command: CliCommand,
+    pub json: bool,
+    pub csv: bool,
+    pub api: Option<String>,
+    pub token: Option<String>,
+    pub config_dir: Option<PathBuf>,
+    pub metrics: Vec<String>,
+    pub interval_ms: Option<u64>,
+    pub since: Option<String>,
+    pub until: Option<String>,
+    pub limit: Option<usize>,
+    pub timeout_ms: Option<u64>,
+    pub probe_count: Option<u32>,
+    pub probe_interval_ms: Option<u64>,
+    pub family: Option<FamilyPref>,
+    pub targets: Vec<String>,
+    pub token_action: Option<TokenAction>,
+    pub scopes: Vec<Scope>,
+    pub ttl_days: Option<u64>,
+}
+
+/// 解析命令行参数（不含程序名）；长选项同时支持 `--key value` 与 `--key=value`
+pub fn parse_args<I: IntoIterator<Item = String>>(args: I) -> Result<CliOptions, String> {
+    let mut opts = CliOptions::default();
+    let mut command: Option<CliCommand> = None;
+    let mut help = false;
+    let mut token_args: Vec<String> = Vec::new();
+    let mut it = args.into_iter();
+    while let Some(raw) = it.next() {
+        let (flag, inline) = match raw.split_once('=') {
+            Some((k, v)) if raw.starts_with("--") => (k.to_string(), Some(v.to_string())),
+            _ => (raw.clone(), None),
+        };
+        let mut value = |name: &str| -> Result<String, String> {
+            match inline.clone() {
+                Some(v) => Ok(v),
+                None => it.next().ok_or_else(|| format!("{} 需要一个参数", name)),
+            }
+        };
+        let number = |name: &str, v: String| -> Result<u64, String> {
+            v.trim().parse::<u64>().map_err(|_| format!("{} 需要数字，实际为 {}", name, v))
+        };
+        match flag.as_str() {
+            "-h" | "--help" => help = true,
+            "--json" => opts.json = true,
+            "--csv" => opts.csv = true,
+            "--api" => opts.api = Some(value("--api")?),
+            "--token" => opts.token = Some(value("--token")?),
+            "--config-dir" => opts.config_dir = Some(PathBuf::from(value("--config-dir")?)),
+            "-m" | "--metrics" => {
+                opts.metrics = value("--metrics")?
+                    .split(',')
+                    .map(|s| s.trim().to_string())
+                    .filter(|s| !s.is_empty())
+                    .collect();
+            }
+            "-i" | "--interval" => opts.interval_ms = Some(number("--interval", value("--interval")?)?.max(200)),
+            "--since" => opts.since = Some(value("--since")?),
+            "--until" => opts.until = Some(value("--until")?),
+            "-n" | "--limit" => opts.limit = Some(number("--limit", value("--limit")?)? as usize),
+            "--timeout" => opts.timeout_ms = Some(number("--timeout", value("--timeout")?)?),
+            "-c" | "--count" => opts.probe_count = Some(number("--count", value("--count")?)?.clamp(1, 100) as u32),
+            "--probe-interval" => opts.probe_interval_ms = Some(number("--probe-interval", value("--probe-interval")?)?),
+            "--family" => {
+                let v = value("--family")?;
+                opts.family = Some(serde_json::from_value(serde_json::Value::String(v.clone())).map_err(|_| format!("--family 取值无效: {}", v))?);
+            }
+            "--scopes" => {
+                for v in value("--scopes")?.split(',').map(|s| s.trim()).filter(|s| !s.is_empty()) {
+                    opts.scopes.push(serde_json::from_value(serde_json::Value::String(v.to_string())).map_err(|_| format!("--scopes 取值无效: {}", v))?);
+                }
+            }
+            "--ttl-days" => opts.ttl_days = Some(number("--ttl-days", value("--ttl-days")?)?),
+            s if s.starts_with('-') && s.len() > 1 && !s[1..].starts_with(|c: char| c.is_ascii_digit()) => {
+                return Err(format!("未知选项: {}", s));
+            }
+            _ if command.is_none() => {
+                command = Some(match raw.as_str() {
+                    "snapshot" => CliCommand::Snapshot,
+                    "watch" => CliCommand::Watch,
+                    "smart" => CliCommand::Smart,
+                    "rtt" => CliCommand::Rtt,
+                    "history" => CliCommand::History,
+                    "outages" => CliCommand::Outages,
+                    "token" => CliCommand::Token,
+                    "help" => CliCommand::Help,
+                    other => return Err(format!("未知子命令: {}", other)),
+                });
+            }
+            _ if command == Some(CliCommand::Rtt) => opts.targets.push(raw),
+            _ if command == Some(CliCommand::Token) => token_args.push(raw),
+            _ => return Err(format!("多余的参数: {}", raw)),
+        }
+    }
+    // -h 优先于子命令
+    opts.command = if help { CliCommand::Help } else { command.unwrap_or_default() };
+    if opts.command == CliCommand::Token {
+        opts.token_action = Some(match token_args.iter().map(|s| s.as_str()).collect::<Vec<_>>().as_slice() {
+            ["create", name] if !name.trim().is_empty() => TokenAction::Create(name.to_string()),
+            ["list"] => TokenAction::List,
+            ["revoke", id] => TokenAction::Revoke(id.to_string()),
+            _ => return Err("token 用法: token create <名称> [--scopes a,b] [--ttl-days N] | token list | token revoke <id>".into()),
+        });
+    }
+    if opts.token.is_none() {
+        opts.token = std::env::var(TOKEN_ENV).ok().filter(|t| !t.trim().is_empty());
+    }
+    Ok(opts)
+}
+
+/// 解析时间参数：毫秒时间戳，或相对当前的负偏移（-30s / -10m / -2h / -1d）
+pub fn parse_time_arg(s: &str, now_ms: i64) -> Result<i64, String> {
+    let s = s.trim();
+    if let Some(rel) = s.strip_prefix('-') {
+        let (num, unit_ms) = match rel.chars().last() {
+            Some('s') => (&rel[..rel.len() - 1], 1_000),
+            Some('m') => (&rel[..rel.len() - 1], 60_000),
+            Some('h') => (&rel[..rel.len() - 1], 3_600_000),
+            Some('d') => (&rel[..rel.len() - 1], 86_400_000),
+            _ => (rel, 1),
+        };
+        let n: i64 = num.parse().map_err(|_| format!("无法解析时间: {}", s))?;
+        return Ok(now_ms - n * unit_ms);
+    }
+    s.parse::<i64>().map_err(|_| format!("无法解析时间: {}", s))
+}
+
+// ---- 格式化 ----
+
+fn format_bytes_rate(bps: f64) -> String {
+    if bps >= 1024.0 * 1024.0 {
+        format!("{:.1} MB/s", bps / (1024.0 * 1024.0))
+    } else if bps >= 1024.0 {
+        format!("{:.1} KB/s", bps / 1024.0)
+    } else {
+        format!("{:.0} B/s", bps)
+    }
+}
+
+/// 按指标单位格式化数值
+pub fn format_metric(key: &str, v: f64) -> String {
+    match metric_meta(key).and_then(|m| m.unit) {
+        Some("B/s") => format_bytes_rate(v),
+        Some("%") => format!("{:.1}%", v),
+        Some("°C") => format!("{:.1}°C", v),
+        Some("GB") => format!("{:.2} GB", v),
+        Some("ms") => format!("{:.1} ms", v),
+        Some(unit) if v.fract() == 0.0 => format!("{:.0} {}", v, unit),
+        Some(unit) => format!("{:.1} {}", v, unit),
+        None if v.fract() == 0.0 => format!("{:.0}", v),
+        None => format!("{:.2}", v),
+    }
+}
+
+// 终端显示宽度（CJK 等全角字符按 2 列计）
+fn display_width(s: &str) -> usize {
+    s.chars().map(|c| if (c as u32) >= 0x1100 && !matches!(c, '°' | '—') { 2 } else { 1 }).sum()
+}
+
+fn pad(s: &str, width: usize) -> String {
+    let w = display_width(s);
+    if w >= width { s.to_string() } else { format!("{}{}", s, " ".repeat(width - w)) }
+}
+
+/// 渲染对齐表格（首行为表头）
+fn render_table(header: &[String], rows: &[Vec<String>]) -> String {
+    let cols = header.len();
+    let mut widths: Vec<usize> = header.iter().map(|h| display_width(h)).collect();
+    for r in rows {
+        for (i, c) in r.iter().enumerate().take(cols) {
+            widths[i] = widths[i].max(display_width(c));
+        }
+    }
+    let line = |cells: &[String]| -> String {
+        cells.iter().enumerate().map(|(i, c)| pad(c, widths[i])).collect::<Vec<_>>().join("  ").trim_end().to_string()
+    };
+    let mut out = String::new();
+    out.push_str(&line(header));
+    out.push('\n');
+    out.push_str(&widths.iter().map(|w| "-".repeat(*w)).collect::<Vec<_>>().join("  "));
+    out.push('\n');
+    for r in rows {
+        out.push_str(&line(r));
+        out.push('\n');
+    }
+    out
+}
+
+fn format_ts(ts_ms: i64) -> String {
+    use chrono::TimeZone;
+    chrono::Local
+        .timestamp_millis_opt(ts_ms)
+        .single()
+        .map(|t| t.format("%Y-%m-%d %H:%M:%S").to_string())
+        .unwrap_or_else(|| ts_ms.to_string())
+}
+
+/// 快照表格：指标键 / 名称 / 数值；未指定指标时附带公网与 Wi‑Fi 文本信息
+pub fn render_snapshot_table(s: &SensorSnapshot, metrics: &[String]) -> String {
+    let rows: Vec<Vec<String>> = snapshot_scalar_fields(s)
+        .into_iter()
+        .filter(|(k, _)| metrics.is_empty() || metrics.iter().any(|m| m == k))
+        .map(|(k, v)| {
+            let name = metric_meta(k).map(|m| m.name).unwrap_or("");
+            vec![k.to_string(), name.to_string(), format_metric(k, v)]
+        })
+        .collect();
+    let mut out = format!("采样时间: {}\n", format_ts(s.timestamp_ms));
+    out.push_str(&render_table(&["指标".into(), "名称".into(), "数值".into()], &rows));
+    if metrics.is_empty() {
+        let texts = [
+            ("公网 IP", s.public_ip.as_deref()),
+            ("运营商", s.isp.as_deref()),
+            ("Wi‑Fi", s.wifi_ssid.as_deref()),
+        ];
+        for (label, v) in texts.iter() {
+            if let Some(v) = v { out.push_str(&format!("{}: {}\n", label, v)); }
+        }
+    }
+    out
+}
+
+/// 历史表格：时间列 + 各指标列（未指定指标时取所有出现过的指标）
+pub fn render_history_table(points: &[HistoryPoint], metrics: &[String]) -> String {
+    let keys: Vec<String> = if metrics.is_empty() {
+        let set: BTreeSet<&String> = points.iter().flat_map(|p| p.values.keys()).collect();
+        set.into_iter().cloned().collect()
+    } else {
+        metrics.to_vec()
+    };
+    let mut header = vec!["时间".to_string()];
+    header.extend(keys.iter().cloned());
+    let rows: Vec<Vec<String>> = points
+        .iter()
+        .map(|p| {
+            let mut r = vec![format_ts(p.timestamp_ms)];
+            r.extend(keys.iter().map(|k| p.values.get(k).map(|v| format_metric(k, *v)).unwrap_or_else(|| "—".into())));
+            r
+        })
+        .collect();
+    render_table(&header, &rows)
+}
+
+/// SMART 健康报告表格
+pub fn render_smart_table(list: &[SmartHealthPayload]) -> String {
+    let opt = |v: Option<String>| v.unwrap_or_else(|| "—".into());
+    let rows: Vec<Vec<String>> = list
+        .iter()
+        .map(|d| {
+            let status = match d.predict_fail {
+                Some(true) => "预警".to_string(),
+                Some(false) => "正常".to_string(),
+                None => "未知".to_string(),
+            };
+            vec![
+                opt(d.device.clone()),
+                opt(d.drive_letter.clone()),
+                status,
+                opt(d.temp_c.map(|t| format!("{:.0}°C", t))),
+                opt(d.power_on_hours.map(|h| h.to_string())),
+                opt(d.reallocated.map(|v| v.to_string())),
+                opt(d.pending.map(|v| v.to_string())),
+                opt(d.uncorrectable.map(|v| v.to_string())),
+                opt(d.nvme_percentage_used_pct.map(|v| format!("{:.0}%", v))),
+                opt(d.nvme_available_spare_pct.map(|v| format!("{:.0}%", v))),
+            ]
+        })
+        .collect();
+    let header: Vec<String> = ["设备", "盘符", "状态", "温度", "通电小时", "重映射", "待映射", "不可纠正", "NVMe磨损", "可用备用"]
+        .iter().map(|s| s.to_string()).collect();
+    render_table(&header, &rows)
+}
+
+/// RTT 结果表格
+pub fn render_rtt_table(results: &[RttResultPayload]) -> String {
+    let rows: Vec<Vec<String>> = results
+        .iter()
+        .map(|r| {
+            let ok = r.success.unwrap_or(false);
+            let ms = |v: Option<f64>| v.map(|v| format!("{:.1} ms", v)).unwrap_or_else(|| "—".into());
+            vec![
+                match r.name.as_ref() {
+                    Some(n) => format!("{} ({})", n, r.target),
+                    None => r.target.clone(),
+                },
+                match (r.method.as_ref(), r.status) {
+                    (Some(m), Some(code)) => format!("{} {}", m, code),
+                    (Some(m), None) => m.clone(),
+                    (None, _) => "—".into(),
+                },
+                match (r.addr.as_ref(), r.family.as_ref()) {
+                    (Some(a), _) => a.clone(),
+                    (None, Some(f)) => format!("({})", f),
+                    (None, None) => "—".into(),
+                },
+                if ok { "成功".into() } else { "失败".into() },
+                ms(r.rtt_ms),
+                ms(r.min_ms),
+                ms(r.max_ms),
+                ms(r.p95_ms),
+                ms(r.jitter_ms),
+                match (r.received, r.sent, r.loss_pct) {
+                    (Some(rx), Some(tx), Some(l)) => format!("{:.0}% ({}/{})", l, rx, tx),
+                    _ => "—".into(),
+                },
+                r.ttl.map(|v| v.to_string()).unwrap_or_else(|| "—".into()),
+            ]
+        })
+        .collect();
+    let header: Vec<String> = ["目标", "方式", "地址", "结果", "平均", "最小", "最大", "P95", "抖动", "丢包", "TTL"]
+        .iter().map(|s| s.to_string()).collect();
+    render_table(&header, &rows)
+}
+
+/// 中断时间线表格（新记录在前；进行中的中断结束时间显示为“进行中”）
+pub fn render_outage_table(list: &[Outage]) -> String {
+    let rows: Vec<Vec<String>> = list
+        .iter()
+        .map(|o| {
+            vec![
+                format_ts(o.start_ms),
+                o.end_ms.map(format_ts).unwrap_or_else(|| "进行中".into()),
+                o.duration_ms.map(|d| format_duration(d / 1000)).unwrap_or_else(|| "—".into()),
+                o.state.label().to_string(),
+                o.cause.clone().unwrap_or_else(|| "—".into()),
+            ]
+        })
+        .collect();
+    let header: Vec<String> = ["开始", "结束", "时长", "状态", "疑似原因"].iter().map(|s| s.to_string()).collect();
+    render_table(&header, &rows)
+}
+
+fn format_duration(secs: i64) -> String {
+    match secs {
+        s if s < 60 => format!("{}s", s),
+        s if s < 3600 => format!("{}m{:02}s", s / 60, s % 60),
+        s => format!("{}h{:02}m", s / 3600, s % 3600 / 60),
+    }
+}
+
+// ---- 数据来源 ----
+
+fn load_cli_config(opts: &CliOptions) -> AppConfig {
+    let dir = resolve_config_dir(&AgentOptions { config_dir: opts.config_dir.clone(), ..Default::default() });
+    crate::config_utils::load_config_from(&dir.join("config.json"))
+}
+
+/// 规范化 API 地址：--api 优先；否则（仅 history）取配置中已启用的本地 API
+fn api_base(opts: &CliOptions, cfg: Option<&AppConfig>) -> Option<String> {
+    if let Some(a) = opts.api.as_ref() {
+        let a = a.trim().trim_end_matches('/');
+        return Some(if a.contains("://") { a.to_string() } else { format!("http://{}", a) });
+    }
+    let api = cfg?.api.as_ref().filter(|a| a.enabled)?;
+    let bind = api.bind.clone().unwrap_or_else(|| "127.0.0.1".into());
+    let host = if bind == "0.0.0.0" { "127.0.0.1".to_string() } else if bind == "::" { "[::1]".to_string() } else { bind };
+    let scheme = if api.tls.is_some() { "https" } else { "http" };
+    Some(format!("{}://{}:{}", scheme, host, api.port.unwrap_or(18730)))
+}
+
+fn api_get(base: &str, path_and_query: &str, token: Option<&str>) -> Result<serde_json::Value, String> {
+    let url = format!("{}{}", base, path_and_query);
+    let mut req = ureq::get(&url).timeout(Duration::from_secs(10));
+    if let Some(t) = token {
+        req = req.set("Authorization", &format!("Bearer {}", t));
+    }
+    match req.call() {
+        Ok(resp) => resp.into_json::<serde_json::Value>().map_err(|e| format!("解析响应失败: {}", e)),
+        Err(ureq::Error::Status(code, resp)) => {
+            Err(format!("{} 返回 HTTP {}: {}", url, code, resp.into_string().unwrap_or_default().trim()))
+        }
+        Err(e) => Err(format!("请求 {} 失败: {}", url, e)),
+    }
+}
+
+/// 通过本地 API 查询历史序列
+pub fn fetch_history(
+    base: &str,
+    token: Option<&str>,
+    metrics: &[String],
+    since_ms: Option<i64>,
+    until_ms: Option<i64>,
+    limit: usize,
+) -> Result<Vec<HistoryPoint>, String> {
+    let mut q = vec![format!("limit={}", limit)];
+    if !metrics.is_empty() { q.push(format!("metrics={}", metrics.join(","))); }
+    if let Some(s) = since_ms { q.push(format!("since={}", s)); }
+    if let Some(u) = until_ms { q.push(format!("until={}", u)); }
+    let v = api_get(base, &format!("/api/history?{}", q.join("&")), token)?;
+    serde_json::from_value(v.get("points").cloned().unwrap_or_default()).map_err(|e| format!("历史数据格式错误: {}", e))
+}
+
+/// 在本进程内运行采样循环（无 Sink、无桥接），每个快照交给回调
+fn start_local_collector(cfg: AppConfig, interval_ms: u64, on_snapshot: impl Fn(&SensorSnapshot) + Send + 'static) -> (Arc<AtomicBool>, std::thread::JoinHandle<()>) {
+    let cfg = AppConfig { interval_ms: Some(interval_ms), ..cfg };
+    let shutdown = Arc::new(AtomicBool::new(false));
+    let (_ctrl_tx, ctrl_rx) = mpsc::channel();
+    let handle = start_collector(
+        CollectorContext {
+            config: Arc::new(Mutex::new(cfg)),
+            scheduler: Arc::new(Mutex::new(Default::default())),
+            state_store: Arc::new(Mutex::new(StateStore::new())),
+            public_net: Arc::new(Mutex::new(PublicNetInfo::default())),
+            bridge_data: Arc::new(Mutex::new((None, Instant::now()))),
+            influx: None,
+            mqtt: None,
+            notifier: None,
+            ctrl_rx,
+            shutdown: shutdown.clone(),
+        },
+        CollectorHooks { on_snapshot: Some(Box::new(on_snapshot)), ..Default::default() },
+    );
+    (shutdown, handle)
+}
+
+fn collect_local_snapshot(cfg: AppConfig) -> Result<SensorSnapshot, String> {
+    let (tx, rx) = mpsc::channel::<SensorSnapshot>();
+    let tx = Mutex::new(tx);
+    let (shutdown, handle) = start_local_collector(cfg, LOCAL_SAMPLE_INTERVAL_MS, move |s| {
+        if let Ok(tx) = tx.lock() { let _ = tx.send(s.clone()); }
+    });
+    let mut last = None;
+    for _ in 0..LOCAL_SAMPLE_TICKS {
+        match rx.recv_timeout(Duration::from_secs(30)) {
+            Ok(s) => last = Some(s),
+            Err(_) => break,
+        }
+    }
+    shutdown.store(true, Ordering::Relaxed);
+    drop(rx);
+    let _ = handle.join();
+    last.ok_or_else(|| "采集超时，未获得快照".to_string())
+}
+
+fn fetch_snapshot(opts: &CliOptions) -> Result<SensorSnapshot, String> {
+    match api_base(opts, None) {
+        Some(base) => {
+            let v = api_get(&base, "/api/snapshot", opts.token.as_deref())?;
+            if v.is_null() { return Err("实例尚未产生快照".into()); }
+            serde_json::from_value(v).map_err(|e| format!("快照格式错误: {}", e))
+        }
+        None => collect_local_snapshot(load_cli_config(opts)),
+    }
+}
+
+fn print_json<T: serde::Serialize>(v: &T) -> Result<(), String> {
+    println!("{}", serde_json::to_string_pretty(v).map_err(|e| e.to_string())?);
+    Ok(())
+}
+
+fn render_watch_frame(s: &SensorSnapshot, metrics: &[String]) -> String {
+    // 清屏并回到左上角
+    format!("\x1b[2J\x1b[H{}\n(Ctrl+C 退出)\n", render_snapshot_table(s, metrics))
+}
+
+// ---- 子命令 ----
+
+fn cmd_watch(opts: &CliOptions) -> Result<(), String> {
+    let metrics: Vec<String> = if opts.metrics.is_empty() {
+        WATCH_DEFAULT_METRICS.iter().map(|s| s.to_string()).collect()
+    } else {
+        opts.metrics.clone()
+    };
+    let interval_ms = opts.interval_ms.unwrap_or(1000);
+    match api_base(opts, None) {
+        Some(base) => loop {
+            let frame = match api_get(&base, "/api/snapshot", opts.token.as_deref())
+                .and_then(|v| serde_json::from_value::<SensorSnapshot>(v).map_err(|e| e.to_string()))
+            {
+                Ok(s) => render_watch_frame(&s, &metrics),
+                Err(e) => format!("\x1b[2J\x1b[H读取失败: {}\n", e),
+            };
+            print!("{}", frame);
+            let _ = std::io::stdout().flush();
+            std::thread::sleep(Duration::from_millis(interval_ms));
+        },
+        None => {
+            let (_shutdown, handle) = start_local_collector(load_cli_config(opts), interval_ms, move |s| {
+                print!("{}", render_watch_frame(s, &metrics));
+                let _ = std::io::stdout().flush();
+            });
+            let _ = handle.join();
+            Ok(())
+        }
+    }
+}
+
+fn cmd_smart(opts: &CliOptions) -> Result<(), String> {
+    let list: Vec<SmartHealthPayload> = match api_base(opts, None) {
+        Some(base) => {
+            let v = api_get(&base, "/api/smart", opts.token.as_deref())?;
+            serde_json::from_value(v.get("smart").cloned().unwrap_or_default()).unwrap_or_default()
+        }
+        None => crate::collector::query_smart_health().unwrap_or_default(),
+    };
+    if opts.json { return print_json(&list); }
+    if list.is_empty() {
+        println!("未获取到 SMART 数据（可能需要管理员权限或 smartctl）");
+        return Ok(());
+    }
+    print!("{}", render_smart_table(&list));
+    Ok(())
+}
+
+fn cmd_rtt(opts: &CliOptions) -> Result<(), String> {
+    let cfg = load_cli_config(opts);
+    let mut targets = if opts.targets.is_empty() {
+        crate::ping_utils::targets_from_config(&cfg)
+    } else {
+        opts.targets.iter().map(|t| RttTarget::new(t)).collect()
+    };
+    // 命令行参数优先于全局默认与目标级配置
+    let mut defaults = ProbeDefaults::from_config(&cfg);
+    if let Some(family) = opts.family { defaults.family = family; }
+    if let Some(ms) = opts.timeout_ms { defaults.timeout_ms = ms; }
+    if let Some(n) = opts.probe_count { defaults.count = n; }
+    if let Some(ms) = opts.probe_interval_ms { defaults.interval_ms = ms; }
+    for t in targets.iter_mut() {
+        if opts.family.is_some() { t.family = None; }
+        if opts.timeout_ms.is_some() { t.timeout_ms = None; }
+        if opts.probe_count.is_some() { t.count = None; }
+        if opts.probe_interval_ms.is_some() { t.interval_ms = None; }
+    }
+    let results = crate::ping_utils::measure_multi_rtt(&targets, &defaults);
+    if opts.json { return print_json(&results); }
+    print!("{}", render_rtt_table(&results));
+    Ok(())
+}
+
+fn cmd_history(opts: &CliOptions) -> Result<(), String> {
+    let cfg = load_cli_config(opts);
+    let base = api_base(opts, Some(&cfg))
+        .ok_or_else(|| "history 需要运行中的实例：请开启本地 API（api.enabled）或通过 --api 指定地址".to_string())?;
+    let now = chrono::Local::now().timestamp_millis();
+    let since = opts.since.as_deref().map(|s| parse_time_arg(s, now)).transpose()?;
+    let until = opts.until.as_deref().map(|s| parse_time_arg(s, now)).transpose()?;
+    let points = fetch_history(&base, opts.token.as_deref(), &opts.metrics, since, until, opts.limit.unwrap_or(60))?;
+    if opts.json { return print_json(&points); }
+    if points.is_empty() {
+        println!("没有匹配的历史数据");
+        return Ok(());
+    }
+    print!("{}", render_history_table(&points, &opts.metrics));
+    Ok(())
+}
+
+fn cmd_outages(opts: &CliOptions) -> Result<(), String> {
+    let now = chrono::Local::now().timestamp_millis();
+    let since = opts.since.as_deref().map(|s| parse_time_arg(s, now)).transpose()?;
+    let until = opts.until.as_deref().map(|s| parse_time_arg(s, now)).transpose()?;
+    let limit = opts.limit.unwrap_or(200);
+    // 指定 --api 时读取运行中的实例（含进行中的中断）；否则直接读取本地中断日志
+    let list: Vec<Outage> = match api_base(opts, None) {
+        Some(base) => {
+            let mut q = vec![format!("limit={}", limit)];
+            if let Some(s) = since { q.push(format!("since={}", s)); }
+            if let Some(u) = until { q.push(format!("until={}", u)); }
+            let v = api_get(&base, &format!("/api/outages?{}", q.join("&")), opts.token.as_deref())?;
+            serde_json::from_value(v.get("outages").cloned().unwrap_or_default()).map_err(|e| format!("中断记录格式错误: {}", e))?
+        }
+        None => {
+            let dir = resolve_config_dir(&AgentOptions { config_dir: opts.config_dir.clone(), ..Default::default() });
+            let max = crate::config_utils::load_config_from(&dir.join("config.json")).connectivity_journal_max_entries;
+            let all = crate::connectivity::read_entries(&crate::connectivity::journal_path(&dir), max);
+            crate::connectivity::filter_outages(all, since, until, limit)
+        }
+    };
+    if opts.csv {
+        print!("{}", crate::connectivity::export_csv(&list));
+        return Ok(());
+    }
+    if opts.json { return print_json(&list); }
+    if list.is_empty() {
+        println!("没有匹配的中断记录");
+        return Ok(());
+    }
+    print!("{}", render_outage_table(&list));
+    Ok(())
+}
+
+/// Token 列表表格
+pub fn render_token_table(list: &[ApiTokenInfo]) -> String {
+    let rows: Vec<Vec<String>> = list
+        .iter()
+        .map(|t| {
+            vec![
+                t.id.clone(),
+                t.name.clone(),
+                t.scopes.iter().map(|s| s.as_str()).collect::<Vec<_>>().join(","),
+                format_ts(t.created_ms),
+                t.expires_ms.map(format_ts).unwrap_or_else(|| "永不".into()),
+            ]
+        })
+        .collect();
+    let header: Vec<String> = ["ID", "名称", "作用域", "创建时间", "过期时间"].iter().map(|s| s.to_string()).collect();
+    render_table(&header, &rows)
+}
+
+fn cmd_token(opts: &CliOptions) -> Result<(), String> {
+    let dir = resolve_config_dir(&AgentOptions { config_dir: opts.config_dir.clone(), ..Default::default() });
+    match opts.token_action.as_ref() {
+        Some(TokenAction::Create(name)) => {
+            let created = crate::api_auth::create_token(&dir, name, opts.scopes.clone(), opts.ttl_days)?;
+            if opts.json { return print_json(&created); }
+            println!("已创建 Token {}（{}）", created.id, created.scopes.iter().map(|s| s.as_str()).collect::<Vec<_>>().join(","));
+            println!("{}", created.token);
+            eprintln!("明文只显示这一次，请妥善保存");
+            Ok(())
+        }
+        Some(TokenAction::List) => {
+            let list = crate::api_auth::list_tokens(&dir)?;
+            if opts.json { return print_json(&list); }
+            if list.is_empty() {
+                println!("没有 API Token（{}）", dir.display());
+                return Ok(());
+            }
+            print!("{}", render_token_table(&list));
+            Ok(())
+        }
+        Some(TokenAction::Revoke(id)) => {
+            if !crate::api_auth::revoke_token(&dir, id)? {
+                return Err(format!("Token 不存在: {}", id));
+            }
+            if opts.json { return print_json(&serde_json::json!({ "revoked": id })); }
+            println!("已吊销 Token {}", id);
+            Ok(())
+        }
+        None => Err("缺少 token 动作".into()),
+    }
+}
+
+/// 执行子命令
+pub fn run(opts: CliOptions) -> Result<(), String> {
+    match opts.command {
+        CliCommand::Help => {
+            println!("{}", USAGE);
+            Ok(())
+        }
+        CliCommand::Snapshot => {
+            let snap = fetch_snapshot(&opts)?;
+            if opts.json { return print_json(&snap); }
+            print!("{}", render_snapshot_table(&snap, &opts.metrics));
+            Ok(())
+        }
+        CliCommand::Watch => cmd_watch(&opts),
+        CliCommand::Smart => cmd_smart(&opts),
+        CliCommand::Rtt => cmd_rtt(&opts),
+        CliCommand::History => cmd_history(&opts),
+        CliCommand::Outages => cmd_outages(&opts),
+        CliCommand::Token => cmd_token(&opts),
+    }
+}
@@ -4,6 +4,7 @@
 use serde::{Deserialize, Serialize};
 use crate::scheduler::SchedulerState;
 use crate::state_store::{StateStore, TickTelemetry, Aggregated};
+#[cfg(feature = "desktop")]
 use tauri::{AppHandle, Manager};
 use std::path::PathBuf;
 // use crate::test_runner::{TestRunner, TestSummary};
@@ -15,16 +16,54 @@ pub struct AppConfig {
     // 兼容旧字段 tray_show_mem：若为 true 则等价于 "mem"，否则为 "cpu"
     pub tray_bottom_mode: Option<String>,
     // 兼容保留（已弃用）：托盘第二行 true=显示内存%，false=显示CPU%
+    #[serde(default)]
     pub tray_show_mem: bool,
     // 网络接口白名单：为空或缺省表示聚合全部
     pub net_interfaces: Option<Vec<String>>,
     // 公网查询开关（默认启用）。false 可关闭公网 IP/ISP 拉取
     pub public_net_enabled: Option<bool>,
-    // 公网查询 API（可空使用内置：优先 ip-api.com，失败回退 ipinfo.io）
+    // 公网查询 API（兼容旧字段：作为首个提供者，失败时回退内置列表；配置了 public_net_providers 时忽略）
     pub public_net_api: Option<String>,
+    // 公网 IP 提供者：字符串（URL，"stun:host:port" 为 STUN）或对象 { url, name, family, ip_path, isp_path, asn_path, country_path }
+    #[serde(default, deserialize_with = "crate::public_net_utils::deserialize_providers")]
+    pub public_net_providers: Option<Vec<crate::public_net_utils::PublicNetProvider>>,
+    // 是否同时查询 IPv6 公网地址（默认启用）、单个提供者超时（默认 5000ms）、变化历史上限（默认 500）
+    pub public_net_ipv6: Option<bool>,
+    pub public_net_timeout_ms: Option<u64>,
+    pub public_net_history_max_entries: Option<usize>,
     // 多目标 RTT 配置
-    pub rtt_targets: Option<Vec<String>>,   // 形如 "1.1.1.1:443"
+    // 目标可为字符串（"1.1.1.1:443"）或对象：{ target, name, group, method, port, path, expect_status, query, timeout_ms, count, interval_ms, family }
+    #[serde(default, deserialize_with = "crate::ping_utils::deserialize_targets")]
+    pub rtt_targets: Option<Vec<crate::ping_utils::RttTarget>>,
     pub rtt_timeout_ms: Option<u64>,        // 默认 300ms
+    pub rtt_probe_count: Option<u32>,       // 每目标每轮探测包数，默认 5（1..100）
+    pub rtt_probe_interval_ms: Option<u64>, // 相邻探测包间隔，默认 200ms
+    // 地址族偏好：auto / v4 / v6 / both / happy_eyeballs（默认 auto：优先 IPv4）
+    pub rtt_family: Option<crate::ping_utils::FamilyPref>,
+    // DNS 探测：域名（默认 example.com / www.baidu.com）与自定义解析器（"8.8.8.8"、"[2606:4700::1111]:53"、"tcp://9.9.9.9"）
+    pub dns_probe_names: Option<Vec<String>>,
+    pub dns_probe_servers: Option<Vec<String>>,
+    // 是否同时探测系统解析器（默认 true）、传输方式 udp/tcp/both（默认 udp）、单次查询超时（默认 1000ms）
+    pub dns_probe_system: Option<bool>,
+    pub dns_probe_transport: Option<crate::dns_probe::DnsTransport>,
+    pub dns_probe_timeout_ms: Option<u64>,
+    // 路径分析：目标（默认取 rtt_targets）、方式 icmp/udp（默认 icmp）、最大跳数（默认 30）、轮数（默认 5）、单跳超时（默认 1000ms）、轮间隔（默认 200ms）
+    pub traceroute_targets: Option<Vec<String>>,
+    pub traceroute_method: Option<crate::traceroute::TraceMethod>,
+    pub traceroute_max_hops: Option<u8>,
+    pub traceroute_rounds: Option<u32>,
+    pub traceroute_timeout_ms: Option<u64>,
+    pub traceroute_interval_ms: Option<u64>,
+    // RTT 越限自动路径分析：平均延迟（ms）/ 丢包率（%）阈值，均未设置则不自动触发；同一目标冷却时间（默认 300 秒）
+    pub traceroute_rtt_threshold_ms: Option<f64>,
+    pub traceroute_loss_threshold_pct: Option<f64>,
+    pub traceroute_cooldown_secs: Option<u64>,
+    // 连通性状态机：强制门户检测地址（应返回 204，空串关闭）、去抖次数（默认 2）、降级延迟/丢包阈值（默认 300ms / 10%）、中断记录上限（默认 2000）
+    pub connectivity_check_url: Option<String>,
+    pub connectivity_debounce: Option<u32>,
+    pub connectivity_degraded_rtt_ms: Option<f64>,
+    pub connectivity_degraded_loss_pct: Option<f64>,
+    pub connectivity_journal_max_entries: Option<usize>,
     // 集中调度：基础节拍（毫秒）。未设置默认 1000ms
     pub interval_ms: Option<u64>,
     // 集中调度：任务分频（每N个tick执行一次）
@@ -35,13 +74,47 @@ pub struct AppConfig {
     pub pace_logical_disk_every: Option<u64>,
     // SMART 健康（默认每10tick）
     pub pace_smart_every: Option<u64>,
+    // DNS 探测（默认每10tick）
+    pub pace_dns_every: Option<u64>,
+    // 路径分析（默认不定时执行，仅手动触发与 RTT 越限自动触发）
+    pub pace_traceroute_every: Option<u64>,
+    // 公网 IP 查询（默认每1800tick；全部提供者失败时每60tick重试）
+    pub pace_public_net_every: Option<u64>,
+    pub pace_public_net_retry_every: Option<u64>,
+    // 吞吐测速（默认不定时，仅手动触发；设置后每N个tick执行一次）
+    pub pace_speedtest_every: Option<u64>,
     // Top 进程数量（默认 5）
     pub top_n: Option<usize>,
     // 是否启用 SMART 后台 Worker（默认启用）。false 则不启动
     pub smart_enabled: Option<bool>,
+    // InfluxDB line protocol 推送（默认关闭）
+    pub influx: Option<crate::influx_sink::InfluxConfig>,
+    // MQTT 发布与 Home Assistant 自动发现（默认关闭）
+    pub mqtt: Option<crate::mqtt_sink::MqttConfig>,
+    // 历史环形缓冲容量（采样点数，默认 3600，按 1s 节拍约 1 小时）
+    pub history_points: Option<usize>,
+    // 本地 HTTP/WebSocket API（默认关闭，仅监听 127.0.0.1）
+    pub api: Option<crate::api_server::ApiConfig>,
+    // 吞吐测速端点（HTTP 下载/上传地址或 iperf3 服务器，默认未配置）
+    pub speedtest: Option<crate::speedtest::SpeedtestConfig>,
+    // 阈值告警规则（持续时间/滞回/变化率条件）
+    pub alerts: Option<crate::alert_engine::AlertConfig>,
+    // 桥接输出解析模式："lenient"（默认，忽略并上报未知字段）| "strict"（拒收未知字段/未握手/不兼容版本的数据帧）
+    pub bridge_protocol_mode: Option<String>,
+    // 桥接看门狗（输出/心跳新鲜度检测与指数退避重启）
+    pub bridge_watchdog: Option<crate::bridge_manager::BridgeWatchdogConfig>,
+    // 桥接启动（握手完成）后经命令通道下发的采样间隔与硬件分组开关
+    pub bridge_control: Option<crate::bridge_command::BridgeControlConfig>,
+    // 桥接输出录制（JSON Lines，供离线回放）
+    pub bridge_record: Option<crate::bridge_replay::BridgeRecordConfig>,
+    // 桥接回放：以录制文件代替桥接进程（启动时生效）
+    pub bridge_replay: Option<crate::bridge_replay::BridgeReplayConfig>,
+    // 合成传感器模拟器：以场景生成的数据代替桥接进程（启动时生效，优先于回放）
+    pub bridge_simulator: Option<crate::sensor_sim::SimulatorConfig>,
 }
 
 /// Tauri命令：获取调度器状态
+#[cfg(feature = "desktop")]
 #[tauri::command]
 pub fn get_scheduler_state(state: tauri::State<AppState>) -> Result<SchedulerState, String> {
     state.scheduler
@@ -51,6 +124,7 @@ pub fn get_scheduler_state(state: tauri::State<AppState>) -> Result<SchedulerSta
 }
 
 /// Tauri命令：获取 StateStore 的 TickTelemetry
+#[cfg(feature = "desktop")]
 #[tauri::command]
 pub fn get_state_store_tick(state: tauri::State<AppState>) -> Result<TickTelemetry, String> {
     state.state_store
@@ -60,6 +134,7 @@ pub fn get_state_store_tick(state: tauri::State<AppState>) -> Result<TickTelemet
 }
 
 /// Tauri命令：获取 StateStore 的 Aggregated 聚合数据
+#[cfg(feature = "desktop")]
 #[tauri::command]
 pub fn get_state_store_agg(state: tauri::State<AppState>) -> Result<Aggregated, String> {
     state.state_store
@@ -71,8 +146,15 @@ pub fn get_state_store_agg(state: tauri::State<AppState>) -> Result<Aggregated,
 /// 公网信息结构体
 #[derive(Clone, Serialize, Deserialize, Default)]
 pub struct PublicNetInfo {
+    // 主地址：IPv4 优先，无 IPv4 时为 IPv6
     pub ip: Option<String>,
+    pub ipv4: Option<String>,
+    pub ipv6: Option<String>,
     pub isp: Option<String>,
+    pub asn: Option<String>,
+    pub country: Option<String>,
+    // 最近一次成功的提供者
+    pub provider: Option<String>,
     pub last_updated_ms: Option<i64>,
     pub last_error: Option<String>,
 }
@@ -90,21 +172,34 @@ pub struct AppState {
 }
 
 /// 加载应用配置
+#[cfg(feature = "desktop")]
 pub fn load_config(app_handle: &AppHandle) -> AppConfig {
-    let config_path = get_config_path(app_handle);
+    load_config_from(&get_config_path(app_handle))
+}
+
+/// 从指定路径加载配置（供无 AppHandle 的调用方使用，如 sys-sensor-agent）
+pub fn load_config_from(config_path: &std::path::Path) -> AppConfig {
     if config_path.exists() {
-        if let Ok(content) = std::fs::read_to_string(&config_path) {
-            if let Ok(config) = serde_json::from_str::<AppConfig>(&content) {
-                return config;
-            }
+        // 解析失败时回退默认配置，但必须留下原因，否则其余设置会被静默丢弃
+        match std::fs::read_to_string(config_path) {
+            Ok(content) => match serde_json::from_str::<AppConfig>(&content) {
+                Ok(config) => return config,
+                Err(e) => eprintln!("[config] 解析配置文件失败，使用默认配置: {}: {}", config_path.display(), e),
+            },
+            Err(e) => eprintln!("[config] 读取配置文件失败，使用默认配置: {}: {}", config_path.display(), e),
         }
     }
     AppConfig::default()
 }
 
 /// 保存应用配置
+#[cfg(feature = "desktop")]
 pub fn save_config(app_handle: &AppHandle, config: &AppConfig) -> Result<(), String> {
-    let config_path = get_config_path(app_handle);
+    save_config_to(&get_config_path(app_handle), config)
+}
+
+/// 保存应用配置到指定路径（供无 AppHandle 的调用方使用，如本地 API）
+pub fn save_config_to(config_path: &std::path::Path, config: &AppConfig) -> Result<(), String> {
     if let Some(parent) = config_path.parent() {
         if !parent.exists() {
             std::fs::create_dir_all(parent).map_err(|e| format!("创建配置目录失败: {}", e))?;
@@ -115,16 +210,23 @@ pub fn save_config(app_handle: &AppHandle, config: &AppConfig) -> Result<(), Str
     Ok(())
 }
 
-/// 获取配置文件路径
-fn get_config_path(app_handle: &AppHandle) -> PathBuf {
+/// 获取配置目录（config.json、api_tokens.json 等均位于此）
+#[cfg(feature = "desktop")]
+pub fn get_config_dir(app_handle: &AppHandle) -> PathBuf {
     app_handle
         .path()
         .app_config_dir()
         .unwrap_or_else(|_| std::env::current_dir().unwrap_or_else(|_| PathBuf::from(".")))
-        .join("config.json")
+}
+
+/// 获取配置文件路径
+#[cfg(feature = "desktop")]
+fn get_config_path(app_handle: &AppHandle) -> PathBuf {
+    get_config_dir(app_handle).join("config.json")
 }
 
 /// Tauri命令：获取配置
+#[cfg(feature = "desktop")]
 #[tauri::command]
 pub fn get_config(state: tauri::State<AppState>) -> Result<AppConfig, String> {
     state.config
@@ -134,6 +236,7 @@ pub fn get_config(state: tauri::State<AppState>) -> Result<AppConfig, String> {
 }
 
 /// Tauri命令：设置配置
+#[cfg(feature = "desktop")]
 #[tauri::command]
 pub fn set_config(
     new_cfg: AppConfig,
@@ -149,27 +252,75 @@ pub fn set_config(
     // 广播配置变更事件，便于前端监听刷新
     {
         use tauri::Emitter;
+        crate::event_hub::publish("config", &new_cfg);
         let _ = app_handle.emit("config://changed", &new_cfg);
     }
     Ok(())
 }
 
 /// 将 JSON 补丁增量合并到配置
-fn apply_patch(cfg: &mut AppConfig, patch: &serde_json::Value) {
+pub fn apply_patch(cfg: &mut AppConfig, patch: &serde_json::Value) {
     let obj = match patch.as_object() { Some(m) => m, None => return };
     // 字符串与布尔/数值字段
     if let Some(v) = obj.get("tray_bottom_mode") { cfg.tray_bottom_mode = v.as_str().map(|s| s.to_string()); }
     if let Some(v) = obj.get("tray_show_mem") { cfg.tray_show_mem = v.as_bool().unwrap_or(cfg.tray_show_mem); }
     if let Some(v) = obj.get("public_net_enabled") { cfg.public_net_enabled = v.as_bool(); }
     if let Some(v) = obj.get("public_net_api") { cfg.public_net_api = v.as_str().map(|s| s.to_string()); }
+    if let Some(v) = obj.get("public_net_providers") {
+        cfg.public_net_providers = if v.is_null() { None } else { v.as_array().map(|l| l.iter().filter_map(crate::public_net_utils::PublicNetProvider::from_value).collect()) };
+    }
+    if let Some(v) = obj.get("public_net_ipv6") { cfg.public_net_ipv6 = v.as_bool(); }
+    if let Some(v) = obj.get("public_net_timeout_ms") { cfg.public_net_timeout_ms = v.as_u64(); }
+    if let Some(v) = obj.get("public_net_history_max_entries") { cfg.public_net_history_max_entries = v.as_u64().map(|n| n as usize); }
+    if let Some(v) = obj.get("bridge_protocol_mode") { cfg.bridge_protocol_mode = v.as_str().map(|s| s.to_string()); }
     if let Some(v) = obj.get("rtt_timeout_ms") { cfg.rtt_timeout_ms = v.as_u64(); }
+    if let Some(v) = obj.get("rtt_probe_count") { cfg.rtt_probe_count = v.as_u64().map(|n| n.clamp(1, 100) as u32); }
+    if let Some(v) = obj.get("rtt_probe_interval_ms") { cfg.rtt_probe_interval_ms = v.as_u64(); }
+    if let Some(v) = obj.get("rtt_family") {
+        cfg.rtt_family = if v.is_null() { None } else { serde_json::from_value(v.clone()).ok().or(cfg.rtt_family.take()) };
+    }
+    if let Some(v) = obj.get("dns_probe_names") {
+        cfg.dns_probe_names = if v.is_null() { None } else { serde_json::from_value(v.clone()).ok().or(cfg.dns_probe_names.take()) };
+    }
+    if let Some(v) = obj.get("dns_probe_servers") {
+        cfg.dns_probe_servers = if v.is_null() { None } else { serde_json::from_value(v.clone()).ok().or(cfg.dns_probe_servers.take()) };
+    }
+    if let Some(v) = obj.get("dns_probe_system") { cfg.dns_probe_system = v.as_bool(); }
+    if let Some(v) = obj.get("dns_probe_transport") {
+        cfg.dns_probe_transport = if v.is_null() { None } else { serde_json::from_value(v.clone()).ok().or(cfg.dns_probe_transport.take()) };
+    }
+    if let Some(v) = obj.get("dns_probe_timeout_ms") { cfg.dns_probe_timeout_ms = v.as_u64(); }
+    if let Some(v) = obj.get("traceroute_targets") {
+        cfg.traceroute_targets = if v.is_null() { None } else { serde_json::from_value(v.clone()).ok().or(cfg.traceroute_targets.take()) };
+    }
+    if let Some(v) = obj.get("traceroute_method") {
+        cfg.traceroute_method = if v.is_null() { None } else { serde_json::from_value(v.clone()).ok().or(cfg.traceroute_method.take()) };
+    }
+    if let Some(v) = obj.get("traceroute_max_hops") { cfg.traceroute_max_hops = v.as_u64().map(|n| n.clamp(1, 64) as u8); }
+    if let Some(v) = obj.get("traceroute_rounds") { cfg.traceroute_rounds = v.as_u64().map(|n| n.clamp(1, 100) as u32); }
+    if let Some(v) = obj.get("traceroute_timeout_ms") { cfg.traceroute_timeout_ms = v.as_u64(); }
+    if let Some(v) = obj.get("traceroute_interval_ms") { cfg.traceroute_interval_ms = v.as_u64(); }
+    if let Some(v) = obj.get("traceroute_rtt_threshold_ms") { cfg.traceroute_rtt_threshold_ms = v.as_f64(); }
+    if let Some(v) = obj.get("traceroute_loss_threshold_pct") { cfg.traceroute_loss_threshold_pct = v.as_f64(); }
+    if let Some(v) = obj.get("traceroute_cooldown_secs") { cfg.traceroute_cooldown_secs = v.as_u64(); }
+    if let Some(v) = obj.get("connectivity_check_url") { cfg.connectivity_check_url = v.as_str().map(|s| s.to_string()); }
+    if let Some(v) = obj.get("connectivity_debounce") { cfg.connectivity_debounce = v.as_u64().map(|n| n.clamp(1, 100) as u32); }
+    if let Some(v) = obj.get("connectivity_degraded_rtt_ms") { cfg.connectivity_degraded_rtt_ms = v.as_f64(); }
+    if let Some(v) = obj.get("connectivity_degraded_loss_pct") { cfg.connectivity_degraded_loss_pct = v.as_f64(); }
+    if let Some(v) = obj.get("connectivity_journal_max_entries") { cfg.connectivity_journal_max_entries = v.as_u64().map(|n| n as usize); }
     if let Some(v) = obj.get("interval_ms") { cfg.interval_ms = v.as_u64(); }
     if let Some(v) = obj.get("pace_rtt_multi_every") { cfg.pace_rtt_multi_every = v.as_u64(); }
     if let Some(v) = obj.get("pace_net_if_every") { cfg.pace_net_if_every = v.as_u64(); }
     if let Some(v) = obj.get("pace_logical_disk_every") { cfg.pace_logical_disk_every = v.as_u64(); }
     if let Some(v) = obj.get("pace_smart_every") { cfg.pace_smart_every = v.as_u64(); }
+    if let Some(v) = obj.get("pace_dns_every") { cfg.pace_dns_every = v.as_u64(); }
+    if let Some(v) = obj.get("pace_traceroute_every") { cfg.pace_traceroute_every = v.as_u64(); }
+    if let Some(v) = obj.get("pace_public_net_every") { cfg.pace_public_net_every = v.as_u64(); }
+    if let Some(v) = obj.get("pace_public_net_retry_every") { cfg.pace_public_net_retry_every = v.as_u64(); }
+    if let Some(v) = obj.get("pace_speedtest_every") { cfg.pace_speedtest_every = v.as_u64(); }
     if let Some(v) = obj.get("top_n") { cfg.top_n = v.as_u64().map(|x| x as usize); }
     if let Some(v) = obj.get("smart_enabled") { cfg.smart_enabled = v.as_bool(); }
+    if let Some(v) = obj.get("history_points") { cfg.history_points = v.as_u64().map(|x| x as usize); }
 
     // 列表字段
     if let Some(v) = obj.get("net_interfaces") {
@@ -179,16 +330,48 @@ fn apply_patch(cfg: &mut AppConfig, patch: &serde_json::Value) {
             cfg.net_interfaces = Some(list);
         }
     }
+    // 结构化字段：整体替换（null 表示清空）
+    if let Some(v) = obj.get("influx") {
+        cfg.influx = if v.is_null() { None } else { serde_json::from_value(v.clone()).ok().or(cfg.influx.take()) };
+    }
+    if let Some(v) = obj.get("mqtt") {
+        cfg.mqtt = if v.is_null() { None } else { serde_json::from_value(v.clone()).ok().or(cfg.mqtt.take()) };
+    }
+    if let Some(v) = obj.get("api") {
+        cfg.api = if v.is_null() { None } else { serde_json::from_value(v.clone()).ok().or(cfg.api.take()) };
+    }
+    if let Some(v) = obj.get("speedtest") {
+        cfg.speedtest = if v.is_null() { None } else { serde_json::from_value(v.clone()).ok().or(cfg.speedtest.take()) };
+    }
+    if let Some(v) = obj.get("alerts") {
+        cfg.alerts = if v.is_null() { None } else { serde_json::from_value(v.clone()).ok().or(cfg.alerts.take()) };
+    }
+    if let Some(v) = obj.get("bridge_watchdog") {
+        cfg.bridge_watchdog = if v.is_null() { None } else { serde_json::from_value(v.clone()).ok().or(cfg.bridge_watchdog.take()) };
+    }
+    if let Some(v) = obj.get("bridge_control") {
+        cfg.bridge_control = if v.is_null() { None } else { serde_json::from_value(v.clone()).ok().or(cfg.bridge_control.take()) };
+    }
+    if let Some(v) = obj.get("bridge_record") {
+        cfg.bridge_record = if v.is_null() { None } else { serde_json::from_value(v.clone()).ok().or(cfg.bridge_record.take()) };
+    }
+    if let Some(v) = obj.get("bridge_replay") {
+        cfg.bridge_replay = if v.is_null() { None } else { serde_json::from_value(v.clone()).ok().or(cfg.bridge_replay.take()) };
+    }
+    if let Some(v) = obj.get("bridge_simulator") {
+        cfg.bridge_simulator = if v.is_null() { None } else { serde_json::from_value(v.clone()).ok().or(cfg.bridge_simulator.take()) };
+    }
     if let Some(v) = obj.get("rtt_targets") {
         if v.is_null() { cfg.rtt_targets = None; }
         else if let Some(arr) = v.as_array() {
-            let list: Vec<String> = arr.iter().filter_map(|x| x.as_str().map(|s| s.to_string())).collect();
+            let list: Vec<crate::ping_utils::RttTarget> = arr.iter().filter_map(crate::ping_utils::RttTarget::from_value).collect();
             cfg.rtt_targets = Some(list);
         }
     }
 }
 
 /// Tauri命令：增量热更新配置（只变更传入字段）
+#[cfg(feature = "desktop")]
 #[tauri::command]
 pub fn cmd_cfg_update(
     patch: serde_json::Value,
@@ -209,26 +392,28 @@ pub fn cmd_cfg_update(
     // 广播配置变更事件，便于前端监听刷新
     {
         use tauri::Emitter;
+        crate::event_hub::publish("config", &cfg);
         let _ = app_handle.emit("config://changed", &cfg);
     }
     Ok(cfg)
 }
 
 /// Tauri命令：问候语（示例命令）
-#[tauri::command]
+#[cfg_attr(feature = "desktop", tauri::command)]
 #[allow(dead_code)]
 pub fn greet(name: &str) -> String {
     format!("Hello, {}! You've been greeted from Rust!", name)
 }
 
 /// 列出网络接口
-#[tauri::command]
+#[cfg_attr(feature = "desktop", tauri::command)]
 pub fn list_net_interfaces() -> Vec<String> {
     // 返回空列表，实际实现可以根据需要添加
     vec![]
 }
 
 /// Tauri命令：立即触发 SMART 刷新
+#[cfg(feature = "desktop")]
 #[tauri::command]
 pub fn smart_refresh(state: tauri::State<AppState>) -> Result<bool, String> {
     if let Ok(guard) = state.smart.lock() {
@@ -239,12 +424,23 @@ pub fn smart_refresh(state: tauri::State<AppState>) -> Result<bool, String> {
 }
 
 /// Tauri命令：获取最近一次 SMART 快照（含 last_error）
-#[tauri::command]
+#[cfg_attr(feature = "desktop", tauri::command)]
 pub fn smart_get_last() -> serde_json::Value {
     crate::smart_worker::get_last_snapshot()
 }
 
+/// SMART Worker 回调：每次采集后向 webview 广播 "sensor://smart"
+#[cfg(feature = "desktop")]
+pub fn smart_listener(app_handle: &AppHandle) -> crate::smart_worker::SmartListener {
+    use tauri::Emitter;
+    let app = app_handle.clone();
+    std::sync::Arc::new(move |payload: &serde_json::Value| {
+        let _ = app.emit("sensor://smart", payload);
+    })
+}
+
 /// Tauri命令：运行时启用/禁用 SMART Worker（并持久化 smart_enabled）
+#[cfg(feature = "desktop")]
 #[tauri::command]
 pub fn smart_enable(enabled: bool, state: tauri::State<AppState>, app_handle: AppHandle) -> Result<bool, String> {
     use tauri::Emitter;
@@ -253,7 +449,7 @@ pub fn smart_enable(enabled: bool, state: tauri::State<AppState>, app_handle: Ap
         let mut smart_lock = state.smart.lock().map_err(|_| "获取 SMART Worker 锁失败".to_string())?;
         if enabled {
             if smart_lock.is_none() {
-                let worker = crate::smart_worker::start(app_handle.clone());
+                let worker = crate::smart_worker::start(Some(smart_listener(&app_handle)));
                 *smart_lock = Some(worker);
             }
         } else {
@@ -289,7 +485,7 @@ pub fn smart_enable(enabled: bool, state: tauri::State<AppState>, app_handle: Ap
 //     Ok("Bridge tests not implemented yet".to_string())
 // }
 /// 运行 C# 桥接层测试
-#[tauri::command]
+#[cfg_attr(feature = "desktop", tauri::command)]
 pub async fn run_bridge_tests() -> Result<serde_json::Value, String> {
     use std::process::Command;
     
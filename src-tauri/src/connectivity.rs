@@ -0,0 +1,389 @@
+// 网络连通性状态机与中断时间线
+// 说明：
+// - 每轮 RTT 结果到达时，结合最近一次 DNS 探测与强制门户检测（HTTP generate_204）归类为：
+//   online / degraded / offline / captive_portal / dns_failure
+// - 去抖：同一新状态连续出现 connectivity_debounce 次（默认 2）才切换，切换时间取首次出现的时间
+// - 任意非 online 状态的连续区间记为一次中断（outage）：开始/结束/时长/最严重状态/疑似原因/经历的状态
+// - 已结束的中断追加到配置目录下的 outages.jsonl（每行一条 JSON），超过上限（默认 2000）时丢弃最旧的记录（文件按 jsonl_journal 规则延迟压缩）
+// - 进行中的中断只在内存中维护，查询时排在最前；导出 CSV 便于向运营商报障
+
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+
+use serde::{Deserialize, Serialize};
+
+use crate::dns_probe::DnsProbeReport;
+use crate::http_timing::HttpTiming;
+use crate::jsonl_journal::JsonlJournal;
+use crate::process_utils::RttResultPayload;
+
+const JOURNAL_FILE: &str = "outages.jsonl";
+const DEFAULT_MAX_ENTRIES: usize = 2000;
+pub const DEFAULT_CHECK_URL: &str = "http://connectivitycheck.gstatic.com/generate_204";
+pub const DEFAULT_DEBOUNCE: u32 = 2;
+pub const DEFAULT_DEGRADED_RTT_MS: f64 = 300.0;
+pub const DEFAULT_DEGRADED_LOSS_PCT: f64 = 10.0;
+
+/// 连通性状态
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConnState {
+    Online,
+    // 部分目标不可达、丢包或延迟越限、部分解析器异常
+    Degraded,
+    // 全部 RTT 目标不可达
+    Offline,
+    // 连通性检测被重定向/改写（需登录的 Wi‑Fi 等）
+    CaptivePortal,
+    // 目标可达但全部解析器无可用应答
+    DnsFailure,
+}
+
+impl ConnState {
+    pub fn label(self) -> &'static str {
+        match self {
+            ConnState::Online => "online",
+            ConnState::Degraded => "degraded",
+            ConnState::Offline => "offline",
+            ConnState::CaptivePortal => "captive_portal",
+            ConnState::DnsFailure => "dns_failure",
+        }
+    }
+
+    // 严重程度：中断期间取最严重的状态作为该次中断的类型
+    fn severity(self) -> u8 {
+        match self {
+            ConnState::Online => 0,
+            ConnState::Degraded => 1,
+            ConnState::DnsFailure => 2,
+            ConnState::CaptivePortal => 3,
+            ConnState::Offline => 4,
+        }
+    }
+}
+
+/// 降级判定阈值
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ConnThresholds {
+    pub rtt_ms: f64,
+    pub loss_pct: f64,
+}
+
+impl Default for ConnThresholds {
+    fn default() -> Self {
+        Self { rtt_ms: DEFAULT_DEGRADED_RTT_MS, loss_pct: DEFAULT_DEGRADED_LOSS_PCT }
+    }
+}
+
+impl ConnThresholds {
+    pub fn from_config(cfg: &crate::config_utils::AppConfig) -> Self {
+        let d = Self::default();
+        Self {
+            rtt_ms: cfg.connectivity_degraded_rtt_ms.unwrap_or(d.rtt_ms),
+            loss_pct: cfg.connectivity_degraded_loss_pct.unwrap_or(d.loss_pct),
+        }
+    }
+}
+
+/// 强制门户检测地址（配置为空串时关闭检测）
+pub fn check_url_from_config(cfg: &crate::config_utils::AppConfig) -> Option<String> {
+    match cfg.connectivity_check_url.as_deref().map(str::trim) {
+        Some("") => None,
+        Some(u) => Some(u.to_string()),
+        None => Some(DEFAULT_CHECK_URL.to_string()),
+    }
+}
+
+/// 单次归类结果
+#[derive(Clone, Debug, PartialEq)]
+pub struct ConnSample {
+    pub state: ConnState,
+    pub cause: Option<String>,
+}
+
+/// 按 RTT / DNS / 强制门户检测结果归类；无任何输入时返回 None
+/// 优先级：强制门户 > 离线 > DNS 故障 > 降级 > 在线
+pub fn classify(
+    rtt: &[RttResultPayload],
+    dns: Option<&DnsProbeReport>,
+    captive: Option<&HttpTiming>,
+    th: &ConnThresholds,
+) -> Option<ConnSample> {
+    let dns_servers = dns.map(|d| d.servers.as_slice()).unwrap_or_default();
+    if rtt.is_empty() && dns_servers.is_empty() { return None; }
+    let sample = |state: ConnState, cause: Option<String>| Some(ConnSample { state, cause });
+
+    // 检测地址应返回 204：收到其他状态码说明请求被门户拦截或改写
+    if let Some(status) = captive.and_then(|c| c.status).filter(|s| *s != 204) {
+        return sample(ConnState::CaptivePortal, Some(format!("连通性检测返回 HTTP {}", status)));
+    }
+
+    let ok: Vec<&RttResultPayload> = rtt.iter().filter(|r| r.success == Some(true)).collect();
+    if !rtt.is_empty() && ok.is_empty() {
+        let dns_down = !dns_servers.is_empty() && dns_servers.iter().all(|s| s.answered == 0);
+        let cause = if dns_down { "全部 RTT 目标与解析器均无响应" } else { "全部 RTT 目标不可达" };
+        return sample(ConnState::Offline, Some(cause.to_string()));
+    }
+
+    // 解析器全部无应答或只返回 SERVFAIL
+    if !dns_servers.is_empty() && dns_servers.iter().all(|s| s.answered == 0 || s.servfails >= s.answered) {
+        return sample(ConnState::DnsFailure, Some(format!("{} 个解析器均无可用应答", dns_servers.len())));
+    }
+
+    let mut causes: Vec<String> = Vec::new();
+    let failed: Vec<&str> = rtt.iter().filter(|r| r.success != Some(true)).map(|r| r.target.as_str()).collect();
+    if !failed.is_empty() { causes.push(format!("目标不可达: {}", failed.join(", "))); }
+    let mean = |v: Vec<f64>| (!v.is_empty()).then(|| v.iter().sum::<f64>() / v.len() as f64);
+    if let Some(loss) = mean(ok.iter().filter_map(|r| r.loss_pct).collect()).filter(|l| *l >= th.loss_pct) {
+        causes.push(format!("平均丢包 {:.0}%", loss));
+    }
+    if let Some(avg) = mean(ok.iter().filter_map(|r| r.avg_ms.or(r.rtt_ms)).collect()).filter(|v| *v >= th.rtt_ms) {
+        causes.push(format!("平均延迟 {:.0}ms", avg));
+    }
+    let bad_dns: Vec<&str> = dns_servers.iter().filter(|s| s.flagged).map(|s| s.server.as_str()).collect();
+    if !bad_dns.is_empty() { causes.push(format!("解析器异常: {}", bad_dns.join(", "))); }
+
+    if causes.is_empty() {
+        sample(ConnState::Online, None)
+    } else {
+        sample(ConnState::Degraded, Some(causes.join("；")))
+    }
+}
+
+/// 一次中断（进行中时 end_ms / duration_ms 为空）
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Outage {
+    pub start_ms: i64,
+    pub end_ms: Option<i64>,
+    pub duration_ms: Option<i64>,
+    // 期间最严重的状态与对应的疑似原因
+    pub state: ConnState,
+    pub cause: Option<String>,
+    // 期间经历的状态（按顺序去除相邻重复）
+    pub states: Vec<ConnState>,
+}
+
+/// 状态切换
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ConnTransition {
+    pub from: Option<ConnState>,
+    pub to: ConnState,
+    pub at_ms: i64,
+    pub cause: Option<String>,
+    // 恢复 online 时结束的中断
+    pub ended: Option<Outage>,
+}
+
+/// 当前连通性（随快照下发）
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct ConnectivityStatus {
+    // 尚未确定（启动后去抖期间）为 None
+    pub state: Option<ConnState>,
+    pub since_ms: Option<i64>,
+    pub cause: Option<String>,
+    // 进行中的中断开始时间
+    pub outage_start_ms: Option<i64>,
+}
+
+/// 去抖状态机
+#[derive(Clone, Debug, Default)]
+pub struct ConnMonitor {
+    state: Option<ConnState>,
+    since_ms: Option<i64>,
+    cause: Option<String>,
+    // 候选状态：(状态, 连续次数, 首次出现时间)
+    pending: Option<(ConnState, u32, i64)>,
+    outage: Option<Outage>,
+}
+
+impl ConnMonitor {
+    /// 输入一个样本；确认切换时返回切换信息
+    pub fn observe(&mut self, s: ConnSample, now_ms: i64, debounce: u32) -> Option<ConnTransition> {
+        if self.state == Some(s.state) {
+            self.pending = None;
+            // 状态不变：原因随最新样本更新（中断记录保留最严重时的原因）
+            self.cause = s.cause;
+            return None;
+        }
+        let (count, first_ms) = match self.pending.as_mut() {
+            Some(p) if p.0 == s.state => {
+                p.1 += 1;
+                (p.1, p.2)
+            }
+            _ => {
+                self.pending = Some((s.state, 1, now_ms));
+                (1, now_ms)
+            }
+        };
+        if count < debounce.max(1) { return None; }
+
+        self.pending = None;
+        let from = self.state.replace(s.state);
+        self.since_ms = Some(first_ms);
+        self.cause = s.cause.clone();
+        let ended = if s.state == ConnState::Online {
+            self.outage.take().map(|mut o| {
+                o.end_ms = Some(first_ms);
+                o.duration_ms = Some(first_ms - o.start_ms);
+                o
+            })
+        } else {
+            let o = self.outage.get_or_insert_with(|| Outage {
+                start_ms: first_ms,
+                end_ms: None,
+                duration_ms: None,
+                state: s.state,
+                cause: s.cause.clone(),
+                states: Vec::new(),
+            });
+            if o.states.last() != Some(&s.state) { o.states.push(s.state); }
+            if s.state.severity() > o.state.severity() {
+                o.state = s.state;
+                o.cause = s.cause.clone();
+            }
+            None
+        };
+        Some(ConnTransition { from, to: s.state, at_ms: first_ms, cause: s.cause, ended })
+    }
+
+    pub fn status(&self) -> ConnectivityStatus {
+        ConnectivityStatus {
+            state: self.state,
+            since_ms: self.since_ms,
+            cause: self.cause.clone(),
+            outage_start_ms: self.outage.as_ref().map(|o| o.start_ms),
+        }
+    }
+
+    /// 进行中的中断（时长计到 now_ms）
+    pub fn ongoing(&self, now_ms: i64) -> Option<Outage> {
+        self.outage.clone().map(|mut o| {
+            o.duration_ms = Some(now_ms - o.start_ms);
+            o
+        })
+    }
+}
+
+#[derive(Default)]
+struct Timeline {
+    file: Option<JsonlJournal>,
+    monitor: ConnMonitor,
+}
+
+static TIMELINE: OnceLock<Mutex<Timeline>> = OnceLock::new();
+
+fn with_timeline<R, F: FnOnce(&mut Timeline) -> R>(f: F) -> Option<R> {
+    let cell = TIMELINE.get_or_init(|| Mutex::new(Timeline::default()));
+    cell.lock().ok().map(|mut g| f(&mut g))
+}
+
+/// 读取中断日志中最新的 max_entries 条（未运行实例时 CLI 直接读取文件）
+pub fn read_entries(path: &Path, max_entries: Option<usize>) -> Vec<Outage> {
+    crate::jsonl_journal::read_latest(path, max_entries.unwrap_or(DEFAULT_MAX_ENTRIES))
+}
+
+/// 中断日志路径
+pub fn journal_path(dir: &Path) -> PathBuf {
+    dir.join(JOURNAL_FILE)
+}
+
+/// 打开日志目录（未调用时中断只在内存中维护）
+pub fn open(dir: &Path, max_entries: Option<usize>) {
+    let file = JsonlJournal::open(journal_path(dir), max_entries.unwrap_or(DEFAULT_MAX_ENTRIES));
+    with_timeline(|t| t.file = Some(file));
+}
+
+/// 追加一条已结束的中断；超出上限时保留最新的 max_entries 条
+fn append(t: &mut Timeline, outage: &Outage, max_entries: Option<usize>) {
+    let max = max_entries.unwrap_or(DEFAULT_MAX_ENTRIES).max(1);
+    let Some(file) = t.file.as_mut() else { return };
+    if let Err(e) = file.append(outage, max) {
+        eprintln!("[connectivity] 写入中断日志失败: {}", e);
+    }
+}
+
+/// 输入一个样本（采集循环调用）；中断结束时写入日志
+pub fn observe(s: ConnSample, now_ms: i64, debounce: u32, max_entries: Option<usize>) -> Option<ConnTransition> {
+    with_timeline(|t| {
+        let tr = t.monitor.observe(s, now_ms, debounce);
+        if let Some(o) = tr.as_ref().and_then(|tr| tr.ended.as_ref()) { append(t, o, max_entries); }
+        tr
+    })
+    .flatten()
+}
+
+/// 当前连通性
+pub fn status() -> ConnectivityStatus {
+    with_timeline(|t| t.monitor.status()).unwrap_or_default()
+}
+
+/// 查询中断（新记录在前；进行中的中断排在最前）
+pub fn query(since_ms: Option<i64>, until_ms: Option<i64>, limit: usize) -> Vec<Outage> {
+    let now = chrono::Local::now().timestamp_millis();
+    let (file, ongoing) = with_timeline(|t| (t.file.clone(), t.monitor.ongoing(now))).unwrap_or_default();
+    let mut out: Vec<Outage> = file.map(|f| f.read()).unwrap_or_default();
+    out.extend(ongoing);
+    filter_outages(out, since_ms, until_ms, limit)
+}
+
+/// 按时间窗口过滤（与窗口有重叠即保留），新记录在前
+pub fn filter_outages(mut list: Vec<Outage>, since_ms: Option<i64>, until_ms: Option<i64>, limit: usize) -> Vec<Outage> {
+    list.retain(|o| {
+        since_ms.map(|s| o.end_ms.unwrap_or(i64::MAX) >= s).unwrap_or(true) && until_ms.map(|u| o.start_ms <= u).unwrap_or(true)
+    });
+    list.sort_by_key(|o| std::cmp::Reverse(o.start_ms));
+    list.truncate(limit);
+    list
+}
+
+fn format_ts(ts_ms: i64) -> String {
+    use chrono::TimeZone;
+    chrono::Local
+        .timestamp_millis_opt(ts_ms)
+        .single()
+        .map(|t| t.format("%Y-%m-%d %H:%M:%S").to_string())
+        .unwrap_or_else(|| ts_ms.to_string())
+}
+
+fn csv_field(s: &str) -> String {
+    if s.contains([',', '"', '\n']) { format!("\"{}\"", s.replace('"', "\"\"")) } else { s.to_string() }
+}
+
+/// 导出 CSV（本地时间，按开始时间升序；进行中的中断结束时间为空）
+pub fn export_csv(outages: &[Outage]) -> String {
+    let mut list: Vec<&Outage> = outages.iter().collect();
+    list.sort_by_key(|o| o.start_ms);
+    let mut out = String::from("start,end,duration_s,state,cause,states\n");
+    for o in list {
+        out.push_str(&format!(
+            "{},{},{},{},{},{}\n",
+            format_ts(o.start_ms),
+            o.end_ms.map(format_ts).unwrap_or_default(),
+            o.duration_ms.map(|d| format!("{:.0}", d as f64 / 1000.0)).unwrap_or_default(),
+            o.state.label(),
+            csv_field(o.cause.as_deref().unwrap_or("")),
+            o.states.iter().map(|s| s.label()).collect::<Vec<_>>().join("/"),
+        ));
+    }
+    out
+}
+
+/// Tauri命令：当前连通性与中断时间线（新记录在前，默认 200 条）
+#[cfg_attr(feature = "desktop", tauri::command)]
+pub fn connectivity_timeline(since_ms: Option<i64>, until_ms: Option<i64>, limit: Option<usize>) -> serde_json::Value {
+    serde_json::json!({
+        "status": status(),
+        "outages": query(since_ms, until_ms, limit.unwrap_or(200)),
+    })
+}
+
+/// Tauri命令：导出中断记录（format: "csv"（默认）/ "json"），返回文本内容
+#[cfg_attr(feature = "desktop", tauri::command)]
+pub fn connectivity_export(format: Option<String>, since_ms: Option<i64>, until_ms: Option<i64>) -> Result<String, String> {
+    let list = query(since_ms, until_ms, usize::MAX);
+    match format.as_deref().unwrap_or("csv") {
+        "csv" => Ok(export_csv(&list)),
+        "json" => serde_json::to_string_pretty(&list).map_err(|e| e.to_string()),
+        other => Err(format!("不支持的导出格式: {}", other)),
+    }
+}
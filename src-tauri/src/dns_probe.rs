@@ -0,0 +1,443 @@
+// =============================================================================
+// DNS 解析延迟与解析器健康探测
+// - 解析器来源：系统（网卡 DNS 配置，非 Windows 为空时回退 /etc/resolv.conf）与自定义（dns_probe_servers）
+// - 直接向各解析器发送 A 记录查询（UDP / TCP），记录延迟、RCODE 与应答地址
+// - 自定义解析器可用 "tcp://" / "udp://" 前缀单独指定传输方式，否则跟随 dns_probe_transport
+// - 一致性：同一域名各解析器 NOERROR 应答的地址集合是否一致（CDN 域名可能天然不一致，仅作提示）
+// - 超时或返回 SERVFAIL 的解析器标记为 flagged
+// - RTT 目标的 dns 探测方式（ping_utils）同样经 query 发送 UDP 查询
+// =============================================================================
+
+use std::io::{Read, Write};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, TcpStream, UdpSocket};
+use std::sync::atomic::{AtomicU16, Ordering};
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+use crate::types::NetIfPayload;
+
+pub const DEFAULT_TIMEOUT_MS: u64 = 1000;
+pub const DEFAULT_NAMES: [&str; 2] = ["example.com", "www.baidu.com"];
+
+static NEXT_ID: AtomicU16 = AtomicU16::new(0x5d00);
+
+/// 传输方式：udp（默认）/ tcp / both（两种各查一次）
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DnsTransport {
+    #[default]
+    Udp,
+    Tcp,
+    Both,
+}
+
+impl DnsTransport {
+    fn expand(self) -> &'static [DnsTransport] {
+        match self {
+            DnsTransport::Udp => &[DnsTransport::Udp],
+            DnsTransport::Tcp => &[DnsTransport::Tcp],
+            DnsTransport::Both => &[DnsTransport::Udp, DnsTransport::Tcp],
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            DnsTransport::Udp => "udp",
+            DnsTransport::Tcp => "tcp",
+            DnsTransport::Both => "both",
+        }
+    }
+}
+
+/// 待探测的解析器
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DnsServer {
+    pub addr: SocketAddr,
+    // "system" / "custom"
+    pub source: String,
+    // 单独指定的传输方式；None 跟随全局配置
+    pub transport: Option<DnsTransport>,
+}
+
+/// 解析解析器地址："8.8.8.8"、"1.1.1.1:53"、"[2606:4700::1111]:53"、"tcp://9.9.9.9"（仅支持 IP 字面量）
+pub fn parse_server(s: &str, source: &str) -> Option<DnsServer> {
+    let s = s.trim();
+    let (transport, rest) = if let Some(r) = s.strip_prefix("tcp://") {
+        (Some(DnsTransport::Tcp), r)
+    } else if let Some(r) = s.strip_prefix("udp://") {
+        (Some(DnsTransport::Udp), r)
+    } else {
+        (None, s)
+    };
+    let (host, port) = crate::ping_utils::split_host_port(rest.trim_end_matches('/'));
+    let ip: IpAddr = host.parse().ok()?;
+    Some(DnsServer { addr: SocketAddr::new(ip, port.unwrap_or(53)), source: source.to_string(), transport })
+}
+
+/// 读取 resolv.conf 中的 nameserver 行
+pub fn parse_resolv_conf(text: &str) -> Vec<String> {
+    text.lines()
+        .filter_map(|l| {
+            let mut it = l.split_whitespace();
+            (it.next() == Some("nameserver")).then(|| it.next()).flatten().map(str::to_string)
+        })
+        .collect()
+}
+
+/// 系统解析器：汇总各网卡 DNS 配置并去重
+pub fn system_servers(net_ifs: Option<&[NetIfPayload]>) -> Vec<DnsServer> {
+    #[allow(unused_mut)]
+    let mut raw: Vec<String> = net_ifs
+        .unwrap_or_default()
+        .iter()
+        .filter_map(|n| n.dns_servers.clone())
+        .flatten()
+        .collect();
+    #[cfg(not(windows))]
+    if raw.is_empty() {
+        if let Ok(text) = std::fs::read_to_string("/etc/resolv.conf") { raw = parse_resolv_conf(&text); }
+    }
+    let mut out: Vec<DnsServer> = Vec::new();
+    for s in raw.iter().filter_map(|s| parse_server(s, "system")) {
+        if !out.iter().any(|o| o.addr == s.addr) { out.push(s); }
+    }
+    out
+}
+
+/// 按配置汇总待探测解析器：系统（dns_probe_system，默认启用）+ 自定义；同一地址只保留首个
+pub fn servers_from_config(cfg: &crate::config_utils::AppConfig, net_ifs: Option<&[NetIfPayload]>) -> Vec<DnsServer> {
+    let mut out: Vec<DnsServer> = Vec::new();
+    if cfg.dns_probe_system.unwrap_or(true) { out.extend(system_servers(net_ifs)); }
+    for s in cfg.dns_probe_servers.iter().flatten().filter_map(|s| parse_server(s, "custom")) {
+        if !out.iter().any(|o| o.addr == s.addr && o.transport == s.transport) { out.push(s); }
+    }
+    out
+}
+
+/// 探测域名（未配置时使用内置默认）
+pub fn names_from_config(cfg: &crate::config_utils::AppConfig) -> Vec<String> {
+    cfg.dns_probe_names
+        .clone()
+        .filter(|v| !v.is_empty())
+        .unwrap_or_else(|| DEFAULT_NAMES.iter().map(|s| s.to_string()).collect())
+}
+
+/// 单次查询结果
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct DnsQueryResult {
+    pub server: String,
+    pub source: String,
+    pub transport: String,
+    pub name: String,
+    pub latency_ms: Option<f64>,
+    pub rcode: Option<u8>,
+    pub rcode_name: Option<String>,
+    // A/AAAA 应答地址（排序去重）
+    pub answers: Vec<String>,
+    // UDP 应答被截断（TC 位）
+    pub truncated: bool,
+    pub timeout: bool,
+    pub error: Option<String>,
+}
+
+/// 解析器健康汇总（按 解析器 + 传输方式）
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct DnsServerHealth {
+    pub server: String,
+    pub source: String,
+    pub transport: String,
+    pub queries: u32,
+    pub answered: u32,
+    pub timeouts: u32,
+    pub servfails: u32,
+    pub avg_ms: Option<f64>,
+    pub max_ms: Option<f64>,
+    // ok / degraded（部分超时或 SERVFAIL）/ down（无任何应答）
+    pub status: String,
+    pub flagged: bool,
+}
+
+/// 单域名跨解析器一致性
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct DnsConsistency {
+    pub name: String,
+    pub consistent: bool,
+    // 不同的应答地址集合（首个为多数派）
+    pub variants: Vec<Vec<String>>,
+    // 应答与多数派不一致的解析器
+    pub outliers: Vec<String>,
+}
+
+/// 一轮探测报告
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct DnsProbeReport {
+    pub timestamp_ms: i64,
+    pub results: Vec<DnsQueryResult>,
+    pub servers: Vec<DnsServerHealth>,
+    pub consistency: Vec<DnsConsistency>,
+    // 被标记的解析器（超时 / SERVFAIL），形如 "8.8.8.8:53/udp"
+    pub flagged: Vec<String>,
+}
+
+/// 解析后的应答
+#[derive(Clone, Debug, PartialEq)]
+pub struct DnsResponse {
+    pub rcode: u8,
+    pub truncated: bool,
+    pub answers: Vec<String>,
+}
+
+pub fn rcode_name(rcode: u8) -> String {
+    match rcode {
+        0 => "NOERROR".to_string(),
+        1 => "FORMERR".to_string(),
+        2 => "SERVFAIL".to_string(),
+        3 => "NXDOMAIN".to_string(),
+        4 => "NOTIMP".to_string(),
+        5 => "REFUSED".to_string(),
+        other => format!("RCODE{}", other),
+    }
+}
+
+/// 跳过（可能压缩的）域名，返回其后的偏移
+fn skip_name(buf: &[u8], mut pos: usize) -> Option<usize> {
+    loop {
+        let len = *buf.get(pos)?;
+        if len == 0 { return Some(pos + 1); }
+        if len & 0xc0 == 0xc0 {
+            buf.get(pos + 1)?;
+            return Some(pos + 2);
+        }
+        pos += 1 + len as usize;
+    }
+}
+
+/// 组装 DNS 查询报文（A 记录，RD=1）；域名非法时返回 None
+pub fn build_dns_query(id: u16, name: &str) -> Option<Vec<u8>> {
+    let mut q = Vec::with_capacity(18 + name.len());
+    q.extend_from_slice(&id.to_be_bytes());
+    // flags=RD，QDCOUNT=1，其余计数为 0
+    q.extend_from_slice(&[0x01, 0x00, 0, 1, 0, 0, 0, 0, 0, 0]);
+    for label in name.trim().trim_end_matches('.').split('.') {
+        if label.is_empty() || label.len() > 63 { return None; }
+        q.push(label.len() as u8);
+        q.extend_from_slice(label.as_bytes());
+    }
+    q.push(0);
+    // QTYPE=A，QCLASS=IN
+    q.extend_from_slice(&[0, 1, 0, 1]);
+    Some(q)
+}
+
+/// 解析应答报文：校验 ID 与 QR 位；截断报文中不完整的记录忽略
+pub fn parse_response(buf: &[u8], id: u16) -> Option<DnsResponse> {
+    if buf.len() < 12 || u16::from_be_bytes([buf[0], buf[1]]) != id || buf[2] & 0x80 == 0 { return None; }
+    let truncated = buf[2] & 0x02 != 0;
+    let rcode = buf[3] & 0x0f;
+    let qdcount = u16::from_be_bytes([buf[4], buf[5]]);
+    let ancount = u16::from_be_bytes([buf[6], buf[7]]);
+    let mut answers: Vec<String> = Vec::new();
+    let mut pos = 12;
+    for _ in 0..qdcount {
+        pos = skip_name(buf, pos)? + 4;
+    }
+    for _ in 0..ancount {
+        let Some(p) = skip_name(buf, pos) else { break };
+        let Some(hdr) = buf.get(p..p + 10) else { break };
+        let rtype = u16::from_be_bytes([hdr[0], hdr[1]]);
+        let rdlen = u16::from_be_bytes([hdr[8], hdr[9]]) as usize;
+        let Some(rdata) = buf.get(p + 10..p + 10 + rdlen) else { break };
+        match (rtype, rdata.len()) {
+            (1, 4) => answers.push(Ipv4Addr::new(rdata[0], rdata[1], rdata[2], rdata[3]).to_string()),
+            (28, 16) => {
+                let mut b = [0u8; 16];
+                b.copy_from_slice(rdata);
+                answers.push(Ipv6Addr::from(b).to_string());
+            }
+            _ => {}
+        }
+        pos = p + 10 + rdlen;
+    }
+    answers.sort();
+    answers.dedup();
+    Some(DnsResponse { rcode, truncated, answers })
+}
+
+enum QueryFail {
+    Timeout,
+    Error(String),
+}
+
+fn io_fail(e: std::io::Error) -> QueryFail {
+    match e.kind() {
+        std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut => QueryFail::Timeout,
+        _ => QueryFail::Error(e.to_string()),
+    }
+}
+
+fn query_udp(server: SocketAddr, packet: &[u8], id: u16, timeout: Duration) -> Result<(f64, DnsResponse), QueryFail> {
+    let bind: SocketAddr = if server.is_ipv4() { ([0, 0, 0, 0], 0).into() } else { (Ipv6Addr::UNSPECIFIED, 0).into() };
+    let sock = UdpSocket::bind(bind).map_err(io_fail)?;
+    sock.connect(server).map_err(io_fail)?;
+    let start = Instant::now();
+    sock.send(packet).map_err(io_fail)?;
+    let deadline = start + timeout;
+    let mut buf = [0u8; 4096];
+    loop {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() { return Err(QueryFail::Timeout); }
+        sock.set_read_timeout(Some(remaining)).map_err(io_fail)?;
+        let n = sock.recv(&mut buf).map_err(io_fail)?;
+        // 其他 ID 的报文（迟到的旧应答）丢弃
+        if let Some(resp) = parse_response(&buf[..n], id) {
+            return Ok((start.elapsed().as_secs_f64() * 1000.0, resp));
+        }
+    }
+}
+
+/// TCP 查询：延迟包含建连（即实际 TCP 解析成本）
+fn query_tcp(server: SocketAddr, packet: &[u8], id: u16, timeout: Duration) -> Result<(f64, DnsResponse), QueryFail> {
+    let start = Instant::now();
+    let mut stream = TcpStream::connect_timeout(&server, timeout).map_err(io_fail)?;
+    stream.set_read_timeout(Some(timeout)).map_err(io_fail)?;
+    stream.set_write_timeout(Some(timeout)).map_err(io_fail)?;
+    let mut framed = (packet.len() as u16).to_be_bytes().to_vec();
+    framed.extend_from_slice(packet);
+    stream.write_all(&framed).map_err(io_fail)?;
+    let mut len = [0u8; 2];
+    stream.read_exact(&mut len).map_err(io_fail)?;
+    let mut body = vec![0u8; u16::from_be_bytes(len) as usize];
+    stream.read_exact(&mut body).map_err(io_fail)?;
+    let resp = parse_response(&body, id).ok_or_else(|| QueryFail::Error("无效的 DNS 应答".to_string()))?;
+    Ok((start.elapsed().as_secs_f64() * 1000.0, resp))
+}
+
+/// 向单个解析器查询一个域名（A 记录）
+pub fn query(server: &DnsServer, transport: DnsTransport, name: &str, timeout_ms: u64) -> DnsQueryResult {
+    let mut out = DnsQueryResult {
+        server: server.addr.to_string(),
+        source: server.source.clone(),
+        transport: transport.label().to_string(),
+        name: name.to_string(),
+        ..Default::default()
+    };
+    let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+    let Some(packet) = build_dns_query(id, name) else {
+        out.error = Some(format!("无效的域名: {}", name));
+        return out;
+    };
+    let timeout = Duration::from_millis(timeout_ms.max(1));
+    let res = match transport {
+        DnsTransport::Tcp => query_tcp(server.addr, &packet, id, timeout),
+        _ => query_udp(server.addr, &packet, id, timeout),
+    };
+    match res {
+        Ok((ms, resp)) => {
+            out.latency_ms = Some(ms);
+            out.rcode = Some(resp.rcode);
+            out.rcode_name = Some(rcode_name(resp.rcode));
+            out.answers = resp.answers;
+            out.truncated = resp.truncated;
+        }
+        Err(QueryFail::Timeout) => out.timeout = true,
+        Err(QueryFail::Error(e)) => out.error = Some(e),
+    }
+    out
+}
+
+/// 执行一轮探测：各解析器并发，同一解析器内按域名顺序查询
+pub fn run_probe(names: &[String], servers: &[DnsServer], transport: DnsTransport, timeout_ms: u64) -> DnsProbeReport {
+    let jobs: Vec<(&DnsServer, DnsTransport)> = servers
+        .iter()
+        .flat_map(|s| s.transport.unwrap_or(transport).expand().iter().map(move |t| (s, *t)))
+        .collect();
+    let results: Vec<DnsQueryResult> = std::thread::scope(|scope| {
+        let handles: Vec<_> = jobs
+            .iter()
+            .map(|(s, t)| scope.spawn(move || names.iter().map(|n| query(s, *t, n, timeout_ms)).collect::<Vec<_>>()))
+            .collect();
+        handles.into_iter().flat_map(|h| h.join().unwrap_or_default()).collect()
+    });
+    summarize(results, chrono::Local::now().timestamp_millis())
+}
+
+/// 汇总解析器健康度与一致性
+pub fn summarize(results: Vec<DnsQueryResult>, timestamp_ms: i64) -> DnsProbeReport {
+    let mut servers: Vec<DnsServerHealth> = Vec::new();
+    for r in &results {
+        let idx = match servers.iter().position(|h| h.server == r.server && h.transport == r.transport) {
+            Some(i) => i,
+            None => {
+                servers.push(DnsServerHealth {
+                    server: r.server.clone(),
+                    source: r.source.clone(),
+                    transport: r.transport.clone(),
+                    ..Default::default()
+                });
+                servers.len() - 1
+            }
+        };
+        let h = &mut servers[idx];
+        h.queries += 1;
+        if r.rcode.is_some() { h.answered += 1; }
+        if r.timeout { h.timeouts += 1; }
+        if r.rcode == Some(2) { h.servfails += 1; }
+    }
+    for h in servers.iter_mut() {
+        let lats: Vec<f64> = results
+            .iter()
+            .filter(|r| r.server == h.server && r.transport == h.transport)
+            .filter_map(|r| r.latency_ms)
+            .collect();
+        h.avg_ms = (!lats.is_empty()).then(|| lats.iter().sum::<f64>() / lats.len() as f64);
+        h.max_ms = lats.iter().copied().reduce(f64::max);
+        h.flagged = h.timeouts > 0 || h.servfails > 0;
+        h.status = if h.answered == 0 {
+            "down"
+        } else if h.flagged {
+            "degraded"
+        } else {
+            "ok"
+        }
+        .to_string();
+    }
+
+    let mut names: Vec<&str> = Vec::new();
+    for r in &results {
+        if !names.contains(&r.name.as_str()) { names.push(&r.name); }
+    }
+    let consistency = names
+        .into_iter()
+        .map(|name| {
+            // 同一解析器 udp/tcp 各一条时只计一次
+            let mut by_server: Vec<(&str, &Vec<String>)> = Vec::new();
+            for r in results.iter().filter(|r| r.name == name && r.rcode == Some(0) && !r.answers.is_empty()) {
+                if !by_server.iter().any(|(s, _)| *s == r.server) { by_server.push((&r.server, &r.answers)); }
+            }
+            let mut variants: Vec<(Vec<String>, usize)> = Vec::new();
+            for (_, answers) in &by_server {
+                match variants.iter_mut().find(|(v, _)| v == *answers) {
+                    Some((_, n)) => *n += 1,
+                    None => variants.push(((*answers).clone(), 1)),
+                }
+            }
+            variants.sort_by_key(|v| std::cmp::Reverse(v.1));
+            let majority = variants.first().map(|(v, _)| v.clone());
+            let outliers = by_server
+                .iter()
+                .filter(|(_, a)| Some(*a) != majority.as_ref())
+                .map(|(s, _)| s.to_string())
+                .collect();
+            DnsConsistency {
+                name: name.to_string(),
+                consistent: variants.len() <= 1,
+                variants: variants.into_iter().map(|(v, _)| v).collect(),
+                outliers,
+            }
+        })
+        .collect();
+
+    let flagged = servers.iter().filter(|h| h.flagged).map(|h| format!("{}/{}", h.server, h.transport)).collect();
+    DnsProbeReport { timestamp_ms, results, servers, consistency, flagged }
+}
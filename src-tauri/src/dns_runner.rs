@@ -0,0 +1,88 @@
+// DNS Runner：解析器延迟与健康探测
+// 说明：
+// - 依赖 BaseGate 做并发防重入
+// - trigger() 异步执行一轮探测，完成后更新内部快照（DnsProbeReport 的 JSON）
+// - 系统解析器列表由采集循环从网卡信息刷新，经配置提供器传入
+
+use std::sync::{Arc, Mutex};
+use crate::runner::{Runner, BaseGate};
+use crate::dns_probe::{run_probe, DnsServer, DnsTransport};
+
+/// 单轮 DNS 探测配置
+#[derive(Clone, Debug)]
+pub struct DnsRunConfig {
+    pub names: Vec<String>,
+    pub servers: Vec<DnsServer>,
+    pub transport: DnsTransport,
+    pub timeout_ms: u64,
+}
+
+impl DnsRunConfig {
+    pub fn from_config(cfg: &crate::config_utils::AppConfig, net_ifs: Option<&[crate::types::NetIfPayload]>) -> Self {
+        Self {
+            names: crate::dns_probe::names_from_config(cfg),
+            servers: crate::dns_probe::servers_from_config(cfg, net_ifs),
+            transport: cfg.dns_probe_transport.unwrap_or_default(),
+            timeout_ms: cfg.dns_probe_timeout_ms.unwrap_or(crate::dns_probe::DEFAULT_TIMEOUT_MS),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct DnsRunner {
+    gate: Arc<BaseGate>,
+    // 配置提供器：每轮触发时读取
+    cfg_provider: Arc<dyn Fn() -> DnsRunConfig + Send + Sync>,
+    // 最近一次结果快照
+    last_snapshot: Arc<Mutex<serde_json::Value>>,
+}
+
+impl DnsRunner {
+    pub fn new<F>(cfg_provider: F) -> Self
+    where
+        F: Fn() -> DnsRunConfig + Send + Sync + 'static,
+    {
+        Self {
+            gate: Arc::new(BaseGate::new()),
+            cfg_provider: Arc::new(cfg_provider),
+            last_snapshot: Arc::new(Mutex::new(serde_json::json!({}))),
+        }
+    }
+}
+
+impl Runner for DnsRunner {
+    fn name(&self) -> &'static str { "dns_runner" }
+
+    fn trigger(&self, now_ms: i64) {
+        // 防重入
+        if !self.gate.try_enter() { return; }
+        self.gate.set_running();
+        let gate_c = self.gate.clone();
+        let snap_c = self.last_snapshot.clone();
+        let cfg_c = self.cfg_provider.clone();
+        std::thread::spawn(move || {
+            let cfg = (cfg_c)();
+            // 无可用解析器：保留旧快照，仅退出运行态
+            if cfg.servers.is_empty() || cfg.names.is_empty() {
+                gate_c.exit();
+                return;
+            }
+            let mut report = run_probe(&cfg.names, &cfg.servers, cfg.transport, cfg.timeout_ms);
+            report.timestamp_ms = now_ms;
+            if let Ok(v) = serde_json::to_value(&report) {
+                if let Ok(mut g) = snap_c.lock() { *g = v; }
+            }
+            gate_c.mark_ok_and_exit(now_ms);
+        });
+    }
+
+    fn is_running(&self) -> bool { self.gate.is_running() }
+    fn last_ok_ms(&self) -> Option<i64> { self.gate.last_ok_ms() }
+
+    fn snapshot_json(&self) -> serde_json::Value {
+        match self.last_snapshot.lock() {
+            Ok(g) => (*g).clone(),
+            Err(_) => serde_json::json!({}),
+        }
+    }
+}
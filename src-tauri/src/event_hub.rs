@@ -0,0 +1,76 @@
+// 进程内事件总线
+// 说明：
+// - 与 Tauri 事件并行：采样线程在 emit 的同时 publish 到总线，供本地 API/WebSocket 等非 webview 消费者订阅
+// - 流名称："snapshot" | "agg" | "smart" | "config" | "alert" | "public_ip" | "speedtest"（对应 sensor://snapshot 等事件）
+// - 订阅端使用有界通道：消费过慢时丢弃新事件，不阻塞采样线程
+// - 每个流缓存最近一条负载，供 HTTP 查询当前值
+
+use std::collections::HashMap;
+use std::sync::mpsc::{self, Receiver, SyncSender, TrySendError};
+use std::sync::{Arc, Mutex, OnceLock};
+
+use serde::Serialize;
+
+/// 已知事件流
+pub const STREAMS: [&str; 7] = ["snapshot", "agg", "smart", "config", "alert", "public_ip", "speedtest"];
+
+/// 总线事件（负载为已序列化的 JSON，订阅者之间共享）
+#[derive(Clone, Debug)]
+pub struct HubEvent {
+    pub stream: &'static str,
+    pub ts_ms: i64,
+    pub payload: Arc<serde_json::Value>,
+}
+
+#[derive(Default)]
+struct Hub {
+    subscribers: Vec<SyncSender<HubEvent>>,
+    last: HashMap<&'static str, HubEvent>,
+}
+
+static HUB: OnceLock<Mutex<Hub>> = OnceLock::new();
+
+fn hub() -> &'static Mutex<Hub> {
+    HUB.get_or_init(|| Mutex::new(Hub::default()))
+}
+
+/// 规范化流名称（未知流返回 None）
+pub fn stream_name(s: &str) -> Option<&'static str> {
+    STREAMS.iter().copied().find(|x| x.eq_ignore_ascii_case(s.trim()))
+}
+
+/// 发布事件：缓存最新值并分发给所有订阅者
+pub fn publish<T: Serialize>(stream: &'static str, payload: &T) {
+    let value = match serde_json::to_value(payload) {
+        Ok(v) => v,
+        Err(e) => {
+            eprintln!("[event_hub] 序列化 {} 失败: {}", stream, e);
+            return;
+        }
+    };
+    let ev = HubEvent {
+        stream,
+        ts_ms: chrono::Local::now().timestamp_millis(),
+        payload: Arc::new(value),
+    };
+    if let Ok(mut h) = hub().lock() {
+        // 已断开的订阅者顺带清理；队列已满则丢弃本条
+        h.subscribers.retain(|tx| !matches!(tx.try_send(ev.clone()), Err(TrySendError::Disconnected(_))));
+        h.last.insert(stream, ev);
+    }
+}
+
+/// 订阅全部事件流（由调用方自行按流过滤）；capacity 为积压上限
+pub fn subscribe(capacity: usize) -> Receiver<HubEvent> {
+    let (tx, rx) = mpsc::sync_channel(capacity.max(1));
+    if let Ok(mut h) = hub().lock() {
+        h.subscribers.push(tx);
+    }
+    rx
+}
+
+/// 获取某个流最近一次的事件
+pub fn last(stream: &str) -> Option<HubEvent> {
+    let name = stream_name(stream)?;
+    hub().lock().ok().and_then(|h| h.last.get(name).cloned())
+}
@@ -0,0 +1,423 @@
+// =============================================================================
+// HTTP(S) 分阶段计时探测
+// - 按阶段记录：DNS 解析 / TCP 连接 / TLS 握手 / 首字节（TTFB）/ 总耗时
+// - 发送 HEAD 请求，只读取响应头；TLS 时 ALPN 声明 h2 与 http/1.1，按协商结果走 HTTP/2 或 HTTP/1.1
+// - HTTP/2 仅实现单请求所需的最小子集：前言 + SETTINGS + HEADERS，HPACK 只解码首个头字段 :status
+// - 附带状态码、协议版本、ALPN、TLS 版本与证书剩余天数（由叶子证书 notAfter 计算）
+// - 可指定连接地址以固定地址族：此时跳过 DNS，TLS SNI/Host 仍使用 URL 中的主机名
+// - 各阶段耗时互不包含；total_ms 为从开始到响应头读完的总时间
+// =============================================================================
+
+use std::io::{Read, Write};
+use std::net::{IpAddr, SocketAddr, TcpStream, ToSocketAddrs};
+use std::sync::{Arc, OnceLock};
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+// 响应头上限：超出视为异常响应
+const MAX_HEAD_BYTES: usize = 64 * 1024;
+// 探测声明的 ALPN（优先 h2）
+const PROBE_ALPN: &[&[u8]] = &[b"h2", b"http/1.1"];
+const H2_PREFACE: &[u8] = b"PRI * HTTP/2.0\r\n\r\nSM\r\n\r\n";
+
+/// 分阶段计时结果（失败时已完成阶段的耗时仍保留）
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct HttpTiming {
+    pub dns_ms: Option<f64>,
+    pub connect_ms: Option<f64>,
+    pub tls_ms: Option<f64>,
+    pub ttfb_ms: Option<f64>,
+    pub total_ms: Option<f64>,
+    pub status: Option<u16>,
+    // 响应行中的协议版本，如 "HTTP/1.1"
+    pub protocol: Option<String>,
+    // 协商的 TLS 版本，如 "TLSv1.3"（明文 http 为 None）
+    pub tls_version: Option<String>,
+    // TLS 协商的 ALPN 协议，如 "h2"、"http/1.1"（服务端未选择或明文 http 为 None）
+    pub alpn: Option<String>,
+    // 证书剩余有效天数（已过期为负数）
+    pub cert_expiry_days: Option<i64>,
+    pub error: Option<String>,
+}
+
+pub(crate) struct UrlParts {
+    pub tls: bool,
+    pub host: String,
+    pub port: u16,
+    // Host 头（保留 URL 中的端口与 IPv6 方括号）
+    pub authority: String,
+    pub path: String,
+}
+
+pub(crate) fn parse_url(url: &str) -> Option<UrlParts> {
+    let url = url.trim();
+    let (tls, rest) = if let Some(r) = url.strip_prefix("https://") {
+        (true, r)
+    } else {
+        (false, url.strip_prefix("http://")?)
+    };
+    let end = rest.find(['/', '?', '#']).unwrap_or(rest.len());
+    let authority = rest[..end].rsplit('@').next().unwrap_or("");
+    let (host, port) = crate::ping_utils::split_host_port(authority);
+    if host.is_empty() { return None; }
+    let mut path = rest[end..].split('#').next().unwrap_or("").to_string();
+    if !path.starts_with('/') { path.insert(0, '/'); }
+    Some(UrlParts {
+        tls,
+        host,
+        port: port.unwrap_or(if tls { 443 } else { 80 }),
+        authority: authority.to_string(),
+        path,
+    })
+}
+
+fn ms_since(t: Instant) -> f64 {
+    t.elapsed().as_secs_f64() * 1000.0
+}
+
+/// 发起一次分阶段计时的 HEAD 请求
+pub fn probe(url: &str, ip: Option<IpAddr>, timeout_ms: u64) -> HttpTiming {
+    let mut timing = HttpTiming::default();
+    if let Err(e) = run(url, ip, timeout_ms, &mut timing) {
+        timing.error = Some(e);
+    }
+    timing
+}
+
+fn run(url: &str, ip: Option<IpAddr>, timeout_ms: u64, timing: &mut HttpTiming) -> Result<(), String> {
+    let u = parse_url(url).ok_or_else(|| format!("无效的 URL: {}", url))?;
+    let timeout = Duration::from_millis(timeout_ms.max(1));
+    let start = Instant::now();
+
+    // 1) DNS
+    let addr = match ip {
+        Some(ip) => SocketAddr::new(ip, u.port),
+        None => {
+            let addr = (u.host.as_str(), u.port)
+                .to_socket_addrs()
+                .map_err(|e| format!("DNS 解析失败: {}", e))?
+                .next()
+                .ok_or_else(|| "DNS 解析无结果".to_string())?;
+            timing.dns_ms = Some(ms_since(start));
+            addr
+        }
+    };
+
+    // 2) TCP 连接
+    let phase = Instant::now();
+    let mut tcp = TcpStream::connect_timeout(&addr, timeout).map_err(|e| format!("连接失败: {}", e))?;
+    timing.connect_ms = Some(ms_since(phase));
+    tcp.set_read_timeout(Some(timeout)).map_err(|e| e.to_string())?;
+    tcp.set_write_timeout(Some(timeout)).map_err(|e| e.to_string())?;
+    let _ = tcp.set_nodelay(true);
+
+    let request = format!(
+        "HEAD {} HTTP/1.1\r\nHost: {}\r\nUser-Agent: sys-sensor\r\nAccept: */*\r\nConnection: close\r\n\r\n",
+        u.path, u.authority
+    );
+
+    if u.tls {
+        // 3) TLS 握手
+        let name = rustls::pki_types::ServerName::try_from(u.host.clone()).map_err(|e| format!("无效的 TLS 主机名: {}", e))?;
+        let mut conn = rustls::ClientConnection::new(probe_tls_config()?, name).map_err(|e| e.to_string())?;
+        let phase = Instant::now();
+        while conn.is_handshaking() {
+            conn.complete_io(&mut tcp).map_err(|e| format!("TLS 握手失败: {}", e))?;
+        }
+        timing.tls_ms = Some(ms_since(phase));
+        timing.tls_version = conn.protocol_version().map(tls_version_label);
+        let now = chrono::Utc::now().timestamp();
+        timing.cert_expiry_days = conn
+            .peer_certificates()
+            .and_then(|certs| certs.first())
+            .and_then(|c| cert_not_after(c))
+            .map(|not_after| (not_after - now).div_euclid(86_400));
+        timing.alpn = conn.alpn_protocol().map(|p| String::from_utf8_lossy(p).into_owned());
+        let mut stream = rustls::StreamOwned::new(conn, tcp);
+        if timing.alpn.as_deref() == Some("h2") {
+            exchange_h2(&mut stream, &u.authority, &u.path, timing)?;
+        } else {
+            exchange(&mut stream, &request, timing)?;
+        }
+    } else {
+        exchange(&mut tcp, &request, timing)?;
+    }
+    timing.total_ms = Some(ms_since(start));
+    Ok(())
+}
+
+/// 4) 发送请求并读取响应头：TTFB 为请求写出到收到首字节的时间
+fn exchange<S: Read + Write>(s: &mut S, request: &str, timing: &mut HttpTiming) -> Result<(), String> {
+    let phase = Instant::now();
+    s.write_all(request.as_bytes()).and_then(|_| s.flush()).map_err(|e| format!("发送请求失败: {}", e))?;
+    let mut buf: Vec<u8> = Vec::new();
+    let mut tmp = [0u8; 4096];
+    loop {
+        let n = s.read(&mut tmp).map_err(|e| format!("读取响应失败: {}", e))?;
+        if n == 0 { break; }
+        if buf.is_empty() { timing.ttfb_ms = Some(ms_since(phase)); }
+        buf.extend_from_slice(&tmp[..n]);
+        if buf.windows(4).any(|w| w == b"\r\n\r\n") || buf.len() > MAX_HEAD_BYTES { break; }
+    }
+    let head = String::from_utf8_lossy(&buf);
+    let mut parts = head.lines().next().unwrap_or("").split_whitespace();
+    timing.protocol = parts.next().filter(|p| p.starts_with("HTTP/")).map(str::to_string);
+    timing.status = parts.next().and_then(|c| c.parse().ok());
+    if timing.protocol.is_none() || timing.status.is_none() {
+        return Err("无效的 HTTP 响应".to_string());
+    }
+    Ok(())
+}
+
+/// 4') HTTP/2：发送前言 + SETTINGS + HEADERS（HEAD，END_STREAM），读到流 1 的响应 HEADERS 为止
+/// TTFB 为请求写出到收到流 1 首帧的时间（服务端 SETTINGS 与请求无关，不计入）
+pub(crate) fn exchange_h2<S: Read + Write>(s: &mut S, authority: &str, path: &str, timing: &mut HttpTiming) -> Result<(), String> {
+    // 头字段均为“不索引字面量”，名称取静态表：:method=2 / :path=4 / :authority=1 / user-agent=58；:scheme https=7（索引）
+    let mut block = vec![0x02];
+    hpack_str(&mut block, "HEAD");
+    block.push(0x87);
+    block.push(0x04);
+    hpack_str(&mut block, path);
+    block.push(0x01);
+    hpack_str(&mut block, authority);
+    hpack_int(&mut block, 4, 0x00, 58);
+    hpack_str(&mut block, "sys-sensor");
+
+    let mut out = H2_PREFACE.to_vec();
+    out.extend(h2_frame(0x4, 0, 0, &[]));
+    out.extend(h2_frame(0x1, 0x4 | 0x1, 1, &block));
+    let phase = Instant::now();
+    s.write_all(&out).and_then(|_| s.flush()).map_err(|e| format!("发送请求失败: {}", e))?;
+    loop {
+        let (kind, flags, stream, payload) = read_h2_frame(s)?;
+        match (kind, stream) {
+            // 服务端 SETTINGS：回 ACK
+            (0x4, 0) if flags & 0x1 == 0 => {
+                s.write_all(&h2_frame(0x4, 0x1, 0, &[])).and_then(|_| s.flush()).map_err(|e| format!("发送请求失败: {}", e))?;
+            }
+            (0x7, 0) => return Err("服务端关闭连接（GOAWAY）".to_string()),
+            (_, 1) => {
+                if timing.ttfb_ms.is_none() { timing.ttfb_ms = Some(ms_since(phase)); }
+                match kind {
+                    0x3 => return Err("请求被重置（RST_STREAM）".to_string()),
+                    0x1 => {
+                        timing.protocol = Some("HTTP/2".to_string());
+                        timing.status = h2_header_block(flags, &payload).and_then(hpack_status);
+                        return if timing.status.is_some() { Ok(()) } else { Err("无效的 HTTP/2 响应".to_string()) };
+                    }
+                    _ => {}
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+fn h2_frame(kind: u8, flags: u8, stream: u32, payload: &[u8]) -> Vec<u8> {
+    let len = payload.len() as u32;
+    let mut f = vec![(len >> 16) as u8, (len >> 8) as u8, len as u8, kind, flags];
+    f.extend_from_slice(&stream.to_be_bytes());
+    f.extend_from_slice(payload);
+    f
+}
+
+/// 读取一帧：(类型, 标志, 流 ID, 负载)
+fn read_h2_frame<S: Read>(s: &mut S) -> Result<(u8, u8, u32, Vec<u8>), String> {
+    let mut head = [0u8; 9];
+    s.read_exact(&mut head).map_err(|e| format!("读取响应失败: {}", e))?;
+    let len = u32::from_be_bytes([0, head[0], head[1], head[2]]) as usize;
+    if len > MAX_HEAD_BYTES { return Err("无效的 HTTP/2 响应".to_string()); }
+    let stream = u32::from_be_bytes([head[5], head[6], head[7], head[8]]) & 0x7fff_ffff;
+    let mut payload = vec![0u8; len];
+    s.read_exact(&mut payload).map_err(|e| format!("读取响应失败: {}", e))?;
+    Ok((head[3], head[4], stream, payload))
+}
+
+/// 去掉 HEADERS 帧的填充（PADDED）与优先级（PRIORITY）字段，返回头块片段
+fn h2_header_block(flags: u8, payload: &[u8]) -> Option<&[u8]> {
+    let mut b = payload;
+    let mut pad = 0usize;
+    if flags & 0x8 != 0 {
+        pad = *b.first()? as usize;
+        b = &b[1..];
+    }
+    if flags & 0x20 != 0 { b = b.get(5..)?; }
+    b.get(..b.len().checked_sub(pad)?)
+}
+
+/// HPACK 整数编码（n 位前缀，flags 为首字节高位）
+fn hpack_int(out: &mut Vec<u8>, n: u32, flags: u8, v: u64) {
+    let max = (1u64 << n) - 1;
+    if v < max {
+        out.push(flags | v as u8);
+        return;
+    }
+    out.push(flags | max as u8);
+    let mut v = v - max;
+    while v >= 128 {
+        out.push((v % 128) as u8 | 0x80);
+        v /= 128;
+    }
+    out.push(v as u8);
+}
+
+/// HPACK 字符串（不使用 Huffman）
+fn hpack_str(out: &mut Vec<u8>, s: &str) {
+    hpack_int(out, 7, 0x00, s.len() as u64);
+    out.extend_from_slice(s.as_bytes());
+}
+
+fn hpack_read_int(b: &[u8], n: u32) -> Option<(u64, &[u8])> {
+    let max = (1u64 << n) - 1;
+    let mut v = (*b.first()? as u64) & max;
+    let mut rest = &b[1..];
+    if v < max { return Some((v, rest)); }
+    let mut shift = 0;
+    loop {
+        let byte = *rest.first()?;
+        rest = &rest[1..];
+        v += ((byte & 0x7f) as u64) << shift;
+        if byte & 0x80 == 0 { return Some((v, rest)); }
+        shift += 7;
+        if shift > 28 { return None; }
+    }
+}
+
+/// 从响应头块解码 :status（伪头字段必在最前；新连接的动态表为空，只需静态表）
+fn hpack_status(mut b: &[u8]) -> Option<u16> {
+    // 跳过动态表大小更新（001xxxxx）
+    while b.first().is_some_and(|&x| x & 0xe0 == 0x20) {
+        b = hpack_read_int(b, 5)?.1;
+    }
+    let first = *b.first()?;
+    if first & 0x80 != 0 {
+        // 静态表 8..14：200 / 204 / 206 / 304 / 400 / 404 / 500
+        return match hpack_read_int(b, 7)?.0 {
+            8 => Some(200),
+            9 => Some(204),
+            10 => Some(206),
+            11 => Some(304),
+            12 => Some(400),
+            13 => Some(404),
+            14 => Some(500),
+            _ => None,
+        };
+    }
+    // 字面量：带索引 01xxxxxx（6 位前缀）、不索引 0000xxxx / 永不索引 0001xxxx（4 位前缀）；名称须为 :status
+    let prefix = if first & 0x40 != 0 { 6 } else { 4 };
+    let (name, rest) = hpack_read_int(b, prefix)?;
+    if !(8..=14).contains(&name) { return None; }
+    let huffman = *rest.first()? & 0x80 != 0;
+    let (len, rest) = hpack_read_int(rest, 7)?;
+    let raw = rest.get(..len as usize)?;
+    let text = if huffman { huffman_digits(raw)? } else { String::from_utf8(raw.to_vec()).ok()? };
+    text.parse().ok()
+}
+
+/// Huffman 解码（仅数字：'0'..'2' 为 5 位 00000..00010，'3'..'9' 为 6 位 011001..011111；结尾以全 1 填充）
+fn huffman_digits(raw: &[u8]) -> Option<String> {
+    let mut out = String::new();
+    let (mut code, mut len) = (0u32, 0u32);
+    for i in 0..raw.len() * 8 {
+        code = code << 1 | ((raw[i / 8] >> (7 - i % 8)) & 1) as u32;
+        len += 1;
+        match (len, code) {
+            (5, 0..=2) => out.push((b'0' + code as u8) as char),
+            (6, 25..=31) => out.push((b'3' + (code - 25) as u8) as char),
+            (l, c) if l < 6 || (l < 8 && c == (1 << l) - 1) => continue,
+            _ => return None,
+        }
+        code = 0;
+        len = 0;
+    }
+    (code == (1 << len) - 1).then_some(out)
+}
+
+fn build_tls_config(alpn: &[&[u8]]) -> Result<rustls::ClientConfig, String> {
+    let mut roots = rustls::RootCertStore::empty();
+    roots.extend(webpki_roots::TLS_SERVER_ROOTS.iter().cloned());
+    let provider = Arc::new(rustls::crypto::ring::default_provider());
+    let mut cfg = rustls::ClientConfig::builder_with_provider(provider)
+        .with_safe_default_protocol_versions()
+        .map_err(|e| e.to_string())?
+        .with_root_certificates(roots)
+        .with_no_client_auth();
+    cfg.alpn_protocols = alpn.iter().map(|p| p.to_vec()).collect();
+    Ok(cfg)
+}
+
+/// 探测用 TLS 客户端配置（系统内置根证书，ALPN h2 + http/1.1），进程内复用
+pub(crate) fn probe_tls_config() -> Result<Arc<rustls::ClientConfig>, String> {
+    static CFG: OnceLock<Arc<rustls::ClientConfig>> = OnceLock::new();
+    if let Some(cfg) = CFG.get() { return Ok(cfg.clone()); }
+    let cfg = build_tls_config(PROBE_ALPN)?;
+    Ok(CFG.get_or_init(|| Arc::new(cfg)).clone())
+}
+
+/// 仅说 HTTP/1.1 的客户端（如测速）使用的 TLS 配置（ALPN 固定 http/1.1），进程内复用
+pub(crate) fn tls_config() -> Result<Arc<rustls::ClientConfig>, String> {
+    static CFG: OnceLock<Arc<rustls::ClientConfig>> = OnceLock::new();
+    if let Some(cfg) = CFG.get() { return Ok(cfg.clone()); }
+    let cfg = build_tls_config(&[b"http/1.1"])?;
+    Ok(CFG.get_or_init(|| Arc::new(cfg)).clone())
+}
+
+fn tls_version_label(v: rustls::ProtocolVersion) -> String {
+    match v {
+        rustls::ProtocolVersion::TLSv1_3 => "TLSv1.3".to_string(),
+        rustls::ProtocolVersion::TLSv1_2 => "TLSv1.2".to_string(),
+        other => format!("{:?}", other),
+    }
+}
+
+/// 读取一个 DER TLV：返回 (tag, 内容, 剩余字节)
+fn der_next(b: &[u8]) -> Option<(u8, &[u8], &[u8])> {
+    let tag = *b.first()?;
+    let first = *b.get(1)?;
+    let (len, hdr) = if first & 0x80 == 0 {
+        (first as usize, 2)
+    } else {
+        let n = (first & 0x7f) as usize;
+        if n == 0 || n > 4 { return None; }
+        let mut len = 0usize;
+        for i in 0..n { len = (len << 8) | *b.get(2 + i)? as usize; }
+        (len, 2 + n)
+    };
+    let body = b.get(hdr..hdr.checked_add(len)?)?;
+    Some((tag, body, &b[hdr + len..]))
+}
+
+/// 解析 X.509 证书的 notAfter（Unix 秒）
+/// Certificate ::= SEQUENCE { tbsCertificate, ... }
+/// TBSCertificate ::= SEQUENCE { [0] version?, serialNumber, signature, issuer, validity, ... }
+pub fn cert_not_after(der: &[u8]) -> Option<i64> {
+    let (tag, cert, _) = der_next(der)?;
+    if tag != 0x30 { return None; }
+    let (tag, tbs, _) = der_next(cert)?;
+    if tag != 0x30 { return None; }
+    let mut rest = tbs;
+    let (tag, _, after_version) = der_next(rest)?;
+    if tag == 0xa0 { rest = after_version; }
+    // 跳过 serialNumber / signature / issuer
+    for _ in 0..3 { rest = der_next(rest)?.2; }
+    let (tag, validity, _) = der_next(rest)?;
+    if tag != 0x30 { return None; }
+    let (_, _, after_not_before) = der_next(validity)?;
+    let (tag, value, _) = der_next(after_not_before)?;
+    parse_asn1_time(tag, value)
+}
+
+/// UTCTime（YYMMDDHHMMSSZ，50 以下为 20xx）/ GeneralizedTime（YYYYMMDDHHMMSSZ）转 Unix 秒
+pub fn parse_asn1_time(tag: u8, value: &[u8]) -> Option<i64> {
+    let s = std::str::from_utf8(value).ok()?.strip_suffix('Z')?;
+    let full = match tag {
+        0x17 => {
+            let yy: i32 = s.get(0..2)?.parse().ok()?;
+            format!("{}{}", if yy >= 50 { 1900 + yy } else { 2000 + yy }, s.get(2..)?)
+        }
+        0x18 => s.to_string(),
+        _ => return None,
+    };
+    chrono::NaiveDateTime::parse_from_str(&full, "%Y%m%d%H%M%S").ok().map(|dt| dt.and_utc().timestamp())
+}
@@ -0,0 +1,435 @@
+// =============================================================================
+// Linux ICMP 回显（ping）
+// - 优先使用非特权数据报套接字 SOCK_DGRAM + IPPROTO_ICMP/ICMPV6（需 net.ipv4.ping_group_range 包含当前组）
+// - 不允许时回退原始套接字 SOCK_RAW（需 root 或 CAP_NET_RAW）
+// - 按标识符/序号/源地址匹配回复：数据报套接字的标识符由内核改写为本地端口，仅校验序号
+// - TTL/Hop Limit 通过 IP_RECVTTL / IPV6_RECVHOPLIMIT 辅助数据读取（原始 IPv4 套接字直接取 IP 头）
+// - 每个探测独立超时；期间收到的无关报文（其他进程的回复、ICMP 差错）直接丢弃
+// - 逐跳探测（路径分析）：按 TTL 发送 ICMP 回显或 UDP 高端口数据报，中间路由的超时差错经 IP_RECVERR 错误队列读取
+// =============================================================================
+
+use std::mem;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+use std::sync::atomic::{AtomicBool, AtomicU16, Ordering};
+use std::time::{Duration, Instant};
+
+use crate::traceroute::HopReply;
+
+const ICMP_ECHO_REQUEST: u8 = 8;
+const ICMP_ECHO_REPLY: u8 = 0;
+const ICMPV6_ECHO_REQUEST: u8 = 128;
+const ICMPV6_ECHO_REPLY: u8 = 129;
+// 负载长度（与 Windows IcmpSendEcho 保持一致）
+const PAYLOAD_LEN: usize = 32;
+// 逐跳 UDP 探测的起始目的端口（与 traceroute 一致，实际端口为 起始 + TTL）
+const TRACE_UDP_PORT: u16 = 33434;
+
+static NEXT_SEQ: AtomicU16 = AtomicU16::new(1);
+// 无权限创建套接字时只提示一次（之后 RTT 探测静默回退 TCP/HTTPS）
+static WARNED: AtomicBool = AtomicBool::new(false);
+
+/// 回显结果
+#[derive(Clone, Debug, PartialEq)]
+pub struct EchoReply {
+    pub rtt_ms: f64,
+    // 回复报文的 TTL（IPv6 为 Hop Limit）；无法读取时为 None
+    pub ttl: Option<u8>,
+    pub seq: u16,
+    // 是否使用了原始套接字
+    pub raw: bool,
+}
+
+/// 发送一次 ICMP 回显请求并等待匹配的回复
+pub fn echo(ip: IpAddr, timeout: Duration) -> Result<EchoReply, String> {
+    let seq = NEXT_SEQ.fetch_add(1, Ordering::Relaxed);
+    let (fd, raw) = open_socket(ip)?;
+    // 原始套接字的标识符需自行保证唯一（进程号），数据报套接字由内核分配
+    let ident = (std::process::id() as u16) ^ 0x5353;
+    enable_ttl(&fd, ip);
+
+    let packet = build_request(ip, ident, seq);
+    let (addr, addr_len) = sockaddr(ip);
+    let start = Instant::now();
+    let sent = unsafe {
+        libc::sendto(
+            fd.as_raw_fd(),
+            packet.as_ptr().cast(),
+            packet.len(),
+            0,
+            (&addr as *const libc::sockaddr_storage).cast(),
+            addr_len,
+        )
+    };
+    if sent < 0 {
+        return Err(format!("发送 ICMP 请求失败: {}", std::io::Error::last_os_error()));
+    }
+
+    let deadline = start + timeout;
+    let mut buf = [0u8; 1500];
+    loop {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            return Err(format!("ICMP 超时（{}ms）", timeout.as_millis()));
+        }
+        let mut pfd = libc::pollfd { fd: fd.as_raw_fd(), events: libc::POLLIN, revents: 0 };
+        let ready = unsafe { libc::poll(&mut pfd, 1, remaining.as_millis().clamp(1, i32::MAX as u128) as i32) };
+        if ready < 0 {
+            let err = std::io::Error::last_os_error();
+            if err.kind() == std::io::ErrorKind::Interrupted { continue; }
+            return Err(format!("等待 ICMP 回复失败: {}", err));
+        }
+        if ready == 0 { continue; }
+
+        let Some((len, from, cmsg_ttl)) = recv(&fd, &mut buf) else { continue };
+        let rtt_ms = start.elapsed().as_secs_f64() * 1000.0;
+        if from != Some(ip) { continue; }
+        let Some((icmp, ip_ttl)) = strip_ip_header(ip, raw, &buf[..len]) else { continue };
+        if !matches_reply(ip, raw, ident, seq, icmp) { continue; }
+        return Ok(EchoReply { rtt_ms, ttl: cmsg_ttl.or(ip_ttl), seq, raw });
+    }
+}
+
+/// 以指定 TTL 发送一个逐跳探测并等待应答（Ok(None) 表示本跳超时无应答）：
+/// - udp=false：ICMP 回显请求，中间路由返回超时差错，目的地返回回显应答
+/// - udp=true：发往高端口的 UDP 数据报（无需特权），目的地返回端口不可达
+pub fn probe_hop(ip: IpAddr, ttl: u8, udp: bool, timeout: Duration) -> Result<Option<HopReply>, String> {
+    let seq = NEXT_SEQ.fetch_add(1, Ordering::Relaxed);
+    let ident = (std::process::id() as u16) ^ 0x5353;
+    let (fd, raw) = if udp { (open_udp_socket(ip)?, false) } else { open_socket(ip)? };
+    let (ttl_level, ttl_opt, err_opt) = match ip {
+        IpAddr::V4(_) => (libc::IPPROTO_IP, libc::IP_TTL, libc::IP_RECVERR),
+        IpAddr::V6(_) => (libc::IPPROTO_IPV6, libc::IPV6_UNICAST_HOPS, libc::IPV6_RECVERR),
+    };
+    set_int_opt(&fd, ttl_level, ttl_opt, ttl as libc::c_int).map_err(|e| format!("设置 TTL 失败: {}", e))?;
+    set_int_opt(&fd, ttl_level, err_opt, 1).map_err(|e| format!("启用错误队列失败: {}", e))?;
+
+    // 连接到目的地：原始套接字只接收该地址相关的差错，UDP 的端口不可达也会进入错误队列
+    let (mut addr, addr_len) = sockaddr(ip);
+    if udp { set_port(&mut addr, ip, TRACE_UDP_PORT.wrapping_add(ttl as u16)); }
+    if unsafe { libc::connect(fd.as_raw_fd(), (&addr as *const libc::sockaddr_storage).cast(), addr_len) } < 0 {
+        return Err(format!("连接失败: {}", std::io::Error::last_os_error()));
+    }
+
+    let packet = if udp { vec![0x61u8; PAYLOAD_LEN] } else { build_request(ip, ident, seq) };
+    let start = Instant::now();
+    if unsafe { libc::send(fd.as_raw_fd(), packet.as_ptr().cast(), packet.len(), 0) } < 0 {
+        let err = std::io::Error::last_os_error();
+        // 已有差错排队（如端口不可达）时 send 可能直接报错，交给下方错误队列处理
+        if err.raw_os_error() != Some(libc::ECONNREFUSED) {
+            return Err(format!("发送探测失败: {}", err));
+        }
+    }
+
+    let deadline = start + timeout;
+    let mut buf = [0u8; 1500];
+    loop {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() { return Ok(None); }
+        let mut pfd = libc::pollfd { fd: fd.as_raw_fd(), events: libc::POLLIN, revents: 0 };
+        let ready = unsafe { libc::poll(&mut pfd, 1, remaining.as_millis().clamp(1, i32::MAX as u128) as i32) };
+        if ready < 0 {
+            let err = std::io::Error::last_os_error();
+            if err.kind() == std::io::ErrorKind::Interrupted { continue; }
+            return Err(format!("等待应答失败: {}", err));
+        }
+        if ready == 0 { continue; }
+        let rtt_ms = start.elapsed().as_secs_f64() * 1000.0;
+
+        if pfd.revents & libc::POLLERR != 0 {
+            while let Some(e) = recv_err(&fd, &mut buf) {
+                // ICMP 探测按序号（原始套接字再加标识符）确认差错对应本次请求
+                if !udp && (e.payload.len() < 8 || !matches_quoted(raw, ident, seq, &e.payload)) { continue; }
+                if let Some(hop) = classify_error(ip, &e, rtt_ms)? { return Ok(Some(hop)); }
+            }
+        }
+        if pfd.revents & libc::POLLIN != 0 {
+            let Some((len, from, _)) = recv(&fd, &mut buf) else { continue };
+            if from != Some(ip) { continue; }
+            // UDP：目的端口恰好有服务应答，同样视为到达
+            if udp { return Ok(Some(HopReply { from: ip, rtt_ms, reached: true })); }
+            let Some((icmp, _)) = strip_ip_header(ip, raw, &buf[..len]) else { continue };
+            if matches_reply(ip, raw, ident, seq, icmp) {
+                return Ok(Some(HopReply { from: ip, rtt_ms, reached: true }));
+            }
+        }
+    }
+}
+
+/// 错误队列中的一条差错
+struct SockErr {
+    origin: u8,
+    ty: u8,
+    code: u8,
+    errno: u32,
+    offender: Option<IpAddr>,
+    // 被引用的原始报文（ICMP 探测为回显请求头起）
+    payload: Vec<u8>,
+}
+
+/// 差错引用的原始报文是否为本次回显请求
+fn matches_quoted(raw: bool, ident: u16, seq: u16, quoted: &[u8]) -> bool {
+    u16::from_be_bytes([quoted[6], quoted[7]]) == seq && (!raw || u16::from_be_bytes([quoted[4], quoted[5]]) == ident)
+}
+
+/// 差错归类：超时差错为中间跳；不可达差错来自目的地（或为端口不可达）视为到达；本地错误返回 Err
+fn classify_error(ip: IpAddr, e: &SockErr, rtt_ms: f64) -> Result<Option<HopReply>, String> {
+    let (time_exceeded, unreachable, port_unreachable) = match e.origin {
+        libc::SO_EE_ORIGIN_ICMP => (11, 3, 3),
+        libc::SO_EE_ORIGIN_ICMP6 => (3, 1, 4),
+        libc::SO_EE_ORIGIN_LOCAL => {
+            return Err(format!("本地错误: {}", std::io::Error::from_raw_os_error(e.errno as i32)));
+        }
+        _ => return Ok(None),
+    };
+    let Some(from) = e.offender else { return Ok(None) };
+    let reached = if e.ty == time_exceeded {
+        false
+    } else if e.ty == unreachable {
+        from == ip || e.code == port_unreachable
+    } else {
+        return Ok(None);
+    };
+    Ok(Some(HopReply { from, rtt_ms, reached }))
+}
+
+/// 读取一条错误队列消息（MSG_ERRQUEUE）：返回扩展错误与差错来源地址
+fn recv_err(fd: &OwnedFd, buf: &mut [u8]) -> Option<SockErr> {
+    let mut name: libc::sockaddr_storage = unsafe { mem::zeroed() };
+    let mut iov = libc::iovec { iov_base: buf.as_mut_ptr().cast(), iov_len: buf.len() };
+    let mut control = [0u64; 32];
+    let mut msg: libc::msghdr = unsafe { mem::zeroed() };
+    msg.msg_name = (&mut name as *mut libc::sockaddr_storage).cast();
+    msg.msg_namelen = mem::size_of::<libc::sockaddr_storage>() as libc::socklen_t;
+    msg.msg_iov = &mut iov;
+    msg.msg_iovlen = 1;
+    msg.msg_control = control.as_mut_ptr().cast();
+    msg.msg_controllen = mem::size_of_val(&control) as _;
+
+    let n = unsafe { libc::recvmsg(fd.as_raw_fd(), &mut msg, libc::MSG_ERRQUEUE | libc::MSG_DONTWAIT) };
+    if n < 0 { return None; }
+
+    unsafe {
+        let mut cmsg = libc::CMSG_FIRSTHDR(&msg);
+        while !cmsg.is_null() {
+            let c = &*cmsg;
+            let is_err = (c.cmsg_level == libc::IPPROTO_IP && c.cmsg_type == libc::IP_RECVERR)
+                || (c.cmsg_level == libc::IPPROTO_IPV6 && c.cmsg_type == libc::IPV6_RECVERR);
+            if is_err {
+                let ee_ptr = libc::CMSG_DATA(cmsg).cast::<libc::sock_extended_err>();
+                let ee = std::ptr::read_unaligned(ee_ptr);
+                let offender = from_sockaddr_ptr(libc::SO_EE_OFFENDER(ee_ptr));
+                return Some(SockErr {
+                    origin: ee.ee_origin,
+                    ty: ee.ee_type,
+                    code: ee.ee_code,
+                    errno: ee.ee_errno,
+                    offender,
+                    payload: buf[..n as usize].to_vec(),
+                });
+            }
+            cmsg = libc::CMSG_NXTHDR(&msg, cmsg);
+        }
+    }
+    None
+}
+
+fn open_udp_socket(ip: IpAddr) -> Result<OwnedFd, String> {
+    let domain = if ip.is_ipv4() { libc::AF_INET } else { libc::AF_INET6 };
+    let fd = unsafe { libc::socket(domain, libc::SOCK_DGRAM | libc::SOCK_CLOEXEC, libc::IPPROTO_UDP) };
+    if fd < 0 {
+        return Err(format!("无法创建 UDP 套接字: {}", std::io::Error::last_os_error()));
+    }
+    Ok(unsafe { OwnedFd::from_raw_fd(fd) })
+}
+
+fn set_int_opt(fd: &OwnedFd, level: libc::c_int, opt: libc::c_int, value: libc::c_int) -> std::io::Result<()> {
+    let ret = unsafe {
+        libc::setsockopt(
+            fd.as_raw_fd(),
+            level,
+            opt,
+            (&value as *const libc::c_int).cast(),
+            mem::size_of::<libc::c_int>() as libc::socklen_t,
+        )
+    };
+    if ret < 0 { Err(std::io::Error::last_os_error()) } else { Ok(()) }
+}
+
+/// 打开 ICMP 套接字：先数据报后原始
+fn open_socket(ip: IpAddr) -> Result<(OwnedFd, bool), String> {
+    let (domain, proto) = match ip {
+        IpAddr::V4(_) => (libc::AF_INET, libc::IPPROTO_ICMP),
+        IpAddr::V6(_) => (libc::AF_INET6, libc::IPPROTO_ICMPV6),
+    };
+    let mut errors = Vec::new();
+    for (ty, raw) in [(libc::SOCK_DGRAM, false), (libc::SOCK_RAW, true)] {
+        let fd = unsafe { libc::socket(domain, ty | libc::SOCK_CLOEXEC, proto) };
+        if fd >= 0 {
+            return Ok((unsafe { OwnedFd::from_raw_fd(fd) }, raw));
+        }
+        errors.push(std::io::Error::last_os_error().to_string());
+    }
+    let msg = format!("无法创建 ICMP 套接字（数据报: {}；原始: {}）", errors[0], errors[1]);
+    if !WARNED.swap(true, Ordering::Relaxed) {
+        eprintln!("[icmp] {}；可设置 net.ipv4.ping_group_range 或授予 CAP_NET_RAW，RTT 将回退 TCP/HTTPS", msg);
+    }
+    Err(msg)
+}
+
+/// 请求接收 TTL / Hop Limit 辅助数据（失败不影响测量）
+fn enable_ttl(fd: &OwnedFd, ip: IpAddr) {
+    let on: libc::c_int = 1;
+    let (level, opt) = match ip {
+        IpAddr::V4(_) => (libc::IPPROTO_IP, libc::IP_RECVTTL),
+        IpAddr::V6(_) => (libc::IPPROTO_IPV6, libc::IPV6_RECVHOPLIMIT),
+    };
+    unsafe {
+        libc::setsockopt(
+            fd.as_raw_fd(),
+            level,
+            opt,
+            (&on as *const libc::c_int).cast(),
+            mem::size_of::<libc::c_int>() as libc::socklen_t,
+        );
+    }
+}
+
+/// 组装回显请求（IPv4 自行计算校验和；ICMPv6 校验和由内核计算）
+pub fn build_request(ip: IpAddr, ident: u16, seq: u16) -> Vec<u8> {
+    let mut p = vec![0u8; 8 + PAYLOAD_LEN];
+    p[0] = if ip.is_ipv4() { ICMP_ECHO_REQUEST } else { ICMPV6_ECHO_REQUEST };
+    p[4..6].copy_from_slice(&ident.to_be_bytes());
+    p[6..8].copy_from_slice(&seq.to_be_bytes());
+    for b in p[8..].iter_mut() { *b = 0x61; }
+    if ip.is_ipv4() {
+        let sum = checksum(&p);
+        p[2..4].copy_from_slice(&sum.to_be_bytes());
+    }
+    p
+}
+
+/// Internet 校验和（RFC 1071）
+pub fn checksum(data: &[u8]) -> u16 {
+    let mut sum: u32 = 0;
+    for chunk in data.chunks(2) {
+        let word = if chunk.len() == 2 { u16::from_be_bytes([chunk[0], chunk[1]]) } else { u16::from_be_bytes([chunk[0], 0]) };
+        sum += word as u32;
+    }
+    while sum >> 16 != 0 {
+        sum = (sum & 0xffff) + (sum >> 16);
+    }
+    !(sum as u16)
+}
+
+/// 原始 IPv4 套接字收到的报文带 IP 头：剥离并取 TTL；其余情况原样返回
+pub fn strip_ip_header(ip: IpAddr, raw: bool, buf: &[u8]) -> Option<(&[u8], Option<u8>)> {
+    if !(raw && ip.is_ipv4()) { return Some((buf, None)); }
+    let ihl = (*buf.first()? & 0x0f) as usize * 4;
+    if ihl < 20 || buf.len() < ihl { return None; }
+    Some((&buf[ihl..], Some(buf[8])))
+}
+
+/// 是否为本次请求的回复（数据报套接字的标识符由内核改写，不校验）
+pub fn matches_reply(ip: IpAddr, raw: bool, ident: u16, seq: u16, icmp: &[u8]) -> bool {
+    if icmp.len() < 8 { return false; }
+    let expect = if ip.is_ipv4() { ICMP_ECHO_REPLY } else { ICMPV6_ECHO_REPLY };
+    if icmp[0] != expect || icmp[1] != 0 { return false; }
+    if u16::from_be_bytes([icmp[6], icmp[7]]) != seq { return false; }
+    !raw || u16::from_be_bytes([icmp[4], icmp[5]]) == ident
+}
+
+fn sockaddr(ip: IpAddr) -> (libc::sockaddr_storage, libc::socklen_t) {
+    let mut ss: libc::sockaddr_storage = unsafe { mem::zeroed() };
+    let len = match ip {
+        IpAddr::V4(v4) => {
+            let sin = unsafe { &mut *(&mut ss as *mut libc::sockaddr_storage).cast::<libc::sockaddr_in>() };
+            sin.sin_family = libc::AF_INET as libc::sa_family_t;
+            sin.sin_addr = libc::in_addr { s_addr: u32::from_ne_bytes(v4.octets()) };
+            mem::size_of::<libc::sockaddr_in>()
+        }
+        IpAddr::V6(v6) => {
+            let sin6 = unsafe { &mut *(&mut ss as *mut libc::sockaddr_storage).cast::<libc::sockaddr_in6>() };
+            sin6.sin6_family = libc::AF_INET6 as libc::sa_family_t;
+            sin6.sin6_addr = libc::in6_addr { s6_addr: v6.octets() };
+            mem::size_of::<libc::sockaddr_in6>()
+        }
+    };
+    (ss, len as libc::socklen_t)
+}
+
+fn set_port(ss: &mut libc::sockaddr_storage, ip: IpAddr, port: u16) {
+    match ip {
+        IpAddr::V4(_) => {
+            let sin = unsafe { &mut *(ss as *mut libc::sockaddr_storage).cast::<libc::sockaddr_in>() };
+            sin.sin_port = port.to_be();
+        }
+        IpAddr::V6(_) => {
+            let sin6 = unsafe { &mut *(ss as *mut libc::sockaddr_storage).cast::<libc::sockaddr_in6>() };
+            sin6.sin6_port = port.to_be();
+        }
+    }
+}
+
+/// 读取任意对齐的 sockaddr 指针（错误队列中的差错来源）
+unsafe fn from_sockaddr_ptr(sa: *const libc::sockaddr) -> Option<IpAddr> {
+    match std::ptr::read_unaligned(sa).sa_family as libc::c_int {
+        libc::AF_INET => {
+            let sin = std::ptr::read_unaligned(sa.cast::<libc::sockaddr_in>());
+            Some(IpAddr::V4(Ipv4Addr::from(sin.sin_addr.s_addr.to_ne_bytes())))
+        }
+        libc::AF_INET6 => {
+            let sin6 = std::ptr::read_unaligned(sa.cast::<libc::sockaddr_in6>());
+            Some(IpAddr::V6(Ipv6Addr::from(sin6.sin6_addr.s6_addr)))
+        }
+        _ => None,
+    }
+}
+
+fn from_sockaddr(ss: &libc::sockaddr_storage) -> Option<IpAddr> {
+    match ss.ss_family as libc::c_int {
+        libc::AF_INET => {
+            let sin = unsafe { &*(ss as *const libc::sockaddr_storage).cast::<libc::sockaddr_in>() };
+            Some(IpAddr::V4(Ipv4Addr::from(sin.sin_addr.s_addr.to_ne_bytes())))
+        }
+        libc::AF_INET6 => {
+            let sin6 = unsafe { &*(ss as *const libc::sockaddr_storage).cast::<libc::sockaddr_in6>() };
+            Some(IpAddr::V6(Ipv6Addr::from(sin6.sin6_addr.s6_addr)))
+        }
+        _ => None,
+    }
+}
+
+/// 接收一个报文：返回 (长度, 源地址, 辅助数据中的 TTL/Hop Limit)
+fn recv(fd: &OwnedFd, buf: &mut [u8]) -> Option<(usize, Option<IpAddr>, Option<u8>)> {
+    let mut from: libc::sockaddr_storage = unsafe { mem::zeroed() };
+    let mut iov = libc::iovec { iov_base: buf.as_mut_ptr().cast(), iov_len: buf.len() };
+    // 按 cmsghdr 对齐的辅助数据缓冲区
+    let mut control = [0u64; 16];
+    let mut msg: libc::msghdr = unsafe { mem::zeroed() };
+    msg.msg_name = (&mut from as *mut libc::sockaddr_storage).cast();
+    msg.msg_namelen = mem::size_of::<libc::sockaddr_storage>() as libc::socklen_t;
+    msg.msg_iov = &mut iov;
+    msg.msg_iovlen = 1;
+    msg.msg_control = control.as_mut_ptr().cast();
+    msg.msg_controllen = mem::size_of_val(&control) as _;
+
+    let n = unsafe { libc::recvmsg(fd.as_raw_fd(), &mut msg, libc::MSG_DONTWAIT) };
+    if n < 0 { return None; }
+
+    let mut ttl = None;
+    unsafe {
+        let mut cmsg = libc::CMSG_FIRSTHDR(&msg);
+        while !cmsg.is_null() {
+            let c = &*cmsg;
+            let is_ttl = (c.cmsg_level == libc::IPPROTO_IP && c.cmsg_type == libc::IP_TTL)
+                || (c.cmsg_level == libc::IPPROTO_IPV6 && c.cmsg_type == libc::IPV6_HOPLIMIT);
+            if is_ttl {
+                let v = std::ptr::read_unaligned(libc::CMSG_DATA(cmsg).cast::<libc::c_int>());
+                ttl = u8::try_from(v).ok();
+            }
+            cmsg = libc::CMSG_NXTHDR(&msg, cmsg);
+        }
+    }
+    Some((n as usize, from_sockaddr(&from), ttl))
+}
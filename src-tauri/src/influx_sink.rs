@@ -0,0 +1,592 @@
+// InfluxDB 推送 Sink
+// 说明：
+// - 将每个 tick 的 SensorSnapshot 编码为 InfluxDB line protocol 并批量推送
+// - 支持 HTTP（/api/v2/write，兼容 Telegraf influxdb_v2_listener）与 UDP 两种传输
+// - HTTP 失败按退避重试；仍失败则写入磁盘 spool，恢复在线后优先补发
+// - 采样线程只做 push（非阻塞），编码/发送/重试全部在独立后台线程中完成
+
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+use crate::config_utils::AppConfig;
+use crate::types::SensorSnapshot;
+
+/// InfluxDB 推送配置（AppConfig.influx）
+#[derive(Clone, Debug, Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct InfluxConfig {
+    // 总开关（默认关闭）
+    pub enabled: bool,
+    // 传输方式："http" | "udp"（默认 http）
+    pub transport: Option<String>,
+    // HTTP 基础地址，如 "http://127.0.0.1:8086"
+    pub url: Option<String>,
+    pub org: Option<String>,
+    pub bucket: Option<String>,
+    // API Token（Authorization: Token xxx），可空
+    pub token: Option<String>,
+    // UDP 目标，如 "127.0.0.1:8089"
+    pub udp_addr: Option<String>,
+    // 主 measurement 名称（默认 "sys_sensor"）
+    pub measurement: Option<String>,
+    // 分组 measurement 覆盖：system | gpu | rtt | dns | disk（默认 "<measurement>_<group>"，system 即主名称）
+    pub measurements: Option<HashMap<String, String>>,
+    // 附加静态标签（host 标签自动附加，可在此覆盖）
+    pub tags: Option<HashMap<String, String>>,
+    // 批量大小（行数，默认 500）与最长刷新间隔（默认 10000ms）
+    pub batch_size: Option<usize>,
+    pub flush_interval_ms: Option<u64>,
+    // 重试次数（默认 3）与首次退避（默认 500ms，指数增长）
+    pub max_retries: Option<u32>,
+    pub retry_backoff_ms: Option<u64>,
+    // 单次请求超时（默认 5000ms）
+    pub timeout_ms: Option<u64>,
+    // 离线 spool 上限（字节，默认 16MB，超出丢弃最旧数据）
+    pub spool_max_bytes: Option<u64>,
+}
+
+/// 推送状态（供前端/调试读取）
+#[derive(Clone, Debug, Serialize, Deserialize, Default)]
+pub struct InfluxStatus {
+    pub enabled: bool,
+    pub transport: Option<String>,
+    pub pending_lines: usize,
+    pub sent_lines: u64,
+    pub dropped_lines: u64,
+    pub spool_bytes: u64,
+    pub last_ok_ms: Option<i64>,
+    pub last_error: Option<String>,
+}
+
+static STATUS: OnceLock<Mutex<InfluxStatus>> = OnceLock::new();
+
+fn with_status<F: FnOnce(&mut InfluxStatus)>(f: F) {
+    let cell = STATUS.get_or_init(|| Mutex::new(InfluxStatus::default()));
+    if let Ok(mut g) = cell.lock() { f(&mut g); }
+}
+
+/// Tauri命令：获取 InfluxDB 推送状态
+#[cfg_attr(feature = "desktop", tauri::command)]
+pub fn influx_get_status() -> InfluxStatus {
+    STATUS
+        .get_or_init(|| Mutex::new(InfluxStatus::default()))
+        .lock()
+        .map(|g| g.clone())
+        .unwrap_or_default()
+}
+
+/// 推送句柄：采样线程持有，push 只做一次 channel 发送
+#[derive(Clone)]
+pub struct InfluxSink {
+    tx: Sender<SensorSnapshot>,
+}
+
+impl InfluxSink {
+    pub fn push(&self, snap: &SensorSnapshot) {
+        let _ = self.tx.send(snap.clone());
+    }
+}
+
+/// 发送错误：可重试（网络/5xx/429）、鉴权失败（401/403，不重试但保留 spool 待用户修正）与不可重试（其余 4xx，数据错误）
+#[derive(Debug)]
+pub enum WriteError {
+    Retryable(String),
+    Auth(String),
+    Fatal(String),
+}
+
+impl std::fmt::Display for WriteError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            WriteError::Retryable(s) => write!(f, "retryable: {}", s),
+            WriteError::Auth(s) => write!(f, "auth: {}", s),
+            WriteError::Fatal(s) => write!(f, "fatal: {}", s),
+        }
+    }
+}
+
+// ---- line protocol 编码 ----
+
+fn escape_measurement(s: &str) -> String {
+    s.replace('\\', "\\\\").replace(',', "\\,").replace(' ', "\\ ")
+}
+
+fn escape_tag(s: &str) -> String {
+    s.replace('\\', "\\\\").replace(',', "\\,").replace('=', "\\=").replace(' ', "\\ ")
+}
+
+fn escape_field_str(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+/// 浮点字段格式化：非有限值返回 None（line protocol 不接受 NaN/Inf）
+fn fmt_float(v: f64) -> Option<String> {
+    if v.is_finite() { Some(format!("{}", v)) } else { None }
+}
+
+/// 组装单行：measurement,tags fields timestamp
+fn build_line(
+    measurement: &str,
+    tags: &[(String, String)],
+    fields: &[(String, String)],
+    ts: i64,
+) -> Option<String> {
+    if fields.is_empty() { return None; }
+    let mut line = escape_measurement(measurement);
+    for (k, v) in tags {
+        if k.is_empty() || v.is_empty() { continue; }
+        line.push(',');
+        line.push_str(&escape_tag(k));
+        line.push('=');
+        line.push_str(&escape_tag(v));
+    }
+    line.push(' ');
+    let body: Vec<String> = fields.iter().map(|(k, v)| format!("{}={}", escape_tag(k), v)).collect();
+    line.push_str(&body.join(","));
+    line.push(' ');
+    line.push_str(&ts.to_string());
+    Some(line)
+}
+
+/// 行时间戳：HTTP 写入带 precision=ms；UDP 监听端无精度参数，按纳秒解析
+fn line_timestamp(cfg: &InfluxConfig, ts_ms: i64) -> i64 {
+    if cfg.transport.as_deref() == Some("udp") { ts_ms.saturating_mul(1_000_000) } else { ts_ms }
+}
+
+fn measurement_for(cfg: &InfluxConfig, group: &str) -> String {
+    let base = cfg.measurement.clone().unwrap_or_else(|| "sys_sensor".to_string());
+    if let Some(name) = cfg.measurements.as_ref().and_then(|m| m.get(group)) {
+        return name.clone();
+    }
+    if group == "system" { base } else { format!("{}_{}", base, group) }
+}
+
+/// 将快照编码为多行 line protocol（system/gpu/rtt/dns/disk 五组）
+pub fn encode_snapshot_lines(cfg: &InfluxConfig, snap: &SensorSnapshot, host: &str) -> Vec<String> {
+    let ts = line_timestamp(cfg, snap.timestamp_ms);
+    // 基础标签：host + 配置附加标签（按 key 排序，利于 Influx 写入性能）
+    let mut base_tags: Vec<(String, String)> = vec![("host".to_string(), host.to_string())];
+    if let Some(extra) = cfg.tags.as_ref() {
+        for (k, v) in extra {
+            if let Some(slot) = base_tags.iter_mut().find(|(bk, _)| bk == k) {
+                slot.1 = v.clone();
+            } else {
+                base_tags.push((k.clone(), v.clone()));
+            }
+        }
+    }
+    base_tags.sort_by(|a, b| a.0.cmp(&b.0));
+
+    let with_tag = |k: &str, v: &str| -> Vec<(String, String)> {
+        let mut t = base_tags.clone();
+        t.push((k.to_string(), v.to_string()));
+        t.sort_by(|a, b| a.0.cmp(&b.0));
+        t
+    };
+
+    let mut lines: Vec<String> = Vec::new();
+
+    // 1) system：全部标量指标
+    let sys_fields: Vec<(String, String)> = crate::metrics_utils::snapshot_scalar_fields(snap)
+        .into_iter()
+        .filter_map(|(k, v)| fmt_float(v).map(|s| (k.to_string(), s)))
+        .collect();
+    if let Some(l) = build_line(&measurement_for(cfg, "system"), &base_tags, &sys_fields, ts) {
+        lines.push(l);
+    }
+
+    // 2) gpu：每块 GPU 一行，以 gpu 名称为标签
+    if let Some(gpus) = snap.gpus.as_ref() {
+        let m = measurement_for(cfg, "gpu");
+        for (i, g) in gpus.iter().enumerate() {
+            let name = g.name.clone().unwrap_or_else(|| format!("gpu{}", i));
+            let mut f: Vec<(String, String)> = Vec::new();
+            let pairs: [(&str, Option<f64>); 8] = [
+                ("temp_c", g.temp_c.map(|v| v as f64)),
+                ("load_pct", g.load_pct.map(|v| v as f64)),
+                ("core_mhz", g.core_mhz),
+                ("memory_mhz", g.memory_mhz),
+                ("fan_rpm", g.fan_rpm.map(|v| v as f64)),
+                ("vram_used_mb", g.vram_used_mb),
+                ("vram_total_mb", g.vram_total_mb),
+                ("power_w", g.power_w),
+            ];
+            for (k, v) in pairs.iter() {
+                if let Some(s) = v.and_then(fmt_float) { f.push((k.to_string(), s)); }
+            }
+            if let Some(l) = build_line(&m, &with_tag("gpu", &name), &f, ts) { lines.push(l); }
+        }
+    }
+
+    // 3) rtt：每目标一行
+    if let Some(rtts) = snap.rtt_multi.as_ref() {
+        let m = measurement_for(cfg, "rtt");
+        for r in rtts {
+            let mut f: Vec<(String, String)> = Vec::new();
+            if let Some(s) = r.rtt_ms.and_then(fmt_float) { f.push(("rtt_ms".to_string(), s)); }
+            if let Some(ok) = r.success { f.push(("success".to_string(), ok.to_string())); }
+            let stats = [
+                ("loss_pct", r.loss_pct),
+                ("min_ms", r.min_ms),
+                ("max_ms", r.max_ms),
+                ("stddev_ms", r.stddev_ms),
+                ("p50_ms", r.p50_ms),
+                ("p95_ms", r.p95_ms),
+                ("jitter_ms", r.jitter_ms),
+            ];
+            for (k, v) in stats.iter() {
+                if let Some(s) = v.and_then(fmt_float) { f.push((k.to_string(), s)); }
+            }
+            if let Some(code) = r.status { f.push(("status".to_string(), format!("{}i", code))); }
+            if let Some(h) = r.http.as_ref() {
+                let phases = [
+                    ("http_dns_ms", h.dns_ms),
+                    ("http_connect_ms", h.connect_ms),
+                    ("http_tls_ms", h.tls_ms),
+                    ("http_ttfb_ms", h.ttfb_ms),
+                    ("http_total_ms", h.total_ms),
+                ];
+                for (k, v) in phases.iter() {
+                    if let Some(s) = v.and_then(fmt_float) { f.push((k.to_string(), s)); }
+                }
+                if let Some(days) = h.cert_expiry_days { f.push(("cert_expiry_days".to_string(), format!("{}i", days))); }
+            }
+            // family=both 时同一目标有 v4/v6 两条，按地址族区分序列；分组便于按组聚合
+            let mut tags = with_tag("target", &r.target);
+            if let Some(fam) = r.family.as_ref() {
+                tags.push(("family".to_string(), fam.clone()));
+            }
+            if let Some(g) = r.group.as_ref() {
+                tags.push(("group".to_string(), g.clone()));
+            }
+            tags.sort_by(|a, b| a.0.cmp(&b.0));
+            if let Some(l) = build_line(&m, &tags, &f, ts) { lines.push(l); }
+        }
+    }
+
+    // 3b) dns：每解析器（按传输方式区分）一行
+    if let Some(report) = snap.dns_probe.as_ref() {
+        let m = measurement_for(cfg, "dns");
+        for h in &report.servers {
+            let mut f: Vec<(String, String)> = Vec::new();
+            if let Some(s) = h.avg_ms.and_then(fmt_float) { f.push(("avg_ms".to_string(), s)); }
+            if let Some(s) = h.max_ms.and_then(fmt_float) { f.push(("max_ms".to_string(), s)); }
+            f.push(("queries".to_string(), format!("{}i", h.queries)));
+            f.push(("answered".to_string(), format!("{}i", h.answered)));
+            f.push(("timeouts".to_string(), format!("{}i", h.timeouts)));
+            f.push(("servfails".to_string(), format!("{}i", h.servfails)));
+            f.push(("flagged".to_string(), h.flagged.to_string()));
+            let mut tags = with_tag("server", &h.server);
+            tags.push(("source".to_string(), h.source.clone()));
+            tags.push(("transport".to_string(), h.transport.clone()));
+            tags.sort_by(|a, b| a.0.cmp(&b.0));
+            if let Some(l) = build_line(&m, &tags, &f, ts) { lines.push(l); }
+        }
+    }
+
+    // 4) disk：逻辑磁盘容量
+    if let Some(disks) = snap.logical_disks.as_ref() {
+        let m = measurement_for(cfg, "disk");
+        for d in disks {
+            let drive = match d.drive_letter.as_ref() { Some(s) => s.clone(), None => continue };
+            let mut f: Vec<(String, String)> = Vec::new();
+            if let Some(s) = d.total_gb.and_then(fmt_float) { f.push(("total_gb".to_string(), s)); }
+            if let Some(s) = d.free_gb.and_then(fmt_float) { f.push(("free_gb".to_string(), s)); }
+            if let Some(s) = d.usage_pct.and_then(fmt_float) { f.push(("usage_pct".to_string(), s)); }
+            if let Some(fs) = d.fs.as_ref() { f.push(("fs".to_string(), format!("\"{}\"", escape_field_str(fs)))); }
+            if let Some(l) = build_line(&m, &with_tag("drive", &drive), &f, ts) { lines.push(l); }
+        }
+    }
+
+    lines
+}
+
+// ---- 传输 ----
+
+/// 简易 URL 查询参数编码（仅保留非保留字符）
+fn url_encode(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for b in s.bytes() {
+        match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => out.push(b as char),
+            _ => out.push_str(&format!("%{:02X}", b)),
+        }
+    }
+    out
+}
+
+/// HTTP 写入：POST {url}/api/v2/write?org=..&bucket=..&precision=ms
+pub fn write_http(cfg: &InfluxConfig, body: &str) -> Result<(), WriteError> {
+    let base = cfg.url.clone().unwrap_or_else(|| "http://127.0.0.1:8086".to_string());
+    let mut url = format!("{}/api/v2/write?precision=ms", base.trim_end_matches('/'));
+    if let Some(org) = cfg.org.as_ref() { url.push_str(&format!("&org={}", url_encode(org))); }
+    if let Some(bucket) = cfg.bucket.as_ref() { url.push_str(&format!("&bucket={}", url_encode(bucket))); }
+
+    let timeout = Duration::from_millis(cfg.timeout_ms.unwrap_or(5000));
+    let agent = ureq::AgentBuilder::new()
+        .timeout_connect(timeout)
+        .timeout_read(timeout)
+        .timeout_write(timeout)
+        .build();
+    let mut req = agent.post(&url).set("Content-Type", "text/plain; charset=utf-8");
+    if let Some(tok) = cfg.token.as_ref().filter(|t| !t.is_empty()) {
+        req = req.set("Authorization", &format!("Token {}", tok));
+    }
+    match req.send_string(body) {
+        Ok(_) => Ok(()),
+        Err(ureq::Error::Status(code, resp)) => {
+            let detail = resp.into_string().unwrap_or_default();
+            let msg = format!("HTTP {}: {}", code, detail.trim());
+            match code {
+                429 | 500.. => Err(WriteError::Retryable(msg)),
+                401 | 403 => Err(WriteError::Auth(msg)),
+                _ => Err(WriteError::Fatal(msg)),
+            }
+        }
+        Err(e) => Err(WriteError::Retryable(e.to_string())),
+    }
+}
+
+/// UDP 写入：按行切分为不超过 8KB 的数据报
+pub fn write_udp(cfg: &InfluxConfig, body: &str) -> Result<(), WriteError> {
+    const MAX_DGRAM: usize = 8192;
+    let addr = cfg.udp_addr.clone().unwrap_or_else(|| "127.0.0.1:8089".to_string());
+    let sock = std::net::UdpSocket::bind("0.0.0.0:0").map_err(|e| WriteError::Retryable(e.to_string()))?;
+    let mut chunk = String::new();
+    for line in body.lines() {
+        if !chunk.is_empty() && chunk.len() + line.len() + 1 > MAX_DGRAM {
+            sock.send_to(chunk.as_bytes(), &addr).map_err(|e| WriteError::Retryable(e.to_string()))?;
+            chunk.clear();
+        }
+        chunk.push_str(line);
+        chunk.push('\n');
+    }
+    if !chunk.is_empty() {
+        sock.send_to(chunk.as_bytes(), &addr).map_err(|e| WriteError::Retryable(e.to_string()))?;
+    }
+    Ok(())
+}
+
+fn write_once(cfg: &InfluxConfig, body: &str) -> Result<(), WriteError> {
+    match cfg.transport.as_deref().unwrap_or("http") {
+        "udp" => write_udp(cfg, body),
+        _ => write_http(cfg, body),
+    }
+}
+
+/// 带指数退避的写入
+pub fn write_with_retry(cfg: &InfluxConfig, body: &str) -> Result<(), WriteError> {
+    let retries = cfg.max_retries.unwrap_or(3);
+    let mut backoff = cfg.retry_backoff_ms.unwrap_or(500).max(50);
+    let mut attempt: u32 = 0;
+    loop {
+        match write_once(cfg, body) {
+            Ok(()) => return Ok(()),
+            Err(e @ (WriteError::Fatal(_) | WriteError::Auth(_))) => return Err(e),
+            Err(WriteError::Retryable(e)) => {
+                if attempt >= retries { return Err(WriteError::Retryable(e)); }
+                attempt += 1;
+                std::thread::sleep(Duration::from_millis(backoff));
+                backoff = (backoff * 2).min(30_000);
+            }
+        }
+    }
+}
+
+// ---- 离线 spool ----
+
+fn spool_len(path: &Path) -> u64 {
+    std::fs::metadata(path).map(|m| m.len()).unwrap_or(0)
+}
+
+/// 追加到 spool；超出上限时丢弃最旧的行
+fn spool_append(path: &Path, lines: &[String], max_bytes: u64) -> u64 {
+    if lines.is_empty() { return 0; }
+    let mut add = lines.join("\n");
+    add.push('\n');
+    let mut dropped: u64 = 0;
+    if spool_len(path) + add.len() as u64 > max_bytes {
+        // 读出旧数据，保留尾部使总量不超上限
+        let old = std::fs::read_to_string(path).unwrap_or_default();
+        let mut all: Vec<&str> = old.lines().collect();
+        all.extend(add.lines());
+        let mut total: u64 = all.iter().map(|l| l.len() as u64 + 1).sum();
+        let mut start = 0usize;
+        while total > max_bytes && start < all.len() {
+            total -= all[start].len() as u64 + 1;
+            start += 1;
+        }
+        dropped = start as u64;
+        let mut kept = all[start..].join("\n");
+        if !kept.is_empty() { kept.push('\n'); }
+        if let Err(e) = std::fs::write(path, kept) {
+            eprintln!("[influx] 写入 spool 失败: {}", e);
+        }
+        return dropped;
+    }
+    if let Some(parent) = path.parent() { let _ = std::fs::create_dir_all(parent); }
+    match std::fs::OpenOptions::new().create(true).append(true).open(path) {
+        Ok(mut f) => {
+            if let Err(e) = f.write_all(add.as_bytes()) {
+                eprintln!("[influx] 追加 spool 失败: {}", e);
+                dropped = lines.len() as u64;
+            }
+        }
+        Err(e) => {
+            eprintln!("[influx] 打开 spool 失败: {}", e);
+            dropped = lines.len() as u64;
+        }
+    }
+    dropped
+}
+
+/// 补发 spool：按块发送，全部成功后删除文件；返回补发成功的行数
+pub(crate) fn spool_replay(cfg: &InfluxConfig, path: &Path, batch: usize) -> Result<u64, WriteError> {
+    if spool_len(path) == 0 { return Ok(0); }
+    let content = std::fs::read_to_string(path).map_err(|e| WriteError::Retryable(e.to_string()))?;
+    let lines: Vec<&str> = content.lines().filter(|l| !l.trim().is_empty()).collect();
+    let mut sent: usize = 0;
+    for chunk in lines.chunks(batch.max(1)) {
+        match write_with_retry(cfg, &chunk.join("\n")) {
+            Ok(()) => sent += chunk.len(),
+            // 数据错误的块直接丢弃，避免 spool 永久卡死；鉴权失败与网络错误一样保留
+            Err(WriteError::Fatal(e)) => {
+                eprintln!("[influx] spool 块被服务端拒绝，丢弃 {} 行: {}", chunk.len(), e);
+                sent += chunk.len();
+            }
+            Err(e) => {
+                // 保留未发送部分
+                let mut rest = lines[sent..].join("\n");
+                rest.push('\n');
+                let _ = std::fs::write(path, rest);
+                return Err(e);
+            }
+        }
+    }
+    let _ = std::fs::remove_file(path);
+    Ok(sent as u64)
+}
+
+fn flush(cfg: &InfluxConfig, pending: &mut Vec<String>, spool_path: &Path) {
+    let batch = cfg.batch_size.unwrap_or(500).max(1);
+    let max_spool = cfg.spool_max_bytes.unwrap_or(16 * 1024 * 1024);
+    let is_udp = cfg.transport.as_deref() == Some("udp");
+
+    // 1) 先补发离线积压（UDP 无回执，不使用 spool）
+    if !is_udp {
+        match spool_replay(cfg, spool_path, batch) {
+            Ok(n) => {
+                if n > 0 {
+                    eprintln!("[influx] spool 补发完成: {} 行", n);
+                    with_status(|s| s.sent_lines = s.sent_lines.saturating_add(n));
+                }
+            }
+            Err(e) => {
+                // 仍离线：本批直接进 spool，保持时间顺序
+                let dropped = spool_append(spool_path, pending, max_spool);
+                let n = pending.len();
+                pending.clear();
+                with_status(|s| {
+                    s.last_error = Some(e.to_string());
+                    s.dropped_lines = s.dropped_lines.saturating_add(dropped);
+                    s.spool_bytes = spool_len(spool_path);
+                });
+                eprintln!("[influx] 离线，{} 行写入 spool", n);
+                return;
+            }
+        }
+    }
+
+    if pending.is_empty() {
+        with_status(|s| s.spool_bytes = spool_len(spool_path));
+        return;
+    }
+
+    // 2) 发送本批
+    let lines: Vec<String> = std::mem::take(pending);
+    for chunk in lines.chunks(batch) {
+        match write_with_retry(cfg, &chunk.join("\n")) {
+            Ok(()) => with_status(|s| {
+                s.sent_lines = s.sent_lines.saturating_add(chunk.len() as u64);
+                s.last_ok_ms = Some(chrono::Local::now().timestamp_millis());
+                s.last_error = None;
+            }),
+            Err(WriteError::Fatal(e)) => {
+                eprintln!("[influx] 服务端拒绝写入，丢弃 {} 行: {}", chunk.len(), e);
+                with_status(|s| {
+                    s.dropped_lines = s.dropped_lines.saturating_add(chunk.len() as u64);
+                    s.last_error = Some(e);
+                });
+            }
+            Err(WriteError::Retryable(e) | WriteError::Auth(e)) => {
+                let dropped = if is_udp { chunk.len() as u64 } else { spool_append(spool_path, chunk, max_spool) };
+                with_status(|s| {
+                    s.dropped_lines = s.dropped_lines.saturating_add(dropped);
+                    s.last_error = Some(e.clone());
+                });
+                eprintln!("[influx] 写入失败（{}），{} 行{}", e, chunk.len(), if is_udp { "丢弃" } else { "写入 spool" });
+            }
+        }
+    }
+    with_status(|s| s.spool_bytes = spool_len(spool_path));
+}
+
+/// 启动 InfluxDB 推送后台线程
+pub fn start_influx_sink(cfg_state: Arc<Mutex<AppConfig>>, spool_dir: PathBuf) -> InfluxSink {
+    let (tx, rx): (Sender<SensorSnapshot>, Receiver<SensorSnapshot>) = mpsc::channel();
+    let spool_path = spool_dir.join("influx-spool.lp");
+    let host = sysinfo::System::host_name().unwrap_or_else(|| "unknown".to_string());
+
+    std::thread::Builder::new()
+        .name("influx-sink".into())
+        .spawn(move || {
+            let mut pending: Vec<String> = Vec::new();
+            let mut last_flush = Instant::now();
+            loop {
+                let cfg: InfluxConfig = cfg_state
+                    .lock().ok()
+                    .and_then(|c| c.influx.clone())
+                    .unwrap_or_default();
+                let flush_every = Duration::from_millis(cfg.flush_interval_ms.unwrap_or(10_000).max(200));
+                let batch = cfg.batch_size.unwrap_or(500).max(1);
+
+                match rx.recv_timeout(Duration::from_millis(200)) {
+                    Ok(snap) => {
+                        if cfg.enabled {
+                            pending.extend(encode_snapshot_lines(&cfg, &snap, &host));
+                        }
+                    }
+                    Err(mpsc::RecvTimeoutError::Timeout) => {}
+                    Err(mpsc::RecvTimeoutError::Disconnected) => {
+                        // 退出前尽力刷出
+                        if cfg.enabled && !pending.is_empty() { flush(&cfg, &mut pending, &spool_path); }
+                        return;
+                    }
+                }
+
+                with_status(|s| {
+                    s.enabled = cfg.enabled;
+                    s.transport = cfg.transport.clone().or_else(|| Some("http".to_string()));
+                    s.pending_lines = pending.len();
+                });
+
+                if !cfg.enabled {
+                    pending.clear();
+                    continue;
+                }
+                if pending.len() >= batch || last_flush.elapsed() >= flush_every {
+                    flush(&cfg, &mut pending, &spool_path);
+                    last_flush = Instant::now();
+                }
+            }
+        })
+        .expect("spawn influx-sink");
+
+    InfluxSink { tx }
+}
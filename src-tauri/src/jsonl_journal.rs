@@ -0,0 +1,91 @@
+// JSONL 追加日志（告警日志 / 中断时间线 / 公网 IP 历史 / 测速历史共用）
+// 说明：
+// - 每条记录一行 JSON，平时只追加，不重写文件
+// - 行数超过上限的 1.25 倍时才压缩为最新的 max 条，避免到达上限后每次追加都重写整个文件
+// - 压缩先写同目录下的临时文件再 rename 替换，中途失败不会留下截断的日志
+// - 读取时只返回最新的 max 条，文件中尚未压缩掉的旧记录对调用方不可见
+
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+/// 已打开的日志文件
+#[derive(Clone, Debug)]
+pub struct JsonlJournal {
+    path: PathBuf,
+    // 文件中的非空行数
+    lines: usize,
+    // 最近一次使用的条目上限
+    max: usize,
+}
+
+/// 压缩阈值：上限的 1.25 倍（至少多留 1 条）
+fn compact_threshold(max: usize) -> usize {
+    max + (max / 4).max(1)
+}
+
+/// 解析文件中的全部记录（跳过无法解析的行）
+pub fn read_entries<T: DeserializeOwned>(path: &Path) -> Vec<T> {
+    std::fs::read_to_string(path)
+        .unwrap_or_default()
+        .lines()
+        .filter_map(|l| serde_json::from_str::<T>(l).ok())
+        .collect()
+}
+
+/// 解析文件中最新的 max 条记录
+pub fn read_latest<T: DeserializeOwned>(path: &Path, max: usize) -> Vec<T> {
+    let mut all = read_entries(path);
+    all.split_off(all.len().saturating_sub(max.max(1)))
+}
+
+/// 先写临时文件再替换，避免写到一半时留下截断的文件
+fn write_atomic(path: &Path, text: &str) -> std::io::Result<()> {
+    let mut name = path.file_name().map(|n| n.to_os_string()).unwrap_or_default();
+    name.push(".tmp");
+    let tmp = path.with_file_name(name);
+    let written = std::fs::write(&tmp, text).and_then(|_| std::fs::rename(&tmp, path));
+    if written.is_err() {
+        let _ = std::fs::remove_file(&tmp);
+    }
+    written
+}
+
+impl JsonlJournal {
+    /// 打开日志（文件不存在时在首次追加时创建）
+    pub fn open(path: PathBuf, max: usize) -> Self {
+        let lines = std::fs::read_to_string(&path).unwrap_or_default().lines().filter(|l| !l.trim().is_empty()).count();
+        Self { path, lines, max: max.max(1) }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// 最新的 max 条记录
+    pub fn read<T: DeserializeOwned>(&self) -> Vec<T> {
+        read_latest(&self.path, self.max)
+    }
+
+    /// 追加一条记录；行数超过压缩阈值时改写为最新的 max 条
+    pub fn append<T: Serialize>(&mut self, entry: &T, max: usize) -> std::io::Result<()> {
+        self.max = max.max(1);
+        let line = serde_json::to_string(entry).map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+        if let Some(parent) = self.path.parent() { let _ = std::fs::create_dir_all(parent); }
+        if self.lines + 1 > compact_threshold(self.max) {
+            let text = std::fs::read_to_string(&self.path).unwrap_or_default();
+            let mut all: Vec<&str> = text.lines().filter(|l| !l.trim().is_empty()).collect();
+            all.push(&line);
+            let keep = &all[all.len().saturating_sub(self.max)..];
+            write_atomic(&self.path, &format!("{}\n", keep.join("\n")))?;
+            self.lines = keep.len();
+            return Ok(());
+        }
+        let mut f = std::fs::OpenOptions::new().create(true).append(true).open(&self.path)?;
+        f.write_all(format!("{}\n", line).as_bytes())?;
+        self.lines += 1;
+        Ok(())
+    }
+}
@@ -0,0 +1,131 @@
+// 指标目录工具模块
+// 将 SensorSnapshot 拍平为 (指标键, 数值) 列表，供推送类 Sink 统一取数
+// 并提供指标元数据（名称/单位/设备类别），供 MQTT 自动发现等场景使用
+// 指标键与 SensorSnapshot 字段名保持一致，便于配置与前端对照
+
+use crate::types::SensorSnapshot;
+
+/// 拍平快照中的标量指标（跳过不可用的 None 值）
+pub fn snapshot_scalar_fields(s: &SensorSnapshot) -> Vec<(&'static str, f64)> {
+    let mut out: Vec<(&'static str, f64)> = Vec::with_capacity(48);
+
+    // 必有字段
+    out.push(("cpu_usage", s.cpu_usage as f64));
+    out.push(("mem_used_gb", s.mem_used_gb as f64));
+    out.push(("mem_total_gb", s.mem_total_gb as f64));
+    out.push(("mem_pct", s.mem_pct as f64));
+    out.push(("net_rx_bps", s.net_rx_bps));
+    out.push(("net_tx_bps", s.net_tx_bps));
+    out.push(("net_rx_instant_bps", s.net_rx_instant_bps));
+    out.push(("net_tx_instant_bps", s.net_tx_instant_bps));
+    out.push(("disk_r_bps", s.disk_r_bps));
+    out.push(("disk_w_bps", s.disk_w_bps));
+
+    // 可选字段
+    let opts: [(&'static str, Option<f64>); 30] = [
+        ("mem_avail_gb", s.mem_avail_gb.map(|v| v as f64)),
+        ("swap_used_gb", s.swap_used_gb.map(|v| v as f64)),
+        ("swap_total_gb", s.swap_total_gb.map(|v| v as f64)),
+        ("mem_cache_gb", s.mem_cache_gb.map(|v| v as f64)),
+        ("mem_committed_gb", s.mem_committed_gb.map(|v| v as f64)),
+        ("mem_pages_per_sec", s.mem_pages_per_sec),
+        ("cpu_temp_c", s.cpu_temp_c.map(|v| v as f64)),
+        ("mobo_temp_c", s.mobo_temp_c.map(|v| v as f64)),
+        ("fan_rpm", s.fan_rpm.map(|v| v as f64)),
+        ("cpu_pkg_power_w", s.cpu_pkg_power_w),
+        ("cpu_avg_freq_mhz", s.cpu_avg_freq_mhz),
+        ("disk_r_iops", s.disk_r_iops),
+        ("disk_w_iops", s.disk_w_iops),
+        ("disk_queue_len", s.disk_queue_len),
+        ("net_rx_err_ps", s.net_rx_err_ps),
+        ("net_tx_err_ps", s.net_tx_err_ps),
+        ("ping_rtt_ms", s.ping_rtt_ms),
+        ("packet_loss_pct", s.packet_loss_pct),
+        ("active_connections", s.active_connections.map(|v| v as f64)),
+        ("wifi_signal_pct", s.wifi_signal_pct.map(|v| v as f64)),
+        ("wifi_link_mbps", s.wifi_link_mbps.map(|v| v as f64)),
+        ("wifi_rssi_dbm", s.wifi_rssi_dbm.map(|v| v as f64)),
+        ("battery_percent", s.battery_percent.map(|v| v as f64)),
+        ("battery_time_remaining_sec", s.battery_time_remaining_sec.map(|v| v as f64)),
+        ("battery_time_to_full_sec", s.battery_time_to_full_sec.map(|v| v as f64)),
+        ("hb_tick", s.hb_tick.map(|v| v as f64)),
+        ("idle_sec", s.idle_sec.map(|v| v as f64)),
+        ("exc_count", s.exc_count.map(|v| v as f64)),
+        ("uptime_sec", s.uptime_sec.map(|v| v as f64)),
+        ("since_reopen_sec", s.since_reopen_sec.map(|v| v as f64)),
+    ];
+    for (k, v) in opts.iter() {
+        if let Some(x) = v {
+            if x.is_finite() { out.push((*k, *x)); }
+        }
+    }
+    out
+}
+
+/// 按指标键读取单个标量值
+#[allow(dead_code)]
+pub fn snapshot_metric(s: &SensorSnapshot, key: &str) -> Option<f64> {
+    snapshot_scalar_fields(s)
+        .into_iter()
+        .find(|(k, _)| *k == key)
+        .map(|(_, v)| v)
+}
+
+/// 指标元数据：显示名、单位与 Home Assistant device_class
+#[derive(Debug, Clone, Copy)]
+pub struct MetricMeta {
+    pub name: &'static str,
+    pub unit: Option<&'static str>,
+    pub device_class: Option<&'static str>,
+    // Home Assistant state_class：瞬时量为 "measurement"，单调累计量为 "total_increasing"
+    pub state_class: &'static str,
+}
+
+/// 查询指标元数据（未知指标返回 None）
+pub fn metric_meta(key: &str) -> Option<MetricMeta> {
+    let m = |name, unit, device_class| MetricMeta { name, unit, device_class, state_class: "measurement" };
+    let meta = match key {
+        "cpu_usage" => m("CPU Usage", Some("%"), None),
+        "mem_used_gb" => m("Memory Used", Some("GB"), Some("data_size")),
+        "mem_total_gb" => m("Memory Total", Some("GB"), Some("data_size")),
+        "mem_pct" => m("Memory Usage", Some("%"), None),
+        "mem_avail_gb" => m("Memory Available", Some("GB"), Some("data_size")),
+        "swap_used_gb" => m("Swap Used", Some("GB"), Some("data_size")),
+        "swap_total_gb" => m("Swap Total", Some("GB"), Some("data_size")),
+        "mem_cache_gb" => m("Memory Cache", Some("GB"), Some("data_size")),
+        "mem_committed_gb" => m("Memory Committed", Some("GB"), Some("data_size")),
+        "mem_pages_per_sec" => m("Memory Pages/s", Some("pages/s"), None),
+        "net_rx_bps" => m("Network Download", Some("B/s"), Some("data_rate")),
+        "net_tx_bps" => m("Network Upload", Some("B/s"), Some("data_rate")),
+        "net_rx_instant_bps" => m("Network Download (instant)", Some("B/s"), Some("data_rate")),
+        "net_tx_instant_bps" => m("Network Upload (instant)", Some("B/s"), Some("data_rate")),
+        "disk_r_bps" => m("Disk Read", Some("B/s"), Some("data_rate")),
+        "disk_w_bps" => m("Disk Write", Some("B/s"), Some("data_rate")),
+        "cpu_temp_c" => m("CPU Temperature", Some("°C"), Some("temperature")),
+        "mobo_temp_c" => m("Motherboard Temperature", Some("°C"), Some("temperature")),
+        "fan_rpm" => m("Fan Speed", Some("RPM"), None),
+        "cpu_pkg_power_w" => m("CPU Package Power", Some("W"), Some("power")),
+        "cpu_avg_freq_mhz" => m("CPU Frequency", Some("MHz"), Some("frequency")),
+        "disk_r_iops" => m("Disk Read IOPS", Some("IOPS"), None),
+        "disk_w_iops" => m("Disk Write IOPS", Some("IOPS"), None),
+        "disk_queue_len" => m("Disk Queue Length", None, None),
+        "net_rx_err_ps" => m("Network RX Errors/s", Some("errors/s"), None),
+        "net_tx_err_ps" => m("Network TX Errors/s", Some("errors/s"), None),
+        "ping_rtt_ms" => m("Ping RTT", Some("ms"), Some("duration")),
+        "packet_loss_pct" => m("Packet Loss", Some("%"), None),
+        "active_connections" => m("Active Connections", None, None),
+        "wifi_signal_pct" => m("Wi-Fi Signal", Some("%"), None),
+        "wifi_link_mbps" => m("Wi-Fi Link Speed", Some("Mbit/s"), Some("data_rate")),
+        "wifi_rssi_dbm" => m("Wi-Fi RSSI", Some("dBm"), Some("signal_strength")),
+        "battery_percent" => m("Battery", Some("%"), Some("battery")),
+        "battery_time_remaining_sec" => m("Battery Time Remaining", Some("s"), Some("duration")),
+        "battery_time_to_full_sec" => m("Battery Time To Full", Some("s"), Some("duration")),
+        "hb_tick" => MetricMeta { name: "Bridge Heartbeat", unit: None, device_class: None, state_class: "total_increasing" },
+        "idle_sec" => m("Bridge Idle", Some("s"), Some("duration")),
+        "exc_count" => MetricMeta { name: "Bridge Exceptions", unit: None, device_class: None, state_class: "total_increasing" },
+        "uptime_sec" => MetricMeta { name: "Bridge Uptime", unit: Some("s"), device_class: Some("duration"), state_class: "total_increasing" },
+        "since_reopen_sec" => m("Bridge Since Reopen", Some("s"), Some("duration")),
+        _ => return None,
+    };
+    Some(meta)
+}
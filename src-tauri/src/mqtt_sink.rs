@@ -0,0 +1,616 @@
+// MQTT 发布 Sink（含 Home Assistant 自动发现）
+// 说明：
+// - 内置最小 MQTT 3.1.1 客户端（CONNECT/PUBLISH QoS0/PINGREQ/DISCONNECT），支持 TLS 与用户名密码
+// - 连接时设置 LWT：<base>/availability = "offline"（retain），连接成功后发布 "online"
+// - 为每个选中指标发送 HA discovery 配置（device_class/unit/state_class），随后按各自频率发布状态
+// - 断线后指数退避重连；采样线程只做 push（非阻塞）
+
+use std::collections::HashMap;
+use std::io::{Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+use crate::config_utils::AppConfig;
+use crate::types::SensorSnapshot;
+
+/// 单个发布指标配置
+#[derive(Clone, Debug, Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct MqttMetric {
+    // 指标键（与 SensorSnapshot 字段同名，如 "cpu_usage"）
+    pub key: String,
+    // 状态主题覆盖（默认 "<base_topic>/<key>"）
+    pub topic: Option<String>,
+    // 发布间隔覆盖（默认使用 publish_interval_ms）
+    pub interval_ms: Option<u64>,
+    // 显示名覆盖（默认取指标目录中的名称）
+    pub name: Option<String>,
+}
+
+/// MQTT 发布配置（AppConfig.mqtt）
+#[derive(Clone, Debug, Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct MqttConfig {
+    // 总开关（默认关闭）
+    pub enabled: bool,
+    // Broker 地址与端口（默认 127.0.0.1:1883；启用 TLS 时默认 8883）
+    pub host: Option<String>,
+    pub port: Option<u16>,
+    pub client_id: Option<String>,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    // TLS（默认关闭）；ca_file 为 PEM 格式自定义根证书，缺省使用内置 webpki 根证书
+    pub tls: Option<bool>,
+    pub ca_file: Option<String>,
+    // 心跳（秒，默认 60）
+    pub keepalive_sec: Option<u16>,
+    // 设备节点 ID（默认取主机名，仅保留 [a-zA-Z0-9_-]）
+    pub node_id: Option<String>,
+    // 状态主题前缀（默认 "sys-sensor/<node_id>"）
+    pub base_topic: Option<String>,
+    // Home Assistant 自动发现（默认启用，前缀 "homeassistant"）
+    pub discovery_enabled: Option<bool>,
+    pub discovery_prefix: Option<String>,
+    // 默认发布间隔（默认 10000ms）
+    pub publish_interval_ms: Option<u64>,
+    // 状态消息是否 retain（默认 false）
+    pub retain: Option<bool>,
+    // 发布的指标列表（缺省使用内置常用指标）
+    pub metrics: Option<Vec<MqttMetric>>,
+    // 重连退避（默认 1000ms 起，最长 60000ms）
+    pub reconnect_min_ms: Option<u64>,
+    pub reconnect_max_ms: Option<u64>,
+}
+
+/// 发布状态（供前端/调试读取）
+#[derive(Clone, Debug, Serialize, Deserialize, Default)]
+pub struct MqttStatus {
+    pub enabled: bool,
+    pub connected: bool,
+    pub broker: Option<String>,
+    pub connects: u64,
+    pub published: u64,
+    pub last_connect_ms: Option<i64>,
+    pub next_retry_ms: Option<u64>,
+    pub last_error: Option<String>,
+}
+
+static STATUS: OnceLock<Mutex<MqttStatus>> = OnceLock::new();
+
+fn with_status<F: FnOnce(&mut MqttStatus)>(f: F) {
+    let cell = STATUS.get_or_init(|| Mutex::new(MqttStatus::default()));
+    if let Ok(mut g) = cell.lock() { f(&mut g); }
+}
+
+/// Tauri命令：获取 MQTT 发布状态
+#[cfg_attr(feature = "desktop", tauri::command)]
+pub fn mqtt_get_status() -> MqttStatus {
+    STATUS
+        .get_or_init(|| Mutex::new(MqttStatus::default()))
+        .lock()
+        .map(|g| g.clone())
+        .unwrap_or_default()
+}
+
+const DEFAULT_METRICS: [&str; 9] = [
+    "cpu_usage", "mem_pct", "cpu_temp_c", "fan_rpm",
+    "net_rx_bps", "net_tx_bps", "disk_r_bps", "disk_w_bps", "ping_rtt_ms",
+];
+
+/// 解析后的主题上下文（配置 + 主机名推导）
+#[derive(Clone, Debug)]
+pub struct MqttTopics {
+    pub node_id: String,
+    pub base: String,
+    pub availability: String,
+    pub discovery_prefix: String,
+}
+
+fn sanitize_id(s: &str) -> String {
+    let out: String = s
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '_' || c == '-' { c } else { '_' })
+        .collect();
+    if out.is_empty() { "sys_sensor".to_string() } else { out }
+}
+
+pub fn resolve_topics(cfg: &MqttConfig, host: &str) -> MqttTopics {
+    let node_id = sanitize_id(cfg.node_id.as_deref().unwrap_or(host));
+    let base = cfg
+        .base_topic
+        .clone()
+        .unwrap_or_else(|| format!("sys-sensor/{}", node_id))
+        .trim_end_matches('/')
+        .to_string();
+    MqttTopics {
+        availability: format!("{}/availability", base),
+        discovery_prefix: cfg.discovery_prefix.clone().unwrap_or_else(|| "homeassistant".to_string()),
+        node_id,
+        base,
+    }
+}
+
+/// 实际生效的指标列表
+pub fn effective_metrics(cfg: &MqttConfig) -> Vec<MqttMetric> {
+    match cfg.metrics.as_ref() {
+        Some(list) if !list.is_empty() => list.clone(),
+        _ => DEFAULT_METRICS
+            .iter()
+            .map(|k| MqttMetric { key: k.to_string(), ..Default::default() })
+            .collect(),
+    }
+}
+
+fn state_topic(t: &MqttTopics, m: &MqttMetric) -> String {
+    m.topic.clone().unwrap_or_else(|| format!("{}/{}", t.base, m.key))
+}
+
+/// 生成 Home Assistant discovery 配置消息：(topic, payload)
+pub fn discovery_messages(cfg: &MqttConfig, t: &MqttTopics, host: &str) -> Vec<(String, String)> {
+    let default_every = cfg.publish_interval_ms.unwrap_or(10_000).max(500);
+    let mut out = Vec::new();
+    for m in effective_metrics(cfg) {
+        let meta = crate::metrics_utils::metric_meta(&m.key);
+        let name = m
+            .name
+            .clone()
+            .or_else(|| meta.map(|x| x.name.to_string()))
+            .unwrap_or_else(|| m.key.clone());
+        let every_ms = m.interval_ms.unwrap_or(default_every).max(500);
+        let mut payload = serde_json::json!({
+            "name": name,
+            "unique_id": format!("{}_{}", t.node_id, m.key),
+            "object_id": format!("{}_{}", t.node_id, m.key),
+            "state_topic": state_topic(t, &m),
+            "availability_topic": t.availability,
+            "payload_available": "online",
+            "payload_not_available": "offline",
+            // 超过 3 个发布周期无更新即视为不可用
+            "expire_after": (every_ms * 3 / 1000).max(1),
+            "device": {
+                "identifiers": [format!("sys_sensor_{}", t.node_id)],
+                "name": host,
+                "manufacturer": "sys-sensor",
+                "model": "sys-sensor",
+                "sw_version": env!("CARGO_PKG_VERSION"),
+            },
+        });
+        if let Some(meta) = meta {
+            payload["state_class"] = serde_json::json!(meta.state_class);
+            if let Some(u) = meta.unit { payload["unit_of_measurement"] = serde_json::json!(u); }
+            if let Some(dc) = meta.device_class { payload["device_class"] = serde_json::json!(dc); }
+        }
+        let topic = format!("{}/sensor/{}/{}/config", t.discovery_prefix, t.node_id, m.key);
+        out.push((topic, payload.to_string()));
+    }
+    out
+}
+
+// ---- MQTT 3.1.1 编解码 ----
+
+fn encode_remaining_len(mut len: usize, out: &mut Vec<u8>) {
+    loop {
+        let mut byte = (len % 128) as u8;
+        len /= 128;
+        if len > 0 { byte |= 0x80; }
+        out.push(byte);
+        if len == 0 { break; }
+    }
+}
+
+fn push_str(buf: &mut Vec<u8>, s: &str) {
+    buf.extend_from_slice(&(s.len() as u16).to_be_bytes());
+    buf.extend_from_slice(s.as_bytes());
+}
+
+fn packet(header: u8, body: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(body.len() + 5);
+    out.push(header);
+    encode_remaining_len(body.len(), &mut out);
+    out.extend_from_slice(body);
+    out
+}
+
+/// CONNECT 报文（clean session，LWT 为 availability=offline retain）
+pub fn encode_connect(client_id: &str, keepalive: u16, username: Option<&str>, password: Option<&str>, will_topic: &str) -> Vec<u8> {
+    let mut flags: u8 = 0x02 | 0x04 | 0x20; // clean session + will flag + will retain（QoS0）
+    if username.is_some() { flags |= 0x80; }
+    if username.is_some() && password.is_some() { flags |= 0x40; }
+    let mut body = Vec::new();
+    push_str(&mut body, "MQTT");
+    body.push(4); // 协议级别 3.1.1
+    body.push(flags);
+    body.extend_from_slice(&keepalive.to_be_bytes());
+    push_str(&mut body, client_id);
+    push_str(&mut body, will_topic);
+    push_str(&mut body, "offline");
+    if let Some(u) = username {
+        push_str(&mut body, u);
+        if let Some(p) = password { push_str(&mut body, p); }
+    }
+    packet(0x10, &body)
+}
+
+/// PUBLISH QoS0 报文
+pub fn encode_publish(topic: &str, payload: &[u8], retain: bool) -> Vec<u8> {
+    let mut body = Vec::with_capacity(topic.len() + payload.len() + 2);
+    push_str(&mut body, topic);
+    body.extend_from_slice(payload);
+    packet(0x30 | if retain { 0x01 } else { 0x00 }, &body)
+}
+
+/// 从缓冲区解析一个完整报文：返回 (类型高4位, 报文体, 消耗字节数)
+pub fn decode_packet(buf: &[u8]) -> Option<(u8, Vec<u8>, usize)> {
+    if buf.len() < 2 { return None; }
+    let mut len: usize = 0;
+    let mut mult: usize = 1;
+    let mut idx = 1;
+    loop {
+        let b = *buf.get(idx)?;
+        len += (b & 0x7F) as usize * mult;
+        idx += 1;
+        if b & 0x80 == 0 { break; }
+        mult *= 128;
+        if idx > 4 { return None; }
+    }
+    if buf.len() < idx + len { return None; }
+    Some((buf[0] >> 4, buf[idx..idx + len].to_vec(), idx + len))
+}
+
+// ---- 连接 ----
+
+enum MqttStream {
+    Plain(TcpStream),
+    Tls(Box<rustls::StreamOwned<rustls::ClientConnection, TcpStream>>),
+}
+
+impl Read for MqttStream {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        match self {
+            MqttStream::Plain(s) => s.read(buf),
+            MqttStream::Tls(s) => s.read(buf),
+        }
+    }
+}
+
+impl Write for MqttStream {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        match self {
+            MqttStream::Plain(s) => s.write(buf),
+            MqttStream::Tls(s) => s.write(buf),
+        }
+    }
+    fn flush(&mut self) -> std::io::Result<()> {
+        match self {
+            MqttStream::Plain(s) => s.flush(),
+            MqttStream::Tls(s) => s.flush(),
+        }
+    }
+}
+
+pub(crate) fn tls_config(ca_file: Option<&str>) -> Result<Arc<rustls::ClientConfig>, String> {
+    use rustls::pki_types::pem::PemObject;
+    let mut roots = rustls::RootCertStore::empty();
+    match ca_file {
+        Some(path) => {
+            let certs = rustls::pki_types::CertificateDer::pem_file_iter(path)
+                .map_err(|e| format!("读取 CA 文件失败: {}", e))?;
+            for c in certs {
+                let c = c.map_err(|e| format!("解析 CA 证书失败: {}", e))?;
+                roots.add(c).map_err(|e| format!("添加 CA 证书失败: {}", e))?;
+            }
+        }
+        None => roots.extend(webpki_roots::TLS_SERVER_ROOTS.iter().cloned()),
+    }
+    let provider = Arc::new(rustls::crypto::ring::default_provider());
+    let cfg = rustls::ClientConfig::builder_with_provider(provider)
+        .with_safe_default_protocol_versions()
+        .map_err(|e| e.to_string())?
+        .with_root_certificates(roots)
+        .with_no_client_auth();
+    Ok(Arc::new(cfg))
+}
+
+/// 已建立的 MQTT 连接
+pub struct MqttConn {
+    stream: MqttStream,
+    rx_buf: Vec<u8>,
+    keepalive: Duration,
+    last_tx: Instant,
+    last_rx: Instant,
+    // 已发出、尚未收到 PINGRESP 的 PINGREQ 发送时刻
+    ping_sent: Option<Instant>,
+}
+
+impl MqttConn {
+    /// 建立连接并完成 CONNECT/CONNACK 握手
+    pub fn connect(cfg: &MqttConfig, topics: &MqttTopics) -> Result<Self, String> {
+        let use_tls = cfg.tls.unwrap_or(false);
+        let host = cfg.host.clone().unwrap_or_else(|| "127.0.0.1".to_string());
+        let port = cfg.port.unwrap_or(if use_tls { 8883 } else { 1883 });
+        let addr = (host.as_str(), port)
+            .to_socket_addrs()
+            .map_err(|e| format!("解析 broker 地址失败: {}", e))?
+            .next()
+            .ok_or_else(|| "broker 地址无结果".to_string())?;
+        let tcp = TcpStream::connect_timeout(&addr, Duration::from_secs(5)).map_err(|e| format!("连接 broker 失败: {}", e))?;
+        let _ = tcp.set_nodelay(true);
+        tcp.set_read_timeout(Some(Duration::from_secs(5))).map_err(|e| e.to_string())?;
+
+        let mut stream = if use_tls {
+            let tls = tls_config(cfg.ca_file.as_deref())?;
+            let name = rustls::pki_types::ServerName::try_from(host.clone()).map_err(|e| format!("无效的 TLS 主机名: {}", e))?;
+            let conn = rustls::ClientConnection::new(tls, name).map_err(|e| e.to_string())?;
+            MqttStream::Tls(Box::new(rustls::StreamOwned::new(conn, tcp)))
+        } else {
+            MqttStream::Plain(tcp)
+        };
+
+        let keepalive = cfg.keepalive_sec.unwrap_or(60).max(5);
+        let client_id = cfg.client_id.clone().unwrap_or_else(|| format!("sys-sensor-{}", topics.node_id));
+        let connect = encode_connect(
+            &client_id,
+            keepalive,
+            cfg.username.as_deref().filter(|s| !s.is_empty()),
+            cfg.password.as_deref(),
+            &topics.availability,
+        );
+        stream.write_all(&connect).map_err(|e| format!("发送 CONNECT 失败: {}", e))?;
+        stream.flush().map_err(|e| e.to_string())?;
+
+        // 等待 CONNACK
+        let mut buf: Vec<u8> = Vec::new();
+        let mut tmp = [0u8; 256];
+        let deadline = Instant::now() + Duration::from_secs(5);
+        loop {
+            if let Some((kind, body, used)) = decode_packet(&buf) {
+                buf.drain(..used);
+                if kind != 2 || body.len() < 2 { return Err(format!("意外的握手报文类型: {}", kind)); }
+                return match body[1] {
+                    0 => {
+                        // 握手完成后改为短超时，便于循环内轮询读
+                        if let MqttStream::Plain(s) = &stream { let _ = s.set_read_timeout(Some(Duration::from_millis(50))); }
+                        if let MqttStream::Tls(s) = &stream { let _ = s.sock.set_read_timeout(Some(Duration::from_millis(50))); }
+                        Ok(Self {
+                            stream,
+                            rx_buf: buf,
+                            keepalive: Duration::from_secs(keepalive as u64),
+                            last_tx: Instant::now(),
+                            last_rx: Instant::now(),
+                            ping_sent: None,
+                        })
+                    }
+                    1 => Err("broker 拒绝：协议版本不支持".to_string()),
+                    2 => Err("broker 拒绝：client id 无效".to_string()),
+                    3 => Err("broker 拒绝：服务不可用".to_string()),
+                    4 => Err("broker 拒绝：用户名或密码错误".to_string()),
+                    5 => Err("broker 拒绝：未授权".to_string()),
+                    rc => Err(format!("broker 拒绝：返回码 {}", rc)),
+                };
+            }
+            if Instant::now() > deadline { return Err("等待 CONNACK 超时".to_string()); }
+            match stream.read(&mut tmp) {
+                Ok(0) => return Err("broker 关闭连接".to_string()),
+                Ok(n) => buf.extend_from_slice(&tmp[..n]),
+                Err(e) if matches!(e.kind(), std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut) => {}
+                Err(e) => return Err(format!("读取 CONNACK 失败: {}", e)),
+            }
+        }
+    }
+
+    pub fn publish(&mut self, topic: &str, payload: &str, retain: bool) -> Result<(), String> {
+        self.stream
+            .write_all(&encode_publish(topic, payload.as_bytes(), retain))
+            .and_then(|_| self.stream.flush())
+            .map_err(|e| format!("发布失败: {}", e))?;
+        self.last_tx = Instant::now();
+        Ok(())
+    }
+
+    /// 维持心跳并消费入站报文；连接失效返回 Err
+    pub fn poll(&mut self) -> Result<(), String> {
+        let mut tmp = [0u8; 1024];
+        match self.stream.read(&mut tmp) {
+            Ok(0) => return Err("broker 关闭连接".to_string()),
+            Ok(n) => {
+                self.rx_buf.extend_from_slice(&tmp[..n]);
+                self.last_rx = Instant::now();
+            }
+            Err(e) if matches!(e.kind(), std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut) => {}
+            Err(e) => return Err(format!("读取失败: {}", e)),
+        }
+        // 仅关心 PINGRESP，其余控制报文直接丢弃
+        while let Some((kind, _body, used)) = decode_packet(&self.rx_buf) {
+            self.rx_buf.drain(..used);
+            if kind == 13 { self.ping_sent = None; }
+        }
+        // QoS0 发布没有回执：按入站静默时间探测连接（持续发布时 last_tx 一直是新的），空闲时按出站时间维持 keepalive
+        let half = self.keepalive / 2;
+        if self.ping_sent.is_none() && (self.last_rx.elapsed() >= half || self.last_tx.elapsed() >= half) {
+            self.stream
+                .write_all(&[0xC0, 0x00])
+                .and_then(|_| self.stream.flush())
+                .map_err(|e| format!("发送 PINGREQ 失败: {}", e))?;
+            self.last_tx = Instant::now();
+            self.ping_sent = Some(self.last_tx);
+        }
+        // 只有 PINGREQ 未获应答才算超时
+        if self.ping_sent.is_some_and(|t| t.elapsed() > self.keepalive) {
+            return Err("心跳超时".to_string());
+        }
+        Ok(())
+    }
+
+    /// 主动断开：先发布 offline，再发送 DISCONNECT（DISCONNECT 不会触发 LWT）
+    pub fn disconnect(mut self, availability_topic: &str) {
+        let _ = self.publish(availability_topic, "offline", true);
+        let _ = self.stream.write_all(&[0xE0, 0x00]);
+        let _ = self.stream.flush();
+    }
+}
+
+// ---- 发布线程 ----
+
+/// 发布句柄：采样线程持有，push 只做一次 channel 发送
+#[derive(Clone)]
+pub struct MqttSink {
+    tx: Sender<SensorSnapshot>,
+}
+
+impl MqttSink {
+    pub fn push(&self, snap: &SensorSnapshot) {
+        let _ = self.tx.send(snap.clone());
+    }
+}
+
+/// 下一次重连退避：首次取 reconnect_min_ms，之后翻倍直至 reconnect_max_ms
+fn next_backoff(cfg: &MqttConfig, prev_ms: u64) -> u64 {
+    let min = cfg.reconnect_min_ms.unwrap_or(1000).max(100);
+    let max = cfg.reconnect_max_ms.unwrap_or(60_000).max(min);
+    if prev_ms == 0 { min } else { (prev_ms * 2).min(max) }
+}
+
+fn fmt_value(v: f64) -> String {
+    if v.fract() == 0.0 && v.abs() < 1e15 { format!("{}", v as i64) } else { format!("{:.2}", v) }
+}
+
+/// 启动 MQTT 发布后台线程
+pub fn start_mqtt_sink(cfg_state: Arc<Mutex<AppConfig>>) -> MqttSink {
+    let (tx, rx): (Sender<SensorSnapshot>, Receiver<SensorSnapshot>) = mpsc::channel();
+    let host = sysinfo::System::host_name().unwrap_or_else(|| "sys-sensor".to_string());
+
+    std::thread::Builder::new()
+        .name("mqtt-sink".into())
+        .spawn(move || {
+            // 连接与建连时使用的主题：配置变更后需向旧的 availability 主题发送 offline
+            let mut conn: Option<(MqttConn, MqttTopics)> = None;
+            let mut latest: Option<SensorSnapshot> = None;
+            let mut last_pub: HashMap<String, Instant> = HashMap::new();
+            let mut backoff_ms: u64 = 0;
+            let mut next_retry = Instant::now();
+            // 配置变化（broker/主题/指标）时需要重连并重发 discovery
+            let mut active_cfg_sig: Option<String> = None;
+
+            loop {
+                // 汇总本周期的快照，仅保留最新一帧
+                match rx.recv_timeout(Duration::from_millis(200)) {
+                    Ok(s) => latest = Some(s),
+                    Err(mpsc::RecvTimeoutError::Timeout) => {}
+                    Err(mpsc::RecvTimeoutError::Disconnected) => {
+                        if let Some((c, t)) = conn.take() {
+                            c.disconnect(&t.availability);
+                        }
+                        return;
+                    }
+                }
+                for s in rx.try_iter() { latest = Some(s); }
+
+                let cfg: MqttConfig = cfg_state.lock().ok().and_then(|c| c.mqtt.clone()).unwrap_or_default();
+                let topics = resolve_topics(&cfg, &host);
+                with_status(|s| s.enabled = cfg.enabled);
+
+                let sig = serde_json::to_string(&cfg).unwrap_or_default();
+                if !cfg.enabled || active_cfg_sig.as_deref() != Some(sig.as_str()) {
+                    if let Some((c, t)) = conn.take() {
+                        eprintln!("[mqtt] 配置变更或已禁用，断开连接");
+                        c.disconnect(&t.availability);
+                        with_status(|s| s.connected = false);
+                    }
+                    if !cfg.enabled { active_cfg_sig = None; continue; }
+                }
+
+                // 1) 建连（带指数退避）
+                if conn.is_none() {
+                    if Instant::now() < next_retry { continue; }
+                    let broker = format!("{}:{}", cfg.host.clone().unwrap_or_else(|| "127.0.0.1".to_string()),
+                        cfg.port.unwrap_or(if cfg.tls.unwrap_or(false) { 8883 } else { 1883 }));
+                    // CONNACK 之后的 online / discovery 发布失败与建连失败同样走退避，避免立即重连
+                    let connected = MqttConn::connect(&cfg, &topics).and_then(|mut c| {
+                        c.publish(&topics.availability, "online", true)?;
+                        if cfg.discovery_enabled.unwrap_or(true) {
+                            for (t, p) in discovery_messages(&cfg, &topics, &host) {
+                                c.publish(&t, &p, true)?;
+                            }
+                        }
+                        Ok(c)
+                    });
+                    match connected {
+                        Ok(c) => {
+                            eprintln!("[mqtt] 已连接 broker {}", broker);
+                            backoff_ms = 0;
+                            active_cfg_sig = Some(sig);
+                            last_pub.clear();
+                            with_status(|s| {
+                                s.broker = Some(broker);
+                                s.connected = true;
+                                s.connects = s.connects.saturating_add(1);
+                                s.last_connect_ms = Some(chrono::Local::now().timestamp_millis());
+                                s.next_retry_ms = None;
+                                s.last_error = None;
+                            });
+                            conn = Some((c, topics.clone()));
+                        }
+                        Err(e) => {
+                            backoff_ms = next_backoff(&cfg, backoff_ms);
+                            next_retry = Instant::now() + Duration::from_millis(backoff_ms);
+                            eprintln!("[mqtt] 连接失败（{}），{}ms 后重试", e, backoff_ms);
+                            with_status(|s| {
+                                s.broker = Some(broker);
+                                s.connected = false;
+                                s.next_retry_ms = Some(backoff_ms);
+                                s.last_error = Some(e);
+                            });
+                        }
+                    }
+                    if conn.is_none() { continue; }
+                }
+
+                // 2) 按各指标频率发布
+                let mut failed: Option<String> = None;
+                if let (Some((c, _)), Some(snap)) = (conn.as_mut(), latest.as_ref()) {
+                    let default_every = cfg.publish_interval_ms.unwrap_or(10_000).max(500);
+                    let values = crate::metrics_utils::snapshot_scalar_fields(snap);
+                    let mut published: u64 = 0;
+                    for m in effective_metrics(&cfg) {
+                        let every = Duration::from_millis(m.interval_ms.unwrap_or(default_every).max(500));
+                        let due = last_pub.get(&m.key).map(|t| t.elapsed() >= every).unwrap_or(true);
+                        if !due { continue; }
+                        let Some((_, v)) = values.iter().find(|(k, _)| *k == m.key) else { continue };
+                        if let Err(e) = c.publish(&state_topic(&topics, &m), &fmt_value(*v), cfg.retain.unwrap_or(false)) {
+                            failed = Some(e);
+                            break;
+                        }
+                        last_pub.insert(m.key.clone(), Instant::now());
+                        published += 1;
+                    }
+                    if published > 0 { with_status(|s| s.published = s.published.saturating_add(published)); }
+                }
+
+                // 3) 心跳与入站处理
+                if failed.is_none() {
+                    if let Some((c, _)) = conn.as_mut() {
+                        if let Err(e) = c.poll() { failed = Some(e); }
+                    }
+                }
+
+                if let Some(e) = failed {
+                    eprintln!("[mqtt] 连接中断: {}", e);
+                    conn = None;
+                    backoff_ms = next_backoff(&cfg, 0);
+                    next_retry = Instant::now() + Duration::from_millis(backoff_ms);
+                    with_status(|s| {
+                        s.connected = false;
+                        s.next_retry_ms = Some(backoff_ms);
+                        s.last_error = Some(e);
+                    });
+                }
+            }
+        })
+        .expect("spawn mqtt-sink");
+
+    MqttSink { tx }
+}
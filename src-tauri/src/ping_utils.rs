@@ -1,47 +1,309 @@
 // =============================================================================
-// Ping/RTT 工具模块（Windows）
-// - ICMP RTT（优先）
+// Ping/RTT 工具模块（Windows / Linux）
+// - ICMP / ICMPv6 RTT（优先；Windows 使用 IcmpSendEcho，Linux 使用 icmp_linux 数据报/原始套接字）
 // - TCP 连接 RTT 回退
 // - HTTPS 请求 RTT 回退
 // - 多目标并发测量与聚合
+// - 多包探测：每目标连发 N 个包，统计 min/avg/max/stddev、p50/p95、抖动与丢包率
+// - 目标格式：host、host:port、IPv4/IPv6 字面量、[v6]:port、http(s):// URL
+// - 地址族偏好：auto / v4 / v6 / both / happy_eyeballs，结果按实际使用的地址族标注
+// - 按目标配置：探测方式（icmp/tcp/http/https/dns）、端口、URL 路径、期望状态码、超时、包数、间隔、名称与分组
 // =============================================================================
 
-use std::net::{Ipv4Addr, ToSocketAddrs};
+use std::net::{IpAddr, SocketAddr, TcpStream, ToSocketAddrs};
+use std::sync::mpsc;
 use std::time::{Duration, Instant};
 use std::thread;
 
-use crate::process_utils::{_tcp_rtt_ms, RttResultPayload};
+use serde::{Deserialize, Deserializer, Serialize};
+
+use crate::process_utils::RttResultPayload;
 
 // windows crate: Icmp API
+#[cfg(windows)]
+use ::windows::Win32::Foundation::HANDLE;
+#[cfg(windows)]
 use ::windows::Win32::NetworkManagement::IpHelper::{
     IcmpCreateFile, IcmpSendEcho, IcmpCloseHandle, ICMP_ECHO_REPLY,
+    Icmp6CreateFile, Icmp6SendEcho2, ICMPV6_ECHO_REPLY_LH,
 };
+#[cfg(windows)]
+use ::windows::Win32::Networking::WinSock::{AF_INET6, IN6_ADDR, IN6_ADDR_0, SOCKADDR_IN6};
+
+// Happy Eyeballs（RFC 8305）：IPv6 先行，超过该时间未成功再并行发起 IPv4
+const HAPPY_EYEBALLS_DELAY_MS: u64 = 250;
+
+/// 地址族偏好
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FamilyPref {
+    // 优先 IPv4，无 A 记录时使用 IPv6
+    #[default]
+    Auto,
+    V4,
+    V6,
+    // 两个地址族各测一组（结果分别标注 family，便于对比 v4/v6 路径）
+    Both,
+    // 先发起 IPv6，短暂延迟后并行发起 IPv4，取先成功者
+    HappyEyeballs,
+}
+
+/// 探测方式
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ProbeMethod {
+    // 按目标形态回退：ICMP -> TCP（带端口） -> HTTPS；URL 目标按协议做 HTTP(S)
+    #[default]
+    Auto,
+    Icmp,
+    Tcp,
+    Http,
+    Https,
+    // 目标为 DNS 服务器，测量一次 UDP 查询的往返
+    Dns,
+}
+
+impl ProbeMethod {
+    pub fn label(&self) -> &'static str {
+        match self {
+            ProbeMethod::Auto => "auto",
+            ProbeMethod::Icmp => "icmp",
+            ProbeMethod::Tcp => "tcp",
+            ProbeMethod::Http => "http",
+            ProbeMethod::Https => "https",
+            ProbeMethod::Dns => "dns",
+        }
+    }
+}
 
-/// 解析字符串为 IPv4 地址（支持 "host:port"，优先直接 IPv4 字符串）
-fn parse_ipv4(s: &str) -> Option<Ipv4Addr> {
-    // 1) 如果形如 host:port，先取 host 部分
-    let host = if let Some(idx) = s.rfind(':') {
-        // 排除 IPv6 情况（含有两个或以上冒号）
-        if s.matches(':').count() > 1 { s } else { &s[..idx] }
-    } else { s };
+/// 解析后的探测目标
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ProbeTarget {
+    // 主机名或 IP 字面量（IPv6 不含方括号）
+    pub host: String,
+    pub port: Option<u16>,
+    // 目标本身为 http(s) URL 时保留原串，仅做 HTTP 探测
+    pub url: Option<String>,
+    pub method: ProbeMethod,
+    // http(s) 路径（默认 /）
+    pub path: Option<String>,
+    // http(s) 期望状态码（dns 为期望 RCODE）；未设置时 http 状态 <400、dns NOERROR/NXDOMAIN 视为成功
+    pub expect_status: Option<u16>,
+    // dns 查询的域名
+    pub query: Option<String>,
+}
 
-    // 2) 直接按 IPv4 解析
-    if let Ok(ip) = host.parse::<Ipv4Addr>() { return Some(ip); }
+/// RTT 目标（config.json 中 rtt_targets 的元素；兼容旧的字符串写法 "host:port"）
+/// 未设置的字段使用全局默认值（rtt_timeout_ms / rtt_probe_count / rtt_probe_interval_ms / rtt_family）
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RttTarget {
+    // host、host:port、[v6]:port 或 http(s):// URL；dns 方式下为 DNS 服务器地址
+    pub target: String,
+    // 显示名称
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    // 分组（如 "dns"、"cdn"、"gateway"）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub group: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub method: Option<ProbeMethod>,
+    // 覆盖 target 中的端口（tcp 默认 443，dns 默认 53）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub port: Option<u16>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expect_status: Option<u16>,
+    // dns 查询的域名（默认 example.com）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub query: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout_ms: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub count: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub interval_ms: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub family: Option<FamilyPref>,
+}
+
+impl RttTarget {
+    pub fn new(target: &str) -> Self {
+        Self { target: target.to_string(), ..Default::default() }
+    }
 
-    // 3) 尝试 DNS 解析（附加端口 0）
-    let query = format!("{}:0", host);
-    if let Ok(mut iter) = query.to_socket_addrs() {
-        if let Some(std::net::SocketAddr::V4(v4)) = iter.find(|a| matches!(a, std::net::SocketAddr::V4(_))) {
-            return Some(*v4.ip());
+    /// 从配置值解析：字符串（旧写法）或对象；无法解析时返回 None
+    pub fn from_value(v: &serde_json::Value) -> Option<Self> {
+        match v {
+            serde_json::Value::String(s) => Some(Self::new(s)),
+            serde_json::Value::Object(_) => serde_json::from_value(v.clone()).ok(),
+            _ => None,
         }
+        .filter(|t| !t.target.trim().is_empty())
+    }
+
+    /// 合并目标串与显式配置，得到探测参数
+    pub fn probe(&self) -> ProbeTarget {
+        let mut p = parse_target(&self.target);
+        if let Some(port) = self.port { p.port = Some(port); }
+        if let Some(m) = self.method { p.method = m; }
+        p.path = self.path.clone();
+        p.expect_status = self.expect_status;
+        p.query = self.query.clone();
+        p
     }
-    None
 }
 
-/// ICMP RTT（毫秒）。优先用于 IPv4 目标。返回 None 表示失败
-pub fn icmp_rtt_ms(target: &str, timeout_ms: u32) -> Option<f64> {
-    let ip = parse_ipv4(target)?;
+/// serde：rtt_targets 兼容字符串与对象混写（无法解析的元素忽略）
+pub fn deserialize_targets<'de, D: Deserializer<'de>>(d: D) -> Result<Option<Vec<RttTarget>>, D::Error> {
+    let raw: Option<Vec<serde_json::Value>> = Option::deserialize(d)?;
+    Ok(raw.map(|list| list.iter().filter_map(RttTarget::from_value).collect()))
+}
 
+/// 全局默认探测参数
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ProbeDefaults {
+    pub timeout_ms: u64,
+    pub count: u32,
+    pub interval_ms: u64,
+    pub family: FamilyPref,
+}
+
+impl Default for ProbeDefaults {
+    fn default() -> Self {
+        Self { timeout_ms: 300, count: DEFAULT_PROBE_COUNT, interval_ms: DEFAULT_PROBE_INTERVAL_MS, family: FamilyPref::Auto }
+    }
+}
+
+impl ProbeDefaults {
+    pub fn from_config(cfg: &crate::config_utils::AppConfig) -> Self {
+        let d = Self::default();
+        Self {
+            timeout_ms: cfg.rtt_timeout_ms.unwrap_or(d.timeout_ms),
+            count: cfg.rtt_probe_count.unwrap_or(d.count),
+            interval_ms: cfg.rtt_probe_interval_ms.unwrap_or(d.interval_ms),
+            family: cfg.rtt_family.unwrap_or(d.family),
+        }
+    }
+}
+
+/// 单次探测结果
+#[derive(Clone, Debug, PartialEq)]
+pub struct RttSample {
+    pub rtt_ms: f64,
+    // 实际使用的地址
+    pub addr: IpAddr,
+    // ICMP 回复的 TTL / Hop Limit（TCP/HTTPS 回退时为 None）
+    pub ttl: Option<u8>,
+    // 实际使用的探测方式
+    pub method: ProbeMethod,
+    // http(s) 状态码 / dns RCODE
+    pub status: Option<u16>,
+    // http(s) 分阶段计时
+    pub http: Option<crate::http_timing::HttpTiming>,
+}
+
+/// ICMP 回显结果
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct IcmpEcho {
+    pub rtt_ms: f64,
+    pub ttl: Option<u8>,
+}
+
+/// 按配置生成测量目标（rtt_targets；条目级地址族见 RttTarget.family）
+pub fn targets_from_config(cfg: &crate::config_utils::AppConfig) -> Vec<RttTarget> {
+    cfg.rtt_targets.clone().unwrap_or_else(|| vec![
+        RttTarget::new("114.114.114.114:443"),
+        RttTarget::new("223.5.5.5:443"),
+    ])
+}
+
+/// 拆分 host 与端口：支持 host:port、[v6]:port、[v6] 与不带方括号的 IPv6 字面量
+pub(crate) fn split_host_port(s: &str) -> (String, Option<u16>) {
+    if let Some(rest) = s.strip_prefix('[') {
+        if let Some(end) = rest.find(']') {
+            let port = rest[end + 1..].strip_prefix(':').and_then(|p| p.parse().ok());
+            return (rest[..end].to_string(), port);
+        }
+    }
+    match s.rfind(':') {
+        // 两个及以上冒号：IPv6 字面量，不含端口
+        Some(_) if s.matches(':').count() > 1 => (s.to_string(), None),
+        Some(idx) => match s[idx + 1..].parse::<u16>() {
+            Ok(port) => (s[..idx].to_string(), Some(port)),
+            Err(_) => (s.to_string(), None),
+        },
+        None => (s.to_string(), None),
+    }
+}
+
+/// 解析目标字符串
+pub fn parse_target(s: &str) -> ProbeTarget {
+    let s = s.trim();
+    for scheme in ["http://", "https://"] {
+        if let Some(rest) = s.strip_prefix(scheme) {
+            let authority = rest.split(['/', '?', '#']).next().unwrap_or("");
+            let authority = authority.rsplit('@').next().unwrap_or(authority);
+            let (host, port) = split_host_port(authority);
+            let method = if scheme == "http://" { ProbeMethod::Http } else { ProbeMethod::Https };
+            return ProbeTarget { host, port, url: Some(s.to_string()), method, ..Default::default() };
+        }
+    }
+    let (host, port) = split_host_port(s);
+    ProbeTarget { host, port, ..Default::default() }
+}
+
+/// 解析目标地址（按地址族过滤，保持系统解析顺序并去重）
+pub fn resolve_target(host: &str, family: FamilyPref) -> Vec<IpAddr> {
+    let all: Vec<IpAddr> = match host.parse::<IpAddr>() {
+        Ok(ip) => vec![ip],
+        Err(_) => (host, 0u16)
+            .to_socket_addrs()
+            .map(|it| it.map(|a| a.ip()).collect())
+            .unwrap_or_default(),
+    };
+    let mut out: Vec<IpAddr> = Vec::with_capacity(all.len());
+    for ip in all {
+        if !out.contains(&ip) { out.push(ip); }
+    }
+    match family {
+        FamilyPref::V4 => out.retain(IpAddr::is_ipv4),
+        FamilyPref::V6 => out.retain(IpAddr::is_ipv6),
+        _ => {}
+    }
+    out
+}
+
+/// 地址族标签
+pub fn family_label(ip: &IpAddr) -> &'static str {
+    if ip.is_ipv4() { "v4" } else { "v6" }
+}
+
+/// ICMP 回显（平台实现：Windows IcmpSendEcho / Icmp6SendEcho2，Linux 数据报或原始套接字）。返回 None 表示失败
+pub fn icmp_echo(ip: IpAddr, timeout_ms: u64) -> Option<IcmpEcho> {
+    #[cfg(windows)]
+    {
+        match ip {
+            IpAddr::V4(v4) => icmp_echo_v4(v4, timeout_ms as u32),
+            IpAddr::V6(v6) => icmp_echo_v6(v6, timeout_ms as u32),
+        }
+    }
+    #[cfg(target_os = "linux")]
+    {
+        crate::icmp_linux::echo(ip, Duration::from_millis(timeout_ms))
+            .ok()
+            .map(|r| IcmpEcho { rtt_ms: r.rtt_ms, ttl: r.ttl })
+    }
+    #[cfg(not(any(windows, target_os = "linux")))]
+    {
+        let _ = (ip, timeout_ms);
+        None
+    }
+}
+
+/// ICMP 回显（IPv4）
+#[cfg(windows)]
+fn icmp_echo_v4(ip: std::net::Ipv4Addr, timeout_ms: u32) -> Option<IcmpEcho> {
     // 创建 ICMP 句柄
     let handle = match unsafe { IcmpCreateFile() } {
         Ok(h) => h,
@@ -54,7 +316,8 @@ pub fn icmp_rtt_ms(target: &str, timeout_ms: u32) -> Option<f64> {
     let reply_size = std::mem::size_of::<ICMP_ECHO_REPLY>() + req_data.len() + 8;
     let mut reply_buf: Vec<u8> = vec![0u8; reply_size];
 
-    let dest_addr: u32 = u32::from(ip);
+    // IPAddr 为网络字节序（与 traceroute::probe_hop_v4 一致）
+    let dest_addr: u32 = u32::from_ne_bytes(ip.octets());
 
     let ret = unsafe {
         IcmpSendEcho(
@@ -74,7 +337,7 @@ pub fn icmp_rtt_ms(target: &str, timeout_ms: u32) -> Option<f64> {
         let reply: &ICMP_ECHO_REPLY = unsafe { &*(reply_buf.as_ptr() as *const ICMP_ECHO_REPLY) };
         // Status==0 表示 IP_SUCCESS
         if reply.Status == 0 {
-            Some(reply.RoundTripTime as f64)
+            Some(IcmpEcho { rtt_ms: reply.RoundTripTime as f64, ttl: Some(reply.Options.Ttl) })
         } else {
             None
         }
@@ -88,65 +351,334 @@ pub fn icmp_rtt_ms(target: &str, timeout_ms: u32) -> Option<f64> {
     out
 }
 
+/// ICMP 回显（IPv6；Icmp6SendEcho2 不返回 Hop Limit）
+#[cfg(windows)]
+fn icmp_echo_v6(ip: std::net::Ipv6Addr, timeout_ms: u32) -> Option<IcmpEcho> {
+    let handle = match unsafe { Icmp6CreateFile() } {
+        Ok(h) => h,
+        Err(_) => return None,
+    };
+
+    let req_data: [u8; 32] = [0x61; 32];
+    // 回复缓冲区：ICMPV6_ECHO_REPLY + 请求数据 + 余量（ICMPv6 错误报文）
+    let reply_size = std::mem::size_of::<ICMPV6_ECHO_REPLY_LH>() + req_data.len() + 8;
+    let mut reply_buf: Vec<u8> = vec![0u8; reply_size];
+
+    // 源地址为未指定地址（由系统按路由选择）
+    let source = SOCKADDR_IN6 { sin6_family: AF_INET6, ..Default::default() };
+    let dest = SOCKADDR_IN6 {
+        sin6_family: AF_INET6,
+        sin6_addr: IN6_ADDR { u: IN6_ADDR_0 { Byte: ip.octets() } },
+        ..Default::default()
+    };
+
+    let ret = unsafe {
+        Icmp6SendEcho2(
+            handle,
+            HANDLE::default(),
+            None,
+            None,
+            &source,
+            &dest,
+            req_data.as_ptr().cast(),
+            req_data.len() as u16,
+            None,
+            reply_buf.as_mut_ptr().cast(),
+            reply_buf.len() as u32,
+            timeout_ms,
+        )
+    };
+
+    let out = if ret > 0 {
+        let reply: &ICMPV6_ECHO_REPLY_LH = unsafe { &*(reply_buf.as_ptr() as *const ICMPV6_ECHO_REPLY_LH) };
+        if reply.Status == 0 {
+            Some(IcmpEcho { rtt_ms: reply.RoundTripTime as f64, ttl: None })
+        } else {
+            None
+        }
+    } else {
+        None
+    };
+
+    unsafe { let _ = IcmpCloseHandle(handle); }
+
+    out
+}
+
+/// TCP 连接 RTT（毫秒）
+pub fn tcp_connect_rtt_ms(addr: SocketAddr, timeout_ms: u64) -> Option<f64> {
+    let start = Instant::now();
+    TcpStream::connect_timeout(&addr, Duration::from_millis(timeout_ms))
+        .ok()
+        .map(|_| start.elapsed().as_secs_f64() * 1000.0)
+}
+
 /// HTTPS RTT（毫秒）。对 URL 发起 HEAD 请求
 pub fn https_head_rtt_ms(url: &str, timeout_ms: u64) -> Option<f64> {
-    let agent = ureq::AgentBuilder::new()
-        .timeout_connect(Duration::from_millis(timeout_ms))
-        .timeout_read(Duration::from_millis(timeout_ms))
-        .timeout_write(Duration::from_millis(timeout_ms))
-        .build();
-    let start = Instant::now();
-    match agent.head(url).call() {
-        Ok(_) => Some(start.elapsed().as_secs_f64() * 1000.0),
-        Err(_) => None,
-    }
+    https_head_rtt_ms_via(url, None, timeout_ms)
 }
 
-/// 单目标 RTT（带回退）：ICMP -> TCP -> HTTPS
-pub fn measure_single_rtt(target: &str, timeout_ms: u64) -> Option<f64> {
-    // 1) ICMP（针对 IPv4 主机/地址）
-    if parse_ipv4(target).is_some() {
-        if let Some(ms) = icmp_rtt_ms(target, timeout_ms as u32) { return Some(ms); }
+/// HTTPS RTT（毫秒），可指定连接地址（用于固定地址族；TLS SNI/Host 仍使用 URL 中的主机名）
+pub fn https_head_rtt_ms_via(url: &str, ip: Option<IpAddr>, timeout_ms: u64) -> Option<f64> {
+    let timing = crate::http_timing::probe(url, ip, timeout_ms);
+    timing.status.filter(|status| *status < 400).and(timing.total_ms)
+}
+
+/// http(s) 探测 URL：URL 目标原样使用；否则按 scheme://host[:port]/path 拼接（IPv6 加方括号）
+fn http_url(t: &ProbeTarget) -> String {
+    if let Some(u) = t.url.as_ref() { return u.clone(); }
+    let scheme = if t.method == ProbeMethod::Http { "http" } else { "https" };
+    let host = if t.host.contains(':') { format!("[{}]", t.host) } else { t.host.clone() };
+    let port = t.port.map(|p| format!(":{}", p)).unwrap_or_default();
+    let path = t.path.as_deref().unwrap_or("/");
+    let sep = if path.starts_with('/') { "" } else { "/" };
+    format!("{}://{}{}{}{}", scheme, host, port, sep, path)
+}
+
+/// 对已解析地址执行一次探测。Err 携带不符合期望的状态码（http 状态 / dns RCODE），传输失败为 Err(None)
+/// auto 方式：ICMP/ICMPv6 -> TCP（需端口） -> HTTPS（去掉端口）
+fn probe_ip(t: &ProbeTarget, ip: IpAddr, timeout_ms: u64) -> Result<RttSample, Option<u16>> {
+    let sample = |rtt_ms: f64, method: ProbeMethod, ttl: Option<u8>, status: Option<u16>| RttSample { rtt_ms, addr: ip, ttl, method, status, http: None };
+    match t.method {
+        ProbeMethod::Icmp => icmp_echo(ip, timeout_ms)
+            .map(|e| sample(e.rtt_ms, ProbeMethod::Icmp, e.ttl, None))
+            .ok_or(None),
+        ProbeMethod::Tcp => tcp_connect_rtt_ms(SocketAddr::new(ip, t.port.unwrap_or(443)), timeout_ms)
+            .map(|ms| sample(ms, ProbeMethod::Tcp, None, None))
+            .ok_or(None),
+        ProbeMethod::Http | ProbeMethod::Https => {
+            let timing = crate::http_timing::probe(&http_url(t), Some(ip), timeout_ms);
+            let (status, ms) = timing.status.zip(timing.total_ms).ok_or(None)?;
+            let ok = match t.expect_status { Some(e) => e == status, None => status < 400 };
+            if !ok { return Err(Some(status)); }
+            Ok(RttSample { http: Some(timing), ..sample(ms, t.method, None, Some(status)) })
+        }
+        ProbeMethod::Dns => {
+            let name = t.query.as_deref().unwrap_or("example.com");
+            let server = crate::dns_probe::DnsServer { addr: SocketAddr::new(ip, t.port.unwrap_or(53)), source: "custom".into(), transport: None };
+            let r = crate::dns_probe::query(&server, crate::dns_probe::DnsTransport::Udp, name, timeout_ms);
+            let (ms, rcode) = r.latency_ms.zip(r.rcode).ok_or(None)?;
+            // 未指定期望时 NOERROR(0)/NXDOMAIN(3) 均视为服务器正常应答
+            let ok = match t.expect_status { Some(e) => e == rcode as u16, None => rcode == 0 || rcode == 3 };
+            if ok { Ok(sample(ms, ProbeMethod::Dns, None, Some(rcode as u16))) } else { Err(Some(rcode as u16)) }
+        }
+        ProbeMethod::Auto => {
+            // 1) ICMP
+            if let Some(echo) = icmp_echo(ip, timeout_ms) {
+                return Ok(sample(echo.rtt_ms, ProbeMethod::Icmp, echo.ttl, None));
+            }
+
+            // 2) TCP 连接（要求带端口）
+            if let Some(port) = t.port {
+                if let Some(ms) = tcp_connect_rtt_ms(SocketAddr::new(ip, port), timeout_ms) {
+                    return Ok(sample(ms, ProbeMethod::Tcp, None, None));
+                }
+            }
+
+            // 3) HTTPS 回退：纯主机/地址拼接 https://host/
+            let fallback = ProbeTarget { method: ProbeMethod::Https, port: None, ..t.clone() };
+            probe_ip(&fallback, ip, timeout_ms)
+        }
     }
+}
 
-    // 2) TCP 连接（要求 host:port）
-    if target.contains(':') {
-        if let Some(ms) = _tcp_rtt_ms(target, timeout_ms) { return Some(ms); }
+/// Happy Eyeballs：IPv6 先行，延迟内未成功则并行发起 IPv4，返回先成功者（均失败时返回最后一个错误）
+fn happy_eyeballs(t: &ProbeTarget, addrs: &[IpAddr], timeout_ms: u64) -> Result<RttSample, Option<u16>> {
+    let v6 = addrs.iter().find(|a| a.is_ipv6()).copied();
+    let v4 = addrs.iter().find(|a| a.is_ipv4()).copied();
+    let (tx, rx) = mpsc::channel();
+    let spawn = |ip: IpAddr| {
+        let (tx, t) = (tx.clone(), t.clone());
+        thread::spawn(move || {
+            let _ = tx.send(probe_ip(&t, ip, timeout_ms));
+        });
+    };
+    let mut pending = 0;
+    let mut last_err: Option<u16> = None;
+    if let Some(ip) = v6 {
+        spawn(ip);
+        pending += 1;
+        match rx.recv_timeout(Duration::from_millis(HAPPY_EYEBALLS_DELAY_MS)) {
+            Ok(Ok(hit)) => return Ok(hit),
+            Ok(Err(status)) => {
+                pending -= 1;
+                last_err = status;
+            }
+            Err(_) => {}
+        }
     }
+    if let Some(ip) = v4 {
+        spawn(ip);
+        pending += 1;
+    }
+    while pending > 0 {
+        match rx.recv() {
+            Ok(Ok(hit)) => return Ok(hit),
+            Ok(Err(status)) => {
+                pending -= 1;
+                last_err = status.or(last_err);
+            }
+            Err(_) => break,
+        }
+    }
+    Err(last_err)
+}
 
-    // 3) HTTPS 回退（将目标转换为 URL）
-    // - 若已带协议则直接使用
-    // - 若是纯主机/IPv4，则拼接 https://host/
-    let url = if target.starts_with("http://") || target.starts_with("https://") {
-        target.to_string()
+/// 单目标单次测量：按地址族偏好选择地址（无可用地址时为 Err(None)）
+/// http(s) 探测的 DNS 阶段在此计时（地址字面量不计），并计入分阶段总耗时
+pub fn measure_target_rtt(t: &ProbeTarget, family: FamilyPref, timeout_ms: u64) -> Result<RttSample, Option<u16>> {
+    let dns_start = Instant::now();
+    let addrs = resolve_target(&t.host, family);
+    let dns_ms = t.host.parse::<IpAddr>().is_err().then(|| dns_start.elapsed().as_secs_f64() * 1000.0);
+    let result = if family == FamilyPref::HappyEyeballs {
+        happy_eyeballs(t, &addrs, timeout_ms)
     } else {
-        // 去掉端口
-        let host = if let Some(idx) = target.rfind(':') {
-            if target.matches(':').count() > 1 { target } else { &target[..idx] }
-        } else { target };
-        format!("https://{}/", host)
+        let ip = match family {
+            FamilyPref::V4 | FamilyPref::V6 => addrs.first(),
+            _ => addrs.iter().find(|a| a.is_ipv4()).or(addrs.first()),
+        }
+        .copied()
+        .ok_or(None)?;
+        probe_ip(t, ip, timeout_ms)
+    };
+    result.map(|mut sample| {
+        if let (Some(h), Some(dns)) = (sample.http.as_mut(), dns_ms) {
+            h.dns_ms = Some(dns);
+            h.total_ms = h.total_ms.map(|v| v + dns);
+        }
+        sample
+    })
+}
+
+/// 单目标 RTT（带回退，地址族自动选择）
+pub fn measure_single_rtt(target: &str, timeout_ms: u64) -> Option<f64> {
+    measure_target_rtt(&parse_target(target), FamilyPref::Auto, timeout_ms).ok().map(|s| s.rtt_ms)
+}
+
+/// 默认每目标探测包数
+pub const DEFAULT_PROBE_COUNT: u32 = 5;
+/// 默认相邻探测包间隔（毫秒）
+pub const DEFAULT_PROBE_INTERVAL_MS: u64 = 200;
+
+/// 百分位（最近秩法）：sorted 须为升序且非空
+fn percentile(sorted: &[f64], p: f64) -> f64 {
+    let rank = ((p / 100.0) * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+/// 多包样本统计（samples 按发送顺序，None 表示该包超时/失败）
+pub fn rtt_stats(target: &str, samples: &[Option<f64>]) -> RttResultPayload {
+    let ok: Vec<f64> = samples.iter().flatten().copied().collect();
+    let sent = samples.len() as u32;
+    let received = ok.len() as u32;
+    let mut out = RttResultPayload {
+        target: target.to_string(),
+        success: Some(received > 0),
+        sent: Some(sent),
+        received: Some(received),
+        loss_pct: if sent > 0 { Some((sent - received) as f64 * 100.0 / sent as f64) } else { None },
+        ..Default::default()
     };
-    https_head_rtt_ms(&url, timeout_ms)
+    if ok.is_empty() { return out; }
+
+    let n = ok.len() as f64;
+    let avg = ok.iter().sum::<f64>() / n;
+    let var = ok.iter().map(|v| (v - avg).powi(2)).sum::<f64>() / n;
+    // RFC 3550：J += (|D| - J) / 16，D 为相邻两包传输时间之差（此处以 RTT 之差近似）
+    let mut jitter = 0.0;
+    for w in ok.windows(2) {
+        jitter += ((w[1] - w[0]).abs() - jitter) / 16.0;
+    }
+    let mut sorted = ok.clone();
+    sorted.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+
+    out.rtt_ms = Some(avg);
+    out.min_ms = sorted.first().copied();
+    out.avg_ms = Some(avg);
+    out.max_ms = sorted.last().copied();
+    out.stddev_ms = Some(var.sqrt());
+    out.p50_ms = Some(percentile(&sorted, 50.0));
+    out.p95_ms = Some(percentile(&sorted, 95.0));
+    out.jitter_ms = Some(jitter);
+    out
 }
 
-/// 多目标并发 RTT 测量
-pub fn measure_multi_rtt(targets: &[String], timeout_ms: u64) -> Vec<RttResultPayload> {
+/// 单目标多包探测：顺序发送 count 个探测（相邻间隔 interval_ms），返回统计结果（标注名称/分组/方式/地址族）
+pub fn measure_rtt_burst(target: &RttTarget, defaults: &ProbeDefaults) -> RttResultPayload {
+    let probe = target.probe();
+    let family = target.family.unwrap_or(defaults.family);
+    let timeout_ms = target.timeout_ms.unwrap_or(defaults.timeout_ms);
+    let count = target.count.unwrap_or(defaults.count).clamp(1, 100);
+    let interval_ms = target.interval_ms.unwrap_or(defaults.interval_ms);
+
+    let mut samples = Vec::with_capacity(count as usize);
+    let mut last_ip: Option<IpAddr> = None;
+    let mut last_ttl: Option<u8> = None;
+    let mut last_method: Option<ProbeMethod> = None;
+    let mut last_status: Option<u16> = None;
+    let mut last_http: Option<crate::http_timing::HttpTiming> = None;
+    for i in 0..count {
+        if i > 0 && interval_ms > 0 { thread::sleep(Duration::from_millis(interval_ms)); }
+        match measure_target_rtt(&probe, family, timeout_ms) {
+            Ok(h) => {
+                last_ip = Some(h.addr);
+                last_ttl = h.ttl.or(last_ttl);
+                last_method = Some(h.method);
+                last_status = h.status.or(last_status);
+                last_http = h.http.or(last_http);
+                samples.push(Some(h.rtt_ms));
+            }
+            Err(status) => {
+                last_status = status.or(last_status);
+                samples.push(None);
+            }
+        }
+    }
+    let mut out = rtt_stats(&target.target, &samples);
+    out.name = target.name.clone();
+    out.group = target.group.clone();
+    out.method = Some(last_method.unwrap_or(probe.method).label().to_string());
+    out.status = last_status;
+    out.http = last_http;
+    // 全部失败时按偏好标注（v4/v6 对比时仍能区分）
+    out.family = match (last_ip, family) {
+        (Some(ip), _) => Some(family_label(&ip).to_string()),
+        (None, FamilyPref::V4) => Some("v4".to_string()),
+        (None, FamilyPref::V6) => Some("v6".to_string()),
+        (None, _) => None,
+    };
+    out.addr = last_ip.map(|ip| ip.to_string());
+    out.ttl = last_ttl;
+    out
+}
+
+/// 多目标并发 RTT 测量（family=both 的目标按 v4/v6 各出一条结果）
+pub fn measure_multi_rtt(targets: &[RttTarget], defaults: &ProbeDefaults) -> Vec<RttResultPayload> {
     if targets.is_empty() { return Vec::new(); }
 
-    let mut handles = Vec::with_capacity(targets.len());
-    for t in targets.iter().cloned() {
+    let jobs: Vec<RttTarget> = targets
+        .iter()
+        .flat_map(|t| match t.family.unwrap_or(defaults.family) {
+            FamilyPref::Both => vec![
+                RttTarget { family: Some(FamilyPref::V4), ..t.clone() },
+                RttTarget { family: Some(FamilyPref::V6), ..t.clone() },
+            ],
+            _ => vec![t.clone()],
+        })
+        .collect();
+
+    let mut handles = Vec::with_capacity(jobs.len());
+    for t in jobs {
+        let defaults = *defaults;
         // 直接每目标一个线程（目标数量通常很少）
-        handles.push(thread::spawn(move || {
-            let rtt = measure_single_rtt(&t, timeout_ms);
-            RttResultPayload { target: t, rtt_ms: rtt, success: Some(rtt.is_some()) }
-        }));
+        handles.push(thread::spawn(move || measure_rtt_burst(&t, &defaults)));
     }
 
-    let mut results = Vec::with_capacity(targets.len());
+    let mut results = Vec::with_capacity(handles.len());
     for h in handles {
         match h.join() {
             Ok(item) => results.push(item),
-            Err(_) => results.push(RttResultPayload { target: "<panic>".into(), rtt_ms: None, success: Some(false) }),
+            Err(_) => results.push(RttResultPayload { target: "<panic>".into(), success: Some(false), ..Default::default() }),
         }
     }
     results
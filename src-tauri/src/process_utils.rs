@@ -9,11 +9,37 @@ use std::time::{Duration, Instant};
 // 1. 数据结构定义
 // ================================================================================
 
-#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
 pub struct RttResultPayload {
     pub target: String,
+    // 目标配置中的显示名称与分组
+    pub name: Option<String>,
+    pub group: Option<String>,
+    // 实际使用的探测方式（icmp/tcp/http/https/dns）与最近一次状态码（http 状态 / dns RCODE）
+    pub method: Option<String>,
+    pub status: Option<u16>,
+    // http(s) 目标最近一次成功探测的分阶段计时（DNS/连接/TLS/首字节/总计、协议与证书剩余天数）
+    pub http: Option<crate::http_timing::HttpTiming>,
+    // 多包探测时为成功样本的平均值
     pub rtt_ms: Option<f64>,
     pub success: Option<bool>,
+    // 多包探测统计（发送/收到的包数，丢包率百分比）
+    pub sent: Option<u32>,
+    pub received: Option<u32>,
+    pub loss_pct: Option<f64>,
+    pub min_ms: Option<f64>,
+    pub avg_ms: Option<f64>,
+    pub max_ms: Option<f64>,
+    pub stddev_ms: Option<f64>,
+    pub p50_ms: Option<f64>,
+    pub p95_ms: Option<f64>,
+    // 抖动（RFC 3550 到达间隔抖动估计，按相邻成功样本计算）
+    pub jitter_ms: Option<f64>,
+    // 实际使用的地址族（"v4" / "v6"）与地址
+    pub family: Option<String>,
+    pub addr: Option<String>,
+    // 最近一次 ICMP 回复的 TTL（IPv6 为 Hop Limit）
+    pub ttl: Option<u8>,
 }
 
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
@@ -27,12 +53,14 @@ pub struct TopProcessPayload {
 // 2. RTT 测试函数
 // ================================================================================
 
-/// TCP RTT 测试（连接指定地址并测量往返时间）
+/// TCP RTT 测试（连接指定地址并测量往返时间；支持 host:port 与 [v6]:port，主机名取首个解析结果）
 pub fn _tcp_rtt_ms(addr: &str, timeout_ms: u64) -> Option<f64> {
+    use std::net::ToSocketAddrs;
+    let sock = addr.to_socket_addrs().ok()?.next()?;
     let start = Instant::now();
     let timeout = Duration::from_millis(timeout_ms);
     
-    match TcpStream::connect_timeout(&addr.parse().ok()?, timeout) {
+    match TcpStream::connect_timeout(&sock, timeout) {
         Ok(_) => {
             let elapsed = start.elapsed();
             Some(elapsed.as_secs_f64() * 1000.0)
@@ -54,6 +82,7 @@ pub fn _measure_multi_rtt(targets: &[String], timeout_ms: u64) -> Option<Vec<Rtt
             target: target.clone(),
             rtt_ms,
             success: Some(rtt_ms.is_some()),
+            ..Default::default()
         });
     }
     
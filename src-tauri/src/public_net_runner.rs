@@ -0,0 +1,85 @@
+// Public Net Runner：公网 IP / ISP 查询
+// 说明：
+// - 依赖 BaseGate 做并发防重入
+// - trigger() 异步执行一轮查询（IPv4 / IPv6 并发，各自按提供者顺序回退），完成后更新内部快照（PublicNetLookup 的 JSON）
+// - 两个地址族均失败时不标记成功，采集循环据此切换到重试节拍
+
+use std::sync::{Arc, Mutex};
+use crate::runner::{Runner, BaseGate};
+use crate::public_net_utils::{lookup_all, PublicNetProvider};
+
+/// 单轮公网查询配置
+#[derive(Clone, Debug)]
+pub struct PublicNetRunConfig {
+    pub providers: Vec<PublicNetProvider>,
+    pub ipv6: bool,
+    pub timeout_ms: u64,
+}
+
+impl PublicNetRunConfig {
+    pub fn from_config(cfg: &crate::config_utils::AppConfig) -> Self {
+        Self {
+            providers: crate::public_net_utils::providers_from_config(cfg),
+            ipv6: cfg.public_net_ipv6.unwrap_or(true),
+            timeout_ms: cfg.public_net_timeout_ms.unwrap_or(crate::public_net_utils::DEFAULT_TIMEOUT_MS),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct PublicNetRunner {
+    gate: Arc<BaseGate>,
+    // 配置提供器：每轮触发时读取
+    cfg_provider: Arc<dyn Fn() -> PublicNetRunConfig + Send + Sync>,
+    // 最近一次结果快照
+    last_snapshot: Arc<Mutex<serde_json::Value>>,
+}
+
+impl PublicNetRunner {
+    pub fn new<F>(cfg_provider: F) -> Self
+    where
+        F: Fn() -> PublicNetRunConfig + Send + Sync + 'static,
+    {
+        Self {
+            gate: Arc::new(BaseGate::new()),
+            cfg_provider: Arc::new(cfg_provider),
+            last_snapshot: Arc::new(Mutex::new(serde_json::json!({}))),
+        }
+    }
+}
+
+impl Runner for PublicNetRunner {
+    fn name(&self) -> &'static str { "public_net_runner" }
+
+    fn trigger(&self, now_ms: i64) {
+        // 防重入
+        if !self.gate.try_enter() { return; }
+        self.gate.set_running();
+        let gate_c = self.gate.clone();
+        let snap_c = self.last_snapshot.clone();
+        let cfg_c = self.cfg_provider.clone();
+        std::thread::spawn(move || {
+            let cfg = (cfg_c)();
+            let mut lookup = lookup_all(&cfg.providers, cfg.ipv6, cfg.timeout_ms);
+            lookup.timestamp_ms = now_ms;
+            if let Ok(v) = serde_json::to_value(&lookup) {
+                if let Ok(mut g) = snap_c.lock() { *g = v; }
+            }
+            if lookup.any_ok() {
+                gate_c.mark_ok_and_exit(now_ms);
+            } else {
+                gate_c.exit();
+            }
+        });
+    }
+
+    fn is_running(&self) -> bool { self.gate.is_running() }
+    fn last_ok_ms(&self) -> Option<i64> { self.gate.last_ok_ms() }
+
+    fn snapshot_json(&self) -> serde_json::Value {
+        match self.last_snapshot.lock() {
+            Ok(g) => (*g).clone(),
+            Err(_) => serde_json::json!({}),
+        }
+    }
+}
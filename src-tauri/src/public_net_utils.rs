@@ -1,84 +1,508 @@
 // 公网IP查询工具模块
-// 包含公网IP和ISP信息的后台轮询功能
-
-use crate::config_utils::PublicNetInfo;
-use std::sync::{Arc, Mutex};
-use std::thread;
-use std::time::Duration;
-
-/// 启动公网IP/ISP后台轮询线程
-pub fn start_public_net_polling(
-    cfg_state: Arc<Mutex<crate::config_utils::AppConfig>>,
-    pub_net: Arc<Mutex<PublicNetInfo>>,
-) {
-    thread::spawn(move || {
-        let agent = ureq::AgentBuilder::new()
-            .timeout_connect(Duration::from_secs(5))
-            .timeout_read(Duration::from_secs(5))
-            .timeout_write(Duration::from_secs(5))
-            .build();
-
-        #[derive(serde::Deserialize)]
-        struct IpApiResp { 
-            status: Option<String>, 
-            query: Option<String>, 
-            isp: Option<String>, 
-            message: Option<String> 
+// 说明：
+// - 提供者列表取自配置 public_net_providers（字符串与对象混写）；未配置时使用内置列表：
+//   ip-api.com → ipinfo.io → api64.ipify.org（纯文本）→ STUN（stun.l.google.com:19302）
+//   旧字段 public_net_api 仍生效：作为首个提供者，失败时回退内置列表
+// - HTTP 提供者按 JSON 路径（如 "query"、"data.ip"、"asn[0]"）提取 ip / isp / asn / country；
+//   未指定 ip_path 时依次尝试常见字段，响应不是 JSON 时按纯文本 IP 处理
+// - "stun:host[:port]" 提供者发送 RFC 5389 Binding Request，取 XOR-MAPPED-ADDRESS（无需 HTTP API）
+// - IPv4 / IPv6 分别查询：HTTP 请求经自定义解析器限定地址族，STUN 绑定对应地址族的本地套接字
+// - 查询由 PublicNetRunner 按调度器节拍触发（pace_public_net_every / pace_public_net_retry_every）
+// - 公网地址变化追加到配置目录下的 public_ip_history.jsonl，超过上限（默认 500）时丢弃最旧的记录（文件按 jsonl_journal 规则延迟压缩）
+
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr, ToSocketAddrs, UdpSocket};
+use std::path::Path;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Deserializer, Serialize};
+
+use crate::jsonl_journal::JsonlJournal;
+use crate::ping_utils::{family_label, resolve_target, split_host_port, FamilyPref};
+
+pub const DEFAULT_TIMEOUT_MS: u64 = 5000;
+// 成功后每 1800 tick（默认节拍约 30 分钟）刷新；全部失败时每 60 tick 重试
+pub const DEFAULT_EVERY: u64 = 1800;
+pub const DEFAULT_RETRY_EVERY: u64 = 60;
+const HISTORY_FILE: &str = "public_ip_history.jsonl";
+const DEFAULT_HISTORY_MAX_ENTRIES: usize = 500;
+const STUN_DEFAULT_PORT: u16 = 3478;
+const STUN_MAGIC_COOKIE: u32 = 0x2112_A442;
+// 未指定 ip_path 时依次尝试的字段
+const IP_FIELDS: [&str; 4] = ["ip", "query", "ip_addr", "address"];
+
+/// 公网 IP 提供者
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct PublicNetProvider {
+    // http(s)://... 或 stun:host[:port]
+    pub url: String,
+    pub name: Option<String>,
+    // 限定地址族（v4 / v6）；缺省两个地址族都使用
+    pub family: Option<FamilyPref>,
+    // JSON 路径（点号分隔，数组下标写作 .0 或 [0]）
+    pub ip_path: Option<String>,
+    pub isp_path: Option<String>,
+    pub asn_path: Option<String>,
+    pub country_path: Option<String>,
+}
+
+impl PublicNetProvider {
+    pub fn new(url: &str) -> Self {
+        Self { url: url.trim().to_string(), ..Default::default() }
+    }
+
+    fn with_paths(url: &str, name: &str, ip: &str, isp: &str, asn: &str, country: &str) -> Self {
+        Self {
+            url: url.to_string(),
+            name: Some(name.to_string()),
+            family: None,
+            ip_path: Some(ip.to_string()),
+            isp_path: Some(isp.to_string()),
+            asn_path: Some(asn.to_string()),
+            country_path: Some(country.to_string()),
+        }
+    }
+
+    /// 从配置值解析：字符串（仅 URL）或对象；无法解析时返回 None
+    pub fn from_value(v: &serde_json::Value) -> Option<Self> {
+        match v {
+            serde_json::Value::String(s) => Some(Self::new(s)),
+            serde_json::Value::Object(_) => serde_json::from_value(v.clone()).ok(),
+            _ => None,
         }
-        
-        #[derive(serde::Deserialize)]
-        struct IpInfoResp { 
-            ip: Option<String>, 
-            org: Option<String> 
+        .filter(|p| !p.url.trim().is_empty())
+    }
+
+    /// 显示名称：name 或 URL 中的主机
+    pub fn label(&self) -> String {
+        if let Some(n) = self.name.as_ref().filter(|n| !n.trim().is_empty()) {
+            return n.clone();
         }
+        let rest = self.url.split_once("://").map(|(_, r)| r).unwrap_or(&self.url);
+        let rest = rest.strip_prefix("stun:").unwrap_or(rest);
+        rest.split(['/', '?']).next().unwrap_or(rest).to_string()
+    }
+
+    pub fn is_stun(&self) -> bool {
+        self.url.trim().get(..5).is_some_and(|s| s.eq_ignore_ascii_case("stun:"))
+    }
+
+    fn supports(&self, family: FamilyPref) -> bool {
+        match self.family {
+            Some(f @ (FamilyPref::V4 | FamilyPref::V6)) => f == family,
+            _ => true,
+        }
+    }
+}
+
+/// serde：public_net_providers 兼容字符串与对象混写（无法解析的元素忽略）
+pub fn deserialize_providers<'de, D: Deserializer<'de>>(d: D) -> Result<Option<Vec<PublicNetProvider>>, D::Error> {
+    let raw: Option<Vec<serde_json::Value>> = Option::deserialize(d)?;
+    Ok(raw.map(|list| list.iter().filter_map(PublicNetProvider::from_value).collect()))
+}
+
+/// 内置提供者
+pub fn builtin_providers() -> Vec<PublicNetProvider> {
+    vec![
+        // ip-api.com 免费接口仅支持 HTTP
+        PublicNetProvider::with_paths("http://ip-api.com/json/?fields=status,message,query,isp,as,countryCode", "ip-api.com", "query", "isp", "as", "countryCode"),
+        // ipinfo.io 的 org 形如 "AS15169 Google LLC"
+        PublicNetProvider::with_paths("https://ipinfo.io/json", "ipinfo.io", "ip", "org", "org", "country"),
+        PublicNetProvider::new("https://api64.ipify.org"),
+        PublicNetProvider::new("stun:stun.l.google.com:19302"),
+    ]
+}
+
+/// 配置中的提供者列表（见模块说明）
+pub fn providers_from_config(cfg: &crate::config_utils::AppConfig) -> Vec<PublicNetProvider> {
+    if let Some(list) = cfg.public_net_providers.as_ref().filter(|l| !l.is_empty()) {
+        return list.clone();
+    }
+    let mut out = Vec::new();
+    if let Some(api) = cfg.public_net_api.as_deref().map(str::trim).filter(|a| !a.is_empty()) {
+        out.push(PublicNetProvider::new(api));
+    }
+    out.extend(builtin_providers());
+    out
+}
+
+/// 单个地址族的查询结果
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct PublicIpResult {
+    pub ip: String,
+    pub isp: Option<String>,
+    pub asn: Option<String>,
+    pub country: Option<String>,
+    pub provider: String,
+}
+
+/// 一轮查询（IPv4 / IPv6 各一次）
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct PublicNetLookup {
+    pub timestamp_ms: i64,
+    pub v4: Option<PublicIpResult>,
+    pub v6: Option<PublicIpResult>,
+    pub v4_error: Option<String>,
+    pub v6_error: Option<String>,
+}
+
+impl PublicNetLookup {
+    pub fn any_ok(&self) -> bool {
+        self.v4.is_some() || self.v6.is_some()
+    }
+}
+
+/// 按路径取 JSON 值（空路径返回自身）
+pub fn json_path<'a>(v: &'a serde_json::Value, path: &str) -> Option<&'a serde_json::Value> {
+    let normalized = path.replace('[', ".").replace(']', "");
+    normalized
+        .split('.')
+        .filter(|seg| !seg.is_empty())
+        .try_fold(v, |cur, seg| match cur {
+            serde_json::Value::Array(a) => seg.parse::<usize>().ok().and_then(|i| a.get(i)),
+            _ => cur.get(seg),
+        })
+}
+
+fn value_text(v: &serde_json::Value) -> Option<String> {
+    match v {
+        serde_json::Value::String(s) => Some(s.trim().to_string()).filter(|s| !s.is_empty()),
+        serde_json::Value::Number(n) => Some(n.to_string()),
+        _ => None,
+    }
+}
+
+/// 规范化 ASN：取 "AS13335 Cloudflare" 的前缀或纯数字，统一为 "AS13335"
+pub fn normalize_asn(s: &str) -> Option<String> {
+    let s = s.trim();
+    let digits_from = |t: &str| {
+        let d: String = t.chars().take_while(|c| c.is_ascii_digit()).collect();
+        (!d.is_empty()).then(|| format!("AS{}", d))
+    };
+    if s.get(..2).is_some_and(|p| p.eq_ignore_ascii_case("AS")) {
+        digits_from(&s[2..])
+    } else if !s.is_empty() && s.chars().all(|c| c.is_ascii_digit()) {
+        Some(format!("AS{}", s))
+    } else {
+        None
+    }
+}
+
+/// 从 HTTP 响应体提取结果，并校验地址族
+pub fn extract(p: &PublicNetProvider, body: &str, family: FamilyPref) -> Result<PublicIpResult, String> {
+    let json: Option<serde_json::Value> = serde_json::from_str(body.trim()).ok();
+    let ip_text = match (&json, p.ip_path.as_deref()) {
+        (Some(v), Some(path)) => json_path(v, path).and_then(value_text),
+        (Some(serde_json::Value::String(s)), None) => Some(s.trim().to_string()),
+        (Some(v), None) => IP_FIELDS.iter().find_map(|f| v.get(*f).and_then(value_text)),
+        (None, Some(_)) => return Err("响应不是 JSON".to_string()),
+        (None, None) => body.lines().map(str::trim).find(|l| !l.is_empty()).map(|l| l.to_string()),
+    };
+    // 缺少 IP 时带上接口返回的错误说明（如 ip-api.com 的 message）
+    let ip_text = ip_text.ok_or_else(|| {
+        match json.as_ref().and_then(|v| ["message", "error", "reason"].iter().find_map(|k| v.get(*k).and_then(value_text))) {
+            Some(m) => format!("响应中没有 IP 字段: {}", m),
+            None => "响应中没有 IP 字段".to_string(),
+        }
+    })?;
+    let ip: IpAddr = ip_text.parse().map_err(|_| format!("无效的 IP: {}", ip_text))?;
+    check_family(&ip, family)?;
+    let field = |path: &Option<String>| -> Option<String> {
+        json.as_ref().zip(path.as_deref()).and_then(|(v, p)| json_path(v, p)).and_then(value_text)
+    };
+    Ok(PublicIpResult {
+        ip: ip.to_string(),
+        isp: field(&p.isp_path),
+        asn: field(&p.asn_path).and_then(|a| normalize_asn(&a)),
+        country: field(&p.country_path),
+        provider: p.label(),
+    })
+}
+
+fn check_family(ip: &IpAddr, family: FamilyPref) -> Result<(), String> {
+    match family {
+        FamilyPref::V4 if !ip.is_ipv4() => Err(format!("期望 IPv4，实际返回 {}", ip)),
+        FamilyPref::V6 if !ip.is_ipv6() => Err(format!("期望 IPv6，实际返回 {}", ip)),
+        _ => Ok(()),
+    }
+}
+
+fn family_matches(a: &SocketAddr, family: FamilyPref) -> bool {
+    match family {
+        FamilyPref::V4 => a.is_ipv4(),
+        FamilyPref::V6 => a.is_ipv6(),
+        _ => true,
+    }
+}
 
-        loop {
-            let enabled = cfg_state
-                .lock().ok()
-                .and_then(|c| c.public_net_enabled)
-                .unwrap_or(true);
-            if !enabled {
-                std::thread::sleep(Duration::from_secs(60));
-                continue;
+/// HTTP(S) 提供者（经自定义解析器限定地址族）
+fn http_lookup(p: &PublicNetProvider, family: FamilyPref, timeout_ms: u64) -> Result<PublicIpResult, String> {
+    let timeout = Duration::from_millis(timeout_ms.max(1));
+    let agent = ureq::AgentBuilder::new()
+        .timeout(timeout)
+        .resolver(move |netloc: &str| -> std::io::Result<Vec<SocketAddr>> {
+            let addrs: Vec<SocketAddr> = netloc.to_socket_addrs()?.filter(|a| family_matches(a, family)).collect();
+            if addrs.is_empty() {
+                return Err(std::io::Error::new(std::io::ErrorKind::NotFound, format!("{} 没有 {} 地址", netloc, family_name(family))));
             }
+            Ok(addrs)
+        })
+        .build();
+    let body = match agent.get(&p.url).set("Accept", "application/json, text/plain").call() {
+        Ok(resp) => resp.into_string().map_err(|e| format!("读取响应失败: {}", e))?,
+        Err(ureq::Error::Status(code, _)) => return Err(format!("HTTP {}", code)),
+        Err(e) => return Err(e.to_string()),
+    };
+    extract(p, &body, family)
+}
 
-            let mut ok = false;
-            // 1) ip-api.com
-            let try1 = agent.get("https://ip-api.com/json/?fields=status,query,isp,message").call();
-            if let Ok(resp) = try1 {
-                if let Ok(data) = resp.into_json::<IpApiResp>() {
-                    if data.status.as_deref() == Some("success") {
-                        if let Ok(mut g) = pub_net.lock() {
-                            g.ip = data.query;
-                            g.isp = data.isp;
-                            g.last_updated_ms = Some(chrono::Local::now().timestamp_millis());
-                            g.last_error = None;
-                        }
-                        ok = true;
-                    } else if let Ok(mut g) = pub_net.lock() {
-                        g.last_error = data.message.or(Some("ip-api.com failed".to_string()));
-                    }
-                }
+fn family_name(family: FamilyPref) -> &'static str {
+    if family == FamilyPref::V6 { "IPv6" } else { "IPv4" }
+}
+
+/// 构造 STUN Binding Request
+pub fn stun_request(txid: &[u8; 12]) -> [u8; 20] {
+    let mut req = [0u8; 20];
+    req[0..2].copy_from_slice(&0x0001u16.to_be_bytes());
+    req[4..8].copy_from_slice(&STUN_MAGIC_COOKIE.to_be_bytes());
+    req[8..20].copy_from_slice(txid);
+    req
+}
+
+/// 解析 STUN Binding 响应：优先 XOR-MAPPED-ADDRESS，回退 MAPPED-ADDRESS
+pub fn parse_stun_response(buf: &[u8], txid: &[u8; 12]) -> Result<IpAddr, String> {
+    if buf.len() < 20 || buf[4..8] != STUN_MAGIC_COOKIE.to_be_bytes() || buf[8..20] != txid[..] {
+        return Err("不是本次请求的 STUN 响应".to_string());
+    }
+    let msg_type = u16::from_be_bytes([buf[0], buf[1]]);
+    if msg_type == 0x0111 {
+        return Err("STUN 返回错误响应".to_string());
+    }
+    if msg_type != 0x0101 {
+        return Err(format!("未知的 STUN 消息类型 0x{:04x}", msg_type));
+    }
+    let end = (20 + u16::from_be_bytes([buf[2], buf[3]]) as usize).min(buf.len());
+    let mut mapped: Option<IpAddr> = None;
+    let mut pos = 20;
+    while pos + 4 <= end {
+        let attr = u16::from_be_bytes([buf[pos], buf[pos + 1]]);
+        let len = u16::from_be_bytes([buf[pos + 2], buf[pos + 3]]) as usize;
+        let Some(val) = buf.get(pos + 4..pos + 4 + len) else { break };
+        match attr {
+            // XOR-MAPPED-ADDRESS（0x8020 为 RFC 3489 时代部分服务器使用的旧值）
+            0x0020 | 0x8020 => {
+                if let Some(ip) = stun_address(val, Some(txid)) { return Ok(ip); }
             }
+            0x0001 => mapped = mapped.or(stun_address(val, None)),
+            _ => {}
+        }
+        // 属性按 4 字节对齐
+        pos += 4 + len.div_ceil(4) * 4;
+    }
+    mapped.ok_or_else(|| "STUN 响应中没有映射地址".to_string())
+}
+
+// 地址属性：保留字节、地址族（1=IPv4，2=IPv6）、端口、地址；xor 时按魔数（与事务 ID）异或
+fn stun_address(val: &[u8], xor_txid: Option<&[u8; 12]>) -> Option<IpAddr> {
+    let mut key = [0u8; 16];
+    if let Some(txid) = xor_txid {
+        key[0..4].copy_from_slice(&STUN_MAGIC_COOKIE.to_be_bytes());
+        key[4..16].copy_from_slice(txid);
+    }
+    match *val.get(1)? {
+        0x01 => {
+            let raw: [u8; 4] = val.get(4..8)?.try_into().ok()?;
+            Some(IpAddr::from(std::array::from_fn::<u8, 4, _>(|i| raw[i] ^ key[i])))
+        }
+        0x02 => {
+            let raw: [u8; 16] = val.get(4..20)?.try_into().ok()?;
+            Some(IpAddr::from(std::array::from_fn::<u8, 16, _>(|i| raw[i] ^ key[i])))
+        }
+        _ => None,
+    }
+}
 
-            // 2) fallback ipinfo.io
-            if !ok {
-                let try2 = agent.get("https://ipinfo.io/json").call();
-                if let Ok(resp) = try2 {
-                    if let Ok(data) = resp.into_json::<IpInfoResp>() {
-                        if let Ok(mut g) = pub_net.lock() {
-                            g.ip = data.ip;
-                            g.isp = data.org; // org 常含 ASN+ISP 名称
-                            g.last_updated_ms = Some(chrono::Local::now().timestamp_millis());
-                            g.last_error = None;
-                        }
-                        ok = true;
+/// STUN 提供者：向服务器发送 Binding Request（超时前重发一次）
+fn stun_lookup(p: &PublicNetProvider, family: FamilyPref, timeout_ms: u64) -> Result<PublicIpResult, String> {
+    let u = p.url.trim();
+    let rest = u.get(5..).unwrap_or_default().trim_start_matches('/');
+    let (host, port) = split_host_port(rest);
+    let ip = *resolve_target(&host, family).first().ok_or_else(|| format!("{} 没有 {} 地址", host, family_name(family)))?;
+    let server = SocketAddr::new(ip, port.unwrap_or(STUN_DEFAULT_PORT));
+    let bind = if ip.is_ipv4() { "0.0.0.0:0" } else { "[::]:0" };
+    let sock = UdpSocket::bind(bind).map_err(|e| format!("绑定 UDP 套接字失败: {}", e))?;
+    sock.connect(server).map_err(|e| format!("连接 {} 失败: {}", server, e))?;
+
+    let mut txid = [0u8; 12];
+    getrandom::getrandom(&mut txid).map_err(|e| format!("生成事务 ID 失败: {}", e))?;
+    let req = stun_request(&txid);
+    let timeout = Duration::from_millis(timeout_ms.max(2));
+    let deadline = Instant::now() + timeout;
+    let mut buf = [0u8; 512];
+    let mut last_err = "STUN 请求超时".to_string();
+    for attempt in 0..2 {
+        sock.send(&req).map_err(|e| format!("发送 STUN 请求失败: {}", e))?;
+        let attempt_deadline = if attempt == 0 { Instant::now() + timeout / 2 } else { deadline };
+        while let Some(left) = attempt_deadline.checked_duration_since(Instant::now()).filter(|d| !d.is_zero()) {
+            let _ = sock.set_read_timeout(Some(left));
+            match sock.recv(&mut buf) {
+                Ok(n) => match parse_stun_response(&buf[..n], &txid) {
+                    Ok(addr) => {
+                        check_family(&addr, family)?;
+                        return Ok(PublicIpResult { ip: addr.to_string(), provider: p.label(), ..Default::default() });
                     }
-                }
+                    Err(e) => last_err = e,
+                },
+                Err(e) if matches!(e.kind(), std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut) => break,
+                Err(e) => return Err(format!("接收 STUN 响应失败: {}", e)),
             }
+        }
+    }
+    Err(last_err)
+}
 
-            // 休眠：成功 30 分钟；失败 60 秒
-            std::thread::sleep(if ok { Duration::from_secs(1800) } else { Duration::from_secs(60) });
+/// 查询单个地址族：按顺序尝试提供者，返回首个成功结果；全部失败时汇总各提供者的错误
+pub fn lookup(providers: &[PublicNetProvider], family: FamilyPref, timeout_ms: u64) -> Result<PublicIpResult, String> {
+    let mut errors: Vec<String> = Vec::new();
+    for p in providers.iter().filter(|p| p.supports(family)) {
+        let r = if p.is_stun() { stun_lookup(p, family, timeout_ms) } else { http_lookup(p, family, timeout_ms) };
+        match r {
+            Ok(v) => return Ok(v),
+            Err(e) => errors.push(format!("{}: {}", p.label(), e)),
         }
+    }
+    if errors.is_empty() {
+        return Err(format!("没有支持 {} 的提供者", family_name(family)));
+    }
+    Err(errors.join("；"))
+}
+
+/// 一轮查询：IPv4 与（启用时）IPv6 并发进行
+pub fn lookup_all(providers: &[PublicNetProvider], ipv6: bool, timeout_ms: u64) -> PublicNetLookup {
+    let (v4, v6) = std::thread::scope(|s| {
+        let h6 = ipv6.then(|| s.spawn(|| lookup(providers, FamilyPref::V6, timeout_ms)));
+        let v4 = lookup(providers, FamilyPref::V4, timeout_ms);
+        (v4, h6.map(|h| h.join().unwrap_or_else(|_| Err("查询线程异常".to_string()))))
     });
+    let mut out = PublicNetLookup::default();
+    match v4 {
+        Ok(r) => out.v4 = Some(r),
+        Err(e) => out.v4_error = Some(e),
+    }
+    match v6 {
+        Some(Ok(r)) => out.v6 = Some(r),
+        Some(Err(e)) => out.v6_error = Some(e),
+        None => {}
+    }
+    out
+}
+
+/// 将一轮查询结果合并到共享的公网信息（失败的地址族保留上次的值）
+pub fn apply_lookup(info: &mut crate::config_utils::PublicNetInfo, l: &PublicNetLookup) {
+    if let Some(r) = l.v4.as_ref() { info.ipv4 = Some(r.ip.clone()); }
+    if let Some(r) = l.v6.as_ref() { info.ipv6 = Some(r.ip.clone()); }
+    // ISP / ASN / 国家优先取 IPv4 结果中的字段
+    let pick = |f: fn(&PublicIpResult) -> Option<String>| l.v4.as_ref().and_then(f).or_else(|| l.v6.as_ref().and_then(f));
+    if let Some(isp) = pick(|r| r.isp.clone()) { info.isp = Some(isp); }
+    if let Some(asn) = pick(|r| r.asn.clone()) { info.asn = Some(asn); }
+    if let Some(country) = pick(|r| r.country.clone()) { info.country = Some(country); }
+    if let Some(r) = l.v4.as_ref().or(l.v6.as_ref()) { info.provider = Some(r.provider.clone()); }
+    info.ip = info.ipv4.clone().or_else(|| info.ipv6.clone());
+    if l.any_ok() { info.last_updated_ms = Some(l.timestamp_ms); }
+    let errors: Vec<String> = [("IPv4", &l.v4_error), ("IPv6", &l.v6_error)]
+        .iter()
+        .filter_map(|(k, e)| e.as_ref().map(|e| format!("{}: {}", k, e)))
+        .collect();
+    info.last_error = (!errors.is_empty()).then(|| errors.join("\n"));
+}
+
+/// 公网地址变化（首次记录时 old_ip 为空）
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PublicIpChange {
+    pub timestamp_ms: i64,
+    // "v4" / "v6"
+    pub family: String,
+    pub old_ip: Option<String>,
+    pub new_ip: String,
+    pub isp: Option<String>,
+    pub asn: Option<String>,
+    pub country: Option<String>,
+    pub provider: String,
+}
+
+#[derive(Default)]
+struct History {
+    file: Option<JsonlJournal>,
+    entries: Vec<PublicIpChange>,
+    // 各地址族最近一次记录的地址（比较基准；不随 entries 按条数裁剪，避免一个地址族被挤出后重复记录）
+    last_ip: HashMap<String, String>,
+}
+
+static HISTORY: OnceLock<Mutex<History>> = OnceLock::new();
+
+fn with_history<R, F: FnOnce(&mut History) -> R>(f: F) -> Option<R> {
+    let cell = HISTORY.get_or_init(|| Mutex::new(History::default()));
+    cell.lock().ok().map(|mut g| f(&mut g))
+}
+
+/// 打开历史目录（未调用时变化只在内存中维护）；载入已有记录作为比较基准，重启后不重复记录
+pub fn open_history(dir: &Path, max_entries: Option<usize>) {
+    let file = JsonlJournal::open(dir.join(HISTORY_FILE), max_entries.unwrap_or(DEFAULT_HISTORY_MAX_ENTRIES));
+    let entries: Vec<PublicIpChange> = file.read();
+    with_history(|h| {
+        h.file = Some(file);
+        h.last_ip = entries.iter().map(|e| (e.family.clone(), e.new_ip.clone())).collect();
+        h.entries = entries;
+    });
+}
+
+fn append(h: &mut History, change: &PublicIpChange, max_entries: usize) {
+    h.last_ip.insert(change.family.clone(), change.new_ip.clone());
+    h.entries.push(change.clone());
+    let drop = h.entries.len().saturating_sub(max_entries);
+    h.entries.drain(..drop);
+    let Some(file) = h.file.as_mut() else { return };
+    if let Err(e) = file.append(change, max_entries) {
+        eprintln!("[public_net] 写入公网 IP 历史失败: {}", e);
+    }
+}
+
+/// 记录一轮查询结果：与各地址族最近一次记录比较，地址变化时追加并返回变化列表
+pub fn record_lookup(l: &PublicNetLookup, max_entries: Option<usize>) -> Vec<PublicIpChange> {
+    let max = max_entries.unwrap_or(DEFAULT_HISTORY_MAX_ENTRIES).max(1);
+    with_history(|h| {
+        let mut changes = Vec::new();
+        for r in [l.v4.as_ref(), l.v6.as_ref()].into_iter().flatten() {
+            let Ok(ip) = r.ip.parse::<IpAddr>() else { continue };
+            let family = family_label(&ip);
+            let old_ip = h.last_ip.get(family).cloned();
+            if old_ip.as_deref() == Some(r.ip.as_str()) { continue; }
+            let change = PublicIpChange {
+                timestamp_ms: l.timestamp_ms,
+                family: family.to_string(),
+                old_ip,
+                new_ip: r.ip.clone(),
+                isp: r.isp.clone(),
+                asn: r.asn.clone(),
+                country: r.country.clone(),
+                provider: r.provider.clone(),
+            };
+            append(h, &change, max);
+            changes.push(change);
+        }
+        changes
+    })
+    .unwrap_or_default()
+}
+
+/// 查询变化历史（新记录在前）
+pub fn history(limit: usize) -> Vec<PublicIpChange> {
+    with_history(|h| h.entries.iter().rev().take(limit).cloned().collect()).unwrap_or_default()
+}
+
+/// Tauri命令：公网 IP 变化历史（新记录在前，默认 100 条）
+#[cfg_attr(feature = "desktop", tauri::command)]
+pub fn public_ip_history(limit: Option<usize>) -> Vec<PublicIpChange> {
+    history(limit.unwrap_or(100))
 }
@@ -3,16 +3,30 @@
 // - 依赖 BaseGate 做并发防重入
 // - trigger() 异步执行测量，完成后更新内部快照
 // - 提供 snapshot_json() 供外部拉取或事件上报
+// - 配置了连通性检测地址时与 RTT 测量并发执行一次强制门户检测，结果放在快照 captive 字段
 
 use std::sync::{Arc, Mutex};
 use crate::runner::{Runner, BaseGate};
-use crate::ping_utils::measure_multi_rtt;
+use crate::ping_utils::{measure_multi_rtt, ProbeDefaults, RttTarget};
+
+// 强制门户检测的最短超时（RTT 超时默认仅 300ms，不足以完成一次 HTTP 请求）
+const CAPTIVE_MIN_TIMEOUT_MS: u64 = 2000;
+
+/// 单轮 RTT 采集配置
+#[derive(Clone, Debug)]
+pub struct RttRunConfig {
+    pub targets: Vec<RttTarget>,
+    // 全局默认超时/包数/间隔/地址族（目标可单独覆盖）
+    pub defaults: ProbeDefaults,
+    // 强制门户检测地址（应返回 HTTP 204；None 表示不检测）
+    pub captive_url: Option<String>,
+}
 
 #[derive(Clone)]
 pub struct RttRunner {
     gate: Arc<BaseGate>,
-    // 配置提供器：返回 (targets, timeout_ms)
-    cfg_provider: Arc<dyn Fn() -> (Vec<String>, u64) + Send + Sync>,
+    // 配置提供器：每轮触发时读取
+    cfg_provider: Arc<dyn Fn() -> RttRunConfig + Send + Sync>,
     // 最近一次结果快照
     last_snapshot: Arc<Mutex<serde_json::Value>>,
 }
@@ -20,7 +34,7 @@ pub struct RttRunner {
 impl RttRunner {
     pub fn new<F>(cfg_provider: F) -> Self
     where
-        F: Fn() -> (Vec<String>, u64) + Send + Sync + 'static,
+        F: Fn() -> RttRunConfig + Send + Sync + 'static,
     {
         Self {
             gate: Arc::new(BaseGate::new()),
@@ -41,23 +55,36 @@ impl Runner for RttRunner {
         let snap_c = self.last_snapshot.clone();
         let cfg_c = self.cfg_provider.clone();
         std::thread::spawn(move || {
-            let (targets, timeout_ms) = (cfg_c)();
-            let results = measure_multi_rtt(&targets, timeout_ms);
-            // 简单聚合：min/avg
+            let cfg = (cfg_c)();
+            let (results, captive) = std::thread::scope(|s| {
+                let captive = cfg.captive_url.as_deref().map(|url| {
+                    let timeout_ms = cfg.defaults.timeout_ms.max(CAPTIVE_MIN_TIMEOUT_MS);
+                    s.spawn(move || crate::http_timing::probe(url, None, timeout_ms))
+                });
+                let results = measure_multi_rtt(&cfg.targets, &cfg.defaults);
+                (results, captive.and_then(|h| h.join().ok()))
+            });
+            // 简单聚合：min/avg（各目标均值），丢包率/抖动取各目标平均
             let lats: Vec<f64> = results.iter().filter_map(|r| r.rtt_ms).collect();
-            let min_ms = lats.iter().cloned().fold(f64::INFINITY, f64::min);
-            let avg_ms = if lats.is_empty() { 0.0 } else { lats.iter().sum::<f64>() / lats.len() as f64 };
-            let min_opt = if lats.is_empty() { None } else { Some(min_ms) };
-            let avg_opt = if lats.is_empty() { None } else { Some(avg_ms) };
+            let min_opt = results.iter().filter_map(|r| r.min_ms).reduce(f64::min);
+            let avg_opt = if lats.is_empty() { None } else { Some(lats.iter().sum::<f64>() / lats.len() as f64) };
+            let mean = |v: Vec<f64>| if v.is_empty() { None } else { Some(v.iter().sum::<f64>() / v.len() as f64) };
+            let loss_opt = mean(results.iter().filter_map(|r| r.loss_pct).collect());
+            let jitter_opt = mean(results.iter().filter_map(|r| r.jitter_ms).collect());
 
             let payload = serde_json::json!({
                 "timestamp_ms": now_ms,
-                "targets": targets,
-                "timeout_ms": timeout_ms,
+                "targets": cfg.targets,
+                "timeout_ms": cfg.defaults.timeout_ms,
+                "probe_count": cfg.defaults.count,
+                "probe_interval_ms": cfg.defaults.interval_ms,
                 "results": results,
+                "captive": captive,
                 "summary": {
                     "min_ms": min_opt,
                     "avg_ms": avg_opt,
+                    "loss_pct": loss_opt,
+                    "jitter_ms": jitter_opt,
                 }
             });
             if let Ok(mut g) = snap_c.lock() { *g = payload; }
@@ -74,6 +74,30 @@ pub struct SchedulerState {
     pub smart_is_running: bool,
     pub smart_last_ok_ms: Option<i64>,
     pub smart_age_ms: Option<i64>,
+    pub dns_every: u64,
+    pub dns_last: Option<u64>,
+    pub dns_enabled: bool,
+    pub dns_is_running: bool,
+    pub dns_last_ok_ms: Option<i64>,
+    pub dns_age_ms: Option<i64>,
+    pub trace_every: u64,
+    pub trace_last: Option<u64>,
+    pub trace_enabled: bool,
+    pub trace_is_running: bool,
+    pub trace_last_ok_ms: Option<i64>,
+    pub trace_age_ms: Option<i64>,
+    pub public_net_every: u64,
+    pub public_net_last: Option<u64>,
+    pub public_net_enabled: bool,
+    pub public_net_is_running: bool,
+    pub public_net_last_ok_ms: Option<i64>,
+    pub public_net_age_ms: Option<i64>,
+    pub speedtest_every: u64,
+    pub speedtest_last: Option<u64>,
+    pub speedtest_enabled: bool,
+    pub speedtest_is_running: bool,
+    pub speedtest_last_ok_ms: Option<i64>,
+    pub speedtest_age_ms: Option<i64>,
 }
 
 impl Default for SchedulerState {
@@ -106,12 +130,36 @@ impl Default for SchedulerState {
             smart_is_running: false,
             smart_last_ok_ms: None,
             smart_age_ms: None,
+            dns_every: 10,
+            dns_last: None,
+            dns_enabled: true,
+            dns_is_running: false,
+            dns_last_ok_ms: None,
+            dns_age_ms: None,
+            trace_every: 60,
+            trace_last: None,
+            trace_enabled: true,
+            trace_is_running: false,
+            trace_last_ok_ms: None,
+            trace_age_ms: None,
+            public_net_every: 1800,
+            public_net_last: None,
+            public_net_enabled: true,
+            public_net_is_running: false,
+            public_net_last_ok_ms: None,
+            public_net_age_ms: None,
+            speedtest_every: 3600,
+            speedtest_last: None,
+            speedtest_enabled: true,
+            speedtest_is_running: false,
+            speedtest_last_ok_ms: None,
+            speedtest_age_ms: None,
         }
     }
 }
 
 #[derive(Debug, Clone, Copy)]
-pub enum TaskKind { Rtt, NetIf, LDisk, Smart }
+pub enum TaskKind { Rtt, NetIf, LDisk, Smart, Dns, Trace, PublicNet, Speedtest }
 
 #[derive(Debug, Clone)]
 struct TaskEntry {
@@ -129,6 +177,10 @@ pub struct TaskTable {
     netif: TaskEntry,
     ldisk: TaskEntry,
     smart: TaskEntry,
+    dns: TaskEntry,
+    trace: TaskEntry,
+    public_net: TaskEntry,
+    speedtest: TaskEntry,
 }
 
 impl Default for TaskTable {
@@ -138,6 +190,10 @@ impl Default for TaskTable {
             netif: TaskEntry { gate: PacedGate::new(5), enabled: true, trigger_once: false, is_running: false, last_ok_ms: None },
             ldisk: TaskEntry { gate: PacedGate::new(5), enabled: true, trigger_once: false, is_running: false, last_ok_ms: None },
             smart: TaskEntry { gate: PacedGate::new(10), enabled: true, trigger_once: false, is_running: false, last_ok_ms: None },
+            dns: TaskEntry { gate: PacedGate::new(10), enabled: true, trigger_once: false, is_running: false, last_ok_ms: None },
+            trace: TaskEntry { gate: PacedGate::new(60), enabled: true, trigger_once: false, is_running: false, last_ok_ms: None },
+            public_net: TaskEntry { gate: PacedGate::new(1800), enabled: true, trigger_once: false, is_running: false, last_ok_ms: None },
+            speedtest: TaskEntry { gate: PacedGate::new(3600), enabled: true, trigger_once: false, is_running: false, last_ok_ms: None },
         }
     }
 }
@@ -149,6 +205,10 @@ impl TaskTable {
             TaskKind::NetIf => self.netif.gate.set_every(every),
             TaskKind::LDisk => self.ldisk.gate.set_every(every),
             TaskKind::Smart => self.smart.gate.set_every(every),
+            TaskKind::Dns => self.dns.gate.set_every(every),
+            TaskKind::Trace => self.trace.gate.set_every(every),
+            TaskKind::PublicNet => self.public_net.gate.set_every(every),
+            TaskKind::Speedtest => self.speedtest.gate.set_every(every),
         }
     }
 
@@ -159,6 +219,10 @@ impl TaskTable {
             TaskKind::NetIf => self.netif.enabled = enabled,
             TaskKind::LDisk => self.ldisk.enabled = enabled,
             TaskKind::Smart => self.smart.enabled = enabled,
+            TaskKind::Dns => self.dns.enabled = enabled,
+            TaskKind::Trace => self.trace.enabled = enabled,
+            TaskKind::PublicNet => self.public_net.enabled = enabled,
+            TaskKind::Speedtest => self.speedtest.enabled = enabled,
         }
     }
 
@@ -169,6 +233,10 @@ impl TaskTable {
             TaskKind::NetIf => self.netif.trigger_once = true,
             TaskKind::LDisk => self.ldisk.trigger_once = true,
             TaskKind::Smart => self.smart.trigger_once = true,
+            TaskKind::Dns => self.dns.trigger_once = true,
+            TaskKind::Trace => self.trace.trigger_once = true,
+            TaskKind::PublicNet => self.public_net.trigger_once = true,
+            TaskKind::Speedtest => self.speedtest.trigger_once = true,
         }
     }
 
@@ -178,6 +246,10 @@ impl TaskTable {
             TaskKind::NetIf => &mut self.netif,
             TaskKind::LDisk => &mut self.ldisk,
             TaskKind::Smart => &mut self.smart,
+            TaskKind::Dns => &mut self.dns,
+            TaskKind::Trace => &mut self.trace,
+            TaskKind::PublicNet => &mut self.public_net,
+            TaskKind::Speedtest => &mut self.speedtest,
         };
         if !entry.enabled { return false; }
         if entry.trigger_once {
@@ -189,6 +261,22 @@ impl TaskTable {
         entry.gate.check(tick)
     }
 
+    // 仅消费一次性触发标记（不检查节拍）：用于默认不定时执行的按需任务
+    pub fn take_trigger_once(&mut self, kind: TaskKind) -> bool {
+        let entry = match kind {
+            TaskKind::Rtt => &mut self.rtt,
+            TaskKind::NetIf => &mut self.netif,
+            TaskKind::LDisk => &mut self.ldisk,
+            TaskKind::Smart => &mut self.smart,
+            TaskKind::Dns => &mut self.dns,
+            TaskKind::Trace => &mut self.trace,
+            TaskKind::PublicNet => &mut self.public_net,
+            TaskKind::Speedtest => &mut self.speedtest,
+        };
+        if !entry.enabled { return false; }
+        std::mem::take(&mut entry.trigger_once)
+    }
+
     pub fn fill_state(&self, st: &mut SchedulerState, tick: u64, now_ms: i64) {
         st.tick = tick;
         // RTT
@@ -219,6 +307,34 @@ impl TaskTable {
         st.smart_is_running = self.smart.is_running;
         st.smart_last_ok_ms = self.smart.last_ok_ms;
         st.smart_age_ms = self.smart.last_ok_ms.map(|t| now_ms.saturating_sub(t));
+        // Dns
+        st.dns_every = self.dns.gate.every();
+        st.dns_last = self.dns.gate.last_tick();
+        st.dns_enabled = self.dns.enabled;
+        st.dns_is_running = self.dns.is_running;
+        st.dns_last_ok_ms = self.dns.last_ok_ms;
+        st.dns_age_ms = self.dns.last_ok_ms.map(|t| now_ms.saturating_sub(t));
+        // Trace
+        st.trace_every = self.trace.gate.every();
+        st.trace_last = self.trace.gate.last_tick();
+        st.trace_enabled = self.trace.enabled;
+        st.trace_is_running = self.trace.is_running;
+        st.trace_last_ok_ms = self.trace.last_ok_ms;
+        st.trace_age_ms = self.trace.last_ok_ms.map(|t| now_ms.saturating_sub(t));
+        // PublicNet
+        st.public_net_every = self.public_net.gate.every();
+        st.public_net_last = self.public_net.gate.last_tick();
+        st.public_net_enabled = self.public_net.enabled;
+        st.public_net_is_running = self.public_net.is_running;
+        st.public_net_last_ok_ms = self.public_net.last_ok_ms;
+        st.public_net_age_ms = self.public_net.last_ok_ms.map(|t| now_ms.saturating_sub(t));
+        // Speedtest
+        st.speedtest_every = self.speedtest.gate.every();
+        st.speedtest_last = self.speedtest.gate.last_tick();
+        st.speedtest_enabled = self.speedtest.enabled;
+        st.speedtest_is_running = self.speedtest.is_running;
+        st.speedtest_last_ok_ms = self.speedtest.last_ok_ms;
+        st.speedtest_age_ms = self.speedtest.last_ok_ms.map(|t| now_ms.saturating_sub(t));
     }
 
     // Runner标记：开始、成功、结束
@@ -228,6 +344,10 @@ impl TaskTable {
             TaskKind::NetIf => &mut self.netif,
             TaskKind::LDisk => &mut self.ldisk,
             TaskKind::Smart => &mut self.smart,
+            TaskKind::Dns => &mut self.dns,
+            TaskKind::Trace => &mut self.trace,
+            TaskKind::PublicNet => &mut self.public_net,
+            TaskKind::Speedtest => &mut self.speedtest,
         };
         entry.is_running = true;
     }
@@ -238,6 +358,10 @@ impl TaskTable {
             TaskKind::NetIf => &mut self.netif,
             TaskKind::LDisk => &mut self.ldisk,
             TaskKind::Smart => &mut self.smart,
+            TaskKind::Dns => &mut self.dns,
+            TaskKind::Trace => &mut self.trace,
+            TaskKind::PublicNet => &mut self.public_net,
+            TaskKind::Speedtest => &mut self.speedtest,
         };
         entry.last_ok_ms = Some(now_ms);
     }
@@ -248,6 +372,10 @@ impl TaskTable {
             TaskKind::NetIf => &mut self.netif,
             TaskKind::LDisk => &mut self.ldisk,
             TaskKind::Smart => &mut self.smart,
+            TaskKind::Dns => &mut self.dns,
+            TaskKind::Trace => &mut self.trace,
+            TaskKind::PublicNet => &mut self.public_net,
+            TaskKind::Speedtest => &mut self.speedtest,
         };
         entry.is_running = false;
     }
@@ -261,6 +389,10 @@ impl TaskTable {
             TaskKind::NetIf => &mut self.netif,
             TaskKind::LDisk => &mut self.ldisk,
             TaskKind::Smart => &mut self.smart,
+            TaskKind::Dns => &mut self.dns,
+            TaskKind::Trace => &mut self.trace,
+            TaskKind::PublicNet => &mut self.public_net,
+            TaskKind::Speedtest => &mut self.speedtest,
         };
         entry.is_running = is_running;
         if let Some(t) = last_ok_ms {
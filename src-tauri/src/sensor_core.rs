@@ -0,0 +1,177 @@
+// 采集核心装配
+// 说明：
+// - 共享状态 + 桥接管理 + 公网 IP / 测速历史 + 推送 Sink + 本地 API + SMART Worker + 采样线程
+// - GUI（lib.rs 的 run）与无界面 agent（sys-sensor-agent）都经 start_sensor_core 启动
+// - 两者的差异仅在展示层：GUI 传入托盘/事件钩子，agent 全部留空（核心不依赖 tauri）
+
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{channel, Sender};
+use std::sync::{Arc, Mutex};
+use std::time::Instant as StdInstant;
+
+use crate::api_server::{ApiContext, ConfigListener};
+use crate::collector::{start_collector, CollectorContext, CollectorHooks};
+use crate::config_utils::{AppConfig, PublicNetInfo};
+use crate::scheduler::SchedulerState;
+use crate::smart_worker::{SmartListener, SmartWorker};
+use crate::state_store::StateStore;
+use crate::types::BridgeOut;
+use crate::{parse_task_kind, ControlMsg};
+
+/// 启动参数
+pub struct CoreOptions {
+    pub config: AppConfig,
+    // 配置目录（config.json、api_tokens.json、spool 等）
+    pub config_dir: PathBuf,
+    // 是否启动 sensor-bridge（非 Windows 的 agent 可关闭）
+    pub bridge: bool,
+    // 打包资源中的桥接 EXE；为空时由 bridge_manager 按项目目录/便携目录兜底查找
+    pub packaged_bridge_exe: Option<PathBuf>,
+    // GUI 模式下 SMART Worker 额外向 webview 广播
+    pub on_smart: Option<SmartListener>,
+    pub hooks: CollectorHooks,
+    pub on_config_changed: Option<ConfigListener>,
+}
+
+/// 运行中的采集核心（各字段为共享句柄，可随意克隆）
+#[derive(Clone)]
+pub struct SensorCore {
+    pub config: Arc<Mutex<AppConfig>>,
+    pub public_net: Arc<Mutex<PublicNetInfo>>,
+    pub scheduler: Arc<Mutex<SchedulerState>>,
+    pub state_store: Arc<Mutex<StateStore>>,
+    pub smart: Arc<Mutex<Option<SmartWorker>>>,
+    pub ctrl_tx: Sender<ControlMsg>,
+    shutdown: Arc<AtomicBool>,
+    bridge_pid: Arc<Mutex<Option<u32>>>,
+    collector: Arc<Mutex<Option<std::thread::JoinHandle<()>>>>,
+}
+
+pub fn start_sensor_core(opts: CoreOptions) -> SensorCore {
+    let CoreOptions { config, config_dir, bridge, packaged_bridge_exe, on_smart, hooks, on_config_changed } = opts;
+
+    let cfg_arc: Arc<Mutex<AppConfig>> = Arc::new(Mutex::new(config));
+    let pub_net_arc: Arc<Mutex<PublicNetInfo>> = Arc::new(Mutex::new(PublicNetInfo::default()));
+    let sched_state_arc: Arc<Mutex<SchedulerState>> = Arc::new(Mutex::new(SchedulerState::default()));
+    let state_store_arc: Arc<Mutex<StateStore>> = Arc::new(Mutex::new(StateStore::new()));
+
+    // SMART 后台 Worker（按配置 smart_enabled）
+    let smart_enabled = cfg_arc.lock().ok().and_then(|c| c.smart_enabled).unwrap_or(true);
+    let smart: Arc<Mutex<Option<SmartWorker>>> = Arc::new(Mutex::new(
+        if smart_enabled { Some(crate::smart_worker::start(on_smart)) } else { None },
+    ));
+
+    // 调度控制通道（方案A）
+    let (ctrl_tx, ctrl_rx) = channel::<ControlMsg>();
+
+    // 退出控制与子进程 PID 记录（用于退出时清理）
+    let shutdown: Arc<AtomicBool> = Arc::new(AtomicBool::new(false));
+    let bridge_pid: Arc<Mutex<Option<u32>>> = Arc::new(Mutex::new(None));
+
+    // --- sensor-bridge (.NET) 输出共享 ---
+    let bridge_data: Arc<Mutex<(Option<BridgeOut>, StdInstant)>> = Arc::new(Mutex::new((None, StdInstant::now())));
+    // 配置了回放文件或模拟器时即使不启动真实桥接（非 Windows）也经 bridge_manager 提供桥接数据
+    let stand_in = cfg_arc
+        .lock()
+        .ok()
+        .map(|c| {
+            c.bridge_replay.as_ref().is_some_and(|r| r.source().is_some())
+                || c.bridge_simulator.as_ref().is_some_and(|s| s.is_enabled())
+        })
+        .unwrap_or(false);
+    if bridge || stand_in {
+        crate::bridge_manager::start_bridge_manager(bridge_data.clone(), packaged_bridge_exe, shutdown.clone(), bridge_pid.clone(), cfg_arc.clone());
+    }
+
+    // --- InfluxDB 推送 Sink（配置 influx.enabled 控制，spool 落在配置目录）---
+    let influx = crate::influx_sink::start_influx_sink(cfg_arc.clone(), config_dir.clone());
+
+    // --- MQTT 发布 Sink（配置 mqtt.enabled 控制）---
+    let mqtt = crate::mqtt_sink::start_mqtt_sink(cfg_arc.clone());
+
+    // --- 告警日志（alerts.jsonl，恢复未到期的静默）---
+    let journal_max = cfg_arc.lock().ok().and_then(|c| c.alerts.as_ref().and_then(|a| a.journal_max_entries));
+    crate::alert_journal::open(&config_dir, journal_max);
+    let outage_max = cfg_arc.lock().ok().and_then(|c| c.connectivity_journal_max_entries);
+    crate::connectivity::open(&config_dir, outage_max);
+    // --- 公网 IP 变化历史（public_ip_history.jsonl；查询由采样循环按调度节拍触发）---
+    let public_ip_max = cfg_arc.lock().ok().and_then(|c| c.public_net_history_max_entries);
+    crate::public_net_utils::open_history(&config_dir, public_ip_max);
+    // --- 测速历史（speedtest_history.jsonl；测速由采样循环手动/定时触发）---
+    let speedtest_max = cfg_arc.lock().ok().and_then(|c| c.speedtest.as_ref().and_then(|s| s.history_max_entries));
+    crate::speedtest::open_history(&config_dir, speedtest_max);
+
+    // --- 告警通知分发（alerts.sinks 控制，热更新）---
+    let notifier = crate::alert_notify::start_notifier(cfg_arc.clone());
+
+    // --- 本地 HTTP/WebSocket API（配置 api.enabled 控制，热更新）---
+    let api_trigger_tx = Mutex::new(ctrl_tx.clone());
+    crate::api_server::start_api_server(ApiContext {
+        config: cfg_arc.clone(),
+        scheduler: sched_state_arc.clone(),
+        state_store: state_store_arc.clone(),
+        config_dir,
+        trigger_task: Some(Arc::new(move |kind: &str| {
+            let k = parse_task_kind(kind).ok_or_else(|| format!("unknown task kind: {}", kind))?;
+            let tx = api_trigger_tx.lock().map_err(|_| "lock failed".to_string())?;
+            tx.send(ControlMsg::TriggerOnce(k)).map_err(|e| e.to_string())
+        })),
+        on_config_changed,
+    });
+
+    // --- 采样线程 ---
+    let collector = start_collector(
+        CollectorContext {
+            config: cfg_arc.clone(),
+            scheduler: sched_state_arc.clone(),
+            state_store: state_store_arc.clone(),
+            public_net: pub_net_arc.clone(),
+            bridge_data,
+            influx: Some(influx),
+            mqtt: Some(mqtt),
+            notifier: Some(notifier),
+            ctrl_rx,
+            shutdown: shutdown.clone(),
+        },
+        hooks,
+    );
+
+    SensorCore {
+        config: cfg_arc,
+        public_net: pub_net_arc,
+        scheduler: sched_state_arc,
+        state_store: state_store_arc,
+        smart,
+        ctrl_tx,
+        shutdown,
+        bridge_pid,
+        collector: Arc::new(Mutex::new(Some(collector))),
+    }
+}
+
+impl SensorCore {
+    /// 请求关停：停止 SMART Worker、通知后台线程退出并结束桥接子进程
+    pub fn shutdown(&self) {
+        if let Ok(mut g) = self.smart.lock() {
+            if let Some(w) = g.take() { w.shutdown(); }
+        }
+        self.shutdown.store(true, Ordering::Relaxed);
+        if let Ok(g) = self.bridge_pid.lock() {
+            if let Some(_pid) = *g {
+                #[cfg(windows)]
+                {
+                    let _ = std::process::Command::new("taskkill")
+                        .args(["/PID", &_pid.to_string(), "/T", "/F"])
+                        .output();
+                }
+            }
+        }
+    }
+
+    /// 等待采样线程退出（需先调用 shutdown）
+    pub fn join(&self) {
+        let handle = self.collector.lock().ok().and_then(|mut g| g.take());
+        if let Some(h) = handle { let _ = h.join(); }
+    }
+}
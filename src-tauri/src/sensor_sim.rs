@@ -0,0 +1,610 @@
+// 合成传感器模拟器
+// 说明：
+// - 按场景生成逼真的桥接数据帧与快照指标：负载波形/尖峰、随负载变化且有热惯性的温度、跟随温度曲线的风扇、
+//   周期性网络突发、电池放电/充电、逐步劣化的磁盘（温度升高、重映射/待映射扇区增长直至 predict_fail）
+// - 用作桥接替身：bridge_manager 在配置了 AppConfig.bridge_simulator 时以模拟器代替桥接进程，
+//   数据帧（含握手）走与真实桥接相同的处理流程；桥接之外的指标（CPU 占用、网速、电池、SMART）经 current_overlay() 由采样线程覆盖
+// - 场景为 JSON（scenario_path 文件或内联 scenario），所有字段均有默认值，空场景即可得到一台“普通的台式机”
+// - 时间以场景秒计（speed 倍速推进），同一 seed 的输出可复现，便于集成测试
+
+use std::f64::consts::PI;
+use std::sync::{Mutex, OnceLock};
+
+use serde::{Deserialize, Serialize};
+
+use crate::types::SmartHealthPayload;
+
+/// 模拟器配置（AppConfig.bridge_simulator）
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Default)]
+pub struct SimulatorConfig {
+    // 总开关（默认启用；设置后不再启动真实桥接，启动时生效）
+    pub enabled: Option<bool>,
+    // 场景文件（JSON）；与内联 scenario 同时存在时以文件为准
+    pub scenario_path: Option<String>,
+    pub scenario: Option<Scenario>,
+    // 倍速（默认 1.0，范围 0.01..10000）
+    pub speed: Option<f64>,
+    // 输出间隔（毫秒，默认 1000）
+    pub interval_ms: Option<u64>,
+}
+
+impl SimulatorConfig {
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.unwrap_or(true)
+    }
+
+    pub fn speed(&self) -> f64 {
+        self.speed.filter(|s| s.is_finite() && *s > 0.0).unwrap_or(1.0).clamp(0.01, 10_000.0)
+    }
+
+    /// 解析场景：文件 > 内联 > 默认
+    pub fn load_scenario(&self) -> Result<Scenario, String> {
+        match self.scenario_path.as_deref().map(|p| p.trim()).filter(|p| !p.is_empty()) {
+            Some(path) => {
+                let text = std::fs::read_to_string(path).map_err(|e| format!("读取场景文件失败 {}: {}", path, e))?;
+                serde_json::from_str(&text).map_err(|e| format!("场景文件格式错误 {}: {}", path, e))
+            }
+            None => Ok(self.scenario.clone().unwrap_or_default()),
+        }
+    }
+}
+
+/// 负载正弦分量
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Default)]
+#[serde(default)]
+pub struct LoadWave {
+    pub amplitude_pct: f64,
+    pub period_sec: f64,
+    pub phase_sec: f64,
+}
+
+/// 时间窗：[at_sec, at_sec + duration_sec)
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Default)]
+#[serde(default)]
+pub struct LoadSpike {
+    pub at_sec: f64,
+    pub duration_sec: f64,
+    pub pct: f64,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct LoadModel {
+    pub base_pct: f64,
+    pub waves: Vec<LoadWave>,
+    pub spikes: Vec<LoadSpike>,
+    // 均匀噪声幅度（±）
+    pub noise_pct: f64,
+    pub cores: usize,
+}
+
+impl Default for LoadModel {
+    fn default() -> Self {
+        Self {
+            base_pct: 18.0,
+            waves: vec![LoadWave { amplitude_pct: 12.0, period_sec: 300.0, phase_sec: 0.0 }],
+            spikes: Vec::new(),
+            noise_pct: 4.0,
+            cores: 8,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct ThermalModel {
+    pub ambient_c: f64,
+    // 满载时相对环境温度的稳态温升
+    pub rise_at_full_load_c: f64,
+    // 热时间常数（秒）
+    pub tau_sec: f64,
+    pub idle_power_w: f64,
+    pub tdp_w: f64,
+    pub base_mhz: f64,
+    pub boost_mhz: f64,
+    // 超过该温度进入温度墙：降频并上报 throttle
+    pub throttle_c: f64,
+}
+
+impl Default for ThermalModel {
+    fn default() -> Self {
+        Self {
+            ambient_c: 28.0,
+            rise_at_full_load_c: 52.0,
+            tau_sec: 25.0,
+            idle_power_w: 9.0,
+            tdp_w: 88.0,
+            base_mhz: 2900.0,
+            boost_mhz: 4600.0,
+            throttle_c: 95.0,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct FanModel {
+    // 温度 -> 转速曲线（[温度°C, RPM]，按温度升序，区间内线性插值）
+    pub curve: Vec<[f64; 2]>,
+    pub tau_sec: f64,
+    pub max_rpm: f64,
+    // 机箱风扇相对 CPU 风扇的转速比例（0 = 不输出）
+    pub case_fan_ratio: f64,
+}
+
+impl Default for FanModel {
+    fn default() -> Self {
+        Self {
+            curve: vec![[35.0, 700.0], [55.0, 1100.0], [70.0, 1700.0], [85.0, 2600.0], [95.0, 3000.0]],
+            tau_sec: 4.0,
+            max_rpm: 3000.0,
+            case_fan_ratio: 0.6,
+        }
+    }
+}
+
+/// 网络突发：每 every_sec 秒出现一次，持续 duration_sec 秒
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Default)]
+#[serde(default)]
+pub struct NetBurst {
+    pub every_sec: f64,
+    pub offset_sec: f64,
+    pub duration_sec: f64,
+    pub rx_bps: f64,
+    pub tx_bps: f64,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct NetworkModel {
+    pub rx_base_bps: f64,
+    pub tx_base_bps: f64,
+    // 相对噪声（0.2 = ±20%）
+    pub noise: f64,
+    pub bursts: Vec<NetBurst>,
+}
+
+impl Default for NetworkModel {
+    fn default() -> Self {
+        Self {
+            rx_base_bps: 60_000.0,
+            tx_base_bps: 15_000.0,
+            noise: 0.3,
+            bursts: vec![NetBurst { every_sec: 120.0, offset_sec: 30.0, duration_sec: 15.0, rx_bps: 8_000_000.0, tx_bps: 400_000.0 }],
+        }
+    }
+}
+
+/// 时间窗
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Default)]
+#[serde(default)]
+pub struct TimeWindow {
+    pub from_sec: f64,
+    pub to_sec: f64,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct BatteryModel {
+    pub start_pct: f64,
+    // 放电速率（%/小时）：空载与满载，之间按负载线性插值
+    pub drain_idle_pct_per_hour: f64,
+    pub drain_full_pct_per_hour: f64,
+    pub charge_pct_per_hour: f64,
+    // 接通电源的时间窗（为空时始终使用电池）
+    pub ac_windows: Vec<TimeWindow>,
+}
+
+impl Default for BatteryModel {
+    fn default() -> Self {
+        Self {
+            start_pct: 80.0,
+            drain_idle_pct_per_hour: 12.0,
+            drain_full_pct_per_hour: 45.0,
+            charge_pct_per_hour: 60.0,
+            ac_windows: Vec::new(),
+        }
+    }
+}
+
+/// 磁盘劣化：从 start_sec 开始按速率累积坏扇区并升温
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct DiskFailure {
+    pub start_sec: f64,
+    pub reallocated_per_hour: f64,
+    pub pending_per_hour: f64,
+    pub uncorrectable_per_hour: f64,
+    // 劣化开始后一小时内线性升温到的附加温度
+    pub temp_rise_c: f64,
+    // 重映射扇区达到该值后 predict_fail=true
+    pub predict_fail_at: i64,
+}
+
+impl Default for DiskFailure {
+    fn default() -> Self {
+        Self {
+            start_sec: 0.0,
+            reallocated_per_hour: 120.0,
+            pending_per_hour: 30.0,
+            uncorrectable_per_hour: 4.0,
+            temp_rise_c: 12.0,
+            predict_fail_at: 50,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct DiskModel {
+    pub name: String,
+    pub drive_letter: Option<String>,
+    pub base_temp_c: f64,
+    // 满载时附加温升
+    pub load_rise_c: f64,
+    pub power_on_hours: f64,
+    pub failing: Option<DiskFailure>,
+}
+
+impl Default for DiskModel {
+    fn default() -> Self {
+        Self { name: "SIM NVMe 1TB".into(), drive_letter: Some("C:".into()), base_temp_c: 36.0, load_rise_c: 10.0, power_on_hours: 4200.0, failing: None }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct GpuModel {
+    pub name: String,
+    // GPU 负载 = base_pct + follow × CPU 负载
+    pub base_pct: f64,
+    pub follow: f64,
+    pub idle_temp_c: f64,
+    pub rise_at_full_load_c: f64,
+    pub tau_sec: f64,
+    pub idle_power_w: f64,
+    pub max_power_w: f64,
+    pub vram_total_mb: f64,
+}
+
+impl Default for GpuModel {
+    fn default() -> Self {
+        Self {
+            name: "SIM GPU".into(),
+            base_pct: 3.0,
+            follow: 0.4,
+            idle_temp_c: 38.0,
+            rise_at_full_load_c: 40.0,
+            tau_sec: 20.0,
+            idle_power_w: 12.0,
+            max_power_w: 220.0,
+            vram_total_mb: 8192.0,
+        }
+    }
+}
+
+/// 模拟场景
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct Scenario {
+    pub name: Option<String>,
+    pub seed: u64,
+    pub load: LoadModel,
+    pub thermal: ThermalModel,
+    pub fan: FanModel,
+    pub network: NetworkModel,
+    // 为空时不输出电池指标（台式机）
+    pub battery: Option<BatteryModel>,
+    pub disks: Vec<DiskModel>,
+    pub gpus: Vec<GpuModel>,
+}
+
+impl Default for Scenario {
+    fn default() -> Self {
+        Self {
+            name: None,
+            seed: 1,
+            load: LoadModel::default(),
+            thermal: ThermalModel::default(),
+            fan: FanModel::default(),
+            network: NetworkModel::default(),
+            battery: None,
+            disks: vec![DiskModel::default()],
+            gpus: vec![GpuModel::default()],
+        }
+    }
+}
+
+/// 桥接之外的模拟指标（由采样线程覆盖到快照）
+#[derive(Clone, Debug, Serialize, Deserialize, Default)]
+pub struct SimOverlay {
+    pub cpu_usage: f32,
+    pub net_rx_bps: f64,
+    pub net_tx_bps: f64,
+    pub battery_percent: Option<i32>,
+    pub battery_status: Option<String>,
+    pub battery_ac_online: Option<bool>,
+    pub battery_time_remaining_sec: Option<i32>,
+    pub battery_time_to_full_sec: Option<i32>,
+    pub smart_health: Option<Vec<SmartHealthPayload>>,
+}
+
+/// 单步输出：桥接数据帧（与 sensor-bridge 输出同构的 JSON）与覆盖指标
+#[derive(Clone, Debug)]
+pub struct SimFrame {
+    pub bridge: serde_json::Value,
+    pub overlay: SimOverlay,
+}
+
+/// 可复现的伪随机数（splitmix64）
+#[derive(Clone, Debug)]
+struct SimRng(u64);
+
+impl SimRng {
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// [-1, 1) 均匀分布
+    fn symmetric(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64 * 2.0 - 1.0
+    }
+}
+
+/// 一阶惯性：按时间常数向目标逼近
+fn approach(current: f64, target: f64, dt: f64, tau: f64) -> f64 {
+    if tau <= 0.0 { return target; }
+    current + (target - current) * (1.0 - (-dt / tau).exp())
+}
+
+fn in_window(t: f64, from: f64, to: f64) -> bool {
+    t >= from && t < to
+}
+
+/// 分段线性插值（曲线按 x 升序；超出两端取端点值）
+pub fn interpolate_curve(curve: &[[f64; 2]], x: f64) -> f64 {
+    let Some(first) = curve.first() else { return 0.0 };
+    if x <= first[0] { return first[1]; }
+    for w in curve.windows(2) {
+        let ([x0, y0], [x1, y1]) = (w[0], w[1]);
+        if x <= x1 {
+            return if x1 > x0 { y0 + (y1 - y0) * (x - x0) / (x1 - x0) } else { y1 };
+        }
+    }
+    curve.last().map(|p| p[1]).unwrap_or(0.0)
+}
+
+/// 模拟器状态
+pub struct Simulator {
+    scenario: Scenario,
+    rng: SimRng,
+    t: f64,
+    hb_tick: i64,
+    load: f64,
+    cpu_temp: f64,
+    mobo_temp: f64,
+    fan_rpm: f64,
+    battery_pct: Option<f64>,
+    gpu_temps: Vec<f64>,
+}
+
+impl Simulator {
+    pub fn new(scenario: Scenario) -> Self {
+        let th = &scenario.thermal;
+        let load = scenario.load.base_pct.clamp(0.0, 100.0);
+        let cpu_temp = th.ambient_c + th.rise_at_full_load_c * load / 100.0;
+        let mobo_temp = th.ambient_c + 0.25 * (cpu_temp - th.ambient_c);
+        let fan_rpm = interpolate_curve(&scenario.fan.curve, cpu_temp);
+        let battery_pct = scenario.battery.as_ref().map(|b| b.start_pct.clamp(0.0, 100.0));
+        let gpu_temps = scenario.gpus.iter().map(|g| g.idle_temp_c).collect();
+        let rng = SimRng(scenario.seed);
+        Self { scenario, rng, t: 0.0, hb_tick: 0, load, cpu_temp, mobo_temp, fan_rpm, battery_pct, gpu_temps }
+    }
+
+    /// 当前场景时间（秒）
+    pub fn elapsed_sec(&self) -> f64 {
+        self.t
+    }
+
+    pub fn scenario(&self) -> &Scenario {
+        &self.scenario
+    }
+
+    /// 场景时间 t 的目标负载（不含惯性）
+    fn target_load(&mut self, t: f64) -> f64 {
+        let m = &self.scenario.load;
+        let mut load = m.base_pct;
+        for w in m.waves.iter().filter(|w| w.period_sec > 0.0) {
+            load += w.amplitude_pct * (2.0 * PI * (t + w.phase_sec) / w.period_sec).sin();
+        }
+        for s in m.spikes.iter() {
+            if in_window(t, s.at_sec, s.at_sec + s.duration_sec) { load = load.max(s.pct); }
+        }
+        let noise = m.noise_pct;
+        (load + noise * self.rng.symmetric()).clamp(0.0, 100.0)
+    }
+
+    /// 推进 dt 秒并输出一帧
+    pub fn step(&mut self, dt: f64) -> SimFrame {
+        let dt = dt.max(0.0);
+        self.t += dt;
+        self.hb_tick += 1;
+        let t = self.t;
+        self.load = self.target_load(t);
+        let load = self.load;
+
+        // CPU：温度随负载以热时间常数逼近稳态，主板温度跟随更慢
+        let th = self.scenario.thermal.clone();
+        let target = th.ambient_c + th.rise_at_full_load_c * load / 100.0;
+        self.cpu_temp = approach(self.cpu_temp, target, dt, th.tau_sec);
+        let mobo_target = th.ambient_c + 0.25 * (self.cpu_temp - th.ambient_c);
+        self.mobo_temp = approach(self.mobo_temp, mobo_target, dt, th.tau_sec * 4.0);
+        let throttling = self.cpu_temp >= th.throttle_c;
+        let boost = (load / 50.0).min(1.0);
+        let mut freq = th.base_mhz + (th.boost_mhz - th.base_mhz) * boost;
+        if throttling { freq = th.base_mhz * 0.8; }
+        let power = th.idle_power_w + (th.tdp_w - th.idle_power_w) * load / 100.0;
+
+        let cores = self.scenario.load.cores.max(1);
+        let core_loads: Vec<f64> = (0..cores).map(|_| (load + 8.0 * self.rng.symmetric()).clamp(0.0, 100.0)).collect();
+        let core_clocks: Vec<f64> = (0..cores).map(|_| (freq + 50.0 * self.rng.symmetric()).round()).collect();
+        let core_temps: Vec<f64> = (0..cores).map(|_| self.cpu_temp - 2.0 + 2.0 * self.rng.symmetric()).collect();
+
+        // 风扇：按温度曲线取目标转速，带惯性
+        let fan = self.scenario.fan.clone();
+        self.fan_rpm = approach(self.fan_rpm, interpolate_curve(&fan.curve, self.cpu_temp), dt, fan.tau_sec);
+        let fan_pct = if fan.max_rpm > 0.0 { (self.fan_rpm / fan.max_rpm * 100.0).clamp(0.0, 100.0) } else { 0.0 };
+        let mut fans = vec![serde_json::json!({ "name": "CPU Fan", "rpm": self.fan_rpm.round() as i32, "pct": fan_pct.round() as i32 })];
+        if fan.case_fan_ratio > 0.0 {
+            fans.push(serde_json::json!({ "name": "Case Fan", "rpm": (self.fan_rpm * fan.case_fan_ratio).round() as i32, "pct": (fan_pct * fan.case_fan_ratio).round() as i32 }));
+        }
+
+        // 网络：基线 + 噪声 + 周期突发
+        let net = self.scenario.network.clone();
+        let mut rx = net.rx_base_bps * (1.0 + net.noise * self.rng.symmetric());
+        let mut tx = net.tx_base_bps * (1.0 + net.noise * self.rng.symmetric());
+        for b in net.bursts.iter().filter(|b| b.every_sec > 0.0) {
+            let phase = (t - b.offset_sec).rem_euclid(b.every_sec);
+            if t >= b.offset_sec && phase < b.duration_sec {
+                rx += b.rx_bps;
+                tx += b.tx_bps;
+            }
+        }
+
+        // 电池：接通电源时充电，否则按负载放电
+        let mut overlay = SimOverlay { cpu_usage: load as f32, net_rx_bps: rx.max(0.0), net_tx_bps: tx.max(0.0), ..Default::default() };
+        if let (Some(b), Some(pct)) = (self.scenario.battery.as_ref(), self.battery_pct.as_mut()) {
+            let ac = b.ac_windows.iter().any(|w| in_window(t, w.from_sec, w.to_sec));
+            let drain = b.drain_idle_pct_per_hour + (b.drain_full_pct_per_hour - b.drain_idle_pct_per_hour) * load / 100.0;
+            *pct = if ac { *pct + b.charge_pct_per_hour * dt / 3600.0 } else { *pct - drain * dt / 3600.0 }.clamp(0.0, 100.0);
+            let p = *pct;
+            overlay.battery_percent = Some(p.round() as i32);
+            overlay.battery_ac_online = Some(ac);
+            overlay.battery_status = Some(match (ac, p >= 99.5) {
+                (true, true) => "已充满",
+                (true, false) => "充电中",
+                (false, _) => "放电中",
+            }.to_string());
+            if ac && p < 99.5 && b.charge_pct_per_hour > 0.0 {
+                overlay.battery_time_to_full_sec = Some(((100.0 - p) / b.charge_pct_per_hour * 3600.0) as i32);
+            }
+            if !ac && drain > 0.0 {
+                overlay.battery_time_remaining_sec = Some((p / drain * 3600.0) as i32);
+            }
+        }
+
+        // 磁盘：温度随负载，劣化后升温并累积坏扇区
+        let mut storage = Vec::new();
+        let mut smart = Vec::new();
+        for d in self.scenario.disks.iter() {
+            let fail_elapsed = d.failing.as_ref().map(|f| (t - f.start_sec).max(0.0)).unwrap_or(0.0);
+            let fail_hours = fail_elapsed / 3600.0;
+            let extra = d.failing.as_ref().map(|f| f.temp_rise_c * fail_hours.min(1.0)).unwrap_or(0.0);
+            let temp = d.base_temp_c + d.load_rise_c * load / 100.0 + extra;
+            storage.push(serde_json::json!({ "name": d.name, "tempC": (temp * 10.0).round() / 10.0 }));
+            let count = |per_hour: f64| (per_hour * fail_hours).floor() as i64;
+            let (realloc, pending, uncorrectable, predict) = match d.failing.as_ref() {
+                Some(f) if t >= f.start_sec => {
+                    let r = count(f.reallocated_per_hour);
+                    (r, count(f.pending_per_hour), count(f.uncorrectable_per_hour), r >= f.predict_fail_at)
+                }
+                _ => (0, 0, 0, false),
+            };
+            smart.push(SmartHealthPayload {
+                device: Some(d.name.clone()),
+                drive_letter: d.drive_letter.clone(),
+                predict_fail: Some(predict),
+                temp_c: Some(temp as f32),
+                power_on_hours: Some((d.power_on_hours + t / 3600.0) as i32),
+                reallocated: Some(realloc),
+                pending: Some(pending),
+                uncorrectable: Some(uncorrectable),
+                crc_err: Some(0),
+                power_cycles: Some(812),
+                host_reads_bytes: None,
+                host_writes_bytes: None,
+                nvme_percentage_used_pct: None,
+                nvme_available_spare_pct: None,
+                nvme_available_spare_threshold_pct: None,
+                nvme_media_errors: if uncorrectable > 0 { Some(uncorrectable) } else { None },
+            });
+        }
+        if !smart.is_empty() { overlay.smart_health = Some(smart); }
+
+        // GPU：负载部分跟随 CPU，温度/功耗带惯性
+        let mut gpus = Vec::new();
+        for (i, g) in self.scenario.gpus.clone().iter().enumerate() {
+            let gl = (g.base_pct + g.follow * load + 3.0 * self.rng.symmetric()).clamp(0.0, 100.0);
+            let gt = approach(self.gpu_temps[i], g.idle_temp_c + g.rise_at_full_load_c * gl / 100.0, dt, g.tau_sec);
+            self.gpu_temps[i] = gt;
+            let vram = g.vram_total_mb * (0.1 + 0.6 * gl / 100.0);
+            gpus.push(serde_json::json!({
+                "name": g.name,
+                "tempC": (gt * 10.0).round() / 10.0,
+                "hotspotTempC": ((gt + 9.0) * 10.0).round() / 10.0,
+                "loadPct": gl.round(),
+                "coreMhz": (300.0 + 1500.0 * gl / 100.0).round(),
+                "memoryMhz": if gl > 5.0 { 7000.0 } else { 405.0 },
+                "fanRpm": interpolate_curve(&fan.curve, gt).round() as i32,
+                "vramUsedMb": vram.round(),
+                "vramTotalMb": g.vram_total_mb,
+                "powerW": ((g.idle_power_w + (g.max_power_w - g.idle_power_w) * gl / 100.0) * 10.0).round() / 10.0,
+                "powerLimitW": g.max_power_w,
+            }));
+        }
+
+        let vcore = 0.9 + 0.35 * load / 100.0 + 0.01 * self.rng.symmetric();
+        let bridge = serde_json::json!({
+            "cpuTempC": (self.cpu_temp * 10.0).round() / 10.0,
+            "moboTempC": (self.mobo_temp * 10.0).round() / 10.0,
+            "fans": fans[..1],
+            "fansExtra": fans[1..],
+            "moboVoltages": [
+                { "name": "Vcore", "volts": (vcore * 1000.0).round() / 1000.0 },
+                { "name": "+12V", "volts": 12.04 },
+                { "name": "+5V", "volts": 5.02 },
+            ],
+            "storageTemps": storage,
+            "gpus": gpus,
+            "isAdmin": true,
+            "hasTemp": true,
+            "hasTempValue": true,
+            "hasFan": true,
+            "hasFanValue": true,
+            "cpuPkgPowerW": (power * 10.0).round() / 10.0,
+            "cpuAvgFreqMhz": freq.round(),
+            "cpuThrottleActive": throttling,
+            "cpuThrottleReasons": if throttling { vec!["Thermal"] } else { Vec::new() },
+            "sinceReopenSec": t as i32,
+            "cpuCoreLoadsPct": core_loads.iter().map(|v| (v * 10.0).round() / 10.0).collect::<Vec<_>>(),
+            "cpuCoreClocksMhz": core_clocks,
+            "cpuCoreTempsC": core_temps.iter().map(|v| (v * 10.0).round() / 10.0).collect::<Vec<_>>(),
+            "hbTick": self.hb_tick,
+            "idleSec": 0,
+            "excCount": 0,
+            "uptimeSec": t as i64,
+        });
+        SimFrame { bridge, overlay }
+    }
+}
+
+static OVERLAY: OnceLock<Mutex<Option<SimOverlay>>> = OnceLock::new();
+
+fn with_overlay<R, F: FnOnce(&mut Option<SimOverlay>) -> R>(f: F) -> Option<R> {
+    let cell = OVERLAY.get_or_init(|| Mutex::new(None));
+    cell.lock().ok().map(|mut g| f(&mut g))
+}
+
+/// 发布最新覆盖指标（模拟器停止时传 None）
+pub fn publish_overlay(overlay: Option<SimOverlay>) {
+    with_overlay(|o| *o = overlay);
+}
+
+/// 模拟器运行中时返回最新覆盖指标
+pub fn current_overlay() -> Option<SimOverlay> {
+    with_overlay(|o| o.clone()).flatten()
+}
@@ -1,11 +1,14 @@
 // SMART 后台 Worker：定期与按需采集磁盘 SMART 健康数据并广播事件
 // 事件名："sensor://smart"，负载包含设备列表与时间戳
+// 无界面模式（agent）下不传监听回调，仅发布到事件总线
 
 use std::sync::mpsc::{self, Sender, Receiver};
 use std::thread;
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
-use tauri::Emitter;
-use std::sync::{OnceLock, Mutex};
+use std::sync::{Arc, OnceLock, Mutex};
+
+/// 每次采集后的回调（GUI 据此向 webview 广播 "sensor://smart"）
+pub type SmartListener = Arc<dyn Fn(&serde_json::Value) + Send + Sync>;
 
 #[derive(Clone)]
 pub struct SmartWorker {
@@ -37,7 +40,7 @@ fn update_caches(payload: &serde_json::Value, err_detail: Option<(&'static str,
     }
 }
 
-fn collect_and_emit(app: &tauri::AppHandle) {
+fn collect_and_emit(listener: Option<&SmartListener>) {
     let mut err: Option<(&'static str, &'static str)> = None;
     let data = (|| {
         match wmi::COMLibrary::new() {
@@ -61,7 +64,8 @@ fn collect_and_emit(app: &tauri::AppHandle) {
     });
     // 更新缓存与错误状态
     update_caches(&payload, err);
-    let _ = app.emit("sensor://smart", payload);
+    crate::event_hub::publish("smart", &payload);
+    if let Some(f) = listener { f(&payload); }
 }
 
 pub fn get_last_snapshot() -> serde_json::Value {
@@ -107,7 +111,7 @@ impl SmartWorker {
 #[derive(Debug)]
 enum SmartCmd { Refresh, Shutdown }
 
-// 事件负载改为 serde_json::Value，以满足 tauri::Emitter::emit 的 Clone 约束
+// 事件负载为 serde_json::Value，GUI 回调可直接转发给 webview
 
 // 全局保存后台线程句柄，便于退出时回收
 static WORKER_HANDLE: OnceLock<Mutex<Option<std::thread::JoinHandle<()>>>> = OnceLock::new();
@@ -127,15 +131,14 @@ struct SmartStats {
 
 static SMART_STATS: OnceLock<Mutex<SmartStats>> = OnceLock::new();
 
-pub fn start(app: tauri::AppHandle) -> SmartWorker {
+pub fn start(listener: Option<SmartListener>) -> SmartWorker {
     let (tx, rx): (Sender<SmartCmd>, Receiver<SmartCmd>) = mpsc::channel();
 
     // 后台采集线程
-    let app_handle = app.clone();
     let handle = thread::Builder::new()
         .name("smart-worker".into())
         .spawn(move || {
-            worker_loop(app_handle, rx);
+            worker_loop(listener, rx);
         })
         .expect("spawn smart-worker");
 
@@ -152,11 +155,11 @@ fn now_ts_ms() -> i64 {
     SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_millis() as i64).unwrap_or(0)
 }
 
-fn worker_loop(app: tauri::AppHandle, rx: Receiver<SmartCmd>) {
+fn worker_loop(listener: Option<SmartListener>, rx: Receiver<SmartCmd>) {
     let next_interval = Duration::from_secs(10); // 默认 10s 采集一次
     loop {
         // 先执行一次采集（冷启动尽快填充）
-        collect_and_emit(&app);
+        collect_and_emit(listener.as_ref());
 
         // 间隔期间监听指令，提前刷新或退出
         let mut elapsed = Duration::from_secs(0);
@@ -164,7 +167,7 @@ fn worker_loop(app: tauri::AppHandle, rx: Receiver<SmartCmd>) {
             let wait = Duration::from_millis(200);
             match rx.recv_timeout(wait) {
                 Ok(SmartCmd::Refresh) => {
-                    collect_and_emit(&app);
+                    collect_and_emit(listener.as_ref());
                     // 刷新后重置间隔累计
                     elapsed = Duration::from_secs(0);
                     continue;
@@ -0,0 +1,712 @@
+// =============================================================================
+// 吞吐测速（按需 / 可选定时）
+// - 协议：HTTP（GET 下载 / POST 上传，到时即断开）或 iperf3 兼容服务器（TCP，下载使用反向模式）
+// - 端点完全自托管：任意 HTTP 服务器上的大文件 + 可接收 POST 的地址，或本机/局域网的 `iperf3 -s`；
+//   另一台运行 sys-sensor 的机器开启本地 API 与 api.speedtest_endpoint 后亦可作为端点（token 需带 speedtest 作用域）：
+//   GET /api/speedtest/download?bytes=N 与 POST /api/speedtest/upload
+// - 多连接并发，剔除预热段（TCP 慢启动）后计算持续吞吐；峰值取 500ms 窗口
+// - 负载下延迟：测速前测空闲延迟，测速期间持续探测，按延迟增量评定 bufferbloat 等级（A+ ~ F）
+// - 结果追加到配置目录下的 speedtest_history.jsonl，超过上限（默认 200）时丢弃最旧的记录（文件按 jsonl_journal 规则延迟压缩）
+// =============================================================================
+
+use std::io::{Read, Write};
+use std::net::{SocketAddr, TcpStream, ToSocketAddrs};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+use crate::http_timing::{parse_url, UrlParts};
+use crate::jsonl_journal::JsonlJournal;
+
+pub const DEFAULT_DURATION_SECS: u64 = 10;
+pub const DEFAULT_STREAMS: u32 = 4;
+pub const DEFAULT_WARMUP_MS: u64 = 2000;
+pub const DEFAULT_TIMEOUT_MS: u64 = 5000;
+pub const DEFAULT_IPERF3_PORT: u16 = 5201;
+// 本地端点：下载默认字节数与上限
+pub const ENDPOINT_DEFAULT_BYTES: u64 = 1 << 30;
+pub const ENDPOINT_MAX_BYTES: u64 = 16 << 30;
+const HISTORY_FILE: &str = "speedtest_history.jsonl";
+const DEFAULT_HISTORY_MAX_ENTRIES: usize = 200;
+// 读写块大小（iperf3 的 len 参数同此值）
+pub const BLOCK_BYTES: usize = 128 * 1024;
+// HTTP 上传单个请求声明的长度：到时即断开连接，不要求发完
+const UPLOAD_REQUEST_BYTES: u64 = 256 * 1024 * 1024;
+const SAMPLE_TICK_MS: u64 = 100;
+const PEAK_WINDOW_MS: u64 = 500;
+const LATENCY_INTERVAL_MS: u64 = 250;
+const IDLE_PROBES: usize = 5;
+const MAX_HEAD_BYTES: usize = 64 * 1024;
+const MAX_IPERF_JSON_BYTES: usize = 1024 * 1024;
+
+/// 测速协议
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SpeedtestProtocol {
+    #[default]
+    Http,
+    Iperf3,
+}
+
+/// 测速方向
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SpeedtestDirection {
+    #[default]
+    Both,
+    Download,
+    Upload,
+}
+
+/// 测速配置（AppConfig.speedtest）
+#[derive(Clone, Debug, Serialize, Deserialize, Default, PartialEq)]
+pub struct SpeedtestConfig {
+    // 协议："http" | "iperf3"（默认 http）
+    pub protocol: Option<SpeedtestProtocol>,
+    // HTTP 下载地址（GET，响应体即测试数据，读完后重复请求直至到时）
+    pub download_url: Option<String>,
+    // HTTP 上传地址（POST，服务器丢弃请求体即可）；缺省时不测上传
+    pub upload_url: Option<String>,
+    // iperf3 服务器 host[:port]（默认端口 5201）
+    pub server: Option<String>,
+    // HTTP 端点的 Bearer Token（如另一台 sys-sensor 的本地 API），可空
+    pub token: Option<String>,
+    // 测试方向：both | download | upload（默认 both）
+    pub direction: Option<SpeedtestDirection>,
+    // 每个方向的测试时长（秒，默认 10，范围 2..=60）
+    pub duration_secs: Option<u64>,
+    // 并发连接数（默认 4，范围 1..=16）
+    pub streams: Option<u32>,
+    // 预热时长（毫秒，默认 2000，不计入持续吞吐；最多为测试时长的一半）
+    pub warmup_ms: Option<u64>,
+    // 连接/读写超时（毫秒，默认 5000）
+    pub timeout_ms: Option<u64>,
+    // 延迟探测目标（语法同 rtt_targets 字符串）；缺省：HTTP 取端点 host:port，iperf3 取服务器主机（ICMP）
+    pub latency_target: Option<String>,
+    // 历史记录上限（默认 200）
+    pub history_max_entries: Option<usize>,
+}
+
+/// 单轮测速参数（由配置补全默认值）
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SpeedtestOptions {
+    pub protocol: SpeedtestProtocol,
+    pub download_url: Option<String>,
+    pub upload_url: Option<String>,
+    pub server: Option<String>,
+    pub token: Option<String>,
+    pub direction: SpeedtestDirection,
+    pub duration_ms: u64,
+    pub streams: u32,
+    pub warmup_ms: u64,
+    pub timeout_ms: u64,
+    pub latency_target: Option<String>,
+}
+
+fn non_empty(s: &Option<String>) -> Option<String> {
+    s.as_deref().map(str::trim).filter(|s| !s.is_empty()).map(str::to_string)
+}
+
+impl SpeedtestOptions {
+    pub fn from_config(cfg: &crate::config_utils::AppConfig) -> Self {
+        let c = cfg.speedtest.clone().unwrap_or_default();
+        let duration_ms = c.duration_secs.unwrap_or(DEFAULT_DURATION_SECS).clamp(2, 60) * 1000;
+        Self {
+            protocol: c.protocol.unwrap_or_default(),
+            download_url: non_empty(&c.download_url),
+            upload_url: non_empty(&c.upload_url),
+            server: non_empty(&c.server),
+            token: non_empty(&c.token),
+            direction: c.direction.unwrap_or_default(),
+            duration_ms,
+            streams: c.streams.unwrap_or(DEFAULT_STREAMS).clamp(1, 16),
+            warmup_ms: c.warmup_ms.unwrap_or(DEFAULT_WARMUP_MS).min(duration_ms / 2),
+            timeout_ms: c.timeout_ms.unwrap_or(DEFAULT_TIMEOUT_MS).max(100),
+            latency_target: non_empty(&c.latency_target),
+        }
+    }
+
+    /// 端点描述（结果与历史中展示）；未配置端点时为 None
+    pub fn endpoint(&self) -> Option<String> {
+        match self.protocol {
+            SpeedtestProtocol::Http => self.download_url.clone().or_else(|| self.upload_url.clone()),
+            SpeedtestProtocol::Iperf3 => self.server.clone(),
+        }
+    }
+
+    /// 延迟探测目标：iperf3 服务器在测试期间会把新连接当作数据流，因此默认只用主机名（ICMP）
+    fn latency_probe_target(&self) -> Option<String> {
+        if self.latency_target.is_some() { return self.latency_target.clone(); }
+        match self.protocol {
+            SpeedtestProtocol::Http => {
+                let u = parse_url(&self.endpoint()?)?;
+                Some(if u.host.contains(':') { format!("[{}]:{}", u.host, u.port) } else { format!("{}:{}", u.host, u.port) })
+            }
+            SpeedtestProtocol::Iperf3 => Some(crate::ping_utils::split_host_port(&self.server.clone()?).0),
+        }
+    }
+}
+
+/// 单个方向的测速结果
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct DirectionResult {
+    // 持续吞吐（剔除预热段，Mbit/s）
+    pub mbps: Option<f64>,
+    // 峰值吞吐（500ms 窗口，Mbit/s）
+    pub peak_mbps: Option<f64>,
+    // 计量窗口内的字节数与时长
+    pub bytes: u64,
+    pub duration_ms: u64,
+    pub streams: u32,
+    // 负载下延迟（中位数）及相对空闲延迟的增量
+    pub loaded_latency_ms: Option<f64>,
+    pub latency_increase_ms: Option<f64>,
+    pub grade: Option<String>,
+    pub error: Option<String>,
+}
+
+/// 一次测速结果
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct SpeedtestResult {
+    pub timestamp_ms: i64,
+    pub protocol: SpeedtestProtocol,
+    pub endpoint: String,
+    // 触发方式：manual / schedule
+    pub trigger: String,
+    // 空闲延迟（中位数）
+    pub idle_latency_ms: Option<f64>,
+    pub download: Option<DirectionResult>,
+    pub upload: Option<DirectionResult>,
+    // 整体 bufferbloat 等级（两个方向中较差者）
+    pub bufferbloat_grade: Option<String>,
+    pub error: Option<String>,
+}
+
+const GRADES: [&str; 6] = ["A+", "A", "B", "C", "D", "F"];
+
+/// 按负载下延迟增量评定 bufferbloat 等级
+pub fn bufferbloat_grade(increase_ms: f64) -> &'static str {
+    match increase_ms {
+        d if d < 5.0 => "A+",
+        d if d < 30.0 => "A",
+        d if d < 60.0 => "B",
+        d if d < 200.0 => "C",
+        d if d < 400.0 => "D",
+        _ => "F",
+    }
+}
+
+/// 取较差的等级
+pub fn worse_grade<'a>(a: Option<&'a str>, b: Option<&'a str>) -> Option<&'a str> {
+    let rank = |g: &str| GRADES.iter().position(|x| *x == g).unwrap_or(GRADES.len());
+    match (a, b) {
+        (Some(a), Some(b)) => Some(if rank(b) > rank(a) { b } else { a }),
+        (a, b) => a.or(b),
+    }
+}
+
+fn median(samples: &mut [f64]) -> Option<f64> {
+    if samples.is_empty() { return None; }
+    samples.sort_by(|a, b| a.total_cmp(b));
+    let n = samples.len();
+    Some((samples[(n - 1) / 2] + samples[n / 2]) / 2.0)
+}
+
+fn mbps(bytes: u64, elapsed: Duration) -> Option<f64> {
+    let secs = elapsed.as_secs_f64();
+    (secs > 0.0).then(|| bytes as f64 * 8.0 / secs / 1_000_000.0)
+}
+
+// ---- 负载引擎 ----
+
+/// 负载阶段参数
+#[derive(Clone, Debug)]
+struct LoadPlan {
+    streams: u32,
+    duration_ms: u64,
+    warmup_ms: u64,
+    timeout_ms: u64,
+    latency_target: Option<String>,
+}
+
+/// 并发执行 streams 个 worker（worker 累加字节计数，stop 置位后尽快返回），
+/// 同时按固定间隔探测延迟；到时或全部 worker 退出时结束
+fn run_load<F>(plan: &LoadPlan, worker: F) -> DirectionResult
+where
+    F: Fn(usize, &AtomicU64, &AtomicBool) -> Result<(), String> + Sync,
+{
+    let counter = AtomicU64::new(0);
+    let stop = AtomicBool::new(false);
+    let measuring = AtomicBool::new(false);
+    let active = AtomicUsize::new(plan.streams as usize);
+    let errors: Mutex<Vec<String>> = Mutex::new(Vec::new());
+    let latency: Mutex<Vec<f64>> = Mutex::new(Vec::new());
+    let (window, peak) = std::thread::scope(|s| {
+        for i in 0..plan.streams as usize {
+            let (worker, counter, stop, active, errors) = (&worker, &counter, &stop, &active, &errors);
+            s.spawn(move || {
+                if let Err(e) = worker(i, counter, stop) {
+                    if let Ok(mut g) = errors.lock() { g.push(e); }
+                }
+                active.fetch_sub(1, Ordering::SeqCst);
+            });
+        }
+        if let Some(target) = plan.latency_target.as_deref() {
+            let (stop, measuring, latency) = (&stop, &measuring, &latency);
+            let timeout_ms = plan.timeout_ms.min(2000);
+            s.spawn(move || {
+                while !stop.load(Ordering::SeqCst) {
+                    let rtt = crate::ping_utils::measure_single_rtt(target, timeout_ms);
+                    if let (Some(v), true) = (rtt, measuring.load(Ordering::SeqCst)) {
+                        if let Ok(mut g) = latency.lock() { g.push(v); }
+                    }
+                    std::thread::sleep(Duration::from_millis(LATENCY_INTERVAL_MS));
+                }
+            });
+        }
+        let start = Instant::now();
+        let mut base: Option<(Instant, u64)> = None;
+        let mut last = (start, 0u64);
+        let mut peak: Option<f64> = None;
+        let end = loop {
+            std::thread::sleep(Duration::from_millis(SAMPLE_TICK_MS));
+            let now = Instant::now();
+            let bytes = counter.load(Ordering::SeqCst);
+            let elapsed = now.duration_since(start);
+            if base.is_none() && elapsed >= Duration::from_millis(plan.warmup_ms) {
+                base = Some((now, bytes));
+                last = (now, bytes);
+                measuring.store(true, Ordering::SeqCst);
+            } else if base.is_some() && now.duration_since(last.0) >= Duration::from_millis(PEAK_WINDOW_MS) {
+                if let Some(rate) = mbps(bytes - last.1, now.duration_since(last.0)) {
+                    peak = Some(peak.map_or(rate, |p: f64| p.max(rate)));
+                }
+                last = (now, bytes);
+            }
+            if elapsed >= Duration::from_millis(plan.duration_ms) || active.load(Ordering::SeqCst) == 0 {
+                break (now, bytes);
+            }
+        };
+        stop.store(true, Ordering::SeqCst);
+        let (t0, b0) = base.unwrap_or((start, 0));
+        ((end.0.duration_since(t0), end.1 - b0), peak)
+    });
+    let (elapsed, bytes) = window;
+    let mut r = DirectionResult {
+        mbps: mbps(bytes, elapsed).filter(|_| bytes > 0),
+        peak_mbps: peak,
+        bytes,
+        duration_ms: elapsed.as_millis() as u64,
+        streams: plan.streams,
+        loaded_latency_ms: latency.lock().ok().and_then(|mut g| median(&mut g)),
+        ..Default::default()
+    };
+    if r.mbps.is_none() {
+        r.peak_mbps = None;
+        r.error = Some(errors.lock().ok().and_then(|g| g.first().cloned()).unwrap_or_else(|| "未传输任何数据".to_string()));
+    }
+    r
+}
+
+/// 空闲延迟：测速前连续探测若干次取中位数
+fn idle_latency(target: &str, timeout_ms: u64) -> Option<f64> {
+    let mut samples: Vec<f64> = Vec::new();
+    for i in 0..IDLE_PROBES {
+        if i > 0 { std::thread::sleep(Duration::from_millis(100)); }
+        if let Some(v) = crate::ping_utils::measure_single_rtt(target, timeout_ms.min(2000)) { samples.push(v); }
+    }
+    median(&mut samples)
+}
+
+// ---- HTTP ----
+
+trait Conn: Read + Write + Send {}
+impl<T: Read + Write + Send> Conn for T {}
+
+fn http_connect(u: &UrlParts, timeout_ms: u64) -> Result<Box<dyn Conn>, String> {
+    let timeout = Duration::from_millis(timeout_ms);
+    let addr = (u.host.as_str(), u.port)
+        .to_socket_addrs()
+        .map_err(|e| format!("DNS 解析失败: {}", e))?
+        .next()
+        .ok_or_else(|| "DNS 解析无结果".to_string())?;
+    let tcp = TcpStream::connect_timeout(&addr, timeout).map_err(|e| format!("连接失败: {}", e))?;
+    tcp.set_read_timeout(Some(timeout)).map_err(|e| e.to_string())?;
+    tcp.set_write_timeout(Some(timeout)).map_err(|e| e.to_string())?;
+    if !u.tls { return Ok(Box::new(tcp)); }
+    let name = rustls::pki_types::ServerName::try_from(u.host.clone()).map_err(|e| format!("无效的 TLS 主机名: {}", e))?;
+    let conn = rustls::ClientConnection::new(crate::http_timing::tls_config()?, name).map_err(|e| e.to_string())?;
+    Ok(Box::new(rustls::StreamOwned::new(conn, tcp)))
+}
+
+fn request_head(method: &str, u: &UrlParts, token: Option<&str>, content_length: Option<u64>) -> String {
+    let mut head = format!("{} {} HTTP/1.1\r\nHost: {}\r\nUser-Agent: sys-sensor\r\nAccept: */*\r\nCache-Control: no-store\r\n", method, u.path, u.authority);
+    if let Some(t) = token { head.push_str(&format!("Authorization: Bearer {}\r\n", t)); }
+    if let Some(n) = content_length { head.push_str(&format!("Content-Type: application/octet-stream\r\nContent-Length: {}\r\n", n)); }
+    head.push_str("Connection: close\r\n\r\n");
+    head
+}
+
+/// 读取响应头：返回状态码与已读入的响应体前缀
+fn read_response_head(s: &mut dyn Conn) -> Result<(u16, Vec<u8>), String> {
+    let mut buf: Vec<u8> = Vec::new();
+    let mut tmp = [0u8; 4096];
+    let end = loop {
+        if let Some(pos) = buf.windows(4).position(|w| w == b"\r\n\r\n") { break pos; }
+        if buf.len() > MAX_HEAD_BYTES { return Err("响应头过大".to_string()); }
+        match s.read(&mut tmp) {
+            Ok(0) => return Err("连接在响应头之前关闭".to_string()),
+            Ok(n) => buf.extend_from_slice(&tmp[..n]),
+            Err(e) => return Err(format!("读取响应失败: {}", e)),
+        }
+    };
+    let head = String::from_utf8_lossy(&buf[..end]);
+    let status = head
+        .lines()
+        .next()
+        .and_then(|l| l.split_whitespace().nth(1))
+        .and_then(|c| c.parse::<u16>().ok())
+        .ok_or_else(|| "无效的 HTTP 响应".to_string())?;
+    Ok((status, buf[end + 4..].to_vec()))
+}
+
+fn check_status(status: u16) -> Result<(), String> {
+    if (200..300).contains(&status) { Ok(()) } else { Err(format!("HTTP {}", status)) }
+}
+
+/// 下载 worker：读完响应体后重复请求，直至 stop
+fn http_download(u: &UrlParts, token: Option<&str>, timeout_ms: u64, counter: &AtomicU64, stop: &AtomicBool) -> Result<(), String> {
+    let mut buf = vec![0u8; BLOCK_BYTES];
+    while !stop.load(Ordering::SeqCst) {
+        let mut s = http_connect(u, timeout_ms)?;
+        s.write_all(request_head("GET", u, token, None).as_bytes()).map_err(|e| format!("发送请求失败: {}", e))?;
+        let (status, prefix) = read_response_head(&mut *s)?;
+        check_status(status)?;
+        counter.fetch_add(prefix.len() as u64, Ordering::SeqCst);
+        loop {
+            if stop.load(Ordering::SeqCst) { return Ok(()); }
+            match s.read(&mut buf) {
+                Ok(0) => break,
+                Ok(n) => { counter.fetch_add(n as u64, Ordering::SeqCst); }
+                // TLS 对端未发送 close_notify 直接断开：按读完处理
+                Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => break,
+                Err(e) => return Err(format!("下载中断: {}", e)),
+            }
+        }
+    }
+    Ok(())
+}
+
+/// 上传 worker：声明较大的 Content-Length 持续写入，到时直接断开；写满则读取响应后重复
+fn http_upload(u: &UrlParts, token: Option<&str>, timeout_ms: u64, counter: &AtomicU64, stop: &AtomicBool) -> Result<(), String> {
+    let block = vec![0u8; BLOCK_BYTES];
+    while !stop.load(Ordering::SeqCst) {
+        let mut s = http_connect(u, timeout_ms)?;
+        s.write_all(request_head("POST", u, token, Some(UPLOAD_REQUEST_BYTES)).as_bytes()).map_err(|e| format!("发送请求失败: {}", e))?;
+        let mut sent = 0u64;
+        while sent < UPLOAD_REQUEST_BYTES {
+            if stop.load(Ordering::SeqCst) { return Ok(()); }
+            let n = block.len().min((UPLOAD_REQUEST_BYTES - sent) as usize);
+            if let Err(e) = s.write_all(&block[..n]) {
+                // 服务器提前拒绝（如 401 / 413）时尽量读出状态码
+                return Err(match read_response_head(&mut *s) {
+                    Ok((status, _)) => format!("HTTP {}", status),
+                    Err(_) => format!("上传中断: {}", e),
+                });
+            }
+            sent += n as u64;
+            counter.fetch_add(n as u64, Ordering::SeqCst);
+        }
+        let (status, _) = read_response_head(&mut *s)?;
+        check_status(status)?;
+    }
+    Ok(())
+}
+
+fn http_direction(url: &str, upload: bool, opts: &SpeedtestOptions, plan: &LoadPlan) -> DirectionResult {
+    let Some(u) = parse_url(url) else {
+        return DirectionResult { streams: plan.streams, error: Some(format!("无效的 URL: {}", url)), ..Default::default() };
+    };
+    let token = opts.token.as_deref();
+    run_load(plan, |_, counter, stop| {
+        if upload { http_upload(&u, token, opts.timeout_ms, counter, stop) } else { http_download(&u, token, opts.timeout_ms, counter, stop) }
+    })
+}
+
+// ---- iperf3 ----
+// 控制连接：客户端发送 37 字节 cookie，之后由服务器推送单字节状态；参数与结果为 4 字节大端长度 + JSON
+
+const IPERF_TEST_START: i8 = 1;
+const IPERF_TEST_RUNNING: i8 = 2;
+const IPERF_TEST_END: i8 = 4;
+const IPERF_PARAM_EXCHANGE: i8 = 9;
+const IPERF_CREATE_STREAMS: i8 = 10;
+const IPERF_SERVER_TERMINATE: i8 = 11;
+const IPERF_EXCHANGE_RESULTS: i8 = 13;
+const IPERF_DISPLAY_RESULTS: i8 = 14;
+const IPERF_DONE: i8 = 16;
+const IPERF_ACCESS_DENIED: i8 = -1;
+const IPERF_SERVER_ERROR: i8 = -2;
+pub const IPERF_COOKIE_SIZE: usize = 37;
+
+/// 生成 iperf3 cookie：36 个 [a-z2-7] 字符 + NUL
+pub fn iperf3_cookie() -> Result<[u8; IPERF_COOKIE_SIZE], String> {
+    const ALPHABET: &[u8; 32] = b"abcdefghijklmnopqrstuvwxyz234567";
+    let mut raw = [0u8; IPERF_COOKIE_SIZE - 1];
+    getrandom::getrandom(&mut raw).map_err(|e| format!("生成 iperf3 cookie 失败: {}", e))?;
+    let mut cookie = [0u8; IPERF_COOKIE_SIZE];
+    for (c, r) in cookie.iter_mut().zip(raw) { *c = ALPHABET[(r & 31) as usize]; }
+    Ok(cookie)
+}
+
+fn iperf_expect(ctrl: &mut TcpStream, want: i8) -> Result<(), String> {
+    let mut b = [0u8; 1];
+    ctrl.read_exact(&mut b).map_err(|e| format!("iperf3 控制连接中断: {}", e))?;
+    match b[0] as i8 {
+        s if s == want => Ok(()),
+        IPERF_ACCESS_DENIED => Err("iperf3 服务器忙（正在执行其他测试）".to_string()),
+        IPERF_SERVER_ERROR => {
+            let mut code = [0u8; 4];
+            let errno = ctrl.read_exact(&mut code).ok().map(|_| i32::from_be_bytes(code));
+            Err(format!("iperf3 服务器错误（i_errno={}）", errno.map(|v| v.to_string()).unwrap_or_else(|| "?".into())))
+        }
+        IPERF_SERVER_TERMINATE => Err("iperf3 服务器终止了测试".to_string()),
+        s => Err(format!("iperf3 状态异常：期望 {}，收到 {}", want, s)),
+    }
+}
+
+fn iperf_write_json(ctrl: &mut TcpStream, v: &serde_json::Value) -> Result<(), String> {
+    let body = v.to_string();
+    let mut msg = (body.len() as u32).to_be_bytes().to_vec();
+    msg.extend_from_slice(body.as_bytes());
+    ctrl.write_all(&msg).map_err(|e| format!("iperf3 发送失败: {}", e))
+}
+
+fn iperf_read_json(ctrl: &mut TcpStream) -> Result<serde_json::Value, String> {
+    let mut len = [0u8; 4];
+    ctrl.read_exact(&mut len).map_err(|e| format!("iperf3 读取失败: {}", e))?;
+    let len = u32::from_be_bytes(len) as usize;
+    if len > MAX_IPERF_JSON_BYTES { return Err("iperf3 结果过大".to_string()); }
+    let mut body = vec![0u8; len];
+    ctrl.read_exact(&mut body).map_err(|e| format!("iperf3 读取失败: {}", e))?;
+    serde_json::from_slice(&body).map_err(|e| format!("iperf3 结果解析失败: {}", e))
+}
+
+fn iperf_connect(addr: SocketAddr, cookie: &[u8], timeout_ms: u64) -> Result<TcpStream, String> {
+    let timeout = Duration::from_millis(timeout_ms);
+    let mut s = TcpStream::connect_timeout(&addr, timeout).map_err(|e| format!("连接 iperf3 服务器失败: {}", e))?;
+    s.set_read_timeout(Some(timeout)).map_err(|e| e.to_string())?;
+    s.set_write_timeout(Some(timeout)).map_err(|e| e.to_string())?;
+    s.write_all(cookie).map_err(|e| format!("iperf3 发送 cookie 失败: {}", e))?;
+    Ok(s)
+}
+
+/// 一次 iperf3 TCP 测试（reverse 为 true 时服务器发送，即下载）
+fn iperf3_session(addr: SocketAddr, reverse: bool, plan: &LoadPlan) -> Result<DirectionResult, String> {
+    let cookie = iperf3_cookie()?;
+    let mut ctrl = iperf_connect(addr, &cookie, plan.timeout_ms)?;
+    let _ = ctrl.set_nodelay(true);
+    iperf_expect(&mut ctrl, IPERF_PARAM_EXCHANGE)?;
+    // 服务器按字段是否存在判断 tcp / reverse
+    let mut params = serde_json::json!({
+        "tcp": true,
+        "omit": 0,
+        "time": plan.duration_ms.div_ceil(1000),
+        "num": 0,
+        "blockcount": 0,
+        "parallel": plan.streams,
+        "len": BLOCK_BYTES,
+        "pacing_timer": 1000,
+        "client_version": "3.16",
+    });
+    if reverse { params["reverse"] = serde_json::json!(true); }
+    iperf_write_json(&mut ctrl, &params)?;
+    iperf_expect(&mut ctrl, IPERF_CREATE_STREAMS)?;
+    let streams: Vec<Mutex<TcpStream>> = (0..plan.streams)
+        .map(|_| iperf_connect(addr, &cookie, plan.timeout_ms).map(Mutex::new))
+        .collect::<Result<_, _>>()?;
+    iperf_expect(&mut ctrl, IPERF_TEST_START)?;
+    iperf_expect(&mut ctrl, IPERF_TEST_RUNNING)?;
+    let per_stream: Vec<AtomicU64> = (0..plan.streams).map(|_| AtomicU64::new(0)).collect();
+    let started = Instant::now();
+    let result = run_load(plan, |i, counter, stop| {
+        let mut s = streams[i].lock().map_err(|_| "iperf3 数据流锁异常".to_string())?;
+        let mut buf = vec![0u8; BLOCK_BYTES];
+        while !stop.load(Ordering::SeqCst) {
+            let n = if reverse {
+                match s.read(&mut buf) {
+                    Ok(0) => return Ok(()),
+                    Ok(n) => n,
+                    Err(e) => return Err(format!("iperf3 数据流中断: {}", e)),
+                }
+            } else {
+                s.write_all(&buf).map_err(|e| format!("iperf3 数据流中断: {}", e))?;
+                buf.len()
+            };
+            counter.fetch_add(n as u64, Ordering::SeqCst);
+            per_stream[i].fetch_add(n as u64, Ordering::SeqCst);
+        }
+        Ok(())
+    });
+    let elapsed = started.elapsed().as_secs_f64();
+
+    // 结束测试并交换结果（流 id 与 iperf3 一致：1, 3, 4, ...）
+    ctrl.write_all(&[IPERF_TEST_END as u8]).map_err(|e| format!("iperf3 发送失败: {}", e))?;
+    iperf_expect(&mut ctrl, IPERF_EXCHANGE_RESULTS)?;
+    let stream_results: Vec<serde_json::Value> = per_stream
+        .iter()
+        .enumerate()
+        .map(|(i, b)| serde_json::json!({
+            "id": if i == 0 { 1 } else { i + 2 },
+            "bytes": b.load(Ordering::SeqCst),
+            "retransmits": -1,
+            "jitter": 0,
+            "errors": 0,
+            "omitted_errors": 0,
+            "packets": 0,
+            "omitted_packets": 0,
+            "start_time": 0,
+            "end_time": elapsed,
+        }))
+        .collect();
+    iperf_write_json(&mut ctrl, &serde_json::json!({
+        "cpu_util_total": 0,
+        "cpu_util_user": 0,
+        "cpu_util_system": 0,
+        "sender_has_retransmits": if reverse { 0 } else { -1 },
+        "streams": stream_results,
+    }))?;
+    let _server = iperf_read_json(&mut ctrl)?;
+    iperf_expect(&mut ctrl, IPERF_DISPLAY_RESULTS)?;
+    let _ = ctrl.write_all(&[IPERF_DONE as u8]);
+    Ok(result)
+}
+
+fn iperf3_direction(server: &str, reverse: bool, plan: &LoadPlan) -> DirectionResult {
+    let (host, port) = crate::ping_utils::split_host_port(server);
+    let addr = (host.as_str(), port.unwrap_or(DEFAULT_IPERF3_PORT))
+        .to_socket_addrs()
+        .map_err(|e| format!("DNS 解析失败: {}", e))
+        .and_then(|mut it| it.next().ok_or_else(|| "DNS 解析无结果".to_string()));
+    match addr.and_then(|a| iperf3_session(a, reverse, plan)) {
+        Ok(r) => r,
+        Err(e) => DirectionResult { streams: plan.streams, error: Some(e), ..Default::default() },
+    }
+}
+
+// ---- 测速入口 ----
+
+/// 执行一次测速（阻塞，时长约为方向数 × duration）
+pub fn run(opts: &SpeedtestOptions, trigger: &str) -> SpeedtestResult {
+    let mut r = SpeedtestResult {
+        timestamp_ms: chrono::Local::now().timestamp_millis(),
+        protocol: opts.protocol,
+        endpoint: opts.endpoint().unwrap_or_default(),
+        trigger: trigger.to_string(),
+        ..Default::default()
+    };
+    if r.endpoint.is_empty() {
+        r.error = Some(match opts.protocol {
+            SpeedtestProtocol::Http => "未配置测速端点（speedtest.download_url / upload_url）".to_string(),
+            SpeedtestProtocol::Iperf3 => "未配置 iperf3 服务器（speedtest.server）".to_string(),
+        });
+        return r;
+    }
+    let target = opts.latency_probe_target();
+    r.idle_latency_ms = target.as_deref().and_then(|t| idle_latency(t, opts.timeout_ms));
+    let plan = LoadPlan {
+        streams: opts.streams,
+        duration_ms: opts.duration_ms,
+        warmup_ms: opts.warmup_ms,
+        timeout_ms: opts.timeout_ms,
+        latency_target: target,
+    };
+    let want_down = opts.direction != SpeedtestDirection::Upload;
+    let want_up = opts.direction != SpeedtestDirection::Download;
+    match opts.protocol {
+        SpeedtestProtocol::Http => {
+            if want_down { r.download = opts.download_url.as_deref().map(|u| http_direction(u, false, opts, &plan)); }
+            if want_up { r.upload = opts.upload_url.as_deref().map(|u| http_direction(u, true, opts, &plan)); }
+        }
+        SpeedtestProtocol::Iperf3 => {
+            let server = opts.server.clone().unwrap_or_default();
+            if want_down { r.download = Some(iperf3_direction(&server, true, &plan)); }
+            if want_up { r.upload = Some(iperf3_direction(&server, false, &plan)); }
+        }
+    }
+    for d in [r.download.as_mut(), r.upload.as_mut()].into_iter().flatten() {
+        if let (Some(idle), Some(loaded)) = (r.idle_latency_ms, d.loaded_latency_ms) {
+            let increase = (loaded - idle).max(0.0);
+            d.latency_increase_ms = Some(increase);
+            d.grade = Some(bufferbloat_grade(increase).to_string());
+        }
+    }
+    r.bufferbloat_grade = worse_grade(
+        r.download.as_ref().and_then(|d| d.grade.as_deref()),
+        r.upload.as_ref().and_then(|d| d.grade.as_deref()),
+    )
+    .map(str::to_string);
+    let dirs: Vec<(&str, &DirectionResult)> = [("下载", r.download.as_ref()), ("上传", r.upload.as_ref())]
+        .into_iter()
+        .filter_map(|(name, d)| d.map(|d| (name, d)))
+        .collect();
+    if dirs.is_empty() {
+        r.error = Some("所选方向未配置对应的测速地址".to_string());
+    } else if dirs.iter().all(|(_, d)| d.mbps.is_none()) {
+        r.error = Some(dirs.iter().map(|(name, d)| format!("{}: {}", name, d.error.as_deref().unwrap_or("失败"))).collect::<Vec<_>>().join("；"));
+    }
+    r
+}
+
+// ---- 历史 ----
+
+#[derive(Default)]
+struct History {
+    file: Option<JsonlJournal>,
+    entries: Vec<SpeedtestResult>,
+}
+
+static HISTORY: OnceLock<Mutex<History>> = OnceLock::new();
+
+fn with_history<R, F: FnOnce(&mut History) -> R>(f: F) -> Option<R> {
+    let cell = HISTORY.get_or_init(|| Mutex::new(History::default()));
+    cell.lock().ok().map(|mut g| f(&mut g))
+}
+
+/// 打开历史目录（未调用时结果只在内存中维护）
+pub fn open_history(dir: &Path, max_entries: Option<usize>) {
+    let file = JsonlJournal::open(dir.join(HISTORY_FILE), max_entries.unwrap_or(DEFAULT_HISTORY_MAX_ENTRIES));
+    let entries = file.read();
+    with_history(|h| {
+        h.file = Some(file);
+        h.entries = entries;
+    });
+}
+
+/// 追加一次测速结果
+pub fn record(result: &SpeedtestResult, max_entries: Option<usize>) {
+    let max = max_entries.unwrap_or(DEFAULT_HISTORY_MAX_ENTRIES).max(1);
+    with_history(|h| {
+        h.entries.push(result.clone());
+        let drop = h.entries.len().saturating_sub(max);
+        h.entries.drain(..drop);
+        let Some(file) = h.file.as_mut() else { return };
+        if let Err(e) = file.append(result, max) {
+            eprintln!("[speedtest] 写入测速历史失败: {}", e);
+        }
+    });
+}
+
+/// 查询测速历史（新记录在前）
+pub fn history(limit: usize) -> Vec<SpeedtestResult> {
+    with_history(|h| h.entries.iter().rev().take(limit).cloned().collect()).unwrap_or_default()
+}
+
+/// Tauri命令：测速历史（新记录在前，默认 50 条）
+#[cfg_attr(feature = "desktop", tauri::command)]
+pub fn speedtest_history(limit: Option<usize>) -> Vec<SpeedtestResult> {
+    history(limit.unwrap_or(50))
+}
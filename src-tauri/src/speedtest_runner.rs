@@ -0,0 +1,99 @@
+// Speedtest Runner：吞吐测速（HTTP / iperf3）
+// 说明：
+// - 依赖 BaseGate 做并发防重入
+// - 默认不定时执行：手动请求（trigger_task）入队，trigger() 时取出并标记触发方式；定时由 pace_speedtest_every 开启
+// - 每次测速完成后追加到测速历史，snapshot_json() 返回最近一次结果
+// - 未配置端点或全部方向失败时不标记成功
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use crate::runner::{Runner, BaseGate};
+use crate::speedtest::{run, SpeedtestOptions};
+
+/// 单轮测速配置
+#[derive(Clone, Debug)]
+pub struct SpeedtestRunConfig {
+    pub options: SpeedtestOptions,
+    pub history_max_entries: Option<usize>,
+}
+
+impl SpeedtestRunConfig {
+    pub fn from_config(cfg: &crate::config_utils::AppConfig) -> Self {
+        Self {
+            options: SpeedtestOptions::from_config(cfg),
+            history_max_entries: cfg.speedtest.as_ref().and_then(|s| s.history_max_entries),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct SpeedtestRunner {
+    gate: Arc<BaseGate>,
+    // 配置提供器：每轮触发时读取
+    cfg_provider: Arc<dyn Fn() -> SpeedtestRunConfig + Send + Sync>,
+    // 待执行的手动请求
+    pending: Arc<AtomicBool>,
+    // 最近一次结果快照
+    last_snapshot: Arc<Mutex<serde_json::Value>>,
+}
+
+impl SpeedtestRunner {
+    pub fn new<F>(cfg_provider: F) -> Self
+    where
+        F: Fn() -> SpeedtestRunConfig + Send + Sync + 'static,
+    {
+        Self {
+            gate: Arc::new(BaseGate::new()),
+            cfg_provider: Arc::new(cfg_provider),
+            pending: Arc::new(AtomicBool::new(false)),
+            last_snapshot: Arc::new(Mutex::new(serde_json::json!({}))),
+        }
+    }
+
+    /// 手动请求：下一次 trigger() 时执行
+    pub fn request(&self) {
+        self.pending.store(true, Ordering::SeqCst);
+    }
+
+    pub fn has_pending(&self) -> bool {
+        self.pending.load(Ordering::SeqCst)
+    }
+}
+
+impl Runner for SpeedtestRunner {
+    fn name(&self) -> &'static str { "speedtest_runner" }
+
+    fn trigger(&self, now_ms: i64) {
+        // 防重入：运行中收到的请求留到下次触发
+        if !self.gate.try_enter() { return; }
+        self.gate.set_running();
+        let trigger = if self.pending.swap(false, Ordering::SeqCst) { "manual" } else { "schedule" };
+        let gate_c = self.gate.clone();
+        let snap_c = self.last_snapshot.clone();
+        let cfg_c = self.cfg_provider.clone();
+        std::thread::spawn(move || {
+            let cfg = (cfg_c)();
+            let mut result = run(&cfg.options, trigger);
+            result.timestamp_ms = now_ms;
+            crate::speedtest::record(&result, cfg.history_max_entries);
+            if let Ok(v) = serde_json::to_value(&result) {
+                if let Ok(mut g) = snap_c.lock() { *g = v; }
+            }
+            if result.error.is_none() {
+                gate_c.mark_ok_and_exit(now_ms);
+            } else {
+                gate_c.exit();
+            }
+        });
+    }
+
+    fn is_running(&self) -> bool { self.gate.is_running() }
+    fn last_ok_ms(&self) -> Option<i64> { self.gate.last_ok_ms() }
+
+    fn snapshot_json(&self) -> serde_json::Value {
+        match self.last_snapshot.lock() {
+            Ok(g) => (*g).clone(),
+            Err(_) => serde_json::json!({}),
+        }
+    }
+}
@@ -2,9 +2,13 @@
 // 目标：集中维护各领域最新状态，并在每个 tick 聚合构建对外快照。
 // 当前阶段：先接入 tick 级监控指标，为后续迁移 CPU/内存/网络/磁盘/SMART 等做铺垫。
 
+use std::collections::{BTreeMap, VecDeque};
 use std::time::SystemTime;
 use serde::{Serialize, Deserialize};
 
+// 历史环形缓冲默认容量（按 1s 节拍约 1 小时）
+const DEFAULT_HISTORY_CAPACITY: usize = 3600;
+
 #[derive(Default, Debug, Clone, Serialize, Deserialize)]
 pub struct TickTelemetry {
     pub tick: u64,
@@ -53,10 +57,20 @@ pub struct Aggregated {
     pub smart_last_fail_ms: Option<i64>,
 }
 
+/// 历史查询结果点：时间戳 + 指标键值
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+pub struct HistoryPoint {
+    pub timestamp_ms: i64,
+    pub values: BTreeMap<String, f64>,
+}
+
 #[derive(Default, Debug)]
 pub struct StateStore {
     tick: TickTelemetry,
     agg: Aggregated,
+    // 快照标量指标的历史环形缓冲（键来自 metrics_utils 指标目录）
+    history: VecDeque<(i64, Vec<(&'static str, f64)>)>,
+    history_capacity: usize,
 }
 
 impl StateStore {
@@ -74,6 +88,45 @@ impl StateStore {
 
     pub fn get_agg(&self) -> Aggregated { self.agg.clone() }
 
+    /// 设置历史容量（0 表示使用默认值），超出部分立即丢弃最旧数据
+    pub fn set_history_capacity(&mut self, cap: usize) {
+        self.history_capacity = cap;
+        let cap = self.history_cap();
+        while self.history.len() > cap { self.history.pop_front(); }
+    }
+
+    fn history_cap(&self) -> usize {
+        if self.history_capacity == 0 { DEFAULT_HISTORY_CAPACITY } else { self.history_capacity }
+    }
+
+    /// 追加一帧历史
+    pub fn push_history(&mut self, timestamp_ms: i64, fields: Vec<(&'static str, f64)>) {
+        let cap = self.history_cap();
+        while self.history.len() >= cap { self.history.pop_front(); }
+        self.history.push_back((timestamp_ms, fields));
+    }
+
+    /// 查询历史：按时间范围过滤，可选指标子集；limit 取最新的 N 个点（按时间升序返回）
+    pub fn query_history(&self, metrics: Option<&[String]>, since_ms: Option<i64>, until_ms: Option<i64>, limit: usize) -> Vec<HistoryPoint> {
+        let mut out: Vec<HistoryPoint> = self.history
+            .iter()
+            .rev()
+            .filter(|(ts, _)| since_ms.map(|s| *ts >= s).unwrap_or(true) && until_ms.map(|u| *ts <= u).unwrap_or(true))
+            .take(limit)
+            .map(|(ts, fields)| HistoryPoint {
+                timestamp_ms: *ts,
+                values: fields
+                    .iter()
+                    .filter(|(k, _)| metrics.map(|m| m.iter().any(|x| x == k)).unwrap_or(true))
+                    .map(|(k, v)| (k.to_string(), *v))
+                    .collect(),
+            })
+            .collect();
+        out.reverse();
+        out
+    }
+
+
     #[allow(dead_code)]
     pub fn now_ts_ms() -> i64 {
         SystemTime::now()
@@ -5,7 +5,7 @@ use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::time::{Duration, Instant};
 use tokio::time::timeout;
-use crate::ping_utils::measure_multi_rtt;
+use crate::ping_utils::{measure_multi_rtt, FamilyPref, ProbeDefaults, RttTarget};
 use crate::config_utils::AppConfig;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -64,6 +64,30 @@ impl TestRunner {
         self.test_network_quality().await;
         // RTT 测量测试
         self.test_rtt_measurement().await;
+
+        // RTT 多包统计测试
+        self.test_rtt_stats().await;
+
+        // RTT IPv6 / 地址族测试
+        self.test_rtt_ipv6().await;
+
+        // ICMP 回显测试
+        self.test_icmp_echo().await;
+
+        // RTT 目标级配置测试
+        self.test_rtt_target_config().await;
+
+        // HTTP 分阶段计时测试
+        self.test_http_timing().await;
+
+        // DNS 解析器探测测试
+        self.test_dns_probe().await;
+
+        // 路径分析测试
+        self.test_traceroute().await;
+
+        // 连通性状态机与中断时间线测试
+        self.test_connectivity().await;
         // 7. 电池监控测试（完整）
         self.test_battery_monitoring().await;
 
@@ -76,9 +100,60 @@ impl TestRunner {
         // 10. 公网信息测试
         self.test_public_network().await;
 
+        // 公网 IP 提供者与变化历史测试
+        self.test_public_ip_providers().await;
+
+        // 吞吐测速测试（本地 HTTP 端点 + iperf3 桩）
+        self.test_speedtest().await;
+
         // 11. 系统运行时测试
         self.test_system_runtime().await;
 
+        // InfluxDB 推送测试（本地 HTTP 替身）
+        self.test_influx_sink().await;
+
+        // MQTT 发布测试（本地 broker 替身）
+        self.test_mqtt_sink().await;
+
+        // 本地 HTTP/WebSocket API 测试
+        self.test_api_server().await;
+
+        // API 鉴权/作用域/限流测试
+        self.test_api_auth().await;
+
+        // 无界面 agent（采集核心 + 本地 API）测试
+        self.test_headless_agent().await;
+
+        // 命令行工具（参数解析、表格渲染、历史查询、RTT）测试
+        self.test_cli().await;
+
+        // 告警规则引擎（持续时间/滞回/变化率/热更新）测试
+        self.test_alert_engine().await;
+
+        // 告警通知 Sink（Webhook/SMTP/脚本 + 去重限流）测试
+        self.test_alert_notify().await;
+
+        // 告警日志与确认/静默测试
+        self.test_alert_journal().await;
+
+        // 统计异常检测（EWMA/日周期/负载回归基线）测试
+        self.test_anomaly_detector().await;
+
+        // 桥接协议握手/兼容矩阵/解析模式测试
+        self.test_bridge_protocol().await;
+
+        // 桥接看门狗（输出/心跳新鲜度与指数退避）测试
+        self.test_bridge_watchdog().await;
+
+        // 桥接命令通道（请求/响应、超时与错误）测试
+        self.test_bridge_command().await;
+
+        // 桥接输出录制与回放测试
+        self.test_bridge_replay().await;
+
+        // 合成传感器模拟器测试
+        self.test_sensor_sim().await;
+
         // 12. 基本功能测试
         self.test_basic_functionality().await;
 
@@ -136,8 +211,11 @@ impl TestRunner {
         };
 
         // 加载配置：优先尝试当前目录 config.json，不存在则使用默认
-        let mut targets: Vec<String> = Vec::new();
+        let mut targets: Vec<RttTarget> = Vec::new();
         let mut timeout_ms: u64 = 300;
+        let mut probe_count: u32 = crate::ping_utils::DEFAULT_PROBE_COUNT;
+        let mut probe_interval_ms: u64 = crate::ping_utils::DEFAULT_PROBE_INTERVAL_MS;
+        let mut family = FamilyPref::default();
         let mut cfg_source = "default".to_string();
 
         // 候选路径：./config.json 与 ./src-tauri/config.json
@@ -151,6 +229,9 @@ impl TestRunner {
                     if let Ok(cfg) = serde_json::from_str::<AppConfig>(&content) {
                         if let Some(t) = cfg.rtt_targets { targets = t; }
                         if let Some(tmo) = cfg.rtt_timeout_ms { timeout_ms = tmo; }
+                        if let Some(n) = cfg.rtt_probe_count { probe_count = n; }
+                        if let Some(ms) = cfg.rtt_probe_interval_ms { probe_interval_ms = ms; }
+                        if let Some(f) = cfg.rtt_family { family = f; }
                         cfg_source = path.to_string_lossy().to_string();
                         break;
                     }
@@ -160,13 +241,14 @@ impl TestRunner {
 
         if targets.is_empty() {
             targets = vec![
-                "114.114.114.114:443".to_string(),
-                "223.5.5.5:443".to_string(),
+                RttTarget::new("114.114.114.114:443"),
+                RttTarget::new("223.5.5.5:443"),
             ];
         }
 
         // 执行多目标 RTT 测量
-        let results = measure_multi_rtt(&targets, timeout_ms);
+        let defaults = ProbeDefaults { timeout_ms, count: probe_count, interval_ms: probe_interval_ms, family };
+        let results = measure_multi_rtt(&targets, &defaults);
         let total = results.len();
         let success_cnt = results.iter().filter(|r| r.rtt_ms.is_some()).count();
         let lats: Vec<f64> = results.iter().filter_map(|r| r.rtt_ms).collect();
@@ -188,8 +270,9 @@ impl TestRunner {
         // 写入详情
         if let Some(map) = test.details.as_mut() {
             map.insert("rtt_config_source".to_string(), cfg_source);
-            map.insert("rtt_targets".to_string(), targets.join(", "));
+            map.insert("rtt_targets".to_string(), targets.iter().map(|t| t.target.as_str()).collect::<Vec<_>>().join(", "));
             map.insert("rtt_timeout_ms".to_string(), timeout_ms.to_string());
+            map.insert("rtt_probe_count".to_string(), probe_count.to_string());
             let summary = match (min_ms, avg_ms) {
                 (Some(mi), Some(av)) => format!("{}个目标，成功{}个，min={:.1}ms，avg={:.1}ms", total, success_cnt, mi, av),
                 _ => format!("{}个目标，成功{}个，无有效RTT", total, success_cnt),
@@ -1343,6 +1426,677 @@ impl TestRunner {
         ))
     }
 
+    async fn test_influx_sink(&mut self) {
+        let start = Instant::now();
+        let mut test = TestResult {
+            test_name: "InfluxDB推送测试".to_string(),
+            success: false,
+            message: "".to_string(),
+            duration_ms: 0,
+            details: Some(HashMap::new()),
+            error_details: None,
+        };
+
+        match self.run_influx_sink_test().await {
+            Ok(info) => {
+                test.success = true;
+                test.message = "line protocol 编码与 HTTP 写入正常".to_string();
+                test.details.as_mut().unwrap().insert("influx_info".to_string(), info);
+            }
+            Err(e) => {
+                test.success = false;
+                test.message = "InfluxDB推送测试失败".to_string();
+                test.error_details = Some(e.to_string());
+            }
+        }
+
+        test.duration_ms = start.elapsed().as_millis() as u64;
+        self.test_results.push(test);
+    }
+
+    async fn run_influx_sink_test(&self) -> Result<String, Box<dyn std::error::Error>> {
+        use crate::influx_sink::{encode_snapshot_lines, write_http, InfluxConfig};
+
+        // 本地替身：接收一次写入请求并返回 204
+        let (port, rx) = spawn_http_stub("HTTP/1.1 204 No Content")?;
+        let mut tags = HashMap::new();
+        tags.insert("site".to_string(), "lab 1".to_string());
+        let cfg = InfluxConfig {
+            enabled: true,
+            url: Some(format!("http://127.0.0.1:{}", port)),
+            org: Some("my org".to_string()),
+            bucket: Some("sensors".to_string()),
+            token: Some("test-token".to_string()),
+            tags: Some(tags),
+            ..Default::default()
+        };
+        let snap = crate::types::SensorSnapshot {
+            cpu_usage: 42.5,
+            mem_pct: 61.0,
+            cpu_temp_c: Some(55.0),
+            rtt_multi: Some(vec![crate::process_utils::RttResultPayload {
+                target: "1.1.1.1:443".to_string(),
+                rtt_ms: Some(12.5),
+                success: Some(true),
+                ..Default::default()
+            }]),
+            timestamp_ms: 1_700_000_000_000,
+            ..Default::default()
+        };
+
+        let lines = encode_snapshot_lines(&cfg, &snap, "test host");
+        if lines.len() != 2 {
+            return Err(format!("期望 2 行（system + rtt），实际 {} 行", lines.len()).into());
+        }
+        if !lines[0].starts_with("sys_sensor,host=test\\ host,site=lab\\ 1 ") || !lines[0].contains("cpu_usage=42.5") {
+            return Err(format!("system 行格式错误: {}", lines[0]).into());
+        }
+        if !lines[1].starts_with("sys_sensor_rtt,") || !lines[1].contains("success=true") {
+            return Err(format!("rtt 行格式错误: {}", lines[1]).into());
+        }
+
+        write_http(&cfg, &lines.join("\n")).map_err(|e| e.to_string())?;
+        let (head, body) = rx.recv_timeout(Duration::from_secs(3))?;
+        let request_line = head.lines().next().unwrap_or("").to_string();
+        if !request_line.starts_with("POST /api/v2/write?precision=ms&org=my%20org&bucket=sensors") {
+            return Err(format!("请求路径错误: {}", request_line).into());
+        }
+        if !head.to_ascii_lowercase().contains("authorization: token test-token") {
+            return Err("缺少 Authorization 头".into());
+        }
+        let body = String::from_utf8_lossy(&body).to_string();
+        if body.lines().count() != 2 {
+            return Err(format!("请求体行数错误: {}", body).into());
+        }
+
+        // UDP 监听端没有精度参数，按纳秒解析时间戳
+        let udp = std::net::UdpSocket::bind("127.0.0.1:0")?;
+        udp.set_read_timeout(Some(Duration::from_secs(3)))?;
+        let udp_cfg = InfluxConfig { transport: Some("udp".to_string()), udp_addr: Some(udp.local_addr()?.to_string()), ..cfg.clone() };
+        let udp_lines = encode_snapshot_lines(&udp_cfg, &snap, "test host");
+        if udp_lines.len() != 2 || !udp_lines.iter().all(|l| l.ends_with(" 1700000000000000000")) {
+            return Err(format!("UDP 行时间戳应为纳秒: {:?}", udp_lines).into());
+        }
+        if !lines[0].ends_with(" 1700000000000") {
+            return Err(format!("HTTP 行时间戳应为毫秒: {}", lines[0]).into());
+        }
+        crate::influx_sink::write_udp(&udp_cfg, &udp_lines.join("\n")).map_err(|e| e.to_string())?;
+        let mut dgram = [0u8; 8192];
+        let n = udp.recv(&mut dgram)?;
+        if String::from_utf8_lossy(&dgram[..n]) != format!("{}\n", udp_lines.join("\n")) {
+            return Err(format!("UDP 数据报内容错误: {}", String::from_utf8_lossy(&dgram[..n])).into());
+        }
+
+        // 401/403 是可修正的配置错误，spool 必须保留；其余 4xx 视为数据错误丢弃
+        let spool = std::env::temp_dir().join(format!("sys-sensor-influx-spool-{}.lp", std::process::id()));
+        std::fs::write(&spool, format!("{}\n", lines.join("\n")))?;
+        let (port, _rx) = spawn_http_stub("HTTP/1.1 401 Unauthorized")?;
+        let auth_cfg = InfluxConfig { url: Some(format!("http://127.0.0.1:{}", port)), max_retries: Some(0), ..cfg.clone() };
+        let replay = crate::influx_sink::spool_replay(&auth_cfg, &spool, 500);
+        let kept = std::fs::read_to_string(&spool).unwrap_or_default();
+        if !matches!(replay, Err(crate::influx_sink::WriteError::Auth(_))) || kept.lines().count() != 2 {
+            let _ = std::fs::remove_file(&spool);
+            return Err(format!("鉴权失败不应丢弃 spool: {:?}，剩余 {} 行", replay, kept.lines().count()).into());
+        }
+        let (port, _rx) = spawn_http_stub("HTTP/1.1 400 Bad Request")?;
+        let bad_cfg = InfluxConfig { url: Some(format!("http://127.0.0.1:{}", port)), ..auth_cfg };
+        let replay = crate::influx_sink::spool_replay(&bad_cfg, &spool, 500);
+        let left = spool.exists();
+        let _ = std::fs::remove_file(&spool);
+        if !matches!(replay, Ok(2)) || left {
+            return Err(format!("数据错误的 spool 块应被丢弃: {:?}", replay).into());
+        }
+
+        // 手写的不完整配置（缺 enabled、规则缺 threshold）不能导致整份 config.json 回退默认值
+        let dir = std::env::temp_dir().join(format!("sys-sensor-partial-cfg-{}", std::process::id()));
+        std::fs::create_dir_all(&dir)?;
+        let path = dir.join("config.json");
+        std::fs::write(&path, r#"{"top_n": 7, "influx": {"url": "http://127.0.0.1:8086"}, "mqtt": {"host": "broker"}, "api": {"port": 18800}, "alerts": {"rules": [{"id": "r1", "metric": "cpu_usage"}]}}"#)?;
+        let loaded = crate::config_utils::load_config_from(&path);
+        let _ = std::fs::remove_dir_all(&dir);
+        if loaded.top_n != Some(7) {
+            return Err("部分配置解析失败，其余设置被丢弃".into());
+        }
+        if loaded.influx.as_ref().map(|c| c.enabled) != Some(false) || loaded.alerts.as_ref().and_then(|a| a.rules.as_ref()).map(|r| r.len()) != Some(1) {
+            return Err("缺省字段未按默认值补齐".into());
+        }
+
+        Ok(format!("编码 {} 行，替身服务收到 {} 字节", lines.len(), body.len()))
+    }
+
+    async fn test_mqtt_sink(&mut self) {
+        let start = Instant::now();
+        let mut test = TestResult {
+            test_name: "MQTT发布测试".to_string(),
+            success: false,
+            message: "".to_string(),
+            duration_ms: 0,
+            details: Some(HashMap::new()),
+            error_details: None,
+        };
+
+        match self.run_mqtt_sink_test().await {
+            Ok(info) => {
+                test.success = true;
+                test.message = "MQTT 握手、自动发现与状态发布正常".to_string();
+                test.details.as_mut().unwrap().insert("mqtt_info".to_string(), info);
+            }
+            Err(e) => {
+                test.success = false;
+                test.message = "MQTT发布测试失败".to_string();
+                test.error_details = Some(e.to_string());
+            }
+        }
+
+        test.duration_ms = start.elapsed().as_millis() as u64;
+        self.test_results.push(test);
+    }
+
+    async fn run_mqtt_sink_test(&self) -> Result<String, Box<dyn std::error::Error>> {
+        use crate::mqtt_sink::{decode_packet, discovery_messages, resolve_topics, MqttConfig, MqttConn, MqttMetric};
+        use std::io::{Read, Write};
+
+        // 本地 broker 替身：回复 CONNACK，收集 CONNECT 报文与 PUBLISH 主题
+        let listener = std::net::TcpListener::bind("127.0.0.1:0")?;
+        let port = listener.local_addr()?.port();
+        let (tx, rx) = std::sync::mpsc::channel::<(u8, Vec<u8>)>();
+        std::thread::spawn(move || {
+            let Ok((mut stream, _)) = listener.accept() else { return };
+            let _ = stream.set_read_timeout(Some(Duration::from_secs(3)));
+            let mut buf: Vec<u8> = Vec::new();
+            let mut tmp = [0u8; 4096];
+            loop {
+                while let Some((kind, body, used)) = decode_packet(&buf) {
+                    buf.drain(..used);
+                    if kind == 1 { let _ = stream.write_all(&[0x20, 0x02, 0x00, 0x00]); }
+                    if kind == 14 || tx.send((kind, body)).is_err() { return; }
+                }
+                match stream.read(&mut tmp) {
+                    Ok(0) | Err(_) => return,
+                    Ok(n) => buf.extend_from_slice(&tmp[..n]),
+                }
+            }
+        });
+
+        let cfg = MqttConfig {
+            enabled: true,
+            host: Some("127.0.0.1".to_string()),
+            port: Some(port),
+            username: Some("user".to_string()),
+            password: Some("secret".to_string()),
+            node_id: Some("test-node".to_string()),
+            metrics: Some(vec![
+                MqttMetric { key: "cpu_usage".to_string(), ..Default::default() },
+                MqttMetric { key: "cpu_temp_c".to_string(), topic: Some("custom/temp".to_string()), ..Default::default() },
+            ]),
+            ..Default::default()
+        };
+        let topics = resolve_topics(&cfg, "test host");
+        if topics.availability != "sys-sensor/test-node/availability" {
+            return Err(format!("availability 主题错误: {}", topics.availability).into());
+        }
+        let discovery = discovery_messages(&cfg, &topics, "test host");
+        if discovery.len() != 2 || discovery[0].0 != "homeassistant/sensor/test-node/cpu_usage/config" {
+            return Err(format!("discovery 主题错误: {:?}", discovery.iter().map(|d| &d.0).collect::<Vec<_>>()).into());
+        }
+        let temp_cfg: serde_json::Value = serde_json::from_str(&discovery[1].1)?;
+        if temp_cfg["device_class"] != "temperature" || temp_cfg["state_topic"] != "custom/temp" || temp_cfg["availability_topic"] != topics.availability {
+            return Err(format!("discovery 内容错误: {}", discovery[1].1).into());
+        }
+
+        let mut conn = MqttConn::connect(&cfg, &topics).map_err(|e| e.to_string())?;
+        conn.publish(&topics.availability, "online", true).map_err(|e| e.to_string())?;
+        for (t, p) in &discovery { conn.publish(t, p, true).map_err(|e| e.to_string())?; }
+        conn.publish("sys-sensor/test-node/cpu_usage", "42.50", false).map_err(|e| e.to_string())?;
+        conn.disconnect(&topics.availability);
+
+        let (kind, connect) = rx.recv_timeout(Duration::from_secs(3))?;
+        if kind != 1 {
+            return Err(format!("首个报文应为 CONNECT，实际类型 {}", kind).into());
+        }
+        // 标志位：用户名 + 密码 + will retain + will + clean session
+        if connect.get(7) != Some(&0xE6) {
+            return Err(format!("CONNECT 标志位错误: {:?}", connect.get(7)).into());
+        }
+        let connect_text = String::from_utf8_lossy(&connect).to_string();
+        if !connect_text.contains("sys-sensor/test-node/availability") || !connect_text.contains("offline") {
+            return Err("CONNECT 缺少遗嘱消息".into());
+        }
+
+        let mut published: Vec<String> = Vec::new();
+        while let Ok((kind, body)) = rx.recv_timeout(Duration::from_secs(3)) {
+            if kind != 3 || body.len() < 2 { continue; }
+            let len = u16::from_be_bytes([body[0], body[1]]) as usize;
+            published.push(String::from_utf8_lossy(&body[2..2 + len]).to_string());
+            if published.len() == 5 { break; }
+        }
+        let expected = [
+            topics.availability.as_str(),
+            "homeassistant/sensor/test-node/cpu_usage/config",
+            "homeassistant/sensor/test-node/cpu_temp_c/config",
+            "sys-sensor/test-node/cpu_usage",
+            topics.availability.as_str(),
+        ];
+        if published != expected {
+            return Err(format!("发布主题序列错误: {:?}", published).into());
+        }
+
+        // 发布线程：node_id 变更后应向旧的 availability 主题发送 offline，再以新主题重连
+        let listener = std::net::TcpListener::bind("127.0.0.1:0")?;
+        let port = listener.local_addr()?.port();
+        let seen: std::sync::Arc<std::sync::Mutex<Vec<(String, String)>>> = Default::default();
+        let seen_c = seen.clone();
+        std::thread::spawn(move || {
+            for stream in listener.incoming().take(2) {
+                let Ok(mut stream) = stream else { return };
+                let seen_c = seen_c.clone();
+                std::thread::spawn(move || {
+                    let _ = stream.set_read_timeout(Some(Duration::from_secs(5)));
+                    let mut buf: Vec<u8> = Vec::new();
+                    let mut tmp = [0u8; 4096];
+                    loop {
+                        while let Some((kind, body, used)) = decode_packet(&buf) {
+                            buf.drain(..used);
+                            if kind == 1 { let _ = stream.write_all(&[0x20, 0x02, 0x00, 0x00]); }
+                            if kind == 3 && body.len() >= 2 {
+                                let len = u16::from_be_bytes([body[0], body[1]]) as usize;
+                                let topic = String::from_utf8_lossy(&body[2..2 + len]).to_string();
+                                let payload = String::from_utf8_lossy(&body[2 + len..]).to_string();
+                                seen_c.lock().unwrap().push((topic, payload));
+                            }
+                            if kind == 14 { return; }
+                        }
+                        match stream.read(&mut tmp) {
+                            Ok(0) | Err(_) => return,
+                            Ok(n) => buf.extend_from_slice(&tmp[..n]),
+                        }
+                    }
+                });
+            }
+        });
+        let sink_cfg = MqttConfig { port: Some(port), discovery_enabled: Some(false), node_id: Some("old-node".to_string()), ..cfg.clone() };
+        let cfg_state = std::sync::Arc::new(std::sync::Mutex::new(AppConfig { mqtt: Some(sink_cfg.clone()), ..Default::default() }));
+        let _sink = crate::mqtt_sink::start_mqtt_sink(cfg_state.clone());
+        let wait_for = |topic: &str, payload: &str| {
+            let deadline = Instant::now() + Duration::from_secs(5);
+            while Instant::now() < deadline {
+                if seen.lock().unwrap().iter().any(|(t, p)| t == topic && p == payload) { return true; }
+                std::thread::sleep(Duration::from_millis(50));
+            }
+            false
+        };
+        if !wait_for("sys-sensor/old-node/availability", "online") {
+            return Err("发布线程未上线".into());
+        }
+        cfg_state.lock().unwrap().mqtt = Some(MqttConfig { node_id: Some("new-node".to_string()), ..sink_cfg });
+        if !wait_for("sys-sensor/new-node/availability", "online") {
+            return Err("配置变更后未以新主题重连".into());
+        }
+        let seen = seen.lock().unwrap().clone();
+        if !seen.iter().any(|(t, p)| t == "sys-sensor/old-node/availability" && p == "offline") {
+            return Err(format!("旧 availability 主题未收到 offline: {:?}", seen).into());
+        }
+        if seen.iter().any(|(t, p)| t == "sys-sensor/new-node/availability" && p == "offline") {
+            return Err("offline 被发送到新主题".into());
+        }
+
+        // 持续 QoS0 发布时 broker 不回任何报文：须按入站静默发送 PINGREQ，收到 PINGRESP 后不得判定心跳超时
+        let listener = std::net::TcpListener::bind("127.0.0.1:0")?;
+        let port = listener.local_addr()?.port();
+        let pings = std::sync::Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let pings_c = pings.clone();
+        std::thread::spawn(move || {
+            let Ok((mut stream, _)) = listener.accept() else { return };
+            let _ = stream.set_read_timeout(Some(Duration::from_secs(10)));
+            let mut buf: Vec<u8> = Vec::new();
+            let mut tmp = [0u8; 4096];
+            loop {
+                while let Some((kind, _body, used)) = decode_packet(&buf) {
+                    buf.drain(..used);
+                    if kind == 1 { let _ = stream.write_all(&[0x20, 0x02, 0x00, 0x00]); }
+                    if kind == 12 {
+                        pings_c.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                        let _ = stream.write_all(&[0xD0, 0x00]);
+                    }
+                }
+                match stream.read(&mut tmp) {
+                    Ok(0) | Err(_) => return,
+                    Ok(n) => buf.extend_from_slice(&tmp[..n]),
+                }
+            }
+        });
+        let ka_cfg = MqttConfig { port: Some(port), keepalive_sec: Some(5), ..cfg.clone() };
+        let mut conn = MqttConn::connect(&ka_cfg, &resolve_topics(&ka_cfg, "test host"))?;
+        let started = Instant::now();
+        while started.elapsed() < Duration::from_secs(8) {
+            conn.publish("sys-sensor/test-node/cpu_usage", "1", false)?;
+            conn.poll().map_err(|e| format!("持续发布 {:?} 后断开: {}", started.elapsed(), e))?;
+            std::thread::sleep(Duration::from_millis(100));
+        }
+        if pings.load(std::sync::atomic::Ordering::SeqCst) == 0 {
+            return Err("持续发布期间未发送 PINGREQ".into());
+        }
+
+        Ok(format!("替身 broker 收到 {} 条 PUBLISH，discovery {} 条", published.len(), discovery.len()))
+    }
+
+    async fn test_api_server(&mut self) {
+        let start = Instant::now();
+        let mut test = TestResult {
+            test_name: "本地API测试".to_string(),
+            success: false,
+            message: "".to_string(),
+            duration_ms: 0,
+            details: Some(HashMap::new()),
+            error_details: None,
+        };
+
+        match self.run_api_server_test().await {
+            Ok(info) => {
+                test.success = true;
+                test.message = "HTTP 查询与 WebSocket 订阅正常".to_string();
+                test.details.as_mut().unwrap().insert("api_info".to_string(), info);
+            }
+            Err(e) => {
+                test.success = false;
+                test.message = "本地API测试失败".to_string();
+                test.error_details = Some(e.to_string());
+            }
+        }
+
+        test.duration_ms = start.elapsed().as_millis() as u64;
+        self.test_results.push(test);
+    }
+
+    async fn run_api_server_test(&self) -> Result<String, Box<dyn std::error::Error>> {
+        use crate::api_server::{decode_ws_frame, serve_listener, websocket_accept_key, ApiContext};
+        use std::io::{Read, Write};
+        use std::sync::{Arc, Mutex};
+
+        // RFC 6455 示例握手
+        if websocket_accept_key("dGhlIHNhbXBsZSBub25jZQ==") != "s3pPLMBiTxaQ9kYGzzhZRbK+xOo=" {
+            return Err("Sec-WebSocket-Accept 计算错误".into());
+        }
+
+        let cfg = crate::config_utils::AppConfig {
+            influx: Some(crate::influx_sink::InfluxConfig { token: Some("secret-token".to_string()), ..Default::default() }),
+            ..Default::default()
+        };
+        let mut store = crate::state_store::StateStore::new();
+        for i in 0..5 {
+            store.push_history(1_000 + i, vec![("cpu_usage", 10.0 + i as f64), ("mem_pct", 50.0)]);
+        }
+        let dir = std::env::temp_dir().join(format!("sys-sensor-api-test-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let reader = crate::api_auth::create_token(&dir, "config-reader", vec![crate::api_auth::Scope::ReadConfig], None)?;
+        let ctx = ApiContext {
+            config: Arc::new(Mutex::new(cfg)),
+            scheduler: Arc::new(Mutex::new(crate::scheduler::SchedulerState::default())),
+            state_store: Arc::new(Mutex::new(store)),
+            config_dir: dir.clone(),
+            trigger_task: None,
+            on_config_changed: None,
+        };
+        let config = ctx.config.clone();
+        let handle = serve_listener(std::net::TcpListener::bind("127.0.0.1:0")?, ctx, None).map_err(|e| e.to_string())?;
+        let base = format!("http://{}", handle.addr);
+
+        let snap = crate::types::SensorSnapshot { cpu_usage: 33.0, timestamp_ms: 1_004, ..Default::default() };
+        crate::event_hub::publish("snapshot", &snap);
+        let got: serde_json::Value = ureq::get(&format!("{}/api/snapshot", base)).call()?.into_json()?;
+        if got["cpu_usage"].as_f64() != Some(33.0) {
+            return Err(format!("/api/snapshot 内容错误: {}", got).into());
+        }
+
+        let hist: serde_json::Value = ureq::get(&format!("{}/api/history?metrics=cpu_usage&limit=2", base)).call()?.into_json()?;
+        let points = hist["points"].as_array().cloned().unwrap_or_default();
+        if points.len() != 2 || points[1]["values"]["cpu_usage"].as_f64() != Some(14.0) || points[1]["values"].get("mem_pct").is_some() {
+            return Err(format!("/api/history 内容错误: {}", hist).into());
+        }
+
+        // 回环匿名调用方只有 metrics:read；默认不返回 CORS 头，仅放行 cors_origin 中列出的来源
+        match ureq::get(&format!("{}/api/config", base)).call() {
+            Err(ureq::Error::Status(403, _)) => {}
+            other => return Err(format!("匿名读取配置应返回 403: {:?}", other.map(|r| r.status())).into()),
+        }
+        let resp = ureq::get(&format!("{}/api/tick", base)).set("Origin", "https://evil.example").call()?;
+        if resp.header("Access-Control-Allow-Origin").is_some() {
+            return Err("默认配置不应返回 CORS 头".into());
+        }
+        config.lock().map_err(|_| "lock failed")?.api = Some(crate::api_server::ApiConfig { cors_origin: Some("http://localhost:5173, https://obs.local".to_string()), ..Default::default() });
+        let resp = ureq::get(&format!("{}/api/tick", base)).set("Origin", "https://obs.local").call()?;
+        if resp.header("Access-Control-Allow-Origin") != Some("https://obs.local") {
+            return Err("已列出的来源未放行".into());
+        }
+        let resp = ureq::get(&format!("{}/api/tick", base)).set("Origin", "https://evil.example").call()?;
+        if resp.header("Access-Control-Allow-Origin").is_some() {
+            return Err("未列出的来源被放行".into());
+        }
+
+        let cfg_json: serde_json::Value = ureq::get(&format!("{}/api/config", base)).set("Authorization", &format!("Bearer {}", reader.token)).call()?.into_json()?;
+        if cfg_json["influx"]["token"] != "***" {
+            return Err(format!("/api/config 未脱敏: {}", cfg_json["influx"]).into());
+        }
+
+        // 脱敏范围：HTTP 头、URL userinfo/查询串、用户名、脚本命令；原样回写时保留旧值
+        let secret_cfg = crate::config_utils::AppConfig {
+            influx: Some(crate::influx_sink::InfluxConfig { url: Some("https://u:p@influx.local:8086/api?key=k".to_string()), org: Some("acme".to_string()), ..Default::default() }),
+            mqtt: Some(crate::mqtt_sink::MqttConfig { username: Some("mq-user".to_string()), password: Some("mq-pass".to_string()), ..Default::default() }),
+            alerts: Some(crate::alert_engine::AlertConfig {
+                sinks: Some(vec![crate::alert_notify::NotifySinkConfig {
+                    id: "hook".to_string(),
+                    kind: crate::alert_notify::NotifyKind::Webhook,
+                    url: Some("https://hooks.example.com/services/T0/B0/XXXX".to_string()),
+                    headers: Some(HashMap::from([("Authorization".to_string(), "Bearer abc".to_string())])),
+                    ..Default::default()
+                }]),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let original = serde_json::to_value(&secret_cfg)?;
+        let mut redacted = original.clone();
+        crate::api_server::redact_secrets(&mut redacted);
+        let text = redacted.to_string();
+        for leaked in ["u:p@", "key=k", "acme", "mq-user", "mq-pass", "Bearer abc", "XXXX"] {
+            if text.contains(leaked) {
+                return Err(format!("脱敏后仍包含 {}: {}", leaked, text).into());
+            }
+        }
+        if redacted["influx"]["url"] != "https://***@influx.local:8086/api?***" || redacted["alerts"]["sinks"][0]["url"] != "https://hooks.example.com/***" {
+            return Err(format!("URL 脱敏格式错误: {}", text).into());
+        }
+        let mut patch = serde_json::json!({ "influx": redacted["influx"].clone(), "alerts": redacted["alerts"].clone() });
+        patch["alerts"]["sinks"][0]["headers"]["X-Extra"] = serde_json::json!("1");
+        crate::api_server::restore_redacted(&mut patch, &original);
+        if patch["influx"] != original["influx"] || patch["alerts"]["sinks"][0]["url"] != original["alerts"]["sinks"][0]["url"]
+            || patch["alerts"]["sinks"][0]["headers"]["Authorization"] != "Bearer abc" || patch["alerts"]["sinks"][0]["headers"]["X-Extra"] != "1" {
+            return Err(format!("回写脱敏配置覆盖了原值: {}", patch).into());
+        }
+
+        // 缺少 Sec-WebSocket-Key：400 响应头须完整（沿用请求来源的 CORS 头）
+        let mut bad = std::net::TcpStream::connect(handle.addr)?;
+        bad.set_read_timeout(Some(Duration::from_secs(3)))?;
+        bad.write_all(b"GET /ws HTTP/1.1\r\nHost: localhost\r\nOrigin: https://obs.local\r\nUpgrade: websocket\r\nConnection: Upgrade\r\n\r\n")?;
+        let mut resp = String::new();
+        let _ = bad.read_to_string(&mut resp);
+        let head = resp.split("\r\n\r\n").next().unwrap_or("");
+        if !head.starts_with("HTTP/1.1 400") || !head.contains("\r\nAccess-Control-Allow-Origin: https://obs.local\r\n") || !head.contains("\r\nCache-Control: no-store\r\n") {
+            return Err(format!("缺少握手密钥的响应头错误: {:?}", head).into());
+        }
+
+        // WebSocket：握手 -> 订阅 agg -> 收到事件
+        let mut ws = std::net::TcpStream::connect(handle.addr)?;
+        ws.set_read_timeout(Some(Duration::from_secs(3)))?;
+        ws.write_all(b"GET /ws HTTP/1.1\r\nHost: localhost\r\nUpgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\nSec-WebSocket-Version: 13\r\n\r\n")?;
+        let mut buf: Vec<u8> = Vec::new();
+        let mut tmp = [0u8; 4096];
+        let head_end = loop {
+            if let Some(pos) = buf.windows(4).position(|w| w == b"\r\n\r\n") { break pos + 4; }
+            let n = ws.read(&mut tmp)?;
+            if n == 0 { return Err("握手期间连接关闭".into()); }
+            buf.extend_from_slice(&tmp[..n]);
+        };
+        let head = String::from_utf8_lossy(&buf[..head_end]).to_string();
+        if !head.starts_with("HTTP/1.1 101") || !head.contains("s3pPLMBiTxaQ9kYGzzhZRbK+xOo=") {
+            return Err(format!("WebSocket 握手失败: {}", head).into());
+        }
+        buf.drain(..head_end);
+
+        // 客户端帧需掩码
+        let sub = br#"{"op":"subscribe","streams":["agg"]}"#;
+        let mask = [0x11u8, 0x22, 0x33, 0x44];
+        let mut frame = vec![0x81, 0x80 | sub.len() as u8];
+        frame.extend_from_slice(&mask);
+        frame.extend(sub.iter().enumerate().map(|(i, b)| b ^ mask[i % 4]));
+        ws.write_all(&frame)?;
+
+        let mut messages: Vec<serde_json::Value> = Vec::new();
+        let deadline = Instant::now() + Duration::from_secs(3);
+        let mut published = false;
+        while Instant::now() < deadline {
+            while let Some((_op, payload, _masked, used)) = decode_ws_frame(&buf)? {
+                buf.drain(..used);
+                messages.push(serde_json::from_slice(&payload)?);
+            }
+            if !published && messages.iter().any(|m| m["type"] == "subscribed") {
+                crate::event_hub::publish("snapshot", &snap);
+                crate::event_hub::publish("agg", &serde_json::json!({ "cpu_usage": 12.5 }));
+                published = true;
+            }
+            if messages.iter().any(|m| m["type"] == "event" && m["data"]["cpu_usage"] == 12.5) { break; }
+            let n = ws.read(&mut tmp)?;
+            if n == 0 { break; }
+            buf.extend_from_slice(&tmp[..n]);
+        }
+        handle.stop();
+        let _ = std::fs::remove_dir_all(&dir);
+        if messages.iter().any(|m| m["stream"] == "snapshot") {
+            return Err("收到未订阅的 snapshot 事件".into());
+        }
+        if !messages.iter().any(|m| m["type"] == "event" && m["stream"] == "agg" && m["data"]["cpu_usage"] == 12.5) {
+            return Err(format!("未收到 agg 事件: {:?}", messages).into());
+        }
+
+        Ok(format!("HTTP 3 个路由正常，WebSocket 收到 {} 条消息", messages.len()))
+    }
+
+    async fn test_api_auth(&mut self) {
+        let start = Instant::now();
+        let mut test = TestResult {
+            test_name: "API鉴权测试".to_string(),
+            success: false,
+            message: "".to_string(),
+            duration_ms: 0,
+            details: Some(HashMap::new()),
+            error_details: None,
+        };
+
+        match self.run_api_auth_test().await {
+            Ok(info) => {
+                test.success = true;
+                test.message = "Token 鉴权、作用域与限流正常".to_string();
+                test.details.as_mut().unwrap().insert("auth_info".to_string(), info);
+            }
+            Err(e) => {
+                test.success = false;
+                test.message = "API鉴权测试失败".to_string();
+                test.error_details = Some(e.to_string());
+            }
+        }
+
+        test.duration_ms = start.elapsed().as_millis() as u64;
+        self.test_results.push(test);
+    }
+
+    async fn run_api_auth_test(&self) -> Result<String, Box<dyn std::error::Error>> {
+        use crate::api_auth::{create_token, list_tokens, revoke_token, Scope};
+        use crate::api_server::{serve_listener, ApiConfig, ApiContext};
+        use std::sync::{Arc, Mutex};
+
+        let dir = std::env::temp_dir().join(format!("sys-sensor-auth-test-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let reader = create_token(&dir, "reader", vec![Scope::ReadMetrics], None)?;
+        let admin = create_token(&dir, "admin", vec![Scope::ReadMetrics, Scope::ReadConfig, Scope::WriteConfig, Scope::TriggerTasks], None)?;
+        let stored = std::fs::read_to_string(dir.join("api_tokens.json"))?;
+        if stored.contains(&reader.token) || stored.contains(&admin.token) {
+            return Err("Token 明文被写入磁盘".into());
+        }
+
+        let cfg = crate::config_utils::AppConfig {
+            api: Some(ApiConfig { enabled: true, require_auth: Some(true), rate_limit_per_min: Some(60), rate_limit_burst: Some(8), ..Default::default() }),
+            ..Default::default()
+        };
+        let triggered: Arc<Mutex<Vec<String>>> = Arc::new(Mutex::new(Vec::new()));
+        let triggered_c = triggered.clone();
+        let ctx = ApiContext {
+            config: Arc::new(Mutex::new(cfg)),
+            scheduler: Arc::new(Mutex::new(crate::scheduler::SchedulerState::default())),
+            state_store: Arc::new(Mutex::new(crate::state_store::StateStore::new())),
+            config_dir: dir.clone(),
+            trigger_task: Some(Arc::new(move |kind: &str| {
+                triggered_c.lock().map_err(|_| "lock failed".to_string())?.push(kind.to_string());
+                Ok(())
+            })),
+            on_config_changed: None,
+        };
+        let config = ctx.config.clone();
+        let handle = serve_listener(std::net::TcpListener::bind("127.0.0.1:0")?, ctx, None).map_err(|e| e.to_string())?;
+        let base = format!("http://{}", handle.addr);
+        let status = |r: Result<ureq::Response, ureq::Error>| match r {
+            Ok(resp) => resp.status(),
+            Err(ureq::Error::Status(code, _)) => code,
+            Err(_) => 0,
+        };
+        let bearer = |t: &str| format!("Bearer {}", t);
+
+        let checks = [
+            ("匿名访问", status(ureq::get(&format!("{}/api/tick", base)).call()), 401),
+            ("无效 Token", status(ureq::get(&format!("{}/api/tick", base)).set("Authorization", "Bearer sst_bogus").call()), 401),
+            ("只读 Token 读指标", status(ureq::get(&format!("{}/api/tick", base)).set("Authorization", &bearer(&reader.token)).call()), 200),
+            ("只读 Token 读配置", status(ureq::get(&format!("{}/api/config", base)).set("Authorization", &bearer(&reader.token)).call()), 403),
+            ("query 参数 Token", status(ureq::get(&format!("{}/api/tick?access_token={}", base, reader.token)).call()), 200),
+            ("管理 Token 写配置", status(ureq::request("PATCH", &format!("{}/api/config", base)).set("Authorization", &bearer(&admin.token)).send_string(r#"{"top_n":7}"#)), 200),
+            ("管理 Token 触发任务", status(ureq::post(&format!("{}/api/tasks/rtt/trigger", base)).set("Authorization", &bearer(&admin.token)).call()), 200),
+        ];
+        for (name, got, want) in checks.iter() {
+            if got != want {
+                return Err(format!("{}: 期望 {}，实际 {}", name, want, got).into());
+            }
+        }
+        if config.lock().map_err(|_| "lock failed")?.top_n != Some(7) {
+            return Err("PATCH /api/config 未生效".into());
+        }
+        if !dir.join("config.json").exists() {
+            return Err("PATCH /api/config 未持久化".into());
+        }
+        if triggered.lock().map_err(|_| "lock failed")?.as_slice() != ["rtt"] {
+            return Err("任务触发回调未被调用".into());
+        }
+
+        // 突发上限 8：继续请求应很快触发 429
+        let mut limited_after = None;
+        for i in 0..20 {
+            if status(ureq::get(&format!("{}/api/tick", base)).set("Authorization", &bearer(&reader.token)).call()) == 429 {
+                limited_after = Some(i);
+                break;
+            }
+        }
+        let limited_after = limited_after.ok_or("未触发限流")?;
+
+        if !revoke_token(&dir, &reader.id)? || list_tokens(&dir)?.len() != 1 {
+            return Err("吊销 Token 失败".into());
+        }
+        let after_revoke = status(ureq::get(&format!("{}/api/tick", base)).set("Authorization", &bearer(&reader.token)).call());
+        handle.stop();
+        let _ = std::fs::remove_dir_all(&dir);
+        if after_revoke != 401 {
+            return Err(format!("吊销后仍可访问: {}", after_revoke).into());
+        }
+
+        Ok(format!("{} 项鉴权检查通过，第 {} 次追加请求触发限流", checks.len() + 1, limited_after + 1))
+    }
+
     async fn run_error_handling_test(&self) -> Result<(), Box<dyn std::error::Error>> {
         // 测试错误处理
         let result: Result<(), &str> = Err("测试错误");
@@ -1517,4 +2271,2981 @@ impl TestRunner {
         
         Ok(md)
     }
+
+    async fn test_headless_agent(&mut self) {
+        let start = Instant::now();
+        let mut test = TestResult {
+            test_name: "无界面Agent测试".to_string(),
+            success: false,
+            message: "".to_string(),
+            duration_ms: 0,
+            details: Some(HashMap::new()),
+            error_details: None,
+        };
+
+        match self.run_headless_agent_test().await {
+            Ok(info) => {
+                test.success = true;
+                test.message = "无界面采集核心运行正常".to_string();
+                test.details.as_mut().unwrap().insert("agent_info".to_string(), info);
+            }
+            Err(e) => {
+                test.success = false;
+                test.message = "无界面Agent测试失败".to_string();
+                test.error_details = Some(e.to_string());
+            }
+        }
+
+        test.duration_ms = start.elapsed().as_millis() as u64;
+        self.test_results.push(test);
+    }
+
+    async fn run_headless_agent_test(&self) -> Result<String, Box<dyn std::error::Error>> {
+        use crate::agent::{parse_args, resolve_config_dir, start_agent, AgentOptions};
+        use std::path::PathBuf;
+
+        let args = |v: &[&str]| v.iter().map(|s| s.to_string()).collect::<Vec<String>>();
+        let parsed = parse_args(args(&["--config-dir", "/srv/sensor", "--no-bridge"]))?;
+        if parsed != (AgentOptions { config_dir: Some(PathBuf::from("/srv/sensor")), no_bridge: true, ..Default::default() }) {
+            return Err(format!("参数解析错误: {:?}", parsed).into());
+        }
+        let replay = parse_args(args(&["--bridge-replay", "cap.jsonl", "--replay-speed", "10", "--replay-loop"]))?;
+        if replay.bridge_replay != Some(PathBuf::from("cap.jsonl")) || replay.replay_speed != Some(10.0) || !replay.replay_loop {
+            return Err(format!("回放参数解析错误: {:?}", replay).into());
+        }
+        if parse_args(args(&["--config-dir"])).is_ok() || parse_args(args(&["--bogus"])).is_ok() || parse_args(args(&["--replay-speed", "0"])).is_ok() {
+            return Err("非法参数未被拒绝".into());
+        }
+
+        // 临时配置目录：开启本地 API（随机端口）与历史缓冲，关闭 SMART Worker 与桥接
+        let dir = std::env::temp_dir().join(format!("sys-sensor-agent-test-{}", std::process::id()));
+        let port = std::net::TcpListener::bind("127.0.0.1:0")?.local_addr()?.port();
+        let cfg = crate::config_utils::AppConfig {
+            interval_ms: Some(200),
+            smart_enabled: Some(false),
+            history_points: Some(100),
+            api: Some(crate::api_server::ApiConfig { enabled: true, port: Some(port), ..Default::default() }),
+            ..Default::default()
+        };
+        crate::config_utils::save_config_to(&dir.join("config.json"), &cfg)?;
+        let opts = AgentOptions { config_dir: Some(dir.clone()), no_bridge: true, ..Default::default() };
+        if resolve_config_dir(&opts) != dir {
+            return Err("配置目录解析错误".into());
+        }
+
+        let (sensor, _) = start_agent(&opts);
+        // 等待 API 上线并产出至少 2 个采样点（首个 tick 含 WMI 初始化，留足时间）
+        let url = format!("http://127.0.0.1:{}/api/history?metrics=cpu_usage", port);
+        let deadline = Instant::now() + std::time::Duration::from_secs(20);
+        let mut points = 0usize;
+        while Instant::now() < deadline {
+            if let Ok(resp) = ureq::get(&url).call() {
+                let v: serde_json::Value = resp.into_json()?;
+                points = v["points"].as_array().map(|a| a.len()).unwrap_or(0);
+                if points >= 2 { break; }
+            }
+            std::thread::sleep(std::time::Duration::from_millis(200));
+        }
+        sensor.shutdown();
+        sensor.join();
+        let _ = std::fs::remove_dir_all(&dir);
+
+        if points < 2 {
+            return Err(format!("agent 未通过本地 API 输出采样数据（{} 个点）", points).into());
+        }
+        Ok(format!("agent 无界面运行，本地 API 返回 {} 个历史采样点，已正常停止", points))
+    }
+
+    async fn test_cli(&mut self) {
+        let start = Instant::now();
+        let mut test = TestResult {
+            test_name: "命令行工具测试".to_string(),
+            success: false,
+            message: "".to_string(),
+            duration_ms: 0,
+            details: Some(HashMap::new()),
+            error_details: None,
+        };
+
+        match self.run_cli_test().await {
+            Ok(info) => {
+                test.success = true;
+                test.message = "sys-sensor-cli 各子命令工作正常".to_string();
+                test.details.as_mut().unwrap().insert("cli_info".to_string(), info);
+            }
+            Err(e) => {
+                test.success = false;
+                test.message = "命令行工具测试失败".to_string();
+                test.error_details = Some(e.to_string());
+            }
+        }
+
+        test.duration_ms = start.elapsed().as_millis() as u64;
+        self.test_results.push(test);
+    }
+
+    async fn run_cli_test(&self) -> Result<String, Box<dyn std::error::Error>> {
+        use crate::api_server::{serve_listener, ApiConfig, ApiContext};
+        use crate::cli::{fetch_history, parse_args, parse_time_arg, render_history_table, render_rtt_table, render_snapshot_table, CliCommand};
+        use std::sync::{Arc, Mutex};
+
+        let args = |v: &[&str]| v.iter().map(|s| s.to_string()).collect::<Vec<String>>();
+        let parsed = parse_args(args(&["history", "-m", "cpu_usage,mem_pct", "--since=-10m", "-n", "5", "--json", "--token", "t"]))?;
+        if parsed.command != CliCommand::History
+            || parsed.metrics != ["cpu_usage", "mem_pct"]
+            || parsed.since.as_deref() != Some("-10m")
+            || parsed.limit != Some(5)
+            || !parsed.json
+            || parsed.token.as_deref() != Some("t")
+        {
+            return Err(format!("参数解析错误: {:?}", parsed).into());
+        }
+        let rtt = parse_args(args(&["rtt", "1.1.1.1:443", "8.8.8.8:53", "--timeout", "500", "-c", "3", "--probe-interval=50"]))?;
+        if rtt.command != CliCommand::Rtt || rtt.targets.len() != 2 || rtt.timeout_ms != Some(500)
+            || rtt.probe_count != Some(3) || rtt.probe_interval_ms != Some(50)
+        {
+            return Err(format!("rtt 参数解析错误: {:?}", rtt).into());
+        }
+        let outages = parse_args(args(&["outages", "--csv", "--since", "-1d"]))?;
+        if outages.command != CliCommand::Outages || !outages.csv || outages.since.as_deref() != Some("-1d") {
+            return Err(format!("outages 参数解析错误: {:?}", outages).into());
+        }
+        if parse_args(args(&["snapshot", "--bogus"])).is_ok() || parse_args(args(&["frobnicate"])).is_ok() || parse_args(args(&["snapshot", "extra"])).is_ok() {
+            return Err("非法参数未被拒绝".into());
+        }
+        if parse_args(args(&["watch", "--help"]))?.command != CliCommand::Help {
+            return Err("--help 未优先生效".into());
+        }
+        let now = 10_000_000i64;
+        if parse_time_arg("-10m", now)? != now - 600_000 || parse_time_arg("-2h", now)? != now - 7_200_000 || parse_time_arg("12345", now)? != 12345 {
+            return Err("时间参数解析错误".into());
+        }
+        if parse_time_arg("-xm", now).is_ok() {
+            return Err("非法时间参数未被拒绝".into());
+        }
+
+        // 快照表格：指标子集 + 单位格式化
+        let snap = crate::types::SensorSnapshot { cpu_usage: 12.5, net_rx_bps: 2048.0, ..Default::default() };
+        let table = render_snapshot_table(&snap, &args(&["cpu_usage", "net_rx_bps"]));
+        if !table.contains("12.5%") || !table.contains("2.0 KB/s") || table.contains("mem_pct") {
+            return Err(format!("快照表格渲染错误:\n{}", table).into());
+        }
+
+        // 历史查询：经本地 API 取回预置的采样点
+        let store = Arc::new(Mutex::new(crate::state_store::StateStore::new()));
+        {
+            let mut ss = store.lock().map_err(|_| "lock failed")?;
+            for i in 0..10i64 {
+                ss.push_history(1_000 * i, vec![("cpu_usage", i as f64), ("mem_pct", 50.0)]);
+            }
+        }
+        let ctx = ApiContext {
+            config: Arc::new(Mutex::new(crate::config_utils::AppConfig {
+                api: Some(ApiConfig { enabled: true, ..Default::default() }),
+                ..Default::default()
+            })),
+            scheduler: Arc::new(Mutex::new(crate::scheduler::SchedulerState::default())),
+            state_store: store,
+            config_dir: std::env::temp_dir(),
+            trigger_task: None,
+            on_config_changed: None,
+        };
+        let handle = serve_listener(std::net::TcpListener::bind("127.0.0.1:0")?, ctx, None).map_err(|e| e.to_string())?;
+        let base = format!("http://{}", handle.addr);
+        let points = fetch_history(&base, None, &args(&["cpu_usage"]), Some(3_000), None, 4);
+        handle.stop();
+        let points = points?;
+        let values: Vec<f64> = points.iter().filter_map(|p| p.values.get("cpu_usage").copied()).collect();
+        if values != [6.0, 7.0, 8.0, 9.0] || points.iter().any(|p| p.values.contains_key("mem_pct")) {
+            return Err(format!("历史查询结果错误: {:?}", values).into());
+        }
+        let history = render_history_table(&points, &args(&["cpu_usage"]));
+        if history.lines().count() != 2 + points.len() || !history.contains("9.0%") {
+            return Err(format!("历史表格渲染错误:\n{}", history).into());
+        }
+
+        // RTT：本地监听端口应可达（ICMP 不可用时回退 TCP 连接）
+        let listener = std::net::TcpListener::bind("127.0.0.1:0")?;
+        let open = format!("127.0.0.1:{}", listener.local_addr()?.port());
+        let probe = crate::ping_utils::RttTarget::new(&open);
+        let defaults = crate::ping_utils::ProbeDefaults { timeout_ms: 500, count: 3, interval_ms: 0, ..Default::default() };
+        let results = crate::ping_utils::measure_multi_rtt(std::slice::from_ref(&probe), &defaults);
+        if results.len() != 1 || results[0].target != open || results[0].success != Some(true) || results[0].sent != Some(3) {
+            return Err(format!("RTT 结果错误:\n{}", render_rtt_table(&results)).into());
+        }
+
+        // token：无运行实例时直接管理配置目录下的 api_tokens.json；文件被其他进程改写后鉴权缓存随之刷新
+        let dir = std::env::temp_dir().join(format!("sys-sensor-cli-token-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let dir_arg = dir.to_string_lossy().to_string();
+        if parse_args(args(&["token"])).is_ok() || parse_args(args(&["token", "revoke"])).is_ok() || parse_args(args(&["token", "create", "a", "--scopes", "root"])).is_ok() {
+            return Err("非法 token 参数未被拒绝".into());
+        }
+        let create = parse_args(args(&["token", "create", "ci", "--scopes", "metrics:read,config:read", "--ttl-days", "7", "--config-dir", &dir_arg]))?;
+        if create.token_action != Some(crate::cli::TokenAction::Create("ci".to_string())) || create.scopes.len() != 2 || create.ttl_days != Some(7) {
+            return Err(format!("token 参数解析错误: {:?}", create).into());
+        }
+        crate::cli::run(create)?;
+        let tokens = crate::api_auth::list_tokens(&dir)?;
+        if tokens.len() != 1 || tokens[0].name != "ci" || tokens[0].scopes.len() != 2 || tokens[0].expires_ms.is_none() {
+            return Err(format!("token create 结果错误: {:?}", tokens).into());
+        }
+        let extra = crate::api_auth::create_token(&dir, "probe", vec![], None)?;
+        if crate::api_auth::authenticate_token(&dir, &extra.token).is_none() {
+            return Err("新建 Token 鉴权失败".into());
+        }
+        std::fs::write(dir.join("api_tokens.json"), "[]")?;
+        if crate::api_auth::authenticate_token(&dir, &extra.token).is_some() {
+            return Err("api_tokens.json 被外部改写后仍使用旧缓存".into());
+        }
+        if crate::cli::run(parse_args(args(&["token", "revoke", &tokens[0].id, "--config-dir", &dir_arg]))?).is_ok() {
+            return Err("吊销不存在的 Token 应报错".into());
+        }
+        let _ = std::fs::remove_dir_all(&dir);
+
+        // snapshot --json：本进程采集时诊断日志只能走 stderr，stdout 须是可直接解析的 JSON
+        let exe_dir = std::env::current_exe()?.parent().map(|p| p.to_path_buf()).ok_or("无法定位可执行文件目录")?;
+        let cli_name = format!("sys-sensor-cli{}", std::env::consts::EXE_SUFFIX);
+        let find_cli = || [exe_dir.join(&cli_name), exe_dir.join("..").join(&cli_name)].into_iter().find(|p| p.exists());
+        if find_cli().is_none() {
+            std::process::Command::new(env!("CARGO"))
+                .args(["build", "--bin", "sys-sensor-cli"])
+                .current_dir(env!("CARGO_MANIFEST_DIR"))
+                .status()?;
+        }
+        let cli = find_cli().ok_or("未找到 sys-sensor-cli 可执行文件")?;
+        let cfg_dir = std::env::temp_dir().join(format!("sys-sensor-cli-json-{}", std::process::id()));
+        std::fs::create_dir_all(&cfg_dir)?;
+        let out = std::process::Command::new(&cli)
+            .args(["snapshot", "--json", "--config-dir"])
+            .arg(&cfg_dir)
+            .output()?;
+        let _ = std::fs::remove_dir_all(&cfg_dir);
+        if !out.status.success() {
+            return Err(format!("snapshot --json 退出码异常: {:?}\n{}", out.status, String::from_utf8_lossy(&out.stderr)).into());
+        }
+        let json: serde_json::Value = serde_json::from_slice(&out.stdout)
+            .map_err(|e| format!("snapshot --json 输出不是合法 JSON: {}\n{}", e, String::from_utf8_lossy(&out.stdout)))?;
+        if json.get("cpu_usage").is_none() {
+            return Err(format!("snapshot --json 缺少 cpu_usage: {}", json).into());
+        }
+
+        Ok(format!("参数解析/时间解析/表格渲染正常，history 返回 {} 个点，RTT 目标 {} 可达", points.len(), open))
+    }
+
+    async fn test_alert_engine(&mut self) {
+        let start = Instant::now();
+        let mut test = TestResult {
+            test_name: "告警规则引擎测试".to_string(),
+            success: false,
+            message: "".to_string(),
+            duration_ms: 0,
+            details: Some(HashMap::new()),
+            error_details: None,
+        };
+
+        match self.run_alert_engine_test().await {
+            Ok(info) => {
+                test.success = true;
+                test.message = "告警规则评估正常".to_string();
+                test.details.as_mut().unwrap().insert("alert_info".to_string(), info);
+            }
+            Err(e) => {
+                test.success = false;
+                test.message = "告警规则引擎测试失败".to_string();
+                test.error_details = Some(e.to_string());
+            }
+        }
+
+        test.duration_ms = start.elapsed().as_millis() as u64;
+        self.test_results.push(test);
+    }
+
+    async fn run_alert_engine_test(&self) -> Result<String, Box<dyn std::error::Error>> {
+        use crate::alert_engine::{metric_values, AlertCondition, AlertConfig, AlertEngine, AlertOp, AlertRule, AlertSeverity, AlertState, AlertTransition};
+
+        let temp_rule = AlertRule {
+            id: "cpu_hot".into(),
+            metric: "cpu_temp_c".into(),
+            op: AlertOp::Gt,
+            threshold: 90.0,
+            clear_threshold: Some(80.0),
+            for_sec: Some(3),
+            severity: Some(AlertSeverity::Critical),
+            ..Default::default()
+        };
+        let rate_rule = AlertRule {
+            id: "rtt_spike".into(),
+            metric: "rtt_avg_ms".into(),
+            op: AlertOp::Gt,
+            threshold: 10.0,
+            condition: Some(AlertCondition::Rate),
+            rate_window_sec: Some(5),
+            ..Default::default()
+        };
+        let mut cfg = AlertConfig { rules: Some(vec![temp_rule.clone(), rate_rule]), ..Default::default() };
+        let vals = |pairs: &[(&str, f64)]| pairs.iter().map(|(k, v)| (k.to_string(), *v)).collect::<HashMap<String, f64>>();
+
+        // 持续时间：超过阈值需满 3 秒才触发
+        let mut engine = AlertEngine::new();
+        let mut fired = Vec::new();
+        for (i, t) in [95.0, 96.0, 97.0, 98.0].iter().enumerate() {
+            fired.extend(engine.tick(Some(&cfg), 1_000 * i as i64, &vals(&[("cpu_temp_c", *t)])));
+        }
+        if fired.len() != 1 || fired[0].state != AlertTransition::Firing || fired[0].timestamp_ms != 3_000 || fired[0].severity != AlertSeverity::Critical {
+            return Err(format!("持续时间条件错误: {:?}", fired).into());
+        }
+        let status = crate::alert_engine::alert_get_status();
+        if status.active != 1 || status.rules.iter().find(|r| r.id == "cpu_hot").map(|r| r.state) != Some(AlertState::Firing) {
+            return Err("告警状态未更新".into());
+        }
+
+        // 滞回：回落到 85（高于恢复阈值 80）不恢复，降到 79 才恢复
+        if !engine.tick(Some(&cfg), 4_000, &vals(&[("cpu_temp_c", 85.0)])).is_empty() {
+            return Err("滞回区间内不应恢复".into());
+        }
+        // 指标缺失时保持状态
+        if !engine.tick(Some(&cfg), 5_000, &vals(&[])).is_empty() {
+            return Err("指标缺失不应产生转换".into());
+        }
+        let resolved = engine.tick(Some(&cfg), 6_000, &vals(&[("cpu_temp_c", 79.0)]));
+        if resolved.len() != 1 || resolved[0].state != AlertTransition::Resolved || resolved[0].fired_at_ms != 3_000 {
+            return Err(format!("恢复事件错误: {:?}", resolved).into());
+        }
+
+        // 抖动：未满持续时间即回落，不触发
+        let mut flap = Vec::new();
+        for (i, t) in [95.0, 70.0, 95.0, 70.0].iter().enumerate() {
+            flap.extend(engine.tick(Some(&cfg), 10_000 + 1_000 * i as i64, &vals(&[("cpu_temp_c", *t)])));
+        }
+        if !flap.is_empty() {
+            return Err(format!("抖动不应触发: {:?}", flap).into());
+        }
+
+        // 变化率：RTT 在 2 秒内从 20ms 升到 60ms（20ms/s > 10ms/s）
+        let mut rate = Vec::new();
+        for (i, v) in [20.0, 40.0, 60.0].iter().enumerate() {
+            rate.extend(engine.tick(Some(&cfg), 20_000 + 1_000 * i as i64, &vals(&[("rtt_avg_ms", *v)])));
+        }
+        if rate.len() != 1 || rate[0].rule_id != "rtt_spike" || (rate[0].value - 20.0).abs() > 1e-6 {
+            return Err(format!("变化率条件错误: {:?}", rate).into());
+        }
+
+        // 热更新：修改规则内容重置该规则，未修改的规则保持 firing；无效/重复规则被跳过
+        let mut changed = temp_rule.clone();
+        changed.threshold = 99.0;
+        cfg.rules.as_mut().unwrap()[0] = changed;
+        cfg.rules.as_mut().unwrap().push(AlertRule { id: "rtt_spike".into(), metric: "x".into(), ..Default::default() });
+        cfg.rules.as_mut().unwrap().push(AlertRule { id: "no_metric".into(), ..Default::default() });
+        engine.tick(Some(&cfg), 23_000, &vals(&[]));
+        let status = crate::alert_engine::alert_get_status();
+        if status.rules.len() != 2 || status.errors.len() != 2 || status.active != 1 {
+            return Err(format!("热更新/校验错误: {:?}", status).into());
+        }
+        // 修改仍在 firing 的规则：旧告警补发 resolved，新规则从 ok 重新评估
+        cfg.rules.as_mut().unwrap()[1].threshold = 50.0;
+        let edited = engine.tick(Some(&cfg), 23_500, &vals(&[]));
+        if edited.len() != 1 || edited[0].rule_id != "rtt_spike" || edited[0].state != AlertTransition::Resolved || edited[0].fired_at_ms != 21_000 {
+            return Err(format!("修改 firing 规则应补发 resolved: {:?}", edited).into());
+        }
+        if crate::alert_engine::alert_get_status().active != 0 {
+            return Err("修改后的规则不应保持 firing".into());
+        }
+        // 关闭总开关：清空规则，仍在 firing 的规则补发 resolved
+        let instant = AlertRule { id: "instant".into(), metric: "cpu_usage".into(), threshold: 50.0, ..Default::default() };
+        cfg.rules.as_mut().unwrap().push(instant);
+        if engine.tick(Some(&cfg), 23_800, &vals(&[("cpu_usage", 60.0)])).len() != 1 {
+            return Err("instant 规则未触发".into());
+        }
+        cfg.enabled = Some(false);
+        let disabled = engine.tick(Some(&cfg), 24_000, &vals(&[]));
+        if !crate::alert_engine::alert_get_status().rules.is_empty() {
+            return Err("禁用后规则未清空".into());
+        }
+        if disabled.len() != 1 || disabled[0].rule_id != "instant" || disabled[0].state != AlertTransition::Resolved || disabled[0].timestamp_ms != 24_000 {
+            return Err(format!("禁用时 firing 规则应补发 resolved: {:?}", disabled).into());
+        }
+
+        // 恢复阈值方向：> 规则的恢复阈值不能高于触发阈值，< 规则反之
+        let wrong_gt = AlertRule { clear_threshold: Some(95.0), ..temp_rule.clone() };
+        let wrong_lt = AlertRule { op: AlertOp::Lt, threshold: 10.0, clear_threshold: Some(5.0), ..temp_rule.clone() };
+        let right_lt = AlertRule { op: AlertOp::Lt, threshold: 10.0, clear_threshold: Some(15.0), ..temp_rule.clone() };
+        if crate::alert_engine::validate_rule(&wrong_gt).is_ok() || crate::alert_engine::validate_rule(&wrong_lt).is_ok() || crate::alert_engine::validate_rule(&right_lt).is_err() {
+            return Err("clear_threshold 方向校验错误".into());
+        }
+
+        // 指标来源：快照字段优先，聚合字段补充
+        let snap = crate::types::SensorSnapshot { cpu_usage: 42.0, ..Default::default() };
+        let agg = crate::state_store::Aggregated { cpu_usage: Some(1.0), rtt_avg_ms: Some(12.5), ..Default::default() };
+        let merged = metric_values(&snap, Some(&agg));
+        if merged.get("cpu_usage") != Some(&42.0) || merged.get("rtt_avg_ms") != Some(&12.5) || merged.contains_key("timestamp_ms") {
+            return Err("指标汇总错误".into());
+        }
+
+        // 配置热更新入口：alerts 字段整体替换
+        let mut app_cfg = crate::config_utils::AppConfig::default();
+        crate::config_utils::apply_patch(&mut app_cfg, &serde_json::json!({
+            "alerts": { "rules": [{ "id": "mem", "metric": "mem_pct", "op": ">=", "threshold": 95, "for_sec": 30 }] }
+        }));
+        let rules = app_cfg.alerts.and_then(|a| a.rules).unwrap_or_default();
+        if rules.len() != 1 || rules[0].op != AlertOp::Ge || rules[0].for_sec != Some(30) {
+            return Err("alerts 配置解析错误".into());
+        }
+
+        Ok(format!("持续时间/滞回/抖动/变化率/热更新均符合预期，触发时刻 {}ms，恢复时刻 {}ms", fired[0].timestamp_ms, resolved[0].timestamp_ms))
+    }
+
+    async fn test_alert_notify(&mut self) {
+        let start = Instant::now();
+        let mut test = TestResult {
+            test_name: "告警通知Sink测试".to_string(),
+            success: false,
+            message: "".to_string(),
+            duration_ms: 0,
+            details: Some(HashMap::new()),
+            error_details: None,
+        };
+
+        match self.run_alert_notify_test().await {
+            Ok(info) => {
+                test.success = true;
+                test.message = "告警通知发送正常".to_string();
+                test.details.as_mut().unwrap().insert("notify_info".to_string(), info);
+            }
+            Err(e) => {
+                test.success = false;
+                test.message = "告警通知Sink测试失败".to_string();
+                test.error_details = Some(e.to_string());
+            }
+        }
+
+        test.duration_ms = start.elapsed().as_millis() as u64;
+        self.test_results.push(test);
+    }
+
+    async fn run_alert_notify_test(&self) -> Result<String, Box<dyn std::error::Error>> {
+        use crate::alert_engine::{AlertConfig, AlertEvent, AlertSeverity, AlertTransition};
+        use crate::alert_notify::{
+            notify_get_status, render_template, send_test, send_to_sink, start_notifier, webhook_body, NotifyGate, NotifyKind,
+            NotifySinkConfig, SmtpSecurity, WebhookFormat,
+        };
+        use std::sync::{Arc, Mutex};
+
+        let ev = AlertEvent {
+            rule_id: "cpu_hot".into(),
+            rule_name: "CPU \"过热\"".into(),
+            metric: "cpu_temp_c".into(),
+            severity: AlertSeverity::Critical,
+            state: AlertTransition::Firing,
+            value: 97.5,
+            threshold: 90.0,
+            timestamp_ms: 1_700_000_000_000,
+            fired_at_ms: 1_700_000_000_000,
+            message: "CPU 温度 97.5°C".into(),
+        };
+
+        // 模板：JSON 转义 + 合法性校验
+        let rendered = render_template(r#"{"t":"{{rule_name}} {{value}} {{state}}"}"#, &ev, "pc1", true);
+        let v: serde_json::Value = serde_json::from_str(&rendered)?;
+        if v["t"] != "CPU \"过热\" 97.50 firing" {
+            return Err(format!("模板渲染错误: {}", rendered).into());
+        }
+        let bad = NotifySinkConfig { template: Some("{\"t\": {{value}".into()), ..Default::default() };
+        if webhook_body(&bad, &ev, "pc1").is_ok() {
+            return Err("非法模板未被拒绝".into());
+        }
+        let slack = NotifySinkConfig { format: Some(WebhookFormat::Slack), ..Default::default() };
+        let teams = NotifySinkConfig { format: Some(WebhookFormat::Teams), ..Default::default() };
+        if !webhook_body(&slack, &ev, "pc1")?.contains("\"text\"") || !webhook_body(&teams, &ev, "pc1")?.contains("MessageCard") {
+            return Err("内置 Webhook 格式错误".into());
+        }
+
+        // 过滤：最低级别 / 恢复通知 / 去重 / 每小时上限
+        let mut gate = NotifyGate::new();
+        let now = Instant::now();
+        let sink = NotifySinkConfig { id: "s".into(), min_severity: Some(AlertSeverity::Warning), send_resolved: Some(false), dedup_sec: Some(60), max_per_hour: Some(2), ..Default::default() };
+        let info = AlertEvent { severity: AlertSeverity::Info, ..ev.clone() };
+        let resolved = AlertEvent { state: AlertTransition::Resolved, ..ev.clone() };
+        let other = AlertEvent { rule_id: "mem".into(), ..ev.clone() };
+        let third = AlertEvent { rule_id: "disk".into(), ..ev.clone() };
+        let decisions = [
+            gate.admit(&sink, &info, now).is_ok(),
+            gate.admit(&sink, &resolved, now).is_ok(),
+            gate.admit(&sink, &ev, now).is_ok(),
+            gate.admit(&sink, &ev, now + Duration::from_secs(30)).is_ok(),
+            gate.admit(&sink, &other, now + Duration::from_secs(31)).is_ok(),
+            gate.admit(&sink, &third, now + Duration::from_secs(32)).is_ok(),
+            gate.admit(&sink, &third, now + Duration::from_secs(3700)).is_ok(),
+        ];
+        if decisions != [false, false, true, false, true, false, true] {
+            return Err(format!("去重/限流判定错误: {:?}", decisions).into());
+        }
+
+        // Webhook：自定义请求头 + generic 格式
+        let (port, rx) = spawn_http_stub("HTTP/1.1 200 OK")?;
+        let hook = NotifySinkConfig {
+            id: "hook".into(),
+            kind: NotifyKind::Webhook,
+            url: Some(format!("http://127.0.0.1:{}/hook", port)),
+            headers: Some([("X-Token".to_string(), "abc".to_string())].into_iter().collect()),
+            ..Default::default()
+        };
+        send_to_sink(&hook, &ev, "pc1")?;
+        let (head, body) = rx.recv_timeout(Duration::from_secs(5))?;
+        let body: serde_json::Value = serde_json::from_slice(&body)?;
+        if !head.starts_with("POST /hook") || !head.to_ascii_lowercase().contains("x-token: abc") || body["rule_id"] != "cpu_hot" || body["host"] != "pc1" {
+            return Err(format!("Webhook 请求错误: {} {}", head, body).into());
+        }
+        let (port, _rx) = spawn_http_stub("HTTP/1.1 500 Internal Server Error")?;
+        if send_to_sink(&NotifySinkConfig { url: Some(format!("http://127.0.0.1:{}/", port)), ..hook.clone() }, &ev, "pc1").is_ok() {
+            return Err("Webhook 5xx 未报告失败".into());
+        }
+
+        // SMTP：本地替身服务器（明文 + AUTH PLAIN）
+        let (smtp_port, smtp_rx) = spawn_smtp_stub()?;
+        let mail = NotifySinkConfig {
+            id: "mail".into(),
+            kind: NotifyKind::Email,
+            smtp_host: Some("127.0.0.1".into()),
+            smtp_port: Some(smtp_port),
+            smtp_security: Some(SmtpSecurity::None),
+            username: Some("bot".into()),
+            password: Some("secret".into()),
+            from: Some("Sensor <bot@example.com>".into()),
+            to: Some(vec!["ops@example.com".into()]),
+            ..Default::default()
+        };
+        send_to_sink(&mail, &ev, "pc1")?;
+        let (cmds, data) = smtp_rx.recv_timeout(Duration::from_secs(5))?;
+        let expect_auth = format!("AUTH PLAIN {}", base64::Engine::encode(&base64::engine::general_purpose::STANDARD, "\0bot\0secret"));
+        if !cmds.contains(&expect_auth) || !cmds.contains(&"MAIL FROM:<bot@example.com>".to_string()) || !cmds.contains(&"RCPT TO:<ops@example.com>".to_string()) {
+            return Err(format!("SMTP 会话错误: {:?}", cmds).into());
+        }
+        if !data.contains("Subject: =?UTF-8?B?") || !data.contains("To: ops@example.com") {
+            return Err(format!("邮件内容错误: {}", data).into());
+        }
+
+        // 脚本：告警详情经环境变量传入
+        let out_file = std::env::temp_dir().join(format!("sys-sensor-notify-{}.txt", std::process::id()));
+        let (command, args) = if cfg!(windows) {
+            ("powershell".to_string(), vec![
+                "-NoProfile".to_string(),
+                "-Command".to_string(),
+                format!("Set-Content -Path '{}' -Value ($env:SYS_SENSOR_ALERT_RULE_ID + '|' + $env:SYS_SENSOR_ALERT_STATE)", out_file.display()),
+            ])
+        } else {
+            ("sh".to_string(), vec![
+                "-c".to_string(),
+                "printf '%s|%s' \"$SYS_SENSOR_ALERT_RULE_ID\" \"$SYS_SENSOR_ALERT_STATE\" > \"$1\"".to_string(),
+                "sh".to_string(),
+                out_file.display().to_string(),
+            ])
+        };
+        let script = NotifySinkConfig { id: "script".into(), kind: NotifyKind::Script, command: Some(command), args: Some(args), ..Default::default() };
+        send_to_sink(&script, &ev, "pc1")?;
+        let written = std::fs::read_to_string(&out_file)?;
+        let _ = std::fs::remove_file(&out_file);
+        if written.trim() != "cpu_hot|firing" {
+            return Err(format!("脚本环境变量错误: {}", written).into());
+        }
+        // 大量 stderr 输出不应塞满管道导致脚本挂起到超时；失败时错误信息带上 stderr 开头
+        let noisy_args = if cfg!(windows) {
+            vec!["-NoProfile".to_string(), "-Command".to_string(), "[Console]::Error.Write('boom' + ('x' * 262144)); exit 3".to_string()]
+        } else {
+            vec!["-c".to_string(), "printf boom >&2; head -c 262144 /dev/zero | tr '\\0' x >&2; exit 3".to_string()]
+        };
+        let noisy = NotifySinkConfig {
+            args: Some(noisy_args),
+            timeout_ms: Some(10_000),
+            ..script.clone()
+        };
+        let started = Instant::now();
+        match send_to_sink(&noisy, &ev, "pc1") {
+            Err(e) if e.contains("boom") && started.elapsed() < Duration::from_secs(8) => {}
+            other => return Err(format!("大量 stderr 输出的脚本结果错误（{:?}）: {:?}", started.elapsed(), other.map_err(|e| e.chars().take(80).collect::<String>())).into()),
+        }
+
+        // 测试通知命令路径：未知 Sink 报错，指定 Sink 返回结果
+        if send_test(std::slice::from_ref(&hook), Some("nope")).is_ok() {
+            return Err("未知 Sink 未报错".into());
+        }
+        let (port, rx) = spawn_http_stub("HTTP/1.1 204 No Content")?;
+        let test_hook = NotifySinkConfig { id: "t".into(), url: Some(format!("http://127.0.0.1:{}/", port)), ..hook.clone() };
+        let results = send_test(std::slice::from_ref(&test_hook), Some("t"))?;
+        let (_, body) = rx.recv_timeout(Duration::from_secs(5))?;
+        if results.len() != 1 || !results[0].contains("已发送") || !String::from_utf8_lossy(&body).contains("\"rule_id\":\"test\"") {
+            return Err(format!("测试通知错误: {:?}", results).into());
+        }
+
+        // 后台分发：重复事件被去重，状态计数更新
+        let (port, rx) = spawn_http_stub("HTTP/1.1 200 OK")?;
+        let bg = NotifySinkConfig { id: "bg".into(), url: Some(format!("http://127.0.0.1:{}/", port)), ..hook.clone() };
+        let cfg = crate::config_utils::AppConfig {
+            alerts: Some(AlertConfig { sinks: Some(vec![bg]), ..Default::default() }),
+            ..Default::default()
+        };
+        let notifier = start_notifier(Arc::new(Mutex::new(cfg)));
+        notifier.push(&ev);
+        notifier.push(&ev);
+        rx.recv_timeout(Duration::from_secs(5))?;
+        let deadline = Instant::now() + Duration::from_secs(5);
+        let mut bg_status = None;
+        while Instant::now() < deadline {
+            bg_status = notify_get_status().sinks.into_iter().find(|s| s.id == "bg");
+            if bg_status.as_ref().map(|s| s.sent == 1 && s.suppressed == 1).unwrap_or(false) { break; }
+            std::thread::sleep(Duration::from_millis(50));
+        }
+        let bg_status = bg_status.ok_or("后台分发状态缺失")?;
+        if bg_status.sent != 1 || bg_status.suppressed != 1 {
+            return Err(format!("后台分发计数错误: {:?}", bg_status).into());
+        }
+
+        Ok(format!("Webhook/SMTP/脚本发送正常，SMTP 会话 {} 条命令，去重限流判定符合预期", cmds.len()))
+    }
+
+    async fn test_alert_journal(&mut self) {
+        let start = Instant::now();
+        let mut test = TestResult {
+            test_name: "告警日志与确认测试".to_string(),
+            success: false,
+            message: "".to_string(),
+            duration_ms: 0,
+            details: Some(HashMap::new()),
+            error_details: None,
+        };
+
+        match self.run_alert_journal_test().await {
+            Ok(info) => {
+                test.success = true;
+                test.message = "告警日志、确认与静默正常".to_string();
+                test.details.as_mut().unwrap().insert("journal_info".to_string(), info);
+            }
+            Err(e) => {
+                test.success = false;
+                test.message = "告警日志与确认测试失败".to_string();
+                test.error_details = Some(e.to_string());
+            }
+        }
+
+        test.duration_ms = start.elapsed().as_millis() as u64;
+        self.test_results.push(test);
+    }
+
+    async fn run_alert_journal_test(&self) -> Result<String, Box<dyn std::error::Error>> {
+        use crate::alert_engine::{alert_get_status, AlertConfig, AlertEngine, AlertRule, AlertTransition};
+        use crate::alert_journal::{acknowledge, open, query, record_event, silence, silenced_until};
+
+        let dir = std::env::temp_dir().join(format!("sys-sensor-journal-test-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        open(&dir, None);
+
+        let cfg = AlertConfig {
+            rules: Some(vec![AlertRule { id: "mem_high".into(), metric: "mem_pct".into(), threshold: 90.0, ..Default::default() }]),
+            ..Default::default()
+        };
+        let vals = |v: f64| [("mem_pct".to_string(), v)].into_iter().collect::<HashMap<String, f64>>();
+        let mut engine = AlertEngine::new();
+        let now = chrono::Local::now().timestamp_millis();
+
+        if acknowledge("mem_high", None, None).is_ok() {
+            return Err("未知/未触发规则不应可确认".into());
+        }
+        let fired = engine.tick(Some(&cfg), now, &vals(95.0));
+        for ev in fired.iter() { record_event(ev, None); }
+        if fired.len() != 1 {
+            return Err("规则未触发".into());
+        }
+        if alert_get_status().unacknowledged != 1 {
+            return Err("未确认计数错误".into());
+        }
+
+        let ack = acknowledge("mem_high", Some("处理中".into()), None)?;
+        if ack.transition != AlertTransition::Acknowledged || ack.fired_at_ms != Some(now) || acknowledge("mem_high", None, None).is_ok() {
+            return Err(format!("确认结果错误: {:?}", ack).into());
+        }
+        engine.tick(Some(&cfg), now + 1_000, &vals(96.0));
+        let st = alert_get_status();
+        if st.active != 1 || st.unacknowledged != 0 || !st.rules[0].acknowledged {
+            return Err(format!("确认状态未反映: {:?}", st).into());
+        }
+
+        let sil = silence("mem_high", 3600, None, None)?;
+        if silenced_until("mem_high", now + 2_000) != sil.silenced_until_ms {
+            return Err("静默未生效".into());
+        }
+
+        // 恢复后确认失效；再次触发需要重新确认
+        for ev in engine.tick(Some(&cfg), now + 2_000, &vals(50.0)) { record_event(&ev, None); }
+        for ev in engine.tick(Some(&cfg), now + 3_000, &vals(97.0)) { record_event(&ev, None); }
+        if alert_get_status().unacknowledged != 1 {
+            return Err("恢复后确认未清除".into());
+        }
+
+        // 查询：新记录在前，可按规则/时间过滤
+        let all = query(None, None, None, 100);
+        let kinds: Vec<AlertTransition> = all.iter().map(|e| e.transition).collect();
+        let expect = [AlertTransition::Firing, AlertTransition::Resolved, AlertTransition::Silenced, AlertTransition::Acknowledged, AlertTransition::Firing];
+        if kinds != expect {
+            return Err(format!("日志顺序错误: {:?}", kinds).into());
+        }
+        if all[1].value != Some(50.0) || query(Some(now + 2_000), None, Some("mem_high"), 100).len() != 2 || !query(None, None, Some("other"), 100).is_empty() {
+            return Err("日志过滤错误".into());
+        }
+
+        // 重新打开：未到期的静默被恢复
+        open(&dir, None);
+        if silenced_until("mem_high", now + 5_000).is_none() {
+            return Err("重启后静默未恢复".into());
+        }
+        silence("mem_high", 0, None, None)?;
+        open(&dir, None);
+        if silenced_until("mem_high", now + 5_000).is_some() {
+            return Err("解除静默未生效".into());
+        }
+
+        // 条目上限：只保留最新记录
+        let extra = AlertConfig { journal_max_entries: Some(3), ..cfg.clone() };
+        for ev in engine.tick(Some(&extra), now + 4_000, &vals(10.0)) { record_event(&ev, extra.journal_max_entries); }
+        let kept = query(None, None, None, 100);
+        if kept.len() != 3 || kept[0].transition != AlertTransition::Resolved {
+            let _ = std::fs::remove_dir_all(&dir);
+            return Err(format!("日志上限未生效: {} 条", kept.len()).into());
+        }
+
+        // 到达上限后先继续追加，超过 1.25 倍才经临时文件压缩，读取始终只见最新 max 条
+        let path = dir.join("compact.jsonl");
+        let mut file = crate::jsonl_journal::JsonlJournal::open(path.clone(), 4);
+        let raw_lines = || std::fs::read_to_string(&path).map(|t| t.lines().count()).unwrap_or(0);
+        for i in 1..=5u32 { file.append(&i, 4)?; }
+        let latest: Vec<u32> = file.read();
+        if raw_lines() != 5 || latest != [2, 3, 4, 5] {
+            let _ = std::fs::remove_dir_all(&dir);
+            return Err(format!("未到压缩阈值时不应重写: {} 行, 读取 {:?}", raw_lines(), latest).into());
+        }
+        file.append(&6u32, 4)?;
+        let compacted: Vec<u32> = crate::jsonl_journal::read_entries(&path);
+        let leftovers = std::fs::read_dir(&dir)?.filter_map(|e| e.ok()).filter(|e| e.file_name().to_string_lossy().ends_with(".tmp")).count();
+        let reopened: Vec<u32> = crate::jsonl_journal::JsonlJournal::open(path.clone(), 4).read();
+        let _ = std::fs::remove_dir_all(&dir);
+        if compacted != [3, 4, 5, 6] || leftovers != 0 || reopened != compacted {
+            return Err(format!("日志压缩错误: {:?}（残留临时文件 {} 个）", compacted, leftovers).into());
+        }
+
+        Ok(format!("记录 {} 条状态转换，确认/静默/恢复与重启恢复均正常", all.len()))
+    }
+
+    async fn test_anomaly_detector(&mut self) {
+        let start = Instant::now();
+        let mut test = TestResult {
+            test_name: "统计异常检测测试".to_string(),
+            success: false,
+            message: "".to_string(),
+            duration_ms: 0,
+            details: Some(HashMap::new()),
+            error_details: None,
+        };
+
+        match self.run_anomaly_detector_test().await {
+            Ok(info) => {
+                test.success = true;
+                test.message = "异常检测基线与派生告警正常".to_string();
+                test.details.as_mut().unwrap().insert("anomaly_info".to_string(), info);
+            }
+            Err(e) => {
+                test.success = false;
+                test.message = "统计异常检测测试失败".to_string();
+                test.error_details = Some(e.to_string());
+            }
+        }
+
+        test.duration_ms = start.elapsed().as_millis() as u64;
+        self.test_results.push(test);
+    }
+
+    async fn run_anomaly_detector_test(&self) -> Result<String, Box<dyn std::error::Error>> {
+        use crate::alert_engine::{AlertConfig, AlertEngine, AlertTransition};
+        use crate::anomaly_detector::{anomaly_get_status, derived_rules, score_key, AnomalyConfig, AnomalyDetector, AnomalyDirection, AnomalyMetricConfig, AnomalyModel};
+        use crate::state_store::HistoryPoint;
+
+        let t0 = chrono::Local::now().timestamp_millis();
+        // 风扇转速与 CPU 温度线性相关：rpm = 400 + 15 × temp，叠加小幅抖动
+        let frame = |temp: f64, rpm: f64| -> HashMap<String, f64> {
+            [("cpu_temp_c".to_string(), temp), ("fan_rpm".to_string(), rpm)].into_iter().collect()
+        };
+        let temp_at = |i: i64| 45.0 + 15.0 * ((i as f64) / 17.0).sin();
+        let jitter = |i: i64| ((i * 7919) % 11) as f64 - 5.0;
+
+        let cfg = AlertConfig {
+            anomaly: Some(AnomalyConfig {
+                enabled: None,
+                metrics: Some(vec![
+                    AnomalyMetricConfig { metric: "cpu_temp_c".into(), min_samples: Some(50), ..Default::default() },
+                    AnomalyMetricConfig {
+                        metric: "fan_rpm".into(),
+                        model: Some(AnomalyModel::Regression),
+                        regressor: Some("cpu_temp_c".into()),
+                        direction: Some(AnomalyDirection::Below),
+                        min_samples: Some(50),
+                        for_sec: Some(0),
+                        ..Default::default()
+                    },
+                    // 无自变量的回归配置应被跳过
+                    AnomalyMetricConfig { metric: "mobo_temp_c".into(), model: Some(AnomalyModel::Regression), ..Default::default() },
+                ]),
+            }),
+            ..Default::default()
+        };
+        let rules = derived_rules(cfg.anomaly.as_ref().unwrap());
+        if rules.len() != 2 || rules[1].id != "anomaly.fan_rpm" || rules[1].metric != score_key("fan_rpm") {
+            return Err(format!("派生规则错误: {:?}", rules).into());
+        }
+
+        // 以历史环形缓冲预热：首帧即可输出分数
+        let history: Vec<HistoryPoint> = (0..300)
+            .map(|i| HistoryPoint { timestamp_ms: t0 + i * 1000, values: frame(temp_at(i), 400.0 + 15.0 * temp_at(i) + jitter(i)).into_iter().collect() })
+            .collect();
+        let mut detector = AnomalyDetector::new();
+        let mut engine = AlertEngine::new();
+        let now = t0 + 300_000;
+        let mut vals = frame(temp_at(300), 400.0 + 15.0 * temp_at(300) + jitter(300));
+        detector.tick(Some(&cfg), now, &mut vals, || history.clone());
+        let st = anomaly_get_status();
+        if !st.enabled || st.metrics.len() != 2 || st.errors.len() != 1 || !st.metrics.iter().all(|m| m.ready && m.samples >= 300) {
+            return Err(format!("预热/校验状态错误: {:?}", st).into());
+        }
+        let normal = vals.get(&score_key("fan_rpm")).copied().ok_or("就绪后未输出分数")?;
+        if normal > 3.0 || engine.tick(Some(&cfg), now, &vals).iter().any(|e| e.rule_id == "anomaly.fan_rpm") {
+            return Err(format!("正常样本被判为异常: {:.2}", normal).into());
+        }
+
+        // 高负载下温度升高、转速相应升高：回归基线不应报警
+        let mut hot = frame(70.0, 400.0 + 15.0 * 70.0);
+        detector.tick(Some(&cfg), now + 1_000, &mut hot, Vec::new);
+        let hot_score = hot.get(&score_key("fan_rpm")).copied().unwrap_or(f64::NAN);
+        if hot_score.is_nan() || hot_score >= 3.0 {
+            return Err(format!("负载相关的转速变化被误判: {:.2}", hot_score).into());
+        }
+
+        // 同一温度下转速低 30%：触发 anomaly.fan_rpm
+        let mut slow = frame(60.0, (400.0 + 15.0 * 60.0) * 0.7);
+        detector.tick(Some(&cfg), now + 2_000, &mut slow, Vec::new);
+        let slow_score = slow.get(&score_key("fan_rpm")).copied().unwrap_or(0.0);
+        let fired = engine.tick(Some(&cfg), now + 2_000, &slow);
+        if slow_score <= 3.0 || !fired.iter().any(|e| e.rule_id == "anomaly.fan_rpm" && e.state == AlertTransition::Firing) {
+            return Err(format!("转速偏低未触发: score={:.2} events={:?}", slow_score, fired).into());
+        }
+
+        // 基线在偏离期间降权学习：持续的单帧偏离不会立刻被吸收
+        let mut again = frame(60.0, (400.0 + 15.0 * 60.0) * 0.7);
+        detector.tick(Some(&cfg), now + 3_000, &mut again, Vec::new);
+        if again.get(&score_key("fan_rpm")).copied().unwrap_or(0.0) <= 3.0 {
+            return Err("异常样本过快被基线吸收".into());
+        }
+
+        // 配置变化的检测项重建基线；关闭告警总开关后不再输出分数
+        let mut tuned = cfg.clone();
+        if let Some(m) = tuned.anomaly.as_mut().and_then(|a| a.metrics.as_mut()) { m[0].sensitivity = Some(4.0); }
+        let mut v2 = frame(50.0, 1150.0);
+        detector.tick(Some(&tuned), now + 4_000, &mut v2, Vec::new);
+        let st2 = anomaly_get_status();
+        let temp_status = st2.metrics.iter().find(|m| m.metric == "cpu_temp_c").ok_or("缺少 cpu_temp_c 状态")?;
+        if temp_status.samples != 1 || v2.contains_key(&score_key("cpu_temp_c")) || !v2.contains_key(&score_key("fan_rpm")) {
+            return Err(format!("热更新未按检测项重置: {:?}", temp_status).into());
+        }
+        let off = AlertConfig { enabled: Some(false), ..cfg.clone() };
+        let mut v3 = frame(50.0, 1150.0);
+        detector.tick(Some(&off), now + 5_000, &mut v3, Vec::new);
+        if anomaly_get_status().enabled || v3.keys().any(|k| k.starts_with("anomaly_z.")) {
+            return Err("关闭后仍在检测".into());
+        }
+
+        // seasonal：小时桶记忆跨越多天，不能只记住当天这一小时的最近样本
+        let seasonal = AlertConfig {
+            anomaly: Some(AnomalyConfig {
+                enabled: None,
+                metrics: Some(vec![AnomalyMetricConfig { metric: "fan_rpm".into(), model: Some(AnomalyModel::Seasonal), min_samples: Some(10), ..Default::default() }]),
+            }),
+            ..Default::default()
+        };
+        let at_ten = |days: i64| {
+            use chrono::TimeZone;
+            let date = chrono::Local::now().date_naive() + chrono::Duration::days(days);
+            chrono::Local.from_local_datetime(&date.and_hms_opt(10, 0, 0).unwrap()).earliest().map(|d| d.timestamp_millis()).unwrap_or(0)
+        };
+        let mut seasonal_detector = AnomalyDetector::new();
+        for (day, base) in [(-2, 1000.0), (-1, 1060.0)] {
+            for i in 0..3600 {
+                let mut v: HashMap<String, f64> = [("fan_rpm".to_string(), base + 10.0 * jitter(i))].into_iter().collect();
+                seasonal_detector.tick(Some(&seasonal), at_ten(day) + i * 1000, &mut v, Vec::new);
+            }
+        }
+        let mut v4: HashMap<String, f64> = [("fan_rpm".to_string(), 1030.0)].into_iter().collect();
+        seasonal_detector.tick(Some(&seasonal), at_ten(0), &mut v4, Vec::new);
+        let expected = anomaly_get_status().metrics.first().and_then(|m| m.expected).unwrap_or(0.0);
+        if !(1020.0..1040.0).contains(&expected) {
+            return Err(format!("seasonal 小时桶只记住了最近样本: 期望值 {:.1}", expected).into());
+        }
+
+        Ok(format!("正常分数 {:.2}，负载升高 {:.2}，转速偏低 {:.2}", normal, hot_score, slow_score))
+    }
+
+    async fn test_bridge_protocol(&mut self) {
+        let start = Instant::now();
+        let mut test = TestResult {
+            test_name: "桥接协议协商测试".to_string(),
+            success: false,
+            message: "".to_string(),
+            duration_ms: 0,
+            details: Some(HashMap::new()),
+            error_details: None,
+        };
+
+        match self.run_bridge_protocol_test().await {
+            Ok(info) => {
+                test.success = true;
+                test.message = "握手、兼容矩阵与解析模式正常".to_string();
+                test.details.as_mut().unwrap().insert("protocol_info".to_string(), info);
+            }
+            Err(e) => {
+                test.success = false;
+                test.message = "桥接协议协商测试失败".to_string();
+                test.error_details = Some(e.to_string());
+            }
+        }
+
+        test.duration_ms = start.elapsed().as_millis() as u64;
+        self.test_results.push(test);
+    }
+
+    async fn run_bridge_protocol_test(&self) -> Result<String, Box<dyn std::error::Error>> {
+        use crate::bridge_protocol::{
+            begin_session, bridge_get_protocol_status, evaluate_compat, handle_line, set_mode, BridgeCompat, BridgeHello, BridgeLine,
+            BridgeParseMode, HOST_PROTOCOL_VERSION,
+        };
+
+        let hello = |v: u32, min: u32, caps: &[&str]| {
+            serde_json::json!({ "type": "hello", "protocolVersion": v, "minHostVersion": min, "bridgeVersion": "9.9", "capabilities": caps }).to_string()
+        };
+        let frame = r#"{"cpuTempC":51.5,"hbTick":3,"fanCurveId":"silent"}"#;
+
+        // 兼容矩阵
+        let mk = |v: u32, min: Option<u32>| BridgeHello { kind: "hello".into(), protocol_version: v, min_host_version: min, ..Default::default() };
+        let matrix = [
+            (evaluate_compat(None), BridgeCompat::Legacy),
+            (evaluate_compat(Some(&mk(HOST_PROTOCOL_VERSION, None))), BridgeCompat::Match),
+            (evaluate_compat(Some(&mk(HOST_PROTOCOL_VERSION + 1, Some(HOST_PROTOCOL_VERSION)))), BridgeCompat::BridgeNewer),
+            (evaluate_compat(Some(&mk(HOST_PROTOCOL_VERSION + 1, None))), BridgeCompat::Incompatible),
+            (evaluate_compat(Some(&mk(0, Some(0)))), BridgeCompat::BridgeOlder),
+        ];
+        if let Some((got, want)) = matrix.iter().find(|(g, w)| g != w) {
+            return Err(format!("兼容矩阵错误: {:?} != {:?}", got, want).into());
+        }
+
+        // lenient：握手后接收数据帧，未知字段仅统计
+        set_mode(BridgeParseMode::Lenient);
+        begin_session();
+        if !matches!(handle_line(&hello(HOST_PROTOCOL_VERSION, 1, &["temps", "fans", "rgb"])), BridgeLine::Hello(_)) {
+            return Err("握手未识别".into());
+        }
+        match handle_line(frame) {
+            BridgeLine::Frame(out) if out.cpu_temp_c == Some(51.5) && out.hb_tick == Some(3) => {}
+            other => return Err(format!("lenient 数据帧解析错误: {:?}", other).into()),
+        }
+        if !matches!(handle_line("Unhandled exception"), BridgeLine::NonJson) {
+            return Err("非 JSON 行未识别".into());
+        }
+        let st = bridge_get_protocol_status();
+        if st.compat != BridgeCompat::Match
+            || st.warning.is_some()
+            || st.unknown_fields.get("fanCurveId") != Some(&1)
+            || st.unknown_fields.len() != 1
+            || !st.missing_capabilities.iter().any(|c| c == "gpus")
+            || st.extra_capabilities != vec!["rgb".to_string()]
+            || st.frames_ok != 1
+            || st.non_json_lines != 1
+        {
+            return Err(format!("lenient 状态错误: {:?}", st).into());
+        }
+
+        // strict：含未知字段的数据帧被拒收，已知字段帧照常接收
+        set_mode(BridgeParseMode::Strict);
+        if !matches!(handle_line(frame), BridgeLine::Rejected(_)) || !matches!(handle_line(r#"{"cpuTempC":50}"#), BridgeLine::Frame(_)) {
+            return Err("strict 模式未拒收未知字段".into());
+        }
+
+        // strict：未握手的旧版桥接被拒收并给出提示；lenient 下接收
+        begin_session();
+        if !matches!(handle_line(r#"{"cpuTempC":50}"#), BridgeLine::Rejected(_)) || bridge_get_protocol_status().warning.is_none() {
+            return Err("strict 模式未拒收旧版桥接".into());
+        }
+        set_mode(BridgeParseMode::Lenient);
+        let legacy = bridge_get_protocol_status();
+        if !matches!(handle_line(r#"{"cpuTempC":50}"#), BridgeLine::Frame(_)) || legacy.compat != BridgeCompat::Legacy || legacy.warning.is_some() {
+            return Err(format!("lenient 模式旧版桥接处理错误: {:?}", legacy).into());
+        }
+
+        // 不兼容版本：数据帧全部拒收，提示包含版本号
+        begin_session();
+        handle_line(&hello(HOST_PROTOCOL_VERSION + 1, HOST_PROTOCOL_VERSION + 1, &[]));
+        let bad = bridge_get_protocol_status();
+        if !matches!(handle_line(r#"{"cpuTempC":50}"#), BridgeLine::Rejected(_))
+            || bad.compat != BridgeCompat::Incompatible
+            || !bad.warning.as_deref().unwrap_or("").contains("9.9")
+        {
+            return Err(format!("不兼容版本处理错误: {:?}", bad).into());
+        }
+        begin_session();
+
+        Ok(format!("协议 v{}，兼容矩阵 {} 项、lenient/strict 解析均符合预期", HOST_PROTOCOL_VERSION, matrix.len()))
+    }
+
+    async fn test_bridge_watchdog(&mut self) {
+        let start = Instant::now();
+        let mut test = TestResult {
+            test_name: "桥接看门狗测试".to_string(),
+            success: false,
+            message: "".to_string(),
+            duration_ms: 0,
+            details: Some(HashMap::new()),
+            error_details: None,
+        };
+
+        match self.run_bridge_watchdog_test().await {
+            Ok(info) => {
+                test.success = true;
+                test.message = "看门狗判定与退避正常".to_string();
+                test.details.as_mut().unwrap().insert("watchdog_info".to_string(), info);
+            }
+            Err(e) => {
+                test.success = false;
+                test.message = "桥接看门狗测试失败".to_string();
+                test.error_details = Some(e.to_string());
+            }
+        }
+
+        test.duration_ms = start.elapsed().as_millis() as u64;
+        self.test_results.push(test);
+    }
+
+    async fn run_bridge_watchdog_test(&self) -> Result<String, Box<dyn std::error::Error>> {
+        use crate::bridge_manager::{check_watchdog, Backoff, BridgeWatchdogConfig, WatchdogProbe};
+
+        let cfg = BridgeWatchdogConfig { stale_sec: Some(5), hb_stall_sec: Some(10), startup_grace_sec: Some(20), ..Default::default() };
+        let t0 = Instant::now();
+        let at = |s: u64| t0 + Duration::from_secs(s);
+
+        // 启动宽限期内无输出不重启，超出后重启
+        let mut probe = WatchdogProbe::new(t0);
+        if check_watchdog(&cfg, &probe, at(19)).is_some() || check_watchdog(&cfg, &probe, at(21)).is_none() {
+            return Err("启动宽限期判定错误".into());
+        }
+
+        // 有输出且心跳前进：健康；输出中断超过 stale_sec：重启
+        for s in 0..=8 { probe.on_line(at(s), Some(s as i64)); }
+        if check_watchdog(&cfg, &probe, at(12)).is_some() {
+            return Err("健康桥接被误判".into());
+        }
+        let stale = check_watchdog(&cfg, &probe, at(14)).ok_or("输出中断未检测")?;
+
+        // 仍在输出但 hb_tick 不前进：超过 hb_stall_sec 后重启
+        for s in 9..=20 { probe.on_line(at(s), Some(8)); }
+        if check_watchdog(&cfg, &probe, at(18)).is_some() {
+            return Err("心跳停滞过早判定".into());
+        }
+        let stall = check_watchdog(&cfg, &probe, at(20)).ok_or("心跳停滞未检测")?;
+        if !stall.contains("hb_tick=8") {
+            return Err(format!("停滞原因缺少心跳值: {}", stall).into());
+        }
+
+        // 关闭看门狗后不做判定
+        let off = BridgeWatchdogConfig { enabled: Some(false), ..cfg.clone() };
+        if check_watchdog(&off, &probe, at(100)).is_some() {
+            return Err("关闭后仍判定".into());
+        }
+
+        // 指数退避：连续快速失败翻倍并封顶，稳定运行后复位
+        let bcfg = BridgeWatchdogConfig { backoff_initial_ms: Some(500), backoff_max_ms: Some(3000), backoff_reset_sec: Some(60), ..Default::default() };
+        let mut backoff = Backoff::default();
+        let quick = Some(Duration::from_secs(2));
+        let delays: Vec<u64> = vec![
+            backoff.delay_ms(&bcfg, quick),
+            backoff.delay_ms(&bcfg, quick),
+            backoff.delay_ms(&bcfg, None),
+            backoff.delay_ms(&bcfg, quick),
+            backoff.delay_ms(&bcfg, quick),
+            backoff.delay_ms(&bcfg, Some(Duration::from_secs(120))),
+            backoff.delay_ms(&bcfg, quick),
+        ];
+        if delays != vec![500, 1000, 2000, 3000, 3000, 500, 1000] {
+            return Err(format!("退避序列错误: {:?}", delays).into());
+        }
+
+        Ok(format!("{}；{}；退避 {:?}", stale, stall, delays))
+    }
+
+    async fn test_bridge_command(&mut self) {
+        let start = Instant::now();
+        let mut test = TestResult {
+            test_name: "桥接命令通道测试".to_string(),
+            success: false,
+            message: "".to_string(),
+            duration_ms: 0,
+            details: Some(HashMap::new()),
+            error_details: None,
+        };
+
+        match self.run_bridge_command_test().await {
+            Ok(info) => {
+                test.success = true;
+                test.message = "命令请求/响应、超时与错误上报正常".to_string();
+                test.details.as_mut().unwrap().insert("command_info".to_string(), info);
+            }
+            Err(e) => {
+                test.success = false;
+                test.message = "桥接命令通道测试失败".to_string();
+                test.error_details = Some(e.to_string());
+            }
+        }
+
+        test.duration_ms = start.elapsed().as_millis() as u64;
+        self.test_results.push(test);
+    }
+
+    async fn run_bridge_command_test(&self) -> Result<String, Box<dyn std::error::Error>> {
+        use crate::bridge_command::{active_interval_ms, attach, call, detach, set_hardware, set_interval, BridgeControlConfig, BridgeHardwareGroups};
+        use crate::bridge_manager::{check_watchdog, BridgeWatchdogConfig, ControlSync, WatchdogProbe};
+        use crate::bridge_protocol::{begin_session, handle_line, HOST_PROTOCOL_VERSION};
+
+        let hello = |caps: &[&str]| {
+            serde_json::json!({ "type": "hello", "protocolVersion": HOST_PROTOCOL_VERSION, "bridgeVersion": "test", "capabilities": caps }).to_string()
+        };
+        let timeout = Duration::from_millis(300);
+
+        // 未声明 commands 能力：直接拒绝，不写 stdin
+        begin_session();
+        handle_line(&hello(&["temps"]));
+        attach(Box::new(FakeBridgeStdin::default()));
+        if call("ping", serde_json::Value::Null, timeout).is_ok() {
+            return Err("旧版桥接未拒绝命令".into());
+        }
+
+        // 声明 commands 后：请求经替身桥接以响应行回到 handle_line
+        begin_session();
+        handle_line(&hello(&["temps", "commands"]));
+        let v = set_interval(250, Some(1000))?;
+        if v.get("intervalMs").and_then(|x| x.as_u64()) != Some(250) {
+            return Err(format!("setInterval 响应错误: {}", v).into());
+        }
+        let groups = set_hardware(&BridgeHardwareGroups { gpu: Some(false), ..Default::default() }, Some(1000))?;
+        if groups.gpu != Some(false) || groups.cpu != Some(true) {
+            return Err(format!("setHardware 响应错误: {:?}", groups).into());
+        }
+        if set_interval(50, Some(1000)).is_ok() {
+            return Err("越界采样间隔未拒绝".into());
+        }
+
+        // 调到 60s 采样：看门狗阈值随之放宽到 3 个周期，默认 15s/30s 阈值下不应反复重启
+        set_interval(60_000, Some(1000))?;
+        let t0 = Instant::now();
+        let mut probe = WatchdogProbe::new(t0);
+        probe.on_line(t0, Some(1));
+        probe.interval_ms = active_interval_ms();
+        let wd = BridgeWatchdogConfig::default();
+        if probe.interval_ms != Some(60_000) || check_watchdog(&wd, &probe, t0 + Duration::from_secs(90)).is_some() {
+            return Err(format!("长采样间隔下看门狗误判: {:?}", probe.interval_ms).into());
+        }
+        if check_watchdog(&wd, &probe, t0 + Duration::from_secs(181)).is_none() {
+            return Err("长采样间隔下停滞未检测".into());
+        }
+
+        // 桥接返回错误
+        let err = call("noSuchMethod", serde_json::Value::Null, timeout).err().ok_or("错误响应未上报")?;
+        if !err.contains("-32601") {
+            return Err(format!("错误响应内容错误: {}", err).into());
+        }
+
+        // 无响应：超时；迟到的响应被丢弃且不影响后续请求
+        let t = Instant::now();
+        let late = call("hang", serde_json::Value::Null, timeout).err().ok_or("无响应未超时")?;
+        if !late.contains("超时") || t.elapsed() > Duration::from_secs(2) {
+            return Err(format!("超时处理错误: {}", late).into());
+        }
+        std::thread::sleep(Duration::from_millis(400));
+        call("ping", serde_json::Value::Null, timeout)?;
+
+        // 桥接退出：挂起的请求立即失败，之后的请求报告未运行
+        let pending = std::thread::spawn(|| call("hang", serde_json::Value::Null, Duration::from_secs(5)));
+        std::thread::sleep(Duration::from_millis(100));
+        detach("桥接进程已退出");
+        let gone = pending.join().map_err(|_| "请求线程异常")?.err().ok_or("detach 后请求未失败")?;
+        let after = call("ping", serde_json::Value::Null, timeout).err().ok_or("detach 后仍可发送")?;
+        if !gone.contains("已退出") || !after.contains("未运行") {
+            return Err(format!("detach 处理错误: {} / {}", gone, after).into());
+        }
+        if active_interval_ms().is_some() {
+            return Err("桥接退出后仍沿用旧会话的采样间隔".into());
+        }
+        begin_session();
+
+        // bridge_control：下发失败不记为已下发，退避后重试；成功后同一配置不再重复下发
+        let control = BridgeControlConfig { interval_ms: Some(500), hardware: None };
+        let mut sync = ControlSync::default();
+        let t0 = Instant::now();
+        if !sync.begin(&control, t0) || sync.begin(&control, t0) {
+            return Err("bridge_control 首次下发/在途去重错误".into());
+        }
+        sync.finish(control.clone(), false, t0);
+        if sync.begin(&control, t0 + Duration::from_secs(1)) {
+            return Err("bridge_control 失败后未退避".into());
+        }
+        if !sync.begin(&control, t0 + Duration::from_secs(6)) {
+            return Err("bridge_control 失败后未重试".into());
+        }
+        sync.finish(control.clone(), true, t0 + Duration::from_secs(6));
+        if sync.begin(&control, t0 + Duration::from_secs(20)) {
+            return Err("bridge_control 成功后仍重复下发".into());
+        }
+        let changed = BridgeControlConfig { interval_ms: Some(2000), hardware: None };
+        if !sync.begin(&changed, t0 + Duration::from_secs(20)) {
+            return Err("bridge_control 修改后未重新下发".into());
+        }
+
+        Ok(format!("setHardware -> {:?}；{}；{}", groups, err, late))
+    }
+
+    async fn test_bridge_replay(&mut self) {
+        let start = Instant::now();
+        let mut test = TestResult {
+            test_name: "桥接录制与回放测试".to_string(),
+            success: false,
+            message: "".to_string(),
+            duration_ms: 0,
+            details: Some(HashMap::new()),
+            error_details: None,
+        };
+
+        match self.run_bridge_replay_test().await {
+            Ok(info) => {
+                test.success = true;
+                test.message = "录制、轮转与倍速回放正常".to_string();
+                test.details.as_mut().unwrap().insert("replay_info".to_string(), info);
+            }
+            Err(e) => {
+                test.success = false;
+                test.message = "桥接录制与回放测试失败".to_string();
+                test.error_details = Some(e.to_string());
+            }
+        }
+
+        test.duration_ms = start.elapsed().as_millis() as u64;
+        self.test_results.push(test);
+    }
+
+    async fn run_bridge_replay_test(&self) -> Result<String, Box<dyn std::error::Error>> {
+        use crate::bridge_replay::{bridge_get_replay_status, load_recording, replay_delays, BridgeRecorder, BridgeReplayConfig};
+        use std::sync::atomic::{AtomicBool, Ordering};
+        use std::sync::{Arc, Mutex};
+
+        let dir = std::env::temp_dir().join(format!("sys-sensor-replay-test-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let path = dir.join("capture.jsonl");
+
+        // 录制：握手 + 3 帧，间隔 1s；原始行原样保存
+        let hello = r#"{"type":"hello","protocolVersion":2,"capabilities":["temps"]}"#;
+        let frames: Vec<String> = (1..=3).map(|i| format!(r#"{{"cpuTempC":{},"hbTick":{}}}"#, 40 + i, i)).collect();
+        let mut rec = BridgeRecorder::create(&path, 1 << 20)?;
+        rec.record(1_000, hello)?;
+        for (i, f) in frames.iter().enumerate() { rec.record(1_000 + (i as i64 + 1) * 1000, f)?; }
+        drop(rec);
+        std::fs::write(dir.join("junk.jsonl"), "not json\n")?;
+        let lines = load_recording(&path)?;
+        if lines.len() != 4 || lines[0].line != hello || lines[3].line != frames[2] || lines[3].ts != 4_000 {
+            return Err(format!("录制内容错误: {:?}", lines).into());
+        }
+        if load_recording(&dir.join("junk.jsonl")).is_ok() || load_recording(&dir.join("missing.jsonl")).is_ok() {
+            return Err("无效回放文件未报错".into());
+        }
+
+        // 倍速与最大间隔
+        let mut gappy = lines.clone();
+        gappy[3].ts = 600_000;
+        let delays = replay_delays(&gappy, 4.0, 10_000);
+        if delays != vec![0, 250, 250, 2500] {
+            return Err(format!("回放间隔错误: {:?}", delays).into());
+        }
+
+        // 超过上限时轮转为 .1
+        let small = dir.join("small.jsonl");
+        let mut rec = BridgeRecorder::create(&small, 120)?;
+        for f in frames.iter() { rec.record(0, f)?; }
+        let rotated = load_recording(&dir.join("small.jsonl.1"))?;
+        let current = load_recording(&small)?;
+        if rotated.len() + current.len() != 3 || current.is_empty() {
+            return Err(format!("录制轮转错误: {} + {}", rotated.len(), current.len()).into());
+        }
+
+        // 经 bridge_manager 回放（100 倍速，不循环）：数据帧进入共享槽位，协议握手随之完成
+        let bridge_data: Arc<Mutex<(Option<crate::types::BridgeOut>, Instant)>> = Arc::new(Mutex::new((None, Instant::now())));
+        let shutdown = Arc::new(AtomicBool::new(false));
+        let config = Arc::new(Mutex::new(crate::config_utils::AppConfig {
+            bridge_replay: Some(BridgeReplayConfig { path: Some(path.display().to_string()), speed: Some(100.0), ..Default::default() }),
+            ..Default::default()
+        }));
+        crate::bridge_manager::start_bridge_manager(bridge_data.clone(), None, shutdown.clone(), Arc::new(Mutex::new(None)), config);
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while !bridge_get_replay_status().replay_finished && Instant::now() < deadline {
+            std::thread::sleep(Duration::from_millis(20));
+        }
+        shutdown.store(true, Ordering::SeqCst);
+        let st = bridge_get_replay_status();
+        let last = bridge_data.lock().ok().and_then(|g| g.0.clone());
+        let proto = crate::bridge_protocol::bridge_get_protocol_status();
+        crate::bridge_protocol::begin_session();
+        let _ = std::fs::remove_dir_all(&dir);
+        if !st.replay_finished || st.replay_position != 4 || st.replay_error.is_some() {
+            return Err(format!("回放状态错误: {:?}", st).into());
+        }
+        if last.as_ref().and_then(|o| o.hb_tick) != Some(3) || proto.frames_ok != 3 || proto.handshake.is_none() {
+            return Err(format!("回放数据错误: {:?} / {:?}", last.map(|o| o.cpu_temp_c), proto).into());
+        }
+
+        Ok(format!("录制 {} 行，4x 间隔 {:?}，回放 {}/{} 行", lines.len(), delays, st.replay_position, st.replay_total))
+    }
+
+    async fn test_sensor_sim(&mut self) {
+        let start = Instant::now();
+        let mut test = TestResult {
+            test_name: "合成传感器模拟器测试".to_string(),
+            success: false,
+            message: "".to_string(),
+            duration_ms: 0,
+            details: Some(HashMap::new()),
+            error_details: None,
+        };
+
+        match self.run_sensor_sim_test().await {
+            Ok(info) => {
+                test.success = true;
+                test.message = "场景模型与桥接替身正常".to_string();
+                test.details.as_mut().unwrap().insert("sim_info".to_string(), info);
+            }
+            Err(e) => {
+                test.success = false;
+                test.message = "合成传感器模拟器测试失败".to_string();
+                test.error_details = Some(e.to_string());
+            }
+        }
+
+        test.duration_ms = start.elapsed().as_millis() as u64;
+        self.test_results.push(test);
+    }
+
+    async fn run_sensor_sim_test(&self) -> Result<String, Box<dyn std::error::Error>> {
+        use crate::sensor_sim::{
+            current_overlay, BatteryModel, DiskFailure, DiskModel, LoadModel, LoadSpike, NetBurst, NetworkModel, Scenario, SimFrame,
+            Simulator, SimulatorConfig, TimeWindow,
+        };
+        use std::sync::atomic::{AtomicBool, Ordering};
+        use std::sync::{Arc, Mutex};
+
+        // 无噪声场景：10% 基线负载，60..360s 满载；每 100s 一次 10s 网络突发；100..200s 接通电源；磁盘从 0s 开始劣化
+        let scenario = Scenario {
+            load: LoadModel { base_pct: 10.0, waves: Vec::new(), spikes: vec![LoadSpike { at_sec: 60.0, duration_sec: 300.0, pct: 100.0 }], noise_pct: 0.0, cores: 4 },
+            network: NetworkModel { noise: 0.0, bursts: vec![NetBurst { every_sec: 100.0, duration_sec: 10.0, rx_bps: 1e6, ..Default::default() }], ..Default::default() },
+            battery: Some(BatteryModel { start_pct: 50.0, ac_windows: vec![TimeWindow { from_sec: 100.0, to_sec: 200.0 }], ..Default::default() }),
+            disks: vec![DiskModel { failing: Some(DiskFailure { reallocated_per_hour: 3600.0, predict_fail_at: 50, ..Default::default() }), ..Default::default() }],
+            ..Default::default()
+        };
+        let mut sim = Simulator::new(scenario.clone());
+        let frames: Vec<SimFrame> = (0..300).map(|_| sim.step(1.0)).collect();
+        let at = |sec: usize| &frames[sec - 1];
+        let temp = |sec: usize| at(sec).bridge["cpuTempC"].as_f64().unwrap_or(0.0);
+        let fan = |sec: usize| at(sec).bridge["fans"][0]["rpm"].as_i64().unwrap_or(0);
+
+        // 数据帧与 BridgeOut 同构，且不含未知字段
+        let obj = at(1).bridge.as_object().ok_or("数据帧不是对象")?;
+        let unknown = crate::bridge_protocol::unknown_fields(obj);
+        let parsed: crate::types::BridgeOut = serde_json::from_value(at(1).bridge.clone())?;
+        if !unknown.is_empty() || parsed.cpu_core_loads_pct.as_ref().map(|v| v.len()) != Some(4) || parsed.hb_tick != Some(1) {
+            return Err(format!("数据帧结构错误: unknown={:?}", unknown).into());
+        }
+
+        // 同一 seed 可复现
+        let mut again = Simulator::new(scenario.clone());
+        if (0..5).map(|_| again.step(1.0).bridge).collect::<Vec<_>>() != frames[..5].iter().map(|f| f.bridge.clone()).collect::<Vec<_>>() {
+            return Err("同一 seed 输出不一致".into());
+        }
+
+        // 热惯性：负载阶跃后温度逐步上升并趋于稳态，风扇跟随温度
+        let th = &scenario.thermal;
+        let (idle, full) = (th.ambient_c + th.rise_at_full_load_c * 0.1, th.ambient_c + th.rise_at_full_load_c);
+        if (temp(59) - idle).abs() > 0.5 || temp(61) - temp(59) > 5.0 || (temp(250) - full).abs() > 1.0 {
+            return Err(format!("温度惯性错误: {:.1} / {:.1} / {:.1}", temp(59), temp(61), temp(250)).into());
+        }
+        if fan(250) <= fan(59) + 500 || at(250).overlay.cpu_usage < 99.0 {
+            return Err(format!("风扇未跟随温度: {} -> {}", fan(59), fan(250)).into());
+        }
+
+        // 网络突发
+        if at(5).overlay.net_rx_bps < 1e6 || at(50).overlay.net_rx_bps >= 1e6 || at(105).overlay.net_rx_bps < 1e6 {
+            return Err("网络突发窗口错误".into());
+        }
+
+        // 电池：放电 -> 接通电源充电 -> 再放电
+        let bat = |sec: usize| at(sec).overlay.battery_percent.unwrap_or(-1);
+        let st = |sec: usize| at(sec).overlay.battery_status.clone().unwrap_or_default();
+        if !(bat(99) < 50 && bat(199) > bat(99) && bat(299) < bat(199)) || st(50) != "放电中" || st(150) != "充电中" {
+            return Err(format!("电池模型错误: {} {} {} / {} {}", bat(99), bat(199), bat(299), st(50), st(150)).into());
+        }
+        if at(50).overlay.battery_time_remaining_sec.is_none() || at(150).overlay.battery_time_to_full_sec.is_none() {
+            return Err("电池剩余/充满时间缺失".into());
+        }
+
+        // 磁盘劣化：重映射扇区随时间增长，达到阈值后 predict_fail
+        let disk = |sec: usize| at(sec).overlay.smart_health.as_ref().and_then(|v| v.first().cloned()).ok_or("缺少 SMART");
+        let (d10, d60) = (disk(10)?, disk(60)?);
+        if d10.reallocated != Some(10) || d10.predict_fail != Some(false) || d60.reallocated != Some(60) || d60.predict_fail != Some(true) {
+            return Err(format!("磁盘劣化错误: {:?} / {:?}", d10.reallocated, d60.reallocated).into());
+        }
+
+        // 作为桥接替身：bridge_manager 以模拟器输出数据帧并发布覆盖指标，退出后清除
+        let bridge_data: Arc<Mutex<(Option<crate::types::BridgeOut>, Instant)>> = Arc::new(Mutex::new((None, Instant::now())));
+        let shutdown = Arc::new(AtomicBool::new(false));
+        let config = Arc::new(Mutex::new(crate::config_utils::AppConfig {
+            bridge_simulator: Some(SimulatorConfig { scenario: Some(scenario), interval_ms: Some(50), speed: Some(20.0), ..Default::default() }),
+            ..Default::default()
+        }));
+        crate::bridge_manager::start_bridge_manager(bridge_data.clone(), None, shutdown.clone(), Arc::new(Mutex::new(None)), config);
+        let deadline = Instant::now() + Duration::from_secs(5);
+        let mut hb = None;
+        while Instant::now() < deadline {
+            hb = bridge_data.lock().ok().and_then(|g| g.0.as_ref().and_then(|o| o.hb_tick));
+            if hb.unwrap_or(0) >= 3 && current_overlay().is_some() { break; }
+            std::thread::sleep(Duration::from_millis(20));
+        }
+        let proto = crate::bridge_protocol::bridge_get_protocol_status();
+        shutdown.store(true, Ordering::SeqCst);
+        let deadline = Instant::now() + Duration::from_secs(2);
+        while current_overlay().is_some() && Instant::now() < deadline {
+            std::thread::sleep(Duration::from_millis(20));
+        }
+        crate::bridge_protocol::begin_session();
+        if hb.unwrap_or(0) < 3 || proto.handshake.as_ref().and_then(|h| h.bridge_version.clone()).as_deref() != Some("simulator") {
+            return Err(format!("桥接替身未输出数据: hb={:?}", hb).into());
+        }
+        if current_overlay().is_some() {
+            return Err("模拟器退出后覆盖指标未清除".into());
+        }
+
+        Ok(format!(
+            "温度 {:.1}→{:.1}°C，风扇 {}→{} RPM，电池 {}%→{}%→{}%，磁盘重映射 {:?}",
+            temp(59), temp(250), fan(59), fan(250), bat(99), bat(199), bat(299), d60.reallocated
+        ))
+    }
+
+    async fn test_rtt_stats(&mut self) {
+        let start = Instant::now();
+        let mut test = TestResult {
+            test_name: "RTT多包统计测试".to_string(),
+            success: false,
+            message: "".to_string(),
+            duration_ms: 0,
+            details: Some(HashMap::new()),
+            error_details: None,
+        };
+
+        match self.run_rtt_stats_test().await {
+            Ok(info) => {
+                test.success = true;
+                test.message = "丢包率/百分位/抖动统计正常".to_string();
+                test.details.as_mut().unwrap().insert("rtt_stats_info".to_string(), info);
+            }
+            Err(e) => {
+                test.success = false;
+                test.message = "RTT多包统计测试失败".to_string();
+                test.error_details = Some(e.to_string());
+            }
+        }
+
+        test.duration_ms = start.elapsed().as_millis() as u64;
+        self.test_results.push(test);
+    }
+
+    async fn run_rtt_stats_test(&self) -> Result<String, Box<dyn std::error::Error>> {
+        use crate::ping_utils::{measure_rtt_burst, rtt_stats};
+
+        let close = |a: Option<f64>, b: f64| a.map(|v| (v - b).abs() < 1e-9).unwrap_or(false);
+
+        // 5 个包丢 1 个：排序后 [10, 11, 12, 30]
+        let r = rtt_stats("t", &[Some(10.0), Some(12.0), None, Some(11.0), Some(30.0)]);
+        if r.sent != Some(5) || r.received != Some(4) || !close(r.loss_pct, 20.0) || r.success != Some(true) {
+            return Err(format!("包数/丢包率错误: {:?}", r).into());
+        }
+        if !close(r.min_ms, 10.0) || !close(r.max_ms, 30.0) || !close(r.avg_ms, 15.75) || !close(r.rtt_ms, 15.75) {
+            return Err(format!("min/avg/max 错误: {:?}", r).into());
+        }
+        if !close(r.p50_ms, 11.0) || !close(r.p95_ms, 30.0) {
+            return Err(format!("百分位错误: {:?}", r).into());
+        }
+        let var = [10.0f64, 12.0, 11.0, 30.0].iter().map(|v| (v - 15.75).powi(2)).sum::<f64>() / 4.0;
+        if !close(r.stddev_ms, var.sqrt()) {
+            return Err(format!("标准差错误: {:?}", r).into());
+        }
+        // RFC 3550：相邻成功样本差 2、1、19，J += (|D| - J) / 16
+        let mut j = 0.0f64;
+        for d in [2.0f64, 1.0, 19.0] { j += (d - j) / 16.0; }
+        if !close(r.jitter_ms, j) {
+            return Err(format!("抖动错误: 期望 {:.4}，实际 {:?}", j, r.jitter_ms).into());
+        }
+
+        // 全部丢失：丢包 100%，无延迟统计
+        let lost = rtt_stats("t", &[None, None, None]);
+        if lost.success != Some(false) || !close(lost.loss_pct, 100.0) || lost.rtt_ms.is_some() || lost.jitter_ms.is_some() {
+            return Err(format!("全丢包统计错误: {:?}", lost).into());
+        }
+        // 单个样本：无抖动
+        let one = rtt_stats("t", &[Some(5.0)]);
+        if !close(one.jitter_ms, 0.0) || !close(one.p95_ms, 5.0) || !close(one.stddev_ms, 0.0) {
+            return Err(format!("单样本统计错误: {:?}", one).into());
+        }
+
+        // 实测：本地监听端口连发 4 个包应全部收到
+        let listener = std::net::TcpListener::bind("127.0.0.1:0")?;
+        let open = format!("127.0.0.1:{}", listener.local_addr()?.port());
+        let defaults = crate::ping_utils::ProbeDefaults { timeout_ms: 500, count: 4, interval_ms: 10, ..Default::default() };
+        let burst = measure_rtt_burst(&crate::ping_utils::RttTarget::new(&open), &defaults);
+        if burst.sent != Some(4) || burst.received != Some(4) || !close(burst.loss_pct, 0.0) || burst.p95_ms.is_none() {
+            return Err(format!("本地多包探测错误: {:?}", burst).into());
+        }
+
+        Ok(format!("5包丢1 → loss=20%，p50={:?}，p95={:?}，jitter={:.3}ms；本地 {} 4/4", r.p50_ms, r.p95_ms, j, open))
+    }
+
+    async fn test_rtt_ipv6(&mut self) {
+        let start = Instant::now();
+        let mut test = TestResult {
+            test_name: "RTT IPv6与地址族测试".to_string(),
+            success: false,
+            message: "".to_string(),
+            duration_ms: 0,
+            details: Some(HashMap::new()),
+            error_details: None,
+        };
+
+        match self.run_rtt_ipv6_test().await {
+            Ok(info) => {
+                test.success = true;
+                test.message = "目标解析与地址族选择正常".to_string();
+                test.details.as_mut().unwrap().insert("rtt_ipv6_info".to_string(), info);
+            }
+            Err(e) => {
+                test.success = false;
+                test.message = "RTT IPv6与地址族测试失败".to_string();
+                test.error_details = Some(e.to_string());
+            }
+        }
+
+        test.duration_ms = start.elapsed().as_millis() as u64;
+        self.test_results.push(test);
+    }
+
+    async fn run_rtt_ipv6_test(&self) -> Result<String, Box<dyn std::error::Error>> {
+        use crate::ping_utils::{measure_multi_rtt, parse_target, resolve_target, FamilyPref, ProbeDefaults, RttTarget};
+
+        // 目标解析
+        let cases = [
+            ("1.1.1.1:443", "1.1.1.1", Some(443), false),
+            ("example.com", "example.com", None, false),
+            ("[2001:db8::1]:8443", "2001:db8::1", Some(8443), false),
+            ("[::1]", "::1", None, false),
+            ("2001:db8::1", "2001:db8::1", None, false),
+            ("https://[2001:db8::1]:8443/health?x=1", "2001:db8::1", Some(8443), true),
+            ("http://user@example.com/path", "example.com", None, true),
+        ];
+        for (input, host, port, is_url) in cases {
+            let t = parse_target(input);
+            if t.host != host || t.port != port || t.url.is_some() != is_url {
+                return Err(format!("目标解析错误 {}: {:?}", input, t).into());
+            }
+        }
+
+        // 地址族过滤
+        if !resolve_target("2001:db8::1", FamilyPref::V4).is_empty() || !resolve_target("127.0.0.1", FamilyPref::V6).is_empty() {
+            return Err("地址族过滤错误".into());
+        }
+        if resolve_target("::1", FamilyPref::Auto) != vec!["::1".parse::<std::net::IpAddr>()?] {
+            return Err("IPv6 字面量解析错误".into());
+        }
+
+        // IPv6 环回：本机不支持 IPv6 时跳过实测
+        let listener = match std::net::TcpListener::bind("[::1]:0") {
+            Ok(l) => l,
+            Err(e) => return Ok(format!("目标解析/地址族过滤正常；本机无 IPv6 环回，跳过实测（{}）", e)),
+        };
+        let target = format!("[::1]:{}", listener.local_addr()?.port());
+        let defaults = ProbeDefaults { timeout_ms: 500, count: 2, interval_ms: 0, ..Default::default() };
+        let both = measure_multi_rtt(&[RttTarget { family: Some(FamilyPref::Both), ..RttTarget::new(&target) }], &defaults);
+        let v6 = both.iter().find(|r| r.family.as_deref() == Some("v6")).ok_or("缺少 v6 结果")?;
+        let v4 = both.iter().find(|r| r.family.as_deref() == Some("v4")).ok_or("缺少 v4 结果")?;
+        if both.len() != 2 || v6.received != Some(2) || v6.addr.as_deref() != Some("::1") {
+            return Err(format!("v6 结果错误: {:?}", both).into());
+        }
+        // IPv6 字面量没有 IPv4 地址：v4 结果全部丢失
+        if v4.success != Some(false) || v4.loss_pct != Some(100.0) || v4.addr.is_some() {
+            return Err(format!("v4 结果错误: {:?}", v4).into());
+        }
+        let he = measure_multi_rtt(&[RttTarget { family: Some(FamilyPref::HappyEyeballs), count: Some(1), ..RttTarget::new(&target) }], &defaults);
+        if he.len() != 1 || he[0].success != Some(true) || he[0].family.as_deref() != Some("v6") {
+            return Err(format!("happy eyeballs 结果错误: {:?}", he).into());
+        }
+
+        Ok(format!("目标解析/地址族过滤正常；{} v6 {:.2}ms，v4 无地址", target, v6.avg_ms.unwrap_or_default()))
+    }
+
+    async fn test_icmp_echo(&mut self) {
+        let start = Instant::now();
+        let mut test = TestResult {
+            test_name: "ICMP回显测试".to_string(),
+            success: false,
+            message: "".to_string(),
+            duration_ms: 0,
+            details: Some(HashMap::new()),
+            error_details: None,
+        };
+
+        match self.run_icmp_echo_test().await {
+            Ok(info) => {
+                test.success = true;
+                test.message = "ICMP 回显与回复匹配正常".to_string();
+                test.details.as_mut().unwrap().insert("icmp_info".to_string(), info);
+            }
+            Err(e) => {
+                test.success = false;
+                test.message = "ICMP回显测试失败".to_string();
+                test.error_details = Some(e.to_string());
+            }
+        }
+
+        test.duration_ms = start.elapsed().as_millis() as u64;
+        self.test_results.push(test);
+    }
+
+    async fn run_icmp_echo_test(&self) -> Result<String, Box<dyn std::error::Error>> {
+        use crate::ping_utils::{icmp_echo, measure_target_rtt, parse_target, FamilyPref};
+        use std::net::IpAddr;
+
+        // Linux：报文组装/校验和/回复匹配（不依赖权限）
+        #[cfg(target_os = "linux")]
+        {
+            use crate::icmp_linux::{build_request, checksum, matches_reply, strip_ip_header};
+            let v4: IpAddr = "127.0.0.1".parse()?;
+            let v6: IpAddr = "::1".parse()?;
+            let req = build_request(v4, 0x1234, 7);
+            if req[0] != 8 || checksum(&req) != 0 || req.len() != 40 {
+                return Err(format!("IPv4 回显请求错误: {:02x?}", &req[..8]).into());
+            }
+            if build_request(v6, 1, 1)[0] != 128 {
+                return Err("ICMPv6 回显请求类型错误".into());
+            }
+            let mut reply = req.clone();
+            reply[0] = 0;
+            if !matches_reply(v4, true, 0x1234, 7, &reply) || matches_reply(v4, true, 0x4321, 7, &reply) || matches_reply(v4, true, 0x1234, 8, &reply) {
+                return Err("原始套接字回复匹配错误".into());
+            }
+            // 数据报套接字：标识符由内核改写，只校验序号
+            if !matches_reply(v4, false, 0x4321, 7, &reply) || matches_reply(v4, false, 0x1234, 7, &req) {
+                return Err("数据报套接字回复匹配错误".into());
+            }
+            let mut packet = vec![0x45u8, 0, 0, 0, 0, 0, 0, 0, 57, 1, 0, 0, 127, 0, 0, 1, 127, 0, 0, 1];
+            packet.extend_from_slice(&reply);
+            let (icmp, ttl) = strip_ip_header(v4, true, &packet).ok_or("IP 头解析失败")?;
+            if ttl != Some(57) || icmp != reply.as_slice() || strip_ip_header(v4, true, &packet[..10]).is_some() {
+                return Err("IP 头剥离错误".into());
+            }
+        }
+
+        // 环回实测：无 ICMP 权限（或平台不支持）时跳过
+        let lo: IpAddr = "127.0.0.1".parse()?;
+        let Some(echo) = icmp_echo(lo, 1000) else {
+            return Ok("报文组装/回复匹配正常；无 ICMP 权限，跳过环回实测".to_string());
+        };
+        if !(echo.rtt_ms >= 0.0 && echo.rtt_ms < 1000.0) {
+            return Err(format!("环回 RTT 异常: {:?}", echo).into());
+        }
+        // 无端口目标走 ICMP，结果应带 TTL
+        let sample = measure_target_rtt(&parse_target("127.0.0.1"), FamilyPref::V4, 1000).map_err(|_| "环回测量失败")?;
+        if sample.addr != lo || sample.ttl.is_none() {
+            return Err(format!("环回测量结果错误: {:?}", sample).into());
+        }
+        Ok(format!("环回 ICMP {:.3}ms，TTL {:?}", sample.rtt_ms, sample.ttl))
+    }
+
+    async fn test_rtt_target_config(&mut self) {
+        let start = Instant::now();
+        let mut test = TestResult {
+            test_name: "RTT目标配置测试".to_string(),
+            success: false,
+            message: "".to_string(),
+            duration_ms: 0,
+            details: Some(HashMap::new()),
+            error_details: None,
+        };
+
+        match self.run_rtt_target_config_test().await {
+            Ok(info) => {
+                test.success = true;
+                test.message = "目标配置解析与各探测方式正常".to_string();
+                test.details.as_mut().unwrap().insert("rtt_target_info".to_string(), info);
+            }
+            Err(e) => {
+                test.success = false;
+                test.message = "RTT目标配置测试失败".to_string();
+                test.error_details = Some(e.to_string());
+            }
+        }
+
+        test.duration_ms = start.elapsed().as_millis() as u64;
+        self.test_results.push(test);
+    }
+
+    async fn run_rtt_target_config_test(&self) -> Result<String, Box<dyn std::error::Error>> {
+        use crate::ping_utils::{measure_rtt_burst, FamilyPref, ProbeDefaults, ProbeMethod, RttTarget};
+
+        // 字符串与对象混写；非法元素忽略
+        let cfg: AppConfig = serde_json::from_value(serde_json::json!({
+            "tray_show_mem": false,
+            "rtt_targets": [
+                "1.1.1.1:443",
+                { "target": "example.com", "name": "官网", "group": "web", "method": "https", "path": "/health", "expect_status": 204, "count": 2, "family": "v6" },
+                { "name": "缺少目标" },
+                42
+            ]
+        }))?;
+        let list = cfg.rtt_targets.clone().ok_or("rtt_targets 解析为空")?;
+        if list.len() != 2 || list[0] != RttTarget::new("1.1.1.1:443") {
+            return Err(format!("混写目标解析错误: {:?}", list).into());
+        }
+        let web = &list[1];
+        if web.name.as_deref() != Some("官网") || web.method != Some(ProbeMethod::Https) || web.count != Some(2) || web.family != Some(FamilyPref::V6) {
+            return Err(format!("对象目标解析错误: {:?}", web).into());
+        }
+        let probe = web.probe();
+        if probe.host != "example.com" || probe.method != ProbeMethod::Https || probe.path.as_deref() != Some("/health") || probe.expect_status != Some(204) {
+            return Err(format!("探测参数合并错误: {:?}", probe).into());
+        }
+        // 序列化回写时省略未设置字段
+        let js = serde_json::to_value(&list[0])?;
+        if js != serde_json::json!({ "target": "1.1.1.1:443" }) {
+            return Err(format!("目标序列化错误: {}", js).into());
+        }
+
+        // 热更新补丁同样接受混写目标；net_interfaces 仍为字符串列表
+        let mut patched = cfg.clone();
+        crate::config_utils::apply_patch(&mut patched, &serde_json::json!({
+            "rtt_targets": ["8.8.8.8", { "target": "example.org", "method": "http", "expect_status": 200 }, { "name": "缺少目标" }],
+            "net_interfaces": ["eth0", 1]
+        }));
+        let targets = patched.rtt_targets.clone().unwrap_or_default();
+        if targets.len() != 2 || targets[0] != RttTarget::new("8.8.8.8") || targets[1].method != Some(ProbeMethod::Http) || targets[1].expect_status != Some(200) {
+            return Err(format!("补丁目标解析错误: {:?}", targets).into());
+        }
+        if patched.net_interfaces != Some(vec!["eth0".to_string()]) {
+            return Err(format!("补丁网卡列表错误: {:?}", patched.net_interfaces).into());
+        }
+
+        let defaults = ProbeDefaults { timeout_ms: 1000, count: 1, interval_ms: 0, family: FamilyPref::V4 };
+
+        // tcp：目标级端口覆盖目标串
+        let listener = std::net::TcpListener::bind("127.0.0.1:0")?;
+        let port = listener.local_addr()?.port();
+        let tcp = RttTarget { method: Some(ProbeMethod::Tcp), port: Some(port), count: Some(2), ..RttTarget::new("127.0.0.1:1") };
+        let r = measure_rtt_burst(&tcp, &defaults);
+        if r.success != Some(true) || r.method.as_deref() != Some("tcp") || r.sent != Some(2) {
+            return Err(format!("tcp 探测错误: {:?}", r).into());
+        }
+
+        // http：路径与期望状态码
+        let (port, rx) = spawn_http_stub("HTTP/1.1 204 No Content")?;
+        let http = RttTarget {
+            name: Some("stub".into()),
+            group: Some("local".into()),
+            method: Some(ProbeMethod::Http),
+            port: Some(port),
+            path: Some("/health".into()),
+            expect_status: Some(204),
+            ..RttTarget::new("127.0.0.1")
+        };
+        let r = measure_rtt_burst(&http, &defaults);
+        let (head, _) = rx.recv_timeout(Duration::from_secs(3))?;
+        if r.success != Some(true) || r.status != Some(204) || r.method.as_deref() != Some("http") || r.group.as_deref() != Some("local") {
+            return Err(format!("http 探测错误: {:?}", r).into());
+        }
+        if !head.starts_with("HEAD /health HTTP/1.1") {
+            return Err(format!("http 请求行错误: {}", head).into());
+        }
+        // 状态码不符：记为丢包，但保留实际状态码
+        let (port, _rx) = spawn_http_stub("HTTP/1.1 503 Service Unavailable")?;
+        let r = measure_rtt_burst(&RttTarget { port: Some(port), expect_status: None, ..http.clone() }, &defaults);
+        if r.success != Some(false) || r.status != Some(503) || r.loss_pct != Some(100.0) {
+            return Err(format!("http 状态码校验错误: {:?}", r).into());
+        }
+
+        // dns：本地 UDP 桩返回 NXDOMAIN
+        let dns = std::net::UdpSocket::bind("127.0.0.1:0")?;
+        let dns_port = dns.local_addr()?.port();
+        std::thread::spawn(move || {
+            let mut buf = [0u8; 512];
+            for _ in 0..2 {
+                let Ok((n, peer)) = dns.recv_from(&mut buf) else { return };
+                if n < 12 { continue; }
+                let mut resp = buf[..n].to_vec();
+                // QR=1、RD/RA=1、RCODE=3
+                resp[2] = 0x81;
+                resp[3] = 0x83;
+                let _ = dns.send_to(&resp, peer);
+            }
+        });
+        let q = RttTarget { method: Some(ProbeMethod::Dns), port: Some(dns_port), query: Some("nx.example.test".into()), ..RttTarget::new("127.0.0.1") };
+        let r = measure_rtt_burst(&q, &defaults);
+        if r.success != Some(true) || r.status != Some(3) || r.method.as_deref() != Some("dns") {
+            return Err(format!("dns 探测错误: {:?}", r).into());
+        }
+        let r = measure_rtt_burst(&RttTarget { expect_status: Some(0), ..q }, &defaults);
+        if r.success != Some(false) || r.status != Some(3) {
+            return Err(format!("dns RCODE 校验错误: {:?}", r).into());
+        }
+
+        Ok(format!("混写解析 {} 个目标；tcp/http/dns 本地探测正常（http 503 与 dns RCODE=3 按期望判定）", list.len()))
+    }
+
+    async fn test_http_timing(&mut self) {
+        let start = Instant::now();
+        let mut test = TestResult {
+            test_name: "HTTP分阶段计时测试".to_string(),
+            success: false,
+            message: "".to_string(),
+            duration_ms: 0,
+            details: Some(HashMap::new()),
+            error_details: None,
+        };
+
+        match self.run_http_timing_test().await {
+            Ok(info) => {
+                test.success = true;
+                test.message = "HTTP 分阶段计时与证书解析正常".to_string();
+                test.details.as_mut().unwrap().insert("http_timing_info".to_string(), info);
+            }
+            Err(e) => {
+                test.success = false;
+                test.message = "HTTP分阶段计时测试失败".to_string();
+                test.error_details = Some(e.to_string());
+            }
+        }
+
+        test.duration_ms = start.elapsed().as_millis() as u64;
+        self.test_results.push(test);
+    }
+
+    async fn run_http_timing_test(&self) -> Result<String, Box<dyn std::error::Error>> {
+        use crate::http_timing::{cert_not_after, exchange_h2, parse_asn1_time, probe, probe_tls_config, tls_config};
+        use crate::ping_utils::{measure_rtt_burst, ProbeDefaults, RttTarget};
+        use std::io::{Read, Write};
+
+        // 本地 HTTP 服务：各阶段计时齐全，明文无 TLS 阶段
+        let (port, rx) = spawn_http_stub("HTTP/1.1 204 No Content")?;
+        let t = probe(&format!("http://127.0.0.1:{}/health?x=1#frag", port), None, 2000);
+        let (head, _) = rx.recv_timeout(Duration::from_secs(3))?;
+        if t.error.is_some() || t.status != Some(204) || t.protocol.as_deref() != Some("HTTP/1.1") {
+            return Err(format!("本地 HTTP 探测错误: {:?}", t).into());
+        }
+        if t.dns_ms.is_none() || t.connect_ms.is_none() || t.ttfb_ms.is_none() || t.tls_ms.is_some() || t.cert_expiry_days.is_some() {
+            return Err(format!("阶段计时缺失: {:?}", t).into());
+        }
+        let parts = t.dns_ms.unwrap_or_default() + t.connect_ms.unwrap_or_default() + t.ttfb_ms.unwrap_or_default();
+        if t.total_ms.map(|v| v + 1e-6 < parts).unwrap_or(true) {
+            return Err(format!("总耗时小于各阶段之和: {:?}", t).into());
+        }
+        if !head.starts_with("HEAD /health?x=1 HTTP/1.1\r\n") || !head.contains(&format!("Host: 127.0.0.1:{}\r\n", port)) {
+            return Err(format!("请求头错误: {}", head).into());
+        }
+
+        // 连接失败：记录错误，无状态码
+        let closed = std::net::TcpListener::bind("127.0.0.1:0")?.local_addr()?.port();
+        let bad = probe(&format!("http://127.0.0.1:{}/", closed), None, 500);
+        if bad.error.is_none() || bad.status.is_some() || bad.total_ms.is_some() {
+            return Err(format!("连接失败结果错误: {:?}", bad).into());
+        }
+
+        // RTT 快照：http 目标附带分阶段计时
+        let (port, _rx) = spawn_http_stub("HTTP/1.1 200 OK")?;
+        let defaults = ProbeDefaults { timeout_ms: 2000, count: 1, interval_ms: 0, ..Default::default() };
+        let r = measure_rtt_burst(&RttTarget::new(&format!("http://127.0.0.1:{}/", port)), &defaults);
+        let http = r.http.as_ref().ok_or("RTT 结果缺少 http 计时")?;
+        if r.success != Some(true) || http.status != Some(200) || http.connect_ms.is_none() || http.dns_ms.is_some() {
+            return Err(format!("RTT http 计时错误: {:?}", r).into());
+        }
+
+        // ALPN：探测声明 h2 与 http/1.1；测速等 HTTP/1.1 客户端仍只声明 http/1.1
+        if probe_tls_config()?.alpn_protocols != [b"h2".to_vec(), b"http/1.1".to_vec()] || tls_config()?.alpn_protocols != [b"http/1.1".to_vec()] {
+            return Err("TLS ALPN 配置错误".into());
+        }
+        // HTTP/2 交换（本地明文替身）：回 SETTINGS ACK，解码静态表与 Huffman 字面量的 :status
+        let frame = |kind: u8, flags: u8, stream: u32, payload: &[u8]| -> Vec<u8> {
+            let len = payload.len() as u32;
+            [vec![(len >> 16) as u8, (len >> 8) as u8, len as u8, kind, flags], stream.to_be_bytes().to_vec(), payload.to_vec()].concat()
+        };
+        let read_frame = |s: &mut std::net::TcpStream| -> std::io::Result<(u8, u8, Vec<u8>)> {
+            let mut head = [0u8; 9];
+            s.read_exact(&mut head)?;
+            let mut payload = vec![0u8; u32::from_be_bytes([0, head[0], head[1], head[2]]) as usize];
+            s.read_exact(&mut payload)?;
+            Ok((head[3], head[4], payload))
+        };
+        // 503 的 Huffman 编码：011011 00000 011001 + 全 1 填充；204 为静态表索引 9
+        for (status_block, want) in [(vec![0x48, 0x83, 0x6c, 0x0c, 0xff], 503u16), (vec![0x89], 204)] {
+            let listener = std::net::TcpListener::bind("127.0.0.1:0")?;
+            let port = listener.local_addr()?.port();
+            let reply = [frame(0x4, 0, 0, &[]), frame(0x1, 0x5, 1, &status_block)];
+            let server = std::thread::spawn(move || -> std::io::Result<(Vec<u8>, bool)> {
+                let (mut s, _) = listener.accept()?;
+                let mut preface = [0u8; 24];
+                s.read_exact(&mut preface)?;
+                let mut request = Vec::new();
+                while request.is_empty() {
+                    let (kind, _, payload) = read_frame(&mut s)?;
+                    if kind == 0x1 { request = payload; }
+                }
+                s.write_all(&reply[0])?;
+                let (kind, flags, _) = read_frame(&mut s)?;
+                s.write_all(&reply[1])?;
+                Ok((request, &preface == b"PRI * HTTP/2.0\r\n\r\nSM\r\n\r\n" && kind == 0x4 && flags == 0x1))
+            });
+            let mut tcp = std::net::TcpStream::connect(("127.0.0.1", port))?;
+            tcp.set_read_timeout(Some(Duration::from_secs(3)))?;
+            let mut h2 = crate::http_timing::HttpTiming::default();
+            let res = exchange_h2(&mut tcp, "example.org", "/health", &mut h2);
+            let (request, acked) = server.join().map_err(|_| "h2 替身线程异常")??;
+            if res.is_err() || h2.status != Some(want) || h2.protocol.as_deref() != Some("HTTP/2") || h2.ttfb_ms.is_none() || !acked {
+                return Err(format!("HTTP/2 交换错误: {:?} / {:?} / ack={}", res, h2, acked).into());
+            }
+            let has = |needle: &[u8]| request.windows(needle.len()).any(|w| w == needle);
+            if !has(b"\x02\x04HEAD") || !has(b"\x04\x07/health") || !has(b"\x01\x0bexample.org") {
+                return Err(format!("HTTP/2 请求头块错误: {:?}", request).into());
+            }
+        }
+
+        // 证书有效期：UTCTime / GeneralizedTime
+        if parse_asn1_time(0x17, b"491231235959Z") != Some(2524607999) || parse_asn1_time(0x17, b"500101000000Z") != Some(-631152000) {
+            return Err("UTCTime 解析错误".into());
+        }
+        if parse_asn1_time(0x18, b"20500101000000Z") != Some(2524608000) || parse_asn1_time(0x18, b"20500101000000").is_some() {
+            return Err("GeneralizedTime 解析错误".into());
+        }
+        // 最小证书结构：version/serial/signature/issuer/validity
+        let tlv = |tag: u8, body: &[u8]| -> Vec<u8> { [vec![tag, body.len() as u8], body.to_vec()].concat() };
+        let validity = tlv(0x30, &[tlv(0x17, b"240101000000Z"), tlv(0x18, b"20300101000000Z")].concat());
+        let tbs = tlv(0x30, &[tlv(0xa0, &tlv(0x02, &[2])), tlv(0x02, &[1]), tlv(0x30, &[]), tlv(0x30, &[]), validity].concat());
+        let cert = tlv(0x30, &tbs);
+        if cert_not_after(&cert) != Some(1893456000) || cert_not_after(&cert[..cert.len() - 3]).is_some() {
+            return Err("证书 notAfter 解析错误".into());
+        }
+
+        Ok(format!(
+            "本地 204：DNS {:.2}ms / 连接 {:.2}ms / 首字节 {:.2}ms / 总计 {:.2}ms",
+            t.dns_ms.unwrap_or_default(), t.connect_ms.unwrap_or_default(), t.ttfb_ms.unwrap_or_default(), t.total_ms.unwrap_or_default()
+        ))
+    }
+
+    async fn test_dns_probe(&mut self) {
+        let start = Instant::now();
+        let mut test = TestResult {
+            test_name: "DNS解析器探测测试".to_string(),
+            success: false,
+            message: "".to_string(),
+            duration_ms: 0,
+            details: Some(HashMap::new()),
+            error_details: None,
+        };
+
+        match self.run_dns_probe_test().await {
+            Ok(info) => {
+                test.success = true;
+                test.message = "解析器延迟/RCODE/一致性与异常标记正常".to_string();
+                test.details.as_mut().unwrap().insert("dns_probe_info".to_string(), info);
+            }
+            Err(e) => {
+                test.success = false;
+                test.message = "DNS解析器探测测试失败".to_string();
+                test.error_details = Some(e.to_string());
+            }
+        }
+
+        test.duration_ms = start.elapsed().as_millis() as u64;
+        self.test_results.push(test);
+    }
+
+    async fn run_dns_probe_test(&self) -> Result<String, Box<dyn std::error::Error>> {
+        use crate::dns_probe::{parse_resolv_conf, parse_response, parse_server, run_probe, servers_from_config, DnsTransport};
+
+        // 解析器地址与来源
+        let s = parse_server("tcp://[2606:4700::1111]", "custom").ok_or("解析 IPv6 解析器失败")?;
+        if s.addr.to_string() != "[2606:4700::1111]:53" || s.transport != Some(DnsTransport::Tcp) {
+            return Err(format!("解析器地址解析错误: {:?}", s).into());
+        }
+        if parse_server("dns.google", "custom").is_some() {
+            return Err("主机名解析器应被忽略".into());
+        }
+        if parse_resolv_conf("# x\nnameserver 10.0.0.1\nsearch lan\nnameserver ::1\n") != vec!["10.0.0.1", "::1"] {
+            return Err("resolv.conf 解析错误".into());
+        }
+        let net_ifs: Vec<crate::types::NetIfPayload> = vec![
+            serde_json::from_value(serde_json::json!({ "dnsServers": ["192.168.1.1", "8.8.8.8"] }))?,
+            serde_json::from_value(serde_json::json!({ "dnsServers": ["192.168.1.1"] }))?,
+        ];
+        let cfg = AppConfig { dns_probe_servers: Some(vec!["8.8.8.8".into(), "tcp://1.1.1.1".into(), "bad".into()]), ..Default::default() };
+        let list: Vec<String> = servers_from_config(&cfg, Some(&net_ifs)).iter().map(|s| format!("{}/{}", s.addr, s.source)).collect();
+        if list != ["192.168.1.1:53/system", "8.8.8.8:53/system", "1.1.1.1:53/custom"] {
+            return Err(format!("解析器汇总错误: {:?}", list).into());
+        }
+        let cfg = AppConfig { dns_probe_system: Some(false), ..cfg };
+        if servers_from_config(&cfg, Some(&net_ifs)).len() != 2 {
+            return Err("dns_probe_system=false 未生效".into());
+        }
+
+        // 应答解析：压缩指针、CNAME 跳过、AAAA
+        let mut resp = vec![0x12, 0x34, 0x81, 0x80, 0, 1, 0, 3, 0, 0, 0, 0];
+        resp.extend_from_slice(&[1, b'a', 2, b'c', b'n', 0, 0, 1, 0, 1]);
+        resp.extend_from_slice(&[0xc0, 12, 0, 5, 0, 1, 0, 0, 0, 60, 0, 2, 0xc0, 14]);
+        resp.extend_from_slice(&[0xc0, 14, 0, 1, 0, 1, 0, 0, 0, 60, 0, 4, 10, 0, 0, 2]);
+        resp.extend_from_slice(&[0xc0, 14, 0, 28, 0, 1, 0, 0, 0, 60, 0, 16, 0x20, 1, 0x0d, 0xb8, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1]);
+        let parsed = parse_response(&resp, 0x1234).ok_or("应答解析失败")?;
+        if parsed.rcode != 0 || parsed.answers != vec!["10.0.0.2", "2001:db8::1"] || parse_response(&resp, 0x4321).is_some() {
+            return Err(format!("应答解析错误: {:?}", parsed).into());
+        }
+
+        // 本地桩：a/b 应答不同地址，c 返回 SERVFAIL，d 不应答
+        let a = spawn_dns_stub(Some([10, 0, 0, 1]), 0)?;
+        let b = spawn_dns_stub(Some([10, 0, 0, 9]), 0)?;
+        let c = spawn_dns_stub(None, 2)?;
+        let silent = std::net::UdpSocket::bind("127.0.0.1:0")?;
+        let servers = vec![
+            parse_server(&format!("127.0.0.1:{}", a), "custom").ok_or("a")?,
+            parse_server(&format!("127.0.0.1:{}", b), "custom").ok_or("b")?,
+            parse_server(&format!("127.0.0.1:{}", c), "custom").ok_or("c")?,
+            parse_server(&silent.local_addr()?.to_string(), "custom").ok_or("d")?,
+        ];
+        let names = vec!["probe.test".to_string()];
+        let report = run_probe(&names, &servers, DnsTransport::Udp, 300);
+        let health = |port: u16| report.servers.iter().find(|h| h.server == format!("127.0.0.1:{}", port));
+        let ha = health(a).ok_or("缺少 a 汇总")?;
+        if ha.status != "ok" || ha.flagged || ha.avg_ms.is_none() {
+            return Err(format!("正常解析器汇总错误: {:?}", ha).into());
+        }
+        let hc = health(c).ok_or("缺少 c 汇总")?;
+        if hc.servfails != 1 || !hc.flagged || hc.status != "degraded" {
+            return Err(format!("SERVFAIL 汇总错误: {:?}", hc).into());
+        }
+        let hd = health(silent.local_addr()?.port()).ok_or("缺少 d 汇总")?;
+        if hd.timeouts != 1 || hd.status != "down" || !hd.flagged {
+            return Err(format!("超时汇总错误: {:?}", hd).into());
+        }
+        if report.flagged.len() != 2 {
+            return Err(format!("异常标记错误: {:?}", report.flagged).into());
+        }
+        let cons = report.consistency.first().ok_or("缺少一致性结果")?;
+        if cons.consistent || cons.variants.len() != 2 || cons.outliers.len() != 1 {
+            return Err(format!("一致性判定错误: {:?}", cons).into());
+        }
+        let ra = report.results.iter().find(|r| r.server == format!("127.0.0.1:{}", a)).ok_or("缺少 a 结果")?;
+        if ra.rcode_name.as_deref() != Some("NOERROR") || ra.answers != vec!["10.0.0.1"] {
+            return Err(format!("查询结果错误: {:?}", ra).into());
+        }
+
+        // TCP：同端口监听，both 模式各出一条
+        let report = run_probe(&names, &servers[..1], DnsTransport::Both, 1000);
+        let transports: Vec<&str> = report.servers.iter().map(|h| h.transport.as_str()).collect();
+        if transports != ["udp", "tcp"] || report.servers.iter().any(|h| h.flagged) || !report.consistency[0].consistent {
+            return Err(format!("TCP 查询错误: {:?}", report).into());
+        }
+
+        Ok(format!("4 个本地解析器：SERVFAIL/超时已标记，应答不一致 {:?}；udp/tcp 均可达", cons.outliers))
+    }
+
+    async fn test_traceroute(&mut self) {
+        let start = Instant::now();
+        let mut test = TestResult {
+            test_name: "路径分析测试".to_string(),
+            success: false,
+            message: "".to_string(),
+            duration_ms: 0,
+            details: Some(HashMap::new()),
+            error_details: None,
+        };
+
+        match self.run_traceroute_test().await {
+            Ok(info) => {
+                test.success = true;
+                test.message = "逐跳统计、越限判定与本地回环路径正常".to_string();
+                test.details.as_mut().unwrap().insert("traceroute_info".to_string(), info);
+            }
+            Err(e) => {
+                test.success = false;
+                test.message = "路径分析测试失败".to_string();
+                test.error_details = Some(e.to_string());
+            }
+        }
+
+        test.duration_ms = start.elapsed().as_millis() as u64;
+        self.test_results.push(test);
+    }
+
+    async fn run_traceroute_test(&self) -> Result<String, Box<dyn std::error::Error>> {
+        use crate::traceroute::{hop_stats, threshold_reason, trace, HopReply, TraceMethod, TraceOptions};
+
+        // 多轮汇总：第 2 跳两条路径、第 3 跳一轮丢包，第 4 跳到达（第 5 跳起截断）
+        let hop = |ip: &str, rtt_ms: f64, reached: bool| Some(HopReply { from: ip.parse().unwrap(), rtt_ms, reached });
+        let rounds = vec![
+            vec![hop("10.0.0.1", 1.0, false), hop("10.0.1.1", 5.0, false), None, hop("10.0.9.9", 20.0, true), hop("10.0.9.9", 21.0, true)],
+            vec![hop("10.0.0.1", 3.0, false), hop("10.0.2.1", 7.0, false), hop("10.0.3.1", 9.0, false), hop("10.0.9.9", 22.0, true), None],
+        ];
+        let (hops, reached) = hop_stats(&rounds);
+        if !reached || hops.len() != 4 {
+            return Err(format!("路径截断错误: reached={} hops={}", reached, hops.len()).into());
+        }
+        let h1 = &hops[0];
+        if h1.received != 2 || h1.best_ms != Some(1.0) || h1.worst_ms != Some(3.0) || h1.avg_ms != Some(2.0) || h1.stddev_ms != Some(1.0) || h1.last_ms != Some(3.0) {
+            return Err(format!("第 1 跳统计错误: {:?}", h1).into());
+        }
+        if hops[1].addrs != vec!["10.0.1.1", "10.0.2.1"] || hops[2].loss_pct != 50.0 || hops[3].addrs != vec!["10.0.9.9"] {
+            return Err(format!("多路径/丢包统计错误: {:?}", hops).into());
+        }
+        // 均未到达：去掉末尾无应答的跳
+        let (hops, reached) = hop_stats(&[vec![hop("10.0.0.1", 1.0, false), hop("10.0.1.1", 2.0, false), None, None]]);
+        if reached || hops.len() != 2 {
+            return Err(format!("未到达路径截断错误: {:?}", hops).into());
+        }
+
+        // 越限判定
+        let r: crate::process_utils::RttResultPayload = serde_json::from_value(serde_json::json!({ "target": "t", "avg_ms": 120.0, "loss_pct": 0.0 }))?;
+        if threshold_reason(&r, Some(100.0), None).is_none() || threshold_reason(&r, Some(200.0), Some(10.0)).is_some() {
+            return Err("延迟阈值判定错误".into());
+        }
+        let r = crate::process_utils::RttResultPayload { loss_pct: Some(40.0), ..r };
+        if threshold_reason(&r, None, Some(40.0)).is_none() || threshold_reason(&r, None, None).is_some() {
+            return Err("丢包阈值判定错误".into());
+        }
+
+        // 本地回环：UDP 高端口（无需特权）第 1 跳即到达
+        let opts = TraceOptions { method: TraceMethod::Udp, max_hops: 4, rounds: 2, timeout_ms: 500, interval_ms: 0, ..Default::default() };
+        let p = trace("127.0.0.1", &opts);
+        if let Some(e) = p.error.as_ref() {
+            return Err(format!("回环路径分析失败: {}", e).into());
+        }
+        if !p.reached || p.hops.len() != 1 || p.hops[0].addrs != vec!["127.0.0.1"] || p.hops[0].received != 2 {
+            return Err(format!("回环路径错误: {:?}", p).into());
+        }
+
+        Ok(format!("回环 {} 跳，{} 方式，平均 {:.3}ms", p.hops.len(), p.method, p.hops[0].avg_ms.unwrap_or_default()))
+    }
+
+    async fn test_connectivity(&mut self) {
+        let start = Instant::now();
+        let mut test = TestResult {
+            test_name: "连通性状态机测试".to_string(),
+            success: false,
+            message: "".to_string(),
+            duration_ms: 0,
+            details: Some(HashMap::new()),
+            error_details: None,
+        };
+
+        match self.run_connectivity_test().await {
+            Ok(info) => {
+                test.success = true;
+                test.message = "状态归类、去抖切换与中断记录/导出正常".to_string();
+                test.details.as_mut().unwrap().insert("connectivity_info".to_string(), info);
+            }
+            Err(e) => {
+                test.success = false;
+                test.message = "连通性状态机测试失败".to_string();
+                test.error_details = Some(e.to_string());
+            }
+        }
+
+        test.duration_ms = start.elapsed().as_millis() as u64;
+        self.test_results.push(test);
+    }
+
+    async fn run_connectivity_test(&self) -> Result<String, Box<dyn std::error::Error>> {
+        use crate::connectivity::{classify, export_csv, observe, open, query, status, ConnMonitor, ConnSample, ConnState, ConnThresholds};
+        use crate::dns_probe::DnsProbeReport;
+        use crate::http_timing::HttpTiming;
+        use crate::process_utils::RttResultPayload;
+
+        // 归类
+        let th = ConnThresholds::default();
+        let rtt = |target: &str, ok: bool, avg_ms: f64, loss_pct: f64| RttResultPayload {
+            target: target.to_string(),
+            success: Some(ok),
+            avg_ms: ok.then_some(avg_ms),
+            loss_pct: Some(if ok { loss_pct } else { 100.0 }),
+            ..Default::default()
+        };
+        let dns = |servers: serde_json::Value| -> Result<DnsProbeReport, serde_json::Error> {
+            serde_json::from_value(serde_json::json!({ "timestamp_ms": 0, "results": [], "servers": servers, "consistency": [], "flagged": [] }))
+        };
+        let server = |name: &str, answered: u32, servfails: u32, flagged: bool| serde_json::json!({
+            "server": name, "source": "config", "transport": "udp", "queries": 2, "answered": answered,
+            "timeouts": 2 - answered, "servfails": servfails, "avg_ms": null, "max_ms": null, "status": "ok", "flagged": flagged,
+        });
+        let state_of = |r: &[RttResultPayload], d: Option<&DnsProbeReport>, c: Option<&HttpTiming>| classify(r, d, c, &th).map(|s| s.state);
+        let good = [rtt("a", true, 20.0, 0.0), rtt("b", true, 30.0, 0.0)];
+        let healthy_dns = dns(serde_json::json!([server("1.1.1.1:53", 2, 0, false)]))?;
+        let portal = HttpTiming { status: Some(302), ..Default::default() };
+        let generate_204 = HttpTiming { status: Some(204), ..Default::default() };
+        let checks = [
+            (state_of(&[], None, None), None),
+            (state_of(&good, Some(&healthy_dns), Some(&generate_204)), Some(ConnState::Online)),
+            (state_of(&good, None, Some(&portal)), Some(ConnState::CaptivePortal)),
+            (state_of(&[rtt("a", false, 0.0, 0.0), rtt("b", false, 0.0, 0.0)], Some(&healthy_dns), None), Some(ConnState::Offline)),
+            (state_of(&good, Some(&dns(serde_json::json!([server("1.1.1.1:53", 0, 0, true), server("8.8.8.8:53", 2, 2, true)]))?), None), Some(ConnState::DnsFailure)),
+            (state_of(&[rtt("a", true, 20.0, 0.0), rtt("b", false, 0.0, 0.0)], None, None), Some(ConnState::Degraded)),
+            (state_of(&[rtt("a", true, 450.0, 0.0)], None, None), Some(ConnState::Degraded)),
+            (state_of(&[rtt("a", true, 20.0, 25.0)], None, None), Some(ConnState::Degraded)),
+            (state_of(&good, Some(&dns(serde_json::json!([server("1.1.1.1:53", 2, 0, false), server("8.8.8.8:53", 1, 0, true)]))?), None), Some(ConnState::Degraded)),
+        ];
+        for (i, (got, want)) in checks.iter().enumerate() {
+            if got != want {
+                return Err(format!("归类 #{} 错误: {:?}，期望 {:?}", i, got, want).into());
+            }
+        }
+
+        // 去抖：连续 2 次才切换，切换时间取首次出现；单次抖动不切换
+        let sample = |state: ConnState| ConnSample { state, cause: Some(state.label().to_string()) };
+        let mut m = ConnMonitor::default();
+        let mut seq = vec![];
+        for (t, st) in [(0, ConnState::Online), (10, ConnState::Online), (20, ConnState::Offline), (30, ConnState::Online),
+                        (40, ConnState::Degraded), (50, ConnState::Degraded), (60, ConnState::Offline), (70, ConnState::Offline),
+                        (80, ConnState::Degraded), (90, ConnState::Degraded), (100, ConnState::Online), (110, ConnState::Online)] {
+            if let Some(tr) = m.observe(sample(st), t, 2) { seq.push((tr.at_ms, tr.to, tr.ended)); }
+        }
+        let states: Vec<(i64, ConnState)> = seq.iter().map(|(t, s, _)| (*t, *s)).collect();
+        if states != [(0, ConnState::Online), (40, ConnState::Degraded), (60, ConnState::Offline), (80, ConnState::Degraded), (100, ConnState::Online)] {
+            return Err(format!("去抖切换错误: {:?}", states).into());
+        }
+        let ended = seq.last().and_then(|(_, _, e)| e.clone()).ok_or("恢复时未结束中断")?;
+        if ended.start_ms != 40 || ended.end_ms != Some(100) || ended.duration_ms != Some(60) || ended.state != ConnState::Offline
+            || ended.cause.as_deref() != Some("offline") || ended.states != [ConnState::Degraded, ConnState::Offline, ConnState::Degraded] {
+            return Err(format!("中断记录错误: {:?}", ended).into());
+        }
+
+        // 时间线：结束的中断写入日志，进行中的中断排在最前，超出上限丢弃最旧记录
+        let dir = std::env::temp_dir().join(format!("sys-sensor-outage-test-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        open(&dir, Some(2));
+        let base = chrono::Local::now().timestamp_millis() - 3_600_000;
+        let mut t = base;
+        for st in [ConnState::Online, ConnState::Offline, ConnState::Online, ConnState::CaptivePortal, ConnState::Online, ConnState::DnsFailure] {
+            for _ in 0..2 {
+                observe(ConnSample { state: st, cause: Some(format!("原因, \"{}\"", st.label())) }, t, 2, Some(2));
+                t += 60_000;
+            }
+        }
+        let all = query(None, None, 10);
+        let kinds: Vec<(ConnState, bool)> = all.iter().map(|o| (o.state, o.end_ms.is_some())).collect();
+        if kinds != [(ConnState::DnsFailure, false), (ConnState::CaptivePortal, true), (ConnState::Offline, true)] {
+            return Err(format!("时间线查询错误: {:?}", kinds).into());
+        }
+        if status().state != Some(ConnState::DnsFailure) || status().outage_start_ms != Some(all[0].start_ms) {
+            return Err(format!("当前状态错误: {:?}", status()).into());
+        }
+        if query(Some(base + 5 * 60_000), None, 10).len() != 2 || !query(None, Some(base + 60_000), 10).is_empty() {
+            return Err("时间窗口过滤错误".into());
+        }
+        // 再结束一次中断：上限 2 条，最旧的 offline 被丢弃
+        for _ in 0..2 {
+            observe(ConnSample { state: ConnState::Online, cause: None }, t, 2, Some(2));
+            t += 60_000;
+        }
+        let logged = crate::connectivity::read_entries(&crate::connectivity::journal_path(&dir), Some(2));
+        if logged.iter().map(|o| o.state).collect::<Vec<_>>() != [ConnState::CaptivePortal, ConnState::DnsFailure] {
+            return Err(format!("中断日志裁剪错误: {:?}", logged).into());
+        }
+        let csv = export_csv(&query(None, None, 10));
+        let lines: Vec<&str> = csv.lines().collect();
+        if lines.len() != 3 || lines[0] != "start,end,duration_s,state,cause,states" || !lines[1].ends_with(",120,captive_portal,\"原因, \"\"captive_portal\"\"\",captive_portal") {
+            return Err(format!("CSV 导出错误:\n{}", csv).into());
+        }
+        let _ = std::fs::remove_dir_all(&dir);
+
+        Ok(format!("{} 种状态归类正确；{} 次去抖切换；导出 {} 条中断", checks.len(), seq.len(), lines.len() - 1))
+    }
+
+    async fn test_public_ip_providers(&mut self) {
+        let start = Instant::now();
+        let mut test = TestResult {
+            test_name: "公网 IP 提供者测试".to_string(),
+            success: false,
+            message: "".to_string(),
+            duration_ms: 0,
+            details: Some(HashMap::new()),
+            error_details: None,
+        };
+
+        match self.run_public_ip_providers_test().await {
+            Ok(info) => {
+                test.success = true;
+                test.message = "提供者配置、JSON 路径提取、STUN 与变化历史正常".to_string();
+                test.details.as_mut().unwrap().insert("public_ip_info".to_string(), info);
+            }
+            Err(e) => {
+                test.success = false;
+                test.message = "公网 IP 提供者测试失败".to_string();
+                test.error_details = Some(e.to_string());
+            }
+        }
+
+        test.duration_ms = start.elapsed().as_millis() as u64;
+        self.test_results.push(test);
+    }
+
+    async fn run_public_ip_providers_test(&self) -> Result<String, Box<dyn std::error::Error>> {
+        use crate::config_utils::{AppConfig, PublicNetInfo};
+        use crate::ping_utils::FamilyPref;
+        use crate::public_net_utils::{
+            apply_lookup, builtin_providers, extract, history, lookup, normalize_asn, open_history, parse_stun_response,
+            providers_from_config, record_lookup, stun_request, PublicIpResult, PublicNetLookup, PublicNetProvider,
+        };
+
+        // 配置：字符串与对象混写（无效元素忽略）；旧字段 public_net_api 排在内置列表之前
+        let cfg: AppConfig = serde_json::from_value(serde_json::json!({
+            "tray_show_mem": false,
+            "public_net_providers": ["https://ip.example/json", { "url": "stun:stun.example.org", "family": "v6" }, 42, ""],
+        }))?;
+        let providers = providers_from_config(&cfg);
+        if providers.len() != 2 || providers[0].label() != "ip.example" || !providers[1].is_stun() || providers[1].family != Some(FamilyPref::V6) {
+            return Err(format!("提供者配置解析错误: {:?}", providers).into());
+        }
+        let legacy = providers_from_config(&AppConfig { public_net_api: Some("https://legacy.example/ip".into()), ..Default::default() });
+        if legacy.len() != builtin_providers().len() + 1 || legacy[0].url != "https://legacy.example/ip" {
+            return Err(format!("public_net_api 兼容错误: {:?}", legacy).into());
+        }
+
+        // JSON 路径提取 / 纯文本 / 地址族校验 / 接口错误说明
+        let ip_api = PublicNetProvider {
+            ip_path: Some("query".into()), isp_path: Some("isp".into()), asn_path: Some("as".into()), country_path: Some("countryCode".into()),
+            ..PublicNetProvider::new("http://ip-api.com/json")
+        };
+        let r = extract(&ip_api, r#"{"status":"success","query":"203.0.113.9","isp":"Example ISP","as":"AS13335 Cloudflare, Inc.","countryCode":"CN"}"#, FamilyPref::V4)?;
+        if r.ip != "203.0.113.9" || r.isp.as_deref() != Some("Example ISP") || r.asn.as_deref() != Some("AS13335") || r.country.as_deref() != Some("CN") || r.provider != "ip-api.com" {
+            return Err(format!("ip-api 提取错误: {:?}", r).into());
+        }
+        let nested = PublicNetProvider { ip_path: Some("data.ips[1]".into()), asn_path: Some("data.asn".into()), ..PublicNetProvider::new("https://n.example") };
+        let r = extract(&nested, r#"{"data":{"ips":["10.0.0.1","2001:db8::5"],"asn":64500}}"#, FamilyPref::V6)?;
+        if r.ip != "2001:db8::5" || r.asn.as_deref() != Some("AS64500") {
+            return Err(format!("嵌套路径提取错误: {:?}", r).into());
+        }
+        let plain = PublicNetProvider::new("https://api64.ipify.org");
+        if extract(&plain, "198.51.100.20\n", FamilyPref::V4)?.ip != "198.51.100.20" || extract(&plain, "198.51.100.20", FamilyPref::V6).is_ok() {
+            return Err("纯文本提取或地址族校验错误".into());
+        }
+        match extract(&ip_api, r#"{"status":"fail","message":"private range"}"#, FamilyPref::V4) {
+            Err(e) if e.contains("private range") => {}
+            other => return Err(format!("失败响应未带错误说明: {:?}", other).into()),
+        }
+        if normalize_asn("as4134 CHINANET") != Some("AS4134".into()) || normalize_asn("Google LLC").is_some() || normalize_asn("中国电信").is_some() {
+            return Err("ASN 规范化错误".into());
+        }
+
+        // STUN：XOR-MAPPED-ADDRESS（IPv6 按魔数 + 事务 ID 异或）
+        let txid = [7u8; 12];
+        let v6: std::net::SocketAddr = "[2001:db8::77]:5000".parse()?;
+        if parse_stun_response(&stun_binding_response(&txid, v6), &txid)? != v6.ip() || parse_stun_response(&stun_binding_response(&[8u8; 12], v6), &txid).is_ok() {
+            return Err("STUN 响应解析错误".into());
+        }
+        if stun_request(&txid)[..8] != [0, 1, 0, 0, 0x21, 0x12, 0xA4, 0x42] {
+            return Err("STUN 请求格式错误".into());
+        }
+        let mapped: std::net::SocketAddr = "198.51.100.7:40000".parse()?;
+        let stun = PublicNetProvider::new(&format!("stun:127.0.0.1:{}", spawn_stun_stub(mapped)?));
+        let r = lookup(&[stun], FamilyPref::V4, 2000)?;
+        if r.ip != "198.51.100.7" {
+            return Err(format!("STUN 查询错误: {:?}", r).into());
+        }
+
+        // HTTP：首个提供者失败时按顺序回退；IPv6 查询时解析器过滤掉 IPv4 地址
+        let closed = std::net::TcpListener::bind("127.0.0.1:0")?.local_addr()?.port();
+        let port = spawn_http_body_stub(r#"{"ip":"192.0.2.44","org":"AS64501 Example Net"}"#)?;
+        let chain = vec![
+            PublicNetProvider { name: Some("down".into()), ..PublicNetProvider::new(&format!("http://127.0.0.1:{}/", closed)) },
+            PublicNetProvider { isp_path: Some("org".into()), asn_path: Some("org".into()), ..PublicNetProvider::new(&format!("http://127.0.0.1:{}/json", port)) },
+        ];
+        let r = lookup(&chain, FamilyPref::V4, 2000)?;
+        if r.ip != "192.0.2.44" || r.asn.as_deref() != Some("AS64501") || r.provider != format!("127.0.0.1:{}", port) {
+            return Err(format!("HTTP 回退查询错误: {:?}", r).into());
+        }
+        match lookup(&chain, FamilyPref::V6, 2000) {
+            Err(e) if e.contains("down:") => {}
+            other => return Err(format!("IPv6 查询应失败并汇总错误: {:?}", other).into()),
+        }
+
+        // 变化历史：首次记录基准，地址不变不记录；重新打开后保持比较基准
+        let dir = std::env::temp_dir().join(format!("sys-sensor-public-ip-test-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        open_history(&dir, None);
+        let res = |ip: &str| PublicIpResult { ip: ip.into(), isp: Some("ISP".into()), provider: "p".into(), ..Default::default() };
+        let first = PublicNetLookup { timestamp_ms: 1_000, v4: Some(res("192.0.2.1")), ..Default::default() };
+        if record_lookup(&first, None).len() != 1 || !record_lookup(&PublicNetLookup { timestamp_ms: 2_000, ..first.clone() }, None).is_empty() {
+            return Err("首次记录/重复地址判定错误".into());
+        }
+        open_history(&dir, None);
+        let second = PublicNetLookup { timestamp_ms: 3_000, v4: Some(res("192.0.2.2")), v6: Some(res("2001:db8::1")), ..Default::default() };
+        let changes = record_lookup(&second, None);
+        if changes.len() != 2 || changes[0].old_ip.as_deref() != Some("192.0.2.1") || changes[1].family != "v6" || changes[1].old_ip.is_some() {
+            return Err(format!("地址变化记录错误: {:?}", changes).into());
+        }
+        let h = history(10);
+        if h.len() != 3 || h[0].new_ip != "2001:db8::1" || h[2].timestamp_ms != 1_000 {
+            return Err(format!("历史查询错误: {:?}", h).into());
+        }
+        let _ = std::fs::remove_dir_all(&dir);
+
+        // 条数上限挤掉 IPv6 的唯一记录后，IPv6 地址不变仍不重复记录
+        let dir = std::env::temp_dir().join(format!("sys-sensor-public-ip-trim-test-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        open_history(&dir, Some(2));
+        let both = |ts: i64, v4: &str| PublicNetLookup { timestamp_ms: ts, v4: Some(res(v4)), v6: Some(res("2001:db8::1")), ..Default::default() };
+        record_lookup(&both(1_000, "192.0.2.1"), Some(2));
+        record_lookup(&both(2_000, "192.0.2.2"), Some(2));
+        let trimmed = record_lookup(&both(3_000, "192.0.2.3"), Some(2));
+        if trimmed.len() != 1 || trimmed[0].family != "v4" || trimmed[0].old_ip.as_deref() != Some("192.0.2.2") || history(10).iter().any(|e| e.family == "v6") {
+            return Err(format!("裁剪后地址族基准错误: {:?} / {:?}", trimmed, history(10)).into());
+        }
+        let _ = std::fs::remove_dir_all(&dir);
+
+        // 合并：失败的地址族保留上次的值，主地址 IPv4 优先
+        let mut info = PublicNetInfo::default();
+        apply_lookup(&mut info, &second);
+        apply_lookup(&mut info, &PublicNetLookup { timestamp_ms: 4_000, v6_error: Some("timeout".into()), v4: Some(res("192.0.2.3")), ..Default::default() });
+        if info.ip.as_deref() != Some("192.0.2.3") || info.ipv6.as_deref() != Some("2001:db8::1") || info.last_updated_ms != Some(4_000) || !info.last_error.as_deref().unwrap_or("").contains("IPv6") {
+            return Err("公网信息合并错误".into());
+        }
+
+        Ok(format!("{} 个内置提供者；STUN {}；HTTP 回退 {}；记录 {} 次地址变化", builtin_providers().len(), mapped.ip(), r.ip, h.len()))
+    }
+
+    async fn test_speedtest(&mut self) {
+        let start = Instant::now();
+        let mut test = TestResult {
+            test_name: "吞吐测速测试".to_string(),
+            success: false,
+            message: "".to_string(),
+            duration_ms: 0,
+            details: Some(HashMap::new()),
+            error_details: None,
+        };
+
+        match self.run_speedtest_test().await {
+            Ok(info) => {
+                test.success = true;
+                test.message = "HTTP 端点与 iperf3 测速、bufferbloat 评级和历史记录正常".to_string();
+                test.details.as_mut().unwrap().insert("speedtest_info".to_string(), info);
+            }
+            Err(e) => {
+                test.success = false;
+                test.message = "吞吐测速测试失败".to_string();
+                test.error_details = Some(e.to_string());
+            }
+        }
+
+        test.duration_ms = start.elapsed().as_millis() as u64;
+        self.test_results.push(test);
+    }
+
+    async fn run_speedtest_test(&self) -> Result<String, Box<dyn std::error::Error>> {
+        use crate::api_server::{serve_listener, ApiConfig, ApiContext};
+        use crate::speedtest::{
+            bufferbloat_grade, history, open_history, record, run, worse_grade, SpeedtestDirection, SpeedtestOptions, SpeedtestProtocol,
+            SpeedtestResult,
+        };
+        use std::io::{Read, Write};
+        use std::sync::{Arc, Mutex};
+
+        // 配置：空白端点视为未配置；并发数、时长与预热按范围收敛
+        let cfg: AppConfig = serde_json::from_value(serde_json::json!({
+            "tray_show_mem": false,
+            "speedtest": { "protocol": "iperf3", "server": " 192.0.2.10 ", "download_url": "  ", "streams": 99, "duration_secs": 1, "warmup_ms": 5000, "direction": "upload" },
+        }))?;
+        let opts = SpeedtestOptions::from_config(&cfg);
+        if opts.streams != 16 || opts.duration_ms != 2000 || opts.warmup_ms != 1000 || opts.download_url.is_some()
+            || opts.endpoint().as_deref() != Some("192.0.2.10") || opts.direction != SpeedtestDirection::Upload {
+            return Err(format!("测速配置解析错误: {:?}", opts).into());
+        }
+        let unconfigured = run(&SpeedtestOptions::from_config(&AppConfig::default()), "manual");
+        if !unconfigured.error.as_deref().unwrap_or("").contains("未配置") || unconfigured.download.is_some() {
+            return Err(format!("未配置端点时应直接报错: {:?}", unconfigured).into());
+        }
+
+        // bufferbloat 评级
+        if bufferbloat_grade(3.0) != "A+" || bufferbloat_grade(29.9) != "A" || bufferbloat_grade(45.0) != "B" || bufferbloat_grade(450.0) != "F"
+            || worse_grade(Some("A"), Some("C")) != Some("C") || worse_grade(None, Some("B")) != Some("B") {
+            return Err("bufferbloat 评级错误".into());
+        }
+
+        // 本地 API 作为 HTTP 端点（关闭限流：测试数据较小，请求频繁）
+        let dir = std::env::temp_dir().join(format!("sys-sensor-speedtest-test-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let config = Arc::new(Mutex::new(AppConfig {
+            api: Some(ApiConfig { enabled: true, rate_limit_per_min: Some(0), ..Default::default() }),
+            ..Default::default()
+        }));
+        let ctx = ApiContext {
+            config: config.clone(),
+            scheduler: Arc::new(Mutex::new(crate::scheduler::SchedulerState::default())),
+            state_store: Arc::new(Mutex::new(crate::state_store::StateStore::new())),
+            config_dir: dir.clone(),
+            trigger_task: None,
+            on_config_changed: None,
+        };
+        let handle = serve_listener(std::net::TcpListener::bind("127.0.0.1:0")?, ctx, None).map_err(|e| e.to_string())?;
+        let base = format!("http://{}", handle.addr);
+        let request = |head: &str, body: usize| -> Result<String, Box<dyn std::error::Error>> {
+            let mut s = std::net::TcpStream::connect(handle.addr)?;
+            s.set_read_timeout(Some(Duration::from_secs(5)))?;
+            s.write_all(head.as_bytes())?;
+            s.write_all(&vec![0u8; body])?;
+            let mut resp = String::new();
+            let _ = s.read_to_string(&mut resp);
+            Ok(resp)
+        };
+
+        // 测速端点默认关闭；开启后匿名（仅 metrics:read）仍需 speedtest 作用域
+        let download_head = "GET /api/speedtest/download?bytes=1024 HTTP/1.1\r\nHost: t\r\n\r\n";
+        let disabled = request(download_head, 0)?;
+        if !disabled.starts_with("HTTP/1.1 404") {
+            handle.stop();
+            return Err(format!("测速端点未开启时应返回 404: {}", disabled.lines().next().unwrap_or("")).into());
+        }
+        if let Ok(mut c) = config.lock() {
+            if let Some(api) = c.api.as_mut() {
+                api.speedtest_endpoint = Some(true);
+                api.speedtest_max_connections = Some(1);
+            }
+        }
+        let anonymous = request(download_head, 0)?;
+        if !anonymous.starts_with("HTTP/1.1 403") || !anonymous.contains("speedtest") {
+            handle.stop();
+            return Err(format!("匿名访问测速端点应返回 403: {}", anonymous.lines().next().unwrap_or("")).into());
+        }
+        let token = crate::api_auth::create_token(&dir, "speedtest", vec![crate::api_auth::Scope::Speedtest], None)?.token;
+
+        // 并发上限：上传连接未结束时，第二个测速连接返回 503
+        let mut held = std::net::TcpStream::connect(handle.addr)?;
+        held.write_all(format!("POST /api/speedtest/upload HTTP/1.1\r\nHost: t\r\nAuthorization: Bearer {}\r\nContent-Length: 100\r\n\r\n", token).as_bytes())?;
+        std::thread::sleep(Duration::from_millis(300));
+        let busy = request(&format!("GET /api/speedtest/download?bytes=1024 HTTP/1.1\r\nHost: t\r\nAuthorization: Bearer {}\r\n\r\n", token), 0)?;
+        drop(held);
+        if !busy.starts_with("HTTP/1.1 503") {
+            handle.stop();
+            return Err(format!("测速并发上限未生效: {}", busy.lines().next().unwrap_or("")).into());
+        }
+        std::thread::sleep(Duration::from_millis(300));
+        if let Ok(mut c) = config.lock() {
+            if let Some(api) = c.api.as_mut() { api.speedtest_max_connections = None; }
+        }
+
+        // 上传端点不受 64KB 请求体限制
+        let resp = request(&format!("POST /api/speedtest/upload HTTP/1.1\r\nHost: t\r\nAuthorization: Bearer {}\r\nContent-Length: 300000\r\n\r\n", token), 300_000)?;
+        if !resp.starts_with("HTTP/1.1 200") || !resp.contains("\"bytes\":300000") {
+            return Err(format!("上传端点应答错误: {}", resp.lines().next().unwrap_or("")).into());
+        }
+
+        let http = SpeedtestOptions {
+            protocol: SpeedtestProtocol::Http,
+            download_url: Some(format!("{}/api/speedtest/download?bytes=16777216", base)),
+            upload_url: Some(format!("{}/api/speedtest/upload", base)),
+            token: Some(token.clone()),
+            duration_ms: 2000,
+            streams: 2,
+            warmup_ms: 500,
+            timeout_ms: 2000,
+            ..Default::default()
+        };
+        let r = run(&http, "manual");
+        handle.stop();
+        let (down, up) = (r.download.clone().unwrap_or_default(), r.upload.clone().unwrap_or_default());
+        if r.error.is_some() || down.mbps.unwrap_or(0.0) <= 0.0 || up.mbps.unwrap_or(0.0) <= 0.0 || down.streams != 2 || down.duration_ms < 1000 {
+            return Err(format!("HTTP 测速错误: {:?}", r).into());
+        }
+        if r.idle_latency_ms.is_some() && down.loaded_latency_ms.is_some() && (down.grade.is_none() || r.bufferbloat_grade.is_none()) {
+            return Err(format!("有延迟数据时应给出 bufferbloat 评级: {:?}", r).into());
+        }
+
+        // iperf3：下载为反向模式，流 id 与 iperf3 一致；延迟探测使用独立的本地端口（不干扰数据流）
+        let (port, log) = spawn_iperf3_stub(2)?;
+        let latency_listener = std::net::TcpListener::bind("127.0.0.1:0")?;
+        let iperf = SpeedtestOptions {
+            protocol: SpeedtestProtocol::Iperf3,
+            server: Some(format!("127.0.0.1:{}", port)),
+            duration_ms: 2000,
+            streams: 2,
+            warmup_ms: 500,
+            timeout_ms: 2000,
+            latency_target: Some(latency_listener.local_addr()?.to_string()),
+            ..Default::default()
+        };
+        let r2 = run(&iperf, "schedule");
+        // 桩在收到 IPERF_DONE 后才记录会话，稍等其落盘
+        let waited = Instant::now();
+        while log.lock().map(|g| g.len()).unwrap_or(0) < 2 && waited.elapsed() < Duration::from_secs(2) {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        let sessions = log.lock().map(|g| g.clone()).unwrap_or_default();
+        if r2.error.is_some() || r2.download.as_ref().and_then(|d| d.mbps).is_none() || r2.upload.as_ref().and_then(|d| d.mbps).is_none() || sessions.len() != 2 {
+            return Err(format!("iperf3 测速错误: {:?}（{} 次会话）", r2, sessions.len()).into());
+        }
+        let ids: Vec<u64> = sessions[0]["results"]["streams"].as_array().map(|a| a.iter().filter_map(|s| s["id"].as_u64()).collect()).unwrap_or_default();
+        if sessions[0]["params"]["reverse"] != true || sessions[1]["params"].get("reverse").is_some() || sessions[0]["params"]["parallel"] != 2
+            || ids != vec![1, 3] || sessions.iter().any(|s| s["final_state"] != 16) {
+            return Err(format!("iperf3 协议交互错误: {:?}", sessions).into());
+        }
+
+        // 历史：超过上限丢弃最旧的记录，新记录在前，重新打开后保留
+        open_history(&dir, Some(2));
+        for ts in [1_000, 2_000, 3_000] {
+            record(&SpeedtestResult { timestamp_ms: ts, ..r.clone() }, Some(2));
+        }
+        open_history(&dir, Some(2));
+        let h = history(10);
+        if h.len() != 2 || h[0].timestamp_ms != 3_000 || h[1].timestamp_ms != 2_000 || h[0].download != r.download {
+            return Err(format!("测速历史错误: {:?}", h.iter().map(|e| e.timestamp_ms).collect::<Vec<_>>()).into());
+        }
+        let _ = std::fs::remove_dir_all(&dir);
+
+        Ok(format!(
+            "HTTP 下载 {:.0} / 上传 {:.0} Mbps；iperf3 下载 {:.0} / 上传 {:.0} Mbps；bufferbloat {}",
+            down.mbps.unwrap_or(0.0),
+            up.mbps.unwrap_or(0.0),
+            r2.download.and_then(|d| d.mbps).unwrap_or(0.0),
+            r2.upload.and_then(|d| d.mbps).unwrap_or(0.0),
+            r.bufferbloat_grade.as_deref().unwrap_or("—"),
+        ))
+    }
+}
+
+/// 桥接 stdin 替身：按行解析请求，模拟 sensor-bridge 的命令处理并经 handle_line 回送响应
+/// （hang 不回应，迟到 600ms 后才回应，用于超时测试）
+#[derive(Default)]
+struct FakeBridgeStdin {
+    buf: Vec<u8>,
+}
+
+impl std::io::Write for FakeBridgeStdin {
+    fn write(&mut self, data: &[u8]) -> std::io::Result<usize> {
+        self.buf.extend_from_slice(data);
+        while let Some(pos) = self.buf.iter().position(|b| *b == b'\n') {
+            let line: Vec<u8> = self.buf.drain(..=pos).collect();
+            let Ok(req) = serde_json::from_slice::<serde_json::Value>(&line) else { continue };
+            let id = req.get("id").cloned().unwrap_or_default();
+            let params = req.get("params").cloned().unwrap_or_default();
+            let (body, delay_ms) = match req.get("method").and_then(|m| m.as_str()).unwrap_or("") {
+                "ping" => (serde_json::json!({ "result": { "pong": true } }), 0),
+                "setInterval" => (serde_json::json!({ "result": { "intervalMs": params.get("ms") } }), 0),
+                "setHardware" => {
+                    let mut groups = serde_json::json!({ "cpu": true, "gpu": true, "storage": true });
+                    if let (Some(g), Some(p)) = (groups.as_object_mut(), params.as_object()) {
+                        for (k, v) in p { g.insert(k.clone(), v.clone()); }
+                    }
+                    (serde_json::json!({ "result": groups }), 0)
+                }
+                "hang" => (serde_json::json!({ "result": null }), 600),
+                m => (serde_json::json!({ "error": { "code": -32601, "message": format!("unknown method: {}", m) } }), 0),
+            };
+            let mut resp = body;
+            resp["type"] = "response".into();
+            resp["id"] = id;
+            std::thread::spawn(move || {
+                std::thread::sleep(Duration::from_millis(delay_ms));
+                if let crate::bridge_protocol::BridgeLine::Response(r) = crate::bridge_protocol::handle_line(&resp.to_string()) {
+                    crate::bridge_command::handle_response(r);
+                }
+            });
+        }
+        Ok(data.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// 本地 SMTP 替身服务：处理一次会话，回传 (命令列表, DATA 内容)
+fn spawn_smtp_stub() -> StubResult<std::sync::mpsc::Receiver<(Vec<String>, String)>> {
+    use std::io::{BufRead, BufReader, Write};
+    let listener = std::net::TcpListener::bind("127.0.0.1:0")?;
+    let port = listener.local_addr()?.port();
+    let (tx, rx) = std::sync::mpsc::channel();
+    std::thread::spawn(move || {
+        let Ok((stream, _)) = listener.accept() else { return };
+        let _ = stream.set_read_timeout(Some(Duration::from_secs(5)));
+        let Ok(mut writer) = stream.try_clone() else { return };
+        let mut reader = BufReader::new(stream);
+        let mut cmds: Vec<String> = Vec::new();
+        let mut data = String::new();
+        let _ = writer.write_all(b"220 stub ESMTP\r\n");
+        loop {
+            let mut line = String::new();
+            match reader.read_line(&mut line) {
+                Ok(0) | Err(_) => break,
+                Ok(_) => {}
+            }
+            let line = line.trim_end().to_string();
+            let upper = line.to_ascii_uppercase();
+            cmds.push(line);
+            let reply: &[u8] = if upper.starts_with("EHLO") {
+                b"250-stub\r\n250 AUTH PLAIN LOGIN\r\n"
+            } else if upper.starts_with("AUTH") {
+                b"235 ok\r\n"
+            } else if upper == "DATA" {
+                let _ = writer.write_all(b"354 go\r\n");
+                loop {
+                    let mut l = String::new();
+                    match reader.read_line(&mut l) {
+                        Ok(0) | Err(_) => break,
+                        Ok(_) => {}
+                    }
+                    if l == ".\r\n" { break; }
+                    data.push_str(&l);
+                }
+                b"250 queued\r\n"
+            } else if upper == "QUIT" {
+                let _ = writer.write_all(b"221 bye\r\n");
+                break;
+            } else {
+                b"250 ok\r\n"
+            };
+            let _ = writer.write_all(reply);
+        }
+        let _ = tx.send((cmds, data));
+    });
+    Ok((port, rx))
+}
+
+/// 替身服务返回值：(监听端口, 观测句柄)
+type StubResult<T> = Result<(u16, T), Box<dyn std::error::Error>>;
+
+/// 本地 HTTP 替身服务：接受一次请求，返回给定状态行，并回传 (请求头, 请求体)
+fn spawn_http_stub(status_line: &str) -> StubResult<std::sync::mpsc::Receiver<(String, Vec<u8>)>> {
+    use std::io::{Read, Write};
+    let listener = std::net::TcpListener::bind("127.0.0.1:0")?;
+    let port = listener.local_addr()?.port();
+    let status_line = status_line.to_string();
+    let (tx, rx) = std::sync::mpsc::channel();
+    std::thread::spawn(move || {
+        let Ok((mut stream, _)) = listener.accept() else { return };
+        let _ = stream.set_read_timeout(Some(Duration::from_secs(3)));
+        let mut buf: Vec<u8> = Vec::new();
+        let mut tmp = [0u8; 4096];
+        // 读取请求头
+        let header_end = loop {
+            match stream.read(&mut tmp) {
+                Ok(0) | Err(_) => return,
+                Ok(n) => buf.extend_from_slice(&tmp[..n]),
+            }
+            if let Some(pos) = buf.windows(4).position(|w| w == b"\r\n\r\n") { break pos + 4; }
+        };
+        let head = String::from_utf8_lossy(&buf[..header_end]).to_string();
+        let content_len = head
+            .lines()
+            .find_map(|l| {
+                let (k, v) = l.split_once(':')?;
+                if k.trim().eq_ignore_ascii_case("content-length") { v.trim().parse::<usize>().ok() } else { None }
+            })
+            .unwrap_or(0);
+        let mut body = buf[header_end..].to_vec();
+        while body.len() < content_len {
+            match stream.read(&mut tmp) {
+                Ok(0) | Err(_) => break,
+                Ok(n) => body.extend_from_slice(&tmp[..n]),
+            }
+        }
+        let _ = stream.write_all(format!("{}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n", status_line).as_bytes());
+        let _ = tx.send((head, body));
+    });
+    Ok((port, rx))
+}
+
+/// 本地 HTTP 桩：应答一次 200 与给定响应体
+fn spawn_http_body_stub(body: &str) -> Result<u16, Box<dyn std::error::Error>> {
+    use std::io::{Read, Write};
+    let listener = std::net::TcpListener::bind("127.0.0.1:0")?;
+    let port = listener.local_addr()?.port();
+    let body = body.to_string();
+    std::thread::spawn(move || {
+        let Ok((mut stream, _)) = listener.accept() else { return };
+        let _ = stream.set_read_timeout(Some(Duration::from_secs(3)));
+        let mut buf: Vec<u8> = Vec::new();
+        let mut tmp = [0u8; 4096];
+        while !buf.windows(4).any(|w| w == b"\r\n\r\n") {
+            match stream.read(&mut tmp) {
+                Ok(0) | Err(_) => return,
+                Ok(n) => buf.extend_from_slice(&tmp[..n]),
+            }
+        }
+        let head = format!("HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n", body.len());
+        let _ = stream.write_all(format!("{}{}", head, body).as_bytes());
+    });
+    Ok(port)
+}
+
+/// 本地 STUN 桩：应答一次 Binding Request，先带一个未知属性（验证对齐跳过），再带 XOR-MAPPED-ADDRESS
+fn spawn_stun_stub(mapped: std::net::SocketAddr) -> Result<u16, Box<dyn std::error::Error>> {
+    let sock = std::net::UdpSocket::bind("127.0.0.1:0")?;
+    let port = sock.local_addr()?.port();
+    std::thread::spawn(move || {
+        let _ = sock.set_read_timeout(Some(Duration::from_secs(3)));
+        let mut buf = [0u8; 512];
+        let Ok((n, peer)) = sock.recv_from(&mut buf) else { return };
+        if n < 20 || buf[0..2] != [0, 1] { return; }
+        let _ = sock.send_to(&stun_binding_response(&buf[8..20], mapped), peer);
+    });
+    Ok(port)
+}
+
+/// 构造 STUN Binding 成功响应（事务 ID 取自请求）
+fn stun_binding_response(txid: &[u8], mapped: std::net::SocketAddr) -> Vec<u8> {
+    let cookie = 0x2112_A442u32.to_be_bytes();
+    let mut key = cookie.to_vec();
+    key.extend_from_slice(txid);
+    let mut attrs: Vec<u8> = vec![0x80, 0x22, 0, 3, b'f', b'o', b'o', 0];
+    let (family, addr): (u8, Vec<u8>) = match mapped.ip() {
+        std::net::IpAddr::V4(v4) => (1, v4.octets().to_vec()),
+        std::net::IpAddr::V6(v6) => (2, v6.octets().to_vec()),
+    };
+    let xport = mapped.port() ^ 0x2112;
+    attrs.extend_from_slice(&[0x00, 0x20, 0, 4 + addr.len() as u8, 0, family]);
+    attrs.extend_from_slice(&xport.to_be_bytes());
+    attrs.extend(addr.iter().zip(key.iter()).map(|(a, k)| a ^ k));
+    let mut resp = vec![0x01, 0x01];
+    resp.extend_from_slice(&(attrs.len() as u16).to_be_bytes());
+    resp.extend_from_slice(&cookie);
+    resp.extend_from_slice(txid);
+    resp.extend(attrs);
+    resp
+}
+
+/// 本地 iperf3 桩：按协议完成 sessions 次 TCP 测试，记录客户端参数、结果与结束状态
+fn spawn_iperf3_stub(sessions: usize) -> StubResult<std::sync::Arc<std::sync::Mutex<Vec<serde_json::Value>>>> {
+    use std::io::{Read, Write};
+    use std::net::TcpStream;
+    use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+    use std::sync::{Arc, Mutex};
+    let listener = std::net::TcpListener::bind("127.0.0.1:0")?;
+    let port = listener.local_addr()?.port();
+    let log: Arc<Mutex<Vec<serde_json::Value>>> = Arc::new(Mutex::new(Vec::new()));
+    let log_c = log.clone();
+    let read_json = |s: &mut TcpStream| -> Option<serde_json::Value> {
+        let mut len = [0u8; 4];
+        s.read_exact(&mut len).ok()?;
+        let mut body = vec![0u8; u32::from_be_bytes(len) as usize];
+        s.read_exact(&mut body).ok()?;
+        serde_json::from_slice(&body).ok()
+    };
+    let write_json = |s: &mut TcpStream, v: &serde_json::Value| {
+        let body = v.to_string();
+        let _ = s.write_all(&(body.len() as u32).to_be_bytes());
+        let _ = s.write_all(body.as_bytes());
+    };
+    std::thread::spawn(move || {
+        for _ in 0..sessions {
+            let Ok((mut ctrl, _)) = listener.accept() else { return };
+            let _ = ctrl.set_read_timeout(Some(Duration::from_secs(30)));
+            let mut cookie = [0u8; 37];
+            if ctrl.read_exact(&mut cookie).is_err() { return; }
+            let _ = ctrl.write_all(&[9]);
+            let Some(params) = read_json(&mut ctrl) else { return };
+            let reverse = params.get("reverse").is_some();
+            let _ = ctrl.write_all(&[10]);
+            let mut streams = Vec::new();
+            for _ in 0..params["parallel"].as_u64().unwrap_or(1) {
+                let Ok((mut s, _)) = listener.accept() else { return };
+                let mut c = [0u8; 37];
+                if s.read_exact(&mut c).is_err() || c != cookie { return; }
+                streams.push(s);
+            }
+            let _ = ctrl.write_all(&[1, 2]);
+            let done = Arc::new(AtomicBool::new(false));
+            let received = Arc::new(AtomicU64::new(0));
+            let workers: Vec<_> = streams
+                .into_iter()
+                .map(|mut s| {
+                    let (done, received) = (done.clone(), received.clone());
+                    std::thread::spawn(move || {
+                        let _ = s.set_read_timeout(Some(Duration::from_millis(200)));
+                        let _ = s.set_write_timeout(Some(Duration::from_millis(200)));
+                        let mut buf = vec![0u8; 64 * 1024];
+                        while !done.load(Ordering::SeqCst) {
+                            if reverse {
+                                let _ = s.write_all(&buf);
+                            } else if let Ok(n) = s.read(&mut buf) {
+                                if n == 0 { break; }
+                                received.fetch_add(n as u64, Ordering::SeqCst);
+                            }
+                        }
+                    })
+                })
+                .collect();
+            let mut state = [0u8; 1];
+            if ctrl.read_exact(&mut state).is_err() || state[0] != 4 { return; }
+            done.store(true, Ordering::SeqCst);
+            for w in workers { let _ = w.join(); }
+            let _ = ctrl.write_all(&[13]);
+            let Some(results) = read_json(&mut ctrl) else { return };
+            write_json(&mut ctrl, &serde_json::json!({
+                "cpu_util_total": 0, "cpu_util_user": 0, "cpu_util_system": 0, "sender_has_retransmits": 0,
+                "streams": [{ "id": 1, "bytes": received.load(Ordering::SeqCst), "retransmits": 0, "jitter": 0, "errors": 0, "packets": 0 }],
+            }));
+            let _ = ctrl.write_all(&[14]);
+            let _ = ctrl.read_exact(&mut state);
+            if let Ok(mut g) = log_c.lock() {
+                g.push(serde_json::json!({ "params": params, "results": results, "final_state": state[0] }));
+            }
+        }
+    });
+    Ok((port, log))
+}
+
+/// 本地 DNS 桩：同一端口提供 UDP 与 TCP；answer 为 None 时不带应答记录
+fn spawn_dns_stub(answer: Option<[u8; 4]>, rcode: u8) -> Result<u16, Box<dyn std::error::Error>> {
+    use std::io::{Read, Write};
+    let udp = std::net::UdpSocket::bind("127.0.0.1:0")?;
+    let port = udp.local_addr()?.port();
+    let tcp = std::net::TcpListener::bind(("127.0.0.1", port))?;
+    let reply = move |q: &[u8]| -> Vec<u8> {
+        let mut r = q[..2].to_vec();
+        r.extend_from_slice(&[0x81, 0x80 | rcode, 0, 1, 0, answer.is_some() as u8, 0, 0, 0, 0]);
+        r.extend_from_slice(&q[12..]);
+        if let Some(ip) = answer {
+            r.extend_from_slice(&[0xc0, 12, 0, 1, 0, 1, 0, 0, 0, 60, 0, 4]);
+            r.extend_from_slice(&ip);
+        }
+        r
+    };
+    std::thread::spawn(move || {
+        let mut buf = [0u8; 512];
+        while let Ok((n, peer)) = udp.recv_from(&mut buf) {
+            if n > 12 { let _ = udp.send_to(&reply(&buf[..n]), peer); }
+        }
+    });
+    std::thread::spawn(move || {
+        for mut stream in tcp.incoming().flatten() {
+            let mut len = [0u8; 2];
+            if stream.read_exact(&mut len).is_err() { continue; }
+            let mut q = vec![0u8; u16::from_be_bytes(len) as usize];
+            if q.len() <= 12 || stream.read_exact(&mut q).is_err() { continue; }
+            let r = reply(&q);
+            let _ = stream.write_all(&[(r.len() as u16).to_be_bytes().to_vec(), r].concat());
+        }
+    });
+    Ok(port)
 }
@@ -0,0 +1,147 @@
+// Trace Runner：路径分析（traceroute / MTR 风格）
+// 说明：
+// - 依赖 BaseGate 做并发防重入
+// - 请求队列：手动（trigger_task）、定时与 RTT 越限自动请求先入队，trigger() 时一次性取出并发执行
+// - 队列为空时 trigger() 对全部配置目标执行（定时）
+// - 按目标保留最近一次路径，snapshot_json() 返回全部目标的最近路径
+
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+use crate::runner::{Runner, BaseGate};
+use crate::ping_utils::FamilyPref;
+use crate::traceroute::{trace, TraceOptions, TracePath};
+
+/// 单轮路径分析配置
+#[derive(Clone, Debug)]
+pub struct TraceRunConfig {
+    pub targets: Vec<String>,
+    pub options: TraceOptions,
+}
+
+impl TraceRunConfig {
+    pub fn from_config(cfg: &crate::config_utils::AppConfig) -> Self {
+        Self {
+            targets: crate::traceroute::targets_from_config(cfg),
+            options: TraceOptions::from_config(cfg),
+        }
+    }
+}
+
+/// 待执行的路径分析请求（target 为 None 表示全部配置目标）
+#[derive(Clone, Debug)]
+struct TraceRequest {
+    target: Option<String>,
+    family: Option<FamilyPref>,
+    trigger: &'static str,
+    reason: Option<String>,
+}
+
+#[derive(Clone)]
+pub struct TraceRunner {
+    gate: Arc<BaseGate>,
+    // 配置提供器：每轮触发时读取
+    cfg_provider: Arc<dyn Fn() -> TraceRunConfig + Send + Sync>,
+    pending: Arc<Mutex<Vec<TraceRequest>>>,
+    // 各目标最近一次路径
+    paths: Arc<Mutex<BTreeMap<String, TracePath>>>,
+}
+
+impl TraceRunner {
+    pub fn new<F>(cfg_provider: F) -> Self
+    where
+        F: Fn() -> TraceRunConfig + Send + Sync + 'static,
+    {
+        Self {
+            gate: Arc::new(BaseGate::new()),
+            cfg_provider: Arc::new(cfg_provider),
+            pending: Arc::new(Mutex::new(Vec::new())),
+            paths: Arc::new(Mutex::new(BTreeMap::new())),
+        }
+    }
+
+    /// 手动请求：对全部配置目标执行
+    pub fn request_all(&self) {
+        self.push(TraceRequest { target: None, family: None, trigger: "manual", reason: None });
+    }
+
+    /// RTT 越限请求：对单个目标执行（沿用 RTT 结果的地址族）
+    pub fn request_threshold(&self, target: &str, family: Option<FamilyPref>, reason: String) {
+        self.push(TraceRequest { target: Some(target.to_string()), family, trigger: "threshold", reason: Some(reason) });
+    }
+
+    fn push(&self, req: TraceRequest) {
+        if let Ok(mut g) = self.pending.lock() {
+            if !g.iter().any(|r| r.target == req.target) { g.push(req); }
+        }
+    }
+
+    pub fn has_pending(&self) -> bool {
+        self.pending.lock().map(|g| !g.is_empty()).unwrap_or(false)
+    }
+}
+
+impl Runner for TraceRunner {
+    fn name(&self) -> &'static str { "trace_runner" }
+
+    fn trigger(&self, now_ms: i64) {
+        // 防重入：运行中收到的请求留在队列，下次触发时执行
+        if !self.gate.try_enter() { return; }
+        self.gate.set_running();
+        let gate_c = self.gate.clone();
+        let paths_c = self.paths.clone();
+        let cfg_c = self.cfg_provider.clone();
+        let mut requests: Vec<TraceRequest> = self.pending.lock().map(|mut g| std::mem::take(&mut *g)).unwrap_or_default();
+        if requests.is_empty() {
+            requests.push(TraceRequest { target: None, family: None, trigger: "schedule", reason: None });
+        }
+        std::thread::spawn(move || {
+            let cfg = (cfg_c)();
+            // 展开为 (目标, 请求)，同一目标只执行一次（单目标请求优先，保留触发原因）
+            let mut jobs: Vec<(String, TraceRequest)> = Vec::new();
+            for req in requests.iter().filter(|r| r.target.is_some()).chain(requests.iter().filter(|r| r.target.is_none())) {
+                let targets = match &req.target {
+                    Some(t) => vec![t.clone()],
+                    None => cfg.targets.clone(),
+                };
+                for t in targets {
+                    if !jobs.iter().any(|(j, _)| *j == t) { jobs.push((t, req.clone())); }
+                }
+            }
+            if jobs.is_empty() {
+                gate_c.exit();
+                return;
+            }
+            let results: Vec<TracePath> = std::thread::scope(|s| {
+                let handles: Vec<_> = jobs
+                    .iter()
+                    .map(|(target, req)| {
+                        let mut opts = cfg.options;
+                        if let Some(f) = req.family { opts.family = f; }
+                        s.spawn(move || {
+                            let mut p = trace(target, &opts);
+                            p.timestamp_ms = now_ms;
+                            p.trigger = req.trigger.to_string();
+                            p.reason = req.reason.clone();
+                            p
+                        })
+                    })
+                    .collect();
+                handles.into_iter().filter_map(|h| h.join().ok()).collect()
+            });
+            if let Ok(mut g) = paths_c.lock() {
+                for p in results { g.insert(p.target.clone(), p); }
+            }
+            gate_c.mark_ok_and_exit(now_ms);
+        });
+    }
+
+    fn is_running(&self) -> bool { self.gate.is_running() }
+    fn last_ok_ms(&self) -> Option<i64> { self.gate.last_ok_ms() }
+
+    fn snapshot_json(&self) -> serde_json::Value {
+        match self.paths.lock() {
+            Ok(g) => serde_json::json!({ "paths": g.values().collect::<Vec<_>>() }),
+            Err(_) => serde_json::json!({}),
+        }
+    }
+}
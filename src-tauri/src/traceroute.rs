@@ -0,0 +1,406 @@
+// =============================================================================
+// 路径分析（traceroute / MTR 风格）
+// - 逐跳递增 TTL 发送探测：ICMP 回显或 UDP 高端口（Linux 两种均支持；Windows 仅 ICMP，udp 自动回退）
+// - 每轮并发探测 1..=max_hops 各跳，重复多轮后按到达目的地的最小 TTL 截断
+// - 每跳统计：发送/收到、丢包率、最近/平均/最好/最差/标准差，以及应答地址（多路径时可能多个）
+// - 可由 RTT 阈值（延迟/丢包）自动触发，结果附带触发来源与原因
+// =============================================================================
+
+use std::net::IpAddr;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::ping_utils::FamilyPref;
+use crate::process_utils::RttResultPayload;
+
+#[cfg(windows)]
+use ::windows::Win32::Foundation::HANDLE;
+#[cfg(windows)]
+use ::windows::Win32::NetworkManagement::IpHelper::{
+    IcmpCreateFile, IcmpSendEcho, IcmpCloseHandle, ICMP_ECHO_REPLY, IP_OPTION_INFORMATION,
+    Icmp6CreateFile, Icmp6SendEcho2, ICMPV6_ECHO_REPLY_LH,
+};
+#[cfg(windows)]
+use ::windows::Win32::Networking::WinSock::{AF_INET6, IN6_ADDR, IN6_ADDR_0, SOCKADDR_IN6};
+
+pub const DEFAULT_MAX_HOPS: u8 = 30;
+pub const DEFAULT_ROUNDS: u32 = 5;
+pub const DEFAULT_TIMEOUT_MS: u64 = 1000;
+pub const DEFAULT_INTERVAL_MS: u64 = 200;
+// 同一目标两次阈值触发之间的最小间隔（秒）
+pub const DEFAULT_COOLDOWN_SECS: u64 = 300;
+
+/// 逐跳探测方式
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TraceMethod {
+    #[default]
+    Icmp,
+    Udp,
+}
+
+impl TraceMethod {
+    pub fn label(self) -> &'static str {
+        match self {
+            TraceMethod::Icmp => "icmp",
+            TraceMethod::Udp => "udp",
+        }
+    }
+
+    /// 当前平台实际使用的方式（Windows 无非特权 UDP 差错接收，回退 ICMP）
+    pub fn effective(self) -> TraceMethod {
+        if cfg!(windows) { TraceMethod::Icmp } else { self }
+    }
+}
+
+/// 单个逐跳探测的应答
+#[derive(Clone, Debug, PartialEq)]
+pub struct HopReply {
+    // 应答方地址（中间路由或目的地）
+    pub from: IpAddr,
+    pub rtt_ms: f64,
+    // 是否已到达目的地
+    pub reached: bool,
+}
+
+/// 路径分析参数
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TraceOptions {
+    pub method: TraceMethod,
+    pub max_hops: u8,
+    // 重复轮数（每轮每跳一个探测）
+    pub rounds: u32,
+    pub timeout_ms: u64,
+    // 相邻两轮的间隔
+    pub interval_ms: u64,
+    pub family: FamilyPref,
+}
+
+impl Default for TraceOptions {
+    fn default() -> Self {
+        Self {
+            method: TraceMethod::Icmp,
+            max_hops: DEFAULT_MAX_HOPS,
+            rounds: DEFAULT_ROUNDS,
+            timeout_ms: DEFAULT_TIMEOUT_MS,
+            interval_ms: DEFAULT_INTERVAL_MS,
+            family: FamilyPref::Auto,
+        }
+    }
+}
+
+impl TraceOptions {
+    pub fn from_config(cfg: &crate::config_utils::AppConfig) -> Self {
+        let d = Self::default();
+        Self {
+            method: cfg.traceroute_method.unwrap_or(d.method),
+            max_hops: cfg.traceroute_max_hops.unwrap_or(d.max_hops).clamp(1, 64),
+            rounds: cfg.traceroute_rounds.unwrap_or(d.rounds).clamp(1, 100),
+            timeout_ms: cfg.traceroute_timeout_ms.unwrap_or(d.timeout_ms),
+            interval_ms: cfg.traceroute_interval_ms.unwrap_or(d.interval_ms),
+            family: cfg.rtt_family.unwrap_or(d.family),
+        }
+    }
+}
+
+/// 手动/定时路径分析的目标（traceroute_targets；未配置时使用 RTT 目标）
+pub fn targets_from_config(cfg: &crate::config_utils::AppConfig) -> Vec<String> {
+    match cfg.traceroute_targets.clone().filter(|v| !v.is_empty()) {
+        Some(list) => list,
+        None => crate::ping_utils::targets_from_config(cfg).into_iter().map(|t| t.target).collect(),
+    }
+}
+
+/// 单跳统计
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct HopStats {
+    pub ttl: u8,
+    // 应答地址（按首次出现排序；多于一个说明存在负载均衡/多路径）
+    pub addrs: Vec<String>,
+    pub sent: u32,
+    pub received: u32,
+    pub loss_pct: f64,
+    pub last_ms: Option<f64>,
+    pub avg_ms: Option<f64>,
+    pub best_ms: Option<f64>,
+    pub worst_ms: Option<f64>,
+    pub stddev_ms: Option<f64>,
+}
+
+/// 一次路径分析结果
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct TracePath {
+    pub target: String,
+    // 实际探测的目的地址与地址族
+    pub addr: Option<String>,
+    pub family: Option<String>,
+    pub method: String,
+    pub timestamp_ms: i64,
+    pub rounds: u32,
+    // 是否有任意一轮到达目的地
+    pub reached: bool,
+    pub hops: Vec<HopStats>,
+    // 触发来源：manual（手动）/ schedule（定时）/ threshold（RTT 越限）
+    pub trigger: String,
+    pub reason: Option<String>,
+    pub error: Option<String>,
+}
+
+/// 以指定 TTL 发送一个探测；Ok(None) 表示本跳超时无应答
+pub fn probe_hop(ip: IpAddr, ttl: u8, method: TraceMethod, timeout_ms: u64) -> Result<Option<HopReply>, String> {
+    #[cfg(target_os = "linux")]
+    {
+        crate::icmp_linux::probe_hop(ip, ttl, method == TraceMethod::Udp, Duration::from_millis(timeout_ms))
+    }
+    #[cfg(windows)]
+    {
+        let _ = method;
+        match ip {
+            IpAddr::V4(v4) => probe_hop_v4(v4, ttl, timeout_ms as u32),
+            IpAddr::V6(v6) => probe_hop_v6(v6, ttl, timeout_ms as u32),
+        }
+    }
+    #[cfg(not(any(windows, target_os = "linux")))]
+    {
+        let _ = (ip, ttl, method, timeout_ms);
+        Err("当前平台不支持路径分析".to_string())
+    }
+}
+
+// IcmpSendEcho 状态码
+#[cfg(windows)]
+const IP_SUCCESS: u32 = 0;
+#[cfg(windows)]
+const IP_DEST_NET_UNREACHABLE: u32 = 11002;
+#[cfg(windows)]
+const IP_DEST_PORT_UNREACHABLE: u32 = 11005;
+#[cfg(windows)]
+const IP_TTL_EXPIRED_TRANSIT: u32 = 11013;
+
+/// 按 IcmpSendEcho 状态归类：成功为到达，TTL 超时为中间跳，不可达来自目的地时视为到达
+#[cfg(windows)]
+fn classify_status(ip: IpAddr, from: IpAddr, status: u32, rtt_ms: f64) -> Option<HopReply> {
+    match status {
+        IP_SUCCESS => Some(HopReply { from, rtt_ms, reached: true }),
+        IP_TTL_EXPIRED_TRANSIT => Some(HopReply { from, rtt_ms, reached: false }),
+        IP_DEST_NET_UNREACHABLE..=IP_DEST_PORT_UNREACHABLE => Some(HopReply { from, rtt_ms, reached: from == ip }),
+        _ => None,
+    }
+}
+
+/// 逐跳探测（IPv4）：IcmpSendEcho + IP_OPTION_INFORMATION.Ttl
+#[cfg(windows)]
+fn probe_hop_v4(ip: std::net::Ipv4Addr, ttl: u8, timeout_ms: u32) -> Result<Option<HopReply>, String> {
+    let handle = unsafe { IcmpCreateFile() }.map_err(|e| format!("IcmpCreateFile 失败: {}", e))?;
+    let req_data: [u8; 32] = [0x61; 32];
+    let reply_size = std::mem::size_of::<ICMP_ECHO_REPLY>() + req_data.len() + 8;
+    let mut reply_buf: Vec<u8> = vec![0u8; reply_size];
+    let options = IP_OPTION_INFORMATION { Ttl: ttl, ..Default::default() };
+
+    // TTL 超时时返回值可能为 0，以回复缓冲区中的状态与地址为准
+    unsafe {
+        IcmpSendEcho(
+            handle,
+            u32::from_ne_bytes(ip.octets()),
+            req_data.as_ptr().cast(),
+            req_data.len() as u16,
+            Some(&options),
+            reply_buf.as_mut_ptr().cast(),
+            reply_buf.len() as u32,
+            timeout_ms,
+        );
+    }
+    let reply: &ICMP_ECHO_REPLY = unsafe { &*(reply_buf.as_ptr() as *const ICMP_ECHO_REPLY) };
+    let out = if reply.Address != 0 {
+        let from = IpAddr::V4(std::net::Ipv4Addr::from(reply.Address.to_ne_bytes()));
+        classify_status(IpAddr::V4(ip), from, reply.Status, reply.RoundTripTime as f64)
+    } else {
+        None
+    };
+
+    unsafe { let _ = IcmpCloseHandle(handle); }
+    Ok(out)
+}
+
+/// 逐跳探测（IPv6）：Icmp6SendEcho2 + IP_OPTION_INFORMATION.Ttl（Hop Limit）
+#[cfg(windows)]
+fn probe_hop_v6(ip: std::net::Ipv6Addr, ttl: u8, timeout_ms: u32) -> Result<Option<HopReply>, String> {
+    let handle = unsafe { Icmp6CreateFile() }.map_err(|e| format!("Icmp6CreateFile 失败: {}", e))?;
+    let req_data: [u8; 32] = [0x61; 32];
+    let reply_size = std::mem::size_of::<ICMPV6_ECHO_REPLY_LH>() + req_data.len() + 8;
+    let mut reply_buf: Vec<u8> = vec![0u8; reply_size];
+    let options = IP_OPTION_INFORMATION { Ttl: ttl, ..Default::default() };
+
+    let source = SOCKADDR_IN6 { sin6_family: AF_INET6, ..Default::default() };
+    let dest = SOCKADDR_IN6 {
+        sin6_family: AF_INET6,
+        sin6_addr: IN6_ADDR { u: IN6_ADDR_0 { Byte: ip.octets() } },
+        ..Default::default()
+    };
+
+    unsafe {
+        Icmp6SendEcho2(
+            handle,
+            HANDLE::default(),
+            None,
+            None,
+            &source,
+            &dest,
+            req_data.as_ptr().cast(),
+            req_data.len() as u16,
+            Some(&options),
+            reply_buf.as_mut_ptr().cast(),
+            reply_buf.len() as u32,
+            timeout_ms,
+        );
+    }
+    let reply: &ICMPV6_ECHO_REPLY_LH = unsafe { &*(reply_buf.as_ptr() as *const ICMPV6_ECHO_REPLY_LH) };
+    // IPV6_ADDRESS_EX 为紧凑布局：按值拷贝后再取字节（各 u16 已是网络字节序）
+    let words = reply.Address.sin6_addr;
+    let mut bytes = [0u8; 16];
+    for (i, w) in words.iter().enumerate() {
+        bytes[i * 2..i * 2 + 2].copy_from_slice(&w.to_ne_bytes());
+    }
+    let from = std::net::Ipv6Addr::from(bytes);
+    let out = if !from.is_unspecified() {
+        classify_status(IpAddr::V6(ip), IpAddr::V6(from), reply.Status, reply.RoundTripTime as f64)
+    } else {
+        None
+    };
+
+    unsafe { let _ = IcmpCloseHandle(handle); }
+    Ok(out)
+}
+
+/// 选择目的地址：v6 / happy_eyeballs 优先 IPv6，其余优先 IPv4
+fn pick_addr(addrs: &[IpAddr], family: FamilyPref) -> Option<IpAddr> {
+    let prefer_v6 = matches!(family, FamilyPref::V6 | FamilyPref::HappyEyeballs);
+    addrs.iter().find(|ip| ip.is_ipv6() == prefer_v6).or_else(|| addrs.first()).copied()
+}
+
+/// 汇总多轮探测：rounds[i][ttl-1] 为第 i 轮该跳的应答
+/// 按任一轮到达目的地的最小 TTL 截断；均未到达时去掉末尾全部无应答的跳
+pub fn hop_stats(rounds: &[Vec<Option<HopReply>>]) -> (Vec<HopStats>, bool) {
+    let width = rounds.iter().map(Vec::len).max().unwrap_or(0);
+    let dest = rounds
+        .iter()
+        .filter_map(|r| r.iter().position(|h| h.as_ref().is_some_and(|h| h.reached)))
+        .min();
+    let len = match dest {
+        Some(d) => d + 1,
+        None => (0..width)
+            .rev()
+            .find(|&i| rounds.iter().any(|r| matches!(r.get(i), Some(Some(_)))))
+            .map(|i| i + 1)
+            .unwrap_or(0),
+    };
+
+    let hops = (0..len)
+        .map(|i| {
+            let replies: Vec<&HopReply> = rounds.iter().filter_map(|r| r.get(i).and_then(Option::as_ref)).collect();
+            let rtts: Vec<f64> = replies.iter().map(|h| h.rtt_ms).collect();
+            let mut addrs: Vec<String> = Vec::new();
+            for h in &replies {
+                let a = h.from.to_string();
+                if !addrs.contains(&a) { addrs.push(a); }
+            }
+            let sent = rounds.len() as u32;
+            let received = rtts.len() as u32;
+            let mut s = HopStats {
+                ttl: (i + 1) as u8,
+                addrs,
+                sent,
+                received,
+                loss_pct: if sent > 0 { (sent - received) as f64 * 100.0 / sent as f64 } else { 0.0 },
+                last_ms: rtts.last().copied(),
+                ..Default::default()
+            };
+            if !rtts.is_empty() {
+                let n = rtts.len() as f64;
+                let avg = rtts.iter().sum::<f64>() / n;
+                s.avg_ms = Some(avg);
+                s.best_ms = rtts.iter().copied().reduce(f64::min);
+                s.worst_ms = rtts.iter().copied().reduce(f64::max);
+                s.stddev_ms = Some((rtts.iter().map(|v| (v - avg).powi(2)).sum::<f64>() / n).sqrt());
+            }
+            s
+        })
+        .collect();
+    (hops, dest.is_some())
+}
+
+/// 对单个目标做多轮路径分析（阻塞；耗时约 rounds × (timeout_ms + interval_ms)）
+pub fn trace(target: &str, opts: &TraceOptions) -> TracePath {
+    let method = opts.method.effective();
+    let mut out = TracePath {
+        target: target.to_string(),
+        method: method.label().to_string(),
+        ..Default::default()
+    };
+    let host = crate::ping_utils::parse_target(target).host;
+    let Some(ip) = pick_addr(&crate::ping_utils::resolve_target(&host, opts.family), opts.family) else {
+        out.error = Some(format!("无法解析目标: {}", host));
+        return out;
+    };
+    out.addr = Some(ip.to_string());
+    out.family = Some(crate::ping_utils::family_label(&ip).to_string());
+
+    let mut rounds: Vec<Vec<Option<HopReply>>> = Vec::with_capacity(opts.rounds as usize);
+    let mut first_err: Option<String> = None;
+    for i in 0..opts.rounds.max(1) {
+        if i > 0 && opts.interval_ms > 0 { std::thread::sleep(Duration::from_millis(opts.interval_ms)); }
+        // 各跳并发探测：每个探测独立套接字，一轮耗时约为一次超时
+        let results: Vec<Result<Option<HopReply>, String>> = std::thread::scope(|s| {
+            let handles: Vec<_> = (1..=opts.max_hops)
+                .map(|ttl| s.spawn(move || probe_hop(ip, ttl, method, opts.timeout_ms)))
+                .collect();
+            handles.into_iter().map(|h| h.join().unwrap_or_else(|_| Err("探测线程异常".to_string()))).collect()
+        });
+        let mut round = Vec::with_capacity(results.len());
+        for r in results {
+            match r {
+                Ok(h) => round.push(h),
+                Err(e) => {
+                    first_err.get_or_insert(e);
+                    round.push(None);
+                }
+            }
+        }
+        // 首轮全部失败（如无权限创建套接字）：不再继续
+        if i == 0 && round.iter().all(Option::is_none) && first_err.is_some() {
+            out.error = first_err;
+            return out;
+        }
+        rounds.push(round);
+    }
+
+    let (hops, reached) = hop_stats(&rounds);
+    out.rounds = rounds.len() as u32;
+    out.hops = hops;
+    out.reached = reached;
+    if out.hops.is_empty() {
+        out.error = first_err.or_else(|| Some("所有跳均无应答".to_string()));
+    }
+    out
+}
+
+/// RTT 结果是否越过自动路径分析阈值（延迟取平均值，丢包取丢包率）；返回触发原因
+pub fn threshold_reason(r: &RttResultPayload, rtt_threshold_ms: Option<f64>, loss_threshold_pct: Option<f64>) -> Option<String> {
+    if let (Some(loss), Some(thr)) = (r.loss_pct, loss_threshold_pct) {
+        if loss >= thr { return Some(format!("丢包 {:.0}% ≥ {:.0}%", loss, thr)); }
+    }
+    if let (Some(rtt), Some(thr)) = (r.avg_ms.or(r.rtt_ms), rtt_threshold_ms) {
+        if rtt >= thr { return Some(format!("延迟 {:.1}ms ≥ {:.0}ms", rtt, thr)); }
+    }
+    None
+}
+
+/// RTT 结果标注的地址族转为偏好（用于自动触发时沿用同一地址族）
+pub fn family_of(r: &RttResultPayload) -> Option<FamilyPref> {
+    match r.family.as_deref() {
+        Some("v4") => Some(FamilyPref::V4),
+        Some("v6") => Some(FamilyPref::V6),
+        _ => None,
+    }
+}
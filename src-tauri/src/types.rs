@@ -316,7 +316,7 @@ pub struct BridgeStorageTemp {
 // 实时快照数据结构
 // ================================================================================
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct SensorSnapshot {
     pub cpu_usage: f32,
     pub mem_used_gb: f32,
@@ -344,6 +344,10 @@ pub struct SensorSnapshot {
     // 新增：公网 IP 与 ISP
     pub public_ip: Option<String>,
     pub isp: Option<String>,
+    // 公网 IPv6 地址、ASN（如 "AS13335"）与国家/地区代码
+    pub public_ipv6: Option<String>,
+    pub public_asn: Option<String>,
+    pub public_country: Option<String>,
     // 新增：Wi‑Fi 指标（若无连接则为 None）
     pub wifi_ssid: Option<String>,
     pub wifi_signal_pct: Option<i32>,
@@ -389,6 +393,8 @@ pub struct SensorSnapshot {
     pub cpu_throttle_active: Option<bool>,
     pub cpu_throttle_reasons: Option<Vec<String>>,
     pub since_reopen_sec: Option<i32>,
+    // 桥接协议版本不一致提示（兼容时为空）
+    pub bridge_protocol_warning: Option<String>,
     // 每核心：负载/频率/温度（与桥接输出对应）。数组元素可为 null。
     pub cpu_core_loads_pct: Option<Vec<Option<f32>>>,
     pub cpu_core_clocks_mhz: Option<Vec<Option<f64>>>,
@@ -406,6 +412,14 @@ pub struct SensorSnapshot {
     pub active_connections: Option<u32>,
     // 新增：多目标 RTT 结果与 Top 进程
     pub rtt_multi: Option<Vec<crate::process_utils::RttResultPayload>>,
+    // DNS 解析器探测报告（最近一轮）
+    pub dns_probe: Option<crate::dns_probe::DnsProbeReport>,
+    // 路径分析：各目标最近一次路径（手动 / 定时 / RTT 越限触发）
+    pub trace_paths: Option<Vec<crate::traceroute::TracePath>>,
+    // 连通性状态（online / degraded / offline / captive_portal / dns_failure，去抖后）
+    pub connectivity: Option<crate::connectivity::ConnectivityStatus>,
+    // 吞吐测速：最近一次结果（手动 / 定时触发）
+    pub speedtest: Option<crate::speedtest::SpeedtestResult>,
     pub top_cpu_procs: Option<Vec<crate::process_utils::TopProcessPayload>>,
     pub top_mem_procs: Option<Vec<crate::process_utils::TopProcessPayload>>,
     // 新增：GPU 列表
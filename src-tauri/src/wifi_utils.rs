@@ -162,10 +162,10 @@ pub fn read_wifi_info_ext() -> WifiInfoExt {
                     // Debug: 打印潜在带宽/加密相关行，便于定位不同语言/驱动差异
                     if cfg!(debug_assertions) {
                         if tl.contains("width") || t.contains("宽") || t.contains("带宽") {
-                            println!("[wifi][match-cand][width] {}", t);
+                            eprintln!("[wifi][match-cand][width] {}", t);
                         }
                         if tl.contains("cipher") || t.contains("加密") || t.contains("加密类型") || t.contains("加密方式") {
-                            println!("[wifi][match-cand][cipher] {}", t);
+                            eprintln!("[wifi][match-cand][cipher] {}", t);
                         }
                     }
                     
@@ -289,7 +289,7 @@ pub fn read_wifi_info_ext() -> WifiInfoExt {
                     if let Some(w) = width {
                         out.chan_width_mhz = Some(w);
                         if cfg!(debug_assertions) {
-                            println!("[wifi][width][fallback] radio={:?} band={:?} -> width={} MHz", out.radio, out.band, w);
+                            eprintln!("[wifi][width][fallback] radio={:?} band={:?} -> width={} MHz", out.radio, out.band, w);
                         }
                     }
                 }
@@ -298,10 +298,10 @@ pub fn read_wifi_info_ext() -> WifiInfoExt {
                 if cfg!(debug_assertions) {
                     if out.signal_pct.is_none() && out.channel.is_none() && out.radio.is_none() && out.rx_mbps.is_none() && out.tx_mbps.is_none() && out.rssi_dbm.is_none() && out.auth.is_none() && out.cipher.is_none() && out.chan_width_mhz.is_none() {
                         if let Some(raw) = raw_text_for_dbg.as_ref() {
-                            println!("[wifi][raw]\n{}", raw);
+                            eprintln!("[wifi][raw]\n{}", raw);
                         }
                     }
-                    println!(
+                    eprintln!(
                         "[wifi][parsed] ssid={:?} signal%={:?} ch={:?} radio={:?} band={:?} rx={:?} tx={:?} bssid={:?} rssi={:?} rssi_est={} auth={:?} cipher={:?} width={:?}",
                         out.ssid, out.signal_pct, out.channel, out.radio, out.band, out.rx_mbps, out.tx_mbps, out.bssid, out.rssi_dbm, out.rssi_estimated, out.auth, out.cipher, out.chan_width_mhz
                     );